@@ -0,0 +1,267 @@
+use crate::body::BodyEncoding;
+use crate::http::ResponseData;
+use jsonschema::JSONSchema;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    #[default]
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    // Substring of a string, element of an array or key of an object.
+    Contains,
+    NotContains,
+    // Regex over the value as text.
+    Matches,
+    Exists,
+    NotExists,
+}
+
+// A check run against the response once it arrives, e.g.
+// `{"type": "json", "path": "$.items.length", "op": "gt", "value": 0}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Assertion {
+    Status {
+        #[serde(default)]
+        op: Comparison,
+        value: Value,
+    },
+    ResponseTime {
+        max_ms: u64,
+    },
+    Header {
+        name: String,
+        #[serde(default)]
+        op: Comparison,
+        #[serde(default)]
+        value: Option<Value>,
+    },
+    Json {
+        path: String,
+        #[serde(default)]
+        op: Comparison,
+        #[serde(default)]
+        value: Option<Value>,
+    },
+    Schema {
+        schema: Value,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    pub actual: Option<Value>,
+    pub message: Option<String>,
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Numbers compare by value and strings compare with anything by their text, since
+// header values and the like are always strings.
+fn equal(actual: &Value, expected: &Value) -> bool {
+    if let (Some(a), Some(b)) = (number(actual), number(expected)) {
+        if actual.is_number() || expected.is_number() {
+            return a == b;
+        }
+    }
+    if actual.is_string() || expected.is_string() {
+        return text(actual) == text(expected);
+    }
+    actual == expected
+}
+
+fn compare(
+    actual: Option<&Value>,
+    op: Comparison,
+    expected: Option<&Value>,
+) -> Result<bool, String> {
+    let (actual, expected) = match op {
+        Comparison::Exists => return Ok(actual.is_some()),
+        Comparison::NotExists => return Ok(actual.is_none()),
+        _ => match (actual, expected) {
+            (None, _) => return Ok(false),
+            (Some(actual), Some(expected)) => (actual, expected),
+            (Some(_), None) => return Err("Expected value is missing".into()),
+        },
+    };
+    let ordered = |check: fn(f64, f64) -> bool| match (number(actual), number(expected)) {
+        (Some(a), Some(b)) => Ok(check(a, b)),
+        _ => Err("Only numbers can be ordered".to_string()),
+    };
+    let contains = || match actual {
+        Value::Array(items) => items.iter().any(|item| equal(item, expected)),
+        Value::Object(map) => map.contains_key(&text(expected)),
+        other => text(other).contains(&text(expected)),
+    };
+    match op {
+        Comparison::Eq => Ok(equal(actual, expected)),
+        Comparison::Ne => Ok(!equal(actual, expected)),
+        Comparison::Lt => ordered(|a, b| a < b),
+        Comparison::Lte => ordered(|a, b| a <= b),
+        Comparison::Gt => ordered(|a, b| a > b),
+        Comparison::Gte => ordered(|a, b| a >= b),
+        Comparison::Contains => Ok(contains()),
+        Comparison::NotContains => Ok(!contains()),
+        Comparison::Matches => Regex::new(&text(expected))
+            .map(|regex| regex.is_match(&text(actual)))
+            .map_err(|e| format!("Invalid regex: {}", e)),
+        Comparison::Exists | Comparison::NotExists => unreachable!(),
+    }
+}
+
+fn json_body(response: &ResponseData) -> Result<Value, String> {
+    if response.body_encoding != BodyEncoding::Text || response.body_path.is_some() {
+        return Err("Response body is not text".into());
+    }
+    serde_json::from_str(&response.body).map_err(|e| format!("Response body is not JSON: {}", e))
+}
+
+// Returns the actual value looked at and whether it passed.
+fn check(response: &ResponseData, assertion: &Assertion) -> Result<(Option<Value>, bool), String> {
+    match assertion {
+        Assertion::Status { op, value } => {
+            let actual = Value::from(response.status);
+            let passed = compare(Some(&actual), *op, Some(value))?;
+            Ok((Some(actual), passed))
+        }
+        Assertion::ResponseTime { max_ms } => Ok((
+            Some(Value::from(response.elapsed_ms)),
+            response.elapsed_ms <= *max_ms,
+        )),
+        Assertion::Header { name, op, value } => {
+            let actual = response
+                .headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, values)| Value::String(values.join(", ")));
+            let passed = compare(actual.as_ref(), *op, value.as_ref())?;
+            Ok((actual, passed))
+        }
+        Assertion::Json { path, op, value } => {
+            let path = JsonPath::parse(path).map_err(|e| format!("Invalid JSONPath: {}", e))?;
+            let body = json_body(response)?;
+            let actual = path.query(&body).first().cloned();
+            let passed = compare(actual.as_ref(), *op, value.as_ref())?;
+            Ok((actual, passed))
+        }
+        Assertion::Schema { schema } => {
+            let schema =
+                JSONSchema::compile(schema).map_err(|e| format!("Invalid JSON Schema: {}", e))?;
+            let body = json_body(response)?;
+            let errors: Vec<String> = match schema.validate(&body) {
+                Ok(()) => Vec::new(),
+                Err(errors) => errors
+                    .map(|e| format!("{}: {}", e.instance_path, e))
+                    .collect(),
+            };
+            if errors.is_empty() {
+                Ok((None, true))
+            } else {
+                Err(errors.join("; "))
+            }
+        }
+    }
+}
+
+pub fn evaluate(response: &ResponseData, assertions: &[Assertion]) -> Vec<AssertionResult> {
+    assertions
+        .iter()
+        .map(|assertion| {
+            let (actual, passed, message) = match check(response, assertion) {
+                Ok((actual, passed)) => (actual, passed, None),
+                Err(e) => (None, false, Some(e)),
+            };
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed,
+                actual,
+                message,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn response(body: &str) -> ResponseData {
+        ResponseData {
+            status: 201,
+            status_text: "Created".into(),
+            http_version: "HTTP/1.1".into(),
+            headers: HashMap::from([(
+                "content-type".into(),
+                vec!["application/json; charset=utf-8".into()],
+            )]),
+            body: body.into(),
+            content_type: Some("application/json".into()),
+            elapsed_ms: 120,
+            size: body.len() as u64,
+            encoded_size: body.len() as u64,
+            ..ResponseData::default()
+        }
+    }
+
+    #[test]
+    fn evaluates_each_kind_of_assertion() {
+        let assertions: Vec<Assertion> = serde_json::from_value(json!([
+            { "type": "status", "value": 201 },
+            { "type": "status", "op": "lt", "value": 300 },
+            { "type": "response_time", "max_ms": 100 },
+            { "type": "header", "name": "Content-Type", "op": "contains", "value": "json" },
+            { "type": "header", "name": "ETag", "op": "exists" },
+            { "type": "json", "path": "$.id", "value": "42" },
+            { "type": "json", "path": "$.tags", "op": "contains", "value": "new" },
+            { "type": "json", "path": "$.name", "op": "matches", "value": "^Re" },
+            { "type": "json", "path": "$.id", "op": "gt" },
+            { "type": "schema", "schema": {
+                "type": "object",
+                "required": ["id", "name"],
+                "properties": { "name": { "type": "integer" } }
+            } }
+        ]))
+        .unwrap();
+        let results = evaluate(
+            &response(r#"{"id": 42, "name": "Rex", "tags": ["new", "dog"]}"#),
+            &assertions,
+        );
+        let passed: Vec<bool> = results.iter().map(|result| result.passed).collect();
+        assert_eq!(
+            passed,
+            vec![true, true, false, true, false, true, true, true, false, false]
+        );
+        assert_eq!(results[2].actual, Some(json!(120)));
+        assert_eq!(
+            results[8].message.as_deref(),
+            Some("Expected value is missing")
+        );
+        assert!(results[9].message.as_deref().unwrap().starts_with("/name:"));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::jwt::JwtConfig;
+use crate::ntlm::NtlmCredentials;
+use crate::oauth2::OAuthConfig;
+use crate::signing::{HawkCredentials, HmacSignature};
+use crate::sigv4::AwsCredentials;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+    Cookie,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Auth {
+    Basic {
+        username: String,
+        #[serde(default)]
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        name: String,
+        value: String,
+        #[serde(default)]
+        location: ApiKeyLocation,
+    },
+    // Sent only after the server answers with a digest challenge.
+    Digest {
+        username: String,
+        #[serde(default)]
+        password: String,
+    },
+    // Connection-level NTLM handshake, also answered through a Negotiate challenge.
+    // Kerberos is not implemented: a Negotiate server that requires it fails the request.
+    Ntlm(NtlmCredentials),
+    // Signed after the request is built, since the signatures cover the final URL.
+    #[serde(rename = "aws_sigv4")]
+    AwsSigV4(AwsCredentials),
+    Hawk(HawkCredentials),
+    Hmac(HmacSignature),
+    // The variants below are resolved to concrete credentials before the request is
+    // built: a token from the OAuth store, a machine-to-machine grant, a freshly signed
+    // JWT, or a saved profile.
+    #[serde(rename = "oauth2")]
+    OAuth2 {
+        #[serde(default)]
+        environment: Option<String>,
+    },
+    #[serde(rename = "oauth2_grant")]
+    OAuth2Grant {
+        config: OAuthConfig,
+    },
+    Jwt {
+        config: JwtConfig,
+    },
+    Profile {
+        name: String,
+    },
+}
+
+// Replaces any header with the same name regardless of case, so credentials from the
+// auth helper win over a hand-written header.
+fn set_header(headers: &mut HashMap<String, String>, name: &str, value: String) {
+    headers.retain(|key, _| !key.eq_ignore_ascii_case(name));
+    headers.insert(name.to_string(), value);
+}
+
+impl Auth {
+    pub fn apply_headers(&self, headers: &mut HashMap<String, String>) {
+        match self {
+            Auth::Basic { username, password } => {
+                let encoded = STANDARD.encode(format!("{}:{}", username, password));
+                set_header(headers, "Authorization", format!("Basic {}", encoded));
+            }
+            Auth::Bearer { token } => {
+                set_header(headers, "Authorization", format!("Bearer {}", token.trim()));
+            }
+            Auth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Header,
+            } => set_header(headers, name, value.clone()),
+            Auth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Cookie,
+            } => {
+                // An explicit Cookie header also stops reqwest from adding jar cookies,
+                // so the key is appended to whatever the user already sent.
+                let existing = headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case("cookie"))
+                    .map(|(_, value)| value.trim().trim_end_matches(';').to_string())
+                    .filter(|value| !value.is_empty());
+                let pair = format!("{}={}", name, value);
+                let cookie = match existing {
+                    Some(existing) => format!("{}; {}", existing, pair),
+                    None => pair,
+                };
+                set_header(headers, "Cookie", cookie);
+            }
+            Auth::ApiKey { .. }
+            | Auth::Digest { .. }
+            | Auth::Ntlm(_)
+            | Auth::AwsSigV4(_)
+            | Auth::Hawk(_)
+            | Auth::Hmac(_)
+            | Auth::OAuth2 { .. }
+            | Auth::OAuth2Grant { .. }
+            | Auth::Jwt { .. }
+            | Auth::Profile { .. } => {}
+        }
+    }
+
+    pub fn query_pair(&self) -> Option<(String, String)> {
+        match self {
+            Auth::ApiKey {
+                name,
+                value,
+                location: ApiKeyLocation::Query,
+            } => Some((name.clone(), value.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_and_bearer_replace_existing_authorization() {
+        let mut headers = HashMap::from([("authorization".to_string(), "stale".to_string())]);
+        let basic: Auth = serde_json::from_str(
+            r#"{"type":"basic","username":"Aladdin","password":"open sesame"}"#,
+        )
+        .unwrap();
+        basic.apply_headers(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(
+            headers["Authorization"],
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        Auth::Bearer {
+            token: " abc.def ".into(),
+        }
+        .apply_headers(&mut headers);
+        assert_eq!(headers["Authorization"], "Bearer abc.def");
+    }
+
+    #[test]
+    fn api_keys_go_to_header_query_or_cookie() {
+        let key = |location| Auth::ApiKey {
+            name: "X-Api-Key".into(),
+            value: "secret".into(),
+            location,
+        };
+        let mut headers = HashMap::new();
+        key(ApiKeyLocation::Header).apply_headers(&mut headers);
+        assert_eq!(headers["X-Api-Key"], "secret");
+
+        let mut headers = HashMap::new();
+        key(ApiKeyLocation::Query).apply_headers(&mut headers);
+        assert!(headers.is_empty());
+        assert_eq!(
+            key(ApiKeyLocation::Query).query_pair(),
+            Some(("X-Api-Key".to_string(), "secret".to_string()))
+        );
+
+        let mut headers = HashMap::from([("cookie".to_string(), "theme=dark;".to_string())]);
+        key(ApiKeyLocation::Cookie).apply_headers(&mut headers);
+        assert_eq!(headers["Cookie"], "theme=dark; X-Api-Key=secret");
+    }
+}
@@ -0,0 +1,383 @@
+use crate::secrets::{create_private_dir, create_private_file};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::{Stream, StreamExt};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+pub const BASE64_PREVIEW_LIMIT: usize = 2 * 1024 * 1024;
+// Text bodies above this size go to a file, unless the settings say otherwise.
+pub const DEFAULT_SPILL_THRESHOLD: u64 = 20 * 1024 * 1024;
+// How much of a text body written to a file is also returned inline.
+pub const TEXT_PREVIEW_LIMIT: usize = 256 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyEncoding {
+    #[default]
+    Text,
+    Base64,
+    None,
+}
+
+pub fn mime_essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+pub fn is_binary_content_type(content_type: Option<&str>) -> Option<bool> {
+    let essence = mime_essence(content_type?);
+    if essence.is_empty() {
+        return None;
+    }
+    if essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-www-form-urlencoded"
+                | "application/graphql"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "image/svg+xml"
+        )
+    {
+        return Some(false);
+    }
+    if essence.starts_with("image/")
+        || essence.starts_with("audio/")
+        || essence.starts_with("video/")
+        || essence.starts_with("font/")
+        || matches!(
+            essence.as_str(),
+            "application/octet-stream"
+                | "application/pdf"
+                | "application/zip"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/x-tar"
+                | "application/x-7z-compressed"
+                | "application/vnd.rar"
+                | "application/x-protobuf"
+                | "application/grpc"
+                | "application/msgpack"
+                | "application/wasm"
+        )
+    {
+        return Some(true);
+    }
+    None
+}
+
+pub fn looks_binary(content_type: Option<&str>, bytes: &[u8]) -> bool {
+    if let Some(binary) = is_binary_content_type(content_type) {
+        return binary;
+    }
+    let sample = &bytes[..bytes.len().min(8192)];
+    sample.contains(&0) || !is_utf8_sample(sample)
+}
+
+// A sample cut in the middle of a multi-byte sequence is still text.
+fn is_utf8_sample(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+pub fn extension_for_content_type(content_type: Option<&str>) -> &'static str {
+    match content_type.map(mime_essence).as_deref() {
+        Some("image/png") => "png",
+        Some("image/jpeg") => "jpg",
+        Some("image/gif") => "gif",
+        Some("image/webp") => "webp",
+        Some("image/bmp") => "bmp",
+        Some("image/x-icon") | Some("image/vnd.microsoft.icon") => "ico",
+        Some("application/pdf") => "pdf",
+        Some("application/zip") => "zip",
+        Some("application/gzip") | Some("application/x-gzip") => "gz",
+        Some("application/x-tar") => "tar",
+        Some("audio/mpeg") => "mp3",
+        Some("video/mp4") => "mp4",
+        Some("application/wasm") => "wasm",
+        _ => "bin",
+    }
+}
+
+static RESPONSE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+// Sets where response bodies go, normally the app's cache dir. Only the first call counts.
+pub fn set_response_dir(dir: PathBuf) {
+    let _ = RESPONSE_DIR.set(dir);
+}
+
+// Where response bodies that are not kept in memory are written. Until the app sets one
+// (tests, the CLI), a dir private to this process.
+pub fn response_dir() -> PathBuf {
+    RESPONSE_DIR
+        .get_or_init(|| std::env::temp_dir().join(format!("restman-{}", std::process::id())))
+        .clone()
+}
+
+// A new file in `response_dir`, readable by the user alone, under a name nobody can
+// guess ahead of time.
+pub fn create_response_file(
+    content_type: Option<&str>,
+) -> Result<(std::fs::File, PathBuf), String> {
+    let dir = response_dir();
+    create_private_dir(&dir).map_err(|e| e.to_string())?;
+    let name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    let path = dir.join(format!(
+        "response-{}.{}",
+        name,
+        extension_for_content_type(content_type)
+    ));
+    let file = create_private_file(&path).map_err(|e| e.to_string())?;
+    Ok((file, path))
+}
+
+// Deletes the files in `response_dir` last written before `before` that are not in
+// `keep`, e.g. those left over from pruned history or formatted bodies of an earlier
+// run. Returns how many went.
+pub fn sweep_response_dir(keep: &HashSet<PathBuf>, before: SystemTime) -> usize {
+    let Ok(entries) = std::fs::read_dir(response_dir()) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .metadata()
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| modified < before)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !keep.contains(path))
+        .filter(|path| std::fs::remove_file(path).is_ok())
+        .count()
+}
+
+pub fn encode_preview(bytes: &[u8]) -> (String, BodyEncoding) {
+    if bytes.len() > BASE64_PREVIEW_LIMIT {
+        (String::new(), BodyEncoding::None)
+    } else {
+        (STANDARD.encode(bytes), BodyEncoding::Base64)
+    }
+}
+
+pub struct CollectedBody {
+    pub body: String,
+    pub encoding: BodyEncoding,
+    pub path: Option<String>,
+    pub size: u64,
+}
+
+// The text of `bytes` up to any character cut off at the end, and how many bytes that
+// took.
+pub fn text_prefix(bytes: &[u8]) -> (String, usize) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), bytes.len()),
+        Err(e) if e.error_len().is_none() => (
+            String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+            e.valid_up_to(),
+        ),
+        Err(_) => (String::from_utf8_lossy(bytes).into_owned(), bytes.len()),
+    }
+}
+
+// Text bodies are buffered because they are returned inline, until they grow past
+// `spill_threshold`; from then on they are streamed to a temp file and only a preview is
+// returned. Binary bodies are detected from the content type and the first chunk, then
+// streamed straight to a temp file so only the preview window is ever held in memory.
+pub async fn collect_body<S, B, E, F>(
+    mut stream: S,
+    content_type: Option<&str>,
+    spill_threshold: u64,
+    mut on_progress: F,
+) -> Result<CollectedBody, String>
+where
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    E: Display,
+    F: FnMut(u64),
+{
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut file: Option<(File, PathBuf)> = None;
+    let mut binary: Option<bool> = None;
+    let mut received: u64 = 0;
+
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| e.to_string())?;
+        let chunk = chunk.as_ref();
+        received += chunk.len() as u64;
+        if let Some((handle, _)) = file.as_mut() {
+            handle.write_all(chunk).await.map_err(|e| e.to_string())?;
+            if binary == Some(true) && received as usize <= BASE64_PREVIEW_LIMIT {
+                buffer.extend_from_slice(chunk);
+            } else if binary == Some(true) && !buffer.is_empty() {
+                buffer = Vec::new();
+            }
+            on_progress(received);
+            continue;
+        }
+        buffer.extend_from_slice(chunk);
+        let detected = *binary.get_or_insert_with(|| looks_binary(content_type, &buffer));
+        if detected || received > spill_threshold {
+            let (handle, path) = create_response_file(content_type)?;
+            let mut handle = File::from_std(handle);
+            handle.write_all(&buffer).await.map_err(|e| e.to_string())?;
+            file = Some((handle, path));
+            if !detected {
+                buffer.truncate(TEXT_PREVIEW_LIMIT);
+            } else if received as usize > BASE64_PREVIEW_LIMIT {
+                buffer = Vec::new();
+            }
+        }
+        on_progress(received);
+    }
+
+    if let Some((mut handle, path)) = file {
+        handle.flush().await.map_err(|e| e.to_string())?;
+        let (body, encoding) = if binary == Some(false) {
+            (text_prefix(&buffer).0, BodyEncoding::Text)
+        } else if received as usize > BASE64_PREVIEW_LIMIT {
+            (String::new(), BodyEncoding::None)
+        } else {
+            encode_preview(&buffer)
+        };
+        return Ok(CollectedBody {
+            body,
+            encoding,
+            path: Some(path.to_string_lossy().into_owned()),
+            size: received,
+        });
+    }
+    Ok(CollectedBody {
+        body: String::from_utf8_lossy(&buffer).into_owned(),
+        encoding: BodyEncoding::Text,
+        path: None,
+        size: received,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_content_types() {
+        assert_eq!(is_binary_content_type(Some("image/png")), Some(true));
+        assert_eq!(is_binary_content_type(Some("application/pdf")), Some(true));
+        assert_eq!(
+            is_binary_content_type(Some("application/json; charset=utf-8")),
+            Some(false)
+        );
+        assert_eq!(
+            is_binary_content_type(Some("application/problem+json")),
+            Some(false)
+        );
+        assert_eq!(is_binary_content_type(Some("image/svg+xml")), Some(false));
+        assert_eq!(is_binary_content_type(Some("application/x-custom")), None);
+    }
+
+    #[tokio::test]
+    async fn collect_body_streams_binary_to_file_and_reports_progress() {
+        let chunks: Vec<Result<Vec<u8>, String>> =
+            vec![Ok(vec![0x89, b'P', b'N', b'G']), Ok(vec![0x00, 0x01])];
+        let mut progress = Vec::new();
+        let collected = collect_body(
+            futures_util::stream::iter(chunks),
+            Some("image/png"),
+            DEFAULT_SPILL_THRESHOLD,
+            |received| progress.push(received),
+        )
+        .await
+        .unwrap();
+        assert_eq!(progress, vec![4, 6]);
+        assert_eq!(collected.size, 6);
+        assert_eq!(collected.encoding, BodyEncoding::Base64);
+        let path = collected.path.expect("binary body should be saved");
+        let saved = tokio::fs::read(&path).await.unwrap();
+        assert_eq!(saved, vec![0x89, b'P', b'N', b'G', 0x00, 0x01]);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn collect_body_keeps_text_inline_until_the_spill_threshold() {
+        let chunks =
+            || -> Vec<Result<&[u8], String>> { vec![Ok(b"{\"a\":"), Ok(b"\"\xc3\xa9\"}")] };
+        let collected = collect_body(
+            futures_util::stream::iter(chunks()),
+            Some("application/json"),
+            DEFAULT_SPILL_THRESHOLD,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(collected.body, "{\"a\":\"é\"}");
+        assert_eq!(collected.encoding, BodyEncoding::Text);
+        assert!(collected.path.is_none());
+
+        let spilled = collect_body(
+            futures_util::stream::iter(chunks()),
+            Some("application/json"),
+            4,
+            |_| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(spilled.encoding, BodyEncoding::Text);
+        assert_eq!(spilled.body, "{\"a\":");
+        let path = spilled.path.expect("large text body should be saved");
+        assert_eq!(
+            tokio::fs::read_to_string(&path).await.unwrap(),
+            "{\"a\":\"é\"}"
+        );
+        let _ = tokio::fs::remove_file(&path).await;
+        assert_eq!(text_prefix(&"aé".as_bytes()[..2]), ("a".to_string(), 1));
+    }
+
+    #[test]
+    fn response_files_are_private_and_never_reused() {
+        let (_, first) = create_response_file(Some("image/png")).unwrap();
+        let (_, second) = create_response_file(Some("image/png")).unwrap();
+        assert_ne!(first, second);
+        assert!(first.starts_with(response_dir()));
+        assert_eq!(first.extension().unwrap(), "png");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(create_private_file(&first).is_err());
+        let _ = std::fs::remove_file(&first);
+        let _ = std::fs::remove_file(&second);
+    }
+
+    #[test]
+    fn sniffs_body_when_content_type_is_unknown() {
+        assert!(!looks_binary(None, "plain text".as_bytes()));
+        assert!(looks_binary(None, &[0x89, b'P', b'N', b'G', 0x00]));
+        assert!(!looks_binary(None, &"héllo".as_bytes()[..2]));
+    }
+}
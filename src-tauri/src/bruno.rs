@@ -0,0 +1,542 @@
+use crate::auth::{ApiKeyLocation, Auth};
+use crate::environments::{Environment, Variable};
+use crate::http::{MultipartFile, MultipartPayload, RequestInput};
+use crate::ntlm::NtlmCredentials;
+use crate::saved::{new_id, CollectionImport, SavedRequest};
+use crate::sigv4::AwsCredentials;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+
+const METHODS: [&str; 9] = [
+    "get", "post", "put", "delete", "patch", "options", "head", "connect", "trace",
+];
+
+// One `name { ... }` block of a `.bru` file. Dictionary entries prefixed with `~` are
+// disabled.
+#[derive(Clone, Debug, PartialEq)]
+enum Block {
+    Dictionary(Vec<(String, String, bool)>),
+    Text(String),
+    List(Vec<String>),
+}
+
+#[derive(Clone, Debug, Default)]
+struct BruFile {
+    blocks: Vec<(String, Block)>,
+}
+
+impl BruFile {
+    fn block(&self, name: &str) -> Option<&Block> {
+        self.blocks
+            .iter()
+            .find(|(block, _)| block == name)
+            .map(|(_, block)| block)
+    }
+
+    // Enabled entries of a dictionary block.
+    fn entries(&self, name: &str) -> Vec<(String, String)> {
+        match self.block(name) {
+            Some(Block::Dictionary(entries)) => entries
+                .iter()
+                .filter(|(_, _, enabled)| *enabled)
+                .map(|(key, value, _)| (key.clone(), value.clone()))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn value(&self, block: &str, key: &str) -> String {
+        self.entries(block)
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+            .unwrap_or_default()
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        match self.block(name) {
+            Some(Block::Text(text)) => Some(text.clone()),
+            _ => None,
+        }
+    }
+}
+
+// Bodies, scripts and docs hold free text; everything else is `key: value` lines.
+fn is_text_block(name: &str) -> bool {
+    (name.starts_with("body:")
+        && !matches!(
+            name,
+            "body:form-urlencoded" | "body:multipart-form" | "body:file"
+        ))
+        || name.starts_with("script:")
+        || name == "tests"
+        || name == "docs"
+}
+
+// Block contents are indented by two spaces, so a closing brace at the start of a line
+// always ends the block.
+fn parse(content: &str) -> BruFile {
+    let mut file = BruFile::default();
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim_end();
+        let (name, close) = if let Some(name) = line.strip_suffix(" {") {
+            (name.trim(), "}")
+        } else if let Some(name) = line.strip_suffix(" [") {
+            (name.trim(), "]")
+        } else {
+            continue;
+        };
+        let body: Vec<&str> = lines
+            .by_ref()
+            .take_while(|line| line.trim_end() != close)
+            .collect();
+        let block = if close == "]" {
+            Block::List(
+                body.iter()
+                    .map(|line| line.trim().trim_end_matches(',').to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect(),
+            )
+        } else if is_text_block(name) {
+            let text: Vec<&str> = body
+                .iter()
+                .map(|line| line.strip_prefix("  ").unwrap_or(line.trim_start()))
+                .collect();
+            Block::Text(text.join("\n").trim_end().to_string())
+        } else {
+            Block::Dictionary(
+                body.iter()
+                    .filter_map(|line| {
+                        let line = line.trim();
+                        let (enabled, line) = match line.strip_prefix('~') {
+                            Some(line) => (false, line),
+                            None => (true, line),
+                        };
+                        let (key, value) = line.split_once(':')?;
+                        Some((key.trim().to_string(), value.trim().to_string(), enabled))
+                    })
+                    .collect(),
+            )
+        };
+        file.blocks.push((name.to_string(), block));
+    }
+    file
+}
+
+// `Ok(None)` for no auth, `Err` for a mode with no equivalent. `inherit` is resolved
+// by the caller.
+fn auth(file: &BruFile, mode: &str) -> Result<Option<Auth>, String> {
+    let block = format!("auth:{}", mode);
+    let setting = |key: &str| file.value(&block, key);
+    Ok(Some(match mode {
+        "" | "none" => return Ok(None),
+        "basic" => Auth::Basic {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "bearer" => Auth::Bearer {
+            token: setting("token"),
+        },
+        "digest" => Auth::Digest {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "ntlm" => Auth::Ntlm(NtlmCredentials {
+            username: setting("username"),
+            password: setting("password"),
+            domain: setting("domain"),
+        }),
+        "awsv4" => Auth::AwsSigV4(AwsCredentials {
+            access_key_id: setting("accessKeyId"),
+            secret_access_key: setting("secretAccessKey"),
+            session_token: Some(setting("sessionToken")).filter(|token| !token.is_empty()),
+            region: setting("region"),
+            service: setting("service"),
+        }),
+        "apikey" => Auth::ApiKey {
+            name: setting("key"),
+            value: setting("value"),
+            location: match setting("placement").as_str() {
+                "queryparams" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        },
+        other => return Err(format!("{} auth is not supported", other)),
+    }))
+}
+
+// The auth of a collection.bru or folder.bru, which requests set to `inherit` use.
+fn own_auth(file: &BruFile) -> Result<Option<Auth>, String> {
+    auth(file, &file.value("auth", "mode"))
+}
+
+fn file_reference(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("@file(")?
+        .split_once(')')
+        .map(|(path, _)| path)
+}
+
+fn apply_body(
+    file: &BruFile,
+    mode: &str,
+    root: &Path,
+    request: &mut RequestInput,
+) -> Result<(), String> {
+    let content_type = match mode {
+        "" | "none" => return Ok(()),
+        "json" | "graphql" => "application/json",
+        "xml" => "application/xml",
+        "text" => "text/plain",
+        "formUrlEncoded" => {
+            request.form = Some(file.entries("body:form-urlencoded"));
+            return Ok(());
+        }
+        "multipartForm" => {
+            let mut payload = MultipartPayload {
+                fields: HashMap::new(),
+                files: Vec::new(),
+            };
+            for (key, value) in file.entries("body:multipart-form") {
+                match file_reference(&value) {
+                    Some(paths) => payload.files.push(MultipartFile {
+                        name: key,
+                        paths: paths
+                            .split('|')
+                            .map(|path| root.join(path).to_string_lossy().into_owned())
+                            .collect(),
+                    }),
+                    None => {
+                        payload.fields.insert(key, value);
+                    }
+                }
+            }
+            request.multipart = Some(payload);
+            return Ok(());
+        }
+        "file" => {
+            request.body_file = file
+                .entries("body:file")
+                .iter()
+                .find_map(|(_, value)| file_reference(value))
+                .map(|path| root.join(path).to_string_lossy().into_owned());
+            return Ok(());
+        }
+        other => return Err(format!("{} bodies are not supported", other)),
+    };
+    request.body = if mode == "graphql" {
+        let mut payload =
+            serde_json::json!({ "query": file.text("body:graphql").unwrap_or_default() });
+        if let Some(variables) = file
+            .text("body:graphql:vars")
+            .filter(|vars| !vars.trim().is_empty())
+        {
+            payload["variables"] = serde_json::from_str(&variables)
+                .map_err(|e| format!("GraphQL variables are not JSON: {}", e))?;
+        }
+        Some(payload.to_string())
+    } else {
+        file.text(&format!("body:{}", mode))
+    };
+    let has_content_type = request
+        .headers
+        .keys()
+        .any(|key| key.eq_ignore_ascii_case("content-type"));
+    if !has_content_type {
+        request
+            .headers
+            .insert("Content-Type".into(), content_type.into());
+    }
+    Ok(())
+}
+
+fn import_request(
+    file: &BruFile,
+    folder: &str,
+    root: &Path,
+    inherited: &Option<Auth>,
+    warnings: &mut Vec<String>,
+) -> Option<(i64, SavedRequest)> {
+    let name = file.value("meta", "name");
+    let (method, _) = file
+        .blocks
+        .iter()
+        .find(|(block, _)| METHODS.contains(&block.as_str()))?;
+    let location = if folder.is_empty() {
+        name.clone()
+    } else {
+        format!("{}/{}", folder, name)
+    };
+    let mut request = RequestInput {
+        method: method.to_uppercase(),
+        url: file.value(method, "url"),
+        headers: file.entries("headers").into_iter().collect(),
+        ..RequestInput::default()
+    };
+    for (key, value) in file.entries("params:path") {
+        request.url = request.url.replace(&format!(":{}", key), &value);
+    }
+    if let Err(e) = apply_body(file, &file.value(method, "body"), root, &mut request) {
+        warnings.push(format!("{}: {}", location, e));
+    }
+    request.auth = match file.value(method, "auth").as_str() {
+        "inherit" => inherited.clone(),
+        mode => auth(file, mode).unwrap_or_else(|e| {
+            warnings.push(format!("{}: {}", location, e));
+            None
+        }),
+    };
+    if file
+        .blocks
+        .iter()
+        .any(|(block, _)| block.starts_with("script:") || block == "tests")
+    {
+        warnings.push(format!("{}: scripts were not imported", location));
+    }
+    let now = Utc::now();
+    let seq = file.value("meta", "seq").parse().unwrap_or(i64::MAX);
+    Some((
+        seq,
+        SavedRequest {
+            id: new_id(),
+            name,
+            folder: folder.to_string(),
+            request,
+            examples: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        },
+    ))
+}
+
+fn read(path: &Path) -> Result<BruFile, String> {
+    std::fs::read_to_string(path)
+        .map(|content| parse(&content))
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+}
+
+fn walk(
+    dir: &Path,
+    folder: &str,
+    root: &Path,
+    inherited: &Option<Auth>,
+    imported: &mut CollectionImport,
+) -> Result<(), String> {
+    let mut entries: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    let mut requests = Vec::new();
+    for path in entries {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        if file_name.starts_with('.') || file_name == "node_modules" {
+            continue;
+        }
+        if path.is_dir() {
+            if folder.is_empty() && file_name == "environments" {
+                continue;
+            }
+            let nested = if folder.is_empty() {
+                file_name.clone()
+            } else {
+                format!("{}/{}", folder, file_name)
+            };
+            let mut auth = inherited.clone();
+            let folder_file = path.join("folder.bru");
+            if folder_file.exists() {
+                let folder_bru = read(&folder_file)?;
+                if !matches!(folder_bru.value("auth", "mode").as_str(), "" | "inherit") {
+                    auth = own_auth(&folder_bru).unwrap_or_else(|e| {
+                        imported.warnings.push(format!("{}: {}", nested, e));
+                        None
+                    });
+                }
+            }
+            walk(&path, &nested, root, &auth, imported)?;
+        } else if file_name.ends_with(".bru")
+            && file_name != "folder.bru"
+            && file_name != "collection.bru"
+        {
+            let file = read(&path)?;
+            if let Some(request) =
+                import_request(&file, folder, root, inherited, &mut imported.warnings)
+            {
+                requests.push(request);
+            }
+        }
+    }
+    requests.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| x.name.cmp(&y.name)));
+    imported
+        .saved_requests
+        .extend(requests.into_iter().map(|(_, request)| request));
+    Ok(())
+}
+
+// Secret variables are listed by name only, so they come in empty.
+fn environment(name: &str, file: &BruFile) -> Environment {
+    let mut variables: Vec<Variable> = match file.block("vars") {
+        Some(Block::Dictionary(entries)) => entries
+            .iter()
+            .map(|(name, value, enabled)| Variable {
+                name: name.clone(),
+                value: value.clone(),
+                enabled: *enabled,
+                secret: false,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    if let Some(Block::List(names)) = file.block("vars:secret") {
+        variables.extend(names.iter().map(|name| {
+            let (enabled, name) = match name.strip_prefix('~') {
+                Some(name) => (false, name),
+                None => (true, name.as_str()),
+            };
+            Variable {
+                name: name.to_string(),
+                value: String::new(),
+                enabled,
+                secret: true,
+            }
+        }));
+    }
+    Environment {
+        name: name.to_string(),
+        variables,
+    }
+}
+
+// Reads a Bruno collection folder: one saved request per `.bru` file, in folders as on
+// disk, and one environment per file in `environments`.
+pub fn import(root: &Path) -> Result<CollectionImport, String> {
+    let manifest = root.join("bruno.json");
+    let manifest: serde_json::Value = std::fs::read_to_string(&manifest)
+        .map_err(|_| format!("{} is not a Bruno collection", root.display()))
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))?;
+    let name = manifest
+        .get("name")
+        .and_then(|name| name.as_str())
+        .map(String::from)
+        .or_else(|| {
+            root.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Bruno collection".into());
+    let mut imported = CollectionImport {
+        name,
+        ..CollectionImport::default()
+    };
+    let collection_file = root.join("collection.bru");
+    let auth = if collection_file.exists() {
+        own_auth(&read(&collection_file)?).unwrap_or_else(|e| {
+            imported.warnings.push(format!("Collection: {}", e));
+            None
+        })
+    } else {
+        None
+    };
+    walk(root, "", root, &auth, &mut imported)?;
+    if let Ok(entries) = std::fs::read_dir(root.join("environments")) {
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        for path in paths
+            .iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "bru"))
+        {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            imported.environments.push(environment(&name, &read(path)?));
+        }
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_requests_folders_and_environments() {
+        let root = std::env::temp_dir().join(format!("restman-bruno-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("users")).unwrap();
+        std::fs::create_dir_all(root.join("environments")).unwrap();
+        std::fs::write(
+            root.join("bruno.json"),
+            r#"{"version": "1", "name": "Shop", "type": "collection"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("collection.bru"),
+            "auth {\n  mode: bearer\n}\n\nauth:bearer {\n  token: {{token}}\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("users").join("Create user.bru"),
+            "meta {\n  name: Create user\n  type: http\n  seq: 2\n}\n\npost {\n  url: {{base}}/users/:team\n  body: json\n  auth: inherit\n}\n\nparams:path {\n  team: 7\n}\n\nheaders {\n  Accept: application/json\n  ~X-Off: 1\n}\n\nbody:json {\n  {\n    \"name\": \"ann\"\n  }\n}\n\ntests {\n  test(\"ok\", () => {});\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("users").join("List users.bru"),
+            "meta {\n  name: List users\n  type: http\n  seq: 1\n}\n\nget {\n  url: {{base}}/users\n  body: none\n  auth: basic\n}\n\nauth:basic {\n  username: ann\n  password: secret\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("environments").join("Staging.bru"),
+            "vars {\n  base: https://staging.shop.test\n}\nvars:secret [\n  token\n]\n",
+        )
+        .unwrap();
+
+        let imported = import(&root).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+        assert_eq!(imported.name, "Shop");
+        let [list, create] = &imported.saved_requests[..] else {
+            panic!("expected two requests");
+        };
+        assert_eq!(
+            (list.name.as_str(), list.folder.as_str()),
+            ("List users", "users")
+        );
+        assert_eq!(
+            list.request.auth,
+            Some(Auth::Basic {
+                username: "ann".into(),
+                password: "secret".into()
+            })
+        );
+        assert_eq!(create.request.url, "{{base}}/users/7");
+        assert_eq!(create.request.headers.len(), 2);
+        assert_eq!(
+            create.request.body.as_deref(),
+            Some("{\n  \"name\": \"ann\"\n}")
+        );
+        assert_eq!(
+            create.request.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+        assert_eq!(
+            imported.warnings,
+            vec!["users/Create user: scripts were not imported"]
+        );
+        let staging = &imported.environments[0];
+        assert_eq!(staging.name, "Staging");
+        assert_eq!(staging.variables.len(), 2);
+        assert!(staging.variables[1].secret);
+    }
+}
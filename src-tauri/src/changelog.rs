@@ -0,0 +1,228 @@
+use crate::{Endpoint, OpenApiCollection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EndpointRef {
+    pub method: String,
+    pub route: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EndpointChange {
+    pub method: String,
+    pub route: String,
+    // Human-readable notes such as "parameter added: limit".
+    pub changes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CollectionChangelog {
+    pub url: String,
+    pub added: Vec<EndpointRef>,
+    pub removed: Vec<EndpointRef>,
+    pub changed: Vec<EndpointChange>,
+    pub added_schemas: Vec<String>,
+    pub removed_schemas: Vec<String>,
+    pub changed_schemas: Vec<String>,
+}
+
+impl CollectionChangelog {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.added_schemas.is_empty()
+            && self.removed_schemas.is_empty()
+            && self.changed_schemas.is_empty()
+    }
+}
+
+fn endpoints(collection: &OpenApiCollection) -> BTreeMap<(String, String), &Endpoint> {
+    collection
+        .groups
+        .values()
+        .flatten()
+        .map(|endpoint| ((endpoint.route.clone(), endpoint.method.clone()), endpoint))
+        .collect()
+}
+
+fn json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn compare_endpoints(old: &Endpoint, new: &Endpoint) -> Vec<String> {
+    let mut changes = Vec::new();
+    for param in &new.parameters {
+        match old
+            .parameters
+            .iter()
+            .find(|p| p.name == param.name && p.in_type == param.in_type)
+        {
+            None => changes.push(format!("parameter added: {}", param.name)),
+            Some(previous) if json(previous) != json(param) => {
+                changes.push(format!("parameter changed: {}", param.name))
+            }
+            Some(_) => {}
+        }
+    }
+    for param in &old.parameters {
+        if !new
+            .parameters
+            .iter()
+            .any(|p| p.name == param.name && p.in_type == param.in_type)
+        {
+            changes.push(format!("parameter removed: {}", param.name));
+        }
+    }
+    let body = |e: &Endpoint| {
+        json(&(
+            &e.body_example,
+            &e.body_required,
+            &e.body_media_types,
+            &e.body_fields,
+        ))
+    };
+    if body(old) != body(new) {
+        changes.push("request body changed".into());
+    }
+    for (status, response) in &new.responses {
+        match old.responses.get(status) {
+            None => changes.push(format!("response added: {}", status)),
+            Some(previous) if json(previous) != json(response) => {
+                changes.push(format!("response changed: {}", status))
+            }
+            Some(_) => {}
+        }
+    }
+    for status in old.responses.keys() {
+        if !new.responses.contains_key(status) {
+            changes.push(format!("response removed: {}", status));
+        }
+    }
+    if json(&old.security) != json(&new.security) {
+        changes.push("security changed".into());
+    }
+    if old.deprecated != new.deprecated {
+        changes.push(if new.deprecated {
+            "deprecated".into()
+        } else {
+            "no longer deprecated".into()
+        });
+    }
+    if old.summary != new.summary || old.description != new.description {
+        changes.push("description changed".into());
+    }
+    changes
+}
+
+// Endpoints are matched by method and spec path, so a server switch alone is not a
+// change.
+pub fn diff_collections(old: &OpenApiCollection, new: &OpenApiCollection) -> CollectionChangelog {
+    let old_endpoints = endpoints(old);
+    let new_endpoints = endpoints(new);
+    let mut changelog = CollectionChangelog {
+        url: new.url.clone(),
+        ..CollectionChangelog::default()
+    };
+    for ((route, method), endpoint) in &new_endpoints {
+        match old_endpoints.get(&(route.clone(), method.clone())) {
+            None => changelog.added.push(EndpointRef {
+                method: method.clone(),
+                route: route.clone(),
+            }),
+            Some(previous) => {
+                let changes = compare_endpoints(previous, endpoint);
+                if !changes.is_empty() {
+                    changelog.changed.push(EndpointChange {
+                        method: method.clone(),
+                        route: route.clone(),
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    for (route, method) in old_endpoints.keys() {
+        if !new_endpoints.contains_key(&(route.clone(), method.clone())) {
+            changelog.removed.push(EndpointRef {
+                method: method.clone(),
+                route: route.clone(),
+            });
+        }
+    }
+    for (name, digest) in &new.schema_digests {
+        match old.schema_digests.get(name) {
+            None => changelog.added_schemas.push(name.clone()),
+            Some(previous) if previous != digest => changelog.changed_schemas.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    for name in old.schema_digests.keys() {
+        if !new.schema_digests.contains_key(name) {
+            changelog.removed_schemas.push(name.clone());
+        }
+    }
+    changelog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_openapi_internal;
+    use serde_json::json;
+
+    fn collection(paths: Value, schemas: Value) -> OpenApiCollection {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Pets" },
+            "paths": paths,
+            "components": { "schemas": schemas }
+        });
+        parse_openapi_internal(doc, "pets.json", None).unwrap()
+    }
+
+    #[test]
+    fn reports_endpoint_and_schema_changes() {
+        let old = collection(
+            json!({
+                "/pets": {
+                    "get": {
+                        "parameters": [{ "name": "page", "in": "query", "schema": { "type": "integer" } }],
+                        "responses": { "200": { "description": "ok" } }
+                    },
+                    "delete": { "responses": {} }
+                }
+            }),
+            json!({ "Pet": { "type": "object" }, "Old": { "type": "string" } }),
+        );
+        let new = collection(
+            json!({
+                "/pets": {
+                    "get": {
+                        "parameters": [{ "name": "limit", "in": "query", "schema": { "type": "integer" } }],
+                        "responses": { "200": { "description": "ok" }, "429": { "description": "slow down" } }
+                    },
+                    "post": { "responses": {} }
+                }
+            }),
+            json!({ "Pet": { "type": "object", "required": ["name"] }, "New": { "type": "string" } }),
+        );
+        let changelog = diff_collections(&old, &new);
+        assert_eq!(changelog.added[0].method, "POST");
+        assert_eq!(changelog.removed[0].method, "DELETE");
+        assert_eq!(
+            changelog.changed[0].changes,
+            vec![
+                "parameter added: limit",
+                "parameter removed: page",
+                "response added: 429"
+            ]
+        );
+        assert_eq!(changelog.added_schemas, vec!["New"]);
+        assert_eq!(changelog.removed_schemas, vec!["Old"]);
+        assert_eq!(changelog.changed_schemas, vec!["Pet"]);
+        assert!(diff_collections(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    pub value: String,
+}
+
+pub enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+        }
+    }
+
+    pub fn finish(self) -> String {
+        match self {
+            Hasher::Md5(h) => hex::encode(h.finalize()),
+            Hasher::Sha1(h) => hex::encode(h.finalize()),
+            Hasher::Sha256(h) => hex::encode(h.finalize()),
+        }
+    }
+
+    // Feeds the first `limit` bytes of a file (or all of it) into the hasher.
+    pub async fn update_from_file(
+        &mut self,
+        path: &Path,
+        limit: Option<u64>,
+    ) -> Result<(), String> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut reader = file.take(limit.unwrap_or(u64::MAX));
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf).await.map_err(|e| e.to_string())?;
+            if n == 0 {
+                return Ok(());
+            }
+            self.update(&buf[..n]);
+        }
+    }
+}
+
+impl Checksum {
+    pub fn verify(&self, actual: &str) -> Result<(), String> {
+        let expected = self.value.trim();
+        if expected.eq_ignore_ascii_case(actual) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Checksum mismatch: expected {:?} {}, got {}",
+                self.algorithm, expected, actual
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_with_each_algorithm() {
+        let digest = |algorithm| {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(b"hello ");
+            hasher.update(b"world");
+            hasher.finish()
+        };
+        assert_eq!(
+            digest(ChecksumAlgorithm::Md5),
+            "5eb63bbbe01eeed093cb22bb8f5acdc3"
+        );
+        assert_eq!(
+            digest(ChecksumAlgorithm::Sha1),
+            "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed"
+        );
+        assert_eq!(
+            digest(ChecksumAlgorithm::Sha256),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn verify_ignores_case_and_reports_mismatch() {
+        let checksum = Checksum {
+            algorithm: ChecksumAlgorithm::Md5,
+            value: "5EB63BBBE01EEED093CB22BB8F5ACDC3".into(),
+        };
+        assert!(checksum.verify("5eb63bbbe01eeed093cb22bb8f5acdc3").is_ok());
+        assert!(checksum.verify("00").is_err());
+    }
+}
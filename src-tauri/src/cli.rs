@@ -0,0 +1,355 @@
+use crate::environments::Environment;
+use crate::runner::{RunOptions, RunStep, RunSummary, StepResult};
+use crate::saved::SavedRequest;
+use serde_json::Value;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "Usage: restman run <file> [--env <name>] [--data <file>] \
+[--delay <ms>] [--bail] [--honor-rate-limits] [--reporter cli|json|junit]... [--output <dir>]";
+
+const JSON_REPORT: &str = "restman-report.json";
+const JUNIT_REPORT: &str = "restman-junit.xml";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reporter {
+    Cli,
+    Json,
+    Junit,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CliOptions {
+    // A run file (`RunOptions` as JSON) or a workspace export.
+    pub file: String,
+    pub environment: Option<String>,
+    pub data_file: Option<String>,
+    pub delay_ms: u64,
+    pub bail: bool,
+    pub honor_rate_limits: bool,
+    pub reporters: Vec<Reporter>,
+    // Where the json and junit reports are written.
+    pub output: PathBuf,
+}
+
+// Parses the arguments after `run`.
+pub fn parse_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut file = None;
+    let mut options = CliOptions {
+        file: String::new(),
+        environment: None,
+        data_file: None,
+        delay_ms: 0,
+        bail: false,
+        honor_rate_limits: false,
+        reporters: Vec::new(),
+        output: PathBuf::from("."),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--env" | "-e" => options.environment = Some(value()?),
+            "--data" | "-d" => options.data_file = Some(value()?),
+            "--delay" => {
+                options.delay_ms = value()?
+                    .parse()
+                    .map_err(|_| "--delay takes milliseconds".to_string())?
+            }
+            "--bail" => options.bail = true,
+            "--honor-rate-limits" => options.honor_rate_limits = true,
+            "--reporter" | "-r" => {
+                for name in value()?.split(',') {
+                    options.reporters.push(match name.trim() {
+                        "cli" => Reporter::Cli,
+                        "json" => Reporter::Json,
+                        "junit" => Reporter::Junit,
+                        other => return Err(format!("Unknown reporter: {}", other)),
+                    });
+                }
+            }
+            "--output" | "-o" => options.output = PathBuf::from(value()?),
+            flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+            path if file.is_none() => file = Some(path.to_string()),
+            extra => return Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+    options.file = file.ok_or("Missing the file to run")?;
+    if options.reporters.is_empty() {
+        options.reporters.push(Reporter::Cli);
+    }
+    Ok(options)
+}
+
+// Builds the run from a run file, or from a workspace export by running its saved
+// requests in order. Runs do not see the app's own data, so `--env` must name an
+// environment in the file's `environments`.
+pub fn run_options(content: &str, cli: &CliOptions) -> Result<RunOptions, String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let environments: Vec<Environment> = value
+        .get("environments")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| e.to_string())?
+        .unwrap_or_default();
+    let mut run: RunOptions = if value.get("steps").is_some() {
+        serde_json::from_value(value).map_err(|e| e.to_string())?
+    } else if let Some(saved) = value.get("saved_requests") {
+        let saved: Vec<SavedRequest> =
+            serde_json::from_value(saved.clone()).map_err(|e| e.to_string())?;
+        RunOptions {
+            steps: saved
+                .into_iter()
+                .map(|saved| RunStep::Request {
+                    name: if saved.folder.is_empty() {
+                        saved.name
+                    } else {
+                        format!("{}/{}", saved.folder, saved.name)
+                    },
+                    request: Box::new(saved.request),
+                })
+                .collect(),
+            ..RunOptions::default()
+        }
+    } else {
+        return Err("Expected a run file with `steps` or a workspace export".into());
+    };
+    if let Some(name) = &cli.environment {
+        let environment = environments
+            .iter()
+            .find(|environment| &environment.name == name)
+            .ok_or_else(|| format!("No environment named {} in {}", name, cli.file))?;
+        run.variables = Some(environment.values());
+    }
+    if cli.data_file.is_some() {
+        run.data_file = cli.data_file.clone();
+    }
+    if cli.delay_ms > 0 {
+        run.delay_ms = cli.delay_ms;
+    }
+    run.stop_on_failure |= cli.bail;
+    run.honor_rate_limits |= cli.honor_rate_limits;
+    Ok(run)
+}
+
+// Why a step failed, one line per reason.
+fn failures(step: &StepResult) -> Vec<String> {
+    let mut reasons: Vec<String> = step.error.iter().cloned().collect();
+    for result in step.assertions.iter().filter(|result| !result.passed) {
+        let assertion = serde_json::to_string(&result.assertion).unwrap_or_default();
+        reasons.push(match (&result.message, &result.actual) {
+            (Some(message), _) => format!("{}: {}", assertion, message),
+            (None, Some(actual)) => format!("{}: got {}", assertion, actual),
+            (None, None) => assertion,
+        });
+    }
+    if let Some(scripts) = step.response.as_ref().and_then(|r| r.scripts.as_ref()) {
+        reasons.extend(scripts.error.iter().map(|e| format!("Script error: {}", e)));
+        reasons.extend(
+            scripts
+                .tests
+                .iter()
+                .filter(|test| !test.passed)
+                .map(|test| format!("Test failed: {}", test.name)),
+        );
+    }
+    if reasons.is_empty() && !step.passed {
+        if let Some(status) = step.status {
+            reasons.push(format!("Status {}", status));
+        }
+    }
+    reasons
+}
+
+pub fn summary_text(summary: &RunSummary) -> String {
+    let mut text = String::new();
+    for step in &summary.steps {
+        text.push_str(&format!(
+            "{} {} {} {} ({}, {} ms)\n",
+            if step.passed { "PASS" } else { "FAIL" },
+            step.name,
+            step.method,
+            step.url,
+            step.status
+                .map(|status| status.to_string())
+                .unwrap_or_else(|| "no response".into()),
+            step.elapsed_ms
+        ));
+        for reason in failures(step) {
+            text.push_str(&format!("    {}\n", reason));
+        }
+    }
+    text.push_str(&format!(
+        "\n{} passed, {} failed, {} skipped in {} ms\n",
+        summary.passed, summary.failed, summary.skipped, summary.elapsed_ms
+    ));
+    text
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{:.3}", ms as f64 / 1000.0)
+}
+
+// One test suite per data row, one test case per step.
+pub fn junit_report(summary: &RunSummary) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <testsuites name=\"restman\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+        summary.steps.len(),
+        summary.failed,
+        seconds(summary.elapsed_ms)
+    );
+    for iteration in &summary.iterations {
+        let steps: Vec<&StepResult> = summary
+            .steps
+            .iter()
+            .filter(|step| step.iteration == iteration.index)
+            .collect();
+        xml.push_str(&format!(
+            "  <testsuite name=\"Iteration {}\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+            iteration.index + 1,
+            steps.len(),
+            iteration.failed,
+            seconds(steps.iter().map(|step| step.elapsed_ms).sum())
+        ));
+        for step in steps {
+            let name = if step.name.is_empty() {
+                format!("{} {}", step.method, step.url)
+            } else {
+                step.name.clone()
+            };
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"restman\" time=\"{}\"",
+                xml_escape(&name),
+                seconds(step.elapsed_ms)
+            ));
+            let reasons = failures(step);
+            if step.passed {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(&format!(
+                ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                xml_escape(reasons.first().map(String::as_str).unwrap_or("Failed")),
+                xml_escape(&reasons.join("\n"))
+            ));
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+pub fn write_reports(summary: &RunSummary, cli: &CliOptions) -> Result<(), String> {
+    for reporter in &cli.reporters {
+        let (file, content) = match reporter {
+            Reporter::Cli => continue,
+            Reporter::Json => (
+                JSON_REPORT,
+                serde_json::to_string_pretty(summary).map_err(|e| e.to_string())?,
+            ),
+            Reporter::Junit => (JUNIT_REPORT, junit_report(summary)),
+        };
+        std::fs::create_dir_all(&cli.output).map_err(|e| e.to_string())?;
+        std::fs::write(cli.output.join(file), content).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 0 when every step ran and passed, 1 otherwise.
+pub fn exit_code(summary: &RunSummary) -> i32 {
+    if summary.failed == 0 && summary.skipped == 0 {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::RequestInput;
+    use crate::runner::IterationResult;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_arguments_and_workspace_exports() {
+        let cli = parse_args(&args(
+            "api.json --env staging -r junit,json --bail --honor-rate-limits",
+        ))
+        .unwrap();
+        assert_eq!(cli.file, "api.json");
+        assert_eq!(cli.reporters, vec![Reporter::Junit, Reporter::Json]);
+        assert!(cli.bail);
+        assert!(parse_args(&args("api.json --reporter html")).is_err());
+        assert!(parse_args(&args("--env")).is_err());
+        assert!(parse_args(&[]).is_err());
+
+        let export = serde_json::json!({
+            "saved_requests": [
+                { "name": "Login", "folder": "auth", "request": { "method": "POST", "url": "{{base}}/login" } }
+            ],
+            "environments": [
+                { "name": "staging", "variables": [{ "name": "base", "value": "https://staging.test" }] }
+            ]
+        });
+        let run = run_options(&export.to_string(), &cli).unwrap();
+        assert!(run.stop_on_failure);
+        assert!(run.honor_rate_limits);
+        assert_eq!(run.environment, None);
+        assert_eq!(run.variables.unwrap()["base"], "https://staging.test");
+        assert!(matches!(&run.steps[0], RunStep::Request { name, .. } if name == "auth/Login"));
+
+        // An environment the file does not have is an error, not the local one.
+        let production = parse_args(&args("api.json --env production")).unwrap();
+        let err = run_options(&export.to_string(), &production).unwrap_err();
+        assert!(err.contains("production"), "{}", err);
+    }
+
+    #[test]
+    fn writes_junit_with_failures() {
+        let request = RequestInput {
+            method: "GET".into(),
+            url: "https://api.test/<x>".into(),
+            ..RequestInput::default()
+        };
+        let summary = RunSummary {
+            run_id: "run".into(),
+            steps: vec![StepResult::new(
+                "run",
+                0,
+                0,
+                String::new(),
+                &request,
+                Err("connection refused".into()),
+                1500,
+            )],
+            iterations: vec![IterationResult {
+                index: 0,
+                failed: 1,
+                ..IterationResult::default()
+            }],
+            failed: 1,
+            ..RunSummary::default()
+        };
+        let xml = junit_report(&summary);
+        assert!(xml.contains("<testcase name=\"GET https://api.test/&lt;x&gt;\""));
+        assert!(xml.contains("time=\"1.500\""));
+        assert!(xml.contains("<failure message=\"connection refused\">"));
+        assert_eq!(exit_code(&summary), 1);
+    }
+}
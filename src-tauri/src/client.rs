@@ -0,0 +1,580 @@
+use crate::cookies::{jar_key, CookieJar};
+use crate::ntlm::NtlmCredentials;
+use crate::settings::{
+    CertificateFormat, ClientCertificate, PoolSettings, ProxyMode, ProxyServer, ProxySettings,
+    Settings,
+};
+use crate::storage::Store;
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    #[default]
+    Auto,
+    Http1,
+    // Offered first over TLS, falling back to HTTP/1.1 where the server does not take it;
+    // plain http:// stays on HTTP/1.1.
+    Http2,
+    // Cleartext HTTP/2 without negotiation, for servers known to speak it.
+    H2c,
+    Http3,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClientKey {
+    pub http_version: HttpVersion,
+    pub certificate: Option<String>,
+    pub accept_invalid_certs: bool,
+    pub cookie_jar: Option<String>,
+}
+
+// Clients are cached per distinct configuration so connection pools survive between
+// requests. Requests without a jar id share the default cookie jar; named jars keep
+// e.g. staging and production sessions apart.
+pub struct ClientManager {
+    jars: Mutex<HashMap<Option<String>, Arc<CookieJar>>>,
+    cookie_store: Mutex<Option<Arc<Store>>>,
+    settings: Mutex<Settings>,
+    clients: Mutex<HashMap<ClientKey, Client>>,
+}
+
+impl ClientManager {
+    pub fn new(settings: Settings) -> Self {
+        ClientManager {
+            jars: Mutex::new(HashMap::new()),
+            cookie_store: Mutex::new(None),
+            settings: Mutex::new(settings),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Jars are loaded from `store` the first time they are used; `None` keeps cookies
+    // in memory only. Jars already open are written to `store` in full on the next save.
+    pub fn persist_cookies_in(&self, store: Option<Arc<Store>>) {
+        *self.cookie_store.lock().unwrap() = store;
+        for jar in self.jars.lock().unwrap().values() {
+            jar.mark_changed();
+        }
+    }
+
+    // Drops every jar and the clients using them, so cookies are loaded again from
+    // `store`, e.g. after switching workspaces.
+    pub fn reset_cookies(&self, store: Option<Arc<Store>>) {
+        *self.cookie_store.lock().unwrap() = store;
+        self.jars.lock().unwrap().clear();
+        self.clients.lock().unwrap().clear();
+    }
+
+    pub fn jar(&self, id: Option<&str>) -> Arc<CookieJar> {
+        let id = id.filter(|id| !id.is_empty()).map(|id| id.to_string());
+        let mut jars = self.jars.lock().unwrap();
+        if let Some(jar) = jars.get(&id) {
+            return jar.clone();
+        }
+        let jar = Arc::new(CookieJar::default());
+        if let Some(store) = self.cookie_store.lock().unwrap().as_deref() {
+            if let Ok(Some(content)) = store.cookie_jar(&jar_key(id.as_deref())) {
+                jar.load_json(&content);
+            }
+        }
+        jars.insert(id, jar.clone());
+        jar
+    }
+
+    // Writes the jars that changed since they were last saved.
+    pub fn save_cookies(&self) -> Result<(), String> {
+        let store = match self.cookie_store.lock().unwrap().clone() {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        let changed: Vec<(Option<String>, Arc<CookieJar>)> = self
+            .jars
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, jar)| jar.take_changed())
+            .map(|(id, jar)| (id.clone(), jar.clone()))
+            .collect();
+        for (id, jar) in changed {
+            let saved = jar
+                .to_json()
+                .and_then(|json| store.put_cookie_jar(&jar_key(id.as_deref()), &json));
+            if let Err(e) = saved {
+                jar.mark_changed();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, settings: &Settings) -> Result<(), String> {
+        build_proxies(&settings.proxy)?;
+        for certificate in &settings.client_certificates {
+            load_identity(certificate)?;
+        }
+        for path in &settings.ca_certificates {
+            load_ca_certificates(path)?;
+        }
+        *self.settings.lock().unwrap() = settings.clone();
+        self.clients.lock().unwrap().clear();
+        Ok(())
+    }
+
+    // Certificates bound to the collection win over hostname matches.
+    pub fn certificate_for(&self, url: &str, collection: Option<&str>) -> Option<String> {
+        let settings = self.settings.lock().unwrap();
+        let certificates = &settings.client_certificates;
+        if let Some(collection) = collection {
+            if let Some(found) = certificates
+                .iter()
+                .find(|cert| cert.collections.iter().any(|c| c == collection))
+            {
+                return Some(found.name.clone());
+            }
+        }
+        let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+        certificates
+            .iter()
+            .find(|cert| {
+                cert.hosts
+                    .iter()
+                    .any(|pattern| host_matches(pattern, &host))
+            })
+            .map(|cert| cert.name.clone())
+    }
+
+    // Only plain HTTP requests can answer NTLM proxy challenges; HTTPS requests tunnel
+    // through a CONNECT that reqwest sends on its own.
+    pub fn proxy_ntlm_for(&self, url: &str) -> Option<NtlmCredentials> {
+        if !url.trim_start().to_ascii_lowercase().starts_with("http://") {
+            return None;
+        }
+        let settings = self.settings.lock().unwrap();
+        if settings.proxy.mode != ProxyMode::Manual {
+            return None;
+        }
+        let server = settings.proxy.http.as_ref().filter(|s| s.ntlm)?;
+        Some(NtlmCredentials {
+            username: server.username.clone().unwrap_or_default(),
+            password: server.password.clone().unwrap_or_default(),
+            domain: String::new(),
+        })
+    }
+
+    // Whether a request to `url` may go through a proxy. With the system setting, Windows
+    // and macOS keep their proxy outside the environment, so there it always may.
+    pub fn proxy_applies(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let settings = self.settings.lock().unwrap();
+        let proxy = &settings.proxy;
+        match proxy.mode {
+            ProxyMode::None => false,
+            ProxyMode::System => {
+                cfg!(any(target_os = "windows", target_os = "macos"))
+                    || ["http_proxy", "https_proxy", "all_proxy"]
+                        .iter()
+                        .any(|name| {
+                            std::env::var_os(name).is_some()
+                                || std::env::var_os(name.to_ascii_uppercase()).is_some()
+                        })
+            }
+            ProxyMode::Manual => {
+                let server = match url.scheme() {
+                    "http" => proxy.http.as_ref(),
+                    "https" => proxy.https.as_ref(),
+                    _ => None,
+                };
+                (server.is_some() || proxy.socks5.is_some())
+                    && !bypasses_proxy(&proxy.no_proxy, &host)
+            }
+        }
+    }
+
+    pub fn client(&self, key: &ClientKey) -> Result<Client, String> {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(key) {
+            return Ok(client.clone());
+        }
+        let settings = self.settings.lock().unwrap().clone();
+        let client = build_client(self.jar(key.cookie_jar.as_deref()), &settings, key)?;
+        clients.insert(key.clone(), client.clone());
+        Ok(client)
+    }
+}
+
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host.len() > suffix.len() && host.ends_with(&format!(".{}", suffix)),
+        None => pattern == host,
+    }
+}
+
+// `no_proxy` entries as reqwest reads them: "*", a host, or a domain with or without a
+// leading "." or "*.".
+fn bypasses_proxy(no_proxy: &[String], host: &str) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        let domain = entry.trim_start_matches('*').trim_start_matches('.');
+        entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+fn load_ca_certificates(path: &str) -> Result<Vec<Certificate>, String> {
+    let content =
+        std::fs::read(path).map_err(|e| format!("Cannot read CA certificate {}: {}", path, e))?;
+    if let Ok(bundle) = Certificate::from_pem_bundle(&content) {
+        if !bundle.is_empty() {
+            return Ok(bundle);
+        }
+    }
+    Certificate::from_der(&content)
+        .map(|cert| vec![cert])
+        .map_err(|e| format!("Invalid CA certificate {}: {}", path, e))
+}
+
+fn is_certificate_error(err: &(dyn StdError + 'static)) -> bool {
+    let mut source: Option<&(dyn StdError + 'static)> = Some(err);
+    while let Some(current) = source {
+        let message = current.to_string().to_ascii_lowercase();
+        if message.contains("certificate") || message.contains("self signed") {
+            return true;
+        }
+        source = current.source();
+    }
+    false
+}
+
+pub fn describe_send_error(err: &reqwest::Error) -> String {
+    if err.is_connect() && is_certificate_error(err) {
+        let host = err
+            .url()
+            .and_then(|url| url.host_str())
+            .unwrap_or("the server");
+        return format!(
+            "TLS certificate verification failed for {}: {}. Add its CA certificate in settings or enable \"accept invalid certificates\" for this request.",
+            host, err
+        );
+    }
+    err.to_string()
+}
+
+fn load_identity(certificate: &ClientCertificate) -> Result<Identity, String> {
+    let read = |path: &str| {
+        std::fs::read(path).map_err(|e| {
+            format!(
+                "Cannot read {} for certificate {}: {}",
+                path, certificate.name, e
+            )
+        })
+    };
+    let cert = read(&certificate.cert_path)?;
+    let identity = match certificate.format {
+        CertificateFormat::Pkcs12 => {
+            Identity::from_pkcs12_der(&cert, certificate.passphrase.as_deref().unwrap_or(""))
+        }
+        CertificateFormat::Pem => {
+            let key = match certificate.key_path.as_deref().filter(|p| !p.is_empty()) {
+                Some(path) => read(path)?,
+                None => cert.clone(),
+            };
+            Identity::from_pkcs8_pem(&cert, &key)
+        }
+    };
+    identity.map_err(|e| format!("Invalid client certificate {}: {}", certificate.name, e))
+}
+
+fn build_proxy(
+    server: &ProxyServer,
+    make: fn(&str) -> reqwest::Result<Proxy>,
+    no_proxy: &Option<NoProxy>,
+) -> Result<Proxy, String> {
+    let mut proxy =
+        make(server.url.trim()).map_err(|e| format!("Invalid proxy {}: {}", server.url, e))?;
+    if let Some(username) = server
+        .username
+        .as_deref()
+        .filter(|u| !u.is_empty() && !server.ntlm)
+    {
+        proxy = proxy.basic_auth(username, server.password.as_deref().unwrap_or(""));
+    }
+    Ok(proxy.no_proxy(no_proxy.clone()))
+}
+
+fn build_proxies(settings: &ProxySettings) -> Result<Vec<Proxy>, String> {
+    if settings.mode != ProxyMode::Manual {
+        return Ok(Vec::new());
+    }
+    let no_proxy = NoProxy::from_string(&settings.no_proxy.join(","));
+    let mut proxies = Vec::new();
+    if let Some(server) = settings.http.as_ref().filter(|s| !s.url.trim().is_empty()) {
+        proxies.push(build_proxy(server, |url| Proxy::http(url), &no_proxy)?);
+    }
+    if let Some(server) = settings.https.as_ref().filter(|s| !s.url.trim().is_empty()) {
+        proxies.push(build_proxy(server, |url| Proxy::https(url), &no_proxy)?);
+    }
+    if let Some(server) = settings
+        .socks5
+        .as_ref()
+        .filter(|s| !s.url.trim().is_empty())
+    {
+        if !server.url.trim().starts_with("socks5") {
+            return Err(format!(
+                "SOCKS proxy must use socks5:// or socks5h://: {}",
+                server.url
+            ));
+        }
+        proxies.push(build_proxy(server, |url| Proxy::all(url), &no_proxy)?);
+    }
+    Ok(proxies)
+}
+
+fn build_client(
+    jar: Arc<CookieJar>,
+    settings: &Settings,
+    key: &ClientKey,
+) -> Result<Client, String> {
+    let mut builder = Client::builder().cookie_provider(jar);
+    // reqwest picks up the system/environment proxy unless told otherwise.
+    if settings.proxy.mode != ProxyMode::System {
+        builder = builder.no_proxy();
+    }
+    for entry in build_proxies(&settings.proxy)? {
+        builder = builder.proxy(entry);
+    }
+    if let Some(name) = &key.certificate {
+        let certificate = settings
+            .client_certificates
+            .iter()
+            .find(|cert| &cert.name == name)
+            .ok_or_else(|| format!("Unknown client certificate: {}", name))?;
+        builder = builder.identity(load_identity(certificate)?);
+    }
+    for path in &settings.ca_certificates {
+        for certificate in load_ca_certificates(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if key.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder = apply_pool(builder, &settings.pool);
+    builder = match key.http_version {
+        // ALPN already lists h2 ahead of http/1.1.
+        HttpVersion::Auto | HttpVersion::Http2 => builder,
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::H2c => builder.http2_prior_knowledge(),
+        HttpVersion::Http3 => return Err("HTTP/3 is not supported by this build".into()),
+    };
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn apply_pool(mut builder: reqwest::ClientBuilder, pool: &PoolSettings) -> reqwest::ClientBuilder {
+    if let Some(max) = pool.max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max);
+    }
+    if let Some(secs) = pool.idle_timeout_secs {
+        builder = builder.pool_idle_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = pool.tcp_keepalive_secs {
+        builder = builder.tcp_keepalive(Duration::from_secs(secs));
+    }
+    builder.tcp_nodelay(pool.tcp_nodelay)
+}
+
+pub fn version_label(version: reqwest::Version) -> &'static str {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_11 => "HTTP/1.1",
+        reqwest::Version::HTTP_2 => "HTTP/2",
+        reqwest::Version::HTTP_3 => "HTTP/3",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_clients_per_key_and_rejects_http3() {
+        let manager = ClientManager::new(Settings::default());
+        manager.client(&ClientKey::default()).unwrap();
+        manager
+            .client(&ClientKey {
+                http_version: HttpVersion::Http1,
+                ..ClientKey::default()
+            })
+            .unwrap();
+        manager.client(&ClientKey::default()).unwrap();
+        assert_eq!(manager.clients.lock().unwrap().len(), 2);
+        // New pool settings rebuild clients on their next use.
+        let settings = Settings {
+            pool: PoolSettings {
+                max_idle_per_host: Some(4),
+                idle_timeout_secs: Some(30),
+                tcp_keepalive_secs: Some(60),
+                tcp_nodelay: false,
+            },
+            ..Settings::default()
+        };
+        manager.apply(&settings).unwrap();
+        assert!(manager.clients.lock().unwrap().is_empty());
+        manager.client(&ClientKey::default()).unwrap();
+        assert!(manager
+            .client(&ClientKey {
+                http_version: HttpVersion::Http3,
+                ..ClientKey::default()
+            })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn http2_falls_back_to_http1_and_h2c_does_not() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+        let manager = ClientManager::new(Settings::default());
+        let client = |http_version| {
+            manager
+                .client(&ClientKey {
+                    http_version,
+                    ..ClientKey::default()
+                })
+                .unwrap()
+        };
+        let response = client(HttpVersion::Http2).get(&url).send().await.unwrap();
+        assert_eq!(response.version(), reqwest::Version::HTTP_11);
+        assert!(client(HttpVersion::H2c).get(&url).send().await.is_err());
+    }
+
+    #[test]
+    fn named_cookie_jars_are_isolated() {
+        let manager = ClientManager::new(Settings::default());
+        manager
+            .jar(Some("staging"))
+            .set("https://api.example.com/", "session=staging")
+            .unwrap();
+        assert!(manager.jar(None).list(None).is_empty());
+        assert!(manager.jar(Some("")).list(None).is_empty());
+        assert!(manager.jar(Some("production")).list(None).is_empty());
+        assert_eq!(manager.jar(Some("staging")).list(None).len(), 1);
+    }
+
+    #[test]
+    fn manual_proxy_settings_are_validated() {
+        let mut settings = ProxySettings {
+            mode: ProxyMode::Manual,
+            http: Some(ProxyServer {
+                url: "http://proxy.local:3128".into(),
+                username: Some("user".into()),
+                password: Some("secret".into()),
+                ntlm: false,
+            }),
+            socks5: Some(ProxyServer {
+                url: "socks5h://127.0.0.1:1080".into(),
+                ..ProxyServer::default()
+            }),
+            no_proxy: vec!["localhost".into(), ".internal".into()],
+            ..ProxySettings::default()
+        };
+        assert_eq!(build_proxies(&settings).unwrap().len(), 2);
+        settings.socks5 = Some(ProxyServer {
+            url: "http://not-socks:1080".into(),
+            ..ProxyServer::default()
+        });
+        assert!(build_proxies(&settings).is_err());
+        settings.mode = ProxyMode::System;
+        assert!(build_proxies(&settings).unwrap().is_empty());
+    }
+
+    #[test]
+    fn proxy_applies_unless_bypassed() {
+        let manager = ClientManager::new(Settings {
+            proxy: ProxySettings {
+                mode: ProxyMode::Manual,
+                https: Some(ProxyServer {
+                    url: "http://proxy.local:3128".into(),
+                    ..ProxyServer::default()
+                }),
+                no_proxy: vec!["localhost".into(), ".internal".into()],
+                ..ProxySettings::default()
+            },
+            ..Settings::default()
+        });
+        assert!(manager.proxy_applies("https://api.example.com/"));
+        assert!(!manager.proxy_applies("http://api.example.com/"));
+        assert!(!manager.proxy_applies("https://localhost:8443/"));
+        assert!(!manager.proxy_applies("https://billing.internal/"));
+        let direct = ClientManager::new(Settings {
+            proxy: ProxySettings {
+                mode: ProxyMode::None,
+                ..ProxySettings::default()
+            },
+            ..Settings::default()
+        });
+        assert!(!direct.proxy_applies("https://api.example.com/"));
+    }
+
+    #[test]
+    fn selects_certificate_by_collection_then_host() {
+        let manager = ClientManager::new(Settings {
+            client_certificates: vec![
+                ClientCertificate {
+                    name: "internal".into(),
+                    hosts: vec!["*.corp.example".into()],
+                    ..ClientCertificate::default()
+                },
+                ClientCertificate {
+                    name: "payments".into(),
+                    collections: vec!["https://specs/payments.json".into()],
+                    ..ClientCertificate::default()
+                },
+            ],
+            ..Settings::default()
+        });
+        assert_eq!(
+            manager.certificate_for("https://api.corp.example/v1", None),
+            Some("internal".to_string())
+        );
+        assert_eq!(
+            manager.certificate_for(
+                "https://api.corp.example/v1",
+                Some("https://specs/payments.json")
+            ),
+            Some("payments".to_string())
+        );
+        assert_eq!(manager.certificate_for("https://corp.example/", None), None);
+    }
+
+    #[test]
+    fn rejects_unreadable_ca_bundles() {
+        let dir = std::env::temp_dir().join(format!("restman-ca-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bogus.pem");
+        std::fs::write(&path, "not a certificate").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        assert!(load_ca_certificates(&path).is_err());
+        assert!(load_ca_certificates("/nonexistent/ca.pem").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,716 @@
+use crate::body::{mime_essence, BodyEncoding};
+use crate::http::{RequestInput, ResponseData};
+use jsonschema::JSONSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// A response the spec declares for an operation. `status` is as written in the spec:
+// "200", "4XX" or "default".
+#[derive(Clone, Debug)]
+pub struct DeclaredResponse {
+    pub status: String,
+    pub media_types: Vec<String>,
+    pub schema: Option<Value>,
+}
+
+// `location` is the parameter's `in`: "path", "query", "header" or "cookie".
+#[derive(Clone, Debug, Default)]
+pub struct DeclaredParameter {
+    pub name: String,
+    pub location: String,
+    pub required: bool,
+    pub enum_values: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContractOperation {
+    pub method: String,
+    pub route: String,
+    pub parameters: Vec<DeclaredParameter>,
+    pub body_required: bool,
+    pub body_media_types: Vec<String>,
+    // The schema of the JSON request body, when the operation takes one.
+    pub body_schema: Option<Value>,
+    pub responses: Vec<DeclaredResponse>,
+}
+
+// What happens to requests that do not match their operation: sent as they are, sent
+// with the problems listed in the response's contract report, or not sent at all.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestValidation {
+    Off,
+    #[default]
+    Warn,
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    Status,
+    ContentType,
+    Body,
+}
+
+// `path` points into the body, as a JSON pointer, for schema violations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractViolation {
+    pub kind: ViolationKind,
+    pub path: Option<String>,
+    pub message: String,
+}
+
+// A problem with the request as sent. `location` is "path", "query", "header",
+// "cookie" or "body"; `name` is the parameter's, or a JSON pointer into the body.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RequestIssue {
+    pub location: String,
+    pub name: Option<String>,
+    pub message: String,
+}
+
+// How a response measured up to the operation it came from, e.g. "GET /pets/{id}".
+// `declared` is the response of the spec it was checked against; `request` lists what
+// was wrong with the request itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ContractReport {
+    pub operation: String,
+    pub declared: Option<String>,
+    pub violations: Vec<ContractViolation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub request: Vec<RequestIssue>,
+}
+
+fn range_status(status: &str) -> Option<u16> {
+    match status.as_bytes() {
+        [digit, b'X' | b'x', b'X' | b'x'] if digit.is_ascii_digit() => Some((digit - b'0') as u16),
+        _ => None,
+    }
+}
+
+// The segments of the route against the end of the URL's path, so server prefixes
+// such as "/v1" do not matter. Returns how many segments matched literally.
+fn match_route(route: &str, path: &str) -> Option<usize> {
+    let route: Vec<&str> = route.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let tail = path.get(path.len().checked_sub(route.len())?..)?;
+    let mut literal = 0;
+    for (expected, actual) in route.iter().zip(tail) {
+        if expected.starts_with('{') && expected.ends_with('}') {
+            if actual.is_empty() {
+                return None;
+            }
+        } else if expected == actual {
+            literal += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(literal)
+}
+
+// The operation a request was sent to, preferring the most literal route when
+// templated ones also match.
+pub fn find_operation<'a>(
+    operations: &'a [ContractOperation],
+    method: &str,
+    url: &str,
+) -> Option<&'a ContractOperation> {
+    let url = reqwest::Url::parse(url).ok()?;
+    operations
+        .iter()
+        .filter(|operation| operation.method.eq_ignore_ascii_case(method))
+        .filter_map(|operation| Some((match_route(&operation.route, url.path())?, operation)))
+        .max_by_key(|(literal, operation)| (*literal, operation.route.len()))
+        .map(|(_, operation)| operation)
+}
+
+// An exact match, then a range such as "4XX", then `default`.
+fn declared_for(responses: &[DeclaredResponse], status: u16) -> Option<&DeclaredResponse> {
+    let exact = responses.iter().find(|r| r.status.parse() == Ok(status));
+    exact
+        .or_else(|| {
+            responses
+                .iter()
+                .find(|r| range_status(&r.status) == Some(status / 100))
+        })
+        .or_else(|| responses.iter().find(|r| r.status == "default"))
+}
+
+fn media_type_matches(declared: &str, actual: &str) -> bool {
+    let declared = mime_essence(declared);
+    match declared.split_once('/') {
+        _ if declared == actual || declared == "*/*" => true,
+        Some((kind, "*")) => actual
+            .split_once('/')
+            .is_some_and(|(other, _)| other == kind),
+        _ => false,
+    }
+}
+
+fn is_json(media_type: &str) -> bool {
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+// OpenAPI 3.0 marks optional nulls with `nullable`, which JSON Schema spells as a
+// "null" type. Properties marked `hidden`, "readOnly" in requests and "writeOnly" in
+// responses, are not required.
+fn to_json_schema(schema: &Value, hidden: &str) -> Value {
+    match schema {
+        Value::Object(map) => {
+            let mut converted: serde_json::Map<String, Value> = map
+                .iter()
+                .map(|(key, value)| (key.clone(), to_json_schema(value, hidden)))
+                .collect();
+            if let (Some(Value::Array(required)), Some(Value::Object(properties))) =
+                (map.get("required"), map.get("properties"))
+            {
+                let required: Vec<Value> = required
+                    .iter()
+                    .filter(|name| {
+                        let property = name.as_str().and_then(|name| properties.get(name));
+                        !property.is_some_and(|property| property[hidden] == Value::Bool(true))
+                    })
+                    .cloned()
+                    .collect();
+                converted.insert("required".into(), Value::Array(required));
+            }
+            if converted.remove("nullable") == Some(Value::Bool(true)) {
+                match converted.get_mut("type") {
+                    Some(Value::String(kind)) => {
+                        let kind = kind.clone();
+                        converted.insert("type".into(), serde_json::json!([kind, "null"]));
+                    }
+                    Some(_) => {}
+                    None => {
+                        let schema = Value::Object(converted);
+                        return serde_json::json!({ "anyOf": [schema, { "type": "null" }] });
+                    }
+                }
+            }
+            Value::Object(converted)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| to_json_schema(item, hidden))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+// JSON pointers and messages of what in `body` breaks the schema.
+fn schema_errors(
+    schema: &Value,
+    body: &Value,
+    hidden: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let compiled =
+        JSONSchema::compile(&to_json_schema(schema, hidden)).map_err(|e| e.to_string())?;
+    let errors = match compiled.validate(body) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|error| {
+                let path = error.instance_path.to_string();
+                (
+                    if path.is_empty() { "/".into() } else { path },
+                    error.to_string(),
+                )
+            })
+            .collect(),
+    };
+    Ok(errors)
+}
+
+fn violation(kind: ViolationKind, message: String) -> ContractViolation {
+    ContractViolation {
+        kind,
+        path: None,
+        message,
+    }
+}
+
+pub fn validate(operation: &ContractOperation, response: &ResponseData) -> ContractReport {
+    let mut report = ContractReport {
+        operation: format!("{} {}", operation.method.to_uppercase(), operation.route),
+        declared: None,
+        violations: Vec::new(),
+        request: Vec::new(),
+    };
+    let Some(declared) = declared_for(&operation.responses, response.status) else {
+        let statuses: Vec<&str> = operation
+            .responses
+            .iter()
+            .map(|r| r.status.as_str())
+            .collect();
+        report.violations.push(violation(
+            ViolationKind::Status,
+            format!(
+                "Status {} is not declared; the spec lists {}",
+                response.status,
+                if statuses.is_empty() {
+                    "none".to_string()
+                } else {
+                    statuses.join(", ")
+                }
+            ),
+        ));
+        return report;
+    };
+    report.declared = Some(declared.status.clone());
+
+    let has_body = response.size > 0 || !response.body.is_empty() || response.body_path.is_some();
+    if declared.media_types.is_empty() {
+        if has_body {
+            report.violations.push(violation(
+                ViolationKind::Body,
+                format!(
+                    "Response {} declares no body, but one was returned",
+                    declared.status
+                ),
+            ));
+        }
+        return report;
+    }
+    if !has_body {
+        return report;
+    }
+    let actual = response.content_type.as_deref().map(mime_essence);
+    let Some(actual) = actual.filter(|actual| !actual.is_empty()) else {
+        report.violations.push(violation(
+            ViolationKind::ContentType,
+            format!(
+                "No Content-Type; expected {}",
+                declared.media_types.join(", ")
+            ),
+        ));
+        return report;
+    };
+    if !declared
+        .media_types
+        .iter()
+        .any(|declared| media_type_matches(declared, &actual))
+    {
+        report.violations.push(violation(
+            ViolationKind::ContentType,
+            format!(
+                "Content-Type {} is not one of {}",
+                actual,
+                declared.media_types.join(", ")
+            ),
+        ));
+        return report;
+    }
+
+    // Schemas are kept for the JSON media type, so other bodies are not checked.
+    let Some(schema) = declared.schema.as_ref().filter(|_| is_json(&actual)) else {
+        return report;
+    };
+    if response.body_encoding != BodyEncoding::Text || response.body_path.is_some() {
+        return report;
+    }
+    let body: Value = match serde_json::from_str(&response.body) {
+        Ok(body) => body,
+        Err(e) => {
+            report.violations.push(violation(
+                ViolationKind::Body,
+                format!("Body is not JSON: {}", e),
+            ));
+            return report;
+        }
+    };
+    match schema_errors(schema, &body, "writeOnly") {
+        Ok(errors) => report
+            .violations
+            .extend(errors.into_iter().map(|(path, message)| ContractViolation {
+                kind: ViolationKind::Body,
+                path: Some(path),
+                message,
+            })),
+        Err(e) => report.violations.push(violation(
+            ViolationKind::Body,
+            format!("The response schema cannot be used: {}", e),
+        )),
+    }
+    report
+}
+
+fn issue(location: &str, name: Option<&str>, message: String) -> RequestIssue {
+    RequestIssue {
+        location: location.into(),
+        name: name.map(String::from),
+        message,
+    }
+}
+
+// Parameter values as strings; arrays count once per item.
+fn param_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::Null => Vec::new(),
+        Value::String(text) => vec![text.clone()],
+        Value::Array(items) => items.iter().flat_map(param_strings).collect(),
+        other => vec![other.to_string()],
+    }
+}
+
+// The values sent for a parameter, from the structured parameters and from the URL,
+// query pairs and headers as written.
+fn sent_values(
+    input: &RequestInput,
+    url: Option<&reqwest::Url>,
+    route: &str,
+    parameter: &DeclaredParameter,
+) -> Vec<String> {
+    let mut values: Vec<String> = input
+        .params
+        .iter()
+        .flatten()
+        .filter(|param| param.in_type == parameter.location && param.name == parameter.name)
+        .flat_map(|param| param_strings(&param.value))
+        .collect();
+    let name = parameter.name.as_str();
+    match parameter.location.as_str() {
+        "path" => {
+            // The segment where the route has "{name}", unless it was left as written.
+            let placeholder = format!("{{{}}}", name);
+            let route: Vec<&str> = route.trim_matches('/').split('/').collect();
+            let segments: Vec<String> = url
+                .map(|url| {
+                    url.path()
+                        .trim_matches('/')
+                        .split('/')
+                        .map(|segment| segment.replace("%7B", "{").replace("%7D", "}"))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let (Some(index), Some(offset)) = (
+                route.iter().position(|segment| *segment == placeholder),
+                segments.len().checked_sub(route.len()),
+            ) {
+                let segment = &segments[offset + index];
+                if !segment.is_empty() && *segment != placeholder {
+                    values.push(segment.clone());
+                }
+            }
+        }
+        "query" => {
+            let from_url = url
+                .into_iter()
+                .flat_map(|url| url.query_pairs().into_owned());
+            let pairs = from_url.chain(input.query.iter().flatten().cloned());
+            values.extend(pairs.filter(|(key, _)| key == name).map(|(_, value)| value));
+        }
+        "header" => values.extend(
+            input
+                .headers
+                .iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone()),
+        ),
+        "cookie" => values.extend(
+            input
+                .headers
+                .iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case("cookie"))
+                .flat_map(|(_, value)| value.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .filter(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string()),
+        ),
+        _ => {}
+    }
+    values
+}
+
+// Checks a request, with variables already filled in, before it is sent.
+pub fn check_request(operation: &ContractOperation, input: &RequestInput) -> Vec<RequestIssue> {
+    let mut issues = Vec::new();
+    let url = reqwest::Url::parse(&input.url).ok();
+    for parameter in &operation.parameters {
+        let name = Some(parameter.name.as_str());
+        let values = sent_values(input, url.as_ref(), &operation.route, parameter);
+        if values.is_empty() {
+            if parameter.required || parameter.location == "path" {
+                issues.push(issue(
+                    &parameter.location,
+                    name,
+                    format!(
+                        "Required {} parameter {} is missing",
+                        parameter.location, parameter.name
+                    ),
+                ));
+            }
+            continue;
+        }
+        if parameter.enum_values.is_empty() {
+            continue;
+        }
+        if let Some(illegal) = values
+            .iter()
+            .find(|value| !parameter.enum_values.contains(value))
+        {
+            issues.push(issue(
+                &parameter.location,
+                name,
+                format!(
+                    "{} must be one of {}, not {}",
+                    parameter.name,
+                    parameter.enum_values.join(", "),
+                    illegal
+                ),
+            ));
+        }
+    }
+
+    let text = input.body.as_deref().filter(|body| !body.trim().is_empty());
+    let has_body = text.is_some()
+        || input.form.is_some()
+        || input.multipart.is_some()
+        || input.body_file.is_some()
+        || input.graphql.is_some()
+        || input.jsonrpc.is_some();
+    if !has_body {
+        if operation.body_required {
+            issues.push(issue(
+                "body",
+                None,
+                "The operation requires a request body".into(),
+            ));
+        }
+        return issues;
+    }
+    if operation.body_media_types.is_empty() {
+        return issues;
+    }
+    let content_type = input
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| mime_essence(value));
+    if let Some(content_type) = &content_type {
+        if !operation
+            .body_media_types
+            .iter()
+            .any(|declared| media_type_matches(declared, content_type))
+        {
+            issues.push(issue(
+                "body",
+                None,
+                format!(
+                    "Content-Type {} is not one of {}",
+                    content_type,
+                    operation.body_media_types.join(", ")
+                ),
+            ));
+            return issues;
+        }
+    }
+    let (Some(schema), Some(text)) = (&operation.body_schema, text) else {
+        return issues;
+    };
+    if content_type
+        .as_deref()
+        .is_some_and(|content_type| !is_json(content_type))
+    {
+        return issues;
+    }
+    let body: Value = match serde_json::from_str(text) {
+        Ok(body) => body,
+        Err(e) => {
+            issues.push(issue("body", None, format!("Body is not JSON: {}", e)));
+            return issues;
+        }
+    };
+    match schema_errors(schema, &body, "readOnly") {
+        Ok(errors) => issues.extend(errors.into_iter().map(|(path, message)| RequestIssue {
+            location: "body".into(),
+            name: Some(path),
+            message,
+        })),
+        Err(e) => issues.push(issue(
+            "body",
+            None,
+            format!("The request schema cannot be used: {}", e),
+        )),
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::ParameterValue;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn response(status: u16, content_type: Option<&str>, body: &str) -> ResponseData {
+        ResponseData {
+            status,
+            http_version: "HTTP/1.1".into(),
+            body: body.into(),
+            content_type: content_type.map(String::from),
+            size: body.len() as u64,
+            encoded_size: body.len() as u64,
+            ..ResponseData::default()
+        }
+    }
+
+    #[test]
+    fn checks_status_content_type_and_schema() {
+        let operations = vec![
+            ContractOperation {
+                method: "get".into(),
+                route: "/pets/{id}".into(),
+                responses: vec![
+                    DeclaredResponse {
+                        status: "200".into(),
+                        media_types: vec!["application/json".into()],
+                        schema: Some(json!({
+                            "type": "object",
+                            "required": ["id", "name"],
+                            "properties": {
+                                "id": { "type": "integer" },
+                                "name": { "type": "string" },
+                                "tag": { "type": "string", "nullable": true }
+                            }
+                        })),
+                    },
+                    DeclaredResponse {
+                        status: "4XX".into(),
+                        media_types: vec!["application/problem+json".into()],
+                        schema: None,
+                    },
+                ],
+                ..ContractOperation::default()
+            },
+            ContractOperation {
+                method: "GET".into(),
+                route: "/pets/mine".into(),
+                ..ContractOperation::default()
+            },
+        ];
+        let operation =
+            find_operation(&operations, "GET", "https://api.test/v1/pets/7?full=1").unwrap();
+        assert_eq!(operation.route, "/pets/{id}");
+        assert_eq!(
+            find_operation(&operations, "GET", "https://api.test/v1/pets/mine")
+                .unwrap()
+                .route,
+            "/pets/mine"
+        );
+        assert!(find_operation(&operations, "POST", "https://api.test/pets/7").is_none());
+
+        let ok = validate(
+            operation,
+            &response(
+                200,
+                Some("application/json"),
+                r#"{"id":7,"name":"Rex","tag":null}"#,
+            ),
+        );
+        assert_eq!(ok.operation, "GET /pets/{id}");
+        assert_eq!(ok.declared.as_deref(), Some("200"));
+        assert!(ok.violations.is_empty());
+
+        let bad = validate(
+            operation,
+            &response(200, Some("application/json"), r#"{"id":"7"}"#),
+        );
+        let mut paths: Vec<_> = bad
+            .violations
+            .iter()
+            .map(|v| v.path.clone().unwrap())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, vec!["/", "/id"]);
+
+        let wrong_type = validate(
+            operation,
+            &response(404, Some("text/html"), "<h1>Not found</h1>"),
+        );
+        assert_eq!(wrong_type.declared.as_deref(), Some("4XX"));
+        assert_eq!(wrong_type.violations[0].kind, ViolationKind::ContentType);
+
+        let undeclared = validate(operation, &response(500, None, ""));
+        assert_eq!(undeclared.violations[0].kind, ViolationKind::Status);
+        assert_eq!(
+            undeclared.violations[0].message,
+            "Status 500 is not declared; the spec lists 200, 4XX"
+        );
+    }
+
+    #[test]
+    fn flags_missing_parameters_illegal_values_and_bad_bodies() {
+        let parameter =
+            |name: &str, location: &str, required: bool, enum_values: &[&str]| DeclaredParameter {
+                name: name.into(),
+                location: location.into(),
+                required,
+                enum_values: enum_values.iter().map(|value| value.to_string()).collect(),
+            };
+        let operation = ContractOperation {
+            method: "PUT".into(),
+            route: "/stores/{store}/pets/{id}".into(),
+            parameters: vec![
+                parameter("store", "path", true, &[]),
+                parameter("id", "path", true, &[]),
+                parameter("status", "query", false, &["available", "sold"]),
+                parameter("X-Request-Id", "header", true, &[]),
+            ],
+            body_required: true,
+            body_media_types: vec!["application/json".into()],
+            body_schema: Some(json!({
+                "type": "object",
+                "required": ["id", "name"],
+                "properties": {
+                    "id": { "type": "integer", "readOnly": true },
+                    "name": { "type": "string" }
+                }
+            })),
+            ..ContractOperation::default()
+        };
+        let mut input = RequestInput {
+            method: "PUT".into(),
+            url: "https://api.test/v2/stores/main/pets/{id}?status=lost".into(),
+            headers: HashMap::from([("x-request-id".to_string(), "r1".to_string())]),
+            body: Some(r#"{"name":"Rex"}"#.into()),
+            ..RequestInput::default()
+        };
+        let issues = check_request(&operation, &input);
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.location.as_str(), issue.name.as_deref().unwrap_or("")))
+                .collect::<Vec<_>>(),
+            vec![("path", "id"), ("query", "status")]
+        );
+        assert_eq!(
+            issues[1].message,
+            "status must be one of available, sold, not lost"
+        );
+
+        input.url = "https://api.test/v2/stores/main/pets/7".into();
+        input.params = Some(vec![ParameterValue {
+            name: "status".into(),
+            in_type: "query".into(),
+            value: json!(["sold"]),
+            style: None,
+            explode: None,
+        }]);
+        assert!(check_request(&operation, &input).is_empty());
+
+        input.body = Some(r#"{"name":7}"#.into());
+        let issues = check_request(&operation, &input);
+        assert_eq!(
+            (issues[0].location.as_str(), issues[0].name.as_deref()),
+            ("body", Some("/name"))
+        );
+        input.body = None;
+        assert_eq!(
+            check_request(&operation, &input)[0].message,
+            "The operation requires a request body"
+        );
+    }
+}
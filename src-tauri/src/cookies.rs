@@ -0,0 +1,408 @@
+use crate::checksum::{ChecksumAlgorithm, Hasher};
+use chrono::{DateTime, TimeZone, Utc};
+use cookie_store::{CookieExpiration, CookieStore, RawCookie};
+use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+// Where cookies were kept before they moved into the store; read once on upgrade.
+pub const COOKIES_FILE: &str = "cookies.json";
+pub const COOKIE_JARS_DIR: &str = "cookies";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CookieInfo {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    pub host_only: bool,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+// A `Set-Cookie` header of a response as its parts, and whether the jar stored it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SetCookie {
+    pub raw: String,
+    // The response that set it: the final one, or a redirect or challenge before it.
+    #[serde(default)]
+    pub url: String,
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub expires: Option<DateTime<Utc>>,
+    pub max_age: Option<i64>,
+    pub same_site: Option<String>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub accepted: bool,
+    // Why the header was malformed or the jar refused it.
+    pub rejected: Option<String>,
+}
+
+tokio::task_local! {
+    // What the jar did with each Set-Cookie header while `record_cookies` runs.
+    static STORED: RefCell<Vec<SetCookie>>;
+}
+
+// Runs `request` and returns what the jar stored from every response it went through,
+// redirects included. The jar does the storing inside reqwest, so this is the only place
+// that sees the hops.
+pub async fn record_cookies<F: Future>(request: F) -> (F::Output, Vec<SetCookie>) {
+    STORED
+        .scope(RefCell::new(Vec::new()), async {
+            let output = request.await;
+            (output, STORED.with(|stored| stored.take()))
+        })
+        .await
+}
+
+// The header's parts, with the parsed cookie unless it was malformed.
+fn parse_set_cookie(value: &HeaderValue, url: &Url) -> (SetCookie, Option<RawCookie<'static>>) {
+    let raw = String::from_utf8_lossy(value.as_bytes()).into_owned();
+    let mut set_cookie = SetCookie {
+        raw: raw.clone(),
+        url: url.to_string(),
+        name: String::new(),
+        value: String::new(),
+        domain: None,
+        path: None,
+        expires: None,
+        max_age: None,
+        same_site: None,
+        secure: false,
+        http_only: false,
+        accepted: false,
+        rejected: None,
+    };
+    let cookie = match RawCookie::parse(raw) {
+        Ok(cookie) => cookie,
+        Err(e) => {
+            set_cookie.rejected = Some(e.to_string());
+            return (set_cookie, None);
+        }
+    };
+    set_cookie.name = cookie.name().to_string();
+    set_cookie.value = cookie.value().to_string();
+    set_cookie.domain = cookie.domain().map(String::from);
+    set_cookie.path = cookie.path().map(String::from);
+    set_cookie.expires = cookie
+        .expires_datetime()
+        .and_then(|at| Utc.timestamp_opt(at.unix_timestamp(), 0).single());
+    set_cookie.max_age = cookie.max_age().map(|age| age.whole_seconds());
+    set_cookie.same_site = cookie.same_site().map(|s| s.to_string());
+    set_cookie.secure = cookie.secure().unwrap_or(false);
+    set_cookie.http_only = cookie.http_only().unwrap_or(false);
+    (set_cookie, Some(cookie))
+}
+
+// The Set-Cookie headers of a response from a client that keeps no cookies.
+pub fn unstored_cookies(headers: &HeaderMap, url: &Url) -> Vec<SetCookie> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .map(|value| {
+            let (mut set_cookie, cookie) = parse_set_cookie(value, url);
+            if cookie.is_some() {
+                set_cookie.rejected = Some("The client keeps no cookies".into());
+            }
+            set_cookie
+        })
+        .collect()
+}
+
+// A reqwest cookie provider whose contents can be inspected and edited, unlike
+// `reqwest::cookie::Jar`.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    store: RwLock<CookieStore>,
+    // Set whenever the contents change, so only changed jars are written back.
+    changed: AtomicBool,
+}
+
+// Named jars are keyed by arbitrary ids such as collection URLs, so they are stored
+// under a digest of the id, the same name their files used to have. The default jar
+// has the empty key.
+pub fn jar_key(jar: Option<&str>) -> String {
+    match jar {
+        None => String::new(),
+        Some(id) => {
+            let mut hasher = Hasher::new(ChecksumAlgorithm::Sha1);
+            hasher.update(id.as_bytes());
+            hasher.finish()
+        }
+    }
+}
+
+// Lists cookies that belong to `filter` itself, its parent domains or its subdomains.
+fn domain_related(cookie_domain: &str, filter: &str) -> bool {
+    let filter = filter.trim().trim_start_matches('.').to_ascii_lowercase();
+    cookie_domain == filter
+        || filter.ends_with(&format!(".{}", cookie_domain))
+        || cookie_domain.ends_with(&format!(".{}", filter))
+}
+
+impl CookieJar {
+    pub fn list(&self, domain: Option<&str>) -> Vec<CookieInfo> {
+        let store = self.store.read().unwrap();
+        let mut cookies: Vec<CookieInfo> = store
+            .iter_unexpired()
+            .filter_map(|cookie| {
+                let cookie_domain = cookie.domain.as_cow()?.into_owned();
+                if let Some(filter) = domain {
+                    if !domain_related(&cookie_domain, filter) {
+                        return None;
+                    }
+                }
+                let expires = match cookie.expires {
+                    CookieExpiration::AtUtc(at) => {
+                        Utc.timestamp_opt(at.unix_timestamp(), 0).single()
+                    }
+                    CookieExpiration::SessionEnd => None,
+                };
+                Some(CookieInfo {
+                    name: cookie.name().to_string(),
+                    value: cookie.value().to_string(),
+                    domain: cookie_domain,
+                    path: String::from(&cookie.path),
+                    host_only: matches!(cookie.domain, cookie_store::CookieDomain::HostOnly(_)),
+                    secure: cookie.secure().unwrap_or(false),
+                    http_only: cookie.http_only().unwrap_or(false),
+                    same_site: cookie.same_site().map(|s| s.to_string()),
+                    expires,
+                })
+            })
+            .collect();
+        cookies.sort_by(|a, b| (&a.domain, &a.path, &a.name).cmp(&(&b.domain, &b.path, &b.name)));
+        cookies
+    }
+
+    // `cookie` uses Set-Cookie syntax; `url` decides the default domain and path.
+    pub fn set(&self, url: &str, cookie: &str) -> Result<(), String> {
+        let url = Url::parse(url).map_err(|e| e.to_string())?;
+        self.store
+            .write()
+            .unwrap()
+            .parse(cookie, &url)
+            .map_err(|e| e.to_string())?;
+        self.mark_changed();
+        Ok(())
+    }
+
+    pub fn delete(&self, domain: &str, path: &str, name: &str) -> bool {
+        let deleted = self
+            .store
+            .write()
+            .unwrap()
+            .remove(domain, path, name)
+            .is_some();
+        if deleted {
+            self.mark_changed();
+        }
+        deleted
+    }
+
+    pub fn clear(&self, domain: Option<&str>) {
+        let mut store = self.store.write().unwrap();
+        self.mark_changed();
+        let filter = match domain {
+            Some(filter) => filter,
+            None => return store.clear(),
+        };
+        let doomed: Vec<(String, String, String)> = store
+            .iter_any()
+            .filter_map(|cookie| {
+                let cookie_domain = cookie.domain.as_cow()?.into_owned();
+                domain_related(&cookie_domain, filter).then(|| {
+                    (
+                        cookie_domain,
+                        String::from(&cookie.path),
+                        cookie.name().to_string(),
+                    )
+                })
+            })
+            .collect();
+        for (domain, path, name) in doomed {
+            store.remove(&domain, &path, &name);
+        }
+    }
+
+    // The `Cookie` header the jar would send to `url`.
+    pub fn request_header(&self, url: &str) -> Option<String> {
+        let url = Url::parse(url).ok()?;
+        let header = reqwest::cookie::CookieStore::cookies(self, &url)?;
+        header.to_str().ok().map(|value| value.to_string())
+    }
+
+    // Only persistent, unexpired cookies are written; session cookies end with the app.
+    pub fn to_json(&self) -> Result<String, String> {
+        let mut content = Vec::new();
+        self.store
+            .read()
+            .unwrap()
+            .save_json(&mut content)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(content).map_err(|e| e.to_string())
+    }
+
+    pub fn mark_changed(&self) {
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    // Whether the jar changed since the last call.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    // Corrupt cookie data leaves the jar untouched.
+    pub fn load_json(&self, content: &str) {
+        if let Ok(store) = CookieStore::load_json(content.as_bytes()) {
+            *self.store.write().unwrap() = store;
+        }
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut store = self.store.write().unwrap();
+        for value in cookie_headers {
+            let (mut set_cookie, cookie) = parse_set_cookie(value, url);
+            if let Some(cookie) = cookie {
+                match store.insert_raw(&cookie, url) {
+                    Ok(_) => {
+                        set_cookie.accepted = true;
+                        self.mark_changed();
+                    }
+                    Err(e) => set_cookie.rejected = Some(e.to_string()),
+                }
+            }
+            let _ = STORED.try_with(|stored| stored.borrow_mut().push(set_cookie));
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore as _;
+
+    #[test]
+    fn set_list_delete_and_clear_by_domain() {
+        let jar = CookieJar::default();
+        jar.set(
+            "https://api.example.com/v1/login",
+            "session=abc; Path=/; HttpOnly",
+        )
+        .unwrap();
+        jar.set(
+            "https://example.com/",
+            "theme=dark; Domain=example.com; Max-Age=3600",
+        )
+        .unwrap();
+        jar.set("https://other.test/", "id=1").unwrap();
+
+        let listed = jar.list(Some("api.example.com"));
+        assert_eq!(listed.len(), 2);
+        let session = listed.iter().find(|c| c.name == "session").unwrap();
+        assert!(session.host_only && session.http_only && session.expires.is_none());
+        assert!(listed
+            .iter()
+            .any(|c| c.name == "theme" && c.expires.is_some()));
+
+        let url = Url::parse("https://api.example.com/v1/items").unwrap();
+        let header = jar.cookies(&url).unwrap();
+        assert!(header.to_str().unwrap().contains("session=abc"));
+
+        assert!(jar.delete("api.example.com", "/", "session"));
+        assert!(!jar.delete("api.example.com", "/", "session"));
+        jar.clear(Some("example.com"));
+        assert_eq!(jar.list(None).len(), 1);
+        jar.clear(None);
+        assert!(jar.list(None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn records_what_the_jar_stored() {
+        let jar = CookieJar::default();
+        let url = Url::parse("https://api.example.com/login").unwrap();
+        let values = [
+            "session=abc; Path=/; Secure; HttpOnly; SameSite=Lax",
+            "theme=dark; Domain=example.com; Max-Age=60",
+            "stolen=1; Domain=other.test",
+        ]
+        .map(HeaderValue::from_static);
+        let ((), cookies) = record_cookies(async {
+            jar.set_cookies(&mut values.iter(), &url);
+        })
+        .await;
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies[0].url, url.to_string());
+        assert_eq!(
+            (cookies[0].name.as_str(), cookies[0].value.as_str()),
+            ("session", "abc")
+        );
+        assert!(cookies[0].secure && cookies[0].http_only && cookies[0].accepted);
+        assert_eq!(cookies[0].same_site.as_deref(), Some("Lax"));
+        assert_eq!(cookies[1].max_age, Some(60));
+        assert_eq!(cookies[1].domain.as_deref(), Some("example.com"));
+        assert!(!cookies[2].accepted && cookies[2].rejected.is_some());
+        assert_eq!(jar.list(None).len(), 2);
+
+        let mut headers = HeaderMap::new();
+        headers.append(SET_COOKIE, values[0].clone());
+        let unstored = unstored_cookies(&headers, &url);
+        assert!(!unstored[0].accepted && unstored[0].rejected.is_some());
+    }
+
+    #[test]
+    fn only_stored_cookies_mark_the_jar_changed() {
+        let jar = CookieJar::default();
+        let url = Url::parse("https://api.example.com/").unwrap();
+        let rejected = HeaderValue::from_static("stolen=1; Domain=other.test");
+        jar.set_cookies(&mut std::iter::once(&rejected), &url);
+        assert!(!jar.take_changed());
+        let accepted = HeaderValue::from_static("session=abc");
+        jar.set_cookies(&mut std::iter::once(&accepted), &url);
+        assert!(jar.take_changed());
+        assert!(!jar.take_changed());
+        assert!(!jar.delete("api.example.com", "/", "missing"));
+        assert!(!jar.take_changed());
+    }
+
+    #[test]
+    fn persists_only_persistent_cookies() {
+        let jar = CookieJar::default();
+        jar.set("https://example.com/", "keep=1; Max-Age=3600")
+            .unwrap();
+        jar.set("https://example.com/", "drop=1").unwrap();
+        let content = jar.to_json().unwrap();
+
+        let restored = CookieJar::default();
+        restored.load_json(&content);
+        let names: Vec<String> = restored.list(None).into_iter().map(|c| c.name).collect();
+        assert_eq!(names, vec!["keep".to_string()]);
+        assert_eq!(jar_key(Some("https://specs/staging.json")).len(), 40);
+    }
+}
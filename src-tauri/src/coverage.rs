@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// How often one operation of a collection answered with one status, e.g.
+// "GET /pets/{id}" and 404.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CoverageHit {
+    pub operation: String,
+    pub status: u16,
+    pub hits: u64,
+    pub last_seen: DateTime<Utc>,
+}
+
+// An operation as the spec declares it. `statuses` are its response keys: "200",
+// "4XX" or "default".
+#[derive(Clone, Debug)]
+pub struct CoverageOperation {
+    pub operation: String,
+    pub summary: Option<String>,
+    pub tag: String,
+    pub statuses: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StatusCount {
+    pub status: u16,
+    pub hits: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OperationCoverage {
+    pub operation: String,
+    pub summary: Option<String>,
+    pub tag: String,
+    pub hits: u64,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub statuses: Vec<StatusCount>,
+    // Declared responses no request has received yet.
+    pub untested_statuses: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CoverageReport {
+    pub collection: String,
+    pub operations: Vec<OperationCoverage>,
+    pub covered: usize,
+    pub total: usize,
+    pub percent: f64,
+    // Operations never sent, in spec order.
+    pub untested: Vec<String>,
+}
+
+fn covers(declared: &str, status: u16) -> bool {
+    match declared.as_bytes() {
+        [digit, b'X' | b'x', b'X' | b'x'] => status / 100 == (digit - b'0') as u16,
+        _ => declared.parse() == Ok(status),
+    }
+}
+
+// `default` counts as covered by any status no other response of the operation declares.
+fn untested_statuses(declared: &[String], seen: &[StatusCount]) -> Vec<String> {
+    let explicit: Vec<&String> = declared
+        .iter()
+        .filter(|status| *status != "default")
+        .collect();
+    declared
+        .iter()
+        .filter(|status| {
+            !seen.iter().any(|count| match status.as_str() {
+                "default" => !explicit.iter().any(|other| covers(other, count.status)),
+                status => covers(status, count.status),
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+pub fn report(
+    collection: &str,
+    operations: &[CoverageOperation],
+    hits: &[CoverageHit],
+) -> CoverageReport {
+    let operations: Vec<OperationCoverage> = operations
+        .iter()
+        .map(|operation| {
+            let matching: Vec<&CoverageHit> = hits
+                .iter()
+                .filter(|hit| hit.operation == operation.operation)
+                .collect();
+            let mut statuses: Vec<StatusCount> = matching
+                .iter()
+                .map(|hit| StatusCount {
+                    status: hit.status,
+                    hits: hit.hits,
+                })
+                .collect();
+            statuses.sort_by_key(|count| count.status);
+            OperationCoverage {
+                operation: operation.operation.clone(),
+                summary: operation.summary.clone(),
+                tag: operation.tag.clone(),
+                hits: matching.iter().map(|hit| hit.hits).sum(),
+                last_seen: matching.iter().map(|hit| hit.last_seen).max(),
+                untested_statuses: untested_statuses(&operation.statuses, &statuses),
+                statuses,
+            }
+        })
+        .collect();
+    let total = operations.len();
+    let untested: Vec<String> = operations
+        .iter()
+        .filter(|operation| operation.hits == 0)
+        .map(|operation| operation.operation.clone())
+        .collect();
+    let covered = total - untested.len();
+    CoverageReport {
+        collection: collection.to_string(),
+        operations,
+        covered,
+        total,
+        percent: if total == 0 {
+            0.0
+        } else {
+            covered as f64 * 100.0 / total as f64
+        },
+        untested,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_untested_operations_and_statuses() {
+        let operation = |name: &str, statuses: &[&str]| CoverageOperation {
+            operation: name.into(),
+            summary: None,
+            tag: "pets".into(),
+            statuses: statuses.iter().map(|status| status.to_string()).collect(),
+        };
+        let operations = vec![
+            operation("GET /pets", &["200", "default"]),
+            operation("GET /pets/{id}", &["200", "4XX", "default"]),
+            operation("DELETE /pets/{id}", &["204"]),
+        ];
+        let hit = |operation: &str, status: u16, hits: u64| CoverageHit {
+            operation: operation.into(),
+            status,
+            hits,
+            last_seen: Utc::now(),
+        };
+        let hits = vec![
+            hit("GET /pets/{id}", 404, 2),
+            hit("GET /pets/{id}", 200, 5),
+            hit("GET /pets", 500, 1),
+            hit("POST /gone", 200, 1),
+        ];
+        let report = report("https://api.test/openapi.json", &operations, &hits);
+        assert_eq!((report.covered, report.total), (2, 3));
+        assert_eq!(report.untested, vec!["DELETE /pets/{id}"]);
+        let pet = &report.operations[1];
+        assert_eq!(pet.hits, 7);
+        assert_eq!(
+            pet.statuses[0],
+            StatusCount {
+                status: 200,
+                hits: 5
+            }
+        );
+        assert_eq!(pet.untested_statuses, vec!["default"]);
+        assert_eq!(report.operations[0].untested_statuses, vec!["200"]);
+    }
+}
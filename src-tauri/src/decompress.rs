@@ -0,0 +1,112 @@
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentCoding {
+    Identity,
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+// Stacked codings such as "gzip, br" are rare enough that they are passed through raw.
+pub fn parse_content_coding(value: Option<&str>) -> Option<ContentCoding> {
+    let value = match value {
+        Some(value) => value.trim().to_ascii_lowercase(),
+        None => return Some(ContentCoding::Identity),
+    };
+    match value.as_str() {
+        "" | "identity" => Some(ContentCoding::Identity),
+        "gzip" | "x-gzip" => Some(ContentCoding::Gzip),
+        "deflate" => Some(ContentCoding::Deflate),
+        "br" => Some(ContentCoding::Brotli),
+        "zstd" => Some(ContentCoding::Zstd),
+        _ => None,
+    }
+}
+
+// Counts the bytes read off the wire into `encoded` and, when `coding` is given,
+// decodes the stream on the fly.
+pub fn decode_stream<S, E>(
+    stream: S,
+    coding: Option<ContentCoding>,
+    encoded: Arc<AtomicU64>,
+) -> ByteStream
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let counted = stream
+        .map_err(|e| std::io::Error::other(e))
+        .inspect_ok(move |chunk| {
+            encoded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        });
+    let reader = StreamReader::new(counted);
+    match coding {
+        None | Some(ContentCoding::Identity) => Box::pin(ReaderStream::new(reader)),
+        Some(ContentCoding::Gzip) => Box::pin(ReaderStream::new(GzipDecoder::new(reader))),
+        // HTTP "deflate" is zlib-wrapped despite the name.
+        Some(ContentCoding::Deflate) => Box::pin(ReaderStream::new(ZlibDecoder::new(reader))),
+        Some(ContentCoding::Brotli) => Box::pin(ReaderStream::new(BrotliDecoder::new(reader))),
+        Some(ContentCoding::Zstd) => Box::pin(ReaderStream::new(ZstdDecoder::new(reader))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::GzipEncoder;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn parses_content_codings() {
+        assert_eq!(parse_content_coding(None), Some(ContentCoding::Identity));
+        assert_eq!(
+            parse_content_coding(Some("GZIP")),
+            Some(ContentCoding::Gzip)
+        );
+        assert_eq!(
+            parse_content_coding(Some("br")),
+            Some(ContentCoding::Brotli)
+        );
+        assert_eq!(parse_content_coding(Some("gzip, br")), None);
+    }
+
+    #[tokio::test]
+    async fn decodes_gzip_and_counts_encoded_bytes() {
+        let original = "hello ".repeat(1000);
+        let mut compressed = Vec::new();
+        GzipEncoder::new(original.as_bytes())
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+        let chunks: Vec<Result<Bytes, std::io::Error>> = compressed
+            .chunks(100)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let encoded = Arc::new(AtomicU64::new(0));
+        let decoded: Vec<u8> = decode_stream(
+            futures_util::stream::iter(chunks),
+            Some(ContentCoding::Gzip),
+            encoded.clone(),
+        )
+        .try_fold(Vec::new(), |mut acc, chunk| async move {
+            acc.extend_from_slice(&chunk);
+            Ok(acc)
+        })
+        .await
+        .unwrap();
+        assert_eq!(decoded, original.as_bytes());
+        assert_eq!(encoded.load(Ordering::Relaxed), compressed.len() as u64);
+    }
+}
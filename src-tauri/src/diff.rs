@@ -0,0 +1,356 @@
+use crate::body::BodyEncoding;
+use crate::http::{RequestInput, ResponseData};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+// Headers that differ on every response and are never compared.
+const VOLATILE_HEADERS: &[&str] = &[
+    "age",
+    "cf-ray",
+    "date",
+    "expires",
+    "server-timing",
+    "set-cookie",
+    "x-correlation-id",
+    "x-request-id",
+    "x-runtime",
+];
+// Larger text bodies are compared whole instead of line by line.
+const MAX_LINE_PAIRS: usize = 4_000_000;
+
+// One side of a comparison: a response stored in history, or a request sent now,
+// optionally against another environment than its own.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum DiffSide {
+    History {
+        id: i64,
+    },
+    Request {
+        request: Box<RequestInput>,
+        #[serde(default)]
+        environment: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+// `path` is `status`, a lowercased header name, a JSONPath into the body such as
+// `$.items[0].name`, or `line <n>` of a text body (of the left side when removed, of
+// the right side when added).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyKind {
+    Json,
+    Text,
+    // Binary, saved to disk or dropped from history; only the sizes are compared.
+    Opaque,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResponseDiff {
+    pub identical: bool,
+    pub status: Option<Change>,
+    pub headers: Vec<Change>,
+    pub body_kind: BodyKind,
+    pub body: Vec<Change>,
+}
+
+// Fields left out of the comparison. A bare name such as `updatedAt` matches that key
+// anywhere in the body, and that header; a path such as `$.items[*].id` matches where
+// `*` stands for any one key or index.
+struct Ignored {
+    keys: Vec<String>,
+    paths: Vec<Regex>,
+}
+
+impl Ignored {
+    fn new(patterns: &[String]) -> Result<Self, String> {
+        let mut ignored = Ignored {
+            keys: Vec::new(),
+            paths: Vec::new(),
+        };
+        for pattern in patterns.iter().map(|pattern| pattern.trim()) {
+            if pattern.starts_with('$') {
+                let regex = regex::escape(pattern)
+                    .replace(r"\[\*\]", r"\[\d+\]")
+                    .replace(r"\*", r"[^.\[]+");
+                ignored.paths.push(
+                    Regex::new(&format!("^{}$", regex))
+                        .map_err(|e| format!("Invalid ignore pattern {}: {}", pattern, e))?,
+                );
+            } else if !pattern.is_empty() {
+                ignored.keys.push(pattern.to_string());
+            }
+        }
+        Ok(ignored)
+    }
+
+    fn body(&self, path: &str, key: Option<&str>) -> bool {
+        key.is_some_and(|key| self.keys.iter().any(|ignored| ignored == key))
+            || self.paths.iter().any(|regex| regex.is_match(path))
+    }
+
+    fn header(&self, name: &str) -> bool {
+        VOLATILE_HEADERS.contains(&name)
+            || self.keys.iter().any(|key| key.eq_ignore_ascii_case(name))
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    let plain = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}['{}']", path, key.replace('\'', "\\'"))
+    }
+}
+
+fn change(path: String, left: Option<&Value>, right: Option<&Value>) -> Change {
+    let kind = match (left, right) {
+        (None, _) => ChangeKind::Added,
+        (_, None) => ChangeKind::Removed,
+        _ => ChangeKind::Changed,
+    };
+    Change {
+        path,
+        kind,
+        left: left.cloned(),
+        right: right.cloned(),
+    }
+}
+
+// Objects are compared key by key and arrays index by index.
+fn diff_json(
+    left: Option<&Value>,
+    right: Option<&Value>,
+    path: String,
+    key: Option<&str>,
+    ignored: &Ignored,
+    changes: &mut Vec<Change>,
+) {
+    if ignored.body(&path, key) {
+        return;
+    }
+    match (left, right) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let keys: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let path = child_path(&path, key);
+                diff_json(a.get(key), b.get(key), path, Some(key), ignored, changes);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for index in 0..a.len().max(b.len()) {
+                let path = format!("{}[{}]", path, index);
+                diff_json(a.get(index), b.get(index), path, None, ignored, changes);
+            }
+        }
+        (left, right) if left != right => changes.push(change(path, left, right)),
+        _ => {}
+    }
+}
+
+// Longest-common-subsequence line diff.
+fn diff_lines(left: &str, right: &str, changes: &mut Vec<Change>) {
+    let a: Vec<&str> = left.lines().collect();
+    let b: Vec<&str> = right.lines().collect();
+    if a.len() * b.len() > MAX_LINE_PAIRS {
+        if left != right {
+            changes.push(change(
+                "body".into(),
+                Some(&left.into()),
+                Some(&right.into()),
+            ));
+        }
+        return;
+    }
+    let mut common = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            let line = Value::from(a[i]);
+            changes.push(change(format!("line {}", i + 1), Some(&line), None));
+            i += 1;
+        } else {
+            let line = Value::from(b[j]);
+            changes.push(change(format!("line {}", j + 1), None, Some(&line)));
+            j += 1;
+        }
+    }
+}
+
+fn readable(response: &ResponseData) -> bool {
+    response.body_encoding == BodyEncoding::Text
+        && response.body_path.is_none()
+        && (response.size == 0 || !response.body.is_empty())
+}
+
+pub fn diff(
+    left: &ResponseData,
+    right: &ResponseData,
+    ignore: &[String],
+) -> Result<ResponseDiff, String> {
+    let ignored = Ignored::new(ignore)?;
+    let status = (left.status != right.status).then(|| {
+        change(
+            "status".into(),
+            Some(&left.status.into()),
+            Some(&right.status.into()),
+        )
+    });
+
+    let header = |response: &ResponseData, name: &str| {
+        response
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, values)| Value::from(values.join(", ")))
+    };
+    let names: BTreeSet<String> = left
+        .headers
+        .keys()
+        .chain(right.headers.keys())
+        .map(|name| name.to_lowercase())
+        .filter(|name| !ignored.header(name))
+        .collect();
+    let headers: Vec<Change> = names
+        .into_iter()
+        .filter_map(|name| {
+            let (a, b) = (header(left, &name), header(right, &name));
+            (a != b).then(|| change(name, a.as_ref(), b.as_ref()))
+        })
+        .collect();
+
+    let mut body = Vec::new();
+    let body_kind = if !readable(left) || !readable(right) {
+        if left.size != right.size || left.body != right.body {
+            let size = |response: &ResponseData| Value::from(format!("{} bytes", response.size));
+            body.push(change("body".into(), Some(&size(left)), Some(&size(right))));
+        }
+        BodyKind::Opaque
+    } else {
+        match (
+            serde_json::from_str::<Value>(&left.body),
+            serde_json::from_str::<Value>(&right.body),
+        ) {
+            (Ok(a), Ok(b)) => {
+                diff_json(Some(&a), Some(&b), "$".into(), None, &ignored, &mut body);
+                BodyKind::Json
+            }
+            _ => {
+                diff_lines(&left.body, &right.body, &mut body);
+                BodyKind::Text
+            }
+        }
+    };
+
+    Ok(ResponseDiff {
+        identical: status.is_none() && headers.is_empty() && body.is_empty(),
+        status,
+        headers,
+        body_kind,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn response(status: u16, date: &str, body: &str) -> ResponseData {
+        ResponseData {
+            status,
+            http_version: "HTTP/1.1".into(),
+            headers: HashMap::from([
+                ("Date".into(), vec![date.into()]),
+                ("Content-Type".into(), vec!["application/json".into()]),
+            ]),
+            body: body.into(),
+            size: body.len() as u64,
+            encoded_size: body.len() as u64,
+            ..ResponseData::default()
+        }
+    }
+
+    #[test]
+    fn diffs_json_structurally_and_skips_ignored_fields() {
+        let left = response(
+            200,
+            "Mon",
+            r#"{"id": 1, "updatedAt": "t1", "items": [{"id": "a", "n": 1}], "old": true}"#,
+        );
+        let right = response(
+            201,
+            "Tue",
+            r#"{"id": 1, "updatedAt": "t2", "items": [{"id": "b", "n": 2}, {"id": "c"}], "new key": 0}"#,
+        );
+        let ignore = vec!["updatedAt".to_string(), "$.items[*].id".to_string()];
+        let changes = diff(&left, &right, &ignore).unwrap();
+        assert!(!changes.identical);
+        assert_eq!(changes.status.unwrap().right, Some(json!(201)));
+        assert!(changes.headers.is_empty());
+        assert_eq!(changes.body_kind, BodyKind::Json);
+        let paths: Vec<(&str, ChangeKind)> = changes
+            .body
+            .iter()
+            .map(|change| (change.path.as_str(), change.kind))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                ("$.items[0].n", ChangeKind::Changed),
+                ("$.items[1]", ChangeKind::Added),
+                ("$['new key']", ChangeKind::Added),
+                ("$.old", ChangeKind::Removed),
+            ]
+        );
+
+        let text = diff(
+            &response(200, "Mon", "a\nb\nc"),
+            &response(200, "Mon", "a\nc\nd"),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(text.body_kind, BodyKind::Text);
+        assert_eq!(text.body[0].path, "line 2");
+        assert_eq!(text.body[0].kind, ChangeKind::Removed);
+        assert_eq!(text.body[1].path, "line 3");
+        assert_eq!(text.body[1].kind, ChangeKind::Added);
+        assert!(diff(&left, &left, &[]).unwrap().identical);
+    }
+}
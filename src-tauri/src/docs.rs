@@ -0,0 +1,377 @@
+use crate::auth::ApiKeyLocation;
+use crate::security::SecurityScheme;
+use crate::{Endpoint, OpenApiCollection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+// The document is laid out once as blocks, then written in either format.
+enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    List(Vec<String>),
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    Code(String),
+}
+
+fn scheme_summary(name: &str, scheme: &SecurityScheme) -> String {
+    let kind = match scheme {
+        SecurityScheme::ApiKey { name, location } => {
+            let location = match location {
+                ApiKeyLocation::Header => "header",
+                ApiKeyLocation::Query => "query parameter",
+                ApiKeyLocation::Cookie => "cookie",
+            };
+            format!("API key in the {} {}", name, location)
+        }
+        SecurityScheme::Http {
+            scheme,
+            bearer_format,
+        } => match bearer_format {
+            Some(format) => format!("HTTP {} ({})", scheme, format),
+            None => format!("HTTP {}", scheme),
+        },
+        SecurityScheme::OAuth2 { .. } => "OAuth 2".into(),
+        SecurityScheme::OpenIdConnect { url } => format!("OpenID Connect, discovered at {}", url),
+        SecurityScheme::MutualTls => "Mutual TLS".into(),
+    };
+    format!("{}: {}", name, kind)
+}
+
+fn endpoint_blocks(blocks: &mut Vec<Block>, endpoint: &Endpoint) {
+    blocks.push(Block::Heading(
+        3,
+        format!("{} {}", endpoint.method.to_uppercase(), endpoint.route),
+    ));
+    if let Some(summary) = &endpoint.summary {
+        blocks.push(Block::Paragraph(summary.clone()));
+    }
+    if endpoint.deprecated {
+        blocks.push(Block::Paragraph("Deprecated.".into()));
+    }
+    if let Some(description) = &endpoint.description {
+        blocks.push(Block::Paragraph(description.clone()));
+    }
+    if !endpoint.security.is_empty() {
+        // Each requirement is an alternative; the schemes within one are all needed.
+        let alternatives: Vec<String> = endpoint
+            .security
+            .iter()
+            .map(|requirement| {
+                if requirement.is_empty() {
+                    "none".to_string()
+                } else {
+                    requirement
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                }
+            })
+            .collect();
+        blocks.push(Block::Paragraph(format!(
+            "Authentication: {}",
+            alternatives.join(" or ")
+        )));
+    }
+
+    if !endpoint.parameters.is_empty() {
+        let rows = endpoint
+            .parameters
+            .iter()
+            .map(|param| {
+                let mut kind = param.schema_type.clone().unwrap_or_default();
+                if let Some(values) = &param.enum_values {
+                    kind = format!("{} ({})", kind, values.join(", "));
+                }
+                vec![
+                    param.name.clone(),
+                    param.in_type.clone(),
+                    kind,
+                    if param.required { "yes" } else { "no" }.into(),
+                    param.description.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        blocks.push(Block::Paragraph("Parameters".into()));
+        blocks.push(Block::Table(
+            vec!["Name", "In", "Type", "Required", "Description"],
+            rows,
+        ));
+    }
+
+    if !endpoint.body_media_types.is_empty() || endpoint.body_example.is_some() {
+        let mut line = format!(
+            "Request body ({})",
+            if endpoint.body_required {
+                "required"
+            } else {
+                "optional"
+            }
+        );
+        if !endpoint.body_media_types.is_empty() {
+            line = format!("{}: {}", line, endpoint.body_media_types.join(", "));
+        }
+        blocks.push(Block::Paragraph(line));
+        if let Some(description) = &endpoint.body_description {
+            blocks.push(Block::Paragraph(description.clone()));
+        }
+        if !endpoint.body_fields.is_empty() {
+            let rows = endpoint
+                .body_fields
+                .iter()
+                .map(|field| {
+                    vec![
+                        field.name.clone(),
+                        if field.required { "yes" } else { "no" }.into(),
+                        field.description.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            blocks.push(Block::Table(vec!["Field", "Required", "Description"], rows));
+        }
+        if let Some(example) = &endpoint.body_example {
+            blocks.push(Block::Code(example.clone()));
+        }
+    }
+
+    if !endpoint.responses.is_empty() {
+        let rows = endpoint
+            .responses
+            .iter()
+            .map(|(status, response)| {
+                vec![
+                    status.clone(),
+                    response.description.clone().unwrap_or_default(),
+                    response.media_types.join(", "),
+                ]
+            })
+            .collect();
+        blocks.push(Block::Paragraph("Responses".into()));
+        blocks.push(Block::Table(
+            vec!["Status", "Description", "Media type"],
+            rows,
+        ));
+        for (status, response) in &endpoint.responses {
+            if let Some(example) = &response.example {
+                blocks.push(Block::Paragraph(format!("Example {} response", status)));
+                blocks.push(Block::Code(example.clone()));
+            }
+        }
+    }
+}
+
+fn blocks(collection: &OpenApiCollection) -> (String, Vec<Block>) {
+    let title = collection
+        .name_override
+        .clone()
+        .unwrap_or_else(|| collection.name.clone());
+    let mut blocks = vec![
+        Block::Heading(1, title.clone()),
+        Block::Paragraph(format!(
+            "Generated from {} as of {}.",
+            collection.source.as_deref().unwrap_or(&collection.url),
+            collection.last_updated.format("%Y-%m-%d")
+        )),
+    ];
+    if !collection.servers.is_empty() {
+        blocks.push(Block::Heading(2, "Servers".into()));
+        blocks.push(Block::List(
+            collection
+                .servers
+                .iter()
+                .map(|server| match &server.description {
+                    Some(description) => format!("{} ({})", server.url, description),
+                    None => server.url.clone(),
+                })
+                .collect(),
+        ));
+    }
+    if !collection.security_schemes.is_empty() {
+        blocks.push(Block::Heading(2, "Authentication".into()));
+        blocks.push(Block::List(
+            collection
+                .security_schemes
+                .iter()
+                .map(|(name, scheme)| scheme_summary(name, scheme))
+                .collect(),
+        ));
+    }
+    // Tags list every group, in the order the sidebar shows them.
+    for tag in &collection.tags {
+        let Some(endpoints) = collection.groups.get(&tag.name) else {
+            continue;
+        };
+        blocks.push(Block::Heading(2, tag.name.clone()));
+        if let Some(description) = &tag.description {
+            blocks.push(Block::Paragraph(description.clone()));
+        }
+        for endpoint in endpoints {
+            endpoint_blocks(&mut blocks, endpoint);
+        }
+    }
+    (title, blocks)
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn to_markdown(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => {
+                out.push_str(&format!("{} {}\n\n", "#".repeat(*level), text))
+            }
+            Block::Paragraph(text) => out.push_str(&format!("{}\n\n", text)),
+            Block::List(items) => {
+                for item in items {
+                    out.push_str(&format!("- {}\n", item));
+                }
+                out.push('\n');
+            }
+            Block::Table(headers, rows) => {
+                out.push_str(&format!("| {} |\n", headers.join(" | ")));
+                out.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(|cell| markdown_cell(cell)).collect();
+                    out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+                out.push('\n');
+            }
+            Block::Code(code) => {
+                // A fence longer than any backtick run inside the example.
+                let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+                let fence = "`".repeat(longest.max(2) + 1);
+                out.push_str(&format!("{}\n{}\n{}\n\n", fence, code.trim_end(), fence));
+            }
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;padding:0 1em;color:#222}\
+h3{font-family:monospace;border-top:1px solid #ddd;padding-top:1em}\
+table{border-collapse:collapse;margin-bottom:1em}th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+pre{background:#f6f8fa;padding:1em;overflow:auto}";
+
+fn to_html(title: &str, blocks: &[Block]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(title),
+        STYLE
+    );
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => {
+                out.push_str(&format!("<h{0}>{1}</h{0}>\n", level, escape(text)))
+            }
+            Block::Paragraph(text) => out.push_str(&format!("<p>{}</p>\n", escape(text))),
+            Block::List(items) => {
+                out.push_str("<ul>\n");
+                for item in items {
+                    out.push_str(&format!("<li>{}</li>\n", escape(item)));
+                }
+                out.push_str("</ul>\n");
+            }
+            Block::Table(headers, rows) => {
+                out.push_str("<table>\n<tr>");
+                for header in headers {
+                    out.push_str(&format!("<th>{}</th>", escape(header)));
+                }
+                out.push_str("</tr>\n");
+                for row in rows {
+                    out.push_str("<tr>");
+                    for cell in row {
+                        out.push_str(&format!("<td>{}</td>", escape(cell)));
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+            Block::Code(code) => out.push_str(&format!(
+                "<pre><code>{}</code></pre>\n",
+                escape(code.trim_end())
+            )),
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn render(collection: &OpenApiCollection, format: DocFormat) -> String {
+    let (title, blocks) = blocks(collection);
+    match format {
+        DocFormat::Markdown => to_markdown(&blocks),
+        DocFormat::Html => to_html(&title, &blocks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_openapi_internal;
+    use serde_json::json;
+
+    #[test]
+    fn renders_endpoints_in_both_formats() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Pet <Store>" },
+            "servers": [{ "url": "https://api.test/v1", "description": "Production" }],
+            "components": { "securitySchemes": { "token": { "type": "http", "scheme": "bearer" } } },
+            "security": [{ "token": [] }],
+            "tags": [{ "name": "pets", "description": "Everything about pets" }],
+            "paths": {
+                "/pets/{id}": {
+                    "get": {
+                        "tags": ["pets"],
+                        "summary": "Find a pet",
+                        "parameters": [
+                            { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                            { "name": "fields", "in": "query", "description": "Comma | separated", "schema": { "type": "string" } }
+                        ],
+                        "responses": {
+                            "200": {
+                                "description": "The pet",
+                                "content": { "application/json": { "example": { "name": "Rex" } } }
+                            },
+                            "404": { "description": "No such pet" }
+                        }
+                    }
+                }
+            }
+        });
+        let collection =
+            parse_openapi_internal(doc, "https://api.test/openapi.json", None).unwrap();
+
+        let markdown = render(&collection, DocFormat::Markdown);
+        assert!(markdown.starts_with("# Pet <Store>\n\n"));
+        assert!(markdown.contains("- https://api.test/v1 (Production)\n"));
+        assert!(markdown.contains("- token: HTTP bearer\n"));
+        assert!(markdown.contains("## pets\n\nEverything about pets\n\n### GET /pets/{id}\n\nFind a pet\n\nAuthentication: token\n"));
+        assert!(markdown.contains("| fields | query | string | no | Comma \\| separated |\n"));
+        assert!(markdown.contains("| 404 | No such pet |  |\n"));
+        assert!(markdown.contains("Example 200 response\n\n```\n"));
+        assert!(markdown.contains("\"Rex\""));
+
+        let html = render(&collection, DocFormat::Html);
+        assert!(html.contains("<title>Pet &lt;Store&gt;</title>"));
+        assert!(html.contains("<h3>GET /pets/{id}</h3>"));
+        assert!(html.contains("<td>Comma | separated</td>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
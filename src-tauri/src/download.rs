@@ -0,0 +1,711 @@
+use crate::checksum::{Checksum, Hasher};
+use futures_util::future::{join, try_join_all};
+use futures_util::StreamExt;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+// Segments smaller than this are not worth an extra connection.
+pub const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentProgress {
+    pub start: u64,
+    pub end: u64,
+    pub downloaded: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DownloadProgress {
+    pub request_id: Option<String>,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+    pub segments: Vec<SegmentProgress>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DownloadResult {
+    pub path: String,
+    pub size: u64,
+    pub resumed_from: u64,
+}
+
+// Downloads land in `<save_path>.part` until complete, so an interrupted or cancelled
+// download leaves something to resume from and never a truncated file at the target.
+pub fn partial_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+// The ETag or Last-Modified of the response a `.part` file came from, sent as If-Range
+// when resuming so a resource that changed in between is fetched whole again.
+pub fn validator_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".part.validator");
+    PathBuf::from(name)
+}
+
+// If-Range only takes strong ETags; a weak one falls back to Last-Modified.
+fn validator(response: &reqwest::Response) -> Option<String> {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    };
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+}
+
+// Segmented downloads preallocate the whole file, so they use their own temporary
+// name to keep `download` from mistaking one for a resumable prefix.
+pub fn segmented_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".segments");
+    PathBuf::from(name)
+}
+
+// Deletes the segmented download's file when dropped before `keep`, so a cancelled or
+// failed download leaves nothing behind; it cannot be resumed anyway.
+struct SegmentedFile {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl Drop for SegmentedFile {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// Splits `total` bytes into at most `connections` inclusive ranges of at least
+// `min_segment` bytes each.
+pub fn split_ranges(total: u64, connections: u32, min_segment: u64) -> Vec<(u64, u64)> {
+    if total == 0 {
+        return Vec::new();
+    }
+    let count = (connections.max(1) as u64)
+        .min(total.div_ceil(min_segment.max(1)))
+        .max(1);
+    let size = total.div_ceil(count);
+    (0..count)
+        .map(|i| (i * size, ((i + 1) * size).min(total) - 1))
+        .filter(|(start, end)| start <= end)
+        .collect()
+}
+
+// Parses `bytes <start>-<end>/<total>` and `bytes */<total>`.
+pub fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let total = match total.trim() {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+    let start = match range.trim() {
+        "*" => None,
+        range => Some(range.split_once('-')?.0.parse().ok()?),
+    };
+    Some((start, total))
+}
+
+fn content_range(response: &reqwest::Response) -> Option<(Option<u64>, Option<u64>)> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_content_range)
+}
+
+pub async fn download(
+    client: &Client,
+    url: &str,
+    save_path: &str,
+    checksum: Option<&Checksum>,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<DownloadResult, String> {
+    let target = PathBuf::from(save_path);
+    let part = partial_path(&target);
+    let validator_file = validator_path(&target);
+    // Without a validator there is no telling whether the partial file still matches
+    // the resource, so the download starts over.
+    let saved_validator = tokio::fs::read_to_string(&validator_file).await.ok();
+    let mut offset = match &saved_validator {
+        Some(_) => tokio::fs::metadata(&part)
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+
+    let mut request = client.get(url);
+    if let Some(saved) = saved_validator.as_deref().filter(|_| offset > 0) {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, saved.trim());
+    }
+    let mut response = request.send().await.map_err(|e| e.to_string())?;
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file already holds the whole resource.
+        if let Some((_, Some(total))) = content_range(&response) {
+            if total == offset {
+                if let Some(checksum) = checksum {
+                    verify_file(&part, checksum).await?;
+                }
+                tokio::fs::rename(&part, &target)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let _ = tokio::fs::remove_file(&validator_file).await;
+                on_progress(total, Some(total));
+                return Ok(DownloadResult {
+                    path: save_path.to_string(),
+                    size: total,
+                    resumed_from: offset,
+                });
+            }
+        }
+        offset = 0;
+        response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    }
+    if !response.status().is_success() {
+        return Err(format!("Download failed with status {}", response.status()));
+    }
+
+    let (append, total) = if response.status() == StatusCode::PARTIAL_CONTENT {
+        match content_range(&response) {
+            Some((Some(start), total)) if start == offset => (true, total),
+            _ => return Err("Server returned an unexpected Content-Range".into()),
+        }
+    } else {
+        // The server ignored the Range header, or the resource changed since the partial
+        // file was written, and is sending the full body.
+        offset = 0;
+        match validator(&response) {
+            Some(value) => tokio::fs::write(&validator_file, value).await,
+            None => match tokio::fs::remove_file(&validator_file).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+        .map_err(|e| e.to_string())?;
+        (false, response.content_length())
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&part)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut hasher = checksum.map(|c| Hasher::new(c.algorithm));
+    if let Some(hasher) = hasher.as_mut().filter(|_| offset > 0) {
+        hasher.update_from_file(&part, Some(offset)).await?;
+    }
+    let resumed_from = offset;
+    let mut downloaded = offset;
+    on_progress(downloaded, total);
+    let mut stream = response.bytes_stream();
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| e.to_string())?;
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&chunk);
+        }
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    drop(file);
+
+    if let Some(total) = total {
+        if downloaded != total {
+            return Err(format!(
+                "Incomplete download: received {} of {} bytes",
+                downloaded, total
+            ));
+        }
+    }
+    if let (Some(checksum), Some(hasher)) = (checksum, hasher) {
+        if let Err(err) = checksum.verify(&hasher.finish()) {
+            let _ = tokio::fs::remove_file(&part).await;
+            return Err(err);
+        }
+    }
+    tokio::fs::rename(&part, &target)
+        .await
+        .map_err(|e| e.to_string())?;
+    let _ = tokio::fs::remove_file(&validator_file).await;
+    Ok(DownloadResult {
+        path: save_path.to_string(),
+        size: downloaded,
+        resumed_from,
+    })
+}
+
+// A file that fails verification is deleted so a retry starts from scratch.
+async fn verify_file(path: &Path, checksum: &Checksum) -> Result<(), String> {
+    let mut hasher = Hasher::new(checksum.algorithm);
+    hasher.update_from_file(path, None).await?;
+    let result = checksum.verify(&hasher.finish());
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    result
+}
+
+// Returns the resource size when the server honours Range requests.
+async fn probe_ranges(client: &Client, url: &str) -> Option<u64> {
+    let response = client
+        .get(url)
+        .header(RANGE, "bytes=0-0")
+        .send()
+        .await
+        .ok()?;
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    content_range(&response)?.1
+}
+
+async fn fetch_segment(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    index: usize,
+    (start, end): (u64, u64),
+    progress: mpsc::UnboundedSender<(usize, u64)>,
+) -> Result<(), String> {
+    let response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", start, end))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() != StatusCode::PARTIAL_CONTENT
+        || !matches!(content_range(&response), Some((Some(s), _)) if s == start)
+    {
+        return Err(format!(
+            "Server did not honour range {}-{} (status {})",
+            start,
+            end,
+            response.status()
+        ));
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|e| e.to_string())?;
+    let expected = end - start + 1;
+    let mut written = 0u64;
+    let mut stream = response.bytes_stream();
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|e| e.to_string())?;
+        let remaining = (expected - written) as usize;
+        let chunk = &chunk[..chunk.len().min(remaining)];
+        file.write_all(chunk).await.map_err(|e| e.to_string())?;
+        written += chunk.len() as u64;
+        let _ = progress.send((index, chunk.len() as u64));
+        if written == expected {
+            break;
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    if written != expected {
+        return Err(format!(
+            "Incomplete segment {}-{}: received {} of {} bytes",
+            start, end, written, expected
+        ));
+    }
+    Ok(())
+}
+
+// Downloads the resource over several connections when the server supports Range
+// requests, falling back to a single resumable stream otherwise. Interrupted
+// segmented downloads start over.
+pub async fn download_parallel(
+    client: &Client,
+    url: &str,
+    save_path: &str,
+    connections: u32,
+    checksum: Option<&Checksum>,
+    mut on_progress: impl FnMut(u64, Option<u64>, &[SegmentProgress]),
+) -> Result<DownloadResult, String> {
+    let ranges = match probe_ranges(client, url).await {
+        Some(total) if connections > 1 => split_ranges(total, connections, MIN_SEGMENT_SIZE),
+        _ => Vec::new(),
+    };
+    if ranges.len() < 2 {
+        return download(client, url, save_path, checksum, |downloaded, total| {
+            on_progress(downloaded, total, &[])
+        })
+        .await;
+    }
+    let total = ranges.last().map(|(_, end)| end + 1).unwrap_or(0);
+
+    let target = PathBuf::from(save_path);
+    let mut temp = SegmentedFile {
+        path: segmented_path(&target),
+        keep: false,
+    };
+    let file = tokio::fs::File::create(&temp.path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.set_len(total).await.map_err(|e| e.to_string())?;
+    drop(file);
+
+    let mut segments: Vec<SegmentProgress> = ranges
+        .iter()
+        .map(|&(start, end)| SegmentProgress {
+            start,
+            end,
+            downloaded: 0,
+        })
+        .collect();
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let fetches = try_join_all(ranges.iter().enumerate().map(|(index, &range)| {
+        fetch_segment(client, url, &temp.path, index, range, sender.clone())
+    }));
+    drop(sender);
+    let report = async {
+        on_progress(0, Some(total), &segments);
+        while let Some((index, bytes)) = receiver.recv().await {
+            segments[index].downloaded += bytes;
+            let downloaded = segments.iter().map(|s| s.downloaded).sum();
+            on_progress(downloaded, Some(total), &segments);
+        }
+    };
+    let (result, _) = join(fetches, report).await;
+    result?;
+    // Segments arrive out of order, so the digest is computed once the file is whole.
+    if let Some(checksum) = checksum {
+        verify_file(&temp.path, checksum).await?;
+    }
+    tokio::fs::rename(&temp.path, &target)
+        .await
+        .map_err(|e| e.to_string())?;
+    temp.keep = true;
+    Ok(DownloadResult {
+        path: save_path.to_string(),
+        size: total,
+        resumed_from: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::ChecksumAlgorithm;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    const CONTENT: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    // Serves `content` with `"<length>"` as its ETag, honouring If-Range.
+    async fn serve_ranges(listener: TcpListener, content: Vec<u8>) {
+        let content = std::sync::Arc::new(content);
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let content = content.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                let etag = format!("\"{}\"", content.len());
+                let current = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("if-range: "))
+                    .is_none_or(|value| value.trim() == etag);
+                let range = request
+                    .lines()
+                    .filter(|_| current)
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim().split_once('-'))
+                    .map(|(start, end)| {
+                        let start: usize = start.parse().unwrap();
+                        let end = end.parse().unwrap_or(content.len() - 1);
+                        (start, end)
+                    });
+                let mut reply = match range {
+                    Some((start, end)) => format!(
+                        "HTTP/1.1 206 Partial Content\r\nETag: {}\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        etag,
+                        start,
+                        end,
+                        content.len(),
+                        end - start + 1
+                    ),
+                    None => format!(
+                        "HTTP/1.1 200 OK\r\nETag: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        etag,
+                        content.len()
+                    ),
+                }
+                .into_bytes();
+                let (start, end) = range.unwrap_or((0, content.len() - 1));
+                reply.extend_from_slice(&content[start..=end]);
+                let _ = socket.write_all(&reply).await;
+            });
+        }
+    }
+
+    #[test]
+    fn parses_content_range_values() {
+        assert_eq!(
+            parse_content_range("bytes 10-35/36"),
+            Some((Some(10), Some(36)))
+        );
+        assert_eq!(parse_content_range("bytes */36"), Some((None, Some(36))));
+        assert_eq!(parse_content_range("bytes 0-9/*"), Some((Some(0), None)));
+        assert_eq!(parse_content_range("items 0-9/10"), None);
+    }
+
+    #[tokio::test]
+    async fn resumes_from_partial_file_and_renames_on_success() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_ranges(listener, CONTENT.to_vec()));
+
+        let dir = std::env::temp_dir().join(format!("restman-download-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("artifact.bin");
+        tokio::fs::write(partial_path(&target), &CONTENT[..10])
+            .await
+            .unwrap();
+        tokio::fs::write(validator_path(&target), format!("\"{}\"", CONTENT.len()))
+            .await
+            .unwrap();
+
+        let mut reports = Vec::new();
+        let result = download(
+            &Client::new(),
+            &format!("http://{}/artifact.bin", addr),
+            &target.to_string_lossy(),
+            None,
+            |downloaded, total| reports.push((downloaded, total)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.resumed_from, 10);
+        assert_eq!(result.size, CONTENT.len() as u64);
+        assert_eq!(tokio::fs::read(&target).await.unwrap(), CONTENT);
+        assert!(!partial_path(&target).exists());
+        assert!(!validator_path(&target).exists());
+        assert_eq!(reports.first(), Some(&(10, Some(CONTENT.len() as u64))));
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_resource_changed_or_has_no_validator() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_ranges(listener, CONTENT.to_vec()));
+
+        let dir = std::env::temp_dir().join(format!("restman-if-range-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("artifact.bin");
+        let url = format!("http://{}/artifact.bin", addr);
+        let target_path = target.to_string_lossy().into_owned();
+        for validator in [Some("\"older\""), None] {
+            tokio::fs::write(partial_path(&target), b"stale")
+                .await
+                .unwrap();
+            if let Some(validator) = validator {
+                tokio::fs::write(validator_path(&target), validator)
+                    .await
+                    .unwrap();
+            }
+            let result = download(&Client::new(), &url, &target_path, None, |_, _| {})
+                .await
+                .unwrap();
+            assert_eq!(result.resumed_from, 0);
+            assert_eq!(tokio::fs::read(&target).await.unwrap(), CONTENT);
+            assert!(!validator_path(&target).exists());
+        }
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[test]
+    fn splits_ranges_by_connection_count_and_minimum_size() {
+        assert_eq!(split_ranges(10, 3, 1), vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(split_ranges(10, 4, 5), vec![(0, 4), (5, 9)]);
+        assert_eq!(split_ranges(10, 8, 100), vec![(0, 9)]);
+        assert!(split_ranges(0, 4, 1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn parallel_download_stitches_segments() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let content: Vec<u8> = (0..MIN_SEGMENT_SIZE * 3 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        tokio::spawn(serve_ranges(listener, content.clone()));
+
+        let dir = std::env::temp_dir().join(format!("restman-segments-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("large.bin");
+        let mut last = Vec::new();
+        let result = download_parallel(
+            &Client::new(),
+            &format!("http://{}/large.bin", addr),
+            &target.to_string_lossy(),
+            4,
+            None,
+            |_, _, segments| last = segments.to_vec(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.size, content.len() as u64);
+        assert_eq!(tokio::fs::read(&target).await.unwrap(), content);
+        assert!(!segmented_path(&target).exists());
+        assert_eq!(last.len(), 4);
+        assert!(last.iter().all(|s| s.downloaded == s.end - s.start + 1));
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn cancelled_or_failed_segmented_download_removes_its_file() {
+        let total = MIN_SEGMENT_SIZE * 2;
+        // Segments get a few bytes, then the connection hangs or is closed.
+        let hang = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let hanging = hang.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let hang = hanging.load(std::sync::atomic::Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 2048];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+                    let Some((start, end)) = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .and_then(|range| range.trim().split_once('-'))
+                        .and_then(|(start, end)| {
+                            Some((start.parse::<u64>().ok()?, end.parse::<u64>().ok()?))
+                        })
+                    else {
+                        return;
+                    };
+                    let head = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        start,
+                        end,
+                        total,
+                        end - start + 1
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(b"x").await;
+                    if hang && end > 0 {
+                        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+                    }
+                });
+            }
+        });
+
+        let dir =
+            std::env::temp_dir().join(format!("restman-segments-gone-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("large.bin");
+        let url = format!("http://{}/large.bin", addr);
+        let target_path = target.to_string_lossy().into_owned();
+        let client = Client::new();
+        let started = std::sync::Arc::new(tokio::sync::Notify::new());
+        let notify = started.clone();
+        let mut cancelled = Box::pin(download_parallel(
+            &client,
+            &url,
+            &target_path,
+            2,
+            None,
+            |_, _, _| notify.notify_one(),
+        ));
+        tokio::select! {
+            _ = &mut cancelled => panic!("the download should still be running"),
+            _ = started.notified() => {}
+        }
+        assert!(segmented_path(&target).exists());
+        drop(cancelled);
+        assert!(!segmented_path(&target).exists());
+
+        hang.store(false, std::sync::atomic::Ordering::SeqCst);
+        assert!(
+            download_parallel(&client, &url, &target_path, 2, None, |_, _, _| {})
+                .await
+                .is_err()
+        );
+        assert!(!segmented_path(&target).exists());
+        assert!(!target.exists());
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_deletes_partial_file() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_ranges(listener, CONTENT.to_vec()));
+
+        let dir = std::env::temp_dir().join(format!("restman-checksum-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("artifact.bin");
+        let url = format!("http://{}/artifact.bin", addr);
+        let mut checksum = Checksum {
+            algorithm: ChecksumAlgorithm::Sha1,
+            value: "0000000000000000000000000000000000000000".into(),
+        };
+        let target_path = target.to_string_lossy().into_owned();
+        let err = download(
+            &Client::new(),
+            &url,
+            &target_path,
+            Some(&checksum),
+            |_, _| {},
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("Checksum mismatch"));
+        assert!(!partial_path(&target).exists());
+        assert!(!target.exists());
+
+        // Resuming hashes the existing prefix together with the fetched remainder.
+        let mut hasher = Hasher::new(ChecksumAlgorithm::Sha1);
+        hasher.update(CONTENT);
+        checksum.value = hasher.finish();
+        tokio::fs::write(partial_path(&target), &CONTENT[..5])
+            .await
+            .unwrap();
+        tokio::fs::write(validator_path(&target), format!("\"{}\"", CONTENT.len()))
+            .await
+            .unwrap();
+        let resumed = download(
+            &Client::new(),
+            &url,
+            &target_path,
+            Some(&checksum),
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(resumed.resumed_from, 5);
+        assert_eq!(tokio::fs::read(&target).await.unwrap(), CONTENT);
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
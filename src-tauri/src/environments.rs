@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    // Secret values are kept in the OS keychain and masked everywhere else; see `secrets`.
+    #[serde(default)]
+    pub secret: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// A named set of variables, such as "staging", substituted into `{{name}}`
+// placeholders when a request is sent.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default)]
+    pub variables: Vec<Variable>,
+}
+
+impl Environment {
+    // Later definitions of a name win, matching what the user sees last in the list.
+    pub fn values(&self) -> HashMap<String, String> {
+        self.variables
+            .iter()
+            .filter(|variable| variable.enabled)
+            .map(|variable| (variable.name.clone(), variable.value.clone()))
+            .collect()
+    }
+}
+
+pub fn normalize(mut environment: Environment) -> Result<Environment, String> {
+    environment.name = environment.name.trim().to_string();
+    if environment.name.is_empty() {
+        return Err("Environment name is required".into());
+    }
+    for variable in &mut environment.variables {
+        variable.name = variable.name.trim().to_string();
+    }
+    environment
+        .variables
+        .retain(|variable| !variable.name.is_empty());
+    Ok(environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_enabled_named_variables() {
+        let variable = |name: &str, value: &str, enabled: bool| Variable {
+            name: name.into(),
+            value: value.into(),
+            enabled,
+            secret: false,
+        };
+        let environment = normalize(Environment {
+            name: " staging ".into(),
+            variables: vec![
+                variable(" host ", "old.test", true),
+                variable("host", "staging.test", true),
+                variable("token", "secret", false),
+                variable("  ", "dropped", true),
+            ],
+        })
+        .unwrap();
+        assert_eq!(environment.name, "staging");
+        assert_eq!(environment.variables.len(), 3);
+        let values = environment.values();
+        assert_eq!(values["host"], "staging.test");
+        assert!(!values.contains_key("token"));
+        assert!(normalize(Environment {
+            name: "".into(),
+            variables: Vec::new()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::body::BodyEncoding;
+use crate::http::ResponseData;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+// Where in the response a value comes from.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum Source {
+    // JSONPath into the body, e.g. `$.data.token`; the first match is used.
+    Json {
+        path: String,
+    },
+    // First value of a response header, matched case-insensitively.
+    Header {
+        name: String,
+    },
+    // Regex over the body. Without a group, the first capture group is used if the
+    // pattern has one, the whole match otherwise.
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        group: Option<usize>,
+    },
+}
+
+// Stores a value from the response in the environment variable `variable` once the
+// request completes, so later requests can refer to it as `{{variable}}`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Extraction {
+    pub variable: String,
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Extracted {
+    pub variable: String,
+    pub value: Option<String>,
+    pub error: Option<String>,
+}
+
+fn text_body(response: &ResponseData) -> Result<&str, String> {
+    if response.body_encoding != BodyEncoding::Text || response.body_path.is_some() {
+        return Err("Response body is not text".into());
+    }
+    Ok(&response.body)
+}
+
+pub fn extract(response: &ResponseData, source: &Source) -> Result<Option<String>, String> {
+    match source {
+        Source::Json { path } => {
+            let path = JsonPath::parse(path).map_err(|e| format!("Invalid JSONPath: {}", e))?;
+            let body: Value = serde_json::from_str(text_body(response)?)
+                .map_err(|e| format!("Response body is not JSON: {}", e))?;
+            Ok(path.query(&body).first().map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }))
+        }
+        Source::Header { name } => Ok(response
+            .headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .and_then(|(_, values)| values.first().cloned())),
+        Source::Regex { pattern, group } => {
+            let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+            let group = group.unwrap_or(if regex.captures_len() > 1 { 1 } else { 0 });
+            if group >= regex.captures_len() {
+                return Err(format!("Regex has no group {}", group));
+            }
+            Ok(regex
+                .captures(text_body(response)?)
+                .and_then(|captures| captures.get(group))
+                .map(|m| m.as_str().to_string()))
+        }
+    }
+}
+
+// Runs every extraction; failures are reported per variable rather than failing the
+// request that already succeeded.
+pub fn extract_all(response: &ResponseData, extractions: &[Extraction]) -> Vec<Extracted> {
+    extractions
+        .iter()
+        .map(|extraction| {
+            let (value, error) = match extract(response, &extraction.source) {
+                Ok(Some(value)) => (Some(value), None),
+                Ok(None) => (None, Some("No match in the response".to_string())),
+                Err(e) => (None, Some(e)),
+            };
+            Extracted {
+                variable: extraction.variable.clone(),
+                value,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn response(body: &str) -> ResponseData {
+        ResponseData {
+            status: 200,
+            status_text: "OK".into(),
+            http_version: "HTTP/1.1".into(),
+            headers: HashMap::from([("x-request-id".into(), vec!["abc".into()])]),
+            body: body.into(),
+            content_type: Some("application/json".into()),
+            elapsed_ms: 1,
+            size: body.len() as u64,
+            encoded_size: body.len() as u64,
+            ..ResponseData::default()
+        }
+    }
+
+    #[test]
+    fn extracts_from_json_headers_and_regex() {
+        let extractions: Vec<Extraction> = serde_json::from_value(serde_json::json!([
+            { "variable": "token", "from": "json", "path": "$.data.token" },
+            { "variable": "count", "from": "json", "path": "$.data.items.length" },
+            { "variable": "first", "from": "json", "path": "$.data.items[0]" },
+            { "variable": "request", "from": "header", "name": "X-Request-Id" },
+            { "variable": "session", "from": "regex", "pattern": "\"token\":\\s*\"(\\w+)\"" },
+            { "variable": "bad", "from": "regex", "pattern": "(", "group": null }
+        ]))
+        .unwrap();
+        let extracted = extract_all(
+            &response(r#"{"data": {"token": "t0k", "items": [3, 4]}}"#),
+            &extractions,
+        );
+        let values: Vec<Option<&str>> = extracted.iter().map(|e| e.value.as_deref()).collect();
+        assert_eq!(
+            values,
+            vec![Some("t0k"), None, Some("3"), Some("abc"), Some("t0k"), None]
+        );
+        assert_eq!(
+            extracted[1].error.as_deref(),
+            Some("No match in the response")
+        );
+        assert!(extracted[5]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("Invalid regex"));
+        assert!(extract(&response("<html>"), &Source::Json { path: "$.a".into() }).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::body::{create_response_file, text_prefix, TEXT_PREVIEW_LIMIT};
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use serde::{Deserialize, Serialize};
+use serde_json::ser::PrettyFormatter;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const DEFAULT_INDENT: usize = 2;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyLanguage {
+    Json,
+    Xml,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatStyle {
+    Pretty,
+    Minify,
+}
+
+// Formatted text inline, or, for a body read from a file, a new file holding it with
+// its start as `body`, as for large responses.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FormattedBody {
+    pub language: BodyLanguage,
+    pub body: String,
+    pub body_path: Option<String>,
+    pub size: u64,
+}
+
+// Guesses the language from the first non-blank character.
+pub fn detect(prefix: &[u8]) -> Option<BodyLanguage> {
+    match prefix.iter().find(|b| !b.is_ascii_whitespace())? {
+        b'{' | b'[' => Some(BodyLanguage::Json),
+        b'<' => Some(BodyLanguage::Xml),
+        _ => None,
+    }
+}
+
+// Re-serializes token by token, so nothing but the reader's and writer's buffers is held
+// in memory whatever the size of the body.
+fn transcode_json<R: Read, W: Write>(
+    input: R,
+    output: W,
+    style: FormatStyle,
+    indent: usize,
+) -> Result<(), String> {
+    let mut deserializer = serde_json::Deserializer::from_reader(input);
+    let result = match style {
+        FormatStyle::Pretty => {
+            let indent = vec![b' '; indent];
+            let formatter = PrettyFormatter::with_indent(&indent);
+            let mut serializer = serde_json::Serializer::with_formatter(output, formatter);
+            serde_transcode::transcode(&mut deserializer, &mut serializer)
+        }
+        FormatStyle::Minify => {
+            let mut serializer = serde_json::Serializer::new(output);
+            serde_transcode::transcode(&mut deserializer, &mut serializer)
+        }
+    };
+    result.map_err(|e| format!("Invalid JSON: {}", e))?;
+    deserializer
+        .end()
+        .map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+fn transcode_xml<R: BufRead, W: Write>(
+    input: R,
+    output: W,
+    style: FormatStyle,
+    indent: usize,
+) -> Result<(), String> {
+    let mut reader = Reader::from_reader(input);
+    reader.trim_text(true);
+    let mut writer = match style {
+        FormatStyle::Pretty => Writer::new_with_indent(output, b' ', indent),
+        FormatStyle::Minify => Writer::new(output),
+    };
+    let mut buffer = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buffer) {
+            Ok(Event::Eof) => return Ok(()),
+            Ok(event) => writer.write_event(event).map_err(|e| e.to_string())?,
+            Err(e) => {
+                return Err(format!(
+                    "Invalid XML at {}: {}",
+                    reader.buffer_position(),
+                    e
+                ))
+            }
+        }
+        buffer.clear();
+    }
+}
+
+fn transcode<R: BufRead, W: Write>(
+    input: R,
+    output: W,
+    language: BodyLanguage,
+    style: FormatStyle,
+    indent: Option<usize>,
+) -> Result<(), String> {
+    let indent = indent.unwrap_or(DEFAULT_INDENT);
+    match language {
+        BodyLanguage::Json => transcode_json(input, output, style, indent),
+        BodyLanguage::Xml => transcode_xml(input, output, style, indent),
+    }
+}
+
+pub fn format_text(
+    body: &str,
+    language: Option<BodyLanguage>,
+    style: FormatStyle,
+    indent: Option<usize>,
+) -> Result<FormattedBody, String> {
+    let language = language
+        .or_else(|| detect(body.as_bytes()))
+        .ok_or("Body is neither JSON nor XML")?;
+    let mut output = Vec::with_capacity(body.len());
+    transcode(body.as_bytes(), &mut output, language, style, indent)?;
+    Ok(FormattedBody {
+        language,
+        size: output.len() as u64,
+        body: String::from_utf8(output).map_err(|e| e.to_string())?,
+        body_path: None,
+    })
+}
+
+pub fn format_file(
+    path: &Path,
+    language: Option<BodyLanguage>,
+    style: FormatStyle,
+    indent: Option<usize>,
+) -> Result<FormattedBody, String> {
+    let mut input = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+    let language = match language {
+        Some(language) => language,
+        None => detect(input.fill_buf().map_err(|e| e.to_string())?)
+            .ok_or("Body is neither JSON nor XML")?,
+    };
+    let (file, target) = create_response_file(Some(match language {
+        BodyLanguage::Json => "application/json",
+        BodyLanguage::Xml => "application/xml",
+    }))?;
+    let mut output = BufWriter::new(file);
+    let result = transcode(input, &mut output, language, style, indent)
+        .and_then(|_| output.flush().map_err(|e| e.to_string()));
+    drop(output);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&target);
+        return Err(e);
+    }
+    let size = std::fs::metadata(&target).map_err(|e| e.to_string())?.len();
+    let mut preview = Vec::new();
+    File::open(&target)
+        .and_then(|file| {
+            file.take(TEXT_PREVIEW_LIMIT as u64)
+                .read_to_end(&mut preview)
+        })
+        .map_err(|e| e.to_string())?;
+    Ok(FormattedBody {
+        language,
+        body: text_prefix(&preview).0,
+        body_path: Some(target.to_string_lossy().into_owned()),
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_json_and_xml_both_ways() {
+        let json = r#" {"a": [1, {"b": "x y"}], "c": {}} "#;
+        let pretty = format_text(json, None, FormatStyle::Pretty, None).unwrap();
+        assert_eq!(pretty.language, BodyLanguage::Json);
+        assert_eq!(
+            pretty.body,
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": \"x y\"\n    }\n  ],\n  \"c\": {}\n}"
+        );
+        let minified = format_text(&pretty.body, None, FormatStyle::Minify, None).unwrap();
+        assert_eq!(minified.body, r#"{"a":[1,{"b":"x y"}],"c":{}}"#);
+        assert!(format_text("{\"a\": }", None, FormatStyle::Pretty, None).is_err());
+        assert!(format_text("{} {}", None, FormatStyle::Pretty, None).is_err());
+
+        let xml = "<a x=\"1\"><b>text</b>\n   <c/></a>";
+        let pretty = format_text(xml, None, FormatStyle::Pretty, Some(4)).unwrap();
+        assert_eq!(pretty.language, BodyLanguage::Xml);
+        assert_eq!(pretty.body, "<a x=\"1\">\n    <b>text</b>\n    <c/>\n</a>");
+        let minified = format_text(&pretty.body, None, FormatStyle::Minify, None).unwrap();
+        assert_eq!(minified.body, "<a x=\"1\"><b>text</b><c/></a>");
+        assert!(format_text("<a></b>", None, FormatStyle::Pretty, None).is_err());
+        assert!(format_text("plain", None, FormatStyle::Pretty, None).is_err());
+    }
+}
@@ -0,0 +1,416 @@
+use crate::auth::Auth;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+// A GraphQL operation. Requests that carry one are sent as a JSON POST of the query,
+// variables and operation name, whatever their method and body say.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct GraphqlQuery {
+    pub query: String,
+    pub variables: Option<Value>,
+    pub operation_name: Option<String>,
+}
+
+impl GraphqlQuery {
+    pub fn body(&self) -> String {
+        let mut body = json!({ "query": self.query });
+        if let Some(variables) = self.variables.as_ref().filter(|v| !v.is_null()) {
+            body["variables"] = variables.clone();
+        }
+        if let Some(name) = self.operation_name.as_ref().filter(|n| !n.is_empty()) {
+            body["operationName"] = json!(name);
+        }
+        body.to_string()
+    }
+}
+
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { ...InputValue }
+        type { ...TypeRef }
+        isDeprecated
+        deprecationReason
+      }
+      inputFields { ...InputValue }
+      interfaces { ...TypeRef }
+      enumValues(includeDeprecated: true) { name }
+      possibleTypes { ...TypeRef }
+    }
+  }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } } }
+}";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphqlArgument {
+    pub name: String,
+    pub description: Option<String>,
+    // In SDL notation, e.g. `[ID!]!`.
+    pub type_name: String,
+    pub default_value: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphqlField {
+    pub name: String,
+    pub description: Option<String>,
+    pub type_name: String,
+    pub args: Vec<GraphqlArgument>,
+    pub deprecated: bool,
+    pub deprecation_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphqlType {
+    pub name: String,
+    pub kind: String,
+    pub description: Option<String>,
+    pub fields: Vec<GraphqlField>,
+    pub input_fields: Vec<GraphqlArgument>,
+    pub enum_values: Vec<String>,
+    pub interfaces: Vec<String>,
+    pub possible_types: Vec<String>,
+}
+
+// One root field of the schema, with a ready-to-send operation selecting its scalar
+// fields, the way endpoints carry body examples.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphqlOperation {
+    pub field: GraphqlField,
+    pub example: GraphqlQuery,
+}
+
+// The introspected schema of an endpoint, browsable like an OpenAPI collection:
+// operations grouped by root type, and the named types they use. Built-in `__` types
+// are left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphqlSchema {
+    pub name: String,
+    pub url: String,
+    pub queries: Vec<GraphqlOperation>,
+    pub mutations: Vec<GraphqlOperation>,
+    pub subscriptions: Vec<GraphqlOperation>,
+    pub types: Vec<GraphqlType>,
+    pub last_updated: DateTime<Utc>,
+    // Sent with the introspection query, and again on refresh.
+    pub fetch_headers: HashMap<String, String>,
+    pub fetch_auth: Option<Auth>,
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(String::from)
+}
+
+fn list<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn type_name(type_ref: &Value) -> String {
+    let inner = type_ref.get("ofType").unwrap_or(&Value::Null);
+    match type_ref.get("kind").and_then(Value::as_str) {
+        Some("NON_NULL") => format!("{}!", type_name(inner)),
+        Some("LIST") => format!("[{}]", type_name(inner)),
+        _ => text(type_ref, "name").unwrap_or_default(),
+    }
+}
+
+// The named type under any list and non-null wrappers.
+fn named_type(type_name: &str) -> &str {
+    type_name.trim_matches(|c| matches!(c, '[' | ']' | '!'))
+}
+
+fn argument(value: &Value) -> GraphqlArgument {
+    GraphqlArgument {
+        name: text(value, "name").unwrap_or_default(),
+        description: text(value, "description"),
+        type_name: type_name(value.get("type").unwrap_or(&Value::Null)),
+        default_value: text(value, "defaultValue"),
+    }
+}
+
+fn field(value: &Value) -> GraphqlField {
+    GraphqlField {
+        name: text(value, "name").unwrap_or_default(),
+        description: text(value, "description"),
+        type_name: type_name(value.get("type").unwrap_or(&Value::Null)),
+        args: list(value, "args").iter().map(argument).collect(),
+        deprecated: value
+            .get("isDeprecated")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        deprecation_reason: text(value, "deprecationReason"),
+    }
+}
+
+fn graphql_type(value: &Value) -> GraphqlType {
+    let names = |key: &str| -> Vec<String> {
+        list(value, key)
+            .iter()
+            .filter_map(|item| text(item, "name"))
+            .collect()
+    };
+    GraphqlType {
+        name: text(value, "name").unwrap_or_default(),
+        kind: text(value, "kind").unwrap_or_default(),
+        description: text(value, "description"),
+        fields: list(value, "fields").iter().map(field).collect(),
+        input_fields: list(value, "inputFields").iter().map(argument).collect(),
+        enum_values: names("enumValues"),
+        interfaces: names("interfaces"),
+        possible_types: names("possibleTypes"),
+    }
+}
+
+// Selects the scalar and enum fields of `type_name`, descending into object fields
+// without arguments up to `depth` levels. Types already on the path are skipped so
+// cycles end.
+fn selection(
+    type_name: &str,
+    types: &HashMap<&str, &GraphqlType>,
+    depth: usize,
+    path: &mut HashSet<String>,
+    indent: usize,
+) -> Option<String> {
+    let graphql_type = types.get(named_type(type_name))?;
+    if !matches!(graphql_type.kind.as_str(), "OBJECT" | "INTERFACE" | "UNION") {
+        return None;
+    }
+    path.insert(graphql_type.name.clone());
+    let pad = "  ".repeat(indent + 1);
+    let mut lines = Vec::new();
+    for field in graphql_type.fields.iter().filter(|field| !field.deprecated) {
+        let field_type = named_type(&field.type_name);
+        match types.get(field_type).map(|t| t.kind.as_str()) {
+            Some("SCALAR" | "ENUM") => lines.push(format!("{}{}", pad, field.name)),
+            Some(_) if depth > 0 && field.args.is_empty() && !path.contains(field_type) => {
+                if let Some(nested) = selection(field_type, types, depth - 1, path, indent + 1) {
+                    lines.push(format!("{}{} {}", pad, field.name, nested));
+                }
+            }
+            _ => {}
+        }
+    }
+    path.remove(&graphql_type.name);
+    if lines.is_empty() {
+        lines.push(format!("{}__typename", pad));
+    }
+    Some(format!(
+        "{{\n{}\n{}}}",
+        lines.join("\n"),
+        "  ".repeat(indent)
+    ))
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Declares every argument as a variable, with defaults as variable values and nulls
+// for the rest so they show up for editing.
+fn example(
+    keyword: &str,
+    field: &GraphqlField,
+    types: &HashMap<&str, &GraphqlType>,
+) -> GraphqlQuery {
+    let operation_name = capitalize(&field.name);
+    let mut header = format!("{} {}", keyword, operation_name);
+    let mut call = field.name.clone();
+    let mut variables = serde_json::Map::new();
+    if !field.args.is_empty() {
+        let declared: Vec<String> = field
+            .args
+            .iter()
+            .map(|arg| format!("${}: {}", arg.name, arg.type_name))
+            .collect();
+        let passed: Vec<String> = field
+            .args
+            .iter()
+            .map(|arg| format!("{}: ${}", arg.name, arg.name))
+            .collect();
+        header = format!("{}({})", header, declared.join(", "));
+        call = format!("{}({})", call, passed.join(", "));
+        for arg in &field.args {
+            let value = arg
+                .default_value
+                .as_deref()
+                .and_then(|value| serde_json::from_str(value).ok())
+                .unwrap_or(Value::Null);
+            variables.insert(arg.name.clone(), value);
+        }
+    }
+    let selected = selection(&field.type_name, types, 1, &mut HashSet::new(), 1)
+        .map(|selection| format!(" {}", selection))
+        .unwrap_or_default();
+    GraphqlQuery {
+        query: format!("{} {{\n  {}{}\n}}", header, call, selected),
+        variables: (!variables.is_empty()).then_some(Value::Object(variables)),
+        operation_name: Some(operation_name),
+    }
+}
+
+// Builds the schema from an introspection response. GraphQL errors without data are
+// reported as the error.
+pub fn parse_introspection(response: &str, url: &str) -> Result<GraphqlSchema, String> {
+    let response: Value = serde_json::from_str(response)
+        .map_err(|e| format!("Introspection response is not JSON: {}", e))?;
+    let Some(schema) = response.pointer("/data/__schema") else {
+        let errors: Vec<String> = list(&response, "errors")
+            .iter()
+            .filter_map(|error| text(error, "message"))
+            .collect();
+        return Err(if errors.is_empty() {
+            "Introspection response has no schema".to_string()
+        } else {
+            format!("Introspection failed: {}", errors.join("; "))
+        });
+    };
+    let all: Vec<GraphqlType> = list(schema, "types").iter().map(graphql_type).collect();
+    let by_name: HashMap<&str, &GraphqlType> = all.iter().map(|t| (t.name.as_str(), t)).collect();
+    let operations = |root: &str, keyword: &str| -> Vec<GraphqlOperation> {
+        schema
+            .pointer(&format!("/{}/name", root))
+            .and_then(Value::as_str)
+            .and_then(|name| by_name.get(name))
+            .map(|root| {
+                root.fields
+                    .iter()
+                    .map(|field| GraphqlOperation {
+                        field: field.clone(),
+                        example: example(keyword, field, &by_name),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let queries = operations("queryType", "query");
+    let mutations = operations("mutationType", "mutation");
+    let subscriptions = operations("subscriptionType", "subscription");
+    let mut types: Vec<GraphqlType> = all
+        .iter()
+        .filter(|t| !t.name.starts_with("__"))
+        .cloned()
+        .collect();
+    types.sort_by(|a, b| a.name.cmp(&b.name));
+    let name = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(String::from))
+        .unwrap_or_else(|| url.to_string());
+    Ok(GraphqlSchema {
+        name,
+        url: url.to_string(),
+        queries,
+        mutations,
+        subscriptions,
+        types,
+        last_updated: Utc::now(),
+        fetch_headers: HashMap::new(),
+        fetch_auth: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(kind: &str, name: &str) -> Value {
+        json!({ "kind": kind, "name": name, "ofType": null })
+    }
+
+    fn non_null(inner: Value) -> Value {
+        json!({ "kind": "NON_NULL", "name": null, "ofType": inner })
+    }
+
+    #[test]
+    fn builds_schema_and_example_operations_from_introspection() {
+        let scalar = |name: &str| json!({ "kind": "SCALAR", "name": name, "fields": null });
+        let response = json!({ "data": { "__schema": {
+            "queryType": { "name": "Query" },
+            "mutationType": { "name": "Mutation" },
+            "subscriptionType": null,
+            "types": [
+                { "kind": "OBJECT", "name": "Query", "fields": [
+                    { "name": "user", "args": [
+                        { "name": "id", "type": non_null(named("SCALAR", "ID")), "defaultValue": null }
+                    ], "type": named("OBJECT", "User"), "isDeprecated": false }
+                ] },
+                { "kind": "OBJECT", "name": "Mutation", "fields": [
+                    { "name": "ping", "args": [], "type": non_null(named("SCALAR", "String")), "isDeprecated": false }
+                ] },
+                { "kind": "OBJECT", "name": "User", "description": "A person", "fields": [
+                    { "name": "id", "args": [], "type": non_null(named("SCALAR", "ID")), "isDeprecated": false },
+                    { "name": "name", "args": [], "type": named("SCALAR", "String"), "isDeprecated": false },
+                    { "name": "login", "args": [], "type": named("SCALAR", "String"), "isDeprecated": true },
+                    { "name": "friends", "args": [], "type": { "kind": "LIST", "name": null, "ofType": named("OBJECT", "User") }, "isDeprecated": false }
+                ] },
+                scalar("ID"),
+                scalar("String"),
+                { "kind": "OBJECT", "name": "__Schema", "fields": [] }
+            ]
+        } } });
+
+        let schema =
+            parse_introspection(&response.to_string(), "https://api.shop.test/graphql").unwrap();
+        assert_eq!(schema.name, "api.shop.test");
+        let names: Vec<&str> = schema.types.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["ID", "Mutation", "Query", "String", "User"]);
+        assert_eq!(schema.queries[0].field.args[0].type_name, "ID!");
+        assert_eq!(schema.types[4].fields[3].type_name, "[User]");
+        assert_eq!(
+            schema.queries[0].example,
+            GraphqlQuery {
+                query: "query User($id: ID!) {\n  user(id: $id) {\n    id\n    name\n  }\n}".into(),
+                variables: Some(json!({ "id": null })),
+                operation_name: Some("User".into()),
+            }
+        );
+        assert_eq!(
+            schema.mutations[0].example.query,
+            "mutation Ping {\n  ping\n}"
+        );
+        assert_eq!(
+            schema.mutations[0].example.body(),
+            r#"{"operationName":"Ping","query":"mutation Ping {\n  ping\n}"}"#
+        );
+
+        let failed = parse_introspection(
+            r#"{"errors": [{"message": "introspection disabled"}]}"#,
+            "x",
+        );
+        assert_eq!(
+            failed.unwrap_err(),
+            "Introspection failed: introspection disabled"
+        );
+    }
+}
@@ -0,0 +1,608 @@
+use crate::proto;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, Kind, MessageDescriptor, MethodDescriptor};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::metadata::{KeyAndValueRef, MetadataKey, MetadataMap, MetadataValue};
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tonic::Status;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1::{ServerReflectionRequest, ServerReflectionResponse};
+
+// Both versions of the reflection service use the same messages, so the v1 types serve
+// for either path.
+const REFLECTION_PATHS: [&str; 2] = [
+    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+    "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+];
+
+// Where service descriptors come from: `.proto` files on disk, or the server's
+// reflection service.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum DescriptorSource {
+    Protos {
+        files: Vec<String>,
+        #[serde(default)]
+        import_paths: Vec<String>,
+    },
+    Reflection,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrpcMethod {
+    pub name: String,
+    // `package.Service/Method`, as `GrpcCall::method` expects it.
+    pub path: String,
+    pub input_type: String,
+    pub output_type: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub request_template: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrpcService {
+    pub name: String,
+    pub methods: Vec<GrpcMethod>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrpcCall {
+    pub url: String,
+    pub method: String,
+    // The request message in protobuf's JSON mapping.
+    #[serde(default)]
+    pub message: Value,
+    #[serde(default)]
+    pub metadata: Vec<(String, String)>,
+    pub source: DescriptorSource,
+    pub timeout_ms: Option<u64>,
+}
+
+// A finished call. Non-OK statuses are results rather than errors, like HTTP error
+// responses; `messages` holds whatever arrived before the status.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrpcResponse {
+    pub status: i32,
+    pub status_name: String,
+    pub status_message: String,
+    pub messages: Vec<Value>,
+    pub headers: Vec<(String, String)>,
+    pub trailers: Vec<(String, String)>,
+    pub elapsed_ms: u64,
+}
+
+// Encodes and decodes messages whose types are only known at runtime.
+struct DynamicCodec {
+    output: MessageDescriptor,
+}
+
+struct DynamicEncoder;
+
+struct DynamicDecoder(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> DynamicEncoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> DynamicDecoder {
+        DynamicDecoder(self.output.clone())
+    }
+}
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: DynamicMessage, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<DynamicMessage>, Status> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+// URLs without a scheme are taken as plaintext, the usual setup for local servers.
+pub async fn connect(url: &str) -> Result<Channel, String> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("http://{}", url)
+    };
+    let mut endpoint = Endpoint::from_shared(url.clone()).map_err(|e| e.to_string())?;
+    if url.starts_with("https://") {
+        endpoint = endpoint
+            .tls_config(ClientTlsConfig::new().with_native_roots())
+            .map_err(|e| e.to_string())?;
+    }
+    endpoint
+        .connect()
+        .await
+        .map_err(|e| format!("Cannot connect to {}: {}", url, describe(&e)))
+}
+
+// Transport errors wrap the useful part in their source chain.
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+fn apply_metadata(target: &mut MetadataMap, metadata: &[(String, String)]) -> Result<(), String> {
+    for (name, value) in metadata {
+        let name = name.to_ascii_lowercase();
+        if name.ends_with("-bin") {
+            let key = MetadataKey::from_bytes(name.as_bytes()).map_err(|e| e.to_string())?;
+            let bytes = STANDARD
+                .decode(value)
+                .map_err(|e| format!("{} is not base64: {}", name, e))?;
+            target.append_bin(key, MetadataValue::from_bytes(&bytes));
+        } else {
+            let key = MetadataKey::from_bytes(name.as_bytes()).map_err(|e| e.to_string())?;
+            let value = MetadataValue::try_from(value.as_str())
+                .map_err(|e| format!("Invalid value for {}: {}", name, e))?;
+            target.append(key, value);
+        }
+    }
+    Ok(())
+}
+
+// Binary values are shown base64 encoded, as they travel.
+fn metadata_list(metadata: &MetadataMap) -> Vec<(String, String)> {
+    metadata
+        .iter()
+        .map(|entry| match entry {
+            KeyAndValueRef::Ascii(key, value) => (
+                key.to_string(),
+                value.to_str().unwrap_or_default().to_string(),
+            ),
+            KeyAndValueRef::Binary(key, value) => (
+                key.to_string(),
+                String::from_utf8_lossy(value.as_encoded_bytes()).into_owned(),
+            ),
+        })
+        .collect()
+}
+
+async fn reflection_request(
+    channel: &Channel,
+    path: &'static str,
+    metadata: &[(String, String)],
+    request: MessageRequest,
+) -> Result<MessageResponse, Status> {
+    let mut grpc = tonic::client::Grpc::new(channel.clone());
+    grpc.ready()
+        .await
+        .map_err(|e| Status::unavailable(e.to_string()))?;
+    let message = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(request),
+    };
+    let mut request = tonic::Request::new(futures_util::stream::iter(vec![message]));
+    apply_metadata(request.metadata_mut(), metadata).map_err(Status::invalid_argument)?;
+    let codec =
+        tonic_prost::ProstCodec::<ServerReflectionRequest, ServerReflectionResponse>::default();
+    let mut responses = grpc
+        .streaming(
+            request,
+            tonic::codegen::http::uri::PathAndQuery::from_static(path),
+            codec,
+        )
+        .await?
+        .into_inner();
+    match responses.message().await?.and_then(|r| r.message_response) {
+        Some(MessageResponse::ErrorResponse(error)) => {
+            Err(Status::new(error.error_code.into(), error.error_message))
+        }
+        Some(response) => Ok(response),
+        None => Err(Status::unknown("Empty reflection response")),
+    }
+}
+
+fn file_descriptors(
+    response: MessageResponse,
+) -> Result<Vec<prost_types::FileDescriptorProto>, String> {
+    match response {
+        MessageResponse::FileDescriptorResponse(files) => files
+            .file_descriptor_proto
+            .iter()
+            .map(|bytes| {
+                prost_types::FileDescriptorProto::decode(bytes.as_slice())
+                    .map_err(|e| e.to_string())
+            })
+            .collect(),
+        _ => Err("Unexpected reflection response".into()),
+    }
+}
+
+// Asks the server for its services and the files defining them, then for any imported
+// file it left out. Servers that only implement the v1alpha service are supported.
+pub async fn reflect(
+    channel: &Channel,
+    metadata: &[(String, String)],
+) -> Result<DescriptorPool, String> {
+    let mut listed = None;
+    for path in REFLECTION_PATHS {
+        match reflection_request(
+            channel,
+            path,
+            metadata,
+            MessageRequest::ListServices(String::new()),
+        )
+        .await
+        {
+            Err(status) if status.code() == tonic::Code::Unimplemented => continue,
+            result => {
+                listed = Some((
+                    path,
+                    result.map_err(|s| format!("Server reflection failed: {}", s.message()))?,
+                ));
+                break;
+            }
+        }
+    }
+    let Some((path, MessageResponse::ListServicesResponse(list))) = listed else {
+        return Err("The server does not support reflection".into());
+    };
+    let mut pool = DescriptorPool::global();
+    let mut files: HashMap<String, prost_types::FileDescriptorProto> = HashMap::new();
+    let services = list
+        .service
+        .into_iter()
+        .map(|service| service.name)
+        .filter(|name| !name.starts_with("grpc.reflection."));
+    for service in services {
+        let response = reflection_request(
+            channel,
+            path,
+            metadata,
+            MessageRequest::FileContainingSymbol(service.clone()),
+        )
+        .await
+        .map_err(|s| format!("Cannot load {}: {}", service, s.message()))?;
+        for file in file_descriptors(response)? {
+            files.insert(file.name().to_string(), file);
+        }
+    }
+    let mut requested = HashSet::new();
+    loop {
+        let missing: Vec<String> = files
+            .values()
+            .flat_map(|file| file.dependency.iter())
+            .filter(|name| {
+                !files.contains_key(*name)
+                    && pool.get_file_by_name(name).is_none()
+                    && !requested.contains(*name)
+            })
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            break;
+        }
+        for name in missing {
+            requested.insert(name.clone());
+            let response = reflection_request(
+                channel,
+                path,
+                metadata,
+                MessageRequest::FileByFilename(name.clone()),
+            )
+            .await
+            .map_err(|s| format!("Cannot load {}: {}", name, s.message()))?;
+            for file in file_descriptors(response)? {
+                files.insert(file.name().to_string(), file);
+            }
+        }
+    }
+    pool.add_file_descriptor_protos(files.into_values())
+        .map_err(|e| e.to_string())?;
+    Ok(pool)
+}
+
+pub async fn descriptors(
+    source: &DescriptorSource,
+    channel: Option<&Channel>,
+    metadata: &[(String, String)],
+) -> Result<DescriptorPool, String> {
+    match source {
+        DescriptorSource::Protos {
+            files,
+            import_paths,
+        } => {
+            let files: Vec<PathBuf> = files.iter().map(PathBuf::from).collect();
+            let import_paths: Vec<PathBuf> = import_paths.iter().map(PathBuf::from).collect();
+            tokio::task::spawn_blocking(move || proto::load(&files, &import_paths))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        DescriptorSource::Reflection => {
+            let channel = channel.ok_or("Server reflection needs a server URL")?;
+            reflect(channel, metadata).await
+        }
+    }
+}
+
+// An example value for a field of `kind`. Messages already on the path come out empty,
+// so recursive types end.
+fn example(kind: &Kind, path: &mut Vec<String>) -> Value {
+    match kind {
+        Kind::Double | Kind::Float => json!(0.0),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Uint32 | Kind::Fixed32 => json!(0),
+        // 64-bit integers are strings in protobuf's JSON mapping.
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint64 | Kind::Fixed64 => json!("0"),
+        Kind::Bool => json!(false),
+        Kind::String => json!(""),
+        Kind::Bytes => json!(""),
+        Kind::Enum(descriptor) => descriptor
+            .values()
+            .next()
+            .map(|value| json!(value.name()))
+            .unwrap_or(Value::Null),
+        Kind::Message(descriptor) => template(descriptor, path),
+    }
+}
+
+// Well-known types have their own JSON forms, which the template follows.
+fn well_known(descriptor: &MessageDescriptor) -> Option<Value> {
+    Some(match descriptor.full_name() {
+        "google.protobuf.Timestamp" => json!("1970-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => json!("0s"),
+        "google.protobuf.Struct" | "google.protobuf.Any" => json!({}),
+        "google.protobuf.Value" => Value::Null,
+        "google.protobuf.ListValue" => json!([]),
+        "google.protobuf.FieldMask" => json!(""),
+        "google.protobuf.Empty" => json!({}),
+        name if name.starts_with("google.protobuf.") && name.ends_with("Value") => {
+            example(&descriptor.get_field(1)?.kind(), &mut Vec::new())
+        }
+        _ => return None,
+    })
+}
+
+// A request message with every field set to an example of its type, by JSON name. Only
+// the first field of each oneof is filled in, since setting more is an error.
+pub fn template(descriptor: &MessageDescriptor, path: &mut Vec<String>) -> Value {
+    if let Some(value) = well_known(descriptor) {
+        return value;
+    }
+    if path.iter().any(|name| name == descriptor.full_name()) {
+        return json!({});
+    }
+    path.push(descriptor.full_name().to_string());
+    let mut object = Map::new();
+    let mut oneofs = HashSet::new();
+    for field in descriptor.fields() {
+        if let Some(oneof) = field.containing_oneof().filter(|o| !o.is_synthetic()) {
+            if !oneofs.insert(oneof.name().to_string()) {
+                continue;
+            }
+        }
+        let value = if field.is_map() {
+            let entry = field.kind();
+            let entry = entry.as_message().expect("map fields have entry messages");
+            let key = example(&entry.map_entry_key_field().kind(), path);
+            let key = match key {
+                Value::String(key) if key.is_empty() => "key".to_string(),
+                Value::String(key) => key,
+                other => other.to_string(),
+            };
+            json!({ key: example(&entry.map_entry_value_field().kind(), path) })
+        } else if field.is_list() {
+            json!([example(&field.kind(), path)])
+        } else {
+            example(&field.kind(), path)
+        };
+        object.insert(field.json_name().to_string(), value);
+    }
+    path.pop();
+    Value::Object(object)
+}
+
+pub fn services(pool: &DescriptorPool) -> Vec<GrpcService> {
+    let mut services: Vec<GrpcService> = pool
+        .services()
+        .filter(|service| !service.full_name().starts_with("grpc.reflection."))
+        .map(|service| GrpcService {
+            name: service.full_name().to_string(),
+            methods: service
+                .methods()
+                .map(|method| GrpcMethod {
+                    name: method.name().to_string(),
+                    path: format!("{}/{}", service.full_name(), method.name()),
+                    input_type: method.input().full_name().to_string(),
+                    output_type: method.output().full_name().to_string(),
+                    client_streaming: method.is_client_streaming(),
+                    server_streaming: method.is_server_streaming(),
+                    request_template: template(&method.input(), &mut Vec::new()),
+                })
+                .collect(),
+        })
+        .collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    services
+}
+
+fn find_method(pool: &DescriptorPool, path: &str) -> Result<MethodDescriptor, String> {
+    let (service, method) = path
+        .trim_start_matches('/')
+        .rsplit_once(['/', '.'])
+        .ok_or_else(|| format!("{} is not a Service/Method path", path))?;
+    pool.get_service_by_name(service)
+        .and_then(|service| service.methods().find(|m| m.name() == method))
+        .ok_or_else(|| format!("Unknown gRPC method: {}", path))
+}
+
+fn response(
+    status: &Status,
+    messages: Vec<Value>,
+    headers: Vec<(String, String)>,
+    started: Instant,
+) -> GrpcResponse {
+    GrpcResponse {
+        status: status.code() as i32,
+        status_name: format!("{:?}", status.code()),
+        status_message: status.message().to_string(),
+        messages,
+        headers,
+        trailers: metadata_list(status.metadata()),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+// Sends a unary or server-streaming call. Each response message is passed to
+// `on_message` as it arrives, then returned with the rest.
+pub async fn call(
+    channel: Channel,
+    pool: &DescriptorPool,
+    call: &GrpcCall,
+    mut on_message: impl FnMut(usize, &Value),
+) -> Result<GrpcResponse, String> {
+    let method = find_method(pool, &call.method)?;
+    if method.is_client_streaming() {
+        return Err("Client-streaming methods are not supported".into());
+    }
+    let message = if call.message.is_null() {
+        DynamicMessage::new(method.input())
+    } else {
+        DynamicMessage::deserialize(method.input(), call.message.clone())
+            .map_err(|e| format!("Invalid {} message: {}", method.input().full_name(), e))?
+    };
+    let mut request = tonic::Request::new(message);
+    apply_metadata(request.metadata_mut(), &call.metadata)?;
+    if let Some(timeout) = call.timeout_ms {
+        request.set_timeout(Duration::from_millis(timeout));
+    }
+    let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
+    let path =
+        tonic::codegen::http::uri::PathAndQuery::try_from(path).map_err(|e| e.to_string())?;
+    let codec = DynamicCodec {
+        output: method.output(),
+    };
+    let started = Instant::now();
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.map_err(|e| describe(&e))?;
+    let to_json = |message: &DynamicMessage| serde_json::to_value(message).unwrap_or(Value::Null);
+    if !method.is_server_streaming() {
+        return Ok(match grpc.unary(request, path, codec).await {
+            Ok(reply) => {
+                let (headers, message, _) = reply.into_parts();
+                let message = to_json(&message);
+                on_message(0, &message);
+                response(
+                    &Status::ok(""),
+                    vec![message],
+                    metadata_list(&headers),
+                    started,
+                )
+            }
+            Err(status) => response(&status, Vec::new(), Vec::new(), started),
+        });
+    }
+    let reply = match grpc.server_streaming(request, path, codec).await {
+        Ok(reply) => reply,
+        Err(status) => return Ok(response(&status, Vec::new(), Vec::new(), started)),
+    };
+    let headers = metadata_list(reply.metadata());
+    let mut stream = reply.into_inner();
+    let mut messages = Vec::new();
+    loop {
+        match stream.message().await {
+            Ok(Some(message)) => {
+                let message = to_json(&message);
+                on_message(messages.len(), &message);
+                messages.push(message);
+            }
+            Ok(None) => break,
+            Err(status) => return Ok(response(&status, messages, headers, started)),
+        }
+    }
+    let trailers = stream.trailers().await.ok().flatten().unwrap_or_default();
+    let mut result = response(&Status::ok(""), messages, headers, started);
+    result.trailers = metadata_list(&trailers);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_services_with_request_templates() {
+        let file = proto::parse(
+            r#"
+            syntax = "proto3";
+            package shop;
+            import "google/protobuf/timestamp.proto";
+            enum Kind { KIND_UNKNOWN = 0; BOOK = 1; }
+            message Node { string id = 1; Node parent = 2; }
+            message Search {
+              string query = 1;
+              int64 limit = 2;
+              repeated Kind kinds = 3;
+              map<string, int32> boosts = 4;
+              google.protobuf.Timestamp since = 5;
+              oneof scope { string shop_id = 6; string region = 7; }
+              Node under = 8;
+            }
+            service Catalog {
+              rpc Find (Search) returns (Node);
+              rpc Stream (Search) returns (stream Node);
+            }
+            "#,
+            "shop.proto",
+        )
+        .unwrap();
+        let mut pool = DescriptorPool::global();
+        pool.add_file_descriptor_proto(file).unwrap();
+
+        let services = services(&pool);
+        assert_eq!(services.len(), 1);
+        let [find, stream] = &services[0].methods[..] else {
+            panic!("expected two methods");
+        };
+        assert_eq!(find.path, "shop.Catalog/Find");
+        assert!(stream.server_streaming);
+        assert_eq!(
+            find.request_template,
+            json!({
+                "query": "",
+                "limit": "0",
+                "kinds": ["KIND_UNKNOWN"],
+                "boosts": { "key": 0 },
+                "since": "1970-01-01T00:00:00Z",
+                "shopId": "",
+                "under": { "id": "", "parent": {} }
+            })
+        );
+        let input = find_method(&pool, "/shop.Catalog/Find").unwrap().input();
+        assert!(DynamicMessage::deserialize(input, find.request_template.clone()).is_ok());
+        assert!(find_method(&pool, "shop.Catalog.Missing").is_err());
+    }
+}
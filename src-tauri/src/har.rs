@@ -0,0 +1,583 @@
+use crate::body::BodyEncoding;
+use crate::history::HistoryEntry;
+use crate::http::{MultipartPayload, RequestInput, ResponseData};
+use crate::runner::RunSummary;
+use crate::saved::{new_id, SavedExample, SavedRequest};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Headers the HTTP stack sets itself, which would conflict with its own on replay.
+const GENERATED_HEADERS: [&str; 4] = ["content-length", "host", "connection", "transfer-encoding"];
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Har {
+    #[serde(default)]
+    pub log: Log,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Log {
+    pub version: String,
+    pub creator: Creator,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Entry {
+    pub started_date_time: String,
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    pub cache: HashMap<String, serde_json::Value>,
+    pub timings: Timings,
+    // Why no response arrived, for entries of failed requests.
+    #[serde(rename = "_error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct NameValue {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PostData {
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<PostParam>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PostParam {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Response {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub cookies: Vec<NameValue>,
+    pub headers: Vec<NameValue>,
+    pub content: Content,
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+// Phases that were not measured are -1, as HAR specifies.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Timings {
+    pub blocked: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Timings {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            ssl: -1.0,
+            send: 0.0,
+            wait: 0.0,
+            receive: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HarImport {
+    pub saved_requests: Vec<SavedRequest>,
+    pub warnings: Vec<String>,
+}
+
+fn name_values<'a>(pairs: impl Iterator<Item = (&'a String, &'a String)>) -> Vec<NameValue> {
+    let mut pairs: Vec<NameValue> = pairs
+        .map(|(name, value)| NameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.name.cmp(&b.name));
+    pairs
+}
+
+fn export_request(request: &RequestInput) -> Request {
+    let query = request.query.clone().unwrap_or_default();
+    let mut url = request.url.clone();
+    if let Ok(mut parsed) = Url::parse(&request.url) {
+        if !query.is_empty() {
+            parsed.query_pairs_mut().extend_pairs(&query);
+            url = parsed.to_string();
+        }
+    }
+    let content_type = request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.clone());
+    let post_data = if let Some(MultipartPayload { fields, files }) = &request.multipart {
+        let mut params: Vec<PostParam> = fields
+            .iter()
+            .map(|(name, value)| PostParam {
+                name: name.clone(),
+                value: Some(value.clone()),
+                file_name: None,
+            })
+            .collect();
+        params.sort_by(|a, b| a.name.cmp(&b.name));
+        params.extend(files.iter().flat_map(|file| {
+            file.paths.iter().map(|path| PostParam {
+                name: file.name.clone(),
+                value: None,
+                file_name: Some(path.clone()),
+            })
+        }));
+        Some(PostData {
+            mime_type: "multipart/form-data".into(),
+            text: None,
+            params,
+        })
+    } else if let Some(form) = &request.form {
+        Some(PostData {
+            mime_type: "application/x-www-form-urlencoded".into(),
+            text: None,
+            params: form
+                .iter()
+                .map(|(name, value)| PostParam {
+                    name: name.clone(),
+                    value: Some(value.clone()),
+                    file_name: None,
+                })
+                .collect(),
+        })
+    } else if let Some(path) = request.body_file.as_deref().filter(|p| !p.is_empty()) {
+        Some(PostData {
+            mime_type: content_type.unwrap_or_else(|| "application/octet-stream".into()),
+            text: None,
+            params: vec![PostParam {
+                name: String::new(),
+                value: None,
+                file_name: Some(path.to_string()),
+            }],
+        })
+    } else {
+        request
+            .body
+            .clone()
+            .filter(|body| !body.is_empty())
+            .map(|body| PostData {
+                mime_type: content_type.unwrap_or_else(|| "application/json".into()),
+                text: Some(body),
+                params: Vec::new(),
+            })
+    };
+    Request {
+        method: request.method.to_uppercase(),
+        url,
+        http_version: "HTTP/1.1".into(),
+        cookies: Vec::new(),
+        headers: name_values(request.headers.iter()),
+        query_string: query
+            .iter()
+            .map(|(name, value)| NameValue {
+                name: name.clone(),
+                value: value.clone(),
+            })
+            .collect(),
+        body_size: post_data
+            .as_ref()
+            .and_then(|data| data.text.as_ref())
+            .map_or(-1, |text| text.len() as i64),
+        post_data,
+        headers_size: -1,
+    }
+}
+
+fn export_response(response: &ResponseData) -> (Response, Timings) {
+    let headers: Vec<NameValue> = response
+        .header_pairs()
+        .into_iter()
+        .map(|(name, value)| NameValue { name, value })
+        .collect();
+    let (text, encoding) = match response.body_encoding {
+        BodyEncoding::Text => (Some(response.body.clone()), None),
+        BodyEncoding::Base64 => (Some(response.body.clone()), Some("base64".to_string())),
+        BodyEncoding::None => (None, None),
+    };
+    // Bodies kept in a file, or dropped from history for their size, are only partly here.
+    let partial = response.body_path.is_some()
+        || (response.body.is_empty() && response.size > 0 && text.is_some());
+    let timing = &response.timing;
+    // Connection setup is not timed apart, so it stays in `wait`.
+    let timings = Timings {
+        wait: timing.ttfb_ms,
+        receive: timing.download_ms,
+        ..Timings::default()
+    };
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value.clone())
+    };
+    let response = Response {
+        status: response.status,
+        status_text: response.status_text.clone(),
+        http_version: response.http_version.clone(),
+        cookies: Vec::new(),
+        redirect_url: header("location").unwrap_or_default(),
+        content: Content {
+            size: response.size as i64,
+            mime_type: response.content_type.clone().unwrap_or_default(),
+            text,
+            encoding,
+            comment: partial.then(|| "Body truncated; it was too large to keep in full".into()),
+        },
+        headers,
+        headers_size: -1,
+        body_size: response.encoded_size as i64,
+    };
+    (response, timings)
+}
+
+pub fn entry(
+    started: DateTime<Utc>,
+    request: &RequestInput,
+    response: Option<&ResponseData>,
+    error: Option<&str>,
+) -> Entry {
+    let (response, timings, time) = match response {
+        Some(response) => {
+            let (har, timings) = export_response(response);
+            (har, timings, response.timing.total_ms)
+        }
+        None => (Response::default(), Timings::default(), 0.0),
+    };
+    Entry {
+        started_date_time: started.to_rfc3339(),
+        time,
+        request: export_request(request),
+        response,
+        cache: HashMap::new(),
+        timings,
+        error: error.map(String::from),
+    }
+}
+
+fn log(entries: Vec<Entry>) -> Har {
+    Har {
+        log: Log {
+            version: "1.2".into(),
+            creator: Creator {
+                name: "restman".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            entries,
+        },
+    }
+}
+
+pub fn from_history(history: &[HistoryEntry]) -> Har {
+    log(history
+        .iter()
+        .map(|item| {
+            entry(
+                item.timestamp,
+                &item.request,
+                item.response.as_ref(),
+                item.error.as_deref(),
+            )
+        })
+        .collect())
+}
+
+// Steps only record their method and URL, so that is all the requests carry. Steps are
+// timed back from the end of the run, which is when the summary is exported.
+pub fn from_run(summary: &RunSummary) -> Har {
+    let mut started = Utc::now() - chrono::Duration::milliseconds(summary.elapsed_ms as i64);
+    log(summary
+        .steps
+        .iter()
+        .map(|step| {
+            let request = RequestInput {
+                method: step.method.clone(),
+                url: step.url.clone(),
+                ..RequestInput::default()
+            };
+            let entry = entry(
+                started,
+                &request,
+                step.response.as_ref(),
+                step.error.as_deref(),
+            );
+            started += chrono::Duration::milliseconds(step.elapsed_ms as i64);
+            entry
+        })
+        .collect())
+}
+
+fn import_entry(entry: &Entry, warnings: &mut Vec<String>) -> SavedRequest {
+    let request = &entry.request;
+    let url = Url::parse(&request.url).ok();
+    let name = format!(
+        "{} {}",
+        request.method,
+        url.as_ref().map_or(request.url.as_str(), |url| url.path())
+    );
+    let mut input = RequestInput {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: request
+            .headers
+            .iter()
+            .filter(|header| {
+                !header.name.starts_with(':')
+                    && !GENERATED_HEADERS.contains(&header.name.to_ascii_lowercase().as_str())
+            })
+            .map(|header| (header.name.clone(), header.value.clone()))
+            .collect(),
+        ..RequestInput::default()
+    };
+    if let Some(data) = &request.post_data {
+        let essence = data.mime_type.split(';').next().unwrap_or("").trim();
+        // A raw file body is exported as a single unnamed file parameter.
+        let body_file = match &data.params[..] {
+            [PostParam {
+                name,
+                file_name: Some(path),
+                ..
+            }] if name.is_empty() && essence != "multipart/form-data" => Some(path.clone()),
+            _ => None,
+        };
+        if data.params.is_empty() {
+            input.body = data.text.clone().filter(|text| !text.is_empty());
+        } else if body_file.is_some() {
+            input.body_file = body_file;
+        } else if essence == "multipart/form-data" {
+            input
+                .headers
+                .retain(|name, _| !name.eq_ignore_ascii_case("content-type"));
+            let mut fields = HashMap::new();
+            for param in &data.params {
+                match (&param.file_name, &param.value) {
+                    (Some(_), _) => warnings.push(format!(
+                        "{}: file field {} has no file to send",
+                        name, param.name
+                    )),
+                    (None, value) => {
+                        fields.insert(param.name.clone(), value.clone().unwrap_or_default());
+                    }
+                }
+            }
+            input.multipart = Some(MultipartPayload {
+                fields,
+                files: Vec::new(),
+            });
+        } else {
+            input.form = Some(
+                data.params
+                    .iter()
+                    .map(|param| (param.name.clone(), param.value.clone().unwrap_or_default()))
+                    .collect(),
+            );
+        }
+    }
+    let response = &entry.response;
+    let examples = (response.status > 0)
+        .then(|| SavedExample {
+            name: format!("{} {}", response.status, response.status_text)
+                .trim()
+                .to_string(),
+            status: Some(response.status),
+            headers: response
+                .headers
+                .iter()
+                .map(|header| (header.name.clone(), header.value.clone()))
+                .collect(),
+            // Binary bodies are not kept as examples.
+            body: response
+                .content
+                .text
+                .clone()
+                .filter(|_| response.content.encoding.is_none()),
+        })
+        .into_iter()
+        .collect();
+    let now = Utc::now();
+    SavedRequest {
+        id: new_id(),
+        name,
+        folder: url
+            .as_ref()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_default(),
+        request: input,
+        examples,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+// Each entry becomes a saved request in a folder named after its host, with its
+// response as an example.
+pub fn import(content: &str) -> Result<HarImport, String> {
+    let har: Har = serde_json::from_str(content).map_err(|e| format!("Not a HAR file: {}", e))?;
+    let mut warnings = Vec::new();
+    let saved_requests = har
+        .log
+        .entries
+        .iter()
+        .map(|entry| import_entry(entry, &mut warnings))
+        .collect();
+    Ok(HarImport {
+        saved_requests,
+        warnings,
+    })
+}
+
+// History entries, such as traffic captured by the proxy, become saved requests the
+// same way HAR entries do.
+pub fn import_history(history: &[HistoryEntry]) -> HarImport {
+    let mut warnings = Vec::new();
+    let saved_requests = from_history(history)
+        .log
+        .entries
+        .iter()
+        .map(|entry| import_entry(entry, &mut warnings))
+        .collect();
+    HarImport {
+        saved_requests,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timing::ResponseTiming;
+
+    #[test]
+    fn exports_history_with_timings_and_imports_it_back() {
+        let request = RequestInput {
+            method: "post".into(),
+            url: "https://api.test/items".into(),
+            query: Some(vec![("dry run".into(), "1".into())]),
+            headers: HashMap::from([("Content-Type".into(), "application/json".into())]),
+            body: Some("{\"a\": 1}".into()),
+            ..RequestInput::default()
+        };
+        let response = ResponseData {
+            status: 201,
+            status_text: "Created".into(),
+            http_version: "HTTP/1.1".into(),
+            header_list: vec![
+                ("set-cookie".into(), "a=1".into()),
+                ("set-cookie".into(), "b=2".into()),
+            ],
+            body: "{\"id\": 9}".into(),
+            content_type: Some("application/json".into()),
+            elapsed_ms: 50,
+            size: 9,
+            encoded_size: 9,
+            timing: ResponseTiming {
+                ttfb_ms: 40.0,
+                download_ms: 10.0,
+                total_ms: 50.0,
+            },
+            ..ResponseData::default()
+        };
+        let history = vec![
+            HistoryEntry::new(request.clone(), &Ok(response)),
+            HistoryEntry::new(request, &Err("connection refused".into())),
+        ];
+        let har = from_history(&history);
+        let first = &har.log.entries[0];
+        assert_eq!(first.request.url, "https://api.test/items?dry+run=1");
+        assert_eq!(first.request.method, "POST");
+        assert_eq!(first.response.headers.len(), 2);
+        assert_eq!(
+            first.timings,
+            Timings {
+                wait: 40.0,
+                receive: 10.0,
+                ..Timings::default()
+            }
+        );
+        assert_eq!(first.time, 50.0);
+        assert_eq!(
+            har.log.entries[1].error.as_deref(),
+            Some("connection refused")
+        );
+
+        let content = serde_json::to_string(&har).unwrap();
+        assert!(content.contains("\"startedDateTime\""));
+        let imported = import(&content).unwrap();
+        let saved = &imported.saved_requests[0];
+        assert_eq!(
+            (saved.name.as_str(), saved.folder.as_str()),
+            ("POST /items", "api.test")
+        );
+        assert_eq!(saved.request.body.as_deref(), Some("{\"a\": 1}"));
+        assert_eq!(saved.examples[0].body.as_deref(), Some("{\"id\": 9}"));
+        assert!(imported.saved_requests[1].examples.is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const ROW_BYTES: u64 = 16;
+pub const DEFAULT_ROWS: usize = 256;
+const MAX_ROWS: usize = 4096;
+
+// One line of a hexdump: `hex` groups the bytes in two halves of eight, `ascii` shows
+// printable bytes as themselves and the rest as `.`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HexRow {
+    pub offset: u64,
+    pub hex: String,
+    pub ascii: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HexPage {
+    pub offset: u64,
+    pub rows: Vec<HexRow>,
+    pub size: u64,
+    // Offset of the following page, if the file goes on.
+    pub next_offset: Option<u64>,
+}
+
+pub fn rows(bytes: &[u8], offset: u64) -> Vec<HexRow> {
+    bytes
+        .chunks(ROW_BYTES as usize)
+        .enumerate()
+        .map(|(index, row)| {
+            let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+            let (first, second) = hex.split_at(hex.len().min(8));
+            HexRow {
+                offset: offset + index as u64 * ROW_BYTES,
+                hex: [first.join(" "), second.join(" ")]
+                    .join("  ")
+                    .trim_end()
+                    .to_string(),
+                ascii: row
+                    .iter()
+                    .map(|b| {
+                        if b.is_ascii_graphic() || *b == b' ' {
+                            *b as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+// `offset` is rounded down to the start of its row.
+pub fn read_page(path: &Path, offset: u64, rows_wanted: usize) -> Result<HexPage, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let offset = (offset.min(size) / ROW_BYTES) * ROW_BYTES;
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    file.take(rows_wanted.clamp(1, MAX_ROWS) as u64 * ROW_BYTES)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    let end = offset + bytes.len() as u64;
+    Ok(HexPage {
+        offset,
+        rows: rows(&bytes, offset),
+        size,
+        next_offset: (end < size).then_some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dumps_rows_with_hex_and_ascii_columns() {
+        let bytes: Vec<u8> = (0x3c..0x3c + 20).collect();
+        let dumped = rows(&bytes, 32);
+        assert_eq!(dumped.len(), 2);
+        assert_eq!(
+            dumped[0].hex,
+            "3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b"
+        );
+        assert_eq!(dumped[0].ascii, "<=>?@ABCDEFGHIJK");
+        assert_eq!(dumped[1].offset, 48);
+        assert_eq!(dumped[1].hex, "4c 4d 4e 4f");
+
+        let path = std::env::temp_dir().join(format!("restman-hex-{}", std::process::id()));
+        std::fs::write(&path, [0u8, 0x89, b'P', b'N', b'G', b'\n'].repeat(10)).unwrap();
+        let page = read_page(&path, 20, 2).unwrap();
+        assert_eq!(page.offset, 16);
+        assert_eq!(page.rows[0].ascii, "G...PNG...PNG...");
+        assert_eq!(page.next_offset, Some(48));
+        let last = read_page(&path, 48, 2).unwrap();
+        assert_eq!(last.rows.len(), 1);
+        assert_eq!(last.next_offset, None);
+        let _ = std::fs::remove_file(&path);
+    }
+}
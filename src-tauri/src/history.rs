@@ -0,0 +1,109 @@
+use crate::http::{RequestInput, ResponseData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Response bodies above this size are not kept in history; the rest of the response
+// still is.
+pub const HISTORY_BODY_LIMIT: usize = 256 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub request: RequestInput,
+    pub response: Option<ResponseData>,
+    // Set instead of `response` when the request failed or was cancelled.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct HistoryFilter {
+    // Words matched against the method, URL, headers and bodies.
+    pub text: Option<String>,
+    pub collection: Option<String>,
+    pub status_min: Option<u16>,
+    pub status_max: Option<u16>,
+    // Only requests that got no response at all.
+    pub failed: bool,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl HistoryEntry {
+    pub fn new(request: RequestInput, result: &Result<ResponseData, String>) -> Self {
+        let (response, error) = match result {
+            Ok(response) => {
+                let mut response = response.clone();
+                if response.body.len() > HISTORY_BODY_LIMIT {
+                    response.body.clear();
+                }
+                (Some(response), None)
+            }
+            Err(error) => (None, Some(error.clone())),
+        };
+        HistoryEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            request,
+            response,
+            error,
+        }
+    }
+
+    // The text indexed for search: request line, headers and both bodies.
+    pub fn search_columns(&self) -> [String; 4] {
+        let headers = |headers: Vec<String>| headers.join("\n");
+        let request_headers = self
+            .request
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value));
+        let response_headers = self.response.iter().flat_map(|response| {
+            response.headers.iter().flat_map(|(name, values)| {
+                values
+                    .iter()
+                    .map(move |value| format!("{}: {}", name, value))
+            })
+        });
+        [
+            format!("{} {}", self.request.method, self.request.url),
+            headers(request_headers.chain(response_headers).collect()),
+            self.request.body.clone().unwrap_or_default(),
+            self.response
+                .as_ref()
+                .map(|response| response.body.clone())
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+// Turns free text into an FTS5 query that matches entries containing every word, each
+// as a prefix. Words are quoted so operators and punctuation in URLs stay literal.
+pub fn search_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_search_words_as_prefixes() {
+        assert_eq!(
+            search_query(" users  api.test/v1 ").as_deref(),
+            Some("\"users\"* \"api.test/v1\"*")
+        );
+        assert_eq!(
+            search_query("say \"hi\"").as_deref(),
+            Some("\"say\"* \"\"\"hi\"\"\"*")
+        );
+        assert_eq!(search_query("   "), None);
+    }
+}
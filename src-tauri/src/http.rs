@@ -0,0 +1,999 @@
+use crate::assertions::{Assertion, AssertionResult};
+use crate::auth::Auth;
+use crate::body::{collect_body, BodyEncoding};
+use crate::checksum::{ChecksumAlgorithm, Hasher};
+use crate::client::{describe_send_error, version_label, HttpVersion};
+use crate::contract::{ContractReport, RequestValidation};
+use crate::cookies::{record_cookies, unstored_cookies, SetCookie};
+use crate::decompress::{decode_stream, parse_content_coding, ContentCoding, ACCEPT_ENCODING};
+use crate::digest::{new_cnonce, DigestChallenge};
+use crate::extract::{Extracted, Extraction};
+use crate::graphql::GraphqlQuery;
+use crate::jsonrpc::{JsonRpcOutcome, JsonRpcRequest};
+use crate::ntlm::{NtlmCredentials, NtlmHandshake, NtlmTarget};
+use crate::params::ParameterValue;
+use crate::ratelimit::{parse_headers, RateLimitInfo, Throttle};
+use crate::retry::{AttemptRecord, RetryPolicy};
+use crate::scripting::ScriptReport;
+use crate::security::SecurityRequirement;
+use crate::signing;
+use crate::sigv4;
+use crate::soap;
+use crate::timing::{millis, ResponseTiming};
+use chrono::Utc;
+use futures_util::StreamExt;
+use reqwest::header::HeaderValue;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::time::{sleep, Instant};
+use tokio_util::io::ReaderStream;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MultipartFile {
+    pub name: String,
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MultipartPayload {
+    pub fields: HashMap<String, String>,
+    pub files: Vec<MultipartFile>,
+}
+
+// A request as the user composed it, before parameters, auth and cookies are applied.
+// History and saved requests keep this form so sending them again picks up current
+// credentials.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RequestInput {
+    pub method: String,
+    pub url: String,
+    pub query: Option<Vec<(String, String)>>,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+    pub multipart: Option<MultipartPayload>,
+    pub form: Option<Vec<(String, String)>>,
+    pub body_file: Option<String>,
+    // Sent as the JSON body of a POST in place of `body`.
+    pub graphql: Option<GraphqlQuery>,
+    // Likewise for a JSON-RPC call or batch; replies are parsed into `ResponseData::jsonrpc`.
+    pub jsonrpc: Option<JsonRpcRequest>,
+    pub http_version: Option<HttpVersion>,
+    pub collection: Option<String>,
+    pub accept_invalid_certs: Option<bool>,
+    pub retry: Option<RetryPolicy>,
+    pub decompress: Option<bool>,
+    pub cookie_jar: Option<String>,
+    pub auth: Option<Auth>,
+    pub security: Option<Vec<SecurityRequirement>>,
+    pub params: Option<Vec<ParameterValue>>,
+    // Overrides the workspace's active environment for this request.
+    pub environment: Option<String>,
+    // Values to copy from the response into environment variables.
+    pub extract: Option<Vec<Extraction>>,
+    // Checks run against the response; results come back in `ResponseData::assertions`.
+    pub assertions: Option<Vec<Assertion>>,
+    // Rhai scripts run before the request is sent and after the response arrives.
+    pub pre_request_script: Option<String>,
+    pub post_response_script: Option<String>,
+    // Overrides `Settings::request_validation` for this request.
+    pub request_validation: Option<RequestValidation>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestSpec {
+    pub method: String,
+    pub url: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+    pub multipart: Option<MultipartPayload>,
+    pub form: Option<Vec<(String, String)>>,
+    pub body_file: Option<String>,
+    pub decompress: bool,
+    // Text bodies larger than this many bytes are written to a file.
+    pub spill_threshold: u64,
+    pub auth: Option<Auth>,
+    pub proxy_ntlm: Option<NtlmCredentials>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ResponseProgress {
+    pub request_id: Option<String>,
+    pub received: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct UploadProgress {
+    pub request_id: Option<String>,
+    pub sent: u64,
+    pub total: u64,
+}
+
+pub type UploadCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ResponseData {
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub headers: HashMap<String, Vec<String>>,
+    // Every header as it arrived, in wire order and case with repeats. Only filled where
+    // the raw head is read, as for proxy captures: the HTTP client keeps neither order
+    // nor case, so sent requests leave it empty and only fill `headers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub header_list: Vec<(String, String)>,
+    pub body: String,
+    pub body_encoding: BodyEncoding,
+    pub body_path: Option<String>,
+    pub content_type: Option<String>,
+    pub elapsed_ms: u64,
+    pub size: u64,
+    pub encoded_size: u64,
+    pub content_encoding: Option<String>,
+    pub decompressed: bool,
+    pub timing: ResponseTiming,
+    pub attempts: Vec<AttemptRecord>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extracted: Vec<Extracted>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<AssertionResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<ScriptReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cookies: Vec<SetCookie>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jsonrpc: Vec<JsonRpcOutcome>,
+    // Set when the request was sent to an operation of an OpenAPI collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<ContractReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitInfo>,
+}
+
+pub fn collect_headers(headers: &reqwest::header::HeaderMap) -> HashMap<String, Vec<String>> {
+    let mut collected: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, value) in headers.iter() {
+        collected
+            .entry(name.as_str().to_string())
+            .or_default()
+            .push(String::from_utf8_lossy(value.as_bytes()).into_owned());
+    }
+    collected
+}
+
+impl ResponseData {
+    // `header_list` when the wire order is known, otherwise `headers` sorted by name.
+    pub fn header_pairs(&self) -> Vec<(String, String)> {
+        if !self.header_list.is_empty() {
+            return self.header_list.clone();
+        }
+        let mut pairs: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .flat_map(|(name, values)| {
+                values
+                    .iter()
+                    .map(move |value| (name.clone(), value.clone()))
+            })
+            .collect();
+        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        pairs
+    }
+}
+
+const STANDARD_METHODS: [&str; 9] = [
+    "GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS", "CONNECT", "TRACE",
+];
+
+// Standard methods are matched case-insensitively; custom verbs are case-sensitive
+// tokens and are sent exactly as typed.
+pub fn parse_method(method: &str) -> Result<reqwest::Method, String> {
+    let trimmed = method.trim();
+    if trimmed.is_empty() {
+        return Err("Invalid method".into());
+    }
+    let standard = STANDARD_METHODS
+        .iter()
+        .find(|standard| standard.eq_ignore_ascii_case(trimmed));
+    reqwest::Method::from_bytes(standard.copied().unwrap_or(trimmed).as_bytes())
+        .map_err(|_| format!("Invalid method: {}", method))
+}
+
+fn has_header(headers: &HashMap<String, String>, name: &str) -> bool {
+    headers.keys().any(|key| key.eq_ignore_ascii_case(name))
+}
+
+async fn open_with_length(path: &str) -> Result<(File, u64), String> {
+    let file_handle = File::open(path)
+        .await
+        .map_err(|e| format!("Cannot open {}: {}", path, e))?;
+    let length = file_handle
+        .metadata()
+        .await
+        .map_err(|e| e.to_string())?
+        .len();
+    Ok((file_handle, length))
+}
+
+// Shares one byte counter across every file streamed in a request.
+fn tracked_body(
+    file_handle: File,
+    sent: &Arc<AtomicU64>,
+    total: u64,
+    on_upload: Option<&UploadCallback>,
+) -> reqwest::Body {
+    let callback = match on_upload {
+        Some(callback) => callback.clone(),
+        None => return reqwest::Body::from(file_handle),
+    };
+    let sent = sent.clone();
+    let stream = ReaderStream::new(file_handle).inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            let len = bytes.len() as u64;
+            callback(sent.fetch_add(len, Ordering::Relaxed) + len, total);
+        }
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+// Builds a fresh request for every attempt because multipart file streams can only be
+// consumed once.
+async fn build_request(
+    client: &Client,
+    method: reqwest::Method,
+    spec: &RequestSpec,
+    on_upload: Option<&UploadCallback>,
+) -> Result<reqwest::RequestBuilder, String> {
+    let sent = Arc::new(AtomicU64::new(0));
+    let mut request_builder = client.request(method, &spec.url);
+    if !spec.query.is_empty() {
+        request_builder = request_builder.query(&spec.query);
+    }
+    let mut final_headers = spec.headers.clone();
+    if let Some(auth) = &spec.auth {
+        auth.apply_headers(&mut final_headers);
+        if let Some(pair) = auth.query_pair() {
+            request_builder = request_builder.query(&[pair]);
+        }
+    }
+    if spec.multipart.is_some() || spec.form.is_some() {
+        final_headers.retain(|key, _| !key.eq_ignore_ascii_case("content-type"));
+    } else if let Some(body) = spec
+        .body
+        .as_deref()
+        .filter(|_| spec.body_file.as_deref().is_none_or(str::is_empty))
+    {
+        soap::prepare_headers(body, &mut final_headers);
+    }
+    for (key, value) in &final_headers {
+        request_builder = request_builder.header(key, value);
+    }
+    if spec.decompress && !has_header(&final_headers, "accept-encoding") {
+        request_builder = request_builder.header(reqwest::header::ACCEPT_ENCODING, ACCEPT_ENCODING);
+    }
+    if let Some(payload) = &spec.multipart {
+        let mut form = reqwest::multipart::Form::new();
+        for (key, value) in &payload.fields {
+            if !value.is_empty() {
+                form = form.text(key.clone(), value.clone());
+            }
+        }
+        let mut opened = Vec::new();
+        for file in &payload.files {
+            for path in &file.paths {
+                if path.is_empty() {
+                    continue;
+                }
+                let filename = Path::new(path)
+                    .file_name()
+                    .and_then(|value| value.to_str())
+                    .unwrap_or("file")
+                    .to_string();
+                let (file_handle, length) = open_with_length(path).await?;
+                opened.push((file.name.clone(), filename, file_handle, length));
+            }
+        }
+        let total = opened.iter().map(|(_, _, _, length)| length).sum();
+        for (name, filename, file_handle, length) in opened {
+            let body = tracked_body(file_handle, &sent, total, on_upload);
+            let part =
+                reqwest::multipart::Part::stream_with_length(body, length).file_name(filename);
+            form = form.part(name, part);
+        }
+        request_builder = request_builder.multipart(form);
+    } else if let Some(fields) = &spec.form {
+        request_builder = request_builder.form(fields);
+    } else if let Some(path) = spec.body_file.as_deref().filter(|p| !p.is_empty()) {
+        let (file_handle, length) = open_with_length(path).await?;
+        request_builder = request_builder
+            .header(reqwest::header::CONTENT_LENGTH, length)
+            .body(tracked_body(file_handle, &sent, length, on_upload));
+        if !has_header(&final_headers, "content-type") {
+            request_builder = request_builder.header("Content-Type", "application/octet-stream");
+        }
+    } else if let Some(b) = &spec.body {
+        if !b.is_empty() {
+            request_builder = request_builder.body(b.clone());
+            if !has_header(&final_headers, "content-type") {
+                request_builder = request_builder.header("Content-Type", "application/json");
+            }
+        }
+    }
+    Ok(request_builder)
+}
+
+// File bodies are hashed from disk; multipart bodies are generated while streaming and
+// go out unsigned.
+async fn payload_hash(request: &reqwest::Request, spec: &RequestSpec) -> Result<String, String> {
+    if let Some(bytes) = request.body().and_then(|body| body.as_bytes()) {
+        return Ok(sigv4::sha256_hex(bytes));
+    }
+    if request.body().is_none() {
+        return Ok(sigv4::sha256_hex(b""));
+    }
+    if spec.multipart.is_none() {
+        if let Some(path) = spec.body_file.as_deref().filter(|p| !p.is_empty()) {
+            let mut hasher = Hasher::new(ChecksumAlgorithm::Sha256);
+            hasher.update_from_file(Path::new(path), None).await?;
+            return Ok(hasher.finish());
+        }
+    }
+    Ok(sigv4::UNSIGNED_PAYLOAD.to_string())
+}
+
+// Signatures over the body need it in memory; file bodies are read back from disk and
+// streamed multipart bodies are not available.
+async fn body_bytes(
+    request: &reqwest::Request,
+    spec: &RequestSpec,
+) -> Result<Option<Vec<u8>>, String> {
+    match request.body() {
+        None => Ok(Some(Vec::new())),
+        Some(body) => match body.as_bytes() {
+            Some(bytes) => Ok(Some(bytes.to_vec())),
+            None if spec.multipart.is_none() => match spec.body_file.as_deref() {
+                Some(path) => tokio::fs::read(path)
+                    .await
+                    .map(Some)
+                    .map_err(|e| format!("Cannot read {}: {}", path, e)),
+                None => Ok(None),
+            },
+            None => Ok(None),
+        },
+    }
+}
+
+pub async fn execute_request<F>(
+    client: Client,
+    spec: RequestSpec,
+    retry: RetryPolicy,
+    throttle: Option<Throttle>,
+    on_upload: Option<UploadCallback>,
+    mut on_progress: F,
+) -> Result<ResponseData, String>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let req_method = parse_method(&spec.method)?;
+    let max_attempts = retry.attempts();
+    let mut attempts: Vec<AttemptRecord> = Vec::new();
+    let mut attempt = 0;
+    // Digest challenges are answered without using up retry attempts.
+    let mut handshakes = 0;
+    let mut digest: Option<(DigestChallenge, u32)> = None;
+    let mut digest_refreshed = false;
+    let cnonce = new_cnonce();
+    let mut server_ntlm = match &spec.auth {
+        Some(Auth::Ntlm(credentials)) => {
+            Some((NtlmHandshake::new(NtlmTarget::Server), credentials))
+        }
+        _ => None,
+    };
+    let mut proxy_ntlm = spec
+        .proxy_ntlm
+        .as_ref()
+        .map(|credentials| (NtlmHandshake::new(NtlmTarget::Proxy), credentials));
+    // Cookies stored along the way, from challenges and redirects as well.
+    let mut cookies = Vec::new();
+    loop {
+        attempt += 1;
+        let last_attempt = attempt >= max_attempts + handshakes;
+        // Every attempt takes a token, retries and authentication round trips included.
+        if let Some(throttle) = &throttle {
+            throttle.acquire(&spec.url).await;
+        }
+        let request_builder =
+            build_request(&client, req_method.clone(), &spec, on_upload.as_ref()).await?;
+        let mut request = request_builder.build().map_err(|e| e.to_string())?;
+        if let (Some(Auth::Digest { username, password }), Some((challenge, nc))) =
+            (&spec.auth, digest.as_mut())
+        {
+            *nc += 1;
+            let url = request.url();
+            let uri = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let value = challenge.authorization(
+                username,
+                password,
+                request.method().as_str(),
+                &uri,
+                *nc,
+                &cnonce,
+            );
+            request.headers_mut().insert(
+                reqwest::header::AUTHORIZATION,
+                HeaderValue::from_str(&value).map_err(|e| e.to_string())?,
+            );
+        }
+        for (handshake, _) in server_ntlm.iter_mut().chain(proxy_ntlm.iter_mut()) {
+            if let Some((name, value)) = handshake.take_header()? {
+                request.headers_mut().insert(name, value);
+            }
+        }
+        match &spec.auth {
+            Some(Auth::AwsSigV4(credentials)) => {
+                let hash = payload_hash(&request, &spec).await?;
+                sigv4::sign(&mut request, credentials, &hash, Utc::now())?;
+            }
+            Some(Auth::Hawk(credentials)) => {
+                let body = body_bytes(&request, &spec).await?;
+                let (now, nonce) = (Utc::now().timestamp(), signing::new_nonce());
+                signing::sign_hawk(&mut request, credentials, body.as_deref(), now, &nonce)?;
+            }
+            Some(Auth::Hmac(config)) => {
+                let body = body_bytes(&request, &spec).await?;
+                let (now, nonce) = (Utc::now().timestamp(), signing::new_nonce());
+                signing::sign_hmac(&mut request, config, body.as_deref(), now, &nonce)?;
+            }
+            _ => {}
+        }
+        let started = Instant::now();
+        let (result, stored) = record_cookies(client.execute(request)).await;
+        cookies.extend(stored);
+        match result {
+            Err(err) => {
+                let message = describe_send_error(&err);
+                let retryable =
+                    retry.retry_on_network_error && (err.is_connect() || err.is_timeout());
+                if last_attempt || !retryable {
+                    if attempt > 1 {
+                        return Err(format!("{} (after {} attempts)", message, attempt));
+                    }
+                    return Err(message);
+                }
+                let delay = retry.delay_for(attempt, None);
+                attempts.push(AttemptRecord {
+                    attempt,
+                    status: None,
+                    error: Some(message),
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    delay_ms: Some(delay.as_millis() as u64),
+                });
+                sleep(delay).await;
+            }
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let answered = match (status, &spec.auth) {
+                    (401, Some(Auth::Digest { .. })) => {
+                        // Answer the first challenge, and one more if the server reports
+                        // the nonce as stale.
+                        let challenge = DigestChallenge::from_headers(response.headers())
+                            .filter(|c| digest.is_none() || (c.stale && !digest_refreshed));
+                        let answered = challenge.is_some();
+                        if let Some(challenge) = challenge {
+                            digest_refreshed = digest.is_some();
+                            digest = Some((challenge, 0));
+                        }
+                        answered
+                    }
+                    (401, _) | (407, _) => {
+                        let ntlm = if status == 401 {
+                            server_ntlm.as_mut()
+                        } else {
+                            proxy_ntlm.as_mut()
+                        };
+                        match ntlm {
+                            Some((handshake, credentials)) => {
+                                handshake.advance(response.headers(), credentials)?
+                            }
+                            None => false,
+                        }
+                    }
+                    _ => false,
+                };
+                if answered {
+                    attempts.push(AttemptRecord {
+                        attempt,
+                        status: Some(status),
+                        error: None,
+                        elapsed_ms: started.elapsed().as_millis() as u64,
+                        delay_ms: Some(0),
+                    });
+                    handshakes += 1;
+                    // Read the body so the connection goes back to the pool for the
+                    // next step of a connection-bound handshake.
+                    let _ = response.bytes().await;
+                    continue;
+                }
+                if !last_attempt && retry.retries_status(status) {
+                    let server_delay =
+                        parse_headers(&collect_headers(response.headers()), Utc::now())
+                            .and_then(|info| info.delay());
+                    let delay = retry.delay_for(attempt, server_delay);
+                    attempts.push(AttemptRecord {
+                        attempt,
+                        status: Some(status),
+                        error: None,
+                        elapsed_ms: started.elapsed().as_millis() as u64,
+                        delay_ms: Some(delay.as_millis() as u64),
+                    });
+                    drop(response);
+                    sleep(delay).await;
+                    continue;
+                }
+                attempts.push(AttemptRecord {
+                    attempt,
+                    status: Some(status),
+                    error: None,
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    delay_ms: None,
+                });
+                if cookies.is_empty() {
+                    cookies = unstored_cookies(response.headers(), response.url());
+                }
+                let mut data = read_response(
+                    response,
+                    spec.decompress,
+                    spec.spill_threshold,
+                    started,
+                    attempts,
+                    &mut on_progress,
+                )
+                .await?;
+                data.cookies = cookies;
+                return Ok(data);
+            }
+        }
+    }
+}
+
+async fn read_response<F>(
+    response: reqwest::Response,
+    decompress: bool,
+    spill_threshold: u64,
+    started: Instant,
+    attempts: Vec<AttemptRecord>,
+    on_progress: &mut F,
+) -> Result<ResponseData, String>
+where
+    F: FnMut(u64, Option<u64>),
+{
+    let ttfb = started.elapsed();
+    let status = response.status();
+    let version = version_label(response.version()).to_string();
+    let headers = collect_headers(response.headers());
+    let rate_limit = parse_headers(&headers, Utc::now());
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let content_encoding = response
+        .headers()
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    // Unknown or stacked codings are returned as received.
+    let coding = parse_content_coding(content_encoding.as_deref()).filter(|_| decompress);
+    let total = response.content_length();
+    let encoded = Arc::new(AtomicU64::new(0));
+    let collected = collect_body(
+        decode_stream(response.bytes_stream(), coding, encoded.clone()),
+        content_type.as_deref(),
+        spill_threshold,
+        |_| on_progress(encoded.load(Ordering::Relaxed), total),
+    )
+    .await?;
+    let elapsed = started.elapsed();
+    let timing = ResponseTiming {
+        ttfb_ms: millis(ttfb),
+        download_ms: millis(elapsed - ttfb),
+        total_ms: millis(elapsed),
+    };
+
+    Ok(ResponseData {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        http_version: version,
+        headers,
+        header_list: Vec::new(),
+        body: collected.body,
+        body_encoding: collected.encoding,
+        body_path: collected.path,
+        content_type,
+        elapsed_ms: elapsed.as_millis() as u64,
+        size: collected.size,
+        encoded_size: encoded.load(Ordering::Relaxed),
+        content_encoding,
+        decompressed: matches!(coding, Some(c) if c != ContentCoding::Identity),
+        timing,
+        attempts,
+        extracted: Vec::new(),
+        assertions: Vec::new(),
+        scripts: None,
+        cookies: Vec::new(),
+        jsonrpc: Vec::new(),
+        contract: None,
+        rate_limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::DEFAULT_SPILL_THRESHOLD;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn collect_headers_keeps_repeated_values() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=2".parse().unwrap());
+        headers.insert("content-type", "application/json".parse().unwrap());
+        let collected = collect_headers(&headers);
+        assert_eq!(collected["set-cookie"], vec!["a=1", "b=2"]);
+        assert_eq!(collected["content-type"], vec!["application/json"]);
+        let response = ResponseData {
+            headers: collected,
+            ..ResponseData::default()
+        };
+        let pair = |name: &str, value: &str| (name.to_string(), value.to_string());
+        assert_eq!(
+            response.header_pairs(),
+            vec![
+                pair("content-type", "application/json"),
+                pair("set-cookie", "a=1"),
+                pair("set-cookie", "b=2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_method_accepts_standard_and_custom_verbs() {
+        assert_eq!(parse_method("patch").unwrap(), reqwest::Method::PATCH);
+        assert_eq!(parse_method("HEAD").unwrap(), reqwest::Method::HEAD);
+        assert_eq!(parse_method(" options ").unwrap(), reqwest::Method::OPTIONS);
+        assert_eq!(parse_method("PROPFIND").unwrap().as_str(), "PROPFIND");
+        assert_eq!(parse_method("purge").unwrap().as_str(), "purge");
+        assert!(parse_method("").is_err());
+        assert!(parse_method("BAD VERB").is_err());
+    }
+
+    #[tokio::test]
+    async fn form_payload_is_url_encoded() {
+        let spec = RequestSpec {
+            method: "POST".into(),
+            url: "http://localhost/login".into(),
+            query: Vec::new(),
+            headers: HashMap::from([("Content-Type".to_string(), "text/plain".to_string())]),
+            body: Some("ignored".into()),
+            multipart: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+            form: Some(vec![
+                ("user".into(), "a b&c".into()),
+                ("tag".into(), "x".into()),
+                ("tag".into(), "ü=1".into()),
+            ]),
+        };
+        let request = build_request(&Client::new(), reqwest::Method::POST, &spec, None)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.headers()[reqwest::header::CONTENT_TYPE],
+            "application/x-www-form-urlencoded"
+        );
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"user=a+b%26c&tag=x&tag=%C3%BC%3D1");
+    }
+
+    #[tokio::test]
+    async fn query_pairs_are_percent_encoded_and_keep_repeats() {
+        let spec = RequestSpec {
+            method: "GET".into(),
+            url: "http://localhost/search?page=1".into(),
+            query: vec![
+                ("q".into(), "a b/c?".into()),
+                ("tag".into(), "x".into()),
+                ("tag".into(), "y&z".into()),
+            ],
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+        };
+        let request = build_request(&Client::new(), reqwest::Method::GET, &spec, None)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().query(),
+            Some("page=1&q=a+b%2Fc%3F&tag=x&tag=y%26z")
+        );
+    }
+
+    #[tokio::test]
+    async fn body_file_sets_length_and_default_content_type() {
+        let path = std::env::temp_dir().join(format!("restman-body-{}.bin", std::process::id()));
+        tokio::fs::write(&path, [0u8, 1, 2, 3, 255]).await.unwrap();
+        let spec = RequestSpec {
+            method: "PUT".into(),
+            url: "http://localhost/upload".into(),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: Some(path.to_string_lossy().into_owned()),
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+        };
+        let request = build_request(&Client::new(), reqwest::Method::PUT, &spec, None)
+            .await
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[reqwest::header::CONTENT_LENGTH], "5");
+        assert_eq!(
+            request.headers()[reqwest::header::CONTENT_TYPE],
+            "application/octet-stream"
+        );
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn body_file_upload_reports_progress() {
+        let path = std::env::temp_dir().join(format!("restman-upload-{}.bin", std::process::id()));
+        tokio::fs::write(&path, vec![7u8; 100_000]).await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 16384];
+            while !received.ends_with(&[7u8; 64]) || received.len() < 100_000 {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => received.extend_from_slice(&buf[..n]),
+                }
+            }
+            let _ = socket
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .await;
+        });
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let callback: UploadCallback = Arc::new(move |sent, total| {
+            sink.lock().unwrap().push((sent, total));
+        });
+        let spec = RequestSpec {
+            method: "PUT".into(),
+            url: format!("http://{}/upload", addr),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: Some(path.to_string_lossy().into_owned()),
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+        };
+        let response = build_request(&Client::new(), reqwest::Method::PUT, &spec, Some(&callback))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 204);
+        let _ = tokio::fs::remove_file(&path).await;
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.last(), Some(&(100_000, 100_000)));
+        assert!(reports.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[tokio::test]
+    async fn execute_request_retries_on_configured_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let replies = [
+                "HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            ];
+            let mut replies = replies.iter();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                match replies.next() {
+                    Some(reply) => {
+                        let _ = socket.write_all(reply.as_bytes()).await;
+                    }
+                    None => break,
+                }
+            }
+        });
+        let spec = RequestSpec {
+            method: "GET".into(),
+            url: format!("http://{}/", addr),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+        };
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff_base_ms: 1,
+            ..RetryPolicy::default()
+        };
+        let response = execute_request(Client::new(), spec, policy, None, None, |_, _| {})
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "ok");
+        let statuses: Vec<Option<u16>> = response.attempts.iter().map(|a| a.status).collect();
+        assert_eq!(statuses, vec![Some(503), Some(200)]);
+    }
+
+    #[tokio::test]
+    async fn execute_request_answers_digest_challenge() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let requests = seen.clone();
+        tokio::spawn(async move {
+            let replies = [
+                "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"device\", qop=\"auth\", nonce=\"abc\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            ];
+            let mut replies = replies.iter();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                requests
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).to_string());
+                match replies.next() {
+                    Some(reply) => {
+                        let _ = socket.write_all(reply.as_bytes()).await;
+                    }
+                    None => break,
+                }
+            }
+        });
+        let spec = RequestSpec {
+            method: "GET".into(),
+            url: format!("http://{}/status?verbose=1", addr),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: Some(Auth::Digest {
+                username: "admin".into(),
+                password: "secret".into(),
+            }),
+            proxy_ntlm: None,
+        };
+        let response = execute_request(
+            Client::new(),
+            spec,
+            RetryPolicy::default(),
+            None,
+            None,
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.attempts.len(), 2);
+        let seen = seen.lock().unwrap();
+        assert!(!seen[0].to_ascii_lowercase().contains("authorization:"));
+        assert!(seen[1].contains(r#"uri="/status?verbose=1""#));
+        assert!(seen[1].contains("nc=00000001"));
+    }
+
+    #[tokio::test]
+    async fn execute_request_reports_cookies_stored_on_every_hop() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let replies = [
+                "HTTP/1.1 302 Found\r\nLocation: /home\r\nSet-Cookie: hop=1; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nSet-Cookie: final=2\r\nSet-Cookie: stolen=3; Domain=other.test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ];
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+        let jar = Arc::new(crate::cookies::CookieJar::default());
+        let client = Client::builder()
+            .cookie_provider(jar.clone())
+            .build()
+            .unwrap();
+        let spec = RequestSpec {
+            method: "GET".into(),
+            url: format!("http://{}/login", addr),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: None,
+            multipart: None,
+            form: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: None,
+            proxy_ntlm: None,
+        };
+        let response = execute_request(client, spec, RetryPolicy::default(), None, None, |_, _| {})
+            .await
+            .unwrap();
+        let cookies: Vec<(&str, &str, bool)> = response
+            .cookies
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.url.rsplit('/').next().unwrap(),
+                    c.accepted,
+                )
+            })
+            .collect();
+        assert_eq!(
+            cookies,
+            vec![
+                ("hop", "login", true),
+                ("final", "home", true),
+                ("stolen", "home", false)
+            ]
+        );
+        assert_eq!(jar.list(None).len(), 2);
+    }
+}
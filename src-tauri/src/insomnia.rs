@@ -0,0 +1,628 @@
+use crate::auth::{ApiKeyLocation, Auth};
+use crate::environments::{Environment, Variable};
+use crate::http::{MultipartFile, MultipartPayload, RequestInput};
+use crate::ntlm::NtlmCredentials;
+use crate::postman::flatten_params;
+use crate::saved::{new_id, CollectionImport, SavedRequest};
+use crate::signing::{HawkCredentials, HmacAlgorithm};
+use crate::sigv4::AwsCredentials;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// Insomnia writes variables as `{{ _.name }}`; dynamic `{{$...}}` values have no
+// equivalent and are kept as they are.
+fn template(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + end].trim();
+        result.push_str(&rest[..start]);
+        if name.is_empty() || name.starts_with('$') || name.starts_with("_.") {
+            result.push_str(&rest[start..start + end + 2]);
+        } else {
+            result.push_str(&format!("{{{{ _.{} }}}}", name));
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn pairs<'a>(pairs: impl Iterator<Item = (&'a String, &'a String)>) -> Vec<Value> {
+    pairs
+        .map(|(name, value)| json!({ "name": template(name), "value": template(value) }))
+        .collect()
+}
+
+// As for Postman, auth resolved at send time and generic HMAC signing are left out.
+fn export_auth(auth: &Auth) -> Option<Value> {
+    Some(match auth {
+        Auth::Basic { username, password } => json!({
+            "type": "basic",
+            "username": template(username),
+            "password": template(password),
+        }),
+        Auth::Bearer { token } => json!({ "type": "bearer", "token": template(token) }),
+        Auth::ApiKey {
+            name,
+            value,
+            location,
+        } => json!({
+            "type": "apikey",
+            "key": template(name),
+            "value": template(value),
+            "addTo": match location {
+                ApiKeyLocation::Header => "header",
+                ApiKeyLocation::Query => "queryParams",
+                ApiKeyLocation::Cookie => "cookie",
+            },
+        }),
+        Auth::Digest { username, password } => json!({
+            "type": "digest",
+            "username": template(username),
+            "password": template(password),
+        }),
+        Auth::Ntlm(credentials) => json!({
+            "type": "ntlm",
+            "username": template(&credentials.username),
+            "password": template(&credentials.password),
+        }),
+        Auth::AwsSigV4(credentials) => json!({
+            "type": "iam",
+            "accessKeyId": template(&credentials.access_key_id),
+            "secretAccessKey": template(&credentials.secret_access_key),
+            "sessionToken": template(credentials.session_token.as_deref().unwrap_or_default()),
+            "region": template(&credentials.region),
+            "service": template(&credentials.service),
+        }),
+        Auth::Hawk(credentials) => json!({
+            "type": "hawk",
+            "id": template(&credentials.id),
+            "key": template(&credentials.key),
+            "algorithm": match credentials.algorithm {
+                HmacAlgorithm::Sha1 => "sha1",
+                _ => "sha256",
+            },
+            "ext": template(credentials.ext.as_deref().unwrap_or_default()),
+        }),
+        _ => return None,
+    })
+}
+
+fn export_request(saved: &SavedRequest, parent: &str) -> Value {
+    let request = flatten_params(&saved.request);
+    let content_type = request
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.clone());
+    let mut headers: Vec<(&String, &String)> = request.headers.iter().collect();
+    headers.sort();
+    let body = if let Some(payload) = &request.multipart {
+        let mut fields: Vec<(&String, &String)> = payload.fields.iter().collect();
+        fields.sort();
+        let mut params = pairs(fields.into_iter());
+        for file in &payload.files {
+            params.extend(
+                file.paths
+                    .iter()
+                    .map(|path| json!({ "name": file.name, "type": "file", "fileName": path })),
+            );
+        }
+        json!({ "mimeType": "multipart/form-data", "params": params })
+    } else if let Some(form) = &request.form {
+        json!({
+            "mimeType": "application/x-www-form-urlencoded",
+            "params": pairs(form.iter().map(|(key, value)| (key, value))),
+        })
+    } else if let Some(path) = request.body_file.as_deref().filter(|p| !p.is_empty()) {
+        json!({
+            "mimeType": content_type.unwrap_or_else(|| "application/octet-stream".into()),
+            "fileName": path,
+        })
+    } else if let Some(body) = request.body.as_deref().filter(|b| !b.is_empty()) {
+        // Bodies without a content type go out as JSON.
+        let mime_type = content_type.unwrap_or_else(|| "application/json".into());
+        json!({ "mimeType": mime_type, "text": template(body) })
+    } else {
+        json!({})
+    };
+    let id = if saved.id.is_empty() {
+        new_id()
+    } else {
+        saved.id.clone()
+    };
+    json!({
+        "_id": format!("req_{}", id),
+        "_type": "request",
+        "parentId": parent,
+        "name": saved.name,
+        "method": request.method,
+        "url": template(&request.url),
+        "headers": pairs(headers.into_iter()),
+        "parameters": pairs(request.query.iter().flatten().map(|(key, value)| (key, value))),
+        "body": body,
+        "authentication": request.auth.as_ref().and_then(export_auth).unwrap_or_else(|| json!({})),
+    })
+}
+
+// Saved requests as an Insomnia v4 export: a workspace holding one request group per
+// folder.
+pub fn export(name: &str, requests: &[SavedRequest]) -> Value {
+    let workspace = format!("wrk_{}", new_id());
+    let mut resources = vec![json!({
+        "_id": workspace,
+        "_type": "workspace",
+        "parentId": null,
+        "name": name,
+    })];
+    let mut folders: HashMap<String, String> = HashMap::new();
+    for saved in requests {
+        let mut parent = workspace.clone();
+        let mut path = String::new();
+        for segment in saved
+            .folder
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+        {
+            path = if path.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{}/{}", path, segment)
+            };
+            parent = match folders.get(&path) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = format!("fld_{}", new_id());
+                    resources.push(json!({
+                        "_id": id,
+                        "_type": "request_group",
+                        "parentId": parent,
+                        "name": segment,
+                    }));
+                    folders.insert(path.clone(), id.clone());
+                    id
+                }
+            };
+        }
+        resources.push(export_request(saved, &parent));
+    }
+    json!({
+        "_type": "export",
+        "__export_format": 4,
+        "__export_date": Utc::now().to_rfc3339(),
+        "__export_source": "restman",
+        "resources": resources,
+    })
+}
+
+// `{{ _.name }}` back to `{{name}}`; other tags are kept as they are.
+fn untemplate(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        match rest[start + 2..start + end].trim().strip_prefix("_.") {
+            Some(name) => result.push_str(&format!("{{{{{}}}}}", name)),
+            None => result.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    result.push_str(rest);
+    result
+}
+
+fn field(resource: &Value, key: &str) -> String {
+    match resource.get(key) {
+        Some(Value::String(text)) => untemplate(text),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+// Insomnia leaves an unset auth as `{}`, which inherits from the folders above.
+fn import_auth(auth: &Value) -> Result<Option<Auth>, String> {
+    if auth.get("disabled").and_then(Value::as_bool) == Some(true) {
+        return Ok(None);
+    }
+    let setting = |key: &str| field(auth, key);
+    Ok(Some(match setting("type").as_str() {
+        "" | "none" => return Ok(None),
+        "basic" => Auth::Basic {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "bearer" => Auth::Bearer {
+            token: setting("token"),
+        },
+        "apikey" => Auth::ApiKey {
+            name: setting("key"),
+            value: setting("value"),
+            location: match setting("addTo").as_str() {
+                "queryParams" => ApiKeyLocation::Query,
+                "cookie" => ApiKeyLocation::Cookie,
+                _ => ApiKeyLocation::Header,
+            },
+        },
+        "digest" => Auth::Digest {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "ntlm" => Auth::Ntlm(NtlmCredentials {
+            username: setting("username"),
+            password: setting("password"),
+            domain: String::new(),
+        }),
+        "iam" => Auth::AwsSigV4(AwsCredentials {
+            access_key_id: setting("accessKeyId"),
+            secret_access_key: setting("secretAccessKey"),
+            session_token: Some(setting("sessionToken")).filter(|token| !token.is_empty()),
+            region: setting("region"),
+            service: setting("service"),
+        }),
+        "hawk" => Auth::Hawk(HawkCredentials {
+            id: setting("id"),
+            key: setting("key"),
+            algorithm: match setting("algorithm").as_str() {
+                "sha1" => HmacAlgorithm::Sha1,
+                _ => HmacAlgorithm::Sha256,
+            },
+            ext: Some(setting("ext")).filter(|ext| !ext.is_empty()),
+            include_payload_hash: auth.get("validatePayload").and_then(Value::as_bool)
+                == Some(true),
+        }),
+        other => return Err(format!("{} auth is not supported", other)),
+    }))
+}
+
+fn import_body(body: &Value, request: &mut RequestInput) {
+    let mime_type = field(body, "mimeType");
+    let enabled = |params: &Vec<Value>| -> Vec<Value> {
+        params
+            .iter()
+            .filter(|param| param.get("disabled").and_then(Value::as_bool) != Some(true))
+            .cloned()
+            .collect()
+    };
+    if let Some(params) = body.get("params").and_then(Value::as_array) {
+        if mime_type == "multipart/form-data" {
+            let mut payload = MultipartPayload {
+                fields: HashMap::new(),
+                files: Vec::new(),
+            };
+            for param in enabled(params) {
+                if field(&param, "type") == "file" {
+                    payload.files.push(MultipartFile {
+                        name: field(&param, "name"),
+                        paths: vec![field(&param, "fileName")],
+                    });
+                } else {
+                    payload
+                        .fields
+                        .insert(field(&param, "name"), field(&param, "value"));
+                }
+            }
+            request.multipart = Some(payload);
+        } else {
+            request.form = Some(
+                enabled(params)
+                    .iter()
+                    .map(|param| (field(param, "name"), field(param, "value")))
+                    .collect(),
+            );
+        }
+        return;
+    }
+    if let Some(path) = Some(field(body, "fileName")).filter(|path| !path.is_empty()) {
+        request.body_file = Some(path);
+    } else if let Some(text) = Some(field(body, "text")).filter(|text| !text.is_empty()) {
+        request.body = Some(text);
+    } else {
+        return;
+    }
+    // GraphQL bodies are stored as the JSON document that is sent.
+    let content_type = match mime_type.as_str() {
+        "application/graphql" => "application/json".to_string(),
+        _ => mime_type,
+    };
+    let has_content_type = request
+        .headers
+        .keys()
+        .any(|key| key.eq_ignore_ascii_case("content-type"));
+    if !has_content_type && !content_type.is_empty() {
+        request.headers.insert("Content-Type".into(), content_type);
+    }
+}
+
+// Nested environment data is flattened into dotted names, as Insomnia addresses it.
+fn flatten_data(prefix: &str, data: &Value, variables: &mut Vec<Variable>) {
+    match data {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_data(&name, value, variables);
+            }
+        }
+        Value::String(text) => variables.push(Variable {
+            name: prefix.to_string(),
+            value: untemplate(text),
+            enabled: true,
+            secret: false,
+        }),
+        other => variables.push(Variable {
+            name: prefix.to_string(),
+            value: other.to_string(),
+            enabled: true,
+            secret: false,
+        }),
+    }
+}
+
+// Requests keep their request groups as folders and inherit the nearest group's auth.
+// Each sub-environment becomes an environment holding the base environment's values
+// under its own; the base alone becomes one named after the workspace.
+pub fn import(content: &str) -> Result<CollectionImport, String> {
+    let export: Value =
+        serde_json::from_str(content).map_err(|e| format!("Not an Insomnia export: {}", e))?;
+    let resources = export
+        .get("resources")
+        .and_then(Value::as_array)
+        .filter(|_| export.get("_type").and_then(Value::as_str) == Some("export"))
+        .ok_or("Not an Insomnia export")?;
+    let by_id: HashMap<&str, &Value> = resources
+        .iter()
+        .filter_map(|resource| Some((resource.get("_id")?.as_str()?, resource)))
+        .collect();
+    let kind = |resource: &Value| field(resource, "_type");
+    let parent = |resource: &Value| {
+        resource
+            .get("parentId")
+            .and_then(Value::as_str)
+            .and_then(|id| by_id.get(id).copied())
+    };
+    let name = resources
+        .iter()
+        .find(|resource| kind(resource) == "workspace")
+        .map(|workspace| field(workspace, "name"))
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "Insomnia collection".into());
+    let mut imported = CollectionImport {
+        name: name.clone(),
+        ..CollectionImport::default()
+    };
+    for resource in resources {
+        match kind(resource).as_str() {
+            "request" => {}
+            "grpc_request" | "websocket_request" => {
+                imported.warnings.push(format!(
+                    "{}: {} requests are not supported",
+                    field(resource, "name"),
+                    kind(resource).trim_end_matches("_request")
+                ));
+                continue;
+            }
+            _ => continue,
+        }
+        let request_name = field(resource, "name");
+        let mut folders = Vec::new();
+        let mut auth = resource.get("authentication").cloned().unwrap_or_default();
+        let mut ancestor = parent(resource);
+        while let Some(group) = ancestor.filter(|group| kind(group) == "request_group") {
+            folders.push(field(group, "name"));
+            if auth.as_object().is_none_or(|auth| auth.is_empty()) {
+                auth = group.get("authentication").cloned().unwrap_or_default();
+            }
+            ancestor = parent(group);
+        }
+        folders.reverse();
+        let pairs = |key: &str| -> Vec<(String, String)> {
+            resource
+                .get(key)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter(|pair| pair.get("disabled").and_then(Value::as_bool) != Some(true))
+                .map(|pair| (field(pair, "name"), field(pair, "value")))
+                .filter(|(name, _)| !name.is_empty())
+                .collect()
+        };
+        let mut request = RequestInput {
+            method: Some(field(resource, "method"))
+                .filter(|method| !method.is_empty())
+                .unwrap_or_else(|| "GET".into()),
+            url: field(resource, "url"),
+            query: Some(pairs("parameters")).filter(|query| !query.is_empty()),
+            headers: pairs("headers").into_iter().collect(),
+            ..RequestInput::default()
+        };
+        if let Some(body) = resource.get("body") {
+            import_body(body, &mut request);
+        }
+        request.auth = import_auth(&auth).unwrap_or_else(|e| {
+            imported.warnings.push(format!("{}: {}", request_name, e));
+            None
+        });
+        if request.url.contains("{%")
+            || request
+                .body
+                .as_deref()
+                .is_some_and(|body| body.contains("{%"))
+        {
+            imported.warnings.push(format!(
+                "{}: template tags were left as they are",
+                request_name
+            ));
+        }
+        let now = Utc::now();
+        imported.saved_requests.push(SavedRequest {
+            id: new_id(),
+            name: request_name,
+            folder: folders.join("/"),
+            request,
+            examples: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+    let environments: Vec<&Value> = resources
+        .iter()
+        .filter(|resource| kind(resource) == "environment")
+        .collect();
+    let is_base = |environment: &Value| {
+        parent(environment).is_none_or(|parent| kind(parent) != "environment")
+    };
+    for base in environments
+        .iter()
+        .filter(|environment| is_base(environment))
+    {
+        let mut base_variables = Vec::new();
+        flatten_data(
+            "",
+            base.get("data").unwrap_or(&Value::Null),
+            &mut base_variables,
+        );
+        let base_id = base.get("_id");
+        let subs: Vec<&&Value> = environments
+            .iter()
+            .filter(|environment| !is_base(environment) && environment.get("parentId") == base_id)
+            .collect();
+        if subs.is_empty() && !base_variables.is_empty() {
+            imported.environments.push(Environment {
+                name: name.clone(),
+                variables: base_variables.clone(),
+            });
+        }
+        for sub in subs {
+            let mut sub_variables = base_variables.clone();
+            flatten_data(
+                "",
+                sub.get("data").unwrap_or(&Value::Null),
+                &mut sub_variables,
+            );
+            imported.environments.push(Environment {
+                name: field(sub, "name"),
+                variables: sub_variables,
+            });
+        }
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_folders_as_request_groups_with_insomnia_templates() {
+        assert_eq!(
+            template("{{base}}/a/{{ id }}?t={{$timestamp}}"),
+            "{{ _.base }}/a/{{ _.id }}?t={{$timestamp}}"
+        );
+        let saved = SavedRequest {
+            id: "abc".into(),
+            name: "Create".into(),
+            folder: "orders/new".into(),
+            request: RequestInput {
+                method: "POST".into(),
+                url: "{{base}}/orders".into(),
+                body: Some("{\"n\": 1}".into()),
+                auth: Some(Auth::Bearer {
+                    token: "{{token}}".into(),
+                }),
+                ..RequestInput::default()
+            },
+            examples: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let exported = export("Shop", &[saved]);
+        let resources = exported["resources"].as_array().unwrap();
+        let kinds: Vec<&str> = resources
+            .iter()
+            .map(|resource| resource["_type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["workspace", "request_group", "request_group", "request"]
+        );
+        assert_eq!(resources[2]["parentId"], resources[1]["_id"]);
+        assert_eq!(resources[3]["parentId"], resources[2]["_id"]);
+        assert_eq!(resources[3]["_id"], "req_abc");
+        assert_eq!(resources[3]["url"], "{{ _.base }}/orders");
+        assert_eq!(resources[3]["body"]["mimeType"], "application/json");
+        assert_eq!(resources[3]["authentication"]["token"], "{{ _.token }}");
+    }
+
+    #[test]
+    fn imports_groups_auth_bodies_and_environments() {
+        let content = r#"{
+            "_type": "export",
+            "__export_format": 4,
+            "resources": [
+                {"_id": "wrk_1", "_type": "workspace", "parentId": null, "name": "Shop"},
+                {"_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Orders",
+                 "authentication": {"type": "bearer", "token": "{{ _.token }}"}},
+                {"_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "Create",
+                 "method": "POST", "url": "{{ _.base }}/orders",
+                 "headers": [{"name": "X-Off", "value": "1", "disabled": true}],
+                 "parameters": [{"name": "dry", "value": "1"}],
+                 "body": {"mimeType": "application/json", "text": "{\"n\": 1}"},
+                 "authentication": {}},
+                {"_id": "req_2", "_type": "request", "parentId": "wrk_1", "name": "Login",
+                 "method": "POST", "url": "{{ _.base }}/login",
+                 "body": {"mimeType": "application/x-www-form-urlencoded", "params": [{"name": "user", "value": "ann"}]},
+                 "authentication": {"type": "oauth1"}},
+                {"_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base Environment",
+                 "data": {"base": "https://api.shop.test", "auth": {"user": "ann"}}},
+                {"_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                 "data": {"base": "https://staging.shop.test"}}
+            ]
+        }"#;
+        let imported = import(content).unwrap();
+        assert_eq!(imported.name, "Shop");
+        let create = &imported.saved_requests[0];
+        assert_eq!(create.folder, "Orders");
+        assert_eq!(create.request.url, "{{base}}/orders");
+        assert_eq!(create.request.headers["Content-Type"], "application/json");
+        assert_eq!(
+            create.request.query,
+            Some(vec![("dry".to_string(), "1".to_string())])
+        );
+        assert_eq!(
+            create.request.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+        let login = &imported.saved_requests[1];
+        assert_eq!(login.folder, "");
+        assert_eq!(
+            login.request.form,
+            Some(vec![("user".to_string(), "ann".to_string())])
+        );
+        assert_eq!(
+            imported.warnings,
+            vec!["Login: oauth1 auth is not supported"]
+        );
+
+        let [staging] = &imported.environments[..] else {
+            panic!("expected one environment");
+        };
+        assert_eq!(staging.name, "Staging");
+        let values = staging.values();
+        assert_eq!(values["base"], "https://staging.shop.test");
+        assert_eq!(values["auth.user"], "ann");
+        assert!(import("{}").is_err());
+    }
+}
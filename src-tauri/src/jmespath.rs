@@ -0,0 +1,961 @@
+use serde_json::{Map, Number, Value};
+use std::cmp::Ordering;
+
+// A JMESPath (https://jmespath.org/specification.html) parser and interpreter covering
+// the full expression grammar and the built-in functions.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(i64),
+    Literal(Value),
+    Dot,
+    Star,
+    Flatten,
+    Filter,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Comma,
+    Colon,
+    Pipe,
+    Or,
+    And,
+    Not,
+    At,
+    Ampersand,
+    Compare(Comparator),
+    Eof,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparator {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Identity,
+    Field(String),
+    Literal(Value),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Subexpression(Box<Node>, Box<Node>),
+    // Applies the right side to each element of the left side's array.
+    Projection(Box<Node>, Box<Node>),
+    // Same, over the values of an object.
+    ValueProjection(Box<Node>, Box<Node>),
+    FilterProjection(Box<Node>, Box<Node>, Box<Node>),
+    Flatten(Box<Node>),
+    MultiList(Vec<Node>),
+    MultiHash(Vec<(String, Node)>),
+    Or(Box<Node>, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Compare(Comparator, Box<Node>, Box<Node>),
+    Pipe(Box<Node>, Box<Node>),
+    Function(String, Vec<Node>),
+    ExpressionRef(Box<Node>),
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    // Everything up to the unescaped `close`.
+    let delimited = |i: &mut usize, close: char| -> Result<String, String> {
+        let mut text = String::new();
+        *i += 1;
+        while *i < chars.len() && chars[*i] != close {
+            // Quoted identifiers keep their escapes for JSON decoding; elsewhere only
+            // the delimiter and backslash are escaped.
+            if chars[*i] == '\\' && *i + 1 < chars.len() {
+                let escaped = chars[*i + 1];
+                if close == '"' || (escaped != close && escaped != '\\') {
+                    text.push('\\');
+                }
+                *i += 1;
+            }
+            text.push(chars[*i]);
+            *i += 1;
+        }
+        if *i >= chars.len() {
+            return Err(format!("Unterminated {} in expression", close));
+        }
+        *i += 1;
+        Ok(text)
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+                continue;
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Number(
+                    text.parse().map_err(|_| "Number out of range")?,
+                ));
+                continue;
+            }
+            '"' => {
+                let text = delimited(&mut i, '"')?;
+                let name: String = serde_json::from_str(&format!("\"{}\"", text))
+                    .map_err(|e| format!("Invalid quoted identifier: {}", e))?;
+                tokens.push(Token::Identifier(name));
+                continue;
+            }
+            '\'' => {
+                let text = delimited(&mut i, '\'')?;
+                tokens.push(Token::Literal(Value::String(text)));
+                continue;
+            }
+            '`' => {
+                let text = delimited(&mut i, '`')?;
+                let value = serde_json::from_str(text.trim())
+                    .map_err(|e| format!("Invalid literal `{}`: {}", text, e))?;
+                tokens.push(Token::Literal(value));
+                continue;
+            }
+            '[' if next == Some(']') => {
+                i += 1;
+                Token::Flatten
+            }
+            '[' if next == Some('?') => {
+                i += 1;
+                Token::Filter
+            }
+            '|' if next == Some('|') => {
+                i += 1;
+                Token::Or
+            }
+            '&' if next == Some('&') => {
+                i += 1;
+                Token::And
+            }
+            '=' if next == Some('=') => {
+                i += 1;
+                Token::Compare(Comparator::Eq)
+            }
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Compare(Comparator::Ne)
+            }
+            '<' if next == Some('=') => {
+                i += 1;
+                Token::Compare(Comparator::Lte)
+            }
+            '>' if next == Some('=') => {
+                i += 1;
+                Token::Compare(Comparator::Gte)
+            }
+            '<' => Token::Compare(Comparator::Lt),
+            '>' => Token::Compare(Comparator::Gt),
+            '!' => Token::Not,
+            '|' => Token::Pipe,
+            '&' => Token::Ampersand,
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '@' => Token::At,
+            other => return Err(format!("Unexpected character '{}' at {}", other, i)),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+    tokens.push(Token::Eof);
+    Ok(tokens)
+}
+
+// Binding powers from the specification's reference implementation.
+fn binding_power(token: &Token) -> u8 {
+    match token {
+        Token::Pipe => 1,
+        Token::Or => 2,
+        Token::And => 3,
+        Token::Compare(_) => 5,
+        Token::Flatten => 9,
+        Token::Star => 20,
+        Token::Filter => 21,
+        Token::Dot => 40,
+        Token::Not => 45,
+        Token::LBrace => 50,
+        Token::LBracket => 55,
+        Token::LParen => 60,
+        _ => 0,
+    }
+}
+
+// Projections stop at tokens binding looser than this.
+const PROJECTION_STOP: u8 = 10;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn current(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn peek(&self, offset: usize) -> &Token {
+        self.tokens
+            .get(self.position + offset)
+            .unwrap_or(&Token::Eof)
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.current().clone();
+        if self.position + 1 < self.tokens.len() {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if *self.current() == token {
+            self.advance();
+            Ok(())
+        } else {
+            Err(format!("Expected {:?}, found {:?}", token, self.current()))
+        }
+    }
+
+    fn expression(&mut self, power: u8) -> Result<Node, String> {
+        let token = self.advance();
+        let mut left = self.nud(token)?;
+        while power < binding_power(self.current()) {
+            let token = self.advance();
+            left = self.led(token, left)?;
+        }
+        Ok(left)
+    }
+
+    fn nud(&mut self, token: Token) -> Result<Node, String> {
+        Ok(match token {
+            Token::Literal(value) => Node::Literal(value),
+            Token::Identifier(name) => Node::Field(name),
+            Token::At => Node::Identity,
+            Token::Star => {
+                let right = self.projection_rhs(binding_power(&Token::Star))?;
+                Node::ValueProjection(Box::new(Node::Identity), Box::new(right))
+            }
+            Token::Filter => self.filter(Node::Identity)?,
+            Token::LBrace => self.multi_hash()?,
+            Token::LParen => {
+                let inner = self.expression(0)?;
+                self.expect(Token::RParen)?;
+                inner
+            }
+            Token::Flatten => {
+                let right = self.projection_rhs(binding_power(&Token::Flatten))?;
+                Node::Projection(
+                    Box::new(Node::Flatten(Box::new(Node::Identity))),
+                    Box::new(right),
+                )
+            }
+            Token::Not => Node::Not(Box::new(self.expression(binding_power(&Token::Not))?)),
+            Token::Ampersand => Node::ExpressionRef(Box::new(self.expression(0)?)),
+            Token::LBracket => match (self.current(), self.peek(1)) {
+                (Token::Number(_) | Token::Colon, _) => {
+                    let index = self.index()?;
+                    self.project_if_slice(Node::Identity, index)?
+                }
+                (Token::Star, Token::RBracket) => {
+                    self.advance();
+                    self.advance();
+                    let right = self.projection_rhs(binding_power(&Token::Star))?;
+                    Node::Projection(Box::new(Node::Identity), Box::new(right))
+                }
+                _ => self.multi_list()?,
+            },
+            other => return Err(format!("Unexpected {:?}", other)),
+        })
+    }
+
+    fn led(&mut self, token: Token, left: Node) -> Result<Node, String> {
+        let power = binding_power(&token);
+        Ok(match token {
+            Token::Dot if *self.current() == Token::Star => {
+                self.advance();
+                let right = self.projection_rhs(power)?;
+                Node::ValueProjection(Box::new(left), Box::new(right))
+            }
+            Token::Dot => {
+                let right = self.dot_rhs(power)?;
+                Node::Subexpression(Box::new(left), Box::new(right))
+            }
+            Token::Pipe => Node::Pipe(Box::new(left), Box::new(self.expression(power)?)),
+            Token::Or => Node::Or(Box::new(left), Box::new(self.expression(power)?)),
+            Token::And => Node::And(Box::new(left), Box::new(self.expression(power)?)),
+            Token::Compare(op) => {
+                Node::Compare(op, Box::new(left), Box::new(self.expression(power)?))
+            }
+            Token::LParen => {
+                let Node::Field(name) = left else {
+                    return Err("Only functions can be called".into());
+                };
+                let mut args = Vec::new();
+                while *self.current() != Token::RParen {
+                    args.push(self.expression(0)?);
+                    if *self.current() == Token::Comma {
+                        self.advance();
+                    }
+                }
+                self.expect(Token::RParen)?;
+                Node::Function(name, args)
+            }
+            Token::Filter => self.filter(left)?,
+            Token::Flatten => {
+                let right = self.projection_rhs(power)?;
+                Node::Projection(Box::new(Node::Flatten(Box::new(left))), Box::new(right))
+            }
+            Token::LBracket => match self.current() {
+                Token::Number(_) | Token::Colon => {
+                    let index = self.index()?;
+                    self.project_if_slice(left, index)?
+                }
+                _ => {
+                    self.expect(Token::Star)?;
+                    self.expect(Token::RBracket)?;
+                    let right = self.projection_rhs(binding_power(&Token::Star))?;
+                    Node::Projection(Box::new(left), Box::new(right))
+                }
+            },
+            other => return Err(format!("Unexpected {:?}", other)),
+        })
+    }
+
+    fn filter(&mut self, left: Node) -> Result<Node, String> {
+        let condition = self.expression(0)?;
+        self.expect(Token::RBracket)?;
+        let right = if *self.current() == Token::Flatten {
+            Node::Identity
+        } else {
+            self.projection_rhs(binding_power(&Token::Filter))?
+        };
+        Ok(Node::FilterProjection(
+            Box::new(left),
+            Box::new(right),
+            Box::new(condition),
+        ))
+    }
+
+    // `[n]` or `[start:stop:step]`, with the opening bracket already consumed.
+    fn index(&mut self) -> Result<Node, String> {
+        let mut parts = [None, None, None];
+        let mut part = 0;
+        loop {
+            match self.advance() {
+                Token::Number(n) => parts[part] = Some(n),
+                Token::Colon if part < 2 => part += 1,
+                Token::RBracket => break,
+                other => return Err(format!("Unexpected {:?} in index", other)),
+            }
+        }
+        if part == 0 {
+            return parts[0].map(Node::Index).ok_or("Empty index".to_string());
+        }
+        if parts[2] == Some(0) {
+            return Err("Slice step cannot be 0".into());
+        }
+        Ok(Node::Slice(parts[0], parts[1], parts[2]))
+    }
+
+    fn project_if_slice(&mut self, left: Node, index: Node) -> Result<Node, String> {
+        let slice = matches!(index, Node::Slice(..));
+        let indexed = Node::Subexpression(Box::new(left), Box::new(index));
+        if slice {
+            let right = self.projection_rhs(binding_power(&Token::Star))?;
+            Ok(Node::Projection(Box::new(indexed), Box::new(right)))
+        } else {
+            Ok(indexed)
+        }
+    }
+
+    fn projection_rhs(&mut self, power: u8) -> Result<Node, String> {
+        match self.current() {
+            token if binding_power(token) < PROJECTION_STOP => Ok(Node::Identity),
+            Token::LBracket | Token::Filter => self.expression(power),
+            Token::Dot => {
+                self.advance();
+                self.dot_rhs(power)
+            }
+            other => Err(format!("Unexpected {:?} after projection", other)),
+        }
+    }
+
+    fn dot_rhs(&mut self, power: u8) -> Result<Node, String> {
+        match self.current() {
+            Token::Identifier(_) | Token::Star => self.expression(power),
+            Token::LBracket => {
+                self.advance();
+                self.multi_list()
+            }
+            Token::LBrace => {
+                self.advance();
+                self.multi_hash()
+            }
+            other => Err(format!("Unexpected {:?} after '.'", other)),
+        }
+    }
+
+    fn multi_list(&mut self) -> Result<Node, String> {
+        let mut items = vec![self.expression(0)?];
+        while *self.current() == Token::Comma {
+            self.advance();
+            items.push(self.expression(0)?);
+        }
+        self.expect(Token::RBracket)?;
+        Ok(Node::MultiList(items))
+    }
+
+    fn multi_hash(&mut self) -> Result<Node, String> {
+        let mut entries = Vec::new();
+        loop {
+            let Token::Identifier(key) = self.advance() else {
+                return Err("Expected a key in multi-select hash".into());
+            };
+            self.expect(Token::Colon)?;
+            entries.push((key, self.expression(0)?));
+            match self.advance() {
+                Token::Comma => continue,
+                Token::RBrace => break,
+                other => return Err(format!("Unexpected {:?} in multi-select hash", other)),
+            }
+        }
+        Ok(Node::MultiHash(entries))
+    }
+}
+
+fn parse(expression: &str) -> Result<Node, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    let node = parser.expression(0)?;
+    if *parser.current() != Token::Eof {
+        return Err(format!("Unexpected {:?}", parser.current()));
+    }
+    Ok(node)
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        _ => true,
+    }
+}
+
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n)
+            .map(Value::Number)
+            .unwrap_or(Value::Null)
+    }
+}
+
+fn slice(items: &[Value], start: Option<i64>, stop: Option<i64>, step: Option<i64>) -> Vec<Value> {
+    let len = items.len() as i64;
+    let step = step.unwrap_or(1);
+    let clamp = |index: i64, low: i64, high: i64| {
+        let index = if index < 0 { index + len } else { index };
+        index.clamp(low, high)
+    };
+    let mut result = Vec::new();
+    if step > 0 {
+        let (mut i, stop) = (
+            start.map_or(0, |s| clamp(s, 0, len)),
+            stop.map_or(len, |s| clamp(s, 0, len)),
+        );
+        while i < stop {
+            result.push(items[i as usize].clone());
+            i += step;
+        }
+    } else {
+        let (mut i, stop) = (
+            start.map_or(len - 1, |s| clamp(s, -1, len - 1)),
+            stop.map_or(-1, |s| clamp(s, -1, len - 1)),
+        );
+        while i > stop {
+            result.push(items[i as usize].clone());
+            i += step;
+        }
+    }
+    result
+}
+
+fn compare(op: Comparator, left: &Value, right: &Value) -> Value {
+    let ordering = match (left.as_f64(), right.as_f64()) {
+        (Some(a), Some(b)) => a.partial_cmp(&b),
+        _ => None,
+    };
+    match op {
+        Comparator::Eq => Value::Bool(ordering.map_or(left == right, |o| o == Ordering::Equal)),
+        Comparator::Ne => Value::Bool(ordering.map_or(left != right, |o| o != Ordering::Equal)),
+        _ => match ordering {
+            Some(ordering) => Value::Bool(match op {
+                Comparator::Lt => ordering == Ordering::Less,
+                Comparator::Lte => ordering != Ordering::Greater,
+                Comparator::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }),
+            None => Value::Null,
+        },
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// Sorting keys must be all numbers or all strings.
+fn sort_key_order(a: &Value, b: &Value) -> Result<Ordering, String> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => Ok(x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal)),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        _ => Err(format!(
+            "Cannot sort {} and {} together",
+            type_name(a),
+            type_name(b)
+        )),
+    }
+}
+
+fn interpret(node: &Node, value: &Value) -> Result<Value, String> {
+    Ok(match node {
+        Node::Identity => value.clone(),
+        Node::Field(name) => value.get(name).cloned().unwrap_or(Value::Null),
+        Node::Literal(literal) => literal.clone(),
+        Node::Index(index) => match value {
+            Value::Array(items) => {
+                let index = if *index < 0 {
+                    items.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| items.get(index))
+                    .cloned()
+                    .unwrap_or(Value::Null)
+            }
+            _ => Value::Null,
+        },
+        Node::Slice(start, stop, step) => match value {
+            Value::Array(items) => Value::Array(slice(items, *start, *stop, *step)),
+            _ => Value::Null,
+        },
+        Node::Subexpression(left, right) | Node::Pipe(left, right) => {
+            interpret(right, &interpret(left, value)?)?
+        }
+        Node::Projection(left, right) => match interpret(left, value)? {
+            Value::Array(items) => project(items.iter(), right)?,
+            _ => Value::Null,
+        },
+        Node::ValueProjection(left, right) => match interpret(left, value)? {
+            Value::Object(map) => project(map.values(), right)?,
+            _ => Value::Null,
+        },
+        Node::FilterProjection(left, right, condition) => match interpret(left, value)? {
+            Value::Array(items) => {
+                let mut kept = Vec::new();
+                for item in items {
+                    if truthy(&interpret(condition, &item)?) {
+                        kept.push(item);
+                    }
+                }
+                project(kept.iter(), right)?
+            }
+            _ => Value::Null,
+        },
+        Node::Flatten(inner) => match interpret(inner, value)? {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .flat_map(|item| match item {
+                        Value::Array(nested) => nested,
+                        other => vec![other],
+                    })
+                    .collect(),
+            ),
+            _ => Value::Null,
+        },
+        Node::MultiList(items) if !value.is_null() => Value::Array(
+            items
+                .iter()
+                .map(|item| interpret(item, value))
+                .collect::<Result<_, _>>()?,
+        ),
+        Node::MultiHash(entries) if !value.is_null() => {
+            let mut map = Map::new();
+            for (key, item) in entries {
+                map.insert(key.clone(), interpret(item, value)?);
+            }
+            Value::Object(map)
+        }
+        Node::MultiList(_) | Node::MultiHash(_) => Value::Null,
+        Node::Or(left, right) => {
+            let left = interpret(left, value)?;
+            if truthy(&left) {
+                left
+            } else {
+                interpret(right, value)?
+            }
+        }
+        Node::And(left, right) => {
+            let left = interpret(left, value)?;
+            if truthy(&left) {
+                interpret(right, value)?
+            } else {
+                left
+            }
+        }
+        Node::Not(inner) => Value::Bool(!truthy(&interpret(inner, value)?)),
+        Node::Compare(op, left, right) => {
+            compare(*op, &interpret(left, value)?, &interpret(right, value)?)
+        }
+        Node::Function(name, args) => call(name, args, value)?,
+        Node::ExpressionRef(_) => return Err("'&' is only allowed as a function argument".into()),
+    })
+}
+
+fn project<'a>(items: impl Iterator<Item = &'a Value>, right: &Node) -> Result<Value, String> {
+    let mut result = Vec::new();
+    for item in items {
+        let projected = interpret(right, item)?;
+        if !projected.is_null() {
+            result.push(projected);
+        }
+    }
+    Ok(Value::Array(result))
+}
+
+fn call(name: &str, args: &[Node], value: &Value) -> Result<Value, String> {
+    let arity = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{}() takes {} argument(s)", name, count))
+        }
+    };
+    let arg = |index: usize| interpret(&args[index], value);
+    let expression = |index: usize| match &args[index] {
+        Node::ExpressionRef(inner) => Ok(inner.as_ref()),
+        _ => Err(format!("{}() expects an &expression", name)),
+    };
+    let array = |v: Value| match v {
+        Value::Array(items) => Ok(items),
+        other => Err(format!(
+            "{}() expects an array, got {}",
+            name,
+            type_name(&other)
+        )),
+    };
+    let string = |v: Value| match v {
+        Value::String(s) => Ok(s),
+        other => Err(format!(
+            "{}() expects a string, got {}",
+            name,
+            type_name(&other)
+        )),
+    };
+    let float = |v: &Value| {
+        v.as_f64()
+            .ok_or_else(|| format!("{}() expects numbers, got {}", name, type_name(v)))
+    };
+    // Sorts `items` by the value of `key` for each.
+    let sorted_by = |items: Vec<Value>, key: &Node| -> Result<Vec<(Value, Value)>, String> {
+        let mut keyed = items
+            .into_iter()
+            .map(|item| Ok((interpret(key, &item)?, item)))
+            .collect::<Result<Vec<_>, String>>()?;
+        if let Some((first, _)) = keyed.first() {
+            for (key, _) in &keyed {
+                sort_key_order(first, key)?;
+            }
+        }
+        keyed.sort_by(|(a, _), (b, _)| sort_key_order(a, b).unwrap_or(Ordering::Equal));
+        Ok(keyed)
+    };
+    Ok(match name {
+        "abs" | "ceil" | "floor" => {
+            arity(1)?;
+            let n = float(&arg(0)?)?;
+            number(match name {
+                "abs" => n.abs(),
+                "ceil" => n.ceil(),
+                _ => n.floor(),
+            })
+        }
+        "avg" | "sum" => {
+            arity(1)?;
+            let items = array(arg(0)?)?;
+            let total = items.iter().map(float).sum::<Result<f64, String>>()?;
+            match (name, items.len()) {
+                ("avg", 0) => Value::Null,
+                ("avg", count) => number(total / count as f64),
+                _ => number(total),
+            }
+        }
+        "contains" => {
+            arity(2)?;
+            let needle = arg(1)?;
+            Value::Bool(match arg(0)? {
+                Value::Array(items) => items.contains(&needle),
+                Value::String(s) => s.contains(&string(needle)?),
+                other => return Err(format!("contains() cannot search {}", type_name(&other))),
+            })
+        }
+        "starts_with" | "ends_with" => {
+            arity(2)?;
+            let (subject, affix) = (string(arg(0)?)?, string(arg(1)?)?);
+            Value::Bool(if name == "starts_with" {
+                subject.starts_with(&affix)
+            } else {
+                subject.ends_with(&affix)
+            })
+        }
+        "join" => {
+            arity(2)?;
+            let glue = string(arg(0)?)?;
+            let parts = array(arg(1)?)?
+                .into_iter()
+                .map(string)
+                .collect::<Result<Vec<_>, _>>()?;
+            Value::String(parts.join(&glue))
+        }
+        "keys" | "values" => {
+            arity(1)?;
+            match arg(0)? {
+                Value::Object(map) if name == "keys" => {
+                    Value::Array(map.keys().cloned().map(Value::String).collect())
+                }
+                Value::Object(map) => Value::Array(map.values().cloned().collect()),
+                other => {
+                    return Err(format!(
+                        "{}() expects an object, got {}",
+                        name,
+                        type_name(&other)
+                    ))
+                }
+            }
+        }
+        "length" => {
+            arity(1)?;
+            Value::from(match arg(0)? {
+                Value::String(s) => s.chars().count(),
+                Value::Array(items) => items.len(),
+                Value::Object(map) => map.len(),
+                other => return Err(format!("length() cannot measure {}", type_name(&other))),
+            })
+        }
+        "max" | "min" => {
+            arity(1)?;
+            let sorted = sorted_by(array(arg(0)?)?, &Node::Identity)?;
+            let pick = if name == "max" {
+                sorted.last()
+            } else {
+                sorted.first()
+            };
+            pick.map(|(_, item)| item.clone()).unwrap_or(Value::Null)
+        }
+        "max_by" | "min_by" => {
+            arity(2)?;
+            let sorted = sorted_by(array(arg(0)?)?, expression(1)?)?;
+            let pick = if name == "max_by" {
+                sorted.last()
+            } else {
+                sorted.first()
+            };
+            pick.map(|(_, item)| item.clone()).unwrap_or(Value::Null)
+        }
+        "sort" => {
+            arity(1)?;
+            let sorted = sorted_by(array(arg(0)?)?, &Node::Identity)?;
+            Value::Array(sorted.into_iter().map(|(_, item)| item).collect())
+        }
+        "sort_by" => {
+            arity(2)?;
+            let sorted = sorted_by(array(arg(0)?)?, expression(1)?)?;
+            Value::Array(sorted.into_iter().map(|(_, item)| item).collect())
+        }
+        "map" => {
+            arity(2)?;
+            let key = expression(0)?;
+            Value::Array(
+                array(arg(1)?)?
+                    .iter()
+                    .map(|item| interpret(key, item))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        "merge" => {
+            let mut merged = Map::new();
+            for index in 0..args.len() {
+                match arg(index)? {
+                    Value::Object(map) => merged.extend(map),
+                    other => {
+                        return Err(format!(
+                            "merge() expects objects, got {}",
+                            type_name(&other)
+                        ))
+                    }
+                }
+            }
+            Value::Object(merged)
+        }
+        "not_null" => {
+            for index in 0..args.len() {
+                let value = arg(index)?;
+                if !value.is_null() {
+                    return Ok(value);
+                }
+            }
+            Value::Null
+        }
+        "reverse" => {
+            arity(1)?;
+            match arg(0)? {
+                Value::Array(mut items) => {
+                    items.reverse();
+                    Value::Array(items)
+                }
+                Value::String(s) => Value::String(s.chars().rev().collect()),
+                other => return Err(format!("reverse() cannot reverse {}", type_name(&other))),
+            }
+        }
+        "to_array" => {
+            arity(1)?;
+            match arg(0)? {
+                Value::Array(items) => Value::Array(items),
+                other => Value::Array(vec![other]),
+            }
+        }
+        "to_number" => {
+            arity(1)?;
+            match arg(0)? {
+                Value::Number(n) => Value::Number(n),
+                Value::String(s) => s.trim().parse::<f64>().map(number).unwrap_or(Value::Null),
+                _ => Value::Null,
+            }
+        }
+        "to_string" => {
+            arity(1)?;
+            match arg(0)? {
+                Value::String(s) => Value::String(s),
+                other => Value::String(other.to_string()),
+            }
+        }
+        "type" => {
+            arity(1)?;
+            Value::from(type_name(&arg(0)?))
+        }
+        other => return Err(format!("Unknown function: {}()", other)),
+    })
+}
+
+pub fn search(expression: &str, value: &Value) -> Result<Value, String> {
+    interpret(&parse(expression)?, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn evaluates_projections_filters_and_functions() {
+        let data = json!({
+            "people": [
+                { "name": "Ann", "age": 31, "tags": ["a", "b"] },
+                { "name": "Bob", "age": 25, "tags": ["c"] },
+                { "name": "Cy", "age": 40 }
+            ],
+            "meta": { "total": 3, "page": { "next": null } },
+            "weird key": 1
+        });
+        let cases = [
+            ("people[0].name", json!("Ann")),
+            ("people[-1].age", json!(40)),
+            ("people[*].name", json!(["Ann", "Bob", "Cy"])),
+            ("people[?age > `30`].name", json!(["Ann", "Cy"])),
+            ("people[?name == 'Bob'] | [0].age", json!(25)),
+            ("people[].tags[]", json!(["a", "b", "c"])),
+            ("people[:2].name", json!(["Ann", "Bob"])),
+            ("people[::-1].name | [0]", json!("Cy")),
+            ("length(meta.*)", json!(2)),
+            (
+                "people[0].{n: name, t: length(tags)}",
+                json!({ "n": "Ann", "t": 2 }),
+            ),
+            ("[meta.total, \"weird key\"]", json!([3, 1])),
+            ("length(people)", json!(3)),
+            ("max_by(people, &age).name", json!("Cy")),
+            ("sort_by(people, &age)[*].name", json!(["Bob", "Ann", "Cy"])),
+            ("sum(people[*].age)", json!(96)),
+            ("avg(people[*].age)", json!(32)),
+            ("join(', ', people[*].name)", json!("Ann, Bob, Cy")),
+            ("meta.page.next || 'none'", json!("none")),
+            ("!contains(people[*].name, 'Dee')", json!(true)),
+            ("missing.field", Value::Null),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(
+                search(expression, &data).unwrap(),
+                expected,
+                "{}",
+                expression
+            );
+        }
+        assert!(search("people[", &data).is_err());
+        assert!(search("nope(people)", &data).is_err());
+        assert!(search("sort(people)", &data).is_err());
+    }
+}
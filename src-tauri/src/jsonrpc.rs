@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+// One method call. Calls without an id get one when the request is sent; notifications
+// go out without one and get no reply.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct JsonRpcCall {
+    pub method: String,
+    pub params: Option<Value>,
+    pub id: Option<Value>,
+    pub notification: bool,
+}
+
+// A JSON-RPC 2.0 request. Requests that carry one are sent as a JSON POST of the
+// envelope, or of an array of envelopes for several calls or when `batch` is set.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct JsonRpcRequest {
+    pub calls: Vec<JsonRpcCall>,
+    pub batch: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+// A reply, with the method of the call it answers when its id matches one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JsonRpcOutcome {
+    pub id: Value,
+    pub method: Option<String>,
+    pub result: Option<Value>,
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcRequest {
+    pub fn assign_ids(&mut self) {
+        for call in self.calls.iter_mut().filter(|call| !call.notification) {
+            if call.id.as_ref().is_none_or(Value::is_null) {
+                call.id = Some(json!(NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+            }
+        }
+    }
+
+    pub fn body(&self) -> Result<String, String> {
+        if self.calls.is_empty() {
+            return Err("JSON-RPC request has no calls".into());
+        }
+        let mut envelopes = Vec::new();
+        for call in &self.calls {
+            if call.method.trim().is_empty() {
+                return Err("JSON-RPC call needs a method".into());
+            }
+            let mut envelope = json!({ "jsonrpc": "2.0", "method": call.method });
+            match &call.params {
+                Some(params @ (Value::Array(_) | Value::Object(_))) => {
+                    envelope["params"] = params.clone()
+                }
+                None | Some(Value::Null) => {}
+                Some(_) => {
+                    return Err(format!(
+                        "Params of {} must be an array or an object",
+                        call.method
+                    ))
+                }
+            }
+            if !call.notification {
+                envelope["id"] = call.id.clone().unwrap_or(Value::Null);
+            }
+            envelopes.push(envelope);
+        }
+        let body = if envelopes.len() == 1 && !self.batch {
+            envelopes.remove(0)
+        } else {
+            Value::Array(envelopes)
+        };
+        Ok(body.to_string())
+    }
+
+    // The replies in `body`, in the order of the calls they answer; replies with an id
+    // the request did not use, such as parse errors, come last. Empty when the body is
+    // not a JSON-RPC reply.
+    pub fn outcomes(&self, body: &str) -> Vec<JsonRpcOutcome> {
+        let replies = match serde_json::from_str::<Value>(body) {
+            Ok(Value::Array(replies)) => replies,
+            Ok(reply @ Value::Object(_)) => vec![reply],
+            _ => return Vec::new(),
+        };
+        let mut outcomes: Vec<(usize, JsonRpcOutcome)> = replies
+            .into_iter()
+            .filter(|reply| reply.get("result").is_some() || reply.get("error").is_some())
+            .map(|reply| {
+                let id = reply.get("id").cloned().unwrap_or(Value::Null);
+                let position = self
+                    .calls
+                    .iter()
+                    .position(|call| !call.notification && call.id.as_ref() == Some(&id));
+                let outcome = JsonRpcOutcome {
+                    method: position.map(|index| self.calls[index].method.clone()),
+                    result: reply.get("result").cloned(),
+                    error: reply
+                        .get("error")
+                        .cloned()
+                        .and_then(|error| serde_json::from_value(error).ok()),
+                    id,
+                };
+                (position.unwrap_or(usize::MAX), outcome)
+            })
+            .collect();
+        outcomes.sort_by_key(|(position, _)| *position);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_batches_and_matches_replies_by_id() {
+        let mut request = JsonRpcRequest {
+            calls: vec![
+                JsonRpcCall {
+                    method: "eth_blockNumber".into(),
+                    ..JsonRpcCall::default()
+                },
+                JsonRpcCall {
+                    method: "log".into(),
+                    params: Some(json!(["hi"])),
+                    notification: true,
+                    ..JsonRpcCall::default()
+                },
+                JsonRpcCall {
+                    method: "eth_getBalance".into(),
+                    params: Some(json!(["0x1", "latest"])),
+                    id: Some(json!("balance")),
+                    ..JsonRpcCall::default()
+                },
+            ],
+            batch: false,
+        };
+        request.assign_ids();
+        let first = request.calls[0].id.clone().unwrap();
+        assert!(first.is_u64());
+        assert_eq!(request.calls[1].id, None);
+        let body: Value = serde_json::from_str(&request.body().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!([
+                { "jsonrpc": "2.0", "method": "eth_blockNumber", "id": first },
+                { "jsonrpc": "2.0", "method": "log", "params": ["hi"] },
+                { "jsonrpc": "2.0", "method": "eth_getBalance", "params": ["0x1", "latest"], "id": "balance" }
+            ])
+        );
+
+        let reply = json!([
+            { "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "Parse error" } },
+            { "jsonrpc": "2.0", "id": "balance", "error": { "code": -32602, "message": "Invalid params", "data": "bad address" } },
+            { "jsonrpc": "2.0", "id": first, "result": "0x10" }
+        ]);
+        let outcomes = request.outcomes(&reply.to_string());
+        assert_eq!(outcomes[0].method.as_deref(), Some("eth_blockNumber"));
+        assert_eq!(outcomes[0].result, Some(json!("0x10")));
+        assert_eq!(
+            outcomes[1].error.as_ref().map(|e| (e.code, e.data.clone())),
+            Some((-32602, Some(json!("bad address"))))
+        );
+        assert_eq!(
+            (outcomes[2].method.as_deref(), outcomes[2].id.clone()),
+            (None, Value::Null)
+        );
+
+        let single = JsonRpcRequest {
+            calls: vec![JsonRpcCall {
+                method: "ping".into(),
+                params: Some(json!(1)),
+                ..JsonRpcCall::default()
+            }],
+            batch: false,
+        };
+        assert!(single.body().is_err());
+    }
+}
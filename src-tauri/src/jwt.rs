@@ -0,0 +1,129 @@
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    #[serde(rename = "HS256")]
+    Hs256,
+    #[serde(rename = "RS256")]
+    Rs256,
+    #[serde(rename = "ES256")]
+    Es256,
+}
+
+// HS256 keys are the raw secret; RS256 and ES256 keys are PEM-encoded private keys.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "source", rename_all = "lowercase")]
+pub enum JwtKey {
+    Inline { value: String },
+    File { path: String },
+    Keychain { service: String, account: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JwtConfig {
+    pub algorithm: JwtAlgorithm,
+    pub key: JwtKey,
+    pub claims: Value,
+    #[serde(default)]
+    pub key_id: Option<String>,
+    // Fills in `iat` and `exp` unless the claims already set them.
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+}
+
+async fn load_key(key: &JwtKey) -> Result<Vec<u8>, String> {
+    match key {
+        JwtKey::Inline { value } => Ok(value.as_bytes().to_vec()),
+        JwtKey::File { path } => tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("Cannot read key file {}: {}", path, e)),
+        JwtKey::Keychain { service, account } => {
+            let entry = keyring::Entry::new(service, account).map_err(|e| e.to_string())?;
+            entry
+                .get_password()
+                .map(String::into_bytes)
+                .map_err(|e| format!("Cannot read {} from the keychain: {}", service, e))
+        }
+    }
+}
+
+fn encoding_key(algorithm: JwtAlgorithm, material: &[u8]) -> Result<EncodingKey, String> {
+    match algorithm {
+        JwtAlgorithm::Hs256 => Ok(EncodingKey::from_secret(material)),
+        JwtAlgorithm::Rs256 => EncodingKey::from_rsa_pem(material).map_err(|e| e.to_string()),
+        JwtAlgorithm::Es256 => EncodingKey::from_ec_pem(material).map_err(|e| e.to_string()),
+    }
+}
+
+pub fn sign(config: &JwtConfig, material: &[u8], now: i64) -> Result<String, String> {
+    let mut claims = match &config.claims {
+        Value::Object(claims) => claims.clone(),
+        _ => return Err("JWT claims must be a JSON object".into()),
+    };
+    if let Some(expires_in) = config.expires_in {
+        claims.entry("iat").or_insert(now.into());
+        claims.entry("exp").or_insert((now + expires_in).into());
+    }
+    let mut header = Header::new(match config.algorithm {
+        JwtAlgorithm::Hs256 => Algorithm::HS256,
+        JwtAlgorithm::Rs256 => Algorithm::RS256,
+        JwtAlgorithm::Es256 => Algorithm::ES256,
+    });
+    header.kid = config.key_id.clone().filter(|kid| !kid.is_empty());
+    let key = encoding_key(config.algorithm, material)?;
+    jsonwebtoken::encode(&header, &claims, &key).map_err(|e| e.to_string())
+}
+
+pub async fn generate(config: &JwtConfig) -> Result<String, String> {
+    let material = load_key(&config.key).await?;
+    sign(config, &material, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{DecodingKey, Validation};
+    use serde_json::json;
+
+    #[test]
+    fn signs_hs256_with_generated_timestamps() {
+        let config = JwtConfig {
+            algorithm: JwtAlgorithm::Hs256,
+            key: JwtKey::Inline {
+                value: "secret".into(),
+            },
+            claims: json!({"sub": "svc-billing", "exp": 4_000_000_000i64}),
+            key_id: Some("k1".into()),
+            expires_in: Some(300),
+        };
+        let token = sign(&config, b"secret", 1_700_000_000).unwrap();
+        let decoded = jsonwebtoken::decode::<Value>(
+            &token,
+            &DecodingKey::from_secret(b"secret"),
+            &Validation::new(Algorithm::HS256),
+        )
+        .unwrap();
+        assert_eq!(decoded.header.kid.as_deref(), Some("k1"));
+        assert_eq!(decoded.claims["sub"], "svc-billing");
+        assert_eq!(decoded.claims["iat"], 1_700_000_000);
+        assert_eq!(decoded.claims["exp"], 4_000_000_000i64);
+    }
+
+    #[test]
+    fn rejects_bad_claims_and_keys() {
+        let mut config = JwtConfig {
+            algorithm: JwtAlgorithm::Rs256,
+            key: JwtKey::Inline {
+                value: "not a pem".into(),
+            },
+            claims: json!({}),
+            key_id: None,
+            expires_in: None,
+        };
+        assert!(sign(&config, b"not a pem", 0).is_err());
+        config.claims = json!(["not", "an", "object"]);
+        assert!(sign(&config, b"", 0).unwrap_err().contains("JSON object"));
+    }
+}
@@ -0,0 +1,467 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+// `pointer` is a JSON pointer into the spec, e.g. "/paths/~1pets/get".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub pointer: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+}
+
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn push(
+    findings: &mut Vec<LintFinding>,
+    rule: &str,
+    severity: Severity,
+    message: String,
+    pointer: String,
+) {
+    findings.push(LintFinding {
+        rule: rule.into(),
+        severity,
+        message,
+        pointer,
+    });
+}
+
+fn has_text(value: &Value, key: &str) -> bool {
+    value[key]
+        .as_str()
+        .is_some_and(|text| !text.trim().is_empty())
+}
+
+// Every `$ref` in the document with the pointer of the object holding it.
+fn collect_refs(value: &Value, pointer: &str, refs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map {
+                match (key.as_str(), item) {
+                    ("$ref", Value::String(target)) => {
+                        refs.push((pointer.to_string(), target.clone()))
+                    }
+                    _ => collect_refs(item, &format!("{}/{}", pointer, escape(key)), refs),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_refs(item, &format!("{}/{}", pointer, index), refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Case {
+    Camel,
+    Pascal,
+    Snake,
+    Kebab,
+}
+
+impl Case {
+    fn name(self) -> &'static str {
+        match self {
+            Case::Camel => "camelCase",
+            Case::Pascal => "PascalCase",
+            Case::Snake => "snake_case",
+            Case::Kebab => "kebab-case",
+        }
+    }
+}
+
+// None for names that fit several styles, such as "id", or none at all.
+fn case_of(name: &str) -> Option<Case> {
+    let first = name.chars().next()?;
+    let has_upper = name.chars().any(|c| c.is_ascii_uppercase());
+    let alphanumeric = |c: char| c.is_ascii_alphanumeric();
+    if name.contains('_') && !name.contains('-') && !has_upper {
+        return Some(Case::Snake);
+    }
+    if name.contains('-') && !name.contains('_') && !has_upper {
+        return Some(Case::Kebab);
+    }
+    if !name.chars().all(alphanumeric) || !has_upper {
+        return None;
+    }
+    if first.is_ascii_uppercase() {
+        Some(Case::Pascal)
+    } else {
+        Some(Case::Camel)
+    }
+}
+
+// Flags names that stray from the style most of their kind use.
+fn check_naming(findings: &mut Vec<LintFinding>, kind: &str, names: &[(String, String)]) {
+    let mut counts: BTreeMap<Case, usize> = BTreeMap::new();
+    for (name, _) in names {
+        if let Some(case) = case_of(name) {
+            *counts.entry(case).or_default() += 1;
+        }
+    }
+    let Some((&dominant, _)) = counts
+        .iter()
+        .max_by_key(|(case, count)| (**count, std::cmp::Reverse(**case)))
+    else {
+        return;
+    };
+    if counts.len() < 2 {
+        return;
+    }
+    for (name, pointer) in names {
+        if let Some(case) = case_of(name).filter(|case| *case != dominant) {
+            push(
+                findings,
+                "inconsistent-naming",
+                Severity::Info,
+                format!(
+                    "{} {} is {}, while most {}s are {}",
+                    kind,
+                    name,
+                    case.name(),
+                    kind,
+                    dominant.name()
+                ),
+                pointer.clone(),
+            );
+        }
+    }
+}
+
+fn property_names(schema: &Value, pointer: &str, names: &mut Vec<(String, String)>) {
+    if let Some(properties) = schema["properties"].as_object() {
+        for (name, property) in properties {
+            let pointer = format!("{}/properties/{}", pointer, escape(name));
+            names.push((name.clone(), pointer.clone()));
+            property_names(property, &pointer, names);
+        }
+    }
+    if schema["items"].is_object() {
+        property_names(&schema["items"], &format!("{}/items", pointer), names);
+    }
+}
+
+// Lints a list of parameters and returns the names of those in the path.
+fn check_parameters(
+    doc: &Value,
+    parameters: &Value,
+    pointer: &str,
+    owner: &str,
+    findings: &mut Vec<LintFinding>,
+    names: &mut Vec<(String, String)>,
+) -> HashSet<String> {
+    let mut path_params = HashSet::new();
+    for (index, param) in parameters.as_array().into_iter().flatten().enumerate() {
+        let param = resolve(doc, param);
+        let Some(name) = param["name"].as_str() else {
+            continue;
+        };
+        let param_pointer = format!("{}/{}", pointer, index);
+        if param["in"] == "path" {
+            path_params.insert(name.to_string());
+        }
+        // Header names follow HTTP conventions rather than the API's.
+        if param["in"] != "header" {
+            names.push((name.to_string(), param_pointer.clone()));
+        }
+        if !has_text(param, "description") {
+            push(
+                findings,
+                "parameter-description-missing",
+                Severity::Info,
+                format!("Parameter {} of {} has no description", name, owner),
+                param_pointer,
+            );
+        }
+    }
+    path_params
+}
+
+// Checks an OpenAPI 3 document; Swagger 2 specs are linted after conversion, so
+// pointers follow the OpenAPI 3 layout.
+pub fn lint(doc: &Value) -> LintReport {
+    let mut findings = Vec::new();
+    let mut operation_ids: HashMap<String, String> = HashMap::new();
+    let mut parameter_names = Vec::new();
+    let mut operation_names = Vec::new();
+
+    for (path, item) in doc["paths"].as_object().into_iter().flatten() {
+        let path_pointer = format!("/paths/{}", escape(path));
+        let templated: Vec<&str> = path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .collect();
+        // Parameters of the path item apply to each of its operations.
+        let shared_path_params = check_parameters(
+            doc,
+            &item["parameters"],
+            &format!("{}/parameters", path_pointer),
+            path,
+            &mut findings,
+            &mut parameter_names,
+        );
+        for method in METHODS {
+            let operation = &item[method];
+            if !operation.is_object() {
+                continue;
+            }
+            let pointer = format!("{}/{}", path_pointer, method);
+            let label = format!("{} {}", method.to_uppercase(), path);
+            match operation["operationId"].as_str() {
+                Some(id) => {
+                    operation_names.push((id.to_string(), format!("{}/operationId", pointer)));
+                    if let Some(first) = operation_ids.get(id) {
+                        push(
+                            &mut findings,
+                            "operation-id-duplicate",
+                            Severity::Error,
+                            format!("operationId {} of {} is also used by {}", id, label, first),
+                            format!("{}/operationId", pointer),
+                        );
+                    } else {
+                        operation_ids.insert(id.to_string(), label.clone());
+                    }
+                }
+                None => push(
+                    &mut findings,
+                    "operation-id-missing",
+                    Severity::Warning,
+                    format!("{} has no operationId", label),
+                    pointer.clone(),
+                ),
+            }
+            if !has_text(operation, "summary") && !has_text(operation, "description") {
+                push(
+                    &mut findings,
+                    "operation-description-missing",
+                    Severity::Warning,
+                    format!("{} has neither a summary nor a description", label),
+                    pointer.clone(),
+                );
+            }
+            let responses = operation["responses"].as_object();
+            if !responses
+                .is_some_and(|responses| responses.keys().any(|status| status.starts_with('2')))
+            {
+                push(
+                    &mut findings,
+                    "success-response-missing",
+                    Severity::Warning,
+                    format!("{} declares no 2XX response", label),
+                    format!("{}/responses", pointer),
+                );
+            }
+
+            let mut path_params = shared_path_params.clone();
+            path_params.extend(check_parameters(
+                doc,
+                &operation["parameters"],
+                &format!("{}/parameters", pointer),
+                &label,
+                &mut findings,
+                &mut parameter_names,
+            ));
+            for name in templated
+                .iter()
+                .filter(|name| !path_params.contains(**name))
+            {
+                push(
+                    &mut findings,
+                    "path-parameter-undeclared",
+                    Severity::Error,
+                    format!("{} does not declare the path parameter {}", label, name),
+                    pointer.clone(),
+                );
+            }
+        }
+    }
+
+    let mut refs = Vec::new();
+    collect_refs(doc, "", &mut refs);
+    for (pointer, target) in &refs {
+        let resolves = target
+            .strip_prefix('#')
+            .is_some_and(|fragment| doc.pointer(fragment).is_some());
+        if !resolves {
+            push(
+                &mut findings,
+                "undefined-ref",
+                Severity::Error,
+                format!("{} does not resolve", target),
+                pointer.clone(),
+            );
+        }
+    }
+
+    let mut schema_names = Vec::new();
+    let mut properties = Vec::new();
+    for (name, schema) in doc["components"]["schemas"]
+        .as_object()
+        .into_iter()
+        .flatten()
+    {
+        let pointer = format!("/components/schemas/{}", escape(name));
+        schema_names.push((name.clone(), pointer.clone()));
+        property_names(schema, &pointer, &mut properties);
+        // References from inside the schema itself do not count.
+        let target = format!("#{}", pointer);
+        let used = refs.iter().any(|(from, to)| {
+            *to == target && !from.starts_with(&format!("{}/", pointer)) && *from != pointer
+        });
+        if !used {
+            push(
+                &mut findings,
+                "unused-schema",
+                Severity::Warning,
+                format!("Schema {} is never referenced", name),
+                pointer,
+            );
+        }
+        if !has_text(schema, "description") && schema["$ref"].is_null() {
+            push(
+                &mut findings,
+                "schema-description-missing",
+                Severity::Info,
+                format!("Schema {} has no description", name),
+                format!("/components/schemas/{}", escape(name)),
+            );
+        }
+    }
+
+    check_naming(&mut findings, "operationId", &operation_names);
+    check_naming(&mut findings, "parameter", &parameter_names);
+    check_naming(&mut findings, "schema", &schema_names);
+    check_naming(&mut findings, "property", &properties);
+
+    findings.sort_by(|a, b| (a.severity, &a.pointer).cmp(&(b.severity, &b.pointer)));
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    LintReport {
+        errors: count(Severity::Error),
+        warnings: count(Severity::Warning),
+        infos: count(Severity::Info),
+        findings,
+    }
+}
+
+fn resolve<'a>(doc: &'a Value, value: &'a Value) -> &'a Value {
+    value["$ref"]
+        .as_str()
+        .and_then(|target| doc.pointer(target.trim_start_matches('#')))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_missing_ids_broken_refs_unused_schemas_and_naming() {
+        let doc = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/pets/{petId}": {
+                    "get": {
+                        "operationId": "getPet",
+                        "summary": "One pet",
+                        "parameters": [
+                            { "name": "petId", "in": "path", "required": true, "description": "Id" },
+                            { "name": "include_owner", "in": "query", "description": "Expand" }
+                        ],
+                        "responses": {
+                            "200": { "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } } } }
+                        }
+                    },
+                    "delete": {
+                        "operationId": "getPet",
+                        "responses": { "404": { "$ref": "#/components/responses/NotFound" } }
+                    }
+                },
+                "/owners": {
+                    "post": {
+                        "summary": "New owner",
+                        "parameters": [{ "name": "dryRun", "in": "query", "description": "No writes" }],
+                        "responses": { "201": { "description": "Created" } }
+                    }
+                }
+            },
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "description": "A pet",
+                        "properties": {
+                            "petName": { "type": "string" },
+                            "ownerId": { "type": "string" },
+                            "birth_date": { "type": "string" },
+                            "parent": { "$ref": "#/components/schemas/Pet" }
+                        }
+                    },
+                    "Orphan": { "description": "Unused", "properties": { "self": { "$ref": "#/components/schemas/Orphan" } } }
+                }
+            }
+        });
+        let report = lint(&doc);
+        let rules: Vec<(&str, &str)> = report
+            .findings
+            .iter()
+            .map(|f| (f.rule.as_str(), f.pointer.as_str()))
+            .collect();
+        assert!(rules.contains(&(
+            "operation-id-duplicate",
+            "/paths/~1pets~1{petId}/delete/operationId"
+        )));
+        assert!(rules.contains(&(
+            "undefined-ref",
+            "/paths/~1pets~1{petId}/delete/responses/404"
+        )));
+        assert!(rules.contains(&("path-parameter-undeclared", "/paths/~1pets~1{petId}/delete")));
+        assert!(rules.contains(&(
+            "success-response-missing",
+            "/paths/~1pets~1{petId}/delete/responses"
+        )));
+        assert!(rules.contains(&("operation-id-missing", "/paths/~1owners/post")));
+        assert!(rules.contains(&(
+            "operation-description-missing",
+            "/paths/~1pets~1{petId}/delete"
+        )));
+        assert!(rules.contains(&("unused-schema", "/components/schemas/Orphan")));
+        assert!(!rules.contains(&("unused-schema", "/components/schemas/Pet")));
+        assert!(rules.contains(&(
+            "inconsistent-naming",
+            "/paths/~1pets~1{petId}/get/parameters/1"
+        )));
+        assert!(rules.contains(&(
+            "inconsistent-naming",
+            "/components/schemas/Pet/properties/birth_date"
+        )));
+        assert_eq!(report.findings[0].severity, Severity::Error);
+        assert_eq!(report.errors, 3);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::http::{RequestInput, ResponseData};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Upper bounds, in milliseconds, of the latency histogram's buckets; slower responses
+// land in a final open-ended bucket.
+const HISTOGRAM_BOUNDS_MS: &[f64] = &[
+    5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+// Distinct error messages counted separately; the rest are lumped together.
+const MAX_ERROR_KINDS: usize = 20;
+const DEFAULT_CONCURRENCY: usize = 10;
+const MAX_CONCURRENCY: usize = 1000;
+
+// Sends `request` from `concurrency` workers until `duration_secs` pass or
+// `iterations` requests were sent, whichever comes first; with neither set, 100
+// requests are sent. Workers start spread out over `ramp_up_secs`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LoadTestOptions {
+    pub test_id: Option<String>,
+    pub request: RequestInput,
+    pub concurrency: Option<usize>,
+    pub duration_secs: Option<u64>,
+    pub iterations: Option<u64>,
+    pub ramp_up_secs: u64,
+}
+
+impl LoadTestOptions {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or(DEFAULT_CONCURRENCY)
+            .clamp(1, MAX_CONCURRENCY)
+    }
+
+    pub fn iterations(&self) -> Option<u64> {
+        match (self.iterations, self.duration_secs) {
+            (None, None) => Some(100),
+            (iterations, _) => iterations,
+        }
+    }
+}
+
+// Why a response counts as an error: a 4xx/5xx status or a failed assertion.
+pub fn response_error(response: &ResponseData) -> Option<String> {
+    if response.status >= 400 {
+        return Some(format!("HTTP {}", response.status));
+    }
+    response
+        .assertions
+        .iter()
+        .find(|result| !result.passed)
+        .map(|result| match &result.message {
+            Some(message) => format!("Assertion failed: {}", message),
+            None => "Assertion failed".to_string(),
+        })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistogramBucket {
+    // None for the last bucket, which has no upper bound.
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+// Sent as `load-test-progress` while the test runs and returned when it ends.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LoadTestReport {
+    pub test_id: String,
+    pub done: bool,
+    pub elapsed_ms: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    // Completed requests per second.
+    pub throughput: f64,
+    pub latency: LatencyStats,
+    pub histogram: Vec<HistogramBucket>,
+    pub status_codes: BTreeMap<u16, u64>,
+    pub error_kinds: BTreeMap<String, u64>,
+}
+
+// Collects the outcome of every request. Latencies are kept in microseconds.
+#[derive(Default)]
+pub struct LoadRecorder {
+    latencies: Vec<u64>,
+    errors: u64,
+    status_codes: BTreeMap<u16, u64>,
+    error_kinds: BTreeMap<String, u64>,
+}
+
+fn percentile(sorted: &[u64], p: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).saturating_sub(1);
+    sorted[rank] as f64 / 1000.0
+}
+
+impl LoadRecorder {
+    // `status` is None when no response came back; `error` says why the request failed,
+    // whether or not it got a response.
+    pub fn record(&mut self, micros: u64, status: Option<u16>, error: Option<String>) {
+        self.latencies.push(micros);
+        if let Some(status) = status {
+            *self.status_codes.entry(status).or_default() += 1;
+        }
+        if let Some(error) = error {
+            self.errors += 1;
+            let kind = if self.error_kinds.len() < MAX_ERROR_KINDS
+                || self.error_kinds.contains_key(&error)
+            {
+                error
+            } else {
+                "Other errors".to_string()
+            };
+            *self.error_kinds.entry(kind).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self, test_id: &str, elapsed_ms: u64, done: bool) -> LoadTestReport {
+        let mut sorted = self.latencies.clone();
+        sorted.sort_unstable();
+        let requests = sorted.len() as u64;
+        let mut histogram: Vec<HistogramBucket> = HISTOGRAM_BOUNDS_MS
+            .iter()
+            .map(|bound| HistogramBucket {
+                le_ms: Some(*bound),
+                count: 0,
+            })
+            .chain(std::iter::once(HistogramBucket {
+                le_ms: None,
+                count: 0,
+            }))
+            .collect();
+        for micros in &sorted {
+            let ms = *micros as f64 / 1000.0;
+            let bucket = HISTOGRAM_BOUNDS_MS
+                .iter()
+                .position(|bound| ms <= *bound)
+                .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+            histogram[bucket].count += 1;
+        }
+        LoadTestReport {
+            test_id: test_id.to_string(),
+            done,
+            elapsed_ms,
+            requests,
+            errors: self.errors,
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                self.errors as f64 / requests as f64
+            },
+            throughput: if elapsed_ms == 0 {
+                0.0
+            } else {
+                requests as f64 * 1000.0 / elapsed_ms as f64
+            },
+            latency: LatencyStats {
+                min_ms: sorted.first().map_or(0.0, |min| *min as f64 / 1000.0),
+                mean_ms: if requests == 0 {
+                    0.0
+                } else {
+                    sorted.iter().sum::<u64>() as f64 / requests as f64 / 1000.0
+                },
+                p50_ms: percentile(&sorted, 50),
+                p90_ms: percentile(&sorted, 90),
+                p95_ms: percentile(&sorted, 95),
+                p99_ms: percentile(&sorted, 99),
+                max_ms: sorted.last().map_or(0.0, |max| *max as f64 / 1000.0),
+            },
+            histogram,
+            status_codes: self.status_codes.clone(),
+            error_kinds: self.error_kinds.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_percentiles_histogram_and_errors() {
+        let mut recorder = LoadRecorder::default();
+        for ms in 1..=100u64 {
+            let status = if ms % 10 == 0 { 500 } else { 200 };
+            let error = (status == 500).then(|| "Status 500".to_string());
+            recorder.record(ms * 1000, Some(status), error);
+        }
+        recorder.record(20_000_000, None, Some("timed out".into()));
+        let report = recorder.report("t", 2000, true);
+        assert_eq!(report.requests, 101);
+        assert_eq!(report.errors, 11);
+        assert_eq!(report.throughput, 50.5);
+        assert_eq!(report.latency.p50_ms, 51.0);
+        assert_eq!(report.latency.p99_ms, 100.0);
+        assert_eq!(report.latency.max_ms, 20000.0);
+        assert_eq!(report.status_codes[&500], 10);
+        assert_eq!(report.error_kinds["timed out"], 1);
+        assert_eq!(report.histogram[0].count, 5);
+        assert_eq!(report.histogram.last().unwrap().count, 1);
+        assert_eq!(
+            report
+                .histogram
+                .iter()
+                .map(|bucket| bucket.count)
+                .sum::<u64>(),
+            101
+        );
+
+        let options: LoadTestOptions = serde_json::from_value(serde_json::json!({
+            "request": { "method": "GET", "url": "https://api.test" },
+            "concurrency": 5000
+        }))
+        .unwrap();
+        assert_eq!(options.concurrency(), MAX_CONCURRENCY);
+        assert_eq!(options.iterations(), Some(100));
+    }
+}
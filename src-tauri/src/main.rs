@@ -3,17 +3,141 @@
     windows_subsystem = "windows"
 )]
 
-use tauri::{command, State, Manager};
+mod assertions;
+mod auth;
+mod body;
+mod bruno;
+mod changelog;
+mod checksum;
+mod cli;
+mod client;
+mod contract;
+mod cookies;
+mod coverage;
+mod decompress;
+mod diff;
+mod digest;
+mod docs;
+mod download;
+mod environments;
+mod extract;
+mod format;
+mod graphql;
+mod grpc;
+mod har;
+mod hexdump;
+mod history;
+mod http;
+mod insomnia;
+mod jmespath;
+mod jsonrpc;
+mod jwt;
+mod lint;
+mod loadtest;
+mod mock;
+mod monitors;
+mod mqtt;
+mod ntlm;
+mod oauth2;
+mod offline;
+mod params;
+mod postman;
+mod profiles;
+mod proto;
+mod proxy;
+mod query;
+mod ratelimit;
+mod refs;
+mod response_file;
+mod retry;
+mod runner;
+mod saved;
+mod scripting;
+mod secrets;
+mod security;
+mod servers;
+mod settings;
+mod signing;
+mod sigv4;
+mod snippet;
+mod soap;
+mod spec;
+mod specdiff;
+mod specgen;
+mod storage;
+mod swagger;
+mod template;
+mod timing;
+mod watcher;
+mod webhook;
+mod workspaces;
+
+use assertions::Assertion;
+use auth::Auth;
+use checksum::Checksum;
+use chrono::{DateTime, Utc};
+use client::{ClientKey, ClientManager, HttpVersion};
+use contract::{ContractOperation, DeclaredParameter, DeclaredResponse, RequestValidation};
+use cookies::CookieInfo;
+use coverage::{CoverageOperation, CoverageReport};
+use dashmap::DashMap;
+use diff::{DiffSide, ResponseDiff};
+use docs::DocFormat;
+use download::{download_parallel, DownloadProgress, DownloadResult, SegmentProgress};
+use environments::{Environment, Variable};
+use extract::{Extracted, Extraction};
+use format::{BodyLanguage, FormatStyle, FormattedBody};
+use futures_util::future::{abortable, join, join_all, AbortHandle};
+use futures_util::stream::{self, StreamExt};
+use graphql::{GraphqlQuery, GraphqlSchema};
+use grpc::{DescriptorSource, GrpcCall, GrpcResponse, GrpcService};
+use har::HarImport;
+use hexdump::HexPage;
+use history::{HistoryEntry, HistoryFilter};
+use http::{
+    execute_request, MultipartFile, MultipartPayload, RequestInput, RequestSpec, ResponseData,
+    ResponseProgress, UploadCallback, UploadProgress,
+};
+use jsonrpc::JsonRpcRequest;
+use jwt::JwtConfig;
+use lint::LintReport;
+use loadtest::{LoadRecorder, LoadTestOptions, LoadTestReport};
+use mock::{MockHit, MockOptions, MockResponse, MockRoute, MockServer, MockServerInfo};
+use monitors::{Monitor, MonitorAlert, MonitorCheck, MonitorStatus};
+use mqtt::{MqttMessage, MqttOptions, MqttPublish, MqttSubscription};
+use oauth2::{grant_cache_key, OAuthConfig, OAuthToken, TokenStore, DEFAULT_ENVIRONMENT};
+use offline::{NetworkStatus, QueuedRequest, QueuedRequestSent, WorkspaceQueue};
+use params::{apply_parameters, ParameterValue};
+use profiles::{AuthProfile, ProfileStore};
+use proxy::{CertificateAuthority, Exchange, Proxy, ProxyInfo, ProxyOptions};
+use query::{BodySource, QueryLanguage, QueryResult};
+use ratelimit::{RateLimitWait, RateLimiter, Throttle};
 use reqwest::Client;
+use response_file::{BodyChunk, SearchResult};
+use retry::RetryPolicy;
+use runner::{IterationResult, RunContext, RunOptions, RunStep, RunSummary, StepResult};
+use saved::{CollectionImport, SavedExample, SavedRequest};
+use secrets::Secrets;
+use security::{select_credentials, SecurityRequirement, SecurityScheme};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
-use tokio::time::{sleep, Duration};
-use chrono::{DateTime, Utc};
-use tokio::fs::File;
-use futures_util::StreamExt;
 use serde_json::{Map, Value};
-use std::path::Path;
+use servers::{parse_servers, Server};
+use settings::{load_settings, save_settings, Settings};
+use snippet::SnippetLanguage;
+use specdiff::SpecDiff;
+use specgen::GeneratedSpec;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use storage::Store;
+use tauri::{command, Manager, State};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{sleep, Duration, Instant};
+use watcher::{local_spec_path, SpecWatcher};
+use webhook::{WebhookCapture, WebhookInfo, WebhookListener, WebhookOptions};
+use workspaces::{workspace_dir, Workspace, WorkspaceRegistry};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct Parameter {
@@ -23,6 +147,12 @@ struct Parameter {
     required: bool,
     example: Option<serde_json::Value>,
     enum_values: Option<Vec<String>>,
+    schema_type: Option<String>,
+    format: Option<String>,
+    default: Option<Value>,
+    deprecated: bool,
+    style: String,
+    explode: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -42,12 +172,44 @@ struct ResponseSchema {
     schema: Option<Value>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ResponseHeader {
+    name: String,
+    description: Option<String>,
+    required: bool,
+    example: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct EndpointResponse {
+    description: Option<String>,
+    media_types: Vec<String>,
+    example: Option<String>,
+    // Named examples of the preferred media type, for the mock server to choose from.
+    #[serde(default)]
+    examples: BTreeMap<String, String>,
+    headers: Vec<ResponseHeader>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ExternalDocs {
+    url: String,
+    description: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct Endpoint {
     method: String,
     path: String,
+    // The spec path without the server prefix, and the servers the path item or
+    // operation declares in place of the collection's.
+    route: String,
+    servers: Vec<Server>,
     summary: Option<String>,
     description: Option<String>,
+    operation_id: Option<String>,
+    deprecated: bool,
+    external_docs: Option<ExternalDocs>,
     parameters: Vec<Parameter>,
     body_example: Option<String>,
     body_description: Option<String>,
@@ -55,34 +217,127 @@ struct Endpoint {
     body_media_types: Vec<String>,
     body_fields: Vec<BodyField>,
     body_fields_type: Option<String>,
+    // The JSON request body's schema, refs expanded, for checking requests before they go.
+    #[serde(default)]
+    body_schema: Option<Value>,
     response_schemas: Vec<ResponseSchema>,
+    responses: BTreeMap<String, EndpointResponse>,
+    security: Vec<SecurityRequirement>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct TagInfo {
+    name: String,
+    description: Option<String>,
+    external_docs: Option<ExternalDocs>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct TagGroup {
+    name: String,
+    tags: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct OpenApiCollection {
     name: String,
+    // Identifies the collection. Duplicates get a fresh id and keep reading the spec
+    // from the original's URL, kept in `source`.
     url: String,
+    #[serde(default)]
+    source: Option<String>,
+    // A name the user picked, which survives refreshes of the spec's title.
+    #[serde(default)]
+    name_override: Option<String>,
     groups: HashMap<String, Vec<Endpoint>>,
+    // Groups in display order: tags declared at the top level first, as listed, then
+    // any other tag used by an operation. `tag_groups` comes from `x-tagGroups`.
+    tags: Vec<TagInfo>,
+    tag_groups: Vec<TagGroup>,
+    security_schemes: BTreeMap<String, SecurityScheme>,
+    // Digests of `components.schemas`, kept to report schema changes on refresh.
+    schema_digests: BTreeMap<String, String>,
+    servers: Vec<Server>,
+    server_index: usize,
+    server_variables: HashMap<String, String>,
     last_updated: DateTime<Utc>,
     etag: Option<String>,
+    // Validators for servers without ETags: the Last-Modified header, and a digest of
+    // the document so identical content is not parsed again.
+    last_modified: Option<String>,
+    content_hash: Option<String>,
+    // Sent when fetching the spec itself, e.g. for specs behind an API gateway.
+    fetch_headers: HashMap<String, String>,
+    fetch_auth: Option<Auth>,
     sync_enabled: bool,
+    sync_interval_secs: u64,
 }
 
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const SYNC_TICK: Duration = Duration::from_secs(5);
+// Collections refreshed at once by the background sync, and how long each may take.
+const SYNC_CONCURRENCY: usize = 4;
+const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_SYNC_INTERVAL_SECS: u64 = 60;
+const MIN_SYNC_INTERVAL_SECS: u64 = 10;
+const MONITOR_TICK: Duration = Duration::from_secs(15);
+// Due monitors checked at once, so a slow one does not hold up the rest.
+const MONITOR_CONCURRENCY: usize = 4;
+const QUEUE_TICK: Duration = Duration::from_secs(5);
+const LOAD_TEST_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Longest a run waits between steps for a server's rate limit.
+const MAX_RATE_LIMIT_PAUSE: Duration = Duration::from_secs(60);
+// `tauri.bundle.identifier`, which names the data dir and native notifications.
+const APP_IDENTIFIER: &str = "com.restman.dev";
+
 struct AppState {
-    collections: Arc<Mutex<HashMap<String, OpenApiCollection>>>,
-    client: Client,
+    // Sharded, so a command reading one collection does not wait on a sync writing
+    // another. Entries must not be held across an await, nor while touching another.
+    collections: DashMap<String, OpenApiCollection>,
+    clients: ClientManager,
+    // Each handle is tagged with the task that registered it, so a finished task never
+    // removes the entry of a newer one reusing its id.
+    in_flight: Arc<Mutex<HashMap<String, (u64, AbortHandle)>>>,
+    settings: Mutex<Settings>,
+    // The active workspace's tokens and profiles, swapped along with its store.
+    oauth_tokens: RwLock<Arc<TokenStore>>,
+    auth_profiles: RwLock<Arc<ProfileStore>>,
+    spec_watcher: SpecWatcher,
+    // Collection URLs in display order.
+    collection_order: Mutex<Vec<String>>,
+    workspaces: WorkspaceRegistry,
+    // The active workspace's store, swapped when switching workspaces.
+    store: RwLock<Arc<Store>>,
+    secrets: Secrets,
+    data_dir: PathBuf,
+    // Open MQTT connections, by the id the frontend chose for them.
+    mqtt_sessions: Mutex<HashMap<String, mqtt::Session>>,
+    // Running mock servers, by collection URL.
+    mock_servers: Mutex<HashMap<String, MockServer>>,
+    // How each collection's last sync went, by collection URL.
+    sync_status: DashMap<String, SyncStatus>,
+    // The recording proxy, while it runs.
+    proxy: Mutex<Option<Proxy>>,
+    // Webhook listeners by the id the frontend gave them.
+    webhook_listeners: Mutex<HashMap<String, WebhookListener>>,
+    // Holds outgoing requests back to the configured rates.
+    rate_limiter: Arc<RateLimiter>,
+    // What the offline queue's last probes found.
+    network: Mutex<NetworkStatus>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct MultipartFile {
-    name: String,
-    paths: Vec<String>,
-}
+impl AppState {
+    fn store(&self) -> Arc<Store> {
+        self.store.read().unwrap().clone()
+    }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct MultipartPayload {
-    fields: HashMap<String, String>,
-    files: Vec<MultipartFile>,
+    fn oauth_tokens(&self) -> Arc<TokenStore> {
+        self.oauth_tokens.read().unwrap().clone()
+    }
+
+    fn auth_profiles(&self) -> Arc<ProfileStore> {
+        self.auth_profiles.read().unwrap().clone()
+    }
 }
 
 fn resolve_ref<'a>(doc: &'a Value, value: &'a Value, depth: usize) -> &'a Value {
@@ -150,6 +405,52 @@ fn extract_enum_values(doc: &Value, schema: &Value) -> Option<Vec<String>> {
     }
 }
 
+// OpenAPI 3.1 allows `type: [string, "null"]`; the first non-null type is what the
+// value looks like.
+fn schema_type_name(doc: &Value, schema: &Value) -> Option<String> {
+    match resolve_ref(doc, schema, 0).get("type")? {
+        Value::String(name) => Some(name.clone()),
+        Value::Array(names) => names
+            .iter()
+            .filter_map(|name| name.as_str())
+            .find(|name| *name != "null")
+            .map(|name| name.to_string()),
+        _ => None,
+    }
+}
+
+fn schema_format(doc: &Value, schema: &Value) -> Option<String> {
+    resolve_ref(doc, schema, 0)
+        .get("format")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn schema_default(doc: &Value, schema: &Value) -> Option<Value> {
+    resolve_ref(doc, schema, 0)
+        .get("default")
+        .filter(|v| !v.is_null())
+        .cloned()
+}
+
+// Missing `style` and `explode` take the defaults the spec defines per location:
+// `form` with explode for query and cookie parameters, `simple` without for the rest.
+fn parameter_style(param: &Value, in_type: &str) -> (String, bool) {
+    let style = param
+        .get("style")
+        .and_then(|v| v.as_str())
+        .unwrap_or(match in_type {
+            "query" | "cookie" => "form",
+            _ => "simple",
+        })
+        .to_string();
+    let explode = param
+        .get("explode")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(style == "form");
+    (style, explode)
+}
+
 fn extract_required_fields(schema: &Value) -> std::collections::HashSet<String> {
     schema
         .get("required")
@@ -183,24 +484,223 @@ fn extract_schema_example(doc: &Value, schema: &Value) -> Option<Value> {
     None
 }
 
-fn build_example_from_schema(doc: &Value, schema: &Value, depth: usize) -> Option<Value> {
+// Folds `allOf` branches into one schema: properties and other keywords from every
+// branch (the schema's own win), the union of `required`, and object examples pushed
+// down onto the matching properties so a partial example doesn't hide the rest.
+fn merge_all_of(doc: &Value, schema: &Value, depth: usize) -> Value {
+    let resolved = resolve_ref(doc, schema, 0);
+    let mut merged = match resolved.as_object() {
+        Some(map) => map.clone(),
+        None => return resolved.clone(),
+    };
+    let branches = match merged.remove("allOf") {
+        Some(Value::Array(branches)) => branches,
+        _ => return Value::Object(merged),
+    };
+    let mut properties = match merged.remove("properties") {
+        Some(Value::Object(props)) => props,
+        _ => Map::new(),
+    };
+    let mut required: Vec<Value> = match merged.remove("required") {
+        Some(Value::Array(items)) => items,
+        _ => Vec::new(),
+    };
+    let mut examples: Vec<Value> = merged.remove("example").into_iter().collect();
+    for branch in &branches {
+        let branch = if depth > 6 {
+            resolve_ref(doc, branch, 0).clone()
+        } else {
+            merge_all_of(doc, branch, depth + 1)
+        };
+        let Value::Object(branch) = branch else {
+            continue;
+        };
+        for (key, value) in branch {
+            match (key.as_str(), value) {
+                ("properties", Value::Object(props)) => {
+                    for (name, prop) in props {
+                        properties.entry(name).or_insert(prop);
+                    }
+                }
+                ("required", Value::Array(items)) => {
+                    for item in items {
+                        if !required.contains(&item) {
+                            required.push(item);
+                        }
+                    }
+                }
+                ("example", example) => examples.push(example),
+                (_, value) => {
+                    merged.entry(key).or_insert(value);
+                }
+            }
+        }
+    }
+    // Earlier examples (the schema's own first) take precedence.
+    for example in examples.into_iter().rev() {
+        match example {
+            Value::Object(values)
+                if !properties.is_empty() || merged.get("type") == Some(&Value::from("object")) =>
+            {
+                for (name, value) in values {
+                    let prop = properties
+                        .entry(name)
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    let mut expanded = resolve_ref(doc, prop, 0).clone();
+                    if let Some(map) = expanded.as_object_mut() {
+                        map.insert("example".into(), value);
+                    }
+                    *prop = expanded;
+                }
+            }
+            other => {
+                merged.insert("example".into(), other);
+            }
+        }
+    }
+    if !properties.is_empty() {
+        merged.insert("properties".into(), Value::Object(properties));
+        merged.entry("type").or_insert(Value::from("object"));
+    }
+    if !required.is_empty() {
+        merged.insert("required".into(), Value::Array(required));
+    }
+    Value::Object(merged)
+}
+
+// Builds the first `oneOf` branch and sets the discriminator property to the key that
+// maps to it. Mapping values may be full refs or bare schema names; without a mapping
+// the schema name itself is the key.
+fn discriminated_example(
+    doc: &Value,
+    options: &[Value],
+    discriminator: &Value,
+    direction: ExampleDirection,
+    depth: usize,
+) -> Option<Value> {
+    let property = discriminator.get("propertyName")?.as_str()?;
+    let mapping = discriminator.get("mapping").and_then(|v| v.as_object());
+    for option in options {
+        let Some(target) = option.get("$ref").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let name = target.rsplit('/').next().unwrap_or(target);
+        let key = mapping
+            .and_then(|mapping| {
+                mapping
+                    .iter()
+                    .find(|(_, mapped)| mapped.as_str().is_some_and(|m| m == target || m == name))
+                    .map(|(key, _)| key.clone())
+            })
+            .unwrap_or_else(|| name.to_string());
+        let mut example = build_example_from_schema(doc, option, direction, depth + 1)?;
+        if let Some(obj) = example.as_object_mut() {
+            obj.insert(property.to_string(), Value::from(key));
+        }
+        return Some(example);
+    }
+    None
+}
+
+// Request bodies leave out `readOnly` properties (ids, timestamps the server assigns)
+// and responses leave out `writeOnly` ones (passwords and other inputs).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExampleDirection {
+    Request,
+    Response,
+}
+
+fn skipped_for(doc: &Value, schema: &Value, direction: ExampleDirection) -> bool {
+    let flag = match direction {
+        ExampleDirection::Request => "readOnly",
+        ExampleDirection::Response => "writeOnly",
+    };
+    resolve_ref(doc, schema, 0)
+        .get(flag)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+// Removes the properties `direction` leaves out from an example the spec wrote out in
+// full, following the schema into nested objects and arrays.
+fn strip_flagged(
+    doc: &Value,
+    schema: &Value,
+    example: Value,
+    direction: ExampleDirection,
+    depth: usize,
+) -> Value {
+    if depth > 6 {
+        return example;
+    }
+    let mut resolved = resolve_ref(doc, schema, 0).clone();
+    if resolved.get("allOf").is_some() {
+        resolved = merge_all_of(doc, &resolved, depth);
+    }
+    match example {
+        Value::Object(values) => {
+            let props = resolved.get("properties").and_then(|v| v.as_object());
+            let kept = values
+                .into_iter()
+                .filter_map(
+                    |(name, value)| match props.and_then(|props| props.get(&name)) {
+                        Some(prop) if skipped_for(doc, prop, direction) => None,
+                        Some(prop) => {
+                            Some((name, strip_flagged(doc, prop, value, direction, depth + 1)))
+                        }
+                        None => Some((name, value)),
+                    },
+                )
+                .collect();
+            Value::Object(kept)
+        }
+        Value::Array(items) => match resolved.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| strip_flagged(doc, item_schema, item, direction, depth + 1))
+                    .collect(),
+            ),
+            None => Value::Array(items),
+        },
+        other => other,
+    }
+}
+
+fn build_example_from_schema(
+    doc: &Value,
+    schema: &Value,
+    direction: ExampleDirection,
+    depth: usize,
+) -> Option<Value> {
     if depth > 6 {
         return None;
     }
     let resolved = resolve_ref(doc, schema, 0);
+    if resolved.get("allOf").is_some() {
+        let merged = merge_all_of(doc, resolved, depth);
+        return build_example_from_schema(doc, &merged, direction, depth + 1);
+    }
     if let Some(example) = extract_schema_example(doc, resolved) {
-        return Some(example);
+        return Some(strip_flagged(doc, resolved, example, direction, depth));
     }
     if let Some(one_of) = resolved.get("oneOf").and_then(|v| v.as_array()) {
+        if let Some(discriminator) = resolved.get("discriminator") {
+            if let Some(example) =
+                discriminated_example(doc, one_of, discriminator, direction, depth)
+            {
+                return Some(example);
+            }
+        }
         for option in one_of {
-            if let Some(example) = build_example_from_schema(doc, option, depth + 1) {
+            if let Some(example) = build_example_from_schema(doc, option, direction, depth + 1) {
                 return Some(example);
             }
         }
     }
     if let Some(any_of) = resolved.get("anyOf").and_then(|v| v.as_array()) {
         for option in any_of {
-            if let Some(example) = build_example_from_schema(doc, option, depth + 1) {
+            if let Some(example) = build_example_from_schema(doc, option, direction, depth + 1) {
                 return Some(example);
             }
         }
@@ -210,7 +710,12 @@ fn build_example_from_schema(doc: &Value, schema: &Value, depth: usize) -> Optio
         let mut obj = Map::new();
         if let Some(props) = resolved.get("properties").and_then(|v| v.as_object()) {
             for (name, prop_schema) in props {
-                if let Some(example) = build_example_from_schema(doc, prop_schema, depth + 1) {
+                if skipped_for(doc, prop_schema, direction) {
+                    continue;
+                }
+                if let Some(example) =
+                    build_example_from_schema(doc, prop_schema, direction, depth + 1)
+                {
                     obj.insert(name.clone(), example);
                 }
             }
@@ -219,7 +724,7 @@ fn build_example_from_schema(doc: &Value, schema: &Value, depth: usize) -> Optio
     }
     if schema_type == Some("array") {
         if let Some(items) = resolved.get("items") {
-            if let Some(example) = build_example_from_schema(doc, items, depth + 1) {
+            if let Some(example) = build_example_from_schema(doc, items, direction, depth + 1) {
                 return Some(Value::Array(vec![example]));
             }
         }
@@ -242,13 +747,26 @@ fn build_example_from_schema(doc: &Value, schema: &Value, depth: usize) -> Optio
 
 fn expand_query_object_parameters(doc: &Value, param: &Value) -> Option<Vec<Parameter>> {
     let resolved = resolve_ref(doc, param, 0);
-    let in_type = resolved.get("in").and_then(|v| v.as_str()).unwrap_or("query");
+    let in_type = resolved
+        .get("in")
+        .and_then(|v| v.as_str())
+        .unwrap_or("query");
     if in_type != "query" {
         return None;
     }
+    // Exploded form objects send each property as its own parameter and deepObject as
+    // `name[property]`; every other style keeps the object as one parameter.
+    let param_name = resolved.get("name").and_then(|v| v.as_str()).unwrap_or("");
+    let deep = match parameter_style(resolved, in_type) {
+        (style, _) if style == "deepObject" => true,
+        (style, true) if style == "form" => false,
+        _ => return None,
+    };
     let schema = resolved.get("schema")?;
     let resolved_schema = resolve_ref(doc, schema, 0);
-    let props = resolved_schema.get("properties").and_then(|v| v.as_object())?;
+    let props = resolved_schema
+        .get("properties")
+        .and_then(|v| v.as_object())?;
     let required_fields = extract_required_fields(resolved_schema);
     let mut expanded = Vec::new();
     for (name, prop_schema) in props {
@@ -258,12 +776,25 @@ fn expand_query_object_parameters(doc: &Value, param: &Value) -> Option<Vec<Para
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
         expanded.push(Parameter {
-            name: name.clone(),
+            name: if deep {
+                format!("{}[{}]", param_name, name)
+            } else {
+                name.clone()
+            },
             in_type: in_type.to_string(),
             description,
             required: required_fields.contains(name),
             example: extract_schema_example(doc, prop_resolved),
             enum_values: extract_enum_values(doc, prop_resolved),
+            schema_type: schema_type_name(doc, prop_resolved),
+            format: schema_format(doc, prop_resolved),
+            default: schema_default(doc, prop_resolved),
+            deprecated: prop_resolved
+                .get("deprecated")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            style: "form".to_string(),
+            explode: true,
         });
     }
     Some(expanded)
@@ -295,28 +826,43 @@ fn extract_parameter_example(doc: &Value, param: &Value) -> Option<Value> {
 
 fn extract_request_body_example(doc: &Value, request_body: &Value) -> Option<Value> {
     let resolved = resolve_ref(doc, request_body, 0);
-    let content = resolved.get("content")?.as_object()?;
+    extract_content_example(doc, resolved.get("content")?, ExampleDirection::Request)
+}
+
+// Prefers `application/json` and falls back to the first media type: an explicit
+// example, then the first named example, then one built from the schema.
+fn extract_content_example(
+    doc: &Value,
+    content: &Value,
+    direction: ExampleDirection,
+) -> Option<Value> {
+    let content = content.as_object()?;
     let content_value = if let Some(json_content) = content.get("application/json") {
         json_content
     } else {
         content.values().next()?
     };
+    let schema = content_value.get("schema");
+    let stripped = |example: &Value| match schema {
+        Some(schema) => strip_flagged(doc, schema, example.clone(), direction, 0),
+        None => example.clone(),
+    };
     if let Some(example) = content_value.get("example") {
         if !example.is_null() {
-            return Some(example.clone());
+            return Some(stripped(example));
         }
     }
     if let Some(examples) = content_value.get("examples").and_then(|v| v.as_object()) {
         for example in examples.values() {
             if let Some(value) = example.get("value") {
                 if !value.is_null() {
-                    return Some(value.clone());
+                    return Some(stripped(value));
                 }
             }
         }
     }
-    if let Some(schema) = content_value.get("schema") {
-        if let Some(example) = build_example_from_schema(doc, schema, 0) {
+    if let Some(schema) = schema {
+        if let Some(example) = build_example_from_schema(doc, schema, direction, 0) {
             return Some(example);
         }
     }
@@ -344,6 +890,20 @@ fn extract_request_body_description(doc: &Value, request_body: &Value) -> Option
     None
 }
 
+fn extract_request_body_schema(doc: &Value, request_body: &Value) -> Option<Value> {
+    let resolved = resolve_ref(doc, request_body, 0);
+    let content = resolved.get("content")?.as_object()?;
+    let (_, media) = content
+        .iter()
+        .find(|(media_type, _)| media_type.as_str() == "application/json")
+        .or_else(|| {
+            content
+                .iter()
+                .find(|(media_type, _)| media_type.ends_with("+json"))
+        })?;
+    Some(expand_schema_refs(doc, media.get("schema")?, 0)).filter(|schema| !schema.is_null())
+}
+
 fn extract_request_body_media_types(doc: &Value, request_body: &Value) -> Vec<String> {
     let resolved = resolve_ref(doc, request_body, 0);
     let content = match resolved.get("content").and_then(|v| v.as_object()) {
@@ -368,15 +928,14 @@ fn extract_response_schemas(doc: &Value, responses: &Value) -> Vec<ResponseSchem
             .map(|s| s.to_string());
         let content = resolved_response.get("content").and_then(|v| v.as_object());
         if let Some(content_map) = content {
-            let (content_type, content_value) = if let Some(json_content) =
-                content_map.get("application/json")
-            {
-                ("application/json".to_string(), json_content)
-            } else if let Some((key, value)) = content_map.iter().next() {
-                (key.to_string(), value)
-            } else {
-                ("".to_string(), &Value::Null)
-            };
+            let (content_type, content_value) =
+                if let Some(json_content) = content_map.get("application/json") {
+                    ("application/json".to_string(), json_content)
+                } else if let Some((key, value)) = content_map.iter().next() {
+                    (key.to_string(), value)
+                } else {
+                    ("".to_string(), &Value::Null)
+                };
             let schema = content_value
                 .get("schema")
                 .map(|schema| expand_schema_refs(doc, schema, 0))
@@ -404,6 +963,79 @@ fn extract_response_schemas(doc: &Value, responses: &Value) -> Vec<ResponseSchem
     schemas
 }
 
+fn extract_responses(doc: &Value, responses: &Value) -> BTreeMap<String, EndpointResponse> {
+    let resolved = resolve_ref(doc, responses, 0);
+    let responses_obj = match resolved.as_object() {
+        Some(obj) => obj,
+        None => return BTreeMap::new(),
+    };
+    let mut extracted = BTreeMap::new();
+    for (status, response) in responses_obj {
+        let resolved_response = resolve_ref(doc, response, 0);
+        let description = resolved_response
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        let content = resolved_response.get("content");
+        let media_types = content
+            .and_then(|v| v.as_object())
+            .map(|content| content.keys().cloned().collect())
+            .unwrap_or_default();
+        let example = content
+            .and_then(|content| extract_content_example(doc, content, ExampleDirection::Response))
+            .map(|value| value.to_string());
+        let examples = content
+            .and_then(|content| {
+                content
+                    .get("application/json")
+                    .or_else(|| content.as_object()?.values().next())
+            })
+            .and_then(|media| media.get("examples"))
+            .and_then(|v| v.as_object())
+            .map(|examples| {
+                examples
+                    .iter()
+                    .filter_map(|(name, example)| {
+                        Some((
+                            name.clone(),
+                            resolve_ref(doc, example, 0).get("value")?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut headers = Vec::new();
+        if let Some(header_map) = resolved_response.get("headers").and_then(|v| v.as_object()) {
+            for (name, header) in header_map {
+                let resolved_header = resolve_ref(doc, header, 0);
+                headers.push(ResponseHeader {
+                    name: name.clone(),
+                    description: resolved_header
+                        .get("description")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string()),
+                    required: resolved_header
+                        .get("required")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                    example: extract_parameter_example(doc, resolved_header),
+                });
+            }
+        }
+        extracted.insert(
+            status.clone(),
+            EndpointResponse {
+                description,
+                media_types,
+                example,
+                examples,
+                headers,
+            },
+        );
+    }
+    extracted
+}
+
 fn is_binary_schema(doc: &Value, schema: &Value) -> bool {
     let resolved = resolve_ref(doc, schema, 0);
     let schema_type = resolved.get("type").and_then(|v| v.as_str());
@@ -438,6 +1070,9 @@ fn extract_form_fields(doc: &Value, request_body: &Value, content_type: &str) ->
 
     let mut fields = Vec::new();
     for (name, prop_schema) in props {
+        if skipped_for(doc, prop_schema, ExampleDirection::Request) {
+            continue;
+        }
         let resolved_prop = resolve_ref(doc, prop_schema, 0);
         let description = resolved_prop
             .get("description")
@@ -445,13 +1080,11 @@ fn extract_form_fields(doc: &Value, request_body: &Value, content_type: &str) ->
             .map(|s| s.to_string());
         let mut is_file = is_binary_schema(doc, resolved_prop);
         let mut is_array = false;
-        if !is_file {
-            if resolved_prop.get("type").and_then(|v| v.as_str()) == Some("array") {
-                if let Some(items) = resolved_prop.get("items") {
-                    if is_binary_schema(doc, items) {
-                        is_file = true;
-                        is_array = true;
-                    }
+        if !is_file && resolved_prop.get("type").and_then(|v| v.as_str()) == Some("array") {
+            if let Some(items) = resolved_prop.get("items") {
+                if is_binary_schema(doc, items) {
+                    is_file = true;
+                    is_array = true;
                 }
             }
         }
@@ -466,17 +1099,177 @@ fn extract_form_fields(doc: &Value, request_body: &Value, content_type: &str) ->
     fields
 }
 
-fn parse_openapi_internal(content: &str, url: &str, etag: Option<String>) -> Result<OpenApiCollection, String> {
-    let json: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+// Paths parsed so far and the spec's total; returning false stops the parse.
+type ParseProgress = Arc<dyn Fn(usize, usize) -> bool + Send + Sync>;
+
+#[derive(Serialize, Clone, Debug)]
+struct ImportProgress {
+    import_id: String,
+    url: String,
+    processed: usize,
+    total: usize,
+}
+
+// Raises the flag once the future owning it is dropped, which is how an aborted import
+// reaches the parse running on a blocking thread.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Parses a spec and pulls in the documents its external refs point at. Parsing runs on
+// a blocking thread, since specs of several megabytes take a while.
+async fn load_openapi(
+    client: &Client,
+    content: &str,
+    content_type: Option<&str>,
+    url: &str,
+    etag: Option<String>,
+    progress: Option<ParseProgress>,
+) -> Result<OpenApiCollection, String> {
+    let content_hash = sigv4::sha256_hex(content.as_bytes());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+    let (document, content_type, location) = (
+        content.to_string(),
+        content_type.map(String::from),
+        url.to_string(),
+    );
+    let json = tokio::task::spawn_blocking(move || {
+        spec::parse_document(&document, content_type.as_deref(), &location)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let json = refs::bundle_external_refs(client, json, url).await;
+    let location = url.to_string();
+    let mut collection = tokio::task::spawn_blocking(move || {
+        parse_openapi_with_progress(json, &location, etag, &|processed, total| {
+            !cancelled.load(Ordering::Relaxed)
+                && progress
+                    .as_ref()
+                    .is_none_or(|progress| progress(processed, total))
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    collection.content_hash = Some(content_hash);
+    Ok(collection)
+}
+
+// True when `content` is the document the stored collection was parsed from.
+fn spec_unchanged(state: &AppState, url: &str, content: &str) -> bool {
+    let hash = sigv4::sha256_hex(content.as_bytes());
+    state
+        .collections
+        .get(url)
+        .is_some_and(|c| c.content_hash.as_deref() == Some(hash.as_str()))
+}
+
+fn extract_tags(
+    doc: &Value,
+    groups: &HashMap<String, Vec<Endpoint>>,
+) -> (Vec<TagInfo>, Vec<TagGroup>) {
+    let mut tags: Vec<TagInfo> = Vec::new();
+    for tag in doc["tags"].as_array().into_iter().flatten() {
+        let Some(name) = tag["name"].as_str() else {
+            continue;
+        };
+        if tags.iter().any(|t| t.name == name) {
+            continue;
+        }
+        tags.push(TagInfo {
+            name: name.to_string(),
+            description: tag["description"].as_str().map(|s| s.to_string()),
+            external_docs: tag["externalDocs"]["url"].as_str().map(|url| ExternalDocs {
+                url: url.to_string(),
+                description: tag["externalDocs"]["description"]
+                    .as_str()
+                    .map(|s| s.to_string()),
+            }),
+        });
+    }
+    // Undeclared tags have no order of their own, so they follow alphabetically.
+    let mut undeclared: Vec<&String> = groups
+        .keys()
+        .filter(|name| !tags.iter().any(|t| &t.name == *name))
+        .collect();
+    undeclared.sort();
+    for name in undeclared {
+        tags.push(TagInfo {
+            name: name.clone(),
+            description: None,
+            external_docs: None,
+        });
+    }
+    let tag_groups = doc["x-tagGroups"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|group| {
+            Some(TagGroup {
+                name: group["name"].as_str()?.to_string(),
+                tags: group["tags"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(|t| t.as_str().map(|s| s.to_string()))
+                    .collect(),
+            })
+        })
+        .collect();
+    (tags, tag_groups)
+}
+
+#[cfg(test)]
+fn parse_openapi_internal(
+    json: Value,
+    url: &str,
+    etag: Option<String>,
+) -> Result<OpenApiCollection, String> {
+    parse_openapi_with_progress(json, url, etag, &|_, _| true)
+}
+
+// `progress` hears of every path item parsed and stops the parse by returning false.
+fn parse_openapi_with_progress(
+    json: Value,
+    url: &str,
+    etag: Option<String>,
+    progress: &dyn Fn(usize, usize) -> bool,
+) -> Result<OpenApiCollection, String> {
+    let json = if swagger::is_swagger2(&json) {
+        swagger::to_openapi3(&json)
+    } else {
+        json
+    };
     let mut groups: HashMap<String, Vec<Endpoint>> = HashMap::new();
-    let base_url = json["servers"][0]["url"].as_str().unwrap_or("").trim_end_matches('/');
+    let servers = parse_servers(json.get("servers"));
+    let base_url = servers
+        .first()
+        .map(|server| server.default_url())
+        .unwrap_or_default();
 
     if let Some(paths) = json["paths"].as_object() {
-        for (path, methods) in paths {
+        let total = paths.len();
+        for (index, (path, methods)) in paths.iter().enumerate() {
             if let Some(methods_obj) = methods.as_object() {
                 let path_params = methods_obj.get("parameters").and_then(|v| v.as_array());
+                let path_servers = parse_servers(methods_obj.get("servers"));
                 for (method, details) in methods_obj {
-                    if method == "parameters" { continue; }
+                    if method == "parameters" || method == "servers" || !details.is_object() {
+                        continue;
+                    }
+                    let op_servers = parse_servers(details.get("servers"));
+                    let endpoint_servers = if op_servers.is_empty() {
+                        path_servers.clone()
+                    } else {
+                        op_servers
+                    };
+                    let endpoint_base = endpoint_servers
+                        .first()
+                        .map(|server| server.default_url())
+                        .unwrap_or_else(|| base_url.clone());
 
                     let mut params = Vec::new();
                     let mut seen = std::collections::HashSet::new();
@@ -513,21 +1306,28 @@ fn parse_openapi_internal(content: &str, url: &str, etag: Option<String>) -> Res
                                     .and_then(|v| v.as_str())
                             })
                             .map(|s| s.to_string());
+                        let schema = resolved.get("schema");
+                        let (style, explode) = parameter_style(resolved, &in_type);
                         params.push(Parameter {
                             name,
                             in_type,
                             description,
                             required: resolved["required"].as_bool().unwrap_or(false),
                             example: extract_parameter_example(&json, resolved),
-                            enum_values: resolved
-                                .get("schema")
+                            enum_values: schema
                                 .and_then(|schema| extract_enum_values(&json, schema)),
+                            schema_type: schema.and_then(|schema| schema_type_name(&json, schema)),
+                            format: schema.and_then(|schema| schema_format(&json, schema)),
+                            default: schema.and_then(|schema| schema_default(&json, schema)),
+                            deprecated: resolved["deprecated"].as_bool().unwrap_or(false),
+                            style,
+                            explode,
                         });
                     }
 
                     let request_body = details.get("requestBody");
-                    let body_description = request_body
-                        .and_then(|body| extract_request_body_description(&json, body));
+                    let body_description =
+                        request_body.and_then(|body| extract_request_body_description(&json, body));
                     let body_required = request_body
                         .and_then(|body| resolve_ref(&json, body, 0).get("required"))
                         .and_then(|v| v.as_bool())
@@ -538,6 +1338,8 @@ fn parse_openapi_internal(content: &str, url: &str, etag: Option<String>) -> Res
                     let body_media_types = request_body
                         .map(|body| extract_request_body_media_types(&json, body))
                         .unwrap_or_default();
+                    let body_schema =
+                        request_body.and_then(|body| extract_request_body_schema(&json, body));
                     let mut body_fields = Vec::new();
                     let mut body_fields_type = None;
                     if let Some(body) = request_body {
@@ -553,19 +1355,36 @@ fn parse_openapi_internal(content: &str, url: &str, etag: Option<String>) -> Res
                                 body,
                                 "application/x-www-form-urlencoded",
                             );
-                            body_fields_type = Some("application/x-www-form-urlencoded".to_string());
+                            body_fields_type =
+                                Some("application/x-www-form-urlencoded".to_string());
                         }
                     }
                     let response_schemas = details
                         .get("responses")
                         .map(|responses| extract_response_schemas(&json, responses))
                         .unwrap_or_default();
+                    let responses = details
+                        .get("responses")
+                        .map(|responses| extract_responses(&json, responses))
+                        .unwrap_or_default();
 
                     let endpoint = Endpoint {
                         method: method.to_uppercase(),
-                        path: format!("{}{}", base_url, path),
+                        path: format!("{}{}", endpoint_base, path),
+                        route: path.clone(),
+                        servers: endpoint_servers,
                         summary: details["summary"].as_str().map(|s| s.to_string()),
                         description: details["description"].as_str().map(|s| s.to_string()),
+                        operation_id: details["operationId"].as_str().map(|s| s.to_string()),
+                        deprecated: details["deprecated"].as_bool().unwrap_or(false),
+                        external_docs: details["externalDocs"]["url"].as_str().map(|url| {
+                            ExternalDocs {
+                                url: url.to_string(),
+                                description: details["externalDocs"]["description"]
+                                    .as_str()
+                                    .map(|s| s.to_string()),
+                            }
+                        }),
                         parameters: params,
                         body_example,
                         body_description,
@@ -573,169 +1392,3994 @@ fn parse_openapi_internal(content: &str, url: &str, etag: Option<String>) -> Res
                         body_media_types,
                         body_fields,
                         body_fields_type,
+                        body_schema,
                         response_schemas,
+                        responses,
+                        security: security::parse_requirements(&json, details),
                     };
 
                     let tag = details["tags"][0].as_str().unwrap_or("Default").to_string();
-                    groups.entry(tag).or_insert(Vec::new()).push(endpoint);
+                    groups.entry(tag).or_default().push(endpoint);
                 }
             }
+            if !progress(index + 1, total) {
+                return Err("Import cancelled".into());
+            }
         }
     }
 
     let name = json["info"]["title"].as_str().unwrap_or(url).to_string();
+    let (tags, tag_groups) = extract_tags(&json, &groups);
     Ok(OpenApiCollection {
         name,
         url: url.to_string(),
+        source: None,
+        name_override: None,
         groups,
+        tags,
+        tag_groups,
+        security_schemes: security::parse_security_schemes(&json),
+        schema_digests: json
+            .pointer("/components/schemas")
+            .and_then(|v| v.as_object())
+            .map(|schemas| {
+                schemas
+                    .iter()
+                    .map(|(name, schema)| {
+                        (
+                            name.clone(),
+                            sigv4::sha256_hex(schema.to_string().as_bytes()),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        servers,
+        server_index: 0,
+        server_variables: HashMap::new(),
         last_updated: Utc::now(),
         etag,
+        last_modified: None,
+        content_hash: None,
+        fetch_headers: HashMap::new(),
+        fetch_auth: None,
         sync_enabled: true,
+        sync_interval_secs: DEFAULT_SYNC_INTERVAL_SECS,
     })
 }
 
+static NEXT_TASK: AtomicU64 = AtomicU64::new(0);
+
+async fn run_cancellable<T, F>(
+    in_flight: &Mutex<HashMap<String, (u64, AbortHandle)>>,
+    request_id: Option<String>,
+    task: F,
+) -> Result<T, String>
+where
+    F: Future<Output = Result<T, String>>,
+{
+    let request_id = match request_id {
+        Some(id) => id,
+        None => return task.await,
+    };
+    let (task, handle) = abortable(task);
+    let generation = NEXT_TASK.fetch_add(1, Ordering::Relaxed);
+    let previous = in_flight
+        .lock()
+        .unwrap()
+        .insert(request_id.clone(), (generation, handle));
+    if let Some((_, previous)) = previous {
+        previous.abort();
+    }
+    let result = task.await;
+    let mut in_flight = in_flight.lock().unwrap();
+    if in_flight
+        .get(&request_id)
+        .is_some_and(|(owner, _)| *owner == generation)
+    {
+        in_flight.remove(&request_id);
+    }
+    drop(in_flight);
+    result.unwrap_or_else(|_| Err("Request cancelled".into()))
+}
+
+// Turns profile and OAuth references into the credentials that are actually sent.
+async fn resolve_auth(state: &AppState, auth: Auth) -> Result<Auth, String> {
+    let auth = match auth {
+        Auth::Profile { name } => {
+            state
+                .auth_profiles()
+                .get(&name)
+                .ok_or_else(|| format!("Unknown auth profile: {}", name))?
+                .auth
+        }
+        other => other,
+    };
+    let token_client = state.clients.client(&ClientKey::default())?;
+    match auth {
+        Auth::OAuth2 { environment } => {
+            let environment = environment.unwrap_or_else(|| DEFAULT_ENVIRONMENT.to_string());
+            let token = state
+                .oauth_tokens()
+                .current(&token_client, &environment)
+                .await?
+                .ok_or_else(|| format!("No OAuth token for {}; authorize first", environment))?;
+            Ok(Auth::Bearer {
+                token: token.access_token,
+            })
+        }
+        Auth::OAuth2Grant { config } => {
+            let token = state
+                .oauth_tokens()
+                .obtain(&token_client, &grant_cache_key(&config), &config)
+                .await?;
+            Ok(Auth::Bearer {
+                token: token.access_token,
+            })
+        }
+        Auth::Jwt { config } => Ok(Auth::Bearer {
+            token: jwt::generate(&config).await?,
+        }),
+        Auth::Profile { name } => Err(format!("Auth profile {} references another profile", name)),
+        other => Ok(other),
+    }
+}
+
+// Without an explicit auth, requests from a collection use the credentials saved for
+// the operation's security schemes. Only the first goes through the auth pipeline; the
+// rest (typically additional API keys) are applied as headers or query parameters.
+async fn spec_auth(
+    state: &AppState,
+    collection: &str,
+    requirements: &[SecurityRequirement],
+    headers: &mut HashMap<String, String>,
+    query: &mut Vec<(String, String)>,
+) -> Result<Option<Auth>, String> {
+    let schemes = match state.collections.get(collection) {
+        Some(col) => col.security_schemes.clone(),
+        None => return Ok(None),
+    };
+    let bindings = state.settings.lock().unwrap().security_credentials.clone();
+    let selected = select_credentials(requirements, &schemes, |scheme| {
+        bindings
+            .iter()
+            .find(|binding| binding.collection == collection && binding.scheme == scheme)
+            .and_then(|binding| state.auth_profiles().get(&binding.profile))
+            .map(|profile| profile.auth)
+    });
+    let mut resolved = Vec::new();
+    for auth in selected.unwrap_or_default() {
+        resolved.push(resolve_auth(state, auth).await?);
+    }
+    let mut resolved = resolved.into_iter();
+    let primary = resolved.next();
+    for extra in resolved {
+        extra.apply_headers(headers);
+        query.extend(extra.query_pair());
+    }
+    Ok(primary)
+}
+
 #[command]
+#[allow(clippy::too_many_arguments)]
 async fn request(
     method: String,
     url: String,
+    query: Option<Vec<(String, String)>>,
     headers: HashMap<String, String>,
     body: Option<String>,
     multipart: Option<MultipartPayload>,
+    form: Option<Vec<(String, String)>>,
+    body_file: Option<String>,
+    graphql: Option<GraphqlQuery>,
+    jsonrpc: Option<JsonRpcRequest>,
+    request_id: Option<String>,
+    http_version: Option<HttpVersion>,
+    collection: Option<String>,
+    accept_invalid_certs: Option<bool>,
+    retry: Option<RetryPolicy>,
+    decompress: Option<bool>,
+    cookie_jar: Option<String>,
+    auth: Option<Auth>,
+    security: Option<Vec<SecurityRequirement>>,
+    params: Option<Vec<ParameterValue>>,
+    environment: Option<String>,
+    extract: Option<Vec<Extraction>>,
+    assertions: Option<Vec<Assertion>>,
+    pre_request_script: Option<String>,
+    post_response_script: Option<String>,
+    request_validation: Option<RequestValidation>,
+    app_handle: tauri::AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    let client = state.client.clone();
-    let req_method = match method.to_uppercase().as_str() {
-        "GET" => reqwest::Method::GET,
-        "POST" => reqwest::Method::POST,
-        "PUT" => reqwest::Method::PUT,
-        "DELETE" => reqwest::Method::DELETE,
-        "PATCH" => reqwest::Method::PATCH,
-        _ => return Err("Invalid method".into()),
-    };
-
-    let mut request_builder = client.request(req_method, &url);
-    let mut final_headers = headers;
-    if multipart.is_some() {
-        final_headers.retain(|key, _| !key.eq_ignore_ascii_case("content-type"));
-    }
-    for (key, value) in &final_headers {
-        request_builder = request_builder.header(key, value);
-    }
-    if let Some(payload) = multipart {
-        let mut form = reqwest::multipart::Form::new();
-        for (key, value) in payload.fields {
-            if !value.is_empty() {
-                form = form.text(key, value);
-            }
-        }
-        for file in payload.files {
-            for path in file.paths {
-                if path.is_empty() {
-                    continue;
-                }
-                let filename = Path::new(&path)
-                    .file_name()
-                    .and_then(|value| value.to_str())
-                    .unwrap_or("file")
-                    .to_string();
-                let file_handle = File::open(&path).await.map_err(|e| e.to_string())?;
-                let length = file_handle.metadata().await.map_err(|e| e.to_string())?.len();
-                let part = reqwest::multipart::Part::stream_with_length(file_handle, length)
-                    .file_name(filename);
-                form = form.part(file.name.clone(), part);
-            }
-        }
-        request_builder = request_builder.multipart(form);
-    } else if let Some(b) = body {
-        if !b.is_empty() {
-            request_builder = request_builder.body(b);
-            if !final_headers
-                .keys()
-                .any(|key| key.eq_ignore_ascii_case("content-type"))
-            {
-                request_builder = request_builder.header("Content-Type", "application/json");
-            }
-        }
-    }
+) -> Result<ResponseData, String> {
+    let input = RequestInput {
+        method,
+        url,
+        query,
+        headers,
+        body,
+        multipart,
+        form,
+        body_file,
+        graphql,
+        jsonrpc,
+        http_version,
+        collection,
+        accept_invalid_certs,
+        retry,
+        decompress,
+        cookie_jar,
+        auth,
+        security,
+        params,
+        environment,
+        extract,
+        assertions,
+        pre_request_script,
+        post_response_script,
+        request_validation,
+    };
+    send_request(input, request_id, app_handle, &state).await
+}
+
+// Sends the request and records it in history, successful or not.
+async fn send_request(
+    input: RequestInput,
+    request_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: &AppState,
+) -> Result<ResponseData, String> {
+    send_request_with(input, None, request_id, Some(app_handle), state, true).await
+}
 
-    let response = request_builder.send().await.map_err(|e| e.to_string())?;
-    let status = response.status();
-    let headers_map = response.headers().clone();
-    let text = response.text().await.map_err(|e| e.to_string())?;
+// `variables` replaces the environment's, for the collection runner's shared context.
+// Progress events go to `events`, if there is a window to receive them.
+async fn send_request_with(
+    input: RequestInput,
+    variables: Option<HashMap<String, String>>,
+    request_id: Option<String>,
+    events: Option<tauri::AppHandle>,
+    state: &AppState,
+    record: bool,
+) -> Result<ResponseData, String> {
+    let result = send_scripted(input.clone(), variables, request_id, events, state).await;
+    if record {
+        let _ = state
+            .store()
+            .add_history(&HistoryEntry::new(input, &result));
+    }
+    result
+}
 
-    let mut header_str = String::new();
-    for (k, v) in headers_map.iter() {
-        header_str.push_str(&format!("{}: {:?}\n", k, v));
+// Runs the pre-request script, sends the request, then applies extractions, the
+// post-response script and assertions. Variables set along the way are saved to the
+// request's environment.
+async fn send_scripted(
+    input: RequestInput,
+    variables: Option<HashMap<String, String>>,
+    request_id: Option<String>,
+    events: Option<tauri::AppHandle>,
+    state: &AppState,
+) -> Result<ResponseData, String> {
+    let mut variables = match variables {
+        Some(variables) => variables,
+        None => environment_variables(state, input.environment.as_deref())?,
+    };
+    let non_empty =
+        |script: &Option<String>| script.clone().filter(|script| !script.trim().is_empty());
+    let mut request = input.clone();
+    let mut scripts = None;
+    let mut extracted = Vec::new();
+    if let Some(script) = non_empty(&input.pre_request_script) {
+        let (changed_request, report, changed) =
+            scripting::pre_request(&script, request, &mut variables)?;
+        request = changed_request;
+        scripts = Some(report);
+        extracted = changed;
+    }
+    // Ids are assigned up front so replies can be matched to the calls that were sent.
+    if let Some(jsonrpc) = &mut request.jsonrpc {
+        jsonrpc.assign_ids();
+    }
+    let jsonrpc = request.jsonrpc.clone();
+    let operation = contract_operation(state, &request, &variables);
+    let mut request_issues = Vec::new();
+    if let Some(operation) = &operation {
+        let mode = request
+            .request_validation
+            .unwrap_or(state.settings.lock().unwrap().request_validation);
+        if mode != RequestValidation::Off {
+            request_issues = contract::check_request(
+                operation,
+                &template::render_input(request.clone(), &variables),
+            );
+        }
+        if mode == RequestValidation::Block && !request_issues.is_empty() {
+            let problems: Vec<String> = request_issues
+                .iter()
+                .map(|issue| issue.message.clone())
+                .collect();
+            return Err(format!(
+                "Request does not match {} {}: {}",
+                operation.method,
+                operation.route,
+                problems.join("; ")
+            ));
+        }
+    }
+    let mut response = execute_input(request, &variables, request_id, events, state).await?;
+    if let Some(jsonrpc) = &jsonrpc {
+        response.jsonrpc = jsonrpc.outcomes(&response.body);
+    }
+    if let Some(extractions) = &input.extract {
+        extracted.extend(extract::extract_all(&response, extractions));
+    }
+    if let Some(script) = non_empty(&input.post_response_script) {
+        variables.extend(
+            extracted
+                .iter()
+                .filter_map(|item| Some((item.variable.clone(), item.value.clone()?))),
+        );
+        let (report, changed) = scripting::post_response(&script, &response, &variables);
+        scripting::merge(&mut scripts, report);
+        extracted.extend(changed);
     }
+    response.scripts = scripts;
+    if !extracted.is_empty() {
+        response.extracted = store_extracted(state, input.environment.as_deref(), extracted);
+    }
+    if let Some(checks) = &input.assertions {
+        response.assertions = assertions::evaluate(&response, checks);
+    }
+    response.contract = operation.map(|operation| contract::ContractReport {
+        request: request_issues,
+        ..contract::validate(&operation, &response)
+    });
+    if let (Some(report), Some(collection)) = (&response.contract, &input.collection) {
+        let _ = state
+            .store()
+            .record_coverage(collection, &report.operation, response.status);
+    }
+    Ok(response)
+}
 
-    Ok(format!("Status: {}\n\nHeaders:\n{}\n\nBody:\n{}", status, header_str, text))
+// The operations of a collection with what they take and return, for contract checks.
+fn contract_operations(col: &OpenApiCollection) -> Vec<ContractOperation> {
+    col.groups
+        .values()
+        .flatten()
+        .map(|endpoint| ContractOperation {
+            method: endpoint.method.clone(),
+            route: endpoint.route.clone(),
+            parameters: endpoint
+                .parameters
+                .iter()
+                .map(|param| DeclaredParameter {
+                    name: param.name.clone(),
+                    location: param.in_type.clone(),
+                    required: param.required,
+                    enum_values: param.enum_values.clone().unwrap_or_default(),
+                })
+                .collect(),
+            body_required: endpoint.body_required,
+            body_media_types: endpoint.body_media_types.clone(),
+            body_schema: endpoint.body_schema.clone(),
+            responses: endpoint
+                .responses
+                .iter()
+                .map(|(status, response)| DeclaredResponse {
+                    status: status.clone(),
+                    media_types: response.media_types.clone(),
+                    schema: endpoint
+                        .response_schemas
+                        .iter()
+                        .find(|s| &s.status == status)
+                        .and_then(|s| s.schema.clone()),
+                })
+                .collect(),
+        })
+        .collect()
 }
 
+// Which operations of a collection requests have reached, from history, the runner and
+// monitors alike, and which of their declared responses came back.
 #[command]
-async fn import_openapi(url: String, state: State<'_, AppState>) -> Result<OpenApiCollection, String> {
-    let client = Client::new();
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
-    let etag = response.headers().get("etag").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
-    let content = response.text().await.map_err(|e| e.to_string())?;
-    
-    let collection = parse_openapi_internal(&content, &url, etag)?;
-    let mut cols = state.collections.lock().unwrap();
-    cols.insert(url, collection.clone());
-    Ok(collection)
+async fn coverage_report(
+    collection: String,
+    state: State<'_, AppState>,
+) -> Result<CoverageReport, String> {
+    let operations: Vec<CoverageOperation> = {
+        let col = state
+            .collections
+            .get(&collection)
+            .ok_or_else(|| format!("Unknown collection: {}", collection))?;
+        col.tags
+            .iter()
+            .flat_map(|tag| {
+                col.groups
+                    .get(&tag.name)
+                    .into_iter()
+                    .flatten()
+                    .map(move |endpoint| (tag, endpoint))
+            })
+            .map(|(tag, endpoint)| CoverageOperation {
+                operation: format!("{} {}", endpoint.method.to_uppercase(), endpoint.route),
+                summary: endpoint.summary.clone(),
+                tag: tag.name.clone(),
+                statuses: endpoint.responses.keys().cloned().collect(),
+            })
+            .collect()
+    };
+    let hits = state.store().coverage(&collection)?;
+    Ok(coverage::report(&collection, &operations, &hits))
 }
 
 #[command]
-async fn toggle_sync(url: String, enabled: bool, state: State<'_, AppState>) -> Result<(), String> {
-    let mut cols = state.collections.lock().unwrap();
-    if let Some(col) = cols.get_mut(&url) { col.sync_enabled = enabled; }
-    Ok(())
+async fn reset_coverage(collection: String, state: State<'_, AppState>) -> Result<(), String> {
+    state.store().clear_coverage(&collection)
 }
 
-#[command]
-async fn download_file(url: String, save_path: String) -> Result<(), String> {
-    let response = reqwest::get(&url).await.map_err(|e| e.to_string())?;
-    let mut file = File::create(save_path).await.map_err(|e| e.to_string())?;
-    let mut stream = response.bytes_stream();
-    while let Some(item) = stream.next().await {
-        let chunk = item.map_err(|e| e.to_string())?;
-        tokio::io::copy(&mut &chunk[..], &mut file).await.map_err(|e| e.to_string())?;
-    }
-    Ok(())
+// Requests of an OpenAPI collection are checked against the operation their URL matches.
+fn contract_operation(
+    state: &AppState,
+    input: &RequestInput,
+    variables: &HashMap<String, String>,
+) -> Option<ContractOperation> {
+    let operations =
+        contract_operations(state.collections.get(input.collection.as_deref()?)?.value());
+    let url = template::render(&input.url, variables);
+    contract::find_operation(&operations, &input.method, &url).cloned()
 }
 
-async fn background_update_checker(app_handle: tauri::AppHandle) {
-    loop {
-        sleep(Duration::from_secs(60)).await;
-        let state = app_handle.state::<AppState>();
-        let targets: Vec<(String, Option<String>)> = {
-            let cols = state.collections.lock().unwrap();
-            cols.values().filter(|c| c.sync_enabled).map(|c| (c.url.clone(), c.etag.clone())).collect()
+// Variables of the named environment, or of the workspace's active one.
+fn environment_variables(
+    state: &AppState,
+    name: Option<&str>,
+) -> Result<HashMap<String, String>, String> {
+    let store = state.store();
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => match store.active_environment()? {
+            Some(name) => name,
+            None => return Ok(HashMap::new()),
+        },
+    };
+    let environment = store
+        .environment(&name)?
+        .ok_or_else(|| format!("Unknown environment: {}", name))?;
+    // Secret values are only read from the keychain here, when a request is sent.
+    Ok(state
+        .secrets
+        .unseal(&state.workspaces.active(), environment)?
+        .values())
+}
+
+// Saves extracted values into the request's environment, creating variables as needed.
+// Values headed for secret variables are masked in what is returned.
+fn store_extracted(
+    state: &AppState,
+    name: Option<&str>,
+    mut extracted: Vec<Extracted>,
+) -> Vec<Extracted> {
+    let store = state.store();
+    let workspace = state.workspaces.active();
+    let result = (|| {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => store
+                .active_environment()?
+                .ok_or("No active environment to store extracted values in")?,
         };
-        let client = Client::new();
-        for (url, current_etag) in targets {
-            let mut req = client.get(&url);
-            if let Some(etag) = current_etag { req = req.header("If-None-Match", etag); }
-            if let Ok(resp) = req.send().await {
-                if resp.status() == reqwest::StatusCode::OK {
-                    let new_etag = resp.headers().get("etag").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
-                    if let Ok(content) = resp.text().await {
-                        if let Ok(updated_col) = parse_openapi_internal(&content, &url, new_etag) {
-                            let mut cols = state.collections.lock().unwrap();
-                            cols.insert(url.clone(), updated_col.clone());
-                            app_handle.emit_all("collection-updated", updated_col).unwrap();
-                        }
+        let previous = store
+            .environment(&name)?
+            .ok_or_else(|| format!("Unknown environment: {}", name))?;
+        let mut environment = secrets::mask(previous.clone());
+        for item in &mut extracted {
+            let Some(value) = &mut item.value else {
+                continue;
+            };
+            match environment
+                .variables
+                .iter_mut()
+                .rev()
+                .find(|variable| variable.name == item.variable)
+            {
+                Some(variable) => {
+                    variable.value = value.clone();
+                    if variable.secret {
+                        *value = secrets::SECRET_MASK.to_string();
                     }
                 }
+                None => environment.variables.push(Variable {
+                    name: item.variable.clone(),
+                    value: value.clone(),
+                    enabled: true,
+                    secret: false,
+                }),
             }
         }
+        let environment = state
+            .secrets
+            .seal(&workspace, environment, Some(&previous))?;
+        store.put_environment(&environment, None)
+    })();
+    if let Err(e) = result {
+        for item in extracted.iter_mut().filter(|item| item.value.is_some()) {
+            item.error = Some(e.clone());
+        }
     }
+    extracted
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-
+// Resolves variables, parameters and auth into the request that goes on the wire, and
+// the client that sends it.
+async fn prepare_input(
+    input: RequestInput,
+    variables: &HashMap<String, String>,
+    state: &AppState,
+) -> Result<(RequestSpec, ClientKey, RetryPolicy), String> {
+    let input = template::render_input(input, variables);
+    let unresolved = template::unresolved(&input.url);
+    if !unresolved.is_empty() {
+        return Err(format!(
+            "Unresolved variables in URL: {}",
+            unresolved.join(", ")
+        ));
+    }
+    let RequestInput {
+        method,
+        url,
+        query,
+        headers,
+        body,
+        multipart,
+        form,
+        body_file,
+        graphql,
+        jsonrpc,
+        http_version,
+        collection,
+        accept_invalid_certs,
+        retry,
+        decompress,
+        cookie_jar,
+        auth,
+        security,
+        params,
+        ..
+    } = input;
+    let mut headers = headers;
+    let envelope = match (graphql, jsonrpc) {
+        (Some(graphql), _) => Some(graphql.body()),
+        (None, Some(mut jsonrpc)) => {
+            jsonrpc.assign_ids();
+            Some(jsonrpc.body()?)
+        }
+        (None, None) => None,
+    };
+    let (method, body, multipart, form, body_file) = match envelope {
+        Some(envelope) => {
+            if !headers
+                .keys()
+                .any(|name| name.eq_ignore_ascii_case("content-type"))
+            {
+                headers.insert("Content-Type".into(), "application/json".into());
+            }
+            ("POST".to_string(), Some(envelope), None, None, None)
+        }
+        None => (method, body, multipart, form, body_file),
+    };
+    let mut query = query.unwrap_or_default();
+    let params = params.unwrap_or_default();
+    let url = apply_parameters(&url, &params, &mut query, &mut headers)?;
+    // A Cookie header keeps reqwest from adding the jar's cookies, so those are merged
+    // in underneath the cookie parameters.
+    if params.iter().any(|param| param.in_type == "cookie") {
+        if let Some(name) = params::cookie_header_name(&headers) {
+            if let Some(jar_cookies) = state
+                .clients
+                .jar(cookie_jar.as_deref())
+                .request_header(&url)
+            {
+                let merged = params::merge_cookies(&jar_cookies, &headers[&name]);
+                headers.insert(name, merged);
+            }
+        }
+    }
+    let key = ClientKey {
+        http_version: http_version.unwrap_or_default(),
+        certificate: state.clients.certificate_for(&url, collection.as_deref()),
+        accept_invalid_certs: accept_invalid_certs.unwrap_or(false),
+        cookie_jar,
+    };
+    let auth = match (auth, collection.as_deref(), security) {
+        (Some(auth), _, _) => Some(resolve_auth(state, auth).await?),
+        (None, Some(collection), Some(requirements)) => {
+            spec_auth(state, collection, &requirements, &mut headers, &mut query).await?
+        }
+        _ => None,
+    };
+    let proxy_ntlm = state.clients.proxy_ntlm_for(&url);
+    let spec = RequestSpec {
+        method,
+        url,
+        query,
+        headers,
+        body,
+        multipart,
+        form,
+        body_file,
+        decompress: decompress.unwrap_or(true),
+        spill_threshold: state
+            .settings
+            .lock()
+            .unwrap()
+            .large_response_bytes
+            .unwrap_or(body::DEFAULT_SPILL_THRESHOLD),
+        auth,
+        proxy_ntlm,
+    };
+    Ok((spec, key, retry.unwrap_or_default()))
+}
+
+async fn execute_input(
+    input: RequestInput,
+    variables: &HashMap<String, String>,
+    request_id: Option<String>,
+    events: Option<tauri::AppHandle>,
+    state: &AppState,
+) -> Result<ResponseData, String> {
+    let (spec, key, retry) = prepare_input(input, variables, state).await?;
+    let client = state.clients.client(&key)?;
+    let upload_id = request_id.clone();
+    let upload_handle = events.clone();
+    let last_upload_emit: Mutex<Option<Instant>> = Mutex::new(None);
+    let on_upload: UploadCallback = Arc::new(move |sent: u64, total: u64| {
+        let mut last = last_upload_emit.lock().unwrap();
+        if sent < total
+            && last
+                .map(|at| at.elapsed() < PROGRESS_INTERVAL)
+                .unwrap_or(false)
+        {
+            return;
+        }
+        *last = Some(Instant::now());
+        if let Some(handle) = &upload_handle {
+            let _ = handle.emit_all(
+                "upload-progress",
+                UploadProgress {
+                    request_id: upload_id.clone(),
+                    sent,
+                    total,
+                },
+            );
+        }
+    });
+    let wait_id = request_id.clone();
+    let wait_handle = events.clone();
+    let url = spec.url.clone();
+    let progress_id = request_id.clone();
+    let mut last_emit: Option<Instant> = None;
+    let on_progress = move |received: u64, total: Option<u64>| {
+        let done = total.map(|total| received >= total).unwrap_or(false);
+        if !done
+            && last_emit
+                .map(|at| at.elapsed() < PROGRESS_INTERVAL)
+                .unwrap_or(false)
+        {
+            return;
+        }
+        last_emit = Some(Instant::now());
+        if let Some(handle) = &events {
+            let _ = handle.emit_all(
+                "response-progress",
+                ResponseProgress {
+                    request_id: progress_id.clone(),
+                    received,
+                    total,
+                },
+            );
+        }
+    };
+    // Rate limit waits happen inside, so cancelling a request that waits its turn works.
+    let throttle = Throttle {
+        limiter: state.rate_limiter.clone(),
+        on_wait: Box::new(move |wait| {
+            if let Some(handle) = &wait_handle {
+                let _ = handle.emit_all(
+                    "rate-limit-wait",
+                    RateLimitWait {
+                        request_id: wait_id.clone(),
+                        url: url.clone(),
+                        wait_ms: wait.as_millis() as u64,
+                    },
+                );
+            }
+        }),
+    };
+    let result = run_cancellable(
+        &state.in_flight,
+        request_id,
+        execute_request(
+            client,
+            spec,
+            retry,
+            Some(throttle),
+            Some(on_upload),
+            on_progress,
+        ),
+    )
+    .await;
+    // Responses may have set cookies; a failed save must not fail the request.
+    let _ = state.clients.save_cookies();
+    result
+}
+
+// The request as it would be sent, with the request's or active environment, as a
+// snippet for other tools.
+#[command]
+async fn generate_snippet(
+    request: RequestInput,
+    language: SnippetLanguage,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let variables = environment_variables(&state, request.environment.as_deref())?;
+    let (spec, _, _) = prepare_input(request, &variables, &state).await?;
+    snippet::render(&spec, language)
+}
+
+// Makes `store`, opened in `dir`, the current one and loads its collections, cookies,
+// auth profiles and OAuth tokens in place of the previous workspace's.
+fn activate_store(state: &AppState, dir: &Path, store: Arc<Store>) -> Result<(), String> {
+    let stored: Vec<(String, OpenApiCollection)> = store.collections()?;
+    state.spec_watcher.clear();
+    // A spec file that moved away since the last run stays listed; it just is not watched.
+    for (url, col) in &stored {
+        if let Some(path) = local_spec_path(&spec_location(col)) {
+            let _ = state.spec_watcher.watch(&path, url);
+        }
+    }
+    *state.collection_order.lock().unwrap() = stored.iter().map(|(url, _)| url.clone()).collect();
+    state.collections.clear();
+    state.sync_status.clear();
+    for (url, col) in stored {
+        state.collections.insert(url, col);
+    }
+    let persist = state.settings.lock().unwrap().persist_cookies;
+    state.clients.reset_cookies(persist.then(|| store.clone()));
+    *state.oauth_tokens.write().unwrap() = Arc::new(TokenStore::load(dir));
+    *state.auth_profiles.write().unwrap() = Arc::new(ProfileStore::load(dir));
+    *state.store.write().unwrap() = store;
+    Ok(())
+}
+
+// What `export_workspace` writes. Credentials used to fetch specs are left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct WorkspaceExport {
+    workspace: Workspace,
+    exported_at: DateTime<Utc>,
+    collections: Vec<OpenApiCollection>,
+    saved_requests: Vec<SavedRequest>,
+    #[serde(default)]
+    environments: Vec<Environment>,
+}
+
+#[command]
+async fn list_workspaces(state: State<'_, AppState>) -> Result<(String, Vec<Workspace>), String> {
+    Ok((state.workspaces.active(), state.workspaces.list()))
+}
+
+#[command]
+async fn create_workspace(name: String, state: State<'_, AppState>) -> Result<Workspace, String> {
+    state.workspaces.create(&name)
+}
+
+#[command]
+async fn switch_workspace(
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<OpenApiCollection>, String> {
+    if state.workspaces.get(&id).is_none() {
+        return Err(format!("Unknown workspace: {}", id));
+    }
+    let dir = workspace_dir(&state.data_dir, &id);
+    let store = Arc::new(Store::open(&dir)?);
+    let _ = state.clients.save_cookies();
+    activate_store(&state, &dir, store)?;
+    state.workspaces.set_active(&id)?;
+    refresh_other_queues(&state);
+    Ok(state
+        .collection_order
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|url| state.collections.get(url).map(|col| col.clone()))
+        .collect())
+}
+
+#[command]
+async fn delete_workspace(id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let dir = workspace_dir(&state.data_dir, &id);
+    if dir.join(storage::DATABASE_FILE).exists() {
+        for environment in Store::open(&dir)?.environments()? {
+            state.secrets.forget(&id, &environment)?;
+        }
+    }
+    state.workspaces.delete(&id)?;
+    std::fs::remove_dir_all(workspace_dir(&state.data_dir, &id)).or_else(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            Ok(())
+        } else {
+            Err(e.to_string())
+        }
+    })?;
+    refresh_other_queues(&state);
+    Ok(())
+}
+
+#[command]
+async fn export_workspace(
+    id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let workspace = state
+        .workspaces
+        .get(&id)
+        .ok_or_else(|| format!("Unknown workspace: {}", id))?;
+    let store = if id == state.workspaces.active() {
+        state.store()
+    } else {
+        Arc::new(Store::open(&workspace_dir(&state.data_dir, &id))?)
+    };
+    let collections = store
+        .collections::<OpenApiCollection>()?
+        .into_iter()
+        .map(|(_, mut col)| {
+            col.fetch_headers.clear();
+            col.fetch_auth = None;
+            col
+        })
+        .collect();
+    let export = WorkspaceExport {
+        workspace,
+        exported_at: Utc::now(),
+        collections,
+        saved_requests: store.saved_requests()?,
+        environments: store
+            .environments()?
+            .into_iter()
+            .map(secrets::mask)
+            .collect(),
+    };
+    let content = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Saves the requests an import produced, skipping those that are not valid saved
+// requests with a warning.
+fn store_imported(
+    store: &Store,
+    requests: Vec<SavedRequest>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<SavedRequest>, String> {
+    let mut stored = Vec::new();
+    for saved in requests {
+        let name = saved.name.clone();
+        match saved::normalize(saved) {
+            Ok(saved) => {
+                store.put_saved_request(&saved)?;
+                stored.push(saved);
+            }
+            Err(e) => warnings.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok(stored)
+}
+
+// Imports a Postman v2.1 collection file as saved requests, into a new workspace named
+// after the collection when `new_workspace` is set and the active one otherwise.
+// Collection variables become an environment of the same name.
+#[command]
+async fn import_postman(
+    path: String,
+    new_workspace: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<CollectionImport, String> {
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| e.to_string())?;
+    store_collection_import(
+        postman::import(&content)?,
+        new_workspace.unwrap_or(false),
+        &state,
+    )
+}
+
+// Imports an Insomnia v4 export; its base and sub environments become environments.
+#[command]
+async fn import_insomnia(
+    path: String,
+    new_workspace: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<CollectionImport, String> {
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| e.to_string())?;
+    store_collection_import(
+        insomnia::import(&content)?,
+        new_workspace.unwrap_or(false),
+        &state,
+    )
+}
+
+// Imports a Bruno collection folder, the one holding `bruno.json`.
+#[command]
+async fn import_bruno(
+    path: String,
+    new_workspace: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<CollectionImport, String> {
+    let imported = tokio::task::spawn_blocking(move || bruno::import(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())??;
+    store_collection_import(imported, new_workspace.unwrap_or(false), &state)
+}
+
+// Imports the SOAP operations of a WSDL, fetched from a URL or read from a file.
+#[command]
+async fn import_wsdl(
+    location: String,
+    headers: Option<HashMap<String, String>>,
+    auth: Option<Auth>,
+    new_workspace: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<CollectionImport, String> {
+    let content = match local_spec_path(&location) {
+        Some(path) => tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?,
+        None => {
+            let client = state.clients.client(&ClientKey::default())?;
+            let response = spec_request(
+                &state,
+                &client,
+                &location,
+                &headers.unwrap_or_default(),
+                auth.as_ref(),
+            )
+            .await?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .error_for_status()
+            .map_err(|e| e.to_string())?;
+            response.text().await.map_err(|e| e.to_string())?
+        }
+    };
+    store_collection_import(
+        soap::import(&content)?,
+        new_workspace.unwrap_or(false),
+        &state,
+    )
+}
+
+// Environments are replaced by imported ones of the same name.
+fn store_collection_import(
+    mut imported: CollectionImport,
+    new_workspace: bool,
+    state: &AppState,
+) -> Result<CollectionImport, String> {
+    let (workspace, store) = if new_workspace {
+        let workspace = state.workspaces.create(&imported.name)?;
+        let store = Arc::new(Store::open(&workspace_dir(&state.data_dir, &workspace.id))?);
+        (workspace.id, store)
+    } else {
+        (state.workspaces.active(), state.store())
+    };
+    imported.saved_requests = store_imported(
+        &store,
+        std::mem::take(&mut imported.saved_requests),
+        &mut imported.warnings,
+    )?;
+    let mut stored = Vec::new();
+    for environment in std::mem::take(&mut imported.environments) {
+        let environment = environments::normalize(environment)?;
+        let previous = store.environment(&environment.name)?;
+        let environment = state
+            .secrets
+            .seal(&workspace, environment, previous.as_ref())?;
+        store.put_environment(&environment, None)?;
+        stored.push(secrets::mask(environment));
+    }
+    imported.environments = stored;
+    Ok(imported)
+}
+
+// Where `export_requests` takes its requests from: a spec collection's endpoints or the
+// active workspace's saved requests.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+enum ExportSource {
+    Collection { url: String },
+    SavedRequests,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Postman,
+    Insomnia,
+}
+
+// An endpoint as a saved request in its tag's folder. Path parameters become variables
+// and the first example of each other parameter its value.
+fn endpoint_request(group: &str, endpoint: &Endpoint) -> SavedRequest {
+    let example = |param: &Parameter| {
+        param
+            .example
+            .as_ref()
+            .or(param.default.as_ref())
+            .map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+    };
+    let mut request = RequestInput {
+        method: endpoint.method.to_uppercase(),
+        url: endpoint.path.clone(),
+        ..RequestInput::default()
+    };
+    let mut query = Vec::new();
+    for param in endpoint
+        .parameters
+        .iter()
+        .filter(|param| param.required || example(param).is_some())
+    {
+        let value = example(param).unwrap_or_default();
+        match param.in_type.as_str() {
+            "path" => {
+                request.url = request.url.replace(
+                    &format!("{{{}}}", param.name),
+                    &format!("{{{{{}}}}}", param.name),
+                )
+            }
+            "query" => query.push((param.name.clone(), value)),
+            "header" => {
+                request.headers.insert(param.name.clone(), value);
+            }
+            _ => {}
+        }
+    }
+    request.query = Some(query).filter(|query| !query.is_empty());
+    match endpoint.body_fields_type.as_deref() {
+        Some("multipart/form-data") => {
+            let (files, fields): (Vec<&BodyField>, Vec<&BodyField>) =
+                endpoint.body_fields.iter().partition(|field| field.is_file);
+            request.multipart = Some(MultipartPayload {
+                fields: fields
+                    .into_iter()
+                    .map(|field| (field.name.clone(), String::new()))
+                    .collect(),
+                files: files
+                    .into_iter()
+                    .map(|field| MultipartFile {
+                        name: field.name.clone(),
+                        paths: Vec::new(),
+                    })
+                    .collect(),
+            });
+        }
+        Some("application/x-www-form-urlencoded") => {
+            request.form = Some(
+                endpoint
+                    .body_fields
+                    .iter()
+                    .map(|field| (field.name.clone(), String::new()))
+                    .collect(),
+            );
+        }
+        _ => {
+            if let Some(media_type) = endpoint.body_media_types.first() {
+                request
+                    .headers
+                    .insert("Content-Type".into(), media_type.clone());
+            }
+            request.body = endpoint.body_example.clone();
+        }
+    }
+    let examples = endpoint
+        .responses
+        .iter()
+        .filter_map(|(status, response)| {
+            Some(SavedExample {
+                name: response
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| status.clone()),
+                status: status.parse().ok(),
+                headers: response
+                    .media_types
+                    .first()
+                    .map(|media_type| ("Content-Type".to_string(), media_type.clone()))
+                    .into_iter()
+                    .collect(),
+                body: Some(response.example.clone()?),
+            })
+        })
+        .collect();
+    let now = Utc::now();
+    SavedRequest {
+        id: saved::new_id(),
+        name: endpoint
+            .summary
+            .clone()
+            .or_else(|| endpoint.operation_id.clone())
+            .unwrap_or_else(|| format!("{} {}", endpoint.method.to_uppercase(), endpoint.route)),
+        folder: group.to_string(),
+        request,
+        examples,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+// Writes the requests as a Postman v2.1 collection or an Insomnia v4 export.
+#[command]
+async fn export_requests(
+    source: ExportSource,
+    format: ExportFormat,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let (name, requests) = match source {
+        ExportSource::Collection { url } => {
+            let col = state
+                .collections
+                .get(&url)
+                .map(|col| col.clone())
+                .ok_or_else(|| format!("Unknown collection: {}", url))?;
+            // Groups in display order, as the sidebar lists them.
+            let mut groups: Vec<&String> = col
+                .tags
+                .iter()
+                .filter_map(|tag| col.groups.get_key_value(&tag.name).map(|(name, _)| name))
+                .collect();
+            let mut rest: Vec<&String> = col
+                .groups
+                .keys()
+                .filter(|name| !groups.contains(name))
+                .collect();
+            rest.sort();
+            groups.extend(rest);
+            let requests = groups
+                .into_iter()
+                .flat_map(|group| {
+                    col.groups[group]
+                        .iter()
+                        .map(move |endpoint| endpoint_request(group, endpoint))
+                })
+                .collect();
+            (
+                col.name_override
+                    .clone()
+                    .unwrap_or_else(|| col.name.clone()),
+                requests,
+            )
+        }
+        ExportSource::SavedRequests => {
+            let workspace = state
+                .workspaces
+                .get(&state.workspaces.active())
+                .map(|workspace| workspace.name)
+                .unwrap_or_else(|| "Saved requests".into());
+            (workspace, state.store().saved_requests()?)
+        }
+    };
+    let exported = match format {
+        ExportFormat::Postman => postman::export(&name, &requests),
+        ExportFormat::Insomnia => insomnia::export(&name, &requests),
+    };
+    let content = serde_json::to_string_pretty(&exported).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Writes a collection's reference documentation as a standalone Markdown or HTML file.
+#[command]
+async fn export_docs(
+    url: String,
+    format: DocFormat,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let content = {
+        let col = state
+            .collections
+            .get(&url)
+            .ok_or_else(|| format!("Unknown collection: {}", url))?;
+        docs::render(&col, format)
+    };
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Imports the entries of a HAR file as saved requests of the active workspace.
+#[command]
+async fn import_har(path: String, state: State<'_, AppState>) -> Result<HarImport, String> {
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut imported = har::import(&content)?;
+    imported.saved_requests = store_imported(
+        &state.store(),
+        std::mem::take(&mut imported.saved_requests),
+        &mut imported.warnings,
+    )?;
+    Ok(imported)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+enum HarSource {
+    History {
+        #[serde(default)]
+        filter: HistoryFilter,
+    },
+    Run {
+        summary: RunSummary,
+    },
+}
+
+// Writes history entries matching the filter, or the steps of a run, as a HAR file and
+// returns how many entries it holds.
+#[command]
+async fn export_har(
+    source: HarSource,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let exported = match source {
+        HarSource::History { filter } => har::from_history(&state.store().history(&filter)?),
+        HarSource::Run { summary } => har::from_run(&summary),
+    };
+    let content = serde_json::to_string_pretty(&exported).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(exported.log.entries.len())
+}
+
+#[command]
+async fn list_environments(
+    state: State<'_, AppState>,
+) -> Result<(Option<String>, Vec<Environment>), String> {
+    let store = state.store();
+    Ok((
+        store.active_environment()?,
+        store
+            .environments()?
+            .into_iter()
+            .map(secrets::mask)
+            .collect(),
+    ))
+}
+
+// Pass `previous_name` to rename an existing environment. Secret variables still holding
+// the mask keep their stored value.
+#[command]
+async fn save_environment(
+    environment: Environment,
+    previous_name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Environment, String> {
+    let environment = environments::normalize(environment)?;
+    let store = state.store();
+    if previous_name
+        .as_deref()
+        .is_some_and(|previous| previous != environment.name)
+        && store.environment(&environment.name)?.is_some()
+    {
+        return Err(format!(
+            "An environment named {} already exists",
+            environment.name
+        ));
+    }
+    let previous = store.environment(previous_name.as_deref().unwrap_or(&environment.name))?;
+    let environment =
+        state
+            .secrets
+            .seal(&state.workspaces.active(), environment, previous.as_ref())?;
+    store.put_environment(&environment, previous_name.as_deref())?;
+    Ok(secrets::mask(environment))
+}
+
+#[command]
+async fn delete_environment(name: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let store = state.store();
+    if let Some(environment) = store.environment(&name)? {
+        state
+            .secrets
+            .forget(&state.workspaces.active(), &environment)?;
+    }
+    store.delete_environment(&name)
+}
+
+#[command]
+async fn set_active_environment(
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let store = state.store();
+    if let Some(name) = &name {
+        store
+            .environment(name)?
+            .ok_or_else(|| format!("Unknown environment: {}", name))?;
+    }
+    store.set_active_environment(name.as_deref())
+}
+
+#[command]
+async fn list_history(
+    filter: Option<HistoryFilter>,
+    state: State<'_, AppState>,
+) -> Result<Vec<HistoryEntry>, String> {
+    state.store().history(&filter.unwrap_or_default())
+}
+
+#[command]
+async fn replay_history(
+    id: i64,
+    request_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ResponseData, String> {
+    let entry = state
+        .store()
+        .history_entry(id)?
+        .ok_or_else(|| format!("Unknown history entry: {}", id))?;
+    send_request(entry.request, request_id, app_handle, &state).await
+}
+
+// Removes entries older than `max_age_days`, then all but the newest `max_entries`.
+#[command]
+async fn prune_history(
+    max_age_days: Option<u64>,
+    max_entries: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let before = max_age_days.map(|days| Utc::now() - chrono::Duration::days(days as i64));
+    let store = state.store();
+    let (removed, files) = store.prune_history(before, max_entries)?;
+    // A replayed response can share its file with a newer entry. Only files the app
+    // wrote are deleted, whatever the entries say.
+    let kept: HashSet<String> = store.response_files()?.into_iter().collect();
+    for path in files.iter().filter(|path| !kept.contains(*path)) {
+        if let Ok(path) = response_file::response_path(path) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    Ok(removed)
+}
+
+// Deletes the response files written before `started` that no workspace's history
+// refers to any more.
+fn sweep_response_files(state: &AppState, started: std::time::SystemTime) -> Result<usize, String> {
+    let mut keep = HashSet::new();
+    for workspace in state.workspaces.list() {
+        let dir = workspace_dir(&state.data_dir, &workspace.id);
+        if dir.join(storage::DATABASE_FILE).exists() {
+            keep.extend(
+                Store::open(&dir)?
+                    .response_files()?
+                    .into_iter()
+                    .map(PathBuf::from),
+            );
+        }
+    }
+    Ok(body::sweep_response_dir(&keep, started))
+}
+
+// Compares two responses, each taken from history or fetched now, e.g. the same request
+// against two environments. Volatile headers and the fields in `ignore` are left out.
+#[command]
+async fn diff_responses(
+    left: DiffSide,
+    right: DiffSide,
+    ignore: Option<Vec<String>>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ResponseDiff, String> {
+    let (left, right) = join(
+        diff_side(left, &app_handle, &state),
+        diff_side(right, &app_handle, &state),
+    )
+    .await;
+    diff::diff(&left?, &right?, &ignore.unwrap_or_default())
+}
+
+async fn diff_side(
+    side: DiffSide,
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+) -> Result<ResponseData, String> {
+    match side {
+        DiffSide::History { id } => {
+            let entry = state
+                .store()
+                .history_entry(id)?
+                .ok_or_else(|| format!("Unknown history entry: {}", id))?;
+            entry.response.ok_or_else(|| {
+                format!(
+                    "History entry {} has no response: {}",
+                    id,
+                    entry.error.unwrap_or_default()
+                )
+            })
+        }
+        DiffSide::Request {
+            request,
+            environment,
+        } => {
+            let mut request = *request;
+            request.environment = environment.or(request.environment);
+            send_request(request, None, app_handle.clone(), state).await
+        }
+    }
+}
+
+// Runs a JSONPath (the default) or JMESPath query over a stored response body on a
+// blocking thread, so large bodies are filtered without passing through the webview.
+// At most `limit` matches are returned.
+#[command]
+async fn query_response(
+    source: BodySource,
+    language: Option<QueryLanguage>,
+    expression: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<QueryResult, String> {
+    let (inline, path) = match source {
+        BodySource::History { id } => {
+            let entry = state
+                .store()
+                .history_entry(id)?
+                .ok_or_else(|| format!("Unknown history entry: {}", id))?;
+            let response = entry
+                .response
+                .ok_or_else(|| format!("History entry {} has no response", id))?;
+            match response.body_path {
+                Some(path) => (None, PathBuf::from(path)),
+                None if response.body.is_empty() && response.size > 0 => {
+                    return Err(format!(
+                        "The body of history entry {} was too large to keep",
+                        id
+                    ))
+                }
+                None => (Some(response.body), PathBuf::new()),
+            }
+        }
+        BodySource::File { path } => (None, response_file::response_path(&path)?),
+    };
+    tokio::task::spawn_blocking(move || {
+        let body = match inline {
+            Some(body) => query::parse_body(&body)?,
+            None => query::read_body(&path)?,
+        };
+        query::query(
+            &body,
+            language.unwrap_or_default(),
+            &expression,
+            limit.unwrap_or(usize::MAX),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[command]
+async fn list_saved_requests(state: State<'_, AppState>) -> Result<Vec<SavedRequest>, String> {
+    state.store().saved_requests()
+}
+
+// Creates the request when it has no id yet, otherwise replaces the stored one.
+#[command]
+async fn save_request(
+    saved: SavedRequest,
+    state: State<'_, AppState>,
+) -> Result<SavedRequest, String> {
+    let mut saved = saved::normalize(saved)?;
+    let now = Utc::now();
+    match state.store().saved_request(&saved.id)? {
+        Some(existing) => saved.created_at = existing.created_at,
+        None if saved.id.is_empty() => {
+            saved.id = saved::new_id();
+            saved.created_at = now;
+        }
+        None => return Err(format!("Unknown saved request: {}", saved.id)),
+    }
+    saved.updated_at = now;
+    state.store().put_saved_request(&saved)?;
+    Ok(saved)
+}
+
+#[command]
+async fn delete_saved_request(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.store().delete_saved_request(&id)
+}
+
+#[command]
+async fn send_saved_request(
+    id: String,
+    request_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ResponseData, String> {
+    let saved = state
+        .store()
+        .saved_request(&id)?
+        .ok_or_else(|| format!("Unknown saved request: {}", id))?;
+    send_request(saved.request, request_id, app_handle, &state).await
+}
+
+// Sends the steps in order, emitting a `run-step` event after each, once per row of the
+// data file if there is one. Step `i` of row `r` can be cancelled with
+// `cancel_request("<run_id>:<r>:<i>")`.
+#[command]
+async fn run_collection(
+    options: RunOptions,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<RunSummary, String> {
+    run_steps(options, Some(app_handle), &state).await
+}
+
+async fn run_steps(
+    options: RunOptions,
+    events: Option<tauri::AppHandle>,
+    state: &AppState,
+) -> Result<RunSummary, String> {
+    let started = Instant::now();
+    let run_id = options.run_id.clone().unwrap_or_else(saved::new_id);
+    let rows = match &options.data_file {
+        Some(path) => runner::parse_data(
+            &tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| e.to_string())?,
+        )?,
+        None => vec![HashMap::new()],
+    };
+    let mut summary = RunSummary {
+        run_id: run_id.clone(),
+        ..RunSummary::default()
+    };
+    let total = options.steps.len() * rows.len();
+    let mut stopped = false;
+    let mut pause = Duration::ZERO;
+    for (iteration, row) in rows.into_iter().enumerate() {
+        let mut context = RunContext::for_row(row.clone());
+        let mut outcome = IterationResult {
+            index: iteration,
+            data: row,
+            ..IterationResult::default()
+        };
+        for (index, step) in options.steps.iter().enumerate() {
+            let delay = Duration::from_millis(options.delay_ms).max(std::mem::take(&mut pause));
+            if !summary.steps.is_empty() && !delay.is_zero() {
+                sleep(delay).await;
+            }
+            let step_started = Instant::now();
+            let (name, input) = match step {
+                RunStep::Saved { id } => match state.store().saved_request(id)? {
+                    Some(saved) => (saved.name, Ok(saved.request)),
+                    None => (id.clone(), Err(format!("Unknown saved request: {}", id))),
+                },
+                RunStep::Request { name, request } => (name.clone(), Ok(*request.clone())),
+            };
+            let (request, result) = match input {
+                Ok(mut request) => {
+                    request.environment =
+                        request.environment.or_else(|| options.environment.clone());
+                    let variables = match &options.variables {
+                        Some(variables) => Ok(variables.clone()),
+                        None => environment_variables(state, request.environment.as_deref()),
+                    };
+                    let result = match variables {
+                        Ok(variables) => {
+                            send_request_with(
+                                request.clone(),
+                                Some(context.variables(variables)),
+                                Some(format!("{}:{}:{}", run_id, iteration, index)),
+                                events.clone(),
+                                state,
+                                !options.skip_history,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    (request, result)
+                }
+                Err(e) => (RequestInput::default(), Err(e)),
+            };
+            let result = StepResult::new(
+                &run_id,
+                iteration,
+                index,
+                name,
+                &request,
+                result,
+                step_started.elapsed().as_millis() as u64,
+            );
+            context.record(&result.extracted);
+            if options.honor_rate_limits {
+                pause = result
+                    .response
+                    .as_ref()
+                    .and_then(|response| response.rate_limit.as_ref())
+                    .and_then(|info| info.delay())
+                    .unwrap_or_default()
+                    .min(MAX_RATE_LIMIT_PAUSE);
+            }
+            if let Some(handle) = &events {
+                let _ = handle.emit_all("run-step", result.clone());
+            }
+            let passed = result.passed;
+            summary.steps.push(result);
+            if passed {
+                outcome.passed += 1;
+            } else {
+                outcome.failed += 1;
+                if options.stop_on_failure {
+                    stopped = true;
+                    break;
+                }
+            }
+        }
+        summary.passed += outcome.passed;
+        summary.failed += outcome.failed;
+        summary.iterations.push(outcome);
+        if stopped {
+            break;
+        }
+    }
+    summary.skipped = total - summary.steps.len();
+    summary.elapsed_ms = started.elapsed().as_millis() as u64;
+    Ok(summary)
+}
+
+// Sends the request from concurrent workers, emitting a `load-test-progress` report every
+// second. `cancel_request(<test_id>)` stops the test early; the report then covers the
+// requests that completed. Extractions and scripts are dropped, since they would write to
+// the environment from every worker at once.
+#[command]
+async fn load_test(
+    options: LoadTestOptions,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<LoadTestReport, String> {
+    let test_id = options.test_id.clone().unwrap_or_else(saved::new_id);
+    let request = options.request.clone();
+    let variables = environment_variables(&state, request.environment.as_deref())?;
+    let concurrency = options.concurrency();
+    let limit = options.iterations();
+    let duration = options.duration_secs.map(Duration::from_secs);
+    let ramp_up = Duration::from_secs(options.ramp_up_secs);
+    let started = Instant::now();
+    let sent = AtomicU64::new(0);
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let workers = join_all((0..concurrency).map(|worker| {
+        let (request, variables, sender, sent, state) =
+            (&request, &variables, sender.clone(), &sent, &state);
+        async move {
+            // Workers start evenly spread over the ramp-up.
+            sleep(ramp_up * worker as u32 / concurrency as u32).await;
+            loop {
+                if duration.is_some_and(|duration| started.elapsed() >= duration)
+                    || limit.is_some_and(|limit| sent.fetch_add(1, Ordering::SeqCst) >= limit)
+                {
+                    break;
+                }
+                let sent_at = Instant::now();
+                let outcome = match send_lean(request.clone(), variables, state).await {
+                    Ok(response) => (Some(response.status), loadtest::response_error(&response)),
+                    Err(e) => (None, Some(e)),
+                };
+                let _ = sender.send((sent_at.elapsed().as_micros() as u64, outcome));
+            }
+        }
+    }));
+    drop(sender);
+    let mut recorder = LoadRecorder::default();
+    let report = async {
+        let mut last_progress = Instant::now();
+        while let Some((micros, (status, error))) = receiver.recv().await {
+            recorder.record(micros, status, error);
+            if last_progress.elapsed() >= LOAD_TEST_PROGRESS_INTERVAL {
+                last_progress = Instant::now();
+                let _ = app_handle.emit_all(
+                    "load-test-progress",
+                    recorder.report(&test_id, started.elapsed().as_millis() as u64, false),
+                );
+            }
+        }
+    };
+    let _ = run_cancellable(&state.in_flight, Some(test_id.clone()), async {
+        join(workers, report).await;
+        Ok(())
+    })
+    .await;
+    let _ = state.clients.save_cookies();
+    let report = recorder.report(&test_id, started.elapsed().as_millis() as u64, true);
+    let _ = app_handle.emit_all("load-test-progress", report.clone());
+    Ok(report)
+}
+
+// One load test iteration: only assertions are checked; no scripts, extractions,
+// contract checks, coverage, history or events.
+async fn send_lean(
+    input: RequestInput,
+    variables: &HashMap<String, String>,
+    state: &AppState,
+) -> Result<ResponseData, String> {
+    let checks = input.assertions.clone();
+    let (spec, key, retry) = prepare_input(input, variables, state).await?;
+    let client = state.clients.client(&key)?;
+    let mut response = execute_request(client, spec, retry, None, None, |_, _| {}).await?;
+    if let Some(checks) = &checks {
+        response.assertions = assertions::evaluate(&response, checks);
+    }
+    Ok(response)
+}
+
+#[command]
+async fn list_monitors(state: State<'_, AppState>) -> Result<Vec<MonitorStatus>, String> {
+    let store = state.store();
+    store
+        .monitors()?
+        .into_iter()
+        .map(|monitor| {
+            let checks = store.monitor_checks(&monitor.id, monitors::MONITOR_CHECKS_KEPT)?;
+            Ok(MonitorStatus {
+                monitor,
+                stats: monitors::stats(&checks),
+            })
+        })
+        .collect()
+}
+
+#[command]
+async fn save_monitor(monitor: Monitor, state: State<'_, AppState>) -> Result<Monitor, String> {
+    let mut monitor = monitors::normalize(monitor)?;
+    if monitor.id.is_empty() {
+        monitor.id = saved::new_id();
+    }
+    state.store().put_monitor(&monitor)?;
+    Ok(monitor)
+}
+
+#[command]
+async fn delete_monitor(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.store().delete_monitor(&id)
+}
+
+// Newest first.
+#[command]
+async fn monitor_history(
+    id: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MonitorCheck>, String> {
+    state
+        .store()
+        .monitor_checks(&id, limit.unwrap_or(monitors::MONITOR_CHECKS_KEPT))
+}
+
+#[command]
+async fn run_monitor(
+    id: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MonitorCheck, String> {
+    let monitor = state
+        .store()
+        .monitor(&id)?
+        .ok_or_else(|| format!("Unknown monitor: {}", id))?;
+    check_monitor(&app_handle, &state, &monitor).await
+}
+
+#[command]
+async fn cancel_request(request_id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let handle = state.in_flight.lock().unwrap().remove(&request_id);
+    match handle {
+        Some((_, handle)) => {
+            handle.abort();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// Keeps the request until its host can be reached, then sends it in the background and
+// reports with a `queued-request-sent` event. The queue is kept with the workspace.
+#[command]
+async fn queue_request(
+    request: RequestInput,
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<QueuedRequest, String> {
+    let variables = environment_variables(&state, request.environment.as_deref())?;
+    let url = template::render(&request.url, &variables);
+    let unresolved = template::unresolved(&url);
+    if !unresolved.is_empty() {
+        return Err(format!(
+            "Unresolved variables in URL: {}",
+            unresolved.join(", ")
+        ));
+    }
+    let queued = QueuedRequest::new(saved::new_id(), name.unwrap_or_default(), request, &url)?;
+    state.store().put_queued_request(&queued)?;
+    Ok(queued)
+}
+
+#[command]
+async fn queued_requests(state: State<'_, AppState>) -> Result<Vec<QueuedRequest>, String> {
+    state.store().queued_requests()
+}
+
+// Takes the request off the queue, or cancels it if it is being sent right now.
+#[command]
+async fn cancel_queued_request(id: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let sending = state.in_flight.lock().unwrap().remove(&id);
+    if let Some((_, handle)) = &sending {
+        handle.abort();
+    }
+    Ok(state.store().delete_queued_request(&id)? || sending.is_some())
+}
+
+#[command]
+async fn network_status(state: State<'_, AppState>) -> Result<NetworkStatus, String> {
+    Ok(state.network.lock().unwrap().clone())
+}
+
+// Only the active workspace's queue is sent, and only it can change, so the others are
+// counted when the active workspace changes.
+fn refresh_other_queues(state: &AppState) {
+    let active = state.workspaces.active();
+    let queues = state
+        .workspaces
+        .list()
+        .into_iter()
+        .filter(|workspace| workspace.id != active)
+        .filter_map(|workspace| {
+            let dir = workspace_dir(&state.data_dir, &workspace.id);
+            if !dir.join(storage::DATABASE_FILE).exists() {
+                return None;
+            }
+            let count = Store::open(&dir)
+                .and_then(|store| store.queued_requests())
+                .ok()?
+                .len();
+            (count > 0).then_some(WorkspaceQueue {
+                workspace_id: workspace.id,
+                workspace_name: workspace.name,
+                count,
+            })
+        })
+        .collect();
+    state.network.lock().unwrap().other_workspaces = queues;
+}
+
+// Probes through the proxy when a request to the host would go through one.
+async fn queued_host_reachable(state: &AppState, queued: &QueuedRequest) -> bool {
+    let origin = queued.origin();
+    if !state.clients.proxy_applies(&origin) {
+        return offline::reachable(&queued.host, queued.port).await;
+    }
+    // The probe only asks whether the host answers, so its certificate is not checked.
+    match state.clients.client(&ClientKey {
+        accept_invalid_certs: true,
+        ..ClientKey::default()
+    }) {
+        Ok(client) => offline::reachable_through(&client, &origin).await,
+        Err(_) => false,
+    }
+}
+
+#[command]
+async fn save_response_body(body_path: String, save_path: String) -> Result<(), String> {
+    tokio::fs::copy(&body_path, &save_path)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Reads part of a response written to disk (`body_path`) as text, for paging through
+// bodies too large to return whole.
+#[command]
+async fn read_response_chunk(
+    body_path: String,
+    offset: u64,
+    length: usize,
+) -> Result<BodyChunk, String> {
+    let path = response_file::response_path(&body_path)?;
+    tokio::task::spawn_blocking(move || response_file::read_chunk(&path, offset, length))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// Pretty-prints or minifies a JSON or XML body in one streaming pass; the language is
+// sniffed when not given. A response file (`body_path`) is formatted into a new one.
+#[command]
+async fn format_body(
+    body: Option<String>,
+    body_path: Option<String>,
+    language: Option<BodyLanguage>,
+    style: FormatStyle,
+    indent: Option<usize>,
+) -> Result<FormattedBody, String> {
+    let path = body_path
+        .as_deref()
+        .map(response_file::response_path)
+        .transpose()?;
+    tokio::task::spawn_blocking(move || match (path, body) {
+        (Some(path), _) => format::format_file(&path, language, style, indent),
+        (None, Some(body)) => format::format_text(&body, language, style, indent),
+        (None, None) => Err("Nothing to format".into()),
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// A page of `rows` hexdump lines from `offset` of a binary response's `body_path` or of a
+// downloaded file, which may be anywhere the user saved it.
+#[command]
+async fn hex_dump(
+    path: String,
+    offset: Option<u64>,
+    rows: Option<usize>,
+) -> Result<HexPage, String> {
+    tokio::task::spawn_blocking(move || {
+        hexdump::read_page(
+            std::path::Path::new(&path),
+            offset.unwrap_or(0),
+            rows.unwrap_or(hexdump::DEFAULT_ROWS),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[command]
+async fn search_response_body(
+    body_path: String,
+    query: String,
+    case_sensitive: Option<bool>,
+    limit: Option<usize>,
+) -> Result<SearchResult, String> {
+    let path = response_file::response_path(&body_path)?;
+    tokio::task::spawn_blocking(move || {
+        response_file::search(
+            &path,
+            &query,
+            case_sensitive.unwrap_or(false),
+            limit.unwrap_or(1000),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[command]
+async fn get_settings(state: State<'_, AppState>) -> Result<Settings, String> {
+    Ok(state.settings.lock().unwrap().clone())
+}
+
+#[command]
+async fn update_settings(
+    settings: Settings,
+    state: State<'_, AppState>,
+) -> Result<Settings, String> {
+    state.clients.apply(&settings)?;
+    state.rate_limiter.configure(&settings.rate_limit)?;
+    save_settings(&state.data_dir, &settings)?;
+    *state.settings.lock().unwrap() = settings.clone();
+    if settings.persist_cookies {
+        state.clients.persist_cookies_in(Some(state.store()));
+        state.clients.save_cookies()?;
+    } else {
+        state.clients.persist_cookies_in(None);
+        state.store().clear_cookie_jars()?;
+    }
+    Ok(settings)
+}
+
+#[command]
+async fn list_cookies(
+    domain: Option<String>,
+    jar: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CookieInfo>, String> {
+    Ok(state.clients.jar(jar.as_deref()).list(domain.as_deref()))
+}
+
+#[command]
+async fn set_cookie(
+    url: String,
+    cookie: String,
+    jar: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.clients.jar(jar.as_deref()).set(&url, &cookie)?;
+    state.clients.save_cookies()
+}
+
+#[command]
+async fn delete_cookie(
+    domain: String,
+    path: String,
+    name: String,
+    jar: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let deleted = state
+        .clients
+        .jar(jar.as_deref())
+        .delete(&domain, &path, &name);
+    state.clients.save_cookies()?;
+    Ok(deleted)
+}
+
+#[command]
+async fn clear_cookies(
+    domain: Option<String>,
+    jar: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.clients.jar(jar.as_deref()).clear(domain.as_deref());
+    state.clients.save_cookies()
+}
+
+#[command]
+async fn oauth2_authorize(
+    config: OAuthConfig,
+    environment: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OAuthToken, String> {
+    let environment = environment.unwrap_or_else(|| DEFAULT_ENVIRONMENT.to_string());
+    let client = state.clients.client(&ClientKey::default())?;
+    let token = oauth2::authorize(&client, &config, |url| {
+        tauri::api::shell::open(&app_handle.shell_scope(), url, None).map_err(|e| e.to_string())
+    })
+    .await?;
+    state.oauth_tokens().insert(
+        &environment,
+        oauth2::StoredToken {
+            config,
+            token: token.clone(),
+        },
+    )?;
+    Ok(token)
+}
+
+#[command]
+async fn oauth2_get_token(
+    environment: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<OAuthToken>, String> {
+    let environment = environment.unwrap_or_else(|| DEFAULT_ENVIRONMENT.to_string());
+    let client = state.clients.client(&ClientKey::default())?;
+    state.oauth_tokens().current(&client, &environment).await
+}
+
+#[command]
+async fn oauth2_clear_token(
+    environment: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    state
+        .oauth_tokens()
+        .remove(environment.as_deref().unwrap_or(DEFAULT_ENVIRONMENT))
+}
+
+#[command]
+async fn generate_jwt(config: JwtConfig) -> Result<String, String> {
+    jwt::generate(&config).await
+}
+
+#[command]
+async fn list_auth_profiles(state: State<'_, AppState>) -> Result<Vec<AuthProfile>, String> {
+    Ok(state.auth_profiles().list())
+}
+
+#[command]
+async fn save_auth_profile(profile: AuthProfile, state: State<'_, AppState>) -> Result<(), String> {
+    state.auth_profiles().upsert(profile)
+}
+
+#[command]
+async fn delete_auth_profile(name: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.auth_profiles().remove(&name)
+}
+
+// Builds the GET for a spec with the headers and credentials saved for its collection.
+// Only credentials that fit in a header or the query string can be used here.
+async fn spec_request(
+    state: &AppState,
+    client: &Client,
+    url: &str,
+    headers: &HashMap<String, String>,
+    auth: Option<&Auth>,
+) -> Result<reqwest::RequestBuilder, String> {
+    let mut headers = headers.clone();
+    let mut req = client.get(url);
+    if let Some(auth) = auth {
+        let auth = resolve_auth(state, auth.clone()).await?;
+        if !matches!(
+            auth,
+            Auth::Basic { .. } | Auth::Bearer { .. } | Auth::ApiKey { .. }
+        ) {
+            return Err("Spec fetching supports basic, bearer and API key credentials only".into());
+        }
+        auth.apply_headers(&mut headers);
+        if let Some(pair) = auth.query_pair() {
+            req = req.query(&[pair]);
+        }
+    }
+    for (name, value) in &headers {
+        req = req.header(name.as_str(), value.as_str());
+    }
+    Ok(req)
+}
+
+fn spec_location(col: &OpenApiCollection) -> String {
+    col.source.clone().unwrap_or_else(|| col.url.clone())
+}
+
+// Appends newly imported collections to the display order.
+fn add_to_order(state: &AppState, url: &str) {
+    let mut order = state.collection_order.lock().unwrap();
+    if !order.iter().any(|u| u == url) {
+        order.push(url.to_string());
+    }
+}
+
+// Writes the collection to the store; called after each change so a crash loses at
+// most the change in progress.
+fn save_collection(state: &AppState, url: &str) -> Result<(), String> {
+    match state.collections.get(url) {
+        Some(col) => state.store().put_collection(url, col.value()),
+        None => Ok(()),
+    }
+}
+
+// Runs the introspection query like any other request, so auth profiles and the active
+// environment's variables apply. Spilled responses are read back from disk.
+async fn introspect_graphql(
+    url: &str,
+    headers: HashMap<String, String>,
+    auth: Option<Auth>,
+    state: &AppState,
+) -> Result<GraphqlSchema, String> {
+    let query = GraphqlQuery {
+        query: graphql::INTROSPECTION_QUERY.into(),
+        variables: None,
+        operation_name: Some("IntrospectionQuery".into()),
+    };
+    let input = RequestInput {
+        method: "POST".into(),
+        url: url.to_string(),
+        headers: headers.clone(),
+        auth: auth.clone(),
+        graphql: Some(query),
+        ..RequestInput::default()
+    };
+    let variables = environment_variables(state, None)?;
+    let response = execute_input(input, &variables, None, None, state).await?;
+    let body = match &response.body_path {
+        Some(path) => tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => response.body,
+    };
+    let mut schema = graphql::parse_introspection(&body, url).map_err(|e| {
+        if response.status >= 400 {
+            format!("HTTP {}: {}", response.status, e)
+        } else {
+            e
+        }
+    })?;
+    schema.fetch_headers = headers;
+    schema.fetch_auth = auth;
+    Ok(schema)
+}
+
+#[command]
+async fn import_graphql_schema(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    auth: Option<Auth>,
+    state: State<'_, AppState>,
+) -> Result<GraphqlSchema, String> {
+    let schema = introspect_graphql(&url, headers.unwrap_or_default(), auth, &state).await?;
+    state.store().put_graphql_schema(&schema)?;
+    Ok(schema)
+}
+
+// Introspects again with the headers and credentials the schema was imported with.
+#[command]
+async fn refresh_graphql_schema(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<GraphqlSchema, String> {
+    let previous = state
+        .store()
+        .graphql_schema(&url)?
+        .ok_or_else(|| format!("Unknown GraphQL schema: {}", url))?;
+    let schema =
+        introspect_graphql(&url, previous.fetch_headers, previous.fetch_auth, &state).await?;
+    state.store().put_graphql_schema(&schema)?;
+    Ok(schema)
+}
+
+#[command]
+async fn list_graphql_schemas(state: State<'_, AppState>) -> Result<Vec<GraphqlSchema>, String> {
+    state.store().graphql_schemas()
+}
+
+#[command]
+async fn remove_graphql_schema(url: String, state: State<'_, AppState>) -> Result<bool, String> {
+    state.store().delete_graphql_schema(&url)
+}
+
+// Lists a gRPC server's services and methods, from its reflection service or from
+// `.proto` files, with a request template for each method.
+#[command]
+async fn list_grpc_services(
+    url: String,
+    source: DescriptorSource,
+    metadata: Option<Vec<(String, String)>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<GrpcService>, String> {
+    let variables = environment_variables(&state, None)?;
+    let metadata: Vec<(String, String)> = metadata
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| (name, template::render(&value, &variables)))
+        .collect();
+    let channel = match source {
+        DescriptorSource::Reflection => {
+            Some(grpc::connect(&template::render(&url, &variables)).await?)
+        }
+        DescriptorSource::Protos { .. } => None,
+    };
+    let pool = grpc::descriptors(&source, channel.as_ref(), &metadata).await?;
+    Ok(grpc::services(&pool))
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct GrpcMessageEvent {
+    request_id: Option<String>,
+    index: usize,
+    message: Value,
+}
+
+// Variables are substituted into the URL, metadata and message. Response messages are
+// emitted as `grpc-message` events as they arrive, for server-streaming calls.
+#[command]
+async fn grpc_call(
+    mut call: GrpcCall,
+    request_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<GrpcResponse, String> {
+    let variables = environment_variables(&state, None)?;
+    call.url = template::render(&call.url, &variables);
+    call.metadata = call
+        .metadata
+        .into_iter()
+        .map(|(name, value)| (name, template::render(&value, &variables)))
+        .collect();
+    call.message = template::render_json(call.message, &variables);
+    let event_id = request_id.clone();
+    run_cancellable(&state.in_flight, request_id, async {
+        let channel = grpc::connect(&call.url).await?;
+        let pool = grpc::descriptors(&call.source, Some(&channel), &call.metadata).await?;
+        grpc::call(channel, &pool, &call, |index, message| {
+            let _ = app_handle.emit_all(
+                "grpc-message",
+                GrpcMessageEvent {
+                    request_id: event_id.clone(),
+                    index,
+                    message: message.clone(),
+                },
+            );
+        })
+        .await
+    })
+    .await
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct MqttMessageEvent {
+    connection_id: String,
+    message: MqttMessage,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct MqttClosedEvent {
+    connection_id: String,
+    reason: String,
+}
+
+fn mqtt_session(state: &AppState, connection_id: &str) -> Result<mqtt::Session, String> {
+    state
+        .mqtt_sessions
+        .lock()
+        .unwrap()
+        .get(connection_id)
+        .cloned()
+        .ok_or_else(|| format!("No MQTT connection {}", connection_id))
+}
+
+// Connects under `connection_id`, replacing any connection already using it. Received
+// messages arrive as `mqtt-message` events, and `mqtt-closed` reports why the
+// connection ended.
+#[command]
+async fn mqtt_connect(
+    connection_id: String,
+    mut options: MqttOptions,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let variables = environment_variables(&state, None)?;
+    options.url = template::render(&options.url, &variables);
+    options.username = options
+        .username
+        .map(|username| template::render(&username, &variables));
+    options.password = options
+        .password
+        .map(|password| template::render(&password, &variables));
+    let (session, connection) = mqtt::connect(&options).await?;
+    if let Some(previous) = state
+        .mqtt_sessions
+        .lock()
+        .unwrap()
+        .insert(connection_id.clone(), session.clone())
+    {
+        previous.disconnect();
+    }
+    tokio::spawn(async move {
+        let events = app_handle.clone();
+        let id = connection_id.clone();
+        let reason = connection
+            .run(|message| {
+                let _ = events.emit_all(
+                    "mqtt-message",
+                    MqttMessageEvent {
+                        connection_id: id.clone(),
+                        message,
+                    },
+                );
+            })
+            .await;
+        let state = app_handle.state::<AppState>();
+        let mut sessions = state.mqtt_sessions.lock().unwrap();
+        if sessions
+            .get(&connection_id)
+            .is_some_and(|current| current.same(&session))
+        {
+            sessions.remove(&connection_id);
+        }
+        drop(sessions);
+        let _ = app_handle.emit_all(
+            "mqtt-closed",
+            MqttClosedEvent {
+                connection_id,
+                reason,
+            },
+        );
+    });
+    Ok(())
+}
+
+// Returns the QoS the broker granted for each topic, 128 where it refused.
+#[command]
+async fn mqtt_subscribe(
+    connection_id: String,
+    topics: Vec<MqttSubscription>,
+    state: State<'_, AppState>,
+) -> Result<Vec<u8>, String> {
+    mqtt_session(&state, &connection_id)?
+        .subscribe(topics)
+        .await
+}
+
+#[command]
+async fn mqtt_unsubscribe(
+    connection_id: String,
+    topics: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    mqtt_session(&state, &connection_id)?
+        .unsubscribe(topics)
+        .await
+}
+
+#[command]
+async fn mqtt_publish(
+    connection_id: String,
+    mut message: MqttPublish,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let variables = environment_variables(&state, None)?;
+    message.topic = template::render(&message.topic, &variables);
+    if !message.base64 {
+        message.payload = template::render(&message.payload, &variables);
+    }
+    mqtt_session(&state, &connection_id)?.publish(message).await
+}
+
+#[command]
+async fn mqtt_disconnect(
+    connection_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let session = state.mqtt_sessions.lock().unwrap().remove(&connection_id);
+    Ok(session.map(|session| session.disconnect()).is_some())
+}
+
+// One route per operation; endpoints listed under several tags are served once.
+fn mock_routes(col: &OpenApiCollection) -> Vec<MockRoute> {
+    let mut routes: Vec<MockRoute> = Vec::new();
+    for endpoint in col.groups.values().flatten() {
+        if routes
+            .iter()
+            .any(|r| r.method.eq_ignore_ascii_case(&endpoint.method) && r.route == endpoint.route)
+        {
+            continue;
+        }
+        let responses = endpoint
+            .responses
+            .iter()
+            .map(|(status, response)| MockResponse {
+                status: status.clone(),
+                content_type: response
+                    .media_types
+                    .iter()
+                    .find(|m| m.as_str() == "application/json")
+                    .or(response.media_types.first())
+                    .cloned(),
+                example: response.example.clone(),
+                examples: response.examples.clone(),
+                headers: response
+                    .headers
+                    .iter()
+                    .filter_map(|h| {
+                        Some((
+                            h.name.clone(),
+                            h.example.as_ref().map(enum_value_to_string)?,
+                        ))
+                    })
+                    .collect(),
+            })
+            .collect();
+        routes.push(MockRoute {
+            method: endpoint.method.to_uppercase(),
+            route: endpoint.route.clone(),
+            responses,
+        });
+    }
+    routes
+}
+
+// Serves the collection's example responses on a local port, replacing a mock already
+// running for it. Each request served is reported as a `mock-request` event.
+#[command]
+async fn start_mock_server(
+    collection: String,
+    options: Option<MockOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<MockServerInfo, String> {
+    let routes = {
+        mock_routes(
+            state
+                .collections
+                .get(&collection)
+                .ok_or_else(|| format!("Unknown collection: {}", collection))?
+                .value(),
+        )
+    };
+    if let Some(previous) = state.mock_servers.lock().unwrap().remove(&collection) {
+        previous.stop();
+    }
+    let on_hit = Arc::new(move |hit: MockHit| {
+        let _ = app_handle.emit_all("mock-request", hit);
+    });
+    let server =
+        MockServer::start(&collection, routes, options.unwrap_or_default(), on_hit).await?;
+    let info = server.info();
+    state
+        .mock_servers
+        .lock()
+        .unwrap()
+        .insert(collection, server);
+    Ok(info)
+}
+
+#[command]
+async fn stop_mock_server(collection: String, state: State<'_, AppState>) -> Result<bool, String> {
+    let server = state.mock_servers.lock().unwrap().remove(&collection);
+    Ok(server.map(|server| server.stop()).is_some())
+}
+
+// Latency, error injection and selections change in place; the port does not.
+#[command]
+async fn configure_mock_server(
+    collection: String,
+    options: MockOptions,
+    state: State<'_, AppState>,
+) -> Result<MockServerInfo, String> {
+    let servers = state.mock_servers.lock().unwrap();
+    let server = servers
+        .get(&collection)
+        .ok_or_else(|| format!("No mock server for {}", collection))?;
+    server.configure(options)?;
+    Ok(server.info())
+}
+
+#[command]
+async fn list_mock_servers(state: State<'_, AppState>) -> Result<Vec<MockServerInfo>, String> {
+    Ok(state
+        .mock_servers
+        .lock()
+        .unwrap()
+        .values()
+        .map(MockServer::info)
+        .collect())
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct ProxyExchange {
+    history_id: Option<i64>,
+    method: String,
+    url: String,
+    status: u16,
+    replayed: bool,
+}
+
+// The newest response in the active workspace's history to exactly this method and URL.
+fn recorded_response(store: &Store, method: &str, url: &str) -> Option<ResponseData> {
+    store.latest_response(method, url).ok()??.response
+}
+
+// Runs a local HTTP proxy whose traffic is recorded into the active workspace's history,
+// replacing a proxy already running. Every exchange is reported as a `proxy-exchange` event.
+#[command]
+async fn start_proxy(
+    options: Option<ProxyOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<ProxyInfo, String> {
+    let options = options.unwrap_or_default();
+    if let Some(previous) = state.proxy.lock().unwrap().take() {
+        previous.stop();
+    }
+    let authority = if options.intercept_tls {
+        Some(Arc::new(CertificateAuthority::load_or_create(
+            &state.data_dir,
+        )?))
+    } else {
+        None
+    };
+    let recorder_handle = app_handle.clone();
+    let record = Arc::new(move |exchange: Exchange| {
+        let state = recorder_handle.state::<AppState>();
+        let history_id = if exchange.replayed {
+            None
+        } else {
+            state
+                .store()
+                .add_history(&HistoryEntry::new(
+                    exchange.request.clone(),
+                    &Ok(exchange.response.clone()),
+                ))
+                .ok()
+        };
+        let event = ProxyExchange {
+            history_id,
+            method: exchange.request.method,
+            url: exchange.request.url,
+            status: exchange.response.status,
+            replayed: exchange.replayed,
+        };
+        let _ = recorder_handle.emit_all("proxy-exchange", event);
+    });
+    let replay = Arc::new(move |method: &str, url: &str| {
+        recorded_response(&app_handle.state::<AppState>().store(), method, url)
+    });
+    let proxy = Proxy::start(options, authority, record, replay).await?;
+    let info = proxy.info();
+    *state.proxy.lock().unwrap() = Some(proxy);
+    Ok(info)
+}
+
+#[command]
+async fn stop_proxy(state: State<'_, AppState>) -> Result<bool, String> {
+    let proxy = state.proxy.lock().unwrap().take();
+    Ok(proxy.map(|proxy| proxy.stop()).is_some())
+}
+
+#[command]
+async fn proxy_status(state: State<'_, AppState>) -> Result<Option<ProxyInfo>, String> {
+    Ok(state.proxy.lock().unwrap().as_ref().map(Proxy::info))
+}
+
+// Listens on a local port for callbacks such as payment webhooks or OAuth redirects,
+// replacing a listener already running under the same id. Each request received is
+// kept by the listener and reported as a `webhook-request` event.
+#[command]
+async fn start_webhook_listener(
+    listener_id: String,
+    options: Option<WebhookOptions>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<WebhookInfo, String> {
+    if let Some(previous) = state.webhook_listeners.lock().unwrap().remove(&listener_id) {
+        previous.stop();
+    }
+    let on_capture = Arc::new(move |capture: WebhookCapture| {
+        let _ = app_handle.emit_all("webhook-request", capture);
+    });
+    let listener =
+        WebhookListener::start(&listener_id, options.unwrap_or_default(), on_capture).await?;
+    let info = listener.info();
+    state
+        .webhook_listeners
+        .lock()
+        .unwrap()
+        .insert(listener_id, listener);
+    Ok(info)
+}
+
+// Its captures go with it.
+#[command]
+async fn stop_webhook_listener(
+    listener_id: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    let listener = state.webhook_listeners.lock().unwrap().remove(&listener_id);
+    Ok(listener.map(|listener| listener.stop()).is_some())
+}
+
+#[command]
+async fn configure_webhook_listener(
+    listener_id: String,
+    options: WebhookOptions,
+    state: State<'_, AppState>,
+) -> Result<WebhookInfo, String> {
+    let listeners = state.webhook_listeners.lock().unwrap();
+    let listener = listeners
+        .get(&listener_id)
+        .ok_or_else(|| format!("No webhook listener {}", listener_id))?;
+    listener.configure(options)?;
+    Ok(listener.info())
+}
+
+#[command]
+async fn list_webhook_listeners(state: State<'_, AppState>) -> Result<Vec<WebhookInfo>, String> {
+    Ok(state
+        .webhook_listeners
+        .lock()
+        .unwrap()
+        .values()
+        .map(WebhookListener::info)
+        .collect())
+}
+
+#[command]
+async fn webhook_captures(
+    listener_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<WebhookCapture>, String> {
+    let listeners = state.webhook_listeners.lock().unwrap();
+    Ok(listeners
+        .get(&listener_id)
+        .ok_or_else(|| format!("No webhook listener {}", listener_id))?
+        .captures())
+}
+
+// Removes one capture, or all of the listener's captures without an id.
+#[command]
+async fn clear_webhook_captures(
+    listener_id: String,
+    capture_id: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let listeners = state.webhook_listeners.lock().unwrap();
+    Ok(listeners
+        .get(&listener_id)
+        .ok_or_else(|| format!("No webhook listener {}", listener_id))?
+        .clear(capture_id))
+}
+
+// Turns captured exchanges, by history id, into saved requests of the active workspace.
+#[command]
+async fn save_captured_requests(
+    ids: Vec<i64>,
+    state: State<'_, AppState>,
+) -> Result<HarImport, String> {
+    let store = state.store();
+    let mut entries = Vec::new();
+    for id in ids {
+        entries.push(
+            store
+                .history_entry(id)?
+                .ok_or_else(|| format!("No history entry {}", id))?,
+        );
+    }
+    let mut imported = har::import_history(&entries);
+    imported.saved_requests = store_imported(
+        &store,
+        std::mem::take(&mut imported.saved_requests),
+        &mut imported.warnings,
+    )?;
+    Ok(imported)
+}
+
+#[command]
+async fn set_spec_credentials(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    auth: Option<Auth>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let mut col = state
+        .collections
+        .get_mut(&url)
+        .ok_or_else(|| format!("Unknown collection: {}", url))?;
+    col.fetch_headers = headers.unwrap_or_default();
+    col.fetch_auth = auth;
+    drop(col);
+    save_collection(&state, &url)
+}
+
+// With an `import_id`, parsing reports `import-progress` events and `cancel_request`
+// stops the import.
+#[command]
+async fn import_openapi(
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    auth: Option<Auth>,
+    import_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenApiCollection, String> {
+    let progress = import_id.clone().map(|import_id| {
+        let (location, last_emit) = (url.clone(), Mutex::new(None::<Instant>));
+        Arc::new(move |processed: usize, total: usize| {
+            let mut last = last_emit.lock().unwrap();
+            if processed < total && last.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return true;
+            }
+            *last = Some(Instant::now());
+            let _ = app_handle.emit_all(
+                "import-progress",
+                ImportProgress {
+                    import_id: import_id.clone(),
+                    url: location.clone(),
+                    processed,
+                    total,
+                },
+            );
+            true
+        }) as ParseProgress
+    });
+    let local = local_spec_path(&url);
+    let collection = run_cancellable(&state.in_flight, import_id, async {
+        if let Some(path) = &local {
+            let content = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            return load_openapi(
+                &state.clients.client(&ClientKey::default())?,
+                &content,
+                None,
+                &url,
+                None,
+                progress,
+            )
+            .await;
+        }
+        let client = state.clients.client(&ClientKey::default())?;
+        let headers = headers.unwrap_or_default();
+        let response = spec_request(&state, &client, &url, &headers, auth.as_ref())
+            .await?
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .error_for_status()
+            .map_err(|e| e.to_string())?;
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let last_modified = response
+            .headers()
+            .get("last-modified")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string());
+        let content = response.text().await.map_err(|e| e.to_string())?;
+
+        let mut collection = load_openapi(
+            &client,
+            &content,
+            content_type.as_deref(),
+            &url,
+            etag,
+            progress,
+        )
+        .await?;
+        collection.last_modified = last_modified;
+        collection.fetch_headers = headers;
+        collection.fetch_auth = auth;
+        Ok(collection)
+    })
+    .await?;
+    if let Some(path) = &local {
+        state.spec_watcher.watch(path, &url)?;
+    }
+    state.collections.insert(url.clone(), collection.clone());
+    add_to_order(&state, &url);
+    save_collection(&state, &url)?;
+    Ok(collection)
+}
+
+// Reads the collection's spec again, with the credentials it was imported with, and
+// reviews it for quality problems the importer tolerates.
+#[command]
+async fn lint_collection(url: String, state: State<'_, AppState>) -> Result<LintReport, String> {
+    let (location, headers, auth) = {
+        let col = state
+            .collections
+            .get(&url)
+            .ok_or_else(|| format!("Unknown collection: {}", url))?;
+        (
+            spec_location(&col),
+            col.fetch_headers.clone(),
+            col.fetch_auth.clone(),
+        )
+    };
+    let client = state.clients.client(&ClientKey::default())?;
+    let (content, content_type) =
+        fetch_spec(&state, &client, &location, &headers, auth.as_ref()).await?;
+    let json = spec::parse_document(&content, content_type.as_deref(), &location)?;
+    let json = refs::bundle_external_refs(&client, json, &location).await;
+    let json = if swagger::is_swagger2(&json) {
+        swagger::to_openapi3(&json)
+    } else {
+        json
+    };
+    Ok(lint::lint(&json))
+}
+
+// Reads a spec from a local path or URL, along with the content type it was served as.
+async fn fetch_spec(
+    state: &AppState,
+    client: &Client,
+    location: &str,
+    headers: &HashMap<String, String>,
+    auth: Option<&Auth>,
+) -> Result<(String, Option<String>), String> {
+    match local_spec_path(location) {
+        Some(path) => Ok((
+            tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?,
+            None,
+        )),
+        None => {
+            let response = spec_request(state, client, location, headers, auth)
+                .await?
+                .send()
+                .await
+                .map_err(|e| e.to_string())?
+                .error_for_status()
+                .map_err(|e| e.to_string())?;
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string());
+            Ok((
+                response.text().await.map_err(|e| e.to_string())?,
+                content_type,
+            ))
+        }
+    }
+}
+
+// One side of a spec diff: a document to fetch or paste, a loaded collection, or the
+// version of a collection its last sync replaced.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+enum SpecSource {
+    Location {
+        location: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    Content {
+        content: String,
+    },
+    Collection {
+        url: String,
+    },
+    PreviousVersion {
+        url: String,
+    },
+}
+
+async fn load_spec_source(
+    state: &AppState,
+    client: &Client,
+    source: SpecSource,
+) -> Result<OpenApiCollection, String> {
+    match source {
+        SpecSource::Location { location, headers } => {
+            let (content, content_type) =
+                fetch_spec(state, client, &location, &headers, None).await?;
+            load_openapi(
+                client,
+                &content,
+                content_type.as_deref(),
+                &location,
+                None,
+                None,
+            )
+            .await
+        }
+        SpecSource::Content { content } => {
+            load_openapi(client, &content, None, "pasted spec", None, None).await
+        }
+        SpecSource::Collection { url } => state
+            .collections
+            .get(&url)
+            .map(|col| col.clone())
+            .ok_or_else(|| format!("Unknown collection: {}", url)),
+        SpecSource::PreviousVersion { url } => state
+            .store()
+            .previous_collection(&url)?
+            .ok_or_else(|| format!("No earlier version of {} has been synced", url)),
+    }
+}
+
+// Compares two versions of a spec and sorts the changes into breaking and compatible.
+#[command]
+async fn diff_specs(
+    old: SpecSource,
+    new: SpecSource,
+    state: State<'_, AppState>,
+) -> Result<SpecDiff, String> {
+    let client = state.clients.client(&ClientKey::default())?;
+    let old = load_spec_source(&state, &client, old).await?;
+    let new = load_spec_source(&state, &client, new).await?;
+    Ok(specdiff::diff(&old, &new))
+}
+
+// Drafts an OpenAPI document from the history entries matching the filter, rendered with
+// the environment each was sent with. Written to `path` as well when given, as YAML for
+// a .yaml or .yml path and JSON otherwise.
+#[command]
+async fn generate_spec(
+    filter: Option<HistoryFilter>,
+    title: Option<String>,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<GeneratedSpec, String> {
+    let mut variables: HashMap<Option<String>, HashMap<String, String>> = HashMap::new();
+    let entries: Vec<HistoryEntry> = state
+        .store()
+        .history(&filter.unwrap_or_default())?
+        .into_iter()
+        .map(|mut entry| {
+            let environment = entry.request.environment.clone();
+            let variables = variables.entry(environment.clone()).or_insert_with(|| {
+                environment_variables(&state, environment.as_deref()).unwrap_or_default()
+            });
+            entry.request = template::render_input(entry.request, variables);
+            entry
+        })
+        .collect();
+    let generated = specgen::generate(&entries, title.as_deref().unwrap_or("Recorded API"));
+    if let Some(path) = path {
+        let content = if path.ends_with(".yaml") || path.ends_with(".yml") {
+            serde_yaml::to_string(&generated.document).map_err(|e| e.to_string())?
+        } else {
+            serde_json::to_string_pretty(&generated.document).map_err(|e| e.to_string())?
+        };
+        tokio::fs::write(&path, content)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(generated)
+}
+
+// Points every endpoint without its own servers at the chosen collection server.
+fn apply_server(
+    col: &mut OpenApiCollection,
+    index: usize,
+    variables: HashMap<String, String>,
+) -> Result<(), String> {
+    let server = col
+        .servers
+        .get(index)
+        .ok_or_else(|| format!("Collection has no server #{}", index))?;
+    let base = server.expand(&variables)?;
+    for endpoints in col.groups.values_mut() {
+        for endpoint in endpoints.iter_mut().filter(|e| e.servers.is_empty()) {
+            endpoint.path = format!("{}{}", base, endpoint.route);
+        }
+    }
+    col.server_index = index;
+    col.server_variables = variables;
+    Ok(())
+}
+
+// A refreshed spec keeps the server the user picked, as long as it still exists.
+fn keep_server_selection(updated: &mut OpenApiCollection, previous: &OpenApiCollection) {
+    let _ = apply_server(
+        updated,
+        previous.server_index,
+        previous.server_variables.clone(),
+    );
+}
+
+// Stores a refreshed collection and announces it, along with a changelog against the
+// previous version when anything relevant changed.
+fn replace_collection(
+    app_handle: &tauri::AppHandle,
+    mut updated: OpenApiCollection,
+) -> OpenApiCollection {
+    let state = app_handle.state::<AppState>();
+    let changelog = {
+        // Removed, or gone with a workspace switch, while the refresh was in flight.
+        let Some(mut previous) = state.collections.get_mut(&updated.url) else {
+            return updated;
+        };
+        keep_server_selection(&mut updated, &previous);
+        updated.source = previous.source.clone();
+        updated.name_override = previous.name_override.clone();
+        if let Some(name) = &previous.name_override {
+            updated.name = name.clone();
+        }
+        updated.sync_enabled = previous.sync_enabled;
+        updated.sync_interval_secs = previous.sync_interval_secs;
+        updated.fetch_headers = previous.fetch_headers.clone();
+        updated.fetch_auth = previous.fetch_auth.clone();
+        let changelog = changelog::diff_collections(&previous, &updated);
+        let _ = state
+            .store()
+            .put_previous_collection(&updated.url, previous.value());
+        *previous = updated.clone();
+        changelog
+    };
+    if let Some(server) = state.mock_servers.lock().unwrap().get(&updated.url) {
+        server.set_routes(mock_routes(&updated));
+    }
+    let _ = save_collection(&state, &updated.url);
+    let _ = app_handle.emit_all("collection-updated", updated.clone());
+    if !changelog.is_empty() {
+        let _ = app_handle.emit_all("collection-changed", changelog);
+    }
+    updated
+}
+
+// Loads the collection's spec again, from disk or over HTTP, and stores it. Returns
+// None when the server reports the spec unchanged.
+async fn refresh_collection(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+) -> Result<Option<OpenApiCollection>, String> {
+    let state = app_handle.state::<AppState>();
+    let client = state.clients.client(&ClientKey::default())?;
+    let location = state
+        .collections
+        .get(url)
+        .map(|col| spec_location(&col))
+        .unwrap_or_else(|| url.to_string());
+    if let Some(path) = local_spec_path(&location) {
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        if spec_unchanged(&state, url, &content) {
+            return Ok(None);
+        }
+        let mut updated = load_openapi(&client, &content, None, &location, None, None).await?;
+        updated.url = url.to_string();
+        return Ok(Some(replace_collection(app_handle, updated)));
+    }
+    let (current_etag, current_modified, headers, auth) = state
+        .collections
+        .get(url)
+        .map(|c| {
+            (
+                c.etag.clone(),
+                c.last_modified.clone(),
+                c.fetch_headers.clone(),
+                c.fetch_auth.clone(),
+            )
+        })
+        .unwrap_or_default();
+    let mut req = spec_request(&state, &client, &location, &headers, auth.as_ref()).await?;
+    if let Some(etag) = current_etag {
+        req = req.header("If-None-Match", etag);
+    }
+    if let Some(modified) = current_modified {
+        req = req.header("If-Modified-Since", modified);
+    }
+    let resp = req.send().await.map_err(|e| e.to_string())?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let resp = resp.error_for_status().map_err(|e| e.to_string())?;
+    let new_etag = resp
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let last_modified = resp
+        .headers()
+        .get("last-modified")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let content_type = resp
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let content = resp.text().await.map_err(|e| e.to_string())?;
+    if spec_unchanged(&state, url, &content) {
+        if let Some(mut col) = state.collections.get_mut(url) {
+            col.etag = new_etag;
+            col.last_modified = last_modified;
+        }
+        let _ = save_collection(&state, url);
+        return Ok(None);
+    }
+    let mut updated = load_openapi(
+        &client,
+        &content,
+        content_type.as_deref(),
+        &location,
+        new_etag,
+        None,
+    )
+    .await?;
+    updated.url = url.to_string();
+    updated.last_modified = last_modified;
+    Ok(Some(replace_collection(app_handle, updated)))
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum SyncOutcome {
+    Updated,
+    Unchanged,
+    Failed,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct SyncStatus {
+    outcome: SyncOutcome,
+    checked_at: DateTime<Utc>,
+    // The last time the spec was fetched and parsed, for telling how stale a failing
+    // collection is. Before any sync this run, when the stored copy was parsed.
+    last_success: DateTime<Utc>,
+    error: Option<String>,
+    // Failures in a row; reset by the next success.
+    failures: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct SyncFailed {
+    url: String,
+    status: SyncStatus,
+}
+
+// Network and parse errors and error statuses alike are announced as
+// `collection-sync-failed`, on every failed attempt.
+fn record_sync(
+    app_handle: &tauri::AppHandle,
+    url: &str,
+    result: &Result<Option<OpenApiCollection>, String>,
+) {
+    let state = app_handle.state::<AppState>();
+    // A collection removed while its sync was in flight leaves nothing to report on.
+    let Some(last_updated) = state.collections.get(url).map(|col| col.last_updated) else {
+        return;
+    };
+    let previous = state.sync_status.get(url).map(|status| status.clone());
+    let now = Utc::now();
+    let status = match result {
+        Ok(updated) => SyncStatus {
+            outcome: if updated.is_some() {
+                SyncOutcome::Updated
+            } else {
+                SyncOutcome::Unchanged
+            },
+            checked_at: now,
+            last_success: now,
+            error: None,
+            failures: 0,
+        },
+        Err(error) => SyncStatus {
+            outcome: SyncOutcome::Failed,
+            checked_at: now,
+            last_success: previous
+                .as_ref()
+                .map_or(last_updated, |status| status.last_success),
+            error: Some(error.clone()),
+            failures: previous.map_or(0, |status| status.failures) + 1,
+        },
+    };
+    state.sync_status.insert(url.to_string(), status.clone());
+    if status.outcome == SyncOutcome::Failed {
+        let _ = app_handle.emit_all(
+            "collection-sync-failed",
+            SyncFailed {
+                url: url.to_string(),
+                status,
+            },
+        );
+    }
+}
+
+#[command]
+async fn sync_status(state: State<'_, AppState>) -> Result<HashMap<String, SyncStatus>, String> {
+    Ok(state
+        .sync_status
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect())
+}
+
+#[command]
+async fn sync_now(
+    url: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<OpenApiCollection, String> {
+    if !state.collections.contains_key(&url) {
+        return Err(format!("Unknown collection: {}", url));
+    }
+    let result = refresh_collection(&app_handle, &url).await;
+    record_sync(&app_handle, &url, &result);
+    if let Some(updated) = result? {
+        return Ok(updated);
+    }
+    state
+        .collections
+        .get(&url)
+        .map(|col| col.clone())
+        .ok_or_else(|| format!("Unknown collection: {}", url))
+}
+
+#[command]
+async fn set_sync_interval(
+    url: String,
+    seconds: u64,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if seconds < MIN_SYNC_INTERVAL_SECS {
+        return Err(format!(
+            "Sync interval must be at least {} seconds",
+            MIN_SYNC_INTERVAL_SECS
+        ));
+    }
+    let mut col = state
+        .collections
+        .get_mut(&url)
+        .ok_or_else(|| format!("Unknown collection: {}", url))?;
+    col.sync_interval_secs = seconds;
+    drop(col);
+    save_collection(&state, &url)
+}
+
+#[command]
+async fn select_server(
+    url: String,
+    index: usize,
+    variables: Option<HashMap<String, String>>,
+    state: State<'_, AppState>,
+) -> Result<OpenApiCollection, String> {
+    let col = {
+        let mut col = state
+            .collections
+            .get_mut(&url)
+            .ok_or_else(|| format!("Unknown collection: {}", url))?;
+        apply_server(&mut col, index, variables.unwrap_or_default())?;
+        col.clone()
+    };
+    save_collection(&state, &url)?;
+    Ok(col)
+}
+
+#[command]
+async fn list_collections(state: State<'_, AppState>) -> Result<Vec<OpenApiCollection>, String> {
+    Ok(state
+        .collection_order
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|url| state.collections.get(url).map(|col| col.clone()))
+        .collect())
+}
+
+#[command]
+async fn remove_collection(url: String, state: State<'_, AppState>) -> Result<(), String> {
+    if state.collections.remove(&url).is_none() {
+        return Err(format!("Unknown collection: {}", url));
+    }
+    state.collection_order.lock().unwrap().retain(|u| u != &url);
+    state.sync_status.remove(&url);
+    state.spec_watcher.unwatch(&url);
+    if let Some(server) = state.mock_servers.lock().unwrap().remove(&url) {
+        server.stop();
+    }
+    state.store().delete_collection(&url)
+}
+
+#[command]
+async fn rename_collection(
+    url: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<OpenApiCollection, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Collection name cannot be empty".into());
+    }
+    let col = {
+        let mut col = state
+            .collections
+            .get_mut(&url)
+            .ok_or_else(|| format!("Unknown collection: {}", url))?;
+        col.name = name.clone();
+        col.name_override = Some(name);
+        col.clone()
+    };
+    save_collection(&state, &url)?;
+    Ok(col)
+}
+
+// The copy is placed right after the original and starts with sync turned off, so it
+// stays a snapshot until the user opts in.
+#[command]
+async fn duplicate_collection(
+    url: String,
+    state: State<'_, AppState>,
+) -> Result<OpenApiCollection, String> {
+    let copy = {
+        let original = state
+            .collections
+            .get(&url)
+            .map(|col| col.clone())
+            .ok_or_else(|| format!("Unknown collection: {}", url))?;
+        let source = spec_location(&original);
+        let id = (1..)
+            .map(|n| format!("{}#copy-{}", source, n))
+            .find(|id| !state.collections.contains_key(id))
+            .unwrap_or_default();
+        let mut copy = original.clone();
+        copy.url = id.clone();
+        copy.source = Some(source);
+        copy.name = format!("{} (copy)", original.name);
+        copy.name_override = Some(copy.name.clone());
+        copy.sync_enabled = false;
+        state.collections.insert(id, copy.clone());
+        copy
+    };
+    if let Some(path) = local_spec_path(&spec_location(&copy)) {
+        let _ = state.spec_watcher.watch(&path, &copy.url);
+    }
+    let order = {
+        let mut order = state.collection_order.lock().unwrap();
+        let index = order
+            .iter()
+            .position(|u| u == &url)
+            .map(|i| i + 1)
+            .unwrap_or(order.len());
+        order.insert(index, copy.url.clone());
+        order.clone()
+    };
+    save_collection(&state, &copy.url)?;
+    state.store().set_collection_order(&order)?;
+    Ok(copy)
+}
+
+// `urls` lists collections in their new order; any left out keep their relative order
+// after the listed ones.
+#[command]
+async fn reorder_collections(
+    urls: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let order = {
+        let mut order = state.collection_order.lock().unwrap();
+        let mut reordered: Vec<String> = Vec::new();
+        for url in &urls {
+            if order.contains(url) && !reordered.contains(url) {
+                reordered.push(url.clone());
+            }
+        }
+        reordered.extend(order.iter().filter(|u| !urls.contains(u)).cloned());
+        *order = reordered;
+        order.clone()
+    };
+    state.store().set_collection_order(&order)?;
+    Ok(order)
+}
+
+#[command]
+async fn toggle_sync(url: String, enabled: bool, state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(mut col) = state.collections.get_mut(&url) {
+        col.sync_enabled = enabled;
+    }
+    save_collection(&state, &url)
+}
+
+#[command]
+async fn download_file(
+    url: String,
+    save_path: String,
+    request_id: Option<String>,
+    connections: Option<u32>,
+    expected_checksum: Option<Checksum>,
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DownloadResult, String> {
+    let client = state.clients.client(&ClientKey::default())?;
+    let progress_id = request_id.clone();
+    let mut last_emit: Option<Instant> = None;
+    let on_progress = move |downloaded: u64, total: Option<u64>, segments: &[SegmentProgress]| {
+        let finished = total == Some(downloaded);
+        if !finished
+            && last_emit
+                .map(|at| at.elapsed() < PROGRESS_INTERVAL)
+                .unwrap_or(false)
+        {
+            return;
+        }
+        last_emit = Some(Instant::now());
+        let _ = app_handle.emit_all(
+            "download-progress",
+            DownloadProgress {
+                request_id: progress_id.clone(),
+                downloaded,
+                total,
+                segments: segments.to_vec(),
+            },
+        );
+    };
+    run_cancellable(
+        &state.in_flight,
+        request_id,
+        download_parallel(
+            &client,
+            &url,
+            &save_path,
+            connections.unwrap_or(1),
+            expected_checksum.as_ref(),
+            on_progress,
+        ),
+    )
+    .await
+}
+
+// Up to a tenth of the interval is added at random so collections imported together
+// don't keep syncing in lockstep.
+fn sync_delay(interval_secs: u64) -> Duration {
+    let interval = Duration::from_secs(interval_secs.max(MIN_SYNC_INTERVAL_SECS));
+    interval + interval.mul_f64(rand::random::<f64>() * 0.1)
+}
+
+async fn background_update_checker(app_handle: tauri::AppHandle) {
+    // When each collection is next due, and the interval that was scheduled with so a
+    // changed interval takes effect right away.
+    let mut schedule: HashMap<String, (Instant, u64)> = HashMap::new();
+    loop {
+        sleep(SYNC_TICK).await;
+        let state = app_handle.state::<AppState>();
+        let now = Instant::now();
+        let due: Vec<(String, u64)> = {
+            schedule.retain(|url, _| state.collections.contains_key(url));
+            state
+                .collections
+                .iter()
+                .filter(|c| c.sync_enabled && local_spec_path(&spec_location(c)).is_none())
+                .filter_map(|c| {
+                    let entry = schedule.entry(c.url.clone()).or_insert_with(|| {
+                        (now + sync_delay(c.sync_interval_secs), c.sync_interval_secs)
+                    });
+                    if entry.1 != c.sync_interval_secs {
+                        *entry = (now + sync_delay(c.sync_interval_secs), c.sync_interval_secs);
+                    }
+                    (entry.0 <= now).then(|| (c.url.clone(), c.sync_interval_secs))
+                })
+                .collect()
+        };
+        // A few at a time, so one slow host does not hold back every other collection.
+        let app_handle = &app_handle;
+        let results: Vec<(String, u64, Result<Option<OpenApiCollection>, String>)> =
+            stream::iter(due)
+                .map(|(url, interval)| async move {
+                    let result =
+                        tokio::time::timeout(SYNC_TIMEOUT, refresh_collection(app_handle, &url))
+                            .await
+                            .unwrap_or_else(|_| {
+                                Err(format!(
+                                    "Sync timed out after {} seconds",
+                                    SYNC_TIMEOUT.as_secs()
+                                ))
+                            });
+                    (url, interval, result)
+                })
+                .buffer_unordered(SYNC_CONCURRENCY)
+                .collect()
+                .await;
+        for (url, interval, result) in results {
+            record_sync(app_handle, &url, &result);
+            schedule.insert(url, (Instant::now() + sync_delay(interval), interval));
+        }
+    }
+}
+
+// Runs the active workspace's monitors as they come due, one at a time. A monitor is
+// first checked right after it is created or the app starts.
+async fn monitor_scheduler(app_handle: tauri::AppHandle) {
+    let mut schedule: HashMap<String, (Instant, u64)> = HashMap::new();
+    loop {
+        sleep(MONITOR_TICK).await;
+        let state = app_handle.state::<AppState>();
+        let monitors: Vec<Monitor> = state
+            .store()
+            .monitors()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|m| m.enabled)
+            .collect();
+        schedule.retain(|id, _| monitors.iter().any(|m| &m.id == id));
+        let now = Instant::now();
+        let due: Vec<Monitor> = monitors
+            .into_iter()
+            .filter(|m| {
+                let entry = schedule
+                    .entry(m.id.clone())
+                    .or_insert((now, m.interval_minutes));
+                if entry.1 != m.interval_minutes {
+                    *entry = (now, m.interval_minutes);
+                }
+                entry.0 <= now
+            })
+            .collect();
+        for monitor in &due {
+            schedule.insert(
+                monitor.id.clone(),
+                (
+                    now + Duration::from_secs(monitor.interval_minutes * 60),
+                    monitor.interval_minutes,
+                ),
+            );
+        }
+        let (app_handle, state) = (&app_handle, &state);
+        stream::iter(due)
+            .map(|monitor| async move {
+                let _ = check_monitor(app_handle, state, &monitor).await;
+            })
+            .buffer_unordered(MONITOR_CONCURRENCY)
+            .collect::<Vec<()>>()
+            .await;
+    }
+}
+
+// Runs the monitor's steps once and records the outcome. A monitor that starts failing
+// raises a `monitor-alert` event and, unless turned off, a native notification.
+async fn check_monitor(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    monitor: &Monitor,
+) -> Result<MonitorCheck, String> {
+    let options = RunOptions {
+        steps: monitor.steps.clone(),
+        environment: monitor.environment.clone(),
+        skip_history: true,
+        ..RunOptions::default()
+    };
+    let summary = run_steps(options, None, state).await?;
+    let mut check = MonitorCheck::new(&monitor.id, &summary);
+    let store = state.store();
+    let previous = store.monitor_checks(&monitor.id, 1)?.into_iter().next();
+    check.id = store.add_monitor_check(&check)?;
+    let _ = app_handle.emit_all("monitor-check", check.clone());
+    if monitors::should_alert(previous.as_ref(), &check) {
+        let _ = app_handle.emit_all(
+            "monitor-alert",
+            MonitorAlert {
+                monitor: monitor.clone(),
+                check: check.clone(),
+            },
+        );
+        if monitor.notify {
+            let _ = tauri::api::notification::Notification::new(APP_IDENTIFIER)
+                .title(format!("{} is failing", monitor.name))
+                .body(check.error.clone().unwrap_or_default())
+                .show();
+        }
+    }
+    Ok(check)
+}
+
+// Sends queued requests whose host answers again, oldest first; an unreachable host only
+// holds back its own requests. `network-status` is sent when the probes change their
+// mind about being online.
+async fn offline_queue_sender(app_handle: tauri::AppHandle) {
+    loop {
+        sleep(QUEUE_TICK).await;
+        let state = app_handle.state::<AppState>();
+        let queued = state.store().queued_requests().unwrap_or_default();
+        if queued.is_empty() {
+            continue;
+        }
+        let mut targets: Vec<&QueuedRequest> = queued.iter().collect();
+        targets.sort_by_key(|q| q.origin());
+        targets.dedup_by_key(|q| q.origin());
+        let probes = join_all(targets.iter().map(|q| queued_host_reachable(&state, q))).await;
+        let reachable: HashMap<String, bool> =
+            targets.iter().map(|q| q.origin()).zip(probes).collect();
+        let online = reachable.values().any(|up| *up);
+        let (changed, status) = {
+            let mut network = state.network.lock().unwrap();
+            let changed = network.online != online || network.checked_at.is_none();
+            network.online = online;
+            network.checked_at = Some(Utc::now());
+            (changed, network.clone())
+        };
+        if changed {
+            let _ = app_handle.emit_all("network-status", status);
+        }
+        for item in queued {
+            if reachable[&item.origin()] {
+                send_queued(&app_handle, &state, item).await;
+            }
+        }
+    }
+}
+
+async fn send_queued(app_handle: &tauri::AppHandle, state: &AppState, mut queued: QueuedRequest) {
+    // Off the queue first, so from here on a cancel goes to the request being sent.
+    if !state
+        .store()
+        .delete_queued_request(&queued.id)
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let result = send_request(
+        queued.request.clone(),
+        Some(queued.id.clone()),
+        app_handle.clone(),
+        state,
+    )
+    .await;
+    if let Err(e) = &result {
+        // The connection went again while sending: back in line, keeping its place.
+        if e != "Request cancelled" && !queued_host_reachable(state, &queued).await {
+            queued.attempts += 1;
+            queued.last_error = Some(e.clone());
+            let _ = state.store().put_queued_request(&queued);
+            return;
+        }
+    }
+    let (response, error) = match result {
+        Ok(response) => (Some(response), None),
+        Err(e) => (None, Some(e)),
+    };
+    let _ = app_handle.emit_all(
+        "queued-request-sent",
+        QueuedRequestSent {
+            id: queued.id,
+            name: queued.name,
+            response,
+            error,
+        },
+    );
+}
+
+// Re-imports file-based collections when the watcher reports a change. Editors tend to
+// write a file in several steps, so changes are collected briefly before re-parsing.
+async fn spec_file_watcher(app_handle: tauri::AppHandle, mut changes: UnboundedReceiver<String>) {
+    while let Some(first) = changes.recv().await {
+        sleep(Duration::from_millis(200)).await;
+        let mut locations = std::collections::HashSet::from([first]);
+        while let Ok(location) = changes.try_recv() {
+            locations.insert(location);
+        }
+        let state = app_handle.state::<AppState>();
+        for url in locations {
+            let local = state
+                .collections
+                .get(&url)
+                .map(|c| c.sync_enabled && local_spec_path(&spec_location(&c)).is_some());
+            if local == Some(true) {
+                let result = refresh_collection(&app_handle, &url).await;
+                record_sync(&app_handle, &url, &result);
+            }
+        }
+    }
+}
+
+// Opens the active workspace in `data_dir`. Changes to watched spec files arrive on the
+// returned channel.
+fn load_state(data_dir: PathBuf) -> Result<(AppState, UnboundedReceiver<String>), String> {
+    let settings = load_settings(&data_dir);
+    let workspaces = WorkspaceRegistry::load(&data_dir);
+    let dir = workspace_dir(&data_dir, &workspaces.active());
+    let store = Arc::new(Store::open(&dir)?);
+    let clients = ClientManager::new(settings.clone());
+    let (spec_changes, spec_change_rx) = tokio::sync::mpsc::unbounded_channel();
+    let state = AppState {
+        collections: DashMap::new(),
+        clients,
+        in_flight: Arc::new(Mutex::new(HashMap::new())),
+        settings: Mutex::new(settings),
+        oauth_tokens: RwLock::new(Arc::new(TokenStore::load(&dir))),
+        auth_profiles: RwLock::new(Arc::new(ProfileStore::load(&dir))),
+        spec_watcher: SpecWatcher::new(spec_changes)?,
+        collection_order: Mutex::new(Vec::new()),
+        workspaces,
+        store: RwLock::new(store.clone()),
+        secrets: Secrets::keychain(),
+        data_dir,
+        mqtt_sessions: Mutex::new(HashMap::new()),
+        mock_servers: Mutex::new(HashMap::new()),
+        sync_status: DashMap::new(),
+        proxy: Mutex::new(None),
+        webhook_listeners: Mutex::new(HashMap::new()),
+        rate_limiter: Arc::new(RateLimiter::default()),
+        network: Mutex::new(NetworkStatus::default()),
+    };
+    // Limits are checked when saved, so only a hand-edited file fails here; it runs unlimited.
+    let _ = state
+        .rate_limiter
+        .configure(&state.settings.lock().unwrap().rate_limit);
+    activate_store(&state, &dir, store)?;
+    refresh_other_queues(&state);
+    Ok((state, spec_change_rx))
+}
+
+// `restman run <file> ...` runs a collection without a window, for CI. Exits 0 when
+// every step passed, 1 when one failed and 2 when the run could not start.
+async fn run_headless(args: &[String]) -> i32 {
+    let options = match cli::parse_args(args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, cli::USAGE);
+            return 2;
+        }
+    };
+    // A run gets a data directory of its own, so it neither reads nor changes the app's
+    // history, cookies, settings and environments.
+    let data_dir = std::env::temp_dir().join(format!("restman-run-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_dir);
+    let result = async {
+        let (state, _) = load_state(data_dir.clone())?;
+        let content = tokio::fs::read_to_string(&options.file)
+            .await
+            .map_err(|e| format!("Cannot read {}: {}", options.file, e))?;
+        let summary = run_steps(cli::run_options(&content, &options)?, None, &state).await?;
+        if options.reporters.contains(&cli::Reporter::Cli) {
+            print!("{}", cli::summary_text(&summary));
+        }
+        cli::write_reports(&summary, &options)?;
+        Ok::<_, String>(cli::exit_code(&summary))
+    };
+    let code = match result.await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            2
+        }
+    };
+    let _ = std::fs::remove_dir_all(&data_dir);
+    code
+}
+
+// Release builds on Windows are GUI programs without a console, so `run` attaches to
+// the one it was started from, or opens its own, for its output to show.
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+        fn AllocConsole() -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "run") {
+        #[cfg(target_os = "windows")]
+        attach_console();
+        std::process::exit(run_headless(&args[1..]).await);
+    }
+    tauri::Builder::default()
+        .invoke_handler(tauri::generate_handler![
+            request,
+            cancel_request,
+            save_response_body,
+            download_file,
+            get_settings,
+            update_settings,
+            list_cookies,
+            set_cookie,
+            delete_cookie,
+            clear_cookies,
+            oauth2_authorize,
+            oauth2_get_token,
+            oauth2_clear_token,
+            generate_jwt,
+            list_auth_profiles,
+            save_auth_profile,
+            delete_auth_profile,
+            import_openapi,
+            import_graphql_schema,
+            refresh_graphql_schema,
+            list_graphql_schemas,
+            remove_graphql_schema,
+            list_grpc_services,
+            grpc_call,
+            toggle_sync,
+            select_server,
+            sync_now,
+            set_sync_interval,
+            set_spec_credentials,
+            list_collections,
+            remove_collection,
+            rename_collection,
+            duplicate_collection,
+            reorder_collections,
+            list_history,
+            replay_history,
+            prune_history,
+            list_saved_requests,
+            save_request,
+            delete_saved_request,
+            send_saved_request,
+            list_workspaces,
+            create_workspace,
+            switch_workspace,
+            delete_workspace,
+            export_workspace,
+            list_environments,
+            save_environment,
+            delete_environment,
+            set_active_environment,
+            run_collection,
+            list_monitors,
+            save_monitor,
+            delete_monitor,
+            monitor_history,
+            run_monitor,
+            load_test,
+            diff_responses,
+            query_response,
+            read_response_chunk,
+            search_response_body,
+            format_body,
+            hex_dump,
+            generate_snippet,
+            import_postman,
+            import_insomnia,
+            import_bruno,
+            import_wsdl,
+            mqtt_connect,
+            mqtt_subscribe,
+            mqtt_unsubscribe,
+            mqtt_publish,
+            mqtt_disconnect,
+            start_mock_server,
+            stop_mock_server,
+            configure_mock_server,
+            list_mock_servers,
+            start_proxy,
+            stop_proxy,
+            proxy_status,
+            save_captured_requests,
+            start_webhook_listener,
+            stop_webhook_listener,
+            configure_webhook_listener,
+            list_webhook_listeners,
+            webhook_captures,
+            clear_webhook_captures,
+            coverage_report,
+            reset_coverage,
+            lint_collection,
+            diff_specs,
+            generate_spec,
+            export_docs,
+            sync_status,
+            queue_request,
+            queued_requests,
+            cancel_queued_request,
+            network_status,
+            export_requests,
+            import_har,
+            export_har
+        ])
+        .setup(|app| {
+            let data_dir = app
+                .path_resolver()
+                .app_data_dir()
+                .unwrap_or_else(|| std::env::temp_dir().join("restman"));
+            let cache_dir = app
+                .path_resolver()
+                .app_cache_dir()
+                .unwrap_or_else(|| data_dir.join("cache"));
+            body::set_response_dir(cache_dir.join("responses"));
+            let started = std::time::SystemTime::now();
+            let (state, spec_change_rx) = load_state(data_dir)?;
+            app.manage(state);
+            let handle = app.handle();
+            tokio::task::spawn_blocking(move || {
+                let _ = sweep_response_files(&handle.state::<AppState>(), started);
+            });
+            let handle = app.handle();
+            tokio::spawn(async move {
+                background_update_checker(handle).await;
+            });
+            let handle = app.handle();
+            tokio::spawn(async move {
+                monitor_scheduler(handle).await;
+            });
+            let handle = app.handle();
+            tokio::spawn(async move {
+                spec_file_watcher(handle, spec_change_rx).await;
+            });
+            let handle = app.handle();
+            tokio::spawn(async move {
+                offline_queue_sender(handle).await;
+            });
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
     #[test]
     fn request_body_example_uses_first_content_when_json_missing() {
         let doc = json!({
@@ -768,8 +5412,7 @@ mod tests {
         let request_body = doc
             .pointer("/paths/~1aes/post/requestBody")
             .expect("missing requestBody");
-        let example = extract_request_body_example(&doc, request_body)
-            .expect("missing example");
+        let example = extract_request_body_example(&doc, request_body).expect("missing example");
         assert_eq!(example, json!({ "plainText": "Hello Onione!" }));
     }
 
@@ -808,30 +5451,490 @@ mod tests {
         let request_body = doc
             .pointer("/paths/~1aes/post/requestBody")
             .expect("missing requestBody");
-        let example = extract_request_body_example(&doc, request_body)
-            .expect("missing example");
+        let example = extract_request_body_example(&doc, request_body).expect("missing example");
         assert_eq!(example, json!({ "plainText": "FromExample" }));
     }
-}
 
-#[tokio::main]
-async fn main() {
-    let client = Client::builder()
-        .cookie_store(true)
-        .build()
-        .expect("failed to build HTTP client");
-    let state = AppState {
-        collections: Arc::new(Mutex::new(HashMap::new())),
-        client,
-    };
-    tauri::Builder::default()
-        .manage(state)
-        .invoke_handler(tauri::generate_handler![request, download_file, import_openapi, toggle_sync])
-        .setup(|app| {
-            let handle = app.handle();
-            tokio::spawn(async move { background_update_checker(handle).await; });
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    #[test]
+    fn all_of_branches_merge_into_one_example() {
+        let doc = json!({
+            "components": {
+                "schemas": {
+                    "Base": {
+                        "type": "object",
+                        "required": ["id"],
+                        "properties": {
+                            "id": { "type": "integer" },
+                            "name": { "type": "string", "example": "rex" }
+                        }
+                    },
+                    "Dog": {
+                        "allOf": [
+                            { "$ref": "#/components/schemas/Base" },
+                            {
+                                "properties": { "barks": { "type": "boolean" } },
+                                "required": ["barks"],
+                                "example": { "barks": true, "name": "fido" }
+                            }
+                        ]
+                    }
+                }
+            }
+        });
+        let schema = json!({ "$ref": "#/components/schemas/Dog" });
+        assert_eq!(
+            build_example_from_schema(&doc, &schema, ExampleDirection::Request, 0),
+            Some(json!({ "id": 0, "name": "fido", "barks": true }))
+        );
+        let merged = merge_all_of(&doc, &schema, 0);
+        assert_eq!(merged["required"], json!(["id", "barks"]));
+    }
+
+    #[test]
+    fn one_of_with_discriminator_sets_mapping_key() {
+        let doc = json!({
+            "components": {
+                "schemas": {
+                    "Card": {
+                        "type": "object",
+                        "properties": {
+                            "kind": { "type": "string" },
+                            "number": { "type": "string", "example": "4111" }
+                        }
+                    },
+                    "Bank": {
+                        "type": "object",
+                        "properties": { "kind": { "type": "string" }, "iban": { "type": "string" } }
+                    }
+                }
+            }
+        });
+        let mapped = json!({
+            "oneOf": [
+                { "$ref": "#/components/schemas/Card" },
+                { "$ref": "#/components/schemas/Bank" }
+            ],
+            "discriminator": {
+                "propertyName": "kind",
+                "mapping": { "bank_account": "#/components/schemas/Bank", "credit_card": "Card" }
+            }
+        });
+        assert_eq!(
+            build_example_from_schema(&doc, &mapped, ExampleDirection::Request, 0),
+            Some(json!({ "kind": "credit_card", "number": "4111" }))
+        );
+        let implicit = json!({
+            "oneOf": [{ "$ref": "#/components/schemas/Bank" }],
+            "discriminator": { "propertyName": "kind" }
+        });
+        assert_eq!(
+            build_example_from_schema(&doc, &implicit, ExampleDirection::Request, 0),
+            Some(json!({ "kind": "Bank", "iban": "" }))
+        );
+    }
+
+    #[test]
+    fn read_only_and_write_only_properties_follow_direction() {
+        let doc = json!({});
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer", "readOnly": true },
+                "name": { "type": "string", "example": "Rex" },
+                "password": { "type": "string", "writeOnly": true }
+            }
+        });
+        assert_eq!(
+            build_example_from_schema(&doc, &schema, ExampleDirection::Request, 0),
+            Some(json!({ "name": "Rex", "password": "" }))
+        );
+        assert_eq!(
+            build_example_from_schema(&doc, &schema, ExampleDirection::Response, 0),
+            Some(json!({ "id": 0, "name": "Rex" }))
+        );
+    }
+
+    #[test]
+    fn written_out_examples_drop_properties_by_direction() {
+        let doc = json!({
+            "components": {
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer", "readOnly": true },
+                            "name": { "type": "string" },
+                            "password": { "type": "string", "writeOnly": true }
+                        },
+                        "example": { "id": 1, "name": "Rex", "password": "secret" }
+                    }
+                }
+            }
+        });
+        let pets = json!({
+            "type": "array",
+            "items": { "$ref": "#/components/schemas/Pet" }
+        });
+        assert_eq!(
+            build_example_from_schema(&doc, &pets, ExampleDirection::Request, 0),
+            Some(json!([{ "name": "Rex", "password": "secret" }]))
+        );
+        let content = json!({
+            "application/json": {
+                "schema": pets,
+                "example": [{ "id": 1, "name": "Rex", "password": "secret", "extra": true }]
+            }
+        });
+        assert_eq!(
+            extract_content_example(&doc, &content, ExampleDirection::Response),
+            Some(json!([{ "id": 1, "name": "Rex", "extra": true }]))
+        );
+    }
+
+    #[test]
+    fn responses_carry_examples_and_headers_per_status() {
+        let doc = json!({
+            "components": {
+                "schemas": {
+                    "User": {
+                        "type": "object",
+                        "properties": {
+                            "id": { "type": "integer", "example": 7 },
+                            "password": { "type": "string", "writeOnly": true }
+                        }
+                    }
+                },
+                "headers": {
+                    "RateLimit": { "description": "Requests left", "schema": { "type": "integer", "example": 99 } }
+                }
+            }
+        });
+        let responses = json!({
+            "200": {
+                "description": "ok",
+                "headers": { "X-RateLimit-Remaining": { "$ref": "#/components/headers/RateLimit" } },
+                "content": { "application/json": { "schema": { "$ref": "#/components/schemas/User" } } }
+            },
+            "422": {
+                "description": "invalid",
+                "content": {
+                    "application/problem+json": {
+                        "examples": { "missing": { "value": { "title": "name is required" } } }
+                    }
+                }
+            },
+            "204": { "description": "empty" }
+        });
+        let extracted = extract_responses(&doc, &responses);
+        assert_eq!(
+            extracted.keys().collect::<Vec<_>>(),
+            vec!["200", "204", "422"]
+        );
+        let ok = &extracted["200"];
+        assert_eq!(ok.example.as_deref(), Some(r#"{"id":7}"#));
+        assert_eq!(ok.headers[0].name, "X-RateLimit-Remaining");
+        assert_eq!(ok.headers[0].description.as_deref(), Some("Requests left"));
+        assert_eq!(ok.headers[0].example, Some(json!(99)));
+        let invalid = &extracted["422"];
+        assert_eq!(invalid.media_types, vec!["application/problem+json"]);
+        assert_eq!(
+            invalid.example.as_deref(),
+            Some(r#"{"title":"name is required"}"#)
+        );
+        assert!(extracted["204"].example.is_none() && extracted["204"].media_types.is_empty());
+    }
+
+    #[test]
+    fn servers_expand_and_switch_per_collection() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Multi" },
+            "servers": [
+                {
+                    "url": "https://{env}.api.test/v1",
+                    "variables": { "env": { "default": "prod", "enum": ["prod", "staging"] } }
+                },
+                { "url": "http://localhost:8080" }
+            ],
+            "paths": {
+                "/pets": {
+                    "summary": "Pets",
+                    "get": { "tags": ["pets"], "responses": {} }
+                },
+                "/uploads": {
+                    "servers": [{ "url": "https://uploads.api.test" }],
+                    "post": { "tags": ["pets"], "responses": {} }
+                }
+            }
+        });
+        let mut collection = parse_openapi_internal(doc, "multi.json", None).unwrap();
+        let path_of = |col: &OpenApiCollection, route: &str| {
+            col.groups["pets"]
+                .iter()
+                .find(|e| e.route == route)
+                .unwrap()
+                .path
+                .clone()
+        };
+        assert_eq!(collection.groups["pets"].len(), 2);
+        assert_eq!(
+            path_of(&collection, "/pets"),
+            "https://prod.api.test/v1/pets"
+        );
+        assert_eq!(
+            path_of(&collection, "/uploads"),
+            "https://uploads.api.test/uploads"
+        );
+
+        let staging = HashMap::from([("env".to_string(), "staging".to_string())]);
+        apply_server(&mut collection, 0, staging).unwrap();
+        assert_eq!(
+            path_of(&collection, "/pets"),
+            "https://staging.api.test/v1/pets"
+        );
+        apply_server(&mut collection, 1, HashMap::new()).unwrap();
+        assert_eq!(path_of(&collection, "/pets"), "http://localhost:8080/pets");
+        assert_eq!(
+            path_of(&collection, "/uploads"),
+            "https://uploads.api.test/uploads"
+        );
+        assert!(apply_server(&mut collection, 2, HashMap::new()).is_err());
+        assert_eq!(collection.server_index, 1);
+    }
+
+    #[test]
+    fn operations_and_parameters_carry_metadata() {
+        let doc = json!({
+            "openapi": "3.1.0",
+            "info": { "title": "Meta" },
+            "components": {
+                "schemas": { "Status": { "type": "string", "enum": ["open", "closed"], "default": "open" } }
+            },
+            "paths": {
+                "/items/{id}": {
+                    "get": {
+                        "tags": ["items"],
+                        "operationId": "getItem",
+                        "deprecated": true,
+                        "externalDocs": { "url": "https://docs.test/items" },
+                        "parameters": [
+                            {
+                                "name": "id", "in": "path", "required": true,
+                                "schema": { "type": "integer", "format": "int64" }
+                            },
+                            { "name": "status", "in": "query", "schema": { "$ref": "#/components/schemas/Status" } },
+                            {
+                                "name": "since", "in": "query", "deprecated": true,
+                                "schema": { "type": ["string", "null"], "format": "date" }
+                            },
+                            {
+                                "name": "tags", "in": "query", "style": "spaceDelimited", "explode": false,
+                                "schema": { "type": "array", "items": { "type": "string" } }
+                            }
+                        ],
+                        "responses": {}
+                    }
+                }
+            }
+        });
+        let collection = parse_openapi_internal(doc, "meta.json", None).unwrap();
+        let endpoint = &collection.groups["items"][0];
+        assert_eq!(endpoint.operation_id.as_deref(), Some("getItem"));
+        assert!(endpoint.deprecated);
+        assert_eq!(
+            endpoint
+                .external_docs
+                .as_ref()
+                .map(|docs| docs.url.as_str()),
+            Some("https://docs.test/items")
+        );
+        let params = &endpoint.parameters;
+        assert_eq!(params[0].schema_type.as_deref(), Some("integer"));
+        assert_eq!(params[0].format.as_deref(), Some("int64"));
+        assert_eq!(
+            (params[0].style.as_str(), params[0].explode),
+            ("simple", false)
+        );
+        assert_eq!(
+            params[1].enum_values,
+            Some(vec!["open".to_string(), "closed".to_string()])
+        );
+        assert_eq!(params[1].default, Some(json!("open")));
+        assert_eq!(
+            (params[1].style.as_str(), params[1].explode),
+            ("form", true)
+        );
+        assert_eq!(params[2].schema_type.as_deref(), Some("string"));
+        assert!(params[2].deprecated && !params[1].deprecated);
+        assert_eq!(
+            (params[3].style.as_str(), params[3].explode),
+            ("spaceDelimited", false)
+        );
+    }
+
+    #[test]
+    fn query_objects_expand_by_style() {
+        let doc = json!({});
+        let schema = json!({ "type": "object", "properties": { "min": { "type": "integer" } } });
+        let deep =
+            json!({ "name": "price", "in": "query", "style": "deepObject", "schema": schema });
+        let expanded = expand_query_object_parameters(&doc, &deep).unwrap();
+        assert_eq!(expanded[0].name, "price[min]");
+        let form = json!({ "name": "price", "in": "query", "schema": schema });
+        assert_eq!(
+            expand_query_object_parameters(&doc, &form).unwrap()[0].name,
+            "min"
+        );
+        let joined = json!({ "name": "price", "in": "query", "explode": false, "schema": schema });
+        assert!(expand_query_object_parameters(&doc, &joined).is_none());
+    }
+
+    #[test]
+    fn tags_keep_spec_order_and_descriptions() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Zoo" },
+            "tags": [
+                { "name": "zebras", "description": "Striped" },
+                { "name": "ants", "externalDocs": { "url": "https://docs.test/ants" } }
+            ],
+            "x-tagGroups": [{ "name": "Animals", "tags": ["zebras", "ants"] }],
+            "paths": {
+                "/ants": { "get": { "tags": ["ants"], "responses": {} } },
+                "/misc": { "get": { "responses": {} } },
+                "/zebras": { "get": { "tags": ["zebras"], "responses": {} } }
+            }
+        });
+        let collection = parse_openapi_internal(doc, "zoo.json", None).unwrap();
+        let names: Vec<&str> = collection.tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["zebras", "ants", "Default"]);
+        assert_eq!(collection.tags[0].description.as_deref(), Some("Striped"));
+        assert_eq!(
+            collection.tags[1].external_docs.as_ref().unwrap().url,
+            "https://docs.test/ants"
+        );
+        assert_eq!(collection.tag_groups[0].name, "Animals");
+        assert_eq!(collection.tag_groups[0].tags, vec!["zebras", "ants"]);
+    }
+
+    #[test]
+    fn sync_delay_adds_bounded_jitter() {
+        for _ in 0..20 {
+            let delay = sync_delay(100);
+            assert!(delay >= Duration::from_secs(100) && delay <= Duration::from_secs(110));
+        }
+        assert!(sync_delay(1) >= Duration::from_secs(MIN_SYNC_INTERVAL_SECS));
+    }
+
+    #[test]
+    fn swagger2_specs_import_like_openapi3() {
+        let content = r#"
+swagger: "2.0"
+info:
+  title: Legacy
+host: api.example.com
+basePath: /v1
+schemes: [https]
+paths:
+  /users:
+    post:
+      tags: [users]
+      parameters:
+        - name: body
+          in: body
+          schema:
+            $ref: '#/definitions/User'
+      responses:
+        201:
+          description: created
+definitions:
+  User:
+    type: object
+    properties:
+      name:
+        type: string
+        example: ada
+"#;
+        let json = spec::parse_document(content, None, "legacy.yaml").unwrap();
+        let collection = parse_openapi_internal(json, "legacy.yaml", None).unwrap();
+        let endpoint = &collection.groups["users"][0];
+        assert_eq!(collection.name, "Legacy");
+        assert_eq!(endpoint.path, "https://api.example.com/v1/users");
+        assert_eq!(endpoint.body_example.as_deref(), Some(r#"{"name":"ada"}"#));
+        assert_eq!(endpoint.response_schemas[0].status, "201");
+    }
+
+    #[test]
+    fn parse_reports_progress_and_stops_when_asked() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": "Big" },
+            "paths": {
+                "/a": { "get": { "responses": {} } },
+                "/b": { "get": { "responses": {} } },
+                "/c": { "get": { "responses": {} } }
+            }
+        });
+        let seen = Mutex::new(Vec::new());
+        let result =
+            parse_openapi_with_progress(doc.clone(), "big.json", None, &|processed, total| {
+                seen.lock().unwrap().push((processed, total));
+                processed < 2
+            });
+        assert_eq!(result.err(), Some("Import cancelled".to_string()));
+        assert_eq!(*seen.lock().unwrap(), vec![(1, 3), (2, 3)]);
+        let collection = parse_openapi_with_progress(doc, "big.json", None, &|_, _| true).unwrap();
+        assert_eq!(collection.groups["Default"].len(), 3);
+    }
+
+    #[tokio::test]
+    async fn run_cancellable_reports_cancellation() {
+        let in_flight: Arc<Mutex<HashMap<String, (u64, AbortHandle)>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let registry = in_flight.clone();
+        let canceller = tokio::spawn(async move {
+            loop {
+                if let Some((_, handle)) = registry.lock().unwrap().get("req-1") {
+                    handle.abort();
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        });
+        let result: Result<(), String> = run_cancellable(
+            &in_flight,
+            Some("req-1".to_string()),
+            std::future::pending(),
+        )
+        .await;
+        canceller.await.unwrap();
+        assert_eq!(result, Err("Request cancelled".to_string()));
+        assert!(in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn replaced_request_leaves_the_newer_entry_registered() {
+        let in_flight: Arc<Mutex<HashMap<String, (u64, AbortHandle)>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let registry = in_flight.clone();
+        let first = tokio::spawn(async move {
+            run_cancellable::<(), _>(&registry, Some("req-1".into()), std::future::pending()).await
+        });
+        while !in_flight.lock().unwrap().contains_key("req-1") {
+            tokio::task::yield_now().await;
+        }
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let registry = in_flight.clone();
+        let second = tokio::spawn(async move {
+            run_cancellable(&registry, Some("req-1".into()), async {
+                released.await.map_err(|e| e.to_string())
+            })
+            .await
+        });
+        assert_eq!(first.await.unwrap(), Err("Request cancelled".to_string()));
+        assert!(in_flight.lock().unwrap().contains_key("req-1"));
+        release.send(()).unwrap();
+        assert_eq!(second.await.unwrap(), Ok(()));
+        assert!(in_flight.lock().unwrap().is_empty());
+    }
 }
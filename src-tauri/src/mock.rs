@@ -0,0 +1,523 @@
+use crate::proxy::{answered, read_body, read_head, write_reply};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+// A response an operation declares. `status` is as written in the spec, so it may be
+// a range like "4XX" or "default".
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    pub status: String,
+    pub content_type: Option<String>,
+    pub example: Option<String>,
+    pub examples: BTreeMap<String, String>,
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MockRoute {
+    pub method: String,
+    // The spec path, with `{param}` segments matching any value.
+    pub route: String,
+    pub responses: Vec<MockResponse>,
+}
+
+// The response to serve for an operation in place of its first success.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct MockSelection {
+    pub status: Option<u16>,
+    pub example: Option<String>,
+}
+
+fn default_error_status() -> u16 {
+    500
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MockOptions {
+    // 0 picks a free port.
+    pub port: u16,
+    pub latency_ms: u64,
+    // Up to this much is added to `latency_ms`, at random, per request.
+    pub latency_jitter_ms: u64,
+    // The share of requests, from 0 to 1, answered with `error_status` instead.
+    pub error_rate: f64,
+    #[serde(default = "default_error_status")]
+    pub error_status: u16,
+    // Keyed by "METHOD /route", e.g. "GET /pets/{id}".
+    pub selections: HashMap<String, MockSelection>,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        MockOptions {
+            port: 0,
+            latency_ms: 0,
+            latency_jitter_ms: 0,
+            error_rate: 0.0,
+            error_status: default_error_status(),
+            selections: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MockServerInfo {
+    pub collection: String,
+    pub url: String,
+    pub routes: usize,
+    pub options: MockOptions,
+}
+
+// One request the mock answered, reported as it happens.
+#[derive(Serialize, Clone, Debug)]
+pub struct MockHit {
+    pub collection: String,
+    pub method: String,
+    pub path: String,
+    pub route: Option<String>,
+    pub status: u16,
+    pub injected: bool,
+    pub at: DateTime<Utc>,
+}
+
+struct Shared {
+    collection: String,
+    routes: RwLock<Vec<MockRoute>>,
+    options: RwLock<MockOptions>,
+}
+
+pub struct MockServer {
+    shared: Arc<Shared>,
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(
+        collection: &str,
+        routes: Vec<MockRoute>,
+        options: MockOptions,
+        on_hit: Arc<dyn Fn(MockHit) + Send + Sync>,
+    ) -> Result<MockServer, String> {
+        validate(&options)?;
+        let listener = TcpListener::bind(("127.0.0.1", options.port))
+            .await
+            .map_err(|e| format!("Cannot start the mock server: {}", e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let shared = Arc::new(Shared {
+            collection: collection.to_string(),
+            routes: RwLock::new(routes),
+            options: RwLock::new(options),
+        });
+        let serving = shared.clone();
+        let task = tokio::spawn(async move {
+            // Dropped when the server stops, which aborts the requests still being served.
+            let mut connections = JoinSet::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                while connections.try_join_next().is_some() {}
+                let shared = serving.clone();
+                let on_hit = on_hit.clone();
+                connections.spawn(async move { serve(socket, &shared, on_hit.as_ref()).await });
+            }
+            while connections.join_next().await.is_some() {}
+        });
+        Ok(MockServer { shared, port, task })
+    }
+
+    pub fn info(&self) -> MockServerInfo {
+        let mut options = self.shared.options.read().unwrap().clone();
+        options.port = self.port;
+        MockServerInfo {
+            collection: self.shared.collection.clone(),
+            url: format!("http://127.0.0.1:{}", self.port),
+            routes: self.shared.routes.read().unwrap().len(),
+            options,
+        }
+    }
+
+    // Takes effect from the next request; the port stays as it is.
+    pub fn configure(&self, options: MockOptions) -> Result<(), String> {
+        validate(&options)?;
+        *self.shared.options.write().unwrap() = options;
+        Ok(())
+    }
+
+    pub fn set_routes(&self, routes: Vec<MockRoute>) {
+        *self.shared.routes.write().unwrap() = routes;
+    }
+
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+fn validate(options: &MockOptions) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&options.error_rate) {
+        return Err("Error rate must be between 0 and 1".into());
+    }
+    if !(100..=599).contains(&options.error_status) {
+        return Err(format!("{} is not an HTTP status", options.error_status));
+    }
+    Ok(())
+}
+
+fn route_key(method: &str, route: &str) -> String {
+    format!("{} {}", method.to_uppercase(), route)
+}
+
+// The number of literal segments when `path` matches `route`, so the most specific
+// route wins: /pets/mine before /pets/{id}.
+fn match_route(route: &str, path: &str) -> Option<usize> {
+    let route_segments: Vec<&str> = route.trim_matches('/').split('/').collect();
+    let path_segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if route_segments.len() != path_segments.len() {
+        return None;
+    }
+    let mut literal = 0;
+    for (expected, actual) in route_segments.iter().zip(&path_segments) {
+        if expected.starts_with('{') && expected.ends_with('}') {
+            if actual.is_empty() {
+                return None;
+            }
+        } else if expected == actual {
+            literal += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(literal)
+}
+
+fn status_code(status: &str) -> Option<u16> {
+    match status.as_bytes() {
+        [digit, b'X' | b'x', b'X' | b'x'] if digit.is_ascii_digit() => {
+            Some((digit - b'0') as u16 * 100)
+        }
+        _ => status.parse().ok(),
+    }
+}
+
+// The declared response for `status`: an exact match, then a range, then `default`.
+fn find_response(responses: &[MockResponse], status: u16) -> Option<&MockResponse> {
+    let exact = responses.iter().find(|r| r.status.parse() == Ok(status));
+    let range = || {
+        responses.iter().find(|r| {
+            r.status.parse::<u16>().is_err() && status_code(&r.status) == Some(status / 100 * 100)
+        })
+    };
+    exact
+        .or_else(range)
+        .or_else(|| responses.iter().find(|r| r.status == "default"))
+}
+
+// `Prefer: code=404, example=missing` picks the response of one request, as in Prism.
+fn preference(headers: &[(String, String)]) -> MockSelection {
+    let mut selection = MockSelection::default();
+    let prefer = headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("prefer"));
+    for part in prefer.flat_map(|(_, value)| value.split([',', ';'])) {
+        match part.trim().split_once('=') {
+            Some(("code", code)) => selection.status = code.trim().parse().ok(),
+            Some(("example", name)) => {
+                selection.example = Some(name.trim().trim_matches('"').to_string())
+            }
+            _ => {}
+        }
+    }
+    selection
+}
+
+struct Reply {
+    status: u16,
+    content_type: Option<String>,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+fn json_error(status: u16, message: &str) -> Reply {
+    Reply {
+        status,
+        content_type: Some("application/json".into()),
+        headers: Vec::new(),
+        body: serde_json::json!({ "error": message }).to_string(),
+    }
+}
+
+// Picks the response for a request: an injected failure, the status asked for in the
+// Prefer header or the operation's selection, or else the first success declared.
+fn respond(
+    route: &MockRoute,
+    options: &MockOptions,
+    headers: &[(String, String)],
+    injected: bool,
+) -> Reply {
+    let preferred = preference(headers);
+    let selected = options
+        .selections
+        .get(&route_key(&route.method, &route.route));
+    let status = if injected {
+        Some(options.error_status)
+    } else {
+        preferred.status.or(selected.and_then(|s| s.status))
+    };
+    let declared = match status {
+        Some(status) => find_response(&route.responses, status).map(|r| (status, r)),
+        None => route
+            .responses
+            .iter()
+            .filter_map(|r| Some((status_code(&r.status)?, r)))
+            .filter(|(status, _)| (200..300).contains(status))
+            .min_by_key(|(status, _)| *status)
+            .or_else(|| {
+                route
+                    .responses
+                    .first()
+                    .map(|r| (status_code(&r.status).unwrap_or(200), r))
+            }),
+    };
+    let Some((status, response)) = declared else {
+        return match status {
+            Some(status) if injected => json_error(status, "Injected failure"),
+            Some(status) => json_error(status, "The operation declares no such response"),
+            None => json_error(501, "The operation declares no responses"),
+        };
+    };
+    let example_name = preferred
+        .example
+        .or_else(|| selected.and_then(|s| s.example.clone()));
+    let body = example_name
+        .and_then(|name| response.examples.get(&name).cloned())
+        .or_else(|| response.example.clone())
+        .or_else(|| response.examples.values().next().cloned())
+        .unwrap_or_default();
+    Reply {
+        status,
+        content_type: response.content_type.clone().filter(|_| !body.is_empty()),
+        headers: response.headers.clone(),
+        body,
+    }
+}
+
+// Answers one request per connection. CORS preflights are allowed for everything, so
+// a frontend on another origin can call the mock directly.
+async fn serve(
+    mut socket: TcpStream,
+    shared: &Shared,
+    on_hit: &(dyn Fn(MockHit) + Send + Sync),
+) -> Option<()> {
+    let mut reader = BufReader::new(&mut socket);
+    let head = read_head(&mut reader).await;
+    let head = answered(head, reader.get_mut()).await.ok()??;
+    let body = read_body(&mut reader, &head.headers).await;
+    answered(body, reader.get_mut()).await.ok()?;
+    let (method, target, headers) = (head.method, head.target, head.headers);
+
+    let path = target.split(['?', '#']).next().unwrap_or("/").to_string();
+    let options = shared.options.read().unwrap().clone();
+    let (reply, route, injected) = if method == "OPTIONS" {
+        let allow = vec![
+            (
+                "Access-Control-Allow-Methods".to_string(),
+                "GET, POST, PUT, PATCH, DELETE, HEAD, OPTIONS".to_string(),
+            ),
+            ("Access-Control-Allow-Headers".to_string(), "*".to_string()),
+        ];
+        (
+            Reply {
+                status: 204,
+                content_type: None,
+                headers: allow,
+                body: String::new(),
+            },
+            None,
+            false,
+        )
+    } else {
+        let matched = shared
+            .routes
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|route| route.method.eq_ignore_ascii_case(&method))
+            .filter_map(|route| Some((match_route(&route.route, &path)?, route)))
+            .max_by_key(|(literal, _)| *literal)
+            .map(|(_, route)| route.clone());
+        match matched {
+            Some(route) => {
+                let injected =
+                    options.error_rate > 0.0 && rand::thread_rng().gen_bool(options.error_rate);
+                let reply = respond(&route, &options, &headers, injected);
+                (reply, Some(route.route), injected)
+            }
+            None => (
+                json_error(404, "No operation matches this path"),
+                None,
+                false,
+            ),
+        }
+    };
+    let delay = options.latency_ms
+        + match options.latency_jitter_ms {
+            0 => 0,
+            jitter => rand::thread_rng().gen_range(0..=jitter),
+        };
+    if delay > 0 {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+    on_hit(MockHit {
+        collection: shared.collection.clone(),
+        method,
+        path,
+        route,
+        status: reply.status,
+        injected,
+        at: Utc::now(),
+    });
+    write_reply(
+        &mut socket,
+        reply.status,
+        reply.content_type.as_deref(),
+        &reply.headers,
+        reply.body.as_bytes(),
+    )
+    .await;
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: &str, example: Option<&str>, examples: &[(&str, &str)]) -> MockResponse {
+        MockResponse {
+            status: status.into(),
+            content_type: Some("application/json".into()),
+            example: example.map(String::from),
+            examples: examples
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            headers: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_examples_by_route_status_and_preference() {
+        let routes = vec![
+            MockRoute {
+                method: "GET".into(),
+                route: "/pets/{id}".into(),
+                responses: vec![
+                    response("404", Some(r#"{"message":"not found"}"#), &[]),
+                    response(
+                        "200",
+                        None,
+                        &[("rex", r#"{"name":"Rex"}"#), ("tom", r#"{"name":"Tom"}"#)],
+                    ),
+                    response("5XX", Some(r#"{"message":"down"}"#), &[]),
+                ],
+            },
+            MockRoute {
+                method: "GET".into(),
+                route: "/pets/mine".into(),
+                responses: vec![MockResponse {
+                    headers: vec![("X-Total".into(), "1".into())],
+                    ..response("200", Some("[]"), &[])
+                }],
+            },
+        ];
+        let hits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = hits.clone();
+        let server = MockServer::start(
+            "https://api.test/openapi.json",
+            routes,
+            MockOptions::default(),
+            Arc::new(move |hit| recorded.lock().unwrap().push(hit)),
+        )
+        .await
+        .unwrap();
+        let client = reqwest::Client::new();
+        let url = server.info().url;
+        let get = |path: &str| client.get(format!("{}{}", url, path));
+
+        let response = get("/pets/7").send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), r#"{"name":"Rex"}"#);
+        let response = get("/pets/mine?x=1").send().await.unwrap();
+        assert_eq!(response.headers()["x-total"], "1");
+        assert_eq!(response.text().await.unwrap(), "[]");
+        let response = get("/pets/7")
+            .header("Prefer", "code=404")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        let response = get("/pets/7")
+            .header("Prefer", "example=tom")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), r#"{"name":"Tom"}"#);
+        assert_eq!(get("/owners").send().await.unwrap().status(), 404);
+
+        let mut options = MockOptions {
+            error_rate: 1.0,
+            error_status: 503,
+            ..MockOptions::default()
+        };
+        server.configure(options.clone()).unwrap();
+        let response = get("/pets/7").send().await.unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(response.text().await.unwrap(), r#"{"message":"down"}"#);
+
+        options.error_rate = 0.0;
+        options.selections.insert(
+            "GET /pets/{id}".into(),
+            MockSelection {
+                status: Some(404),
+                example: None,
+            },
+        );
+        server.configure(options).unwrap();
+        assert_eq!(get("/pets/7").send().await.unwrap().status(), 404);
+        assert!(server
+            .configure(MockOptions {
+                error_rate: 2.0,
+                ..MockOptions::default()
+            })
+            .is_err());
+
+        server.stop();
+        let hits = hits.lock().unwrap();
+        assert_eq!(hits.len(), 7);
+        assert_eq!(hits[5].route.as_deref(), Some("/pets/{id}"));
+        assert!(hits[5].injected);
+        assert_eq!((hits[4].route.clone(), hits[4].status), (None, 404));
+    }
+
+    #[tokio::test]
+    async fn answers_oversized_headers_with_431() {
+        let server =
+            MockServer::start("spec", Vec::new(), MockOptions::default(), Arc::new(|_| {}))
+                .await
+                .unwrap();
+        let response = reqwest::Client::new()
+            .get(server.info().url)
+            .header("X-Padding", "a".repeat(20 * 1024))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 431);
+        server.stop();
+    }
+}
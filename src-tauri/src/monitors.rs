@@ -0,0 +1,154 @@
+use crate::runner::{RunStep, RunSummary};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// Checks kept per monitor; older ones are dropped as new ones come in.
+pub const MONITOR_CHECKS_KEPT: usize = 1000;
+
+// Runs its steps in the background every `interval_minutes` while the app is open and
+// the monitor's workspace is active. A single saved request is a one-step monitor.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Monitor {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub steps: Vec<RunStep>,
+    pub interval_minutes: u64,
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    // Whether failures raise a native notification as well as a `monitor-alert` event.
+    #[serde(default = "enabled_by_default")]
+    pub notify: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MonitorCheck {
+    #[serde(default)]
+    pub id: i64,
+    pub monitor: String,
+    pub timestamp: DateTime<Utc>,
+    pub passed: bool,
+    pub elapsed_ms: u64,
+    // First failing step's status and error, if any.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl MonitorCheck {
+    pub fn new(monitor: &str, summary: &RunSummary) -> Self {
+        let failed = summary.steps.iter().find(|step| !step.passed);
+        MonitorCheck {
+            id: 0,
+            monitor: monitor.to_string(),
+            timestamp: Utc::now(),
+            passed: failed.is_none() && summary.skipped == 0,
+            elapsed_ms: summary.steps.iter().map(|step| step.elapsed_ms).sum(),
+            status: failed.and_then(|step| step.status),
+            error: failed.map(|step| {
+                step.error
+                    .clone()
+                    .unwrap_or_else(|| format!("{} {} failed its checks", step.method, step.url))
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MonitorStats {
+    pub checks: usize,
+    pub passed: usize,
+    // Share of passing checks, 0 to 100.
+    pub uptime: f64,
+    pub average_ms: u64,
+    pub p95_ms: u64,
+    pub last_check: Option<DateTime<Utc>>,
+    pub last_passed: Option<bool>,
+}
+
+// Payload of the `monitor-alert` event.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MonitorAlert {
+    pub monitor: Monitor,
+    pub check: MonitorCheck,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MonitorStatus {
+    pub monitor: Monitor,
+    pub stats: MonitorStats,
+}
+
+// `checks` are newest first, as the store returns them.
+pub fn stats(checks: &[MonitorCheck]) -> MonitorStats {
+    if checks.is_empty() {
+        return MonitorStats::default();
+    }
+    let passed = checks.iter().filter(|check| check.passed).count();
+    let mut latencies: Vec<u64> = checks.iter().map(|check| check.elapsed_ms).collect();
+    latencies.sort_unstable();
+    let p95 = latencies[((latencies.len() * 95).div_ceil(100)).saturating_sub(1)];
+    MonitorStats {
+        checks: checks.len(),
+        passed,
+        uptime: passed as f64 * 100.0 / checks.len() as f64,
+        average_ms: latencies.iter().sum::<u64>() / latencies.len() as u64,
+        p95_ms: p95,
+        last_check: checks.first().map(|check| check.timestamp),
+        last_passed: checks.first().map(|check| check.passed),
+    }
+}
+
+// Alerts go out when a monitor starts failing, not on every failed check after that.
+pub fn should_alert(previous: Option<&MonitorCheck>, check: &MonitorCheck) -> bool {
+    !check.passed && previous.is_none_or(|previous| previous.passed)
+}
+
+pub fn normalize(mut monitor: Monitor) -> Result<Monitor, String> {
+    monitor.name = monitor.name.trim().to_string();
+    if monitor.name.is_empty() {
+        return Err("Monitor name is required".into());
+    }
+    if monitor.steps.is_empty() {
+        return Err("Monitor needs at least one request".into());
+    }
+    monitor.interval_minutes = monitor.interval_minutes.max(1);
+    Ok(monitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(passed: bool, elapsed_ms: u64) -> MonitorCheck {
+        MonitorCheck {
+            id: 0,
+            monitor: "m".into(),
+            timestamp: Utc::now(),
+            passed,
+            elapsed_ms,
+            status: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn computes_uptime_and_alerts_on_the_first_failure() {
+        let checks: Vec<MonitorCheck> = (1..=20).map(|n| check(n != 1 && n != 5, n * 10)).collect();
+        let stats = stats(&checks);
+        assert_eq!(stats.uptime, 90.0);
+        assert_eq!(stats.average_ms, 105);
+        assert_eq!(stats.p95_ms, 190);
+        assert_eq!(stats.last_passed, Some(false));
+
+        assert!(should_alert(None, &check(false, 1)));
+        assert!(should_alert(Some(&check(true, 1)), &check(false, 1)));
+        assert!(!should_alert(Some(&check(false, 1)), &check(false, 1)));
+        assert!(!should_alert(None, &check(true, 1)));
+    }
+}
@@ -0,0 +1,762 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::{Buf, BufMut, BytesMut};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{interval_at, timeout, Instant};
+use tokio_native_tls::TlsStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_keep_alive() -> u16 {
+    60
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// `url` picks the transport: mqtt:// (TCP, port 1883), mqtts:// (TLS, port 8883), or
+// ws:// and wss:// for brokers behind a WebSocket endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MqttOptions {
+    pub url: String,
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "default_keep_alive")]
+    pub keep_alive_secs: u16,
+    #[serde(default = "default_true")]
+    pub clean_session: bool,
+    #[serde(default)]
+    pub will: Option<MqttPublish>,
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+// Payloads that are not UTF-8 travel base64-encoded, with `base64` set.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MqttPublish {
+    pub topic: String,
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub base64: bool,
+    #[serde(default)]
+    pub qos: u8,
+    #[serde(default)]
+    pub retain: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MqttSubscription {
+    pub topic: String,
+    #[serde(default)]
+    pub qos: u8,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    pub base64: bool,
+    pub qos: u8,
+    pub retain: bool,
+    pub received_at: DateTime<Utc>,
+}
+
+impl MqttPublish {
+    fn bytes(&self) -> Result<Vec<u8>, String> {
+        if self.base64 {
+            STANDARD
+                .decode(self.payload.trim())
+                .map_err(|e| format!("Invalid base64 payload: {}", e))
+        } else {
+            Ok(self.payload.as_bytes().to_vec())
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.topic.is_empty() || self.topic.contains(['#', '+']) {
+            return Err(format!("Cannot publish to topic \"{}\"", self.topic));
+        }
+        check_qos(self.qos)
+    }
+}
+
+fn check_qos(qos: u8) -> Result<(), String> {
+    if qos > 2 {
+        return Err(format!("QoS must be 0, 1 or 2, not {}", qos));
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Packet {
+    ConnAck {
+        code: u8,
+    },
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+        qos: u8,
+        retain: bool,
+        packet_id: u16,
+    },
+    PubAck(u16),
+    PubRec(u16),
+    PubRel(u16),
+    PubComp(u16),
+    SubAck {
+        packet_id: u16,
+        codes: Vec<u8>,
+    },
+    UnsubAck(u16),
+    PingResp,
+}
+
+fn put_string(out: &mut Vec<u8>, value: &[u8]) {
+    out.put_u16(value.len() as u16);
+    out.extend_from_slice(value);
+}
+
+fn frame(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut out = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    out.extend(body);
+    out
+}
+
+fn encode_connect(options: &MqttOptions, client_id: &str) -> Result<Vec<u8>, String> {
+    let mut flags = 0u8;
+    if options.clean_session {
+        flags |= 0x02;
+    }
+    let mut payload = Vec::new();
+    put_string(&mut payload, client_id.as_bytes());
+    if let Some(will) = &options.will {
+        will.validate()?;
+        flags |= 0x04 | (will.qos << 3);
+        if will.retain {
+            flags |= 0x20;
+        }
+        put_string(&mut payload, will.topic.as_bytes());
+        put_string(&mut payload, &will.bytes()?);
+    }
+    if let Some(username) = &options.username {
+        flags |= 0x80;
+        put_string(&mut payload, username.as_bytes());
+    }
+    if let Some(password) = &options.password {
+        flags |= 0x40;
+        put_string(&mut payload, password.as_bytes());
+    }
+    let mut body = Vec::new();
+    put_string(&mut body, b"MQTT");
+    body.push(4);
+    body.push(flags);
+    body.put_u16(options.keep_alive_secs);
+    body.extend(payload);
+    Ok(frame(0x10, body))
+}
+
+fn encode_publish(topic: &str, payload: &[u8], qos: u8, retain: bool, packet_id: u16) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_string(&mut body, topic.as_bytes());
+    if qos > 0 {
+        body.put_u16(packet_id);
+    }
+    body.extend_from_slice(payload);
+    frame(0x30 | (qos << 1) | retain as u8, body)
+}
+
+fn encode_ack(header: u8, packet_id: u16) -> Vec<u8> {
+    frame(header, packet_id.to_be_bytes().to_vec())
+}
+
+fn encode_subscribe(packet_id: u16, topics: &[MqttSubscription]) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    for subscription in topics {
+        put_string(&mut body, subscription.topic.as_bytes());
+        body.push(subscription.qos);
+    }
+    frame(0x82, body)
+}
+
+fn encode_unsubscribe(packet_id: u16, topics: &[String]) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    for topic in topics {
+        put_string(&mut body, topic.as_bytes());
+    }
+    frame(0xA2, body)
+}
+
+fn take_u16(body: &mut BytesMut) -> Result<u16, String> {
+    if body.len() < 2 {
+        return Err("Malformed MQTT packet".into());
+    }
+    Ok(body.get_u16())
+}
+
+// Takes one complete packet off the front of `buffer`, if it holds one.
+fn decode(buffer: &mut BytesMut) -> Result<Option<Packet>, String> {
+    let mut length = 0usize;
+    let mut index = 1;
+    loop {
+        let Some(&byte) = buffer.get(index) else {
+            return Ok(None);
+        };
+        length += ((byte & 0x7F) as usize) << (7 * (index - 1));
+        index += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if index > 4 {
+            return Err("Malformed MQTT packet length".into());
+        }
+    }
+    if buffer.len() < index + length {
+        return Ok(None);
+    }
+    let header = buffer[0];
+    buffer.advance(index);
+    let mut body = buffer.split_to(length);
+    let packet = match header >> 4 {
+        2 => Packet::ConnAck {
+            code: *body.get(1).ok_or("Malformed MQTT packet")?,
+        },
+        3 => {
+            let qos = (header >> 1) & 0x03;
+            let topic_length = take_u16(&mut body)? as usize;
+            if body.len() < topic_length {
+                return Err("Malformed MQTT packet".into());
+            }
+            let topic = String::from_utf8_lossy(&body.split_to(topic_length)).into_owned();
+            let packet_id = if qos > 0 { take_u16(&mut body)? } else { 0 };
+            Packet::Publish {
+                topic,
+                payload: body.to_vec(),
+                qos,
+                retain: header & 0x01 == 1,
+                packet_id,
+            }
+        }
+        4 => Packet::PubAck(take_u16(&mut body)?),
+        5 => Packet::PubRec(take_u16(&mut body)?),
+        6 => Packet::PubRel(take_u16(&mut body)?),
+        7 => Packet::PubComp(take_u16(&mut body)?),
+        9 => Packet::SubAck {
+            packet_id: take_u16(&mut body)?,
+            codes: body.to_vec(),
+        },
+        11 => Packet::UnsubAck(take_u16(&mut body)?),
+        13 => Packet::PingResp,
+        kind => return Err(format!("Unexpected MQTT packet type {}", kind)),
+    };
+    Ok(Some(packet))
+}
+
+fn connack_error(code: u8) -> String {
+    let reason = match code {
+        1 => "unacceptable protocol version",
+        2 => "client identifier rejected",
+        3 => "server unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "unknown reason",
+    };
+    format!("Broker refused the connection: {} ({})", reason, code)
+}
+
+enum Transport {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+    WebSocket(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+}
+
+impl Transport {
+    async fn open(options: &MqttOptions) -> Result<Transport, String> {
+        let url =
+            Url::parse(options.url.trim()).map_err(|e| format!("Invalid broker URL: {}", e))?;
+        let host = url.host_str().ok_or("Broker URL has no host")?.to_string();
+        let tls = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(options.accept_invalid_certs)
+            .build()
+            .map_err(|e| e.to_string())?;
+        match url.scheme() {
+            "mqtt" | "tcp" => {
+                let port = url.port().unwrap_or(1883);
+                let stream = TcpStream::connect((host.as_str(), port))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(Transport::Tcp(stream))
+            }
+            "mqtts" | "ssl" | "tls" => {
+                let port = url.port().unwrap_or(8883);
+                let stream = TcpStream::connect((host.as_str(), port))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let stream = tokio_native_tls::TlsConnector::from(tls)
+                    .connect(&host, stream)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(Transport::Tls(Box::new(stream)))
+            }
+            "ws" | "wss" => {
+                let mut request = url
+                    .as_str()
+                    .into_client_request()
+                    .map_err(|e| e.to_string())?;
+                request
+                    .headers_mut()
+                    .insert("Sec-WebSocket-Protocol", "mqtt".parse().unwrap());
+                let (stream, _) = tokio_tungstenite::connect_async_tls_with_config(
+                    request,
+                    None,
+                    false,
+                    Some(Connector::NativeTls(tls)),
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                Ok(Transport::WebSocket(Box::new(stream)))
+            }
+            scheme => Err(format!("Unsupported broker scheme: {}", scheme)),
+        }
+    }
+
+    async fn send(&mut self, packet: Vec<u8>) -> Result<(), String> {
+        match self {
+            Transport::Tcp(stream) => stream.write_all(&packet).await.map_err(|e| e.to_string()),
+            Transport::Tls(stream) => stream.write_all(&packet).await.map_err(|e| e.to_string()),
+            Transport::WebSocket(stream) => stream
+                .send(Message::Binary(packet))
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    // Appends whatever arrives to `buffer`; false once the broker has closed the
+    // connection. Cancel-safe, so it can sit in a `select!`.
+    async fn receive(&mut self, buffer: &mut BytesMut) -> Result<bool, String> {
+        match self {
+            Transport::Tcp(stream) => {
+                Ok(stream.read_buf(buffer).await.map_err(|e| e.to_string())? > 0)
+            }
+            Transport::Tls(stream) => {
+                Ok(stream.read_buf(buffer).await.map_err(|e| e.to_string())? > 0)
+            }
+            Transport::WebSocket(stream) => loop {
+                match stream.next().await {
+                    Some(Ok(Message::Binary(data))) => {
+                        buffer.extend_from_slice(&data);
+                        return Ok(true);
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Ok(false),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e.to_string()),
+                }
+            },
+        }
+    }
+}
+
+pub enum Command {
+    Publish(MqttPublish, oneshot::Sender<Result<(), String>>),
+    Subscribe(
+        Vec<MqttSubscription>,
+        oneshot::Sender<Result<Vec<u8>, String>>,
+    ),
+    Unsubscribe(Vec<String>, oneshot::Sender<Result<(), String>>),
+    Disconnect,
+}
+
+enum Pending {
+    Publish(oneshot::Sender<Result<(), String>>),
+    Subscribe(oneshot::Sender<Result<Vec<u8>, String>>),
+    Unsubscribe(oneshot::Sender<Result<(), String>>),
+}
+
+// A handle on a running connection; the connection itself lives in the task driving
+// `Connection::run`.
+#[derive(Clone)]
+pub struct Session {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Session {
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<Result<T, String>>) -> Command,
+    ) -> Result<T, String> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| "MQTT connection is closed".to_string())?;
+        response
+            .await
+            .map_err(|_| "MQTT connection is closed".to_string())?
+    }
+
+    // Resolves once the broker has acknowledged the message, for QoS 1 and 2.
+    pub async fn publish(&self, message: MqttPublish) -> Result<(), String> {
+        message.validate()?;
+        self.request(|reply| Command::Publish(message, reply)).await
+    }
+
+    // The QoS granted for each topic; 128 marks a refused subscription.
+    pub async fn subscribe(&self, topics: Vec<MqttSubscription>) -> Result<Vec<u8>, String> {
+        if topics.is_empty() {
+            return Err("No topics to subscribe to".into());
+        }
+        for subscription in &topics {
+            check_qos(subscription.qos)?;
+        }
+        self.request(|reply| Command::Subscribe(topics, reply))
+            .await
+    }
+
+    pub async fn unsubscribe(&self, topics: Vec<String>) -> Result<(), String> {
+        if topics.is_empty() {
+            return Err("No topics to unsubscribe from".into());
+        }
+        self.request(|reply| Command::Unsubscribe(topics, reply))
+            .await
+    }
+
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(Command::Disconnect);
+    }
+
+    pub fn same(&self, other: &Session) -> bool {
+        self.commands.same_channel(&other.commands)
+    }
+}
+
+pub struct Connection {
+    transport: Transport,
+    buffer: BytesMut,
+    keep_alive: u16,
+    commands: mpsc::UnboundedReceiver<Command>,
+}
+
+// Opens the transport and waits for the broker to accept the CONNECT. An empty client
+// id is replaced by a random one.
+pub async fn connect(options: &MqttOptions) -> Result<(Session, Connection), String> {
+    let client_id = if options.client_id.is_empty() {
+        format!("restman-{:08x}", rand::random::<u32>())
+    } else {
+        options.client_id.clone()
+    };
+    let connect = encode_connect(options, &client_id)?;
+    let handshake = async {
+        let mut transport = Transport::open(options).await?;
+        transport.send(connect).await?;
+        let mut buffer = BytesMut::new();
+        loop {
+            if let Some(packet) = decode(&mut buffer)? {
+                return match packet {
+                    Packet::ConnAck { code: 0 } => Ok((transport, buffer)),
+                    Packet::ConnAck { code } => Err(connack_error(code)),
+                    _ => Err("Broker did not acknowledge the connection".into()),
+                };
+            }
+            if !transport.receive(&mut buffer).await? {
+                return Err("Broker closed the connection".into());
+            }
+        }
+    };
+    let (transport, buffer) = timeout(CONNECT_TIMEOUT, handshake)
+        .await
+        .map_err(|_| "Timed out connecting to the broker".to_string())??;
+    let (sender, commands) = mpsc::unbounded_channel();
+    Ok((
+        Session { commands: sender },
+        Connection {
+            transport,
+            buffer,
+            keep_alive: options.keep_alive_secs,
+            commands,
+        },
+    ))
+}
+
+impl Connection {
+    // Drives the connection until it closes, handing every received message to
+    // `on_message`. Returns why it closed.
+    pub async fn run(mut self, mut on_message: impl FnMut(MqttMessage)) -> String {
+        let mut pending: HashMap<u16, Pending> = HashMap::new();
+        let mut received: HashSet<u16> = HashSet::new();
+        let mut next_id: u16 = 0;
+        let period = Duration::from_secs(self.keep_alive.max(1) as u64);
+        let mut ping = interval_at(Instant::now() + period, period);
+        let mut awaiting_pong = false;
+        loop {
+            let result: Result<(), String> = async {
+                while let Some(packet) = decode(&mut self.buffer)? {
+                    match packet {
+                        Packet::Publish {
+                            topic,
+                            payload,
+                            qos,
+                            retain,
+                            packet_id,
+                        } => {
+                            match qos {
+                                1 => self.transport.send(encode_ack(0x40, packet_id)).await?,
+                                2 => self.transport.send(encode_ack(0x50, packet_id)).await?,
+                                _ => {}
+                            }
+                            // A QoS 2 message is delivered once even if the broker resends it
+                            // before our PUBREC arrives.
+                            if qos == 2 && !received.insert(packet_id) {
+                                continue;
+                            }
+                            let (payload, base64) = match String::from_utf8(payload) {
+                                Ok(text) => (text, false),
+                                Err(e) => (STANDARD.encode(e.as_bytes()), true),
+                            };
+                            on_message(MqttMessage {
+                                topic,
+                                payload,
+                                base64,
+                                qos,
+                                retain,
+                                received_at: Utc::now(),
+                            });
+                        }
+                        Packet::PubRel(id) => {
+                            received.remove(&id);
+                            self.transport.send(encode_ack(0x70, id)).await?;
+                        }
+                        Packet::PubRec(id) => self.transport.send(encode_ack(0x62, id)).await?,
+                        Packet::PubAck(id) | Packet::PubComp(id) => {
+                            if let Some(Pending::Publish(reply)) = pending.remove(&id) {
+                                let _ = reply.send(Ok(()));
+                            }
+                        }
+                        Packet::SubAck { packet_id, codes } => {
+                            if let Some(Pending::Subscribe(reply)) = pending.remove(&packet_id) {
+                                let _ = reply.send(Ok(codes));
+                            }
+                        }
+                        Packet::UnsubAck(id) => {
+                            if let Some(Pending::Unsubscribe(reply)) = pending.remove(&id) {
+                                let _ = reply.send(Ok(()));
+                            }
+                        }
+                        Packet::PingResp => awaiting_pong = false,
+                        Packet::ConnAck { .. } => {}
+                    }
+                }
+                Ok(())
+            }
+            .await;
+            if let Err(e) = result {
+                return e;
+            }
+            tokio::select! {
+                received = self.transport.receive(&mut self.buffer) => match received {
+                    Ok(true) => {}
+                    Ok(false) => return "Broker closed the connection".into(),
+                    Err(e) => return e,
+                },
+                command = self.commands.recv() => {
+                    let packet = match command {
+                        None | Some(Command::Disconnect) => {
+                            let _ = self.transport.send(vec![0xE0, 0x00]).await;
+                            return "Disconnected".into();
+                        }
+                        Some(command) => {
+                            loop {
+                                next_id = next_id.wrapping_add(1).max(1);
+                                if !pending.contains_key(&next_id) {
+                                    break;
+                                }
+                            }
+                            match command {
+                                Command::Publish(message, reply) => {
+                                    let payload = match message.bytes() {
+                                        Ok(payload) => payload,
+                                        Err(e) => {
+                                            let _ = reply.send(Err(e));
+                                            continue;
+                                        }
+                                    };
+                                    let packet = encode_publish(&message.topic, &payload, message.qos, message.retain, next_id);
+                                    if message.qos == 0 {
+                                        let sent = self.transport.send(packet).await;
+                                        let failed = sent.as_ref().err().cloned();
+                                        let _ = reply.send(sent);
+                                        if let Some(e) = failed {
+                                            return e;
+                                        }
+                                        continue;
+                                    }
+                                    pending.insert(next_id, Pending::Publish(reply));
+                                    packet
+                                }
+                                Command::Subscribe(topics, reply) => {
+                                    pending.insert(next_id, Pending::Subscribe(reply));
+                                    encode_subscribe(next_id, &topics)
+                                }
+                                Command::Unsubscribe(topics, reply) => {
+                                    pending.insert(next_id, Pending::Unsubscribe(reply));
+                                    encode_unsubscribe(next_id, &topics)
+                                }
+                                Command::Disconnect => unreachable!(),
+                            }
+                        }
+                    };
+                    if let Err(e) = self.transport.send(packet).await {
+                        return e;
+                    }
+                }
+                _ = ping.tick(), if self.keep_alive > 0 => {
+                    if awaiting_pong {
+                        return "Broker stopped responding to pings".into();
+                    }
+                    awaiting_pong = true;
+                    if let Err(e) = self.transport.send(vec![0xC0, 0x00]).await {
+                        return e;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let mut length = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            length += ((byte & 0x7F) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    #[tokio::test]
+    async fn subscribes_receives_and_publishes_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x10);
+            assert_eq!(&body[..7], b"\x00\x04MQTT\x04");
+            assert_eq!(body[7], 0xC2);
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x82);
+            assert_eq!(&body[2..], b"\x00\x09sensors/#\x01");
+            stream
+                .write_all(&[0x90, 0x03, body[0], body[1], 0x01])
+                .await
+                .unwrap();
+            stream
+                .write_all(&encode_publish("sensors/t1", b"21.5", 1, false, 7))
+                .await
+                .unwrap();
+            assert_eq!(read_packet(&mut stream).await, (0x40, vec![0x00, 0x07]));
+
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x33);
+            assert_eq!(&body[..12], b"\x00\x0Acommands/1");
+            assert_eq!(&body[14..], b"\xFF\x00");
+            stream
+                .write_all(&encode_ack(0x40, u16::from_be_bytes([body[12], body[13]])))
+                .await
+                .unwrap();
+            assert_eq!(read_packet(&mut stream).await, (0xE0, vec![]));
+        });
+
+        let options = MqttOptions {
+            url: format!("mqtt://127.0.0.1:{}", port),
+            client_id: "test".into(),
+            username: Some("device".into()),
+            password: Some("secret".into()),
+            keep_alive_secs: 30,
+            clean_session: true,
+            will: None,
+            accept_invalid_certs: false,
+        };
+        let (session, connection) = connect(&options).await.unwrap();
+        let (messages, mut received) = mpsc::unbounded_channel();
+        let running = tokio::spawn(connection.run(move |message| messages.send(message).unwrap()));
+
+        let granted = session
+            .subscribe(vec![MqttSubscription {
+                topic: "sensors/#".into(),
+                qos: 1,
+            }])
+            .await
+            .unwrap();
+        assert_eq!(granted, vec![1]);
+        let message = received.recv().await.unwrap();
+        assert_eq!(
+            (
+                message.topic.as_str(),
+                message.payload.as_str(),
+                message.qos
+            ),
+            ("sensors/t1", "21.5", 1)
+        );
+
+        let publish = MqttPublish {
+            topic: "commands/1".into(),
+            payload: "/wA=".into(),
+            base64: true,
+            qos: 1,
+            retain: true,
+        };
+        session.publish(publish).await.unwrap();
+        let wildcard = MqttPublish {
+            topic: "commands/+".into(),
+            payload: String::new(),
+            base64: false,
+            qos: 0,
+            retain: false,
+        };
+        assert!(session.publish(wildcard).await.is_err());
+
+        session.disconnect();
+        assert_eq!(running.await.unwrap(), "Disconnected");
+        broker.await.unwrap();
+    }
+}
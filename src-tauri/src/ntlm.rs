@@ -0,0 +1,407 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+use rand::RngCore;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION,
+    WWW_AUTHENTICATE,
+};
+use serde::{Deserialize, Serialize};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+// A Negotiate server that turns down the NTLM token, or answers with a Kerberos one.
+// Kerberos needs GSSAPI/SSPI and is not implemented.
+const KERBEROS_UNSUPPORTED: &str =
+    "The server requires Kerberos through Negotiate, which RestMan does not \
+     support; only NTLM is. Enable NTLM for this server or proxy, or use another auth type";
+// UNICODE | OEM | REQUEST_TARGET | NTLM | ALWAYS_SIGN | EXTENDED_SESSIONSECURITY | 128 | 56
+const NEGOTIATE_FLAGS: u32 = 0xa008_8207;
+const NEGOTIATE_OEM: u32 = 0x0000_0002;
+const AV_EOL: u16 = 0;
+const AV_TIMESTAMP: u16 = 7;
+// Seconds between 1601-01-01 (the FILETIME epoch) and the Unix epoch.
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct NtlmCredentials {
+    pub username: String,
+    pub password: String,
+    // Also taken from a `DOMAIN\user` username when left empty.
+    pub domain: String,
+}
+
+impl NtlmCredentials {
+    fn user_and_domain(&self) -> (String, String) {
+        match self.username.split_once('\\') {
+            Some((domain, user)) if self.domain.is_empty() => (user.into(), domain.into()),
+            _ => (self.username.clone(), self.domain.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NtlmChallenge {
+    pub server_challenge: [u8; 8],
+    pub flags: u32,
+    pub target_info: Vec<u8>,
+}
+
+fn utf16le(value: &str) -> Vec<u8> {
+    value
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect()
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+pub fn negotiate_message() -> Vec<u8> {
+    let mut message = SIGNATURE.to_vec();
+    message.extend(1u32.to_le_bytes());
+    message.extend(NEGOTIATE_FLAGS.to_le_bytes());
+    // Empty domain and workstation buffers.
+    message.extend([0u8; 16]);
+    message
+}
+
+pub fn parse_challenge(message: &[u8]) -> Result<NtlmChallenge, String> {
+    if message.get(..8) != Some(&SIGNATURE[..]) || read_u32(message, 8) != Some(2) {
+        return Err("Server sent an invalid NTLM challenge".into());
+    }
+    let flags = read_u32(message, 20).ok_or("NTLM challenge is truncated")?;
+    let server_challenge = message
+        .get(24..32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("NTLM challenge is truncated")?;
+    let target_info = match (read_u16(message, 40), read_u32(message, 44)) {
+        (Some(len), Some(offset)) => message
+            .get(offset as usize..offset as usize + len as usize)
+            .ok_or("NTLM target info is out of bounds")?
+            .to_vec(),
+        _ => Vec::new(),
+    };
+    Ok(NtlmChallenge {
+        server_challenge,
+        flags,
+        target_info,
+    })
+}
+
+fn av_timestamp(target_info: &[u8]) -> Option<[u8; 8]> {
+    let mut at = 0;
+    while let (Some(id), Some(len)) = (read_u16(target_info, at), read_u16(target_info, at + 2)) {
+        let value = target_info.get(at + 4..at + 4 + len as usize)?;
+        match id {
+            AV_EOL => return None,
+            AV_TIMESTAMP => return value.try_into().ok(),
+            _ => at += 4 + len as usize,
+        }
+    }
+    None
+}
+
+fn response_key(credentials: &NtlmCredentials) -> [u8; 16] {
+    let (user, domain) = credentials.user_and_domain();
+    let nt_hash = Md4::digest(utf16le(&credentials.password));
+    hmac_md5(
+        &nt_hash,
+        &[&utf16le(&(user.to_uppercase() + domain.as_str()))],
+    )
+}
+
+// Returns the LMv2 and NTLMv2 responses for a challenge.
+fn responses(
+    credentials: &NtlmCredentials,
+    challenge: &NtlmChallenge,
+    client_challenge: [u8; 8],
+    timestamp: [u8; 8],
+) -> (Vec<u8>, Vec<u8>) {
+    let key = response_key(credentials);
+    let mut blob = vec![1u8, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend(timestamp);
+    blob.extend(client_challenge);
+    blob.extend([0u8; 4]);
+    blob.extend(&challenge.target_info);
+    blob.extend([0u8; 4]);
+    let proof = hmac_md5(&key, &[&challenge.server_challenge, &blob]);
+    let mut nt = proof.to_vec();
+    nt.extend(blob);
+    let mut lm = hmac_md5(&key, &[&challenge.server_challenge, &client_challenge]).to_vec();
+    lm.extend(client_challenge);
+    (lm, nt)
+}
+
+pub fn authenticate_message(credentials: &NtlmCredentials, challenge: &NtlmChallenge) -> Vec<u8> {
+    let mut client_challenge = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut client_challenge);
+    // Servers that send a timestamp expect it echoed back, with an empty LMv2 response.
+    let (timestamp, server_time) = match av_timestamp(&challenge.target_info) {
+        Some(timestamp) => (timestamp, true),
+        None => {
+            let now = chrono::Utc::now().timestamp().max(0) as u64;
+            (
+                ((now + FILETIME_EPOCH_OFFSET) * 10_000_000).to_le_bytes(),
+                false,
+            )
+        }
+    };
+    let (mut lm, nt) = responses(credentials, challenge, client_challenge, timestamp);
+    if server_time {
+        lm = vec![0u8; 24];
+    }
+    let (user, domain) = credentials.user_and_domain();
+    let fields = [
+        lm,
+        nt,
+        utf16le(&domain),
+        utf16le(&user),
+        Vec::new(),
+        Vec::new(),
+    ];
+
+    let mut message = SIGNATURE.to_vec();
+    message.extend(3u32.to_le_bytes());
+    let mut offset = 64u32;
+    for field in &fields {
+        message.extend((field.len() as u16).to_le_bytes());
+        message.extend((field.len() as u16).to_le_bytes());
+        message.extend(offset.to_le_bytes());
+        offset += field.len() as u32;
+    }
+    let flags = NEGOTIATE_FLAGS & !NEGOTIATE_OEM & (challenge.flags | 0x0000_0001);
+    message.extend(flags.to_le_bytes());
+    for field in fields {
+        message.extend(field);
+    }
+    message
+}
+
+// Whether a handshake answers server (401) or proxy (407) challenges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NtlmTarget {
+    Server,
+    Proxy,
+}
+
+// NTLM authenticates a connection rather than a request, so each step relies on the
+// client reusing the kept-alive connection from the previous response.
+#[derive(Debug)]
+pub struct NtlmHandshake {
+    target: NtlmTarget,
+    // "NTLM" or "Negotiate". Only NTLM is spoken through Negotiate: a server that
+    // insists on Kerberos is reported as such rather than answered.
+    scheme: Option<&'static str>,
+    authenticated: bool,
+    pending: Option<String>,
+}
+
+impl NtlmHandshake {
+    pub fn new(target: NtlmTarget) -> Self {
+        NtlmHandshake {
+            target,
+            scheme: None,
+            authenticated: false,
+            pending: None,
+        }
+    }
+
+    fn offers<'a>(&self, headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
+        let name = match self.target {
+            NtlmTarget::Server => WWW_AUTHENTICATE,
+            NtlmTarget::Proxy => PROXY_AUTHENTICATE,
+        };
+        headers
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| {
+                let value = value.trim();
+                let (name, token) = value.split_once(' ').unwrap_or((value, ""));
+                name.eq_ignore_ascii_case(scheme).then(|| token.trim())
+            })
+    }
+
+    // Reads a 401/407 response and prepares the next message. Returns false once there
+    // is nothing left to send, i.e. the server does not speak NTLM or rejected us.
+    pub fn advance(
+        &mut self,
+        headers: &HeaderMap,
+        credentials: &NtlmCredentials,
+    ) -> Result<bool, String> {
+        if self.authenticated {
+            return Ok(false);
+        }
+        let scheme = match self.scheme {
+            Some(scheme) => scheme,
+            None => {
+                let scheme = ["NTLM", "Negotiate"]
+                    .into_iter()
+                    .find(|scheme| self.offers(headers, scheme).is_some());
+                let Some(scheme) = scheme else {
+                    return Ok(false);
+                };
+                self.scheme = Some(scheme);
+                self.pending = Some(format!(
+                    "{} {}",
+                    scheme,
+                    STANDARD.encode(negotiate_message())
+                ));
+                return Ok(true);
+            }
+        };
+        let token = match self.offers(headers, scheme).filter(|t| !t.is_empty()) {
+            Some(token) => token,
+            None if scheme == "Negotiate" => return Err(KERBEROS_UNSUPPORTED.into()),
+            None => return Ok(false),
+        };
+        let challenge = STANDARD.decode(token).map_err(|e| e.to_string())?;
+        if scheme == "Negotiate" && !challenge.starts_with(SIGNATURE) {
+            return Err(KERBEROS_UNSUPPORTED.into());
+        }
+        let challenge = parse_challenge(&challenge)?;
+        let message = authenticate_message(credentials, &challenge);
+        self.pending = Some(format!("{} {}", scheme, STANDARD.encode(message)));
+        self.authenticated = true;
+        Ok(true)
+    }
+
+    // The prepared header goes out with the next request only.
+    pub fn take_header(&mut self) -> Result<Option<(HeaderName, HeaderValue)>, String> {
+        let Some(value) = self.pending.take() else {
+            return Ok(None);
+        };
+        let name = match self.target {
+            NtlmTarget::Server => AUTHORIZATION,
+            NtlmTarget::Proxy => PROXY_AUTHORIZATION,
+        };
+        let value = HeaderValue::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(Some((name, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // MS-NLMP 4.2.4 NTLMv2 authentication example.
+    #[test]
+    fn computes_ms_nlmp_ntlmv2_responses() {
+        let credentials = NtlmCredentials {
+            username: "Domain\\User".into(),
+            password: "Password".into(),
+            domain: String::new(),
+        };
+        let mut target_info = Vec::new();
+        for (id, value) in [(2u16, "Domain"), (1u16, "Server")] {
+            let value = utf16le(value);
+            target_info.extend(id.to_le_bytes());
+            target_info.extend((value.len() as u16).to_le_bytes());
+            target_info.extend(value);
+        }
+        target_info.extend([0u8; 4]);
+        let challenge = NtlmChallenge {
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            flags: 0xe28a_8233,
+            target_info,
+        };
+        assert_eq!(
+            hex::encode(response_key(&credentials)),
+            "0c868a403bfd7a93a3001ef22ef02e3f"
+        );
+        let (lm, nt) = responses(&credentials, &challenge, [0xaa; 8], [0; 8]);
+        assert_eq!(
+            hex::encode(lm),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+        assert_eq!(hex::encode(&nt[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+    }
+
+    #[test]
+    fn handshake_negotiates_then_authenticates() {
+        let mut handshake = NtlmHandshake::new(NtlmTarget::Proxy);
+        let credentials = NtlmCredentials {
+            username: "user".into(),
+            password: "pass".into(),
+            domain: "CORP".into(),
+        };
+        let mut headers = HeaderMap::new();
+        headers.append(PROXY_AUTHENTICATE, "Negotiate".parse().unwrap());
+        assert!(handshake.advance(&headers, &credentials).unwrap());
+        let (name, value) = handshake.take_header().unwrap().unwrap();
+        assert_eq!(name, PROXY_AUTHORIZATION);
+        assert!(value
+            .to_str()
+            .unwrap()
+            .starts_with("Negotiate TlRMTVNTUAAB"));
+        assert!(handshake.take_header().unwrap().is_none());
+
+        let mut message = SIGNATURE.to_vec();
+        message.extend(2u32.to_le_bytes());
+        message.extend([0u8; 8]);
+        message.extend(NEGOTIATE_FLAGS.to_le_bytes());
+        message.extend([7u8; 8]);
+        message.extend([0u8; 16]);
+        let mut headers = HeaderMap::new();
+        let challenge = format!("Negotiate {}", STANDARD.encode(message));
+        headers.append(PROXY_AUTHENTICATE, challenge.parse().unwrap());
+        assert!(handshake.advance(&headers, &credentials).unwrap());
+        let (_, value) = handshake.take_header().unwrap().unwrap();
+        assert!(value
+            .to_str()
+            .unwrap()
+            .starts_with("Negotiate TlRMTVNTUAAD"));
+        // A further challenge means the credentials were rejected.
+        assert!(!handshake.advance(&headers, &credentials).unwrap());
+    }
+
+    #[test]
+    fn kerberos_only_negotiate_is_reported() {
+        let credentials = NtlmCredentials {
+            username: "user".into(),
+            password: "pass".into(),
+            domain: String::new(),
+        };
+        let offer = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.append(WWW_AUTHENTICATE, value.parse().unwrap());
+            headers
+        };
+        // The NTLM token is turned down with a bare Negotiate again...
+        let mut handshake = NtlmHandshake::new(NtlmTarget::Server);
+        assert!(handshake
+            .advance(&offer("Negotiate"), &credentials)
+            .unwrap());
+        let error = handshake
+            .advance(&offer("Negotiate"), &credentials)
+            .unwrap_err();
+        assert!(error.contains("Kerberos"), "{}", error);
+        // ...or answered with a token that is not NTLM.
+        let mut handshake = NtlmHandshake::new(NtlmTarget::Server);
+        assert!(handshake
+            .advance(&offer("Negotiate"), &credentials)
+            .unwrap());
+        let kerberos = format!("Negotiate {}", STANDARD.encode([0x60, 0x82, 0x01, 0x00]));
+        assert!(handshake
+            .advance(&offer(&kerberos), &credentials)
+            .unwrap_err()
+            .contains("Kerberos"));
+    }
+}
@@ -0,0 +1,713 @@
+use crate::secrets::{create_private_dir, write_private};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{timeout, Duration};
+
+pub const DEFAULT_ENVIRONMENT: &str = "default";
+const TOKENS_FILE: &str = "oauth_tokens.json";
+const CALLBACK_PATH: &str = "/callback";
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(300);
+// Tokens this close to expiry are refreshed before use.
+const EXPIRY_SKEW_SECS: i64 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantType {
+    #[default]
+    AuthorizationCode,
+    ClientCredentials,
+    Password,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct OAuthConfig {
+    pub grant_type: GrantType,
+    pub authorization_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+    // 0 lets the OS pick a free port; providers that require an exact redirect URI
+    // need a fixed one.
+    pub redirect_port: u16,
+    // Resource owner credentials for the password grant.
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OAuthToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scope: Option<String>,
+}
+
+impl OAuthToken {
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .map(|at| at - ChronoDuration::seconds(EXPIRY_SKEW_SECS) <= now)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    token_type: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    scope: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+pub fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+pub fn generate_pkce() -> Pkce {
+    let verifier = random_string(64);
+    let challenge = pkce_challenge(&verifier);
+    Pkce {
+        verifier,
+        challenge,
+    }
+}
+
+pub fn authorization_url(
+    config: &OAuthConfig,
+    redirect_uri: &str,
+    state: &str,
+    challenge: &str,
+) -> Result<String, String> {
+    let mut url = Url::parse(&config.authorization_url)
+        .map_err(|e| format!("Invalid authorization URL: {}", e))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("state", state)
+            .append_pair("code_challenge", challenge)
+            .append_pair("code_challenge_method", "S256");
+        if !config.scopes.is_empty() {
+            query.append_pair("scope", &config.scopes.join(" "));
+        }
+    }
+    Ok(url.to_string())
+}
+
+pub struct CallbackListener {
+    listener: TcpListener,
+    pub redirect_uri: String,
+}
+
+pub async fn bind_callback(port: u16) -> Result<CallbackListener, String> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Cannot listen for the OAuth callback: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    Ok(CallbackListener {
+        listener,
+        redirect_uri: format!("http://127.0.0.1:{}{}", port, CALLBACK_PATH),
+    })
+}
+
+async fn reply(socket: &mut TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "<html><body><p>{}</p><p>You can close this window.</p></body></html>",
+        message
+    );
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = socket.write_all(response.as_bytes()).await;
+}
+
+impl CallbackListener {
+    // Serves requests until the redirect arrives; stray requests such as favicon
+    // lookups get a 404 and are ignored.
+    pub async fn wait_for_code(self, expected_state: &str) -> Result<String, String> {
+        loop {
+            let (mut socket, _) = self.listener.accept().await.map_err(|e| e.to_string())?;
+            let mut buf = vec![0u8; 8192];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let target = request
+                .lines()
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .unwrap_or("");
+            let url = match Url::parse(&format!("http://127.0.0.1{}", target)) {
+                Ok(url) if url.path() == CALLBACK_PATH => url,
+                _ => {
+                    reply(&mut socket, "404 Not Found", "Not found.").await;
+                    continue;
+                }
+            };
+            let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            if let Some(error) = params.get("error") {
+                let description = params
+                    .get("error_description")
+                    .map(|d| format!(": {}", d))
+                    .unwrap_or_default();
+                reply(&mut socket, "400 Bad Request", "Authorization failed.").await;
+                return Err(format!("Authorization failed: {}{}", error, description));
+            }
+            if params.get("state").map(String::as_str) != Some(expected_state) {
+                reply(
+                    &mut socket,
+                    "400 Bad Request",
+                    "Authorization state mismatch.",
+                )
+                .await;
+                return Err("OAuth state mismatch; the callback was not for this request".into());
+            }
+            match params.get("code") {
+                Some(code) => {
+                    reply(&mut socket, "200 OK", "Authorization complete.").await;
+                    return Ok(code.clone());
+                }
+                None => {
+                    reply(
+                        &mut socket,
+                        "400 Bad Request",
+                        "Missing authorization code.",
+                    )
+                    .await;
+                    return Err("OAuth callback did not include a code".into());
+                }
+            }
+        }
+    }
+}
+
+pub async fn request_token(
+    client: &Client,
+    config: &OAuthConfig,
+    mut params: Vec<(&str, String)>,
+) -> Result<OAuthToken, String> {
+    params.push(("client_id", config.client_id.clone()));
+    if let Some(secret) = config.client_secret.as_ref().filter(|s| !s.is_empty()) {
+        params.push(("client_secret", secret.clone()));
+    }
+    let response = client
+        .post(&config.token_url)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    let parsed: TokenResponse = serde_json::from_str(&body).map_err(|_| {
+        format!(
+            "Token endpoint returned {} with an unreadable body: {}",
+            status, body
+        )
+    })?;
+    if let Some(error) = parsed.error {
+        let description = parsed
+            .error_description
+            .map(|d| format!(": {}", d))
+            .unwrap_or_default();
+        return Err(format!("Token request failed: {}{}", error, description));
+    }
+    let access_token = parsed
+        .access_token
+        .ok_or_else(|| format!("Token endpoint returned {} without an access_token", status))?;
+    Ok(OAuthToken {
+        access_token,
+        token_type: parsed.token_type.unwrap_or_else(|| "Bearer".into()),
+        refresh_token: parsed.refresh_token,
+        expires_at: parsed
+            .expires_in
+            .map(|seconds| Utc::now() + ChronoDuration::seconds(seconds)),
+        scope: parsed.scope,
+    })
+}
+
+pub async fn exchange_code(
+    client: &Client,
+    config: &OAuthConfig,
+    code: &str,
+    redirect_uri: &str,
+    verifier: &str,
+) -> Result<OAuthToken, String> {
+    request_token(
+        client,
+        config,
+        vec![
+            ("grant_type", "authorization_code".into()),
+            ("code", code.into()),
+            ("redirect_uri", redirect_uri.into()),
+            ("code_verifier", verifier.into()),
+        ],
+    )
+    .await
+}
+
+// Client credentials and password grants need no user interaction, so their tokens can
+// be fetched again whenever they expire.
+pub async fn fetch_token(client: &Client, config: &OAuthConfig) -> Result<OAuthToken, String> {
+    let mut params: Vec<(&str, String)> = match config.grant_type {
+        GrantType::AuthorizationCode => {
+            return Err("The authorization code grant needs an interactive login".into())
+        }
+        GrantType::ClientCredentials => vec![("grant_type", "client_credentials".into())],
+        GrantType::Password => vec![
+            ("grant_type", "password".into()),
+            (
+                "username",
+                config
+                    .username
+                    .clone()
+                    .ok_or("The password grant needs a username")?,
+            ),
+            ("password", config.password.clone().unwrap_or_default()),
+        ],
+    };
+    if !config.scopes.is_empty() {
+        params.push(("scope", config.scopes.join(" ")));
+    }
+    request_token(client, config, params).await
+}
+
+// Providers that do not rotate refresh tokens omit them from the response, so the
+// previous one is kept.
+pub async fn refresh(
+    client: &Client,
+    config: &OAuthConfig,
+    refresh_token: &str,
+) -> Result<OAuthToken, String> {
+    let mut token = request_token(
+        client,
+        config,
+        vec![
+            ("grant_type", "refresh_token".into()),
+            ("refresh_token", refresh_token.into()),
+        ],
+    )
+    .await?;
+    if token.refresh_token.is_none() {
+        token.refresh_token = Some(refresh_token.to_string());
+    }
+    Ok(token)
+}
+
+// Runs the Authorization Code + PKCE flow: `open_browser` receives the authorization
+// URL and the provider redirects back to a one-shot localhost listener.
+pub async fn authorize<F>(
+    client: &Client,
+    config: &OAuthConfig,
+    open_browser: F,
+) -> Result<OAuthToken, String>
+where
+    F: FnOnce(&str) -> Result<(), String>,
+{
+    let listener = bind_callback(config.redirect_port).await?;
+    let redirect_uri = listener.redirect_uri.clone();
+    let pkce = generate_pkce();
+    let state = random_string(32);
+    open_browser(&authorization_url(
+        config,
+        &redirect_uri,
+        &state,
+        &pkce.challenge,
+    )?)?;
+    let code = timeout(CALLBACK_TIMEOUT, listener.wait_for_code(&state))
+        .await
+        .map_err(|_| "Timed out waiting for the OAuth callback".to_string())??;
+    exchange_code(client, config, &code, &redirect_uri, &pkce.verifier).await
+}
+
+// Grant tokens are cached under a digest of the config that issued them, so two
+// requests sharing a config share a token without the key exposing the secret.
+pub fn grant_cache_key(config: &OAuthConfig) -> String {
+    let serialized = serde_json::to_vec(config).unwrap_or_default();
+    format!("grant:{}", hex::encode(&Sha256::digest(&serialized)[..16]))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StoredToken {
+    pub config: OAuthConfig,
+    pub token: OAuthToken,
+}
+
+// Tokens are kept per environment together with the config that issued them, which
+// is what a later refresh needs.
+pub struct TokenStore {
+    path: PathBuf,
+    tokens: Mutex<HashMap<String, StoredToken>>,
+}
+
+impl TokenStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(TOKENS_FILE);
+        let tokens = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        TokenStore {
+            path,
+            tokens: Mutex::new(tokens),
+        }
+    }
+
+    // The password grant's password is kept for the session only; once restarted, the
+    // grant fetches a new token with the password from the request's config.
+    fn save(&self, tokens: &HashMap<String, StoredToken>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            create_private_dir(parent).map_err(|e| e.to_string())?;
+        }
+        let persisted: HashMap<&String, StoredToken> = tokens
+            .iter()
+            .map(|(key, stored)| {
+                let mut stored = stored.clone();
+                stored.config.password = None;
+                (key, stored)
+            })
+            .collect();
+        let content = serde_json::to_string_pretty(&persisted).map_err(|e| e.to_string())?;
+        write_private(&self.path, &content).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, environment: &str) -> Option<StoredToken> {
+        self.tokens.lock().unwrap().get(environment).cloned()
+    }
+
+    pub fn insert(&self, environment: &str, stored: StoredToken) -> Result<(), String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.insert(environment.to_string(), stored);
+        self.save(&tokens)
+    }
+
+    pub fn remove(&self, environment: &str) -> Result<bool, String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        let removed = tokens.remove(environment).is_some();
+        self.save(&tokens)?;
+        Ok(removed)
+    }
+
+    // Returns the stored token, refreshing it first when it is about to expire.
+    pub async fn current(
+        &self,
+        client: &Client,
+        environment: &str,
+    ) -> Result<Option<OAuthToken>, String> {
+        let stored = match self.get(environment) {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+        if !stored.token.needs_refresh(Utc::now()) {
+            return Ok(Some(stored.token));
+        }
+        let token = match stored.token.refresh_token.as_deref() {
+            Some(refresh_token) => refresh(client, &stored.config, refresh_token).await?,
+            None if stored.config.grant_type != GrantType::AuthorizationCode => {
+                fetch_token(client, &stored.config).await?
+            }
+            None => {
+                return Err(format!(
+                    "OAuth token for {} expired and cannot be refreshed; authorize again",
+                    environment
+                ))
+            }
+        };
+        self.insert(
+            environment,
+            StoredToken {
+                config: stored.config,
+                token: token.clone(),
+            },
+        )?;
+        Ok(Some(token))
+    }
+
+    // Returns a cached token for a non-interactive grant, fetching one on first use or
+    // when the config has changed since it was issued.
+    pub async fn obtain(
+        &self,
+        client: &Client,
+        key: &str,
+        config: &OAuthConfig,
+    ) -> Result<OAuthToken, String> {
+        if self.get(key).map(|stored| &stored.config == config) == Some(true) {
+            if let Some(token) = self.current(client, key).await? {
+                return Ok(token);
+            }
+        }
+        let token = fetch_token(client, config).await?;
+        self.insert(
+            key,
+            StoredToken {
+                config: config.clone(),
+                token: token.clone(),
+            },
+        )?;
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn serve_token(listener: TcpListener, body: &'static str) {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    }
+
+    #[test]
+    fn pkce_challenge_matches_rfc7636_example() {
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        assert_eq!(generate_pkce().verifier.len(), 64);
+    }
+
+    #[test]
+    fn builds_authorization_url() {
+        let config = OAuthConfig {
+            authorization_url: "https://auth.example.com/authorize?audience=api".into(),
+            client_id: "app".into(),
+            scopes: vec!["openid".into(), "profile".into()],
+            ..OAuthConfig::default()
+        };
+        let url =
+            authorization_url(&config, "http://127.0.0.1:9000/callback", "xyz", "abc").unwrap();
+        let params: HashMap<String, String> = Url::parse(&url)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(params["audience"], "api");
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["scope"], "openid profile");
+        assert_eq!(params["code_challenge_method"], "S256");
+        assert_eq!(params["redirect_uri"], "http://127.0.0.1:9000/callback");
+    }
+
+    #[tokio::test]
+    async fn callback_listener_checks_state_and_returns_code() {
+        let listener = bind_callback(0).await.unwrap();
+        let redirect_uri = listener.redirect_uri.clone();
+        let waiter = tokio::spawn(async move { listener.wait_for_code("expected").await });
+        let client = Client::new();
+        let favicon = redirect_uri.replace(CALLBACK_PATH, "/favicon.ico");
+        assert_eq!(client.get(&favicon).send().await.unwrap().status(), 404);
+        let page = client
+            .get(format!("{}?code=abc&state=expected", redirect_uri))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(page.status(), 200);
+        assert_eq!(waiter.await.unwrap(), Ok("abc".to_string()));
+
+        let listener = bind_callback(0).await.unwrap();
+        let redirect_uri = listener.redirect_uri.clone();
+        let waiter = tokio::spawn(async move { listener.wait_for_code("expected").await });
+        let _ = client
+            .get(format!("{}?code=abc&state=forged", redirect_uri))
+            .send()
+            .await;
+        assert!(waiter.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn refreshes_expired_tokens_and_keeps_refresh_token() {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(serve_token(
+            server,
+            r#"{"access_token":"fresh","token_type":"Bearer","expires_in":3600}"#,
+        ));
+        let dir = std::env::temp_dir().join(format!("restman-oauth-{}", std::process::id()));
+        let store = TokenStore::load(&dir);
+        store
+            .insert(
+                "staging",
+                StoredToken {
+                    config: OAuthConfig {
+                        token_url: format!("http://{}/token", addr),
+                        client_id: "app".into(),
+                        ..OAuthConfig::default()
+                    },
+                    token: OAuthToken {
+                        access_token: "stale".into(),
+                        token_type: "Bearer".into(),
+                        refresh_token: Some("r1".into()),
+                        expires_at: Some(Utc::now() - ChronoDuration::seconds(5)),
+                        scope: None,
+                    },
+                },
+            )
+            .unwrap();
+
+        let token = store
+            .current(&Client::new(), "staging")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(token.access_token, "fresh");
+        assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+        assert!(!token.needs_refresh(Utc::now()));
+        assert_eq!(TokenStore::load(&dir).get("staging").unwrap().token, token);
+        assert_eq!(store.current(&Client::new(), "prod").await.unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn client_credentials_tokens_are_cached_until_config_changes() {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = server.accept().await {
+                let mut buf = [0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                assert!(
+                    String::from_utf8_lossy(&buf[..n]).contains("grant_type=client_credentials")
+                );
+                let hit = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let body = format!(r#"{{"access_token":"t{}","expires_in":3600}}"#, hit);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let dir = std::env::temp_dir().join(format!("restman-grant-{}", std::process::id()));
+        let store = TokenStore::load(&dir);
+        let mut config = OAuthConfig {
+            grant_type: GrantType::ClientCredentials,
+            token_url: format!("http://{}/token", addr),
+            client_id: "service".into(),
+            client_secret: Some("secret".into()),
+            ..OAuthConfig::default()
+        };
+        let client = Client::new();
+        assert_eq!(
+            store
+                .obtain(&client, "m2m", &config)
+                .await
+                .unwrap()
+                .access_token,
+            "t0"
+        );
+        assert_eq!(
+            store
+                .obtain(&client, "m2m", &config)
+                .await
+                .unwrap()
+                .access_token,
+            "t0"
+        );
+        config.scopes = vec!["read".into()];
+        assert_eq!(
+            store
+                .obtain(&client, "m2m", &config)
+                .await
+                .unwrap()
+                .access_token,
+            "t1"
+        );
+        assert!(fetch_token(&client, &OAuthConfig::default()).await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saves_tokens_privately_without_the_password() {
+        let dir = std::env::temp_dir().join(format!("restman-saved-{}", std::process::id()));
+        let store = TokenStore::load(&dir);
+        let config = OAuthConfig {
+            grant_type: GrantType::Password,
+            token_url: "http://localhost/token".into(),
+            client_id: "app".into(),
+            username: Some("ada".into()),
+            password: Some("hunter2".into()),
+            ..OAuthConfig::default()
+        };
+        let token = OAuthToken {
+            access_token: "t".into(),
+            token_type: "Bearer".into(),
+            refresh_token: None,
+            expires_at: None,
+            scope: None,
+        };
+        store
+            .insert(
+                "staging",
+                StoredToken {
+                    config: config.clone(),
+                    token,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(store.get("staging").unwrap().config, config);
+        let saved = std::fs::read_to_string(dir.join(TOKENS_FILE)).unwrap();
+        assert!(!saved.contains("hunter2"));
+        assert_eq!(
+            TokenStore::load(&dir)
+                .get("staging")
+                .unwrap()
+                .config
+                .password,
+            None
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(dir.join(TOKENS_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
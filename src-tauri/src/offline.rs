@@ -0,0 +1,188 @@
+use crate::http::{RequestInput, ResponseData};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+// How long a probe waits for a connection before calling its host unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// A request held back until its host can be reached. `scheme`, `host` and `port` come
+// from the URL as rendered when it was queued; the request itself is rendered again when
+// sent, with the environment as it is then.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueuedRequest {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub request: RequestInput,
+    #[serde(default)]
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    pub queued_at: DateTime<Utc>,
+    // Sends that failed because the connection dropped, and the last such error.
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+impl QueuedRequest {
+    pub fn new(id: String, name: String, request: RequestInput, url: &str) -> Result<Self, String> {
+        let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| format!("No host in {}", url))?
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_string();
+        let port = parsed
+            .port_or_known_default()
+            .ok_or_else(|| format!("No port for {}", url))?;
+        Ok(QueuedRequest {
+            id,
+            name,
+            request,
+            scheme: parsed.scheme().to_string(),
+            host,
+            port,
+            queued_at: Utc::now(),
+            attempts: 0,
+            last_error: None,
+        })
+    }
+
+    // Requests queued before the scheme was kept are taken to be HTTPS on port 443.
+    pub fn origin(&self) -> String {
+        let scheme = match self.scheme.as_str() {
+            "" if self.port == 443 => "https",
+            "" => "http",
+            scheme => scheme,
+        };
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        format!("{}://{}:{}/", scheme, host, self.port)
+    }
+}
+
+// Requests waiting in a workspace other than the active one. Only the active workspace's
+// queue is sent; these go out once their workspace is switched to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkspaceQueue {
+    pub workspace_id: String,
+    pub workspace_name: String,
+    pub count: usize,
+}
+
+// `online` is whether the last probe reached any queued request's host; `checked_at` is
+// None until something was probed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct NetworkStatus {
+    pub online: bool,
+    pub checked_at: Option<DateTime<Utc>>,
+    pub other_workspaces: Vec<WorkspaceQueue>,
+}
+
+// Payload of `queued-request-sent`: the response, or why sending failed for good.
+#[derive(Serialize, Clone, Debug)]
+pub struct QueuedRequestSent {
+    pub id: String,
+    pub name: String,
+    pub response: Option<ResponseData>,
+    pub error: Option<String>,
+}
+
+// A TCP connection is enough to tell the network is back; nothing is sent over it.
+pub async fn reachable(host: &str, port: u16) -> bool {
+    matches!(
+        timeout(PROBE_TIMEOUT, TcpStream::connect((host, port))).await,
+        Ok(Ok(_))
+    )
+}
+
+// Behind a proxy the host may not be reachable directly at all, so a HEAD request goes
+// through `client`'s proxy instead. A proxy answers 502 or 504 when it could not reach
+// the host either; any other answer means the request can go out.
+pub async fn reachable_through(client: &Client, origin: &str) -> bool {
+    match client.head(origin).timeout(PROBE_TIMEOUT).send().await {
+        Ok(response) => !matches!(response.status().as_u16(), 502 | 504),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn probes_the_queued_requests_host() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{}/orders?draft=1", port);
+        let queued = QueuedRequest::new(
+            "q1".into(),
+            "Create order".into(),
+            RequestInput::default(),
+            &url,
+        )
+        .unwrap();
+        assert_eq!((queued.host.as_str(), queued.port), ("127.0.0.1", port));
+        assert_eq!(queued.origin(), format!("http://127.0.0.1:{}/", port));
+        assert!(reachable(&queued.host, queued.port).await);
+
+        drop(listener);
+        assert!(!reachable(&queued.host, queued.port).await);
+
+        let secure = QueuedRequest::new(
+            String::new(),
+            String::new(),
+            RequestInput::default(),
+            "https://[::1]/",
+        )
+        .unwrap();
+        assert_eq!((secure.host.as_str(), secure.port), ("::1", 443));
+        assert_eq!(secure.origin(), "https://[::1]:443/");
+        assert!(QueuedRequest::new(
+            String::new(),
+            String::new(),
+            RequestInput::default(),
+            "/relative"
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn probes_through_the_proxy() {
+        // The proxy answers each probe with the next status, whatever the host.
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            for status in ["200 OK", "502 Bad Gateway"] {
+                let (mut stream, _) = proxy.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        let client = Client::builder()
+            .proxy(reqwest::Proxy::http(format!("http://{}", address)).unwrap())
+            .build()
+            .unwrap();
+        let origin = "http://only-via-proxy.invalid:80/";
+        assert!(reachable_through(&client, origin).await);
+        assert!(!reachable_through(&client, origin).await);
+        // Nothing answers any more.
+        assert!(!reachable_through(&client, origin).await);
+    }
+}
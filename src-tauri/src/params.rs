@@ -0,0 +1,399 @@
+use crate::sigv4::uri_encode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// A parameter value as typed by the user, serialized by the backend according to the
+// parameter's `style` and `explode` so arrays and objects reach the server in the
+// shape the spec describes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParameterValue {
+    pub name: String,
+    pub in_type: String,
+    pub value: Value,
+    #[serde(default)]
+    pub style: Option<String>,
+    #[serde(default)]
+    pub explode: Option<bool>,
+}
+
+enum Shape {
+    Primitive(String),
+    List(Vec<String>),
+    Object(Vec<(String, String)>),
+}
+
+fn primitive(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn shape(value: &Value) -> Shape {
+    match value {
+        Value::Array(items) => Shape::List(items.iter().map(primitive).collect()),
+        Value::Object(map) => Shape::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), primitive(value)))
+                .collect(),
+        ),
+        other => Shape::Primitive(primitive(other)),
+    }
+}
+
+impl ParameterValue {
+    fn style(&self) -> &str {
+        self.style
+            .as_deref()
+            .unwrap_or(match self.in_type.as_str() {
+                "query" | "cookie" => "form",
+                _ => "simple",
+            })
+    }
+
+    fn explode(&self) -> bool {
+        self.explode.unwrap_or(self.style() == "form")
+    }
+
+    // Optional parameters the user left empty are not sent at all.
+    fn is_empty(&self) -> bool {
+        match &self.value {
+            Value::Null => true,
+            Value::String(s) => s.is_empty(),
+            Value::Array(items) => items.is_empty(),
+            Value::Object(map) => map.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+// Path values are percent-encoded here, since the delimiters the style adds must stay
+// literal in the URL.
+pub fn serialize_path(param: &ParameterValue) -> String {
+    let explode = param.explode();
+    let (prefix, separator) = match param.style() {
+        "label" => (".".to_string(), if explode { "." } else { "," }),
+        "matrix" => (format!(";{}=", param.name), ","),
+        _ => (String::new(), ","),
+    };
+    match shape(&param.value) {
+        Shape::Primitive(value) => format!("{}{}", prefix, uri_encode(&value)),
+        Shape::List(items) => {
+            let items: Vec<String> = items.iter().map(|item| uri_encode(item)).collect();
+            if param.style() == "matrix" && explode {
+                items
+                    .iter()
+                    .map(|item| format!(";{}={}", param.name, item))
+                    .collect()
+            } else {
+                format!("{}{}", prefix, items.join(separator))
+            }
+        }
+        Shape::Object(pairs) => {
+            let pairs: Vec<(String, String)> = pairs
+                .iter()
+                .map(|(key, value)| (uri_encode(key), uri_encode(value)))
+                .collect();
+            match (param.style(), explode) {
+                ("matrix", true) => pairs
+                    .iter()
+                    .map(|(key, value)| format!(";{}={}", key, value))
+                    .collect(),
+                (_, true) => {
+                    let joined: Vec<String> = pairs
+                        .iter()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .collect();
+                    format!("{}{}", prefix, joined.join(separator))
+                }
+                (_, false) => {
+                    let flat: Vec<&str> = pairs
+                        .iter()
+                        .flat_map(|(key, value)| [key.as_str(), value.as_str()])
+                        .collect();
+                    format!("{}{}", prefix, flat.join(","))
+                }
+            }
+        }
+    }
+}
+
+// Returns unencoded pairs; the request builder encodes them for the query string.
+pub fn serialize_query(param: &ParameterValue) -> Vec<(String, String)> {
+    let name = param.name.clone();
+    let explode = param.explode();
+    let delimiter = match param.style() {
+        "spaceDelimited" => " ",
+        "pipeDelimited" => "|",
+        _ => ",",
+    };
+    match shape(&param.value) {
+        Shape::Primitive(value) => vec![(name, value)],
+        Shape::List(items) if explode => {
+            items.into_iter().map(|item| (name.clone(), item)).collect()
+        }
+        Shape::List(items) => vec![(name, items.join(delimiter))],
+        Shape::Object(pairs) if param.style() == "deepObject" => pairs
+            .into_iter()
+            .map(|(key, value)| (format!("{}[{}]", name, key), value))
+            .collect(),
+        Shape::Object(pairs) if explode => pairs,
+        Shape::Object(pairs) => {
+            let flat: Vec<String> = pairs
+                .into_iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect();
+            vec![(name, flat.join(delimiter))]
+        }
+    }
+}
+
+pub fn serialize_header(param: &ParameterValue) -> String {
+    match shape(&param.value) {
+        Shape::Primitive(value) => value,
+        Shape::List(items) => items.join(","),
+        Shape::Object(pairs) => {
+            let parts: Vec<String> = if param.explode() {
+                pairs
+                    .into_iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect()
+            } else {
+                pairs
+                    .into_iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect()
+            };
+            parts.join(",")
+        }
+    }
+}
+
+// Cookie values use the non-exploded form style, since a cookie cannot repeat.
+pub fn serialize_cookie(param: &ParameterValue) -> String {
+    let value = match shape(&param.value) {
+        Shape::Primitive(value) => value,
+        Shape::List(items) => items.join(","),
+        Shape::Object(pairs) => pairs
+            .into_iter()
+            .flat_map(|(key, value)| [key, value])
+            .collect::<Vec<_>>()
+            .join(","),
+    };
+    format!("{}={}", param.name, value)
+}
+
+// Joins two `Cookie` header values; cookies in `overrides` replace same-named ones
+// in `base`.
+pub fn merge_cookies(base: &str, overrides: &str) -> String {
+    let name = |pair: &str| pair.split('=').next().unwrap_or("").trim().to_string();
+    let split = |header: &str| -> Vec<String> {
+        header
+            .split(';')
+            .map(|pair| pair.trim().to_string())
+            .filter(|pair| !pair.is_empty())
+            .collect()
+    };
+    let overrides = split(overrides);
+    let names: Vec<String> = overrides.iter().map(|pair| name(pair)).collect();
+    let mut merged: Vec<String> = split(base)
+        .into_iter()
+        .filter(|pair| !names.contains(&name(pair)))
+        .collect();
+    merged.extend(overrides);
+    merged.join("; ")
+}
+
+pub fn cookie_header_name(headers: &HashMap<String, String>) -> Option<String> {
+    headers
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case("cookie"))
+        .cloned()
+}
+
+// Substitutes path parameters into the `{name}` placeholders of `url` and adds query,
+// header and cookie parameters to the request. Cookie parameters are merged into any
+// `Cookie` header already present.
+pub fn apply_parameters(
+    url: &str,
+    params: &[ParameterValue],
+    query: &mut Vec<(String, String)>,
+    headers: &mut HashMap<String, String>,
+) -> Result<String, String> {
+    let mut url = url.to_string();
+    let mut cookies = Vec::new();
+    for param in params {
+        match param.in_type.as_str() {
+            "path" => {
+                let placeholder = format!("{{{}}}", param.name);
+                if !url.contains(&placeholder) {
+                    continue;
+                }
+                if param.is_empty() {
+                    return Err(format!("Path parameter {} has no value", param.name));
+                }
+                url = url.replace(&placeholder, &serialize_path(param));
+            }
+            "query" if !param.is_empty() => query.extend(serialize_query(param)),
+            "header" if !param.is_empty() => {
+                headers.insert(param.name.clone(), serialize_header(param));
+            }
+            "cookie" if !param.is_empty() => cookies.push(serialize_cookie(param)),
+            _ => {}
+        }
+    }
+    if !cookies.is_empty() {
+        let existing = cookie_header_name(headers)
+            .and_then(|name| headers.remove(&name))
+            .unwrap_or_default();
+        headers.insert(
+            "Cookie".into(),
+            merge_cookies(&existing, &cookies.join("; ")),
+        );
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn param(
+        in_type: &str,
+        style: Option<&str>,
+        explode: Option<bool>,
+        value: Value,
+    ) -> ParameterValue {
+        ParameterValue {
+            name: "id".into(),
+            in_type: in_type.into(),
+            value,
+            style: style.map(|s| s.to_string()),
+            explode,
+        }
+    }
+
+    // Examples from the style table of the OpenAPI specification.
+    #[test]
+    fn serializes_path_styles() {
+        let list = json!([3, 4, 5]);
+        let object = json!({ "role": "admin", "firstName": "Alex" });
+        assert_eq!(serialize_path(&param("path", None, None, json!(5))), "5");
+        assert_eq!(
+            serialize_path(&param("path", None, None, list.clone())),
+            "3,4,5"
+        );
+        assert_eq!(
+            serialize_path(&param("path", None, Some(true), object.clone())),
+            "firstName=Alex,role=admin"
+        );
+        assert_eq!(
+            serialize_path(&param("path", Some("label"), Some(true), list.clone())),
+            ".3.4.5"
+        );
+        assert_eq!(
+            serialize_path(&param("path", Some("label"), None, object.clone())),
+            ".firstName,Alex,role,admin"
+        );
+        assert_eq!(
+            serialize_path(&param("path", Some("matrix"), None, list.clone())),
+            ";id=3,4,5"
+        );
+        assert_eq!(
+            serialize_path(&param("path", Some("matrix"), Some(true), list)),
+            ";id=3;id=4;id=5"
+        );
+        assert_eq!(
+            serialize_path(&param("path", Some("matrix"), Some(true), object)),
+            ";firstName=Alex;role=admin"
+        );
+        assert_eq!(
+            serialize_path(&param("path", None, None, json!("a/b c"))),
+            "a%2Fb%20c"
+        );
+    }
+
+    #[test]
+    fn serializes_query_styles() {
+        let list = json!(["a", "b"]);
+        let pairs = |items: &[(&str, &str)]| -> Vec<(String, String)> {
+            items
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            serialize_query(&param("query", None, None, list.clone())),
+            pairs(&[("id", "a"), ("id", "b")])
+        );
+        assert_eq!(
+            serialize_query(&param("query", None, Some(false), list.clone())),
+            pairs(&[("id", "a,b")])
+        );
+        assert_eq!(
+            serialize_query(&param("query", Some("pipeDelimited"), Some(false), list)),
+            pairs(&[("id", "a|b")])
+        );
+        assert_eq!(
+            serialize_query(&param(
+                "query",
+                Some("deepObject"),
+                Some(true),
+                json!({ "min": 1, "max": 5 })
+            )),
+            pairs(&[("id[max]", "5"), ("id[min]", "1")])
+        );
+    }
+
+    #[test]
+    fn applies_parameters_by_location() {
+        let params = vec![
+            param("path", None, None, json!([1, 2])),
+            ParameterValue {
+                name: "tags".into(),
+                ..param("query", None, None, json!(["x", "y"]))
+            },
+            ParameterValue {
+                name: "skip".into(),
+                ..param("query", None, None, json!(""))
+            },
+            ParameterValue {
+                name: "X-Ids".into(),
+                ..param("header", None, None, json!([7, 8]))
+            },
+        ];
+        let mut query = Vec::new();
+        let mut headers = HashMap::new();
+        let url = apply_parameters(
+            "https://api.test/items/{id}",
+            &params,
+            &mut query,
+            &mut headers,
+        )
+        .unwrap();
+        assert_eq!(url, "https://api.test/items/1,2");
+        assert_eq!(query.len(), 2);
+        assert_eq!(headers["X-Ids"], "7,8");
+
+        let cookies = vec![
+            ParameterValue {
+                name: "session".into(),
+                ..param("cookie", None, None, json!("abc"))
+            },
+            ParameterValue {
+                name: "ids".into(),
+                ..param("cookie", None, None, json!([1, 2]))
+            },
+        ];
+        headers.insert("cookie".into(), "theme=dark; session=old".into());
+        apply_parameters("/", &cookies, &mut query, &mut headers).unwrap();
+        assert_eq!(headers["Cookie"], "theme=dark; session=abc; ids=1,2");
+        assert!(!headers.contains_key("cookie"));
+
+        let missing = vec![param("path", None, None, Value::Null)];
+        assert!(apply_parameters("/items/{id}", &missing, &mut query, &mut headers).is_err());
+    }
+}
@@ -0,0 +1,843 @@
+use crate::auth::{ApiKeyLocation, Auth};
+use crate::environments::{Environment, Variable};
+use crate::http::{MultipartFile, MultipartPayload, RequestInput};
+use crate::ntlm::NtlmCredentials;
+use crate::params::apply_parameters;
+use crate::saved::{new_id, CollectionImport, SavedExample, SavedRequest};
+use crate::signing::{HawkCredentials, HmacAlgorithm};
+use crate::sigv4::AwsCredentials;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// The parts of a Postman v2.1 collection that map onto saved requests. Everything is
+// optional since exports from older Postman versions leave fields out freely.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct Collection {
+    info: Info,
+    item: Vec<Item>,
+    auth: Option<PostmanAuth>,
+    variable: Vec<KeyValue>,
+    event: Vec<Value>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct Info {
+    name: String,
+    schema: String,
+}
+
+// A request when `request` is set, otherwise a folder of further items.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct Item {
+    name: String,
+    item: Option<Vec<Item>>,
+    request: Option<PostmanRequest>,
+    response: Vec<Example>,
+    auth: Option<PostmanAuth>,
+    event: Vec<Value>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum PostmanRequest {
+    Url(String),
+    Full(Box<RequestDetails>),
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct RequestDetails {
+    method: Option<String>,
+    url: Option<PostmanUrl>,
+    header: Vec<KeyValue>,
+    body: Option<PostmanBody>,
+    auth: Option<PostmanAuth>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum PostmanUrl {
+    Raw(String),
+    Parts {
+        #[serde(default)]
+        raw: String,
+        #[serde(default)]
+        variable: Vec<KeyValue>,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct KeyValue {
+    key: String,
+    value: Value,
+    disabled: bool,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    src: Value,
+}
+
+impl KeyValue {
+    fn text(&self) -> String {
+        text(&self.value)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct PostmanBody {
+    mode: String,
+    raw: String,
+    urlencoded: Vec<KeyValue>,
+    formdata: Vec<KeyValue>,
+    file: Option<KeyValue>,
+    graphql: Option<Value>,
+    options: Option<Value>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+struct PostmanAuth {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(flatten)]
+    settings: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+struct Example {
+    name: String,
+    code: Option<u16>,
+    header: Vec<KeyValue>,
+    body: Option<String>,
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Postman keeps auth settings as a list of key/value pairs under the type's name.
+fn auth_setting(auth: &PostmanAuth, key: &str) -> String {
+    auth.settings
+        .get(&auth.kind)
+        .and_then(|settings| settings.as_array())
+        .and_then(|settings| {
+            settings
+                .iter()
+                .find(|setting| setting.get("key").and_then(Value::as_str) == Some(key))
+        })
+        .and_then(|setting| setting.get("value"))
+        .map(text)
+        .unwrap_or_default()
+}
+
+// `Ok(None)` is an explicit "no auth".
+fn convert_auth(auth: &PostmanAuth) -> Result<Option<Auth>, String> {
+    let setting = |key: &str| auth_setting(auth, key);
+    Ok(Some(match auth.kind.as_str() {
+        "noauth" => return Ok(None),
+        "basic" => Auth::Basic {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "bearer" => Auth::Bearer {
+            token: setting("token"),
+        },
+        "apikey" => Auth::ApiKey {
+            name: setting("key"),
+            value: setting("value"),
+            location: match setting("in").as_str() {
+                "query" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        },
+        "digest" => Auth::Digest {
+            username: setting("username"),
+            password: setting("password"),
+        },
+        "ntlm" => Auth::Ntlm(NtlmCredentials {
+            username: setting("username"),
+            password: setting("password"),
+            domain: setting("domain"),
+        }),
+        "awsv4" => Auth::AwsSigV4(AwsCredentials {
+            access_key_id: setting("accessKey"),
+            secret_access_key: setting("secretKey"),
+            session_token: Some(setting("sessionToken")).filter(|token| !token.is_empty()),
+            region: setting("region"),
+            service: setting("service"),
+        }),
+        "hawk" => Auth::Hawk(HawkCredentials {
+            id: setting("authId"),
+            key: setting("authKey"),
+            algorithm: match setting("algorithm").to_lowercase().as_str() {
+                "sha1" => HmacAlgorithm::Sha1,
+                _ => HmacAlgorithm::Sha256,
+            },
+            ext: Some(setting("extraData")).filter(|ext| !ext.is_empty()),
+            include_payload_hash: setting("includePayloadHash") == "true",
+        }),
+        other => return Err(format!("{} auth is not supported", other)),
+    }))
+}
+
+fn content_type_for(language: &str) -> Option<&'static str> {
+    match language {
+        "json" => Some("application/json"),
+        "xml" => Some("application/xml"),
+        "html" => Some("text/html"),
+        "javascript" => Some("application/javascript"),
+        "text" => Some("text/plain"),
+        _ => None,
+    }
+}
+
+fn apply_body(body: &PostmanBody, request: &mut RequestInput) -> Result<(), String> {
+    let enabled = |pairs: &[KeyValue]| -> Vec<KeyValue> {
+        pairs
+            .iter()
+            .filter(|pair| !pair.disabled)
+            .cloned()
+            .collect()
+    };
+    match body.mode.as_str() {
+        "raw" => {
+            if body.raw.is_empty() {
+                return Ok(());
+            }
+            request.body = Some(body.raw.clone());
+            let language = body
+                .options
+                .as_ref()
+                .and_then(|options| options.pointer("/raw/language"))
+                .and_then(Value::as_str)
+                .unwrap_or("text");
+            let has_content_type = request
+                .headers
+                .keys()
+                .any(|key| key.eq_ignore_ascii_case("content-type"));
+            if let (false, Some(content_type)) = (has_content_type, content_type_for(language)) {
+                request
+                    .headers
+                    .insert("Content-Type".into(), content_type.into());
+            }
+        }
+        "urlencoded" => {
+            request.form = Some(
+                enabled(&body.urlencoded)
+                    .iter()
+                    .map(|pair| (pair.key.clone(), pair.text()))
+                    .collect(),
+            )
+        }
+        "formdata" => {
+            let mut payload = MultipartPayload {
+                fields: HashMap::new(),
+                files: Vec::new(),
+            };
+            for pair in enabled(&body.formdata) {
+                if pair.kind.as_deref() == Some("file") {
+                    let paths = match &pair.src {
+                        Value::Array(paths) => paths.iter().map(text).collect(),
+                        src => vec![text(src)],
+                    };
+                    payload.files.push(MultipartFile {
+                        name: pair.key.clone(),
+                        paths: paths.into_iter().filter(|path| !path.is_empty()).collect(),
+                    });
+                } else {
+                    payload.fields.insert(pair.key.clone(), pair.text());
+                }
+            }
+            request.multipart = Some(payload);
+        }
+        "file" => {
+            request.body_file = body
+                .file
+                .as_ref()
+                .map(|file| text(&file.src))
+                .filter(|path| !path.is_empty())
+        }
+        "graphql" => {
+            let graphql = body.graphql.clone().unwrap_or_default();
+            let variables = graphql
+                .get("variables")
+                .and_then(Value::as_str)
+                .filter(|variables| !variables.trim().is_empty())
+                .map(|variables| {
+                    serde_json::from_str(variables)
+                        .map_err(|e| format!("GraphQL variables are not JSON: {}", e))
+                })
+                .transpose()?;
+            let mut payload =
+                serde_json::json!({ "query": graphql.get("query").cloned().unwrap_or_default() });
+            if let Some(variables) = variables {
+                payload["variables"] = variables;
+            }
+            request.body = Some(payload.to_string());
+        }
+        "" => {}
+        other => return Err(format!("{} bodies are not supported", other)),
+    }
+    Ok(())
+}
+
+// Path variables (`:id`) are filled in from the URL's variable list when they have a
+// value, and otherwise left for the user to edit.
+fn convert_url(url: &PostmanUrl) -> String {
+    match url {
+        PostmanUrl::Raw(raw) => raw.clone(),
+        PostmanUrl::Parts { raw, variable } => raw
+            .split('/')
+            .map(|segment| {
+                segment
+                    .strip_prefix(':')
+                    .and_then(|name| variable.iter().find(|v| v.key == name))
+                    .map(KeyValue::text)
+                    .filter(|value| !value.is_empty())
+                    .unwrap_or_else(|| segment.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join("/"),
+    }
+}
+
+struct Walker {
+    saved: Vec<SavedRequest>,
+    warnings: Vec<String>,
+}
+
+impl Walker {
+    fn walk(&mut self, items: &[Item], folder: &[String], auth: &Option<Auth>) {
+        for item in items {
+            let location = folder
+                .iter()
+                .chain(std::iter::once(&item.name))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !item.event.is_empty() {
+                self.warnings
+                    .push(format!("{}: scripts were not imported", location.join("/")));
+            }
+            let auth = match item.auth.as_ref().filter(|auth| auth.kind != "inherit") {
+                Some(own) => self.auth(own, &location),
+                None => auth.clone(),
+            };
+            match (&item.item, &item.request) {
+                (Some(children), _) => self.walk(children, &location, &auth),
+                (None, Some(request)) => self.request(item, request, folder, auth),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn auth(&mut self, auth: &PostmanAuth, location: &[String]) -> Option<Auth> {
+        convert_auth(auth).unwrap_or_else(|e| {
+            self.warnings.push(format!("{}: {}", location.join("/"), e));
+            None
+        })
+    }
+
+    fn request(
+        &mut self,
+        item: &Item,
+        request: &PostmanRequest,
+        folder: &[String],
+        inherited: Option<Auth>,
+    ) {
+        let name = if item.name.trim().is_empty() {
+            "Untitled request".to_string()
+        } else {
+            item.name.clone()
+        };
+        let location = folder
+            .iter()
+            .chain(std::iter::once(&name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut input = RequestInput {
+            method: "GET".into(),
+            auth: inherited,
+            ..RequestInput::default()
+        };
+        match request {
+            PostmanRequest::Url(url) => input.url = url.clone(),
+            PostmanRequest::Full(details) => {
+                if let Some(method) = &details.method {
+                    input.method = method.clone();
+                }
+                input.url = details.url.as_ref().map(convert_url).unwrap_or_default();
+                input.headers = details
+                    .header
+                    .iter()
+                    .filter(|header| !header.disabled)
+                    .map(|header| (header.key.clone(), header.text()))
+                    .collect();
+                if let Some(auth) = details.auth.as_ref().filter(|auth| auth.kind != "inherit") {
+                    input.auth = self.auth(auth, &location);
+                }
+                if let Some(body) = &details.body {
+                    if let Err(e) = apply_body(body, &mut input) {
+                        self.warnings.push(format!("{}: {}", location.join("/"), e));
+                    }
+                }
+            }
+        }
+        let now = Utc::now();
+        self.saved.push(SavedRequest {
+            id: new_id(),
+            name,
+            folder: folder.join("/"),
+            request: input,
+            examples: item
+                .response
+                .iter()
+                .map(|example| SavedExample {
+                    name: example.name.clone(),
+                    status: example.code,
+                    headers: example
+                        .header
+                        .iter()
+                        .map(|header| (header.key.clone(), header.text()))
+                        .collect(),
+                    body: example.body.clone(),
+                })
+                .collect(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+}
+
+// Collection variables become an environment named after the collection, since saved
+// requests resolve `{{name}}` from the active environment.
+pub fn import(content: &str) -> Result<CollectionImport, String> {
+    let collection: Collection =
+        serde_json::from_str(content).map_err(|e| format!("Not a Postman collection: {}", e))?;
+    if !collection.info.schema.is_empty() && !collection.info.schema.contains("v2.") {
+        return Err(format!(
+            "Unsupported Postman collection format: {}",
+            collection.info.schema
+        ));
+    }
+    let name = Some(collection.info.name.trim())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Postman collection")
+        .to_string();
+    let mut walker = Walker {
+        saved: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if !collection.event.is_empty() {
+        walker
+            .warnings
+            .push("Collection scripts were not imported".into());
+    }
+    let auth = collection
+        .auth
+        .as_ref()
+        .and_then(|auth| walker.auth(auth, std::slice::from_ref(&name)));
+    walker.walk(&collection.item, &[], &auth);
+    let environments = (!collection.variable.is_empty()).then(|| Environment {
+        name: name.clone(),
+        variables: collection
+            .variable
+            .iter()
+            .filter(|variable| !variable.key.trim().is_empty())
+            .map(|variable| Variable {
+                name: variable.key.clone(),
+                value: variable.text(),
+                enabled: !variable.disabled,
+                secret: variable.kind.as_deref() == Some("secret"),
+            })
+            .collect(),
+    });
+    Ok(CollectionImport {
+        name,
+        saved_requests: walker.saved,
+        environments: environments.into_iter().collect(),
+        warnings: walker.warnings,
+    })
+}
+
+// Applies the request's parameters to its URL, query and headers, which the other tools
+// have no equivalent for. Parameters that cannot be applied yet, such as a path
+// parameter without a value, are left out.
+pub fn flatten_params(request: &RequestInput) -> RequestInput {
+    let mut flat = request.clone();
+    if let Some(params) = flat.params.take() {
+        let mut query = flat.query.clone().unwrap_or_default();
+        let mut headers = flat.headers.clone();
+        if let Ok(url) = apply_parameters(&flat.url, &params, &mut query, &mut headers) {
+            flat.url = url;
+            flat.query = Some(query).filter(|query| !query.is_empty());
+            flat.headers = headers;
+        }
+    }
+    flat
+}
+
+fn language_for(content_type: &str) -> &'static str {
+    let content_type = content_type.to_ascii_lowercase();
+    ["json", "xml", "html", "javascript"]
+        .into_iter()
+        .find(|language| content_type.contains(language))
+        .unwrap_or("text")
+}
+
+fn pairs(pairs: &[(&str, String)]) -> Value {
+    Value::Array(
+        pairs
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "string" }))
+            .collect(),
+    )
+}
+
+// Auth that is resolved when the request is sent (OAuth, JWT, profiles) has no Postman
+// equivalent and is left out, as is generic HMAC signing.
+fn export_auth(auth: &Auth) -> Option<Value> {
+    let (kind, settings) = match auth {
+        Auth::Basic { username, password } => (
+            "basic",
+            vec![
+                ("username", username.clone()),
+                ("password", password.clone()),
+            ],
+        ),
+        Auth::Bearer { token } => ("bearer", vec![("token", token.clone())]),
+        Auth::ApiKey {
+            name,
+            value,
+            location,
+        } => (
+            "apikey",
+            vec![
+                ("key", name.clone()),
+                ("value", value.clone()),
+                (
+                    "in",
+                    match location {
+                        ApiKeyLocation::Query => "query".into(),
+                        ApiKeyLocation::Header | ApiKeyLocation::Cookie => "header".into(),
+                    },
+                ),
+            ],
+        ),
+        Auth::Digest { username, password } => (
+            "digest",
+            vec![
+                ("username", username.clone()),
+                ("password", password.clone()),
+            ],
+        ),
+        Auth::Ntlm(credentials) => (
+            "ntlm",
+            vec![
+                ("username", credentials.username.clone()),
+                ("password", credentials.password.clone()),
+                ("domain", credentials.domain.clone()),
+            ],
+        ),
+        Auth::AwsSigV4(credentials) => (
+            "awsv4",
+            vec![
+                ("accessKey", credentials.access_key_id.clone()),
+                ("secretKey", credentials.secret_access_key.clone()),
+                (
+                    "sessionToken",
+                    credentials.session_token.clone().unwrap_or_default(),
+                ),
+                ("region", credentials.region.clone()),
+                ("service", credentials.service.clone()),
+            ],
+        ),
+        Auth::Hawk(credentials) => (
+            "hawk",
+            vec![
+                ("authId", credentials.id.clone()),
+                ("authKey", credentials.key.clone()),
+                (
+                    "algorithm",
+                    match credentials.algorithm {
+                        HmacAlgorithm::Sha1 => "sha1".into(),
+                        _ => "sha256".into(),
+                    },
+                ),
+                ("extraData", credentials.ext.clone().unwrap_or_default()),
+            ],
+        ),
+        _ => return None,
+    };
+    Some(json!({ "type": kind, kind: pairs(&settings) }))
+}
+
+fn export_body(request: &RequestInput) -> Option<Value> {
+    if let Some(payload) = &request.multipart {
+        let mut fields: Vec<Value> = payload
+            .fields
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "text" }))
+            .collect();
+        fields.sort_by_key(|field| field["key"].as_str().unwrap_or_default().to_string());
+        fields.extend(payload.files.iter().map(|file| {
+            let src = match &file.paths[..] {
+                [path] => json!(path),
+                paths => json!(paths),
+            };
+            json!({ "key": file.name, "type": "file", "src": src })
+        }));
+        return Some(json!({ "mode": "formdata", "formdata": fields }));
+    }
+    if let Some(form) = &request.form {
+        let fields: Vec<Value> = form
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+        return Some(json!({ "mode": "urlencoded", "urlencoded": fields }));
+    }
+    if let Some(path) = request.body_file.as_deref().filter(|p| !p.is_empty()) {
+        return Some(json!({ "mode": "file", "file": { "src": path } }));
+    }
+    let body = request.body.as_deref().filter(|b| !b.is_empty())?;
+    let content_type = request
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str());
+    // Bodies without a content type go out as JSON, so they are marked as such.
+    let language = content_type.map_or("json", language_for);
+    Some(json!({
+        "mode": "raw",
+        "raw": body,
+        "options": { "raw": { "language": language } },
+    }))
+}
+
+fn export_request(saved: &SavedRequest) -> Value {
+    let request = flatten_params(&saved.request);
+    let mut raw = request.url.clone();
+    if let Some(query) = request.query.as_ref().filter(|query| !query.is_empty()) {
+        let query: Vec<String> = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        raw.push(if raw.contains('?') { '&' } else { '?' });
+        raw.push_str(&query.join("&"));
+    }
+    let mut headers: Vec<(&String, &String)> = request.headers.iter().collect();
+    headers.sort();
+    let mut details = json!({
+        "method": request.method,
+        "header": headers
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect::<Vec<_>>(),
+        "url": raw,
+    });
+    if let Some(body) = export_body(&request) {
+        details["body"] = body;
+    }
+    match request.auth.as_ref() {
+        Some(auth) => {
+            if let Some(auth) = export_auth(auth) {
+                details["auth"] = auth;
+            }
+        }
+        None => details["auth"] = json!({ "type": "noauth" }),
+    }
+    let responses: Vec<Value> = saved
+        .examples
+        .iter()
+        .map(|example| {
+            json!({
+                "name": example.name,
+                "code": example.status,
+                "header": example
+                    .headers
+                    .iter()
+                    .map(|(key, value)| json!({ "key": key, "value": value }))
+                    .collect::<Vec<_>>(),
+                "body": example.body,
+            })
+        })
+        .collect();
+    json!({ "name": saved.name, "request": details, "response": responses })
+}
+
+// Saved requests as a Postman v2.1 collection, one folder per segment of their folder
+// paths.
+pub fn export(name: &str, requests: &[SavedRequest]) -> Value {
+    let mut items: Vec<Value> = Vec::new();
+    for saved in requests {
+        let mut level = &mut items;
+        for segment in saved
+            .folder
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+        {
+            let index = match level
+                .iter()
+                .position(|item| item["name"] == segment && item.get("item").is_some())
+            {
+                Some(index) => index,
+                None => {
+                    level.push(json!({ "name": segment, "item": [] }));
+                    level.len() - 1
+                }
+            };
+            level = level[index]["item"].as_array_mut().unwrap();
+        }
+        level.push(export_request(saved));
+    }
+    json!({
+        "info": {
+            "_postman_id": new_id(),
+            "name": name,
+            "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json",
+        },
+        "item": items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_folders_auth_bodies_variables_and_examples() {
+        let content = r#"{
+            "info": {"name": "Shop", "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"},
+            "auth": {"type": "bearer", "bearer": [{"key": "token", "value": "{{token}}", "type": "string"}]},
+            "variable": [{"key": "base", "value": "https://api.shop.test"}, {"key": "token", "value": "t0k"}],
+            "item": [
+                {"name": "Orders", "item": [
+                    {"name": "Get order", "request": {
+                        "method": "GET",
+                        "url": {"raw": "{{base}}/orders/:id", "variable": [{"key": "id", "value": "42"}]},
+                        "header": [{"key": "Accept", "value": "application/json"}, {"key": "X-Off", "value": "1", "disabled": true}]
+                    }, "response": [{"name": "Found", "code": 200, "header": [{"key": "Content-Type", "value": "application/json"}], "body": "{\"id\": 42}"}]},
+                    {"name": "Create order", "request": {
+                        "method": "POST",
+                        "url": "{{base}}/orders",
+                        "auth": {"type": "noauth"},
+                        "body": {"mode": "raw", "raw": "<order/>", "options": {"raw": {"language": "xml"}}}
+                    }, "event": [{"listen": "test"}]}
+                ]},
+                {"name": "Login", "request": {
+                    "method": "POST",
+                    "url": "{{base}}/login",
+                    "auth": {"type": "oauth1"},
+                    "body": {"mode": "urlencoded", "urlencoded": [{"key": "user", "value": "ann"}]}
+                }}
+            ]
+        }"#;
+        let imported = import(content).unwrap();
+        assert_eq!(imported.name, "Shop");
+        let environment = &imported.environments[0];
+        assert_eq!(environment.values()["base"], "https://api.shop.test");
+
+        let [get, create, login] = &imported.saved_requests[..] else {
+            panic!("expected three requests");
+        };
+        assert_eq!(
+            (get.folder.as_str(), get.name.as_str()),
+            ("Orders", "Get order")
+        );
+        assert_eq!(get.request.url, "{{base}}/orders/42");
+        assert_eq!(get.request.headers.len(), 1);
+        assert_eq!(
+            get.request.auth,
+            Some(Auth::Bearer {
+                token: "{{token}}".into()
+            })
+        );
+        assert_eq!(get.examples[0].status, Some(200));
+        assert_eq!(get.examples[0].body.as_deref(), Some("{\"id\": 42}"));
+
+        assert_eq!(create.request.auth, None);
+        assert_eq!(create.request.body.as_deref(), Some("<order/>"));
+        assert_eq!(create.request.headers["Content-Type"], "application/xml");
+
+        assert_eq!(login.folder, "");
+        assert_eq!(
+            login.request.form,
+            Some(vec![("user".to_string(), "ann".to_string())])
+        );
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "Orders/Create order: scripts were not imported".to_string(),
+                "Login: oauth1 auth is not supported".to_string(),
+            ]
+        );
+        assert!(import(r#"{"info": {"schema": "collection/v1.0.0"}}"#).is_err());
+    }
+
+    #[test]
+    fn exported_collections_import_back() {
+        let saved = |name: &str, folder: &str, request: RequestInput| SavedRequest {
+            id: new_id(),
+            name: name.into(),
+            folder: folder.into(),
+            request,
+            examples: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let requests = vec![
+            saved(
+                "Get user",
+                "users/admin",
+                RequestInput {
+                    method: "GET".into(),
+                    url: "{{base}}/users/{id}".into(),
+                    query: Some(vec![("page".into(), "2".into())]),
+                    params: serde_json::from_str(
+                        r#"[{"name": "id", "in_type": "path", "value": 7}]"#,
+                    )
+                    .unwrap(),
+                    auth: Some(Auth::ApiKey {
+                        name: "X-Key".into(),
+                        value: "{{key}}".into(),
+                        location: ApiKeyLocation::Query,
+                    }),
+                    ..RequestInput::default()
+                },
+            ),
+            saved(
+                "Upload",
+                "users",
+                RequestInput {
+                    method: "POST".into(),
+                    url: "{{base}}/upload".into(),
+                    form: Some(vec![("a".into(), "1".into())]),
+                    ..RequestInput::default()
+                },
+            ),
+        ];
+        let exported = export("Team", &requests);
+        assert_eq!(exported["item"][0]["name"], "users");
+        assert_eq!(exported["item"][0]["item"][0]["name"], "admin");
+        assert_eq!(exported["item"][0]["item"][1]["name"], "Upload");
+
+        let imported = import(&exported.to_string()).unwrap();
+        let get = &imported.saved_requests[0];
+        assert_eq!(get.folder, "users/admin");
+        assert_eq!(get.request.url, "{{base}}/users/7?page=2");
+        assert_eq!(get.request.auth, requests[0].request.auth);
+        let upload = &imported.saved_requests[1];
+        assert_eq!(upload.request.form, requests[1].request.form);
+        assert_eq!(upload.request.auth, None);
+        assert!(imported.warnings.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::auth::Auth;
+use crate::secrets::{create_private_dir, write_private};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const PROFILES_FILE: &str = "auth_profiles.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuthProfile {
+    pub name: String,
+    pub auth: Auth,
+}
+
+// Named auth configurations that requests reference with `Auth::Profile`.
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: Mutex<Vec<AuthProfile>>,
+}
+
+impl ProfileStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(PROFILES_FILE);
+        let profiles = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        ProfileStore {
+            path,
+            profiles: Mutex::new(profiles),
+        }
+    }
+
+    fn save(&self, profiles: &[AuthProfile]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            create_private_dir(parent).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(profiles).map_err(|e| e.to_string())?;
+        write_private(&self.path, &content).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> Vec<AuthProfile> {
+        self.profiles.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<AuthProfile> {
+        self.profiles
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|profile| profile.name == name)
+            .cloned()
+    }
+
+    // Saving a profile under an existing name replaces it.
+    pub fn upsert(&self, profile: AuthProfile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("Auth profile name is required".into());
+        }
+        if matches!(profile.auth, Auth::Profile { .. }) {
+            return Err("An auth profile cannot reference another profile".into());
+        }
+        let mut profiles = self.profiles.lock().unwrap();
+        match profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => profiles.push(profile),
+        }
+        self.save(&profiles)
+    }
+
+    pub fn remove(&self, name: &str) -> Result<bool, String> {
+        let mut profiles = self.profiles.lock().unwrap();
+        let before = profiles.len();
+        profiles.retain(|profile| profile.name != name);
+        let removed = profiles.len() != before;
+        self.save(&profiles)?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upserts_and_persists_profiles() {
+        let dir = std::env::temp_dir().join(format!("restman-profiles-{}", std::process::id()));
+        let store = ProfileStore::load(&dir);
+        store
+            .upsert(AuthProfile {
+                name: "ci".into(),
+                auth: Auth::Bearer {
+                    token: "one".into(),
+                },
+            })
+            .unwrap();
+        store
+            .upsert(AuthProfile {
+                name: "ci".into(),
+                auth: Auth::Bearer {
+                    token: "two".into(),
+                },
+            })
+            .unwrap();
+        assert!(store
+            .upsert(AuthProfile {
+                name: "loop".into(),
+                auth: Auth::Profile { name: "ci".into() },
+            })
+            .is_err());
+
+        let reloaded = ProfileStore::load(&dir);
+        assert_eq!(reloaded.list().len(), 1);
+        assert_eq!(
+            reloaded.get("ci").unwrap().auth,
+            Auth::Bearer {
+                token: "two".into()
+            }
+        );
+        assert!(reloaded.remove("ci").unwrap());
+        assert!(!reloaded.remove("ci").unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,647 @@
+use prost_reflect::DescriptorPool;
+use prost_types::field_descriptor_proto::{Label, Type};
+use prost_types::{
+    DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+    FileDescriptorProto, MessageOptions, MethodDescriptorProto, OneofDescriptorProto,
+    ServiceDescriptorProto,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// A parser for the parts of `.proto` files that shape messages and services. Options,
+// reserved ranges and extensions are skipped; type names are left as written and
+// resolved by the descriptor pool.
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Symbol(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '"' | '\'' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                text.push(match escaped {
+                                    'n' => '\n',
+                                    't' => '\t',
+                                    other => other,
+                                });
+                            }
+                        }
+                        Some('\n') | None => {
+                            return Err(format!("line {}: unterminated string", line))
+                        }
+                        Some(other) => text.push(other),
+                    }
+                }
+                // Adjacent strings are one string, as in C.
+                match tokens.last_mut() {
+                    Some((Token::Text(previous), _)) => previous.push_str(&text),
+                    _ => tokens.push((Token::Text(text), line)),
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '.') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push((Token::Word(word), line));
+            }
+            c => tokens.push((Token::Symbol(c), line)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn scalar_type(name: &str) -> Option<Type> {
+    Some(match name {
+        "double" => Type::Double,
+        "float" => Type::Float,
+        "int64" => Type::Int64,
+        "uint64" => Type::Uint64,
+        "int32" => Type::Int32,
+        "fixed64" => Type::Fixed64,
+        "fixed32" => Type::Fixed32,
+        "bool" => Type::Bool,
+        "string" => Type::String,
+        "bytes" => Type::Bytes,
+        "uint32" => Type::Uint32,
+        "sfixed32" => Type::Sfixed32,
+        "sfixed64" => Type::Sfixed64,
+        "sint32" => Type::Sint32,
+        "sint64" => Type::Sint64,
+        _ => return None,
+    })
+}
+
+// `foo_bar` becomes `FooBarEntry`, the name protoc gives a map field's entry message.
+fn map_entry_name(field: &str) -> String {
+    let mut name = String::new();
+    let mut upper = true;
+    for c in field.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            name.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            name.push(c);
+        }
+    }
+    name + "Entry"
+}
+
+fn field_type(field: &mut FieldDescriptorProto, name: &str) {
+    match scalar_type(name) {
+        Some(scalar) => field.set_type(scalar),
+        None => field.type_name = Some(name.to_string()),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    proto3: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self
+            .tokens
+            .get(self.position.min(self.tokens.len().saturating_sub(1)))
+            .map(|(_, line)| *line)
+            .unwrap_or(1);
+        format!("line {}: {}", line, message)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end of file"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn at(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.at(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", symbol)))
+        }
+    }
+
+    fn word(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Word(word) => Ok(word),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a name"))
+            }
+        }
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        match self.next()? {
+            Token::Text(text) => Ok(text),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a string"))
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<i32, String> {
+        let negative = self.at('-');
+        if negative {
+            self.position += 1;
+        }
+        let word = match self.next()? {
+            Token::Word(word) => word,
+            _ => {
+                self.position -= 1;
+                return Err(self.error("expected a number"));
+            }
+        };
+        let value = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => word.parse::<i64>(),
+        }
+        .ok()
+        .and_then(|value| i32::try_from(if negative { -value } else { value }).ok())
+        .ok_or_else(|| self.error(&format!("`{}` is not a valid number", word)))?;
+        Ok(value)
+    }
+
+    // Skips to the end of a statement, stepping over any braces (aggregate options).
+    fn skip_statement(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => depth -= 1,
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                _ => {}
+            }
+            if depth == 0 && self.tokens[self.position - 1].0 == Token::Symbol('}') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_block(&mut self) -> Result<(), String> {
+        while !self.at('{') {
+            self.next()?;
+        }
+        self.skip_statement()
+    }
+
+    fn skip_field_options(&mut self) -> Result<(), String> {
+        if !self.at('[') {
+            return Ok(());
+        }
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol('[') => depth += 1,
+                Token::Symbol(']') if depth == 1 => return Ok(()),
+                Token::Symbol(']') => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn file(&mut self, name: &str) -> Result<FileDescriptorProto, String> {
+        let mut file = FileDescriptorProto {
+            name: Some(name.to_string()),
+            ..FileDescriptorProto::default()
+        };
+        while let Some(token) = self.peek().cloned() {
+            let Token::Word(keyword) = token else {
+                self.expect(';')?;
+                continue;
+            };
+            self.position += 1;
+            match keyword.as_str() {
+                "syntax" => {
+                    self.expect('=')?;
+                    let syntax = self.text()?;
+                    self.expect(';')?;
+                    match syntax.as_str() {
+                        "proto3" => self.proto3 = true,
+                        "proto2" => {}
+                        other => {
+                            return Err(self.error(&format!("{} syntax is not supported", other)))
+                        }
+                    }
+                    file.syntax = Some(syntax);
+                }
+                "edition" => return Err(self.error("editions are not supported")),
+                "package" => {
+                    file.package = Some(self.word()?);
+                    self.expect(';')?;
+                }
+                "import" => {
+                    if matches!(self.peek(), Some(Token::Word(w)) if w == "public" || w == "weak") {
+                        self.position += 1;
+                    }
+                    file.dependency.push(self.text()?);
+                    self.expect(';')?;
+                }
+                "option" => self.skip_statement()?,
+                "message" => file.message_type.push(self.message()?),
+                "enum" => file.enum_type.push(self.enumeration()?),
+                "service" => file.service.push(self.service()?),
+                "extend" => self.skip_block()?,
+                other => return Err(self.error(&format!("unexpected `{}`", other))),
+            }
+        }
+        Ok(file)
+    }
+
+    fn message(&mut self) -> Result<DescriptorProto, String> {
+        let mut message = DescriptorProto {
+            name: Some(self.word()?),
+            ..DescriptorProto::default()
+        };
+        self.expect('{')?;
+        while !self.at('}') {
+            let keyword = match self.next()? {
+                Token::Word(word) => word,
+                Token::Symbol(';') => continue,
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected a field"));
+                }
+            };
+            match keyword.as_str() {
+                "message" => message.nested_type.push(self.message()?),
+                "enum" => message.enum_type.push(self.enumeration()?),
+                "option" | "reserved" | "extensions" => self.skip_statement()?,
+                "extend" => self.skip_block()?,
+                "oneof" => {
+                    let index = message.oneof_decl.len() as i32;
+                    message.oneof_decl.push(OneofDescriptorProto {
+                        name: Some(self.word()?),
+                        ..OneofDescriptorProto::default()
+                    });
+                    self.expect('{')?;
+                    while !self.at('}') {
+                        let first = self.word()?;
+                        if first == "option" {
+                            self.skip_statement()?;
+                            continue;
+                        }
+                        let mut field = self.field(first, &mut message)?;
+                        field.oneof_index = Some(index);
+                        message.field.push(field);
+                    }
+                    self.expect('}')?;
+                }
+                _ => {
+                    let field = self.field(keyword, &mut message)?;
+                    message.field.push(field);
+                }
+            }
+        }
+        self.expect('}')?;
+        // proto3 `optional` fields each get a synthetic oneof, after the declared ones.
+        for field in message
+            .field
+            .iter_mut()
+            .filter(|f| f.proto3_optional == Some(true))
+        {
+            field.oneof_index = Some(message.oneof_decl.len() as i32);
+            message.oneof_decl.push(OneofDescriptorProto {
+                name: Some(format!("_{}", field.name())),
+                ..OneofDescriptorProto::default()
+            });
+        }
+        Ok(message)
+    }
+
+    // Parses a field whose first word has been read. Map fields add their entry
+    // message to `message`.
+    fn field(
+        &mut self,
+        first: String,
+        message: &mut DescriptorProto,
+    ) -> Result<FieldDescriptorProto, String> {
+        let mut field = FieldDescriptorProto::default();
+        field.set_label(Label::Optional);
+        let type_name = match first.as_str() {
+            "repeated" => {
+                field.set_label(Label::Repeated);
+                self.word()?
+            }
+            "required" => {
+                field.set_label(Label::Required);
+                self.word()?
+            }
+            "optional" => {
+                if self.proto3 {
+                    field.proto3_optional = Some(true);
+                }
+                self.word()?
+            }
+            _ => first,
+        };
+        if type_name == "group" {
+            return Err(self.error("groups are not supported"));
+        }
+        if type_name == "map" && self.at('<') {
+            self.expect('<')?;
+            let key = self.word()?;
+            self.expect(',')?;
+            let value = self.word()?;
+            self.expect('>')?;
+            let name = self.word()?;
+            let entry_name = map_entry_name(&name);
+            let entry_field = |name: &str, number: i32, type_name: &str| {
+                let mut field = FieldDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number),
+                    ..FieldDescriptorProto::default()
+                };
+                field.set_label(Label::Optional);
+                field_type(&mut field, type_name);
+                field
+            };
+            message.nested_type.push(DescriptorProto {
+                name: Some(entry_name.clone()),
+                field: vec![entry_field("key", 1, &key), entry_field("value", 2, &value)],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..MessageOptions::default()
+                }),
+                ..DescriptorProto::default()
+            });
+            field.set_label(Label::Repeated);
+            field.name = Some(name);
+            field.type_name = Some(entry_name);
+        } else {
+            field_type(&mut field, &type_name);
+            field.name = Some(self.word()?);
+        }
+        self.expect('=')?;
+        field.number = Some(self.number()?);
+        self.skip_field_options()?;
+        self.expect(';')?;
+        Ok(field)
+    }
+
+    fn enumeration(&mut self) -> Result<EnumDescriptorProto, String> {
+        let mut enumeration = EnumDescriptorProto {
+            name: Some(self.word()?),
+            ..EnumDescriptorProto::default()
+        };
+        self.expect('{')?;
+        while !self.at('}') {
+            if self.at(';') {
+                self.position += 1;
+                continue;
+            }
+            let name = self.word()?;
+            if name == "option" || name == "reserved" {
+                self.skip_statement()?;
+                continue;
+            }
+            self.expect('=')?;
+            let number = self.number()?;
+            self.skip_field_options()?;
+            self.expect(';')?;
+            enumeration.value.push(EnumValueDescriptorProto {
+                name: Some(name),
+                number: Some(number),
+                ..EnumValueDescriptorProto::default()
+            });
+        }
+        self.expect('}')?;
+        Ok(enumeration)
+    }
+
+    // `(stream Type)`, for either side of an rpc.
+    fn rpc_type(&mut self) -> Result<(String, bool), String> {
+        self.expect('(')?;
+        let mut name = self.word()?;
+        let streaming = name == "stream" && !self.at(')');
+        if streaming {
+            name = self.word()?;
+        }
+        self.expect(')')?;
+        Ok((name, streaming))
+    }
+
+    fn service(&mut self) -> Result<ServiceDescriptorProto, String> {
+        let mut service = ServiceDescriptorProto {
+            name: Some(self.word()?),
+            ..ServiceDescriptorProto::default()
+        };
+        self.expect('{')?;
+        while !self.at('}') {
+            match self.next()? {
+                Token::Symbol(';') => continue,
+                Token::Word(word) if word == "option" => self.skip_statement()?,
+                Token::Word(word) if word == "rpc" => {
+                    let name = self.word()?;
+                    let (input, client_streaming) = self.rpc_type()?;
+                    if self.word()? != "returns" {
+                        self.position -= 1;
+                        return Err(self.error("expected `returns`"));
+                    }
+                    let (output, server_streaming) = self.rpc_type()?;
+                    if self.at('{') {
+                        self.skip_statement()?;
+                    } else {
+                        self.expect(';')?;
+                    }
+                    service.method.push(MethodDescriptorProto {
+                        name: Some(name),
+                        input_type: Some(input),
+                        output_type: Some(output),
+                        client_streaming: Some(client_streaming),
+                        server_streaming: Some(server_streaming),
+                        ..MethodDescriptorProto::default()
+                    });
+                }
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected `rpc`"));
+                }
+            }
+        }
+        self.expect('}')?;
+        Ok(service)
+    }
+}
+
+// Parses one file; `name` is its path relative to an import directory, as imports
+// refer to it.
+pub fn parse(source: &str, name: &str) -> Result<FileDescriptorProto, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source).map_err(|e| format!("{}: {}", name, e))?,
+        position: 0,
+        proto3: false,
+    };
+    parser.file(name).map_err(|e| format!("{}: {}", name, e))
+}
+
+// Loads `files` and everything they import into a pool that also holds the well-known
+// types. Imports are looked up in `import_paths`, then in each file's own directory.
+pub fn load(files: &[PathBuf], import_paths: &[PathBuf]) -> Result<DescriptorPool, String> {
+    let mut pool = DescriptorPool::global();
+    let mut search: Vec<PathBuf> = import_paths.to_vec();
+    let mut pending = Vec::new();
+    for file in files {
+        let name = match search.iter().find_map(|dir| file.strip_prefix(dir).ok()) {
+            Some(relative) => relative.to_path_buf(),
+            None => {
+                search.push(file.parent().unwrap_or(Path::new(".")).to_path_buf());
+                PathBuf::from(file.file_name().unwrap_or_default())
+            }
+        };
+        pending.push(name.to_string_lossy().replace('\\', "/"));
+    }
+    let mut parsed: HashMap<String, FileDescriptorProto> = HashMap::new();
+    while let Some(name) = pending.pop() {
+        if parsed.contains_key(&name) || pool.get_file_by_name(&name).is_some() {
+            continue;
+        }
+        let path = search
+            .iter()
+            .map(|dir| dir.join(&name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("Cannot find {} in the import paths", name))?;
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let file = parse(&source, &name)?;
+        pending.extend(file.dependency.iter().cloned());
+        parsed.insert(name, file);
+    }
+    pool.add_file_descriptor_protos(parsed.into_values())
+        .map_err(|e| e.to_string())?;
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_messages_services_and_imports() {
+        let dir = std::env::temp_dir().join(format!("restman-proto-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("shop")).unwrap();
+        std::fs::write(
+            dir.join("shop").join("common.proto"),
+            "syntax = \"proto3\";\npackage shop;\nenum Status { STATUS_UNKNOWN = 0; ACTIVE = 1 [deprecated = true]; }\n",
+        )
+        .unwrap();
+        let orders = dir.join("shop").join("orders.proto");
+        std::fs::write(
+            &orders,
+            r#"
+            // Orders API
+            syntax = "proto3";
+            package shop.v1;
+            option go_package = "example.com/shop";
+            import "google/protobuf/timestamp.proto";
+            import "shop/common.proto";
+
+            message Order {
+              message Line { string sku = 1; int32 quantity = 2; }
+              string id = 1;
+              repeated Line lines = 2;
+              map<string, string> labels = 3;
+              shop.Status status = 4;
+              google.protobuf.Timestamp created_at = 5;
+              optional string note = 6;
+              oneof payment { string card = 7; string voucher = 8; }
+              reserved 9, 10;
+            }
+            message GetOrder { string id = 1; }
+            service Orders {
+              rpc Get (GetOrder) returns (Order) { option idempotency_level = NO_SIDE_EFFECTS; }
+              rpc Watch (GetOrder) returns (stream Order);
+            }
+            "#,
+        )
+        .unwrap();
+
+        let pool = load(&[orders], std::slice::from_ref(&dir)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        let service = pool.get_service_by_name("shop.v1.Orders").unwrap();
+        let watch = service.methods().nth(1).unwrap();
+        assert!(watch.is_server_streaming() && !watch.is_client_streaming());
+        let order = pool.get_message_by_name("shop.v1.Order").unwrap();
+        assert!(order.get_field_by_name("labels").unwrap().is_map());
+        assert_eq!(
+            order
+                .get_field_by_name("status")
+                .unwrap()
+                .kind()
+                .as_enum()
+                .unwrap()
+                .full_name(),
+            "shop.Status"
+        );
+        assert_eq!(
+            order
+                .oneofs()
+                .map(|o| o.name().to_string())
+                .collect::<Vec<_>>(),
+            ["payment", "_note"]
+        );
+        assert_eq!(
+            parse("message A { int32 x = }", "bad.proto").unwrap_err(),
+            "bad.proto: line 1: expected a number"
+        );
+    }
+}
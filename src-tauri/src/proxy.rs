@@ -0,0 +1,1236 @@
+use crate::body::{encode_preview, looks_binary, BodyEncoding};
+use crate::decompress::{decode_stream, parse_content_coding, ContentCoding};
+use crate::http::{RequestInput, ResponseData};
+use crate::secrets::{create_private_dir, write_private};
+use crate::timing::ResponseTiming;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use chrono::{Datelike, Duration as ChronoDuration, Utc};
+use futures_util::TryStreamExt;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+// The CA lives in its own directory that only the user can open.
+const CA_DIR: &str = "proxy-ca";
+const CA_CERT_FILE: &str = "proxy-ca.pem";
+const CA_KEY_FILE: &str = "proxy-ca-key.pem";
+// Kept short so a leaked key is not useful for long. A new CA, which has to be trusted
+// again, replaces one with less than `CA_RENEW_DAYS` left.
+const CA_VALIDITY_DAYS: i64 = 365;
+const CA_RENEW_DAYS: i64 = 30;
+const MAX_HEADER_LINES: usize = 200;
+// Longest request or header line, and largest request body, read off a connection.
+const MAX_LINE_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: u64 = 32 * 1024 * 1024;
+// Responses are passed on whole, but only this much of each body is kept in history.
+const MAX_RECORDED_BYTES: usize = 10 * 1024 * 1024;
+// Headers about this connection rather than the request, which the proxy does not pass on.
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "proxy-connection",
+    "keep-alive",
+    "proxy-authorization",
+    "proxy-authenticate",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+fn default_port() -> u16 {
+    8899
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProxyOptions {
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_true")]
+    pub record: bool,
+    // Answers requests from the newest recorded response to the same method and URL,
+    // without reaching the server; requests never seen are forwarded as usual.
+    #[serde(default)]
+    pub replay: bool,
+    // Decrypts HTTPS with certificates from the proxy's own CA, which clients must
+    // trust. Otherwise HTTPS passes through as an opaque tunnel and is not recorded.
+    #[serde(default)]
+    pub intercept_tls: bool,
+    // Only these hosts and their subdomains are recorded and replayed; empty means all.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+impl Default for ProxyOptions {
+    fn default() -> Self {
+        ProxyOptions {
+            port: default_port(),
+            record: true,
+            replay: false,
+            intercept_tls: false,
+            hosts: Vec::new(),
+        }
+    }
+}
+
+impl ProxyOptions {
+    fn watches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.hosts.is_empty()
+            || self.hosts.iter().any(|watched| {
+                let watched = watched.trim().to_ascii_lowercase();
+                host == watched || host.ends_with(&format!(".{}", watched))
+            })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ProxyInfo {
+    pub url: String,
+    pub options: ProxyOptions,
+    // The CA certificate to trust for intercepted HTTPS, when interception is on.
+    pub ca_certificate: Option<String>,
+}
+
+// One exchange through the proxy. Replayed ones were answered from a recording.
+pub struct Exchange {
+    pub request: RequestInput,
+    pub response: ResponseData,
+    pub replayed: bool,
+}
+
+pub type Recorder = Arc<dyn Fn(Exchange) + Send + Sync>;
+pub type Replayer = Arc<dyn Fn(&str, &str) -> Option<ResponseData> + Send + Sync>;
+
+// The proxy's CA, created on first use and kept in the data directory. Leaf
+// certificates are issued per host and cached for the life of the process.
+pub struct CertificateAuthority {
+    certificate: Certificate,
+    // As saved and trusted, sent along with each leaf.
+    pem: String,
+    key: KeyPair,
+    pub path: String,
+    leaves: Mutex<HashMap<String, native_tls::Identity>>,
+}
+
+fn ca_params() -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, "rest-man proxy CA");
+    name.push(DnType::OrganizationName, "rest-man");
+    params.distinguished_name = name;
+    // It only ever signs leaves.
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        KeyUsagePurpose::KeyCertSign,
+        KeyUsagePurpose::CrlSign,
+        KeyUsagePurpose::DigitalSignature,
+    ];
+    params
+}
+
+// From yesterday, which keeps clients with a slightly slow clock happy, for `days`.
+fn set_validity(params: &mut CertificateParams, days: i64) {
+    let date = |at: chrono::DateTime<Utc>| {
+        rcgen::date_time_ymd(at.year(), at.month() as u8, at.day() as u8)
+    };
+    params.not_before = date(Utc::now() - ChronoDuration::days(1));
+    params.not_after = date(Utc::now() + ChronoDuration::days(days));
+}
+
+// Whether the saved certificate, dated by its file, is within `CA_RENEW_DAYS` of expiring.
+fn expiring(cert_path: &Path) -> bool {
+    let written = std::fs::metadata(cert_path).and_then(|meta| meta.modified());
+    match written {
+        Ok(written) => {
+            let age = chrono::DateTime::<Utc>::from(written);
+            Utc::now() > age + ChronoDuration::days(CA_VALIDITY_DAYS - CA_RENEW_DAYS)
+        }
+        Err(_) => true,
+    }
+}
+
+impl CertificateAuthority {
+    pub fn load_or_create(data_dir: &Path) -> Result<CertificateAuthority, String> {
+        // Earlier versions kept the key next to everything else, readable by anyone; it
+        // is not reused.
+        let _ = std::fs::remove_file(data_dir.join(CA_KEY_FILE));
+        let _ = std::fs::remove_file(data_dir.join(CA_CERT_FILE));
+        let dir = data_dir.join(CA_DIR);
+        create_private_dir(&dir).map_err(|e| e.to_string())?;
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+        let saved = match (
+            std::fs::read_to_string(&key_path),
+            std::fs::read_to_string(&cert_path),
+        ) {
+            (Ok(key), Ok(pem)) if !expiring(&cert_path) => {
+                Some((KeyPair::from_pem(&key).map_err(|e| e.to_string())?, pem))
+            }
+            _ => None,
+        };
+        let (key, pem) = match saved {
+            Some(saved) => saved,
+            None => {
+                let key = KeyPair::generate().map_err(|e| e.to_string())?;
+                let mut params = ca_params();
+                set_validity(&mut params, CA_VALIDITY_DAYS);
+                let pem = params.self_signed(&key).map_err(|e| e.to_string())?.pem();
+                write_private(&key_path, &key.serialize_pem()).map_err(|e| e.to_string())?;
+                std::fs::write(&cert_path, &pem).map_err(|e| e.to_string())?;
+                (key, pem)
+            }
+        };
+        // Only the name and key of the issuer go into the certificates it signs, so
+        // re-signing the same parameters stands in for the saved certificate.
+        let certificate = ca_params().self_signed(&key).map_err(|e| e.to_string())?;
+        Ok(CertificateAuthority {
+            certificate,
+            pem,
+            key,
+            path: cert_path.to_string_lossy().into_owned(),
+            leaves: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn identity(&self, host: &str) -> Result<native_tls::Identity, String> {
+        if let Some(identity) = self.leaves.lock().unwrap().get(host) {
+            return Ok(identity.clone());
+        }
+        let mut params =
+            CertificateParams::new(vec![host.to_string()]).map_err(|e| e.to_string())?;
+        params.distinguished_name.push(DnType::CommonName, host);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        // Kept under a year, which clients accept even for locally trusted roots.
+        set_validity(&mut params, 365);
+        let key = KeyPair::generate().map_err(|e| e.to_string())?;
+        let leaf = params
+            .signed_by(&key, &self.certificate, &self.key)
+            .map_err(|e| e.to_string())?;
+        let chain = format!("{}{}", leaf.pem(), self.pem);
+        let identity =
+            native_tls::Identity::from_pkcs8(chain.as_bytes(), key.serialize_pem().as_bytes())
+                .map_err(|e| e.to_string())?;
+        self.leaves
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), identity.clone());
+        Ok(identity)
+    }
+}
+
+struct Context {
+    options: ProxyOptions,
+    authority: Option<Arc<CertificateAuthority>>,
+    client: reqwest::Client,
+    record: Recorder,
+    replay: Replayer,
+}
+
+pub struct Proxy {
+    info: ProxyInfo,
+    task: JoinHandle<()>,
+}
+
+impl Proxy {
+    // `authority` is required when the options ask for HTTPS interception.
+    pub async fn start(
+        options: ProxyOptions,
+        authority: Option<Arc<CertificateAuthority>>,
+        record: Recorder,
+        replay: Replayer,
+    ) -> Result<Proxy, String> {
+        let authority = authority.filter(|_| options.intercept_tls);
+        if options.intercept_tls && authority.is_none() {
+            return Err("HTTPS interception needs the proxy CA".into());
+        }
+        let listener = TcpListener::bind(("127.0.0.1", options.port))
+            .await
+            .map_err(|e| format!("Cannot start the proxy: {}", e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        // Clients speak HTTP/1.1 to the proxy, and protocol upgrades need it upstream too.
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .http1_only()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| e.to_string())?;
+        let info = ProxyInfo {
+            url: format!("http://127.0.0.1:{}", port),
+            options: ProxyOptions {
+                port,
+                ..options.clone()
+            },
+            ca_certificate: authority.as_ref().map(|ca| ca.path.clone()),
+        };
+        let context = Arc::new(Context {
+            options,
+            authority,
+            client,
+            record,
+            replay,
+        });
+        let task = tokio::spawn(async move {
+            // Dropped when the proxy stops, which aborts the connections still open.
+            let mut connections = JoinSet::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                while connections.try_join_next().is_some() {}
+                let context = context.clone();
+                connections.spawn(async move {
+                    let _ = handle(socket, &context).await;
+                });
+            }
+            while connections.join_next().await.is_some() {}
+        });
+        Ok(Proxy { info, task })
+    }
+
+    pub fn info(&self) -> ProxyInfo {
+        self.info.clone()
+    }
+
+    // Closes the listener and every connection through it.
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+pub struct Head {
+    pub method: String,
+    pub target: String,
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn io_error(e: std::io::Error) -> String {
+    e.to_string()
+}
+
+// Why a request could not be read off a connection. Requests over the limits are
+// answered with `status` before the connection is closed.
+#[derive(Debug, PartialEq)]
+pub enum ReadError {
+    TooLarge { status: u16, message: &'static str },
+    Failed(String),
+}
+
+impl From<ReadError> for String {
+    fn from(error: ReadError) -> String {
+        match error {
+            ReadError::TooLarge { message, .. } => message.to_string(),
+            ReadError::Failed(message) => message,
+        }
+    }
+}
+
+impl ReadError {
+    // Tells the client its request was too large; other failures leave nothing to say.
+    pub async fn answer<S: AsyncWrite + Unpin>(&self, stream: &mut S) {
+        if let ReadError::TooLarge { status, message } = self {
+            let _ = write_response(stream, *status, &[], message.as_bytes(), true).await;
+        }
+    }
+}
+
+fn read_failed(e: std::io::Error) -> ReadError {
+    ReadError::Failed(e.to_string())
+}
+
+const LINE_TOO_LONG: ReadError = ReadError::TooLarge {
+    status: 431,
+    message: "Request line or header too long",
+};
+const BODY_TOO_LARGE: ReadError = ReadError::TooLarge {
+    status: 413,
+    message: "Request body too large",
+};
+
+// Appends one line, newline included, to `line`; 0 at the end of the stream.
+async fn read_line<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    line: &mut String,
+) -> Result<usize, ReadError> {
+    let mut bytes = Vec::new();
+    let read = (&mut *stream)
+        .take(MAX_LINE_BYTES as u64 + 1)
+        .read_until(b'\n', &mut bytes)
+        .await
+        .map_err(read_failed)?;
+    if read > MAX_LINE_BYTES {
+        return Err(LINE_TOO_LONG);
+    }
+    line.push_str(&String::from_utf8_lossy(&bytes));
+    Ok(read)
+}
+
+// Appends exactly `length` bytes to `body`, as they arrive.
+async fn read_exactly<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    body: &mut Vec<u8>,
+    length: u64,
+) -> Result<(), ReadError> {
+    if body.len() as u64 + length > MAX_BODY_BYTES {
+        return Err(BODY_TOO_LARGE);
+    }
+    let read = (&mut *stream)
+        .take(length)
+        .read_to_end(body)
+        .await
+        .map_err(read_failed)?;
+    if (read as u64) < length {
+        return Err(ReadError::Failed("Connection closed mid-body".into()));
+    }
+    Ok(())
+}
+
+// Passes `result` on, first answering the client if its request was over the limits.
+pub async fn answered<T, S: AsyncWrite + Unpin>(
+    result: Result<T, ReadError>,
+    stream: &mut S,
+) -> Result<T, String> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(error) => {
+            error.answer(stream).await;
+            Err(error.into())
+        }
+    }
+}
+
+// None when the client closed the connection between requests.
+pub async fn read_head<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+) -> Result<Option<Head>, ReadError> {
+    let mut line = String::new();
+    if read_line(stream, &mut line).await? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ReadError::Failed(format!(
+            "Malformed request line: {}",
+            line.trim()
+        )));
+    };
+    let mut head = Head {
+        method: method.to_uppercase(),
+        target: target.to_string(),
+        version: version.to_string(),
+        headers: Vec::new(),
+    };
+    for _ in 0..MAX_HEADER_LINES {
+        line.clear();
+        read_line(stream, &mut line).await?;
+        match line.trim_end().split_once(':') {
+            Some((name, value)) => head
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string())),
+            None => return Ok(Some(head)),
+        }
+    }
+    Err(ReadError::TooLarge {
+        status: 431,
+        message: "Too many request headers",
+    })
+}
+
+pub async fn read_body<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    headers: &[(String, String)],
+) -> Result<Vec<u8>, ReadError> {
+    let mut body = Vec::new();
+    if header(headers, "transfer-encoding")
+        .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"))
+    {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            read_line(stream, &mut line).await?;
+            let size = line.trim().split(';').next().unwrap_or("");
+            let size = u64::from_str_radix(size, 16)
+                .map_err(|_| ReadError::Failed("Malformed chunked body".into()))?;
+            if size == 0 {
+                // Trailers, up to the blank line that ends the body.
+                loop {
+                    line.clear();
+                    if read_line(stream, &mut line).await? == 0 || line.trim().is_empty() {
+                        return Ok(body);
+                    }
+                }
+            }
+            read_exactly(stream, &mut body, size).await?;
+            line.clear();
+            read_line(stream, &mut line).await?;
+        }
+    }
+    if let Some(length) = header(headers, "content-length") {
+        let length = length
+            .parse::<u64>()
+            .map_err(|_| ReadError::Failed(format!("Malformed Content-Length: {}", length)))?;
+        read_exactly(stream, &mut body, length).await?;
+    }
+    Ok(body)
+}
+
+async fn write_head<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    headers: &[(String, String)],
+    close: bool,
+) -> Result<(), String> {
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("");
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await.map_err(io_error)
+}
+
+async fn write_response<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    headers: &[(String, String)],
+    body: &[u8],
+    close: bool,
+) -> Result<(), String> {
+    let mut headers = headers.to_vec();
+    headers.push(("Content-Length".to_string(), body.len().to_string()));
+    write_head(stream, status, &headers, close).await?;
+    stream.write_all(body).await.map_err(io_error)?;
+    stream.flush().await.map_err(io_error)
+}
+
+// The single reply of the mock server and webhook listener: open to any origin for CORS,
+// and the connection is closed after it.
+pub async fn write_reply<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    status: u16,
+    content_type: Option<&str>,
+    headers: &[(String, String)],
+    body: &[u8],
+) {
+    let mut all = vec![("Access-Control-Allow-Origin".to_string(), "*".to_string())];
+    if let Some(content_type) = content_type {
+        all.push(("Content-Type".to_string(), content_type.to_string()));
+    }
+    all.extend_from_slice(headers);
+    let _ = write_response(stream, status, &all, body, true).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn handle(socket: TcpStream, context: &Context) -> Result<(), String> {
+    let mut stream = BufReader::new(socket);
+    let head = read_head(&mut stream).await;
+    let Some(head) = answered(head, stream.get_mut()).await? else {
+        return Ok(());
+    };
+    if head.method != "CONNECT" {
+        return serve(stream, None, Some(head), context).await;
+    }
+    let authority = head.target.clone();
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => authority.as_str(),
+    }
+    .trim_matches(['[', ']'])
+    .to_string();
+    let intercept = context
+        .authority
+        .as_ref()
+        .filter(|_| context.options.watches(&host));
+    let mut client = stream.into_inner();
+    match intercept {
+        Some(authority_ca) => {
+            client
+                .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                .await
+                .map_err(io_error)?;
+            let acceptor = native_tls::TlsAcceptor::new(authority_ca.identity(&host)?)
+                .map_err(|e| e.to_string())?;
+            let tls = tokio_native_tls::TlsAcceptor::from(acceptor)
+                .accept(client)
+                .await
+                .map_err(|e| e.to_string())?;
+            let origin = format!(
+                "https://{}",
+                authority.strip_suffix(":443").unwrap_or(&authority)
+            );
+            serve(BufReader::new(tls), Some(origin), None, context).await
+        }
+        None => {
+            let mut upstream = match TcpStream::connect(&authority).await {
+                Ok(upstream) => upstream,
+                Err(e) => {
+                    let message = format!("Cannot reach {}: {}", authority, e);
+                    return write_response(&mut client, 502, &[], message.as_bytes(), true).await;
+                }
+            };
+            client
+                .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                .await
+                .map_err(io_error)?;
+            tokio::io::copy_bidirectional(&mut client, &mut upstream)
+                .await
+                .map_err(io_error)?;
+            Ok(())
+        }
+    }
+}
+
+// Serves requests on one client connection until either side closes it. `origin` is
+// set inside intercepted tunnels, where request targets are paths; plain proxy
+// requests carry the absolute URL.
+async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: BufReader<S>,
+    origin: Option<String>,
+    mut pending: Option<Head>,
+    context: &Context,
+) -> Result<(), String> {
+    loop {
+        let head = match pending.take() {
+            Some(head) => head,
+            None => {
+                let head = read_head(&mut stream).await;
+                match answered(head, stream.get_mut()).await? {
+                    Some(head) => head,
+                    None => return Ok(()),
+                }
+            }
+        };
+        let url = match &origin {
+            Some(origin) => format!("{}{}", origin, head.target),
+            None => head.target.clone(),
+        };
+        let close = head.version == "HTTP/1.0"
+            || header(&head.headers, "connection")
+                .is_some_and(|value| value.eq_ignore_ascii_case("close"))
+            || header(&head.headers, "proxy-connection")
+                .is_some_and(|value| value.eq_ignore_ascii_case("close"));
+        let body = read_body(&mut stream, &head.headers).await;
+        let body = answered(body, stream.get_mut()).await?;
+        let forwarded = match reqwest::Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {
+                exchange(context, &head, &parsed, body).await
+            }
+            _ => Forwarded::Ready(
+                400,
+                Vec::new(),
+                b"The proxy needs absolute http:// URLs, or CONNECT for HTTPS".to_vec(),
+            ),
+        };
+        match forwarded {
+            Forwarded::Ready(status, headers, body) => {
+                write_response(stream.get_mut(), status, &headers, &body, close).await?;
+            }
+            Forwarded::Upstream(upstream)
+                if upstream.response.status() == reqwest::StatusCode::SWITCHING_PROTOCOLS =>
+            {
+                return tunnel(stream, *upstream, context).await;
+            }
+            Forwarded::Upstream(upstream) => {
+                relay(
+                    stream.get_mut(),
+                    *upstream,
+                    head.method == "HEAD",
+                    close,
+                    context,
+                )
+                .await?;
+            }
+        }
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+// What to send back for one request.
+enum Forwarded {
+    // A replayed recording or the proxy's own error, as status, headers and body.
+    Ready(u16, Vec<(String, String)>, Vec<u8>),
+    // The server's response, passed on as it arrives.
+    Upstream(Box<Upstream>),
+}
+
+struct Upstream {
+    response: reqwest::Response,
+    // Set when the exchange is recorded.
+    request: Option<RequestInput>,
+    started: Instant,
+}
+
+// Passes the server's response on as it arrives, so event streams and long polls reach
+// the client, and records it once it ends with the first `MAX_RECORDED_BYTES` of its body.
+async fn relay<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    upstream: Upstream,
+    head_only: bool,
+    close: bool,
+    context: &Context,
+) -> Result<(), String> {
+    let Upstream {
+        mut response,
+        request,
+        started,
+    } = upstream;
+    let status = response.status().as_u16();
+    let headers = response_headers(&response);
+    let bodiless = head_only || status < 200 || status == 204 || status == 304;
+    let chunked = !bodiless && header(&headers, "content-length").is_none();
+    let mut sent = headers.clone();
+    if chunked {
+        sent.push(("Transfer-Encoding".to_string(), "chunked".to_string()));
+    }
+    write_head(stream, status, &sent, close).await?;
+    let mut kept = Vec::new();
+    let mut received = 0;
+    let relayed = if bodiless {
+        stream.flush().await.map_err(io_error)
+    } else {
+        copy_body(stream, &mut response, chunked, &mut kept, &mut received).await
+    };
+    if let Some(request) = request {
+        let mut response = captured_response(status, &headers, &kept, started.elapsed()).await;
+        // What arrived in all, when the recorded body was cut short.
+        response.encoded_size = received;
+        (context.record)(Exchange {
+            request,
+            response,
+            replayed: false,
+        });
+    }
+    relayed
+}
+
+async fn copy_body<S: AsyncWrite + Unpin>(
+    stream: &mut S,
+    response: &mut reqwest::Response,
+    chunked: bool,
+    kept: &mut Vec<u8>,
+    received: &mut u64,
+) -> Result<(), String> {
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Proxy lost the response: {}", e))?
+    {
+        if chunk.is_empty() {
+            continue;
+        }
+        *received += chunk.len() as u64;
+        let room = MAX_RECORDED_BYTES.saturating_sub(kept.len());
+        kept.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if chunked {
+            let size = format!("{:x}\r\n", chunk.len());
+            stream.write_all(size.as_bytes()).await.map_err(io_error)?;
+            stream.write_all(&chunk).await.map_err(io_error)?;
+            stream.write_all(b"\r\n").await.map_err(io_error)?;
+        } else {
+            stream.write_all(&chunk).await.map_err(io_error)?;
+        }
+        stream.flush().await.map_err(io_error)?;
+    }
+    if chunked {
+        stream.write_all(b"0\r\n\r\n").await.map_err(io_error)?;
+    }
+    stream.flush().await.map_err(io_error)
+}
+
+// Hands the connection over to the protocol the server switched to, such as WebSocket.
+// Only the handshake is recorded.
+async fn tunnel<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: BufReader<S>,
+    upstream: Upstream,
+    context: &Context,
+) -> Result<(), String> {
+    let Upstream {
+        response,
+        request,
+        started,
+    } = upstream;
+    let mut headers = response_headers(&response);
+    for name in ["connection", "upgrade"] {
+        if let Some(value) = response.headers().get(name) {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            headers.push((name.to_string(), value));
+        }
+    }
+    write_head(stream.get_mut(), 101, &headers, false).await?;
+    stream.get_mut().flush().await.map_err(io_error)?;
+    if let Some(request) = request {
+        let response = captured_response(101, &headers, &[], started.elapsed()).await;
+        (context.record)(Exchange {
+            request,
+            response,
+            replayed: false,
+        });
+    }
+    let mut upgraded = response.upgrade().await.map_err(|e| e.to_string())?;
+    tokio::io::copy_bidirectional(&mut stream, &mut upgraded)
+        .await
+        .map_err(io_error)?;
+    Ok(())
+}
+
+// The server's headers that are not about its connection to the proxy.
+fn response_headers(response: &reqwest::Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .filter(|(name, _)| passed_on(name))
+        .collect()
+}
+
+fn passed_on(name: &str) -> bool {
+    !HOP_BY_HOP.iter().any(|hop| name.eq_ignore_ascii_case(hop))
+}
+
+// A replayed recording, or the server's response to pass on and record. Requests to
+// switch protocols, such as WebSocket handshakes, are forwarded with their `Upgrade`.
+async fn exchange(context: &Context, head: &Head, url: &reqwest::Url, body: Vec<u8>) -> Forwarded {
+    let watched = context.options.watches(url.host_str().unwrap_or_default());
+    let request = captured_request(head, url, &body);
+    let upgrade = header(&head.headers, "upgrade").filter(|_| {
+        header(&head.headers, "connection")
+            .is_some_and(|value| value.to_ascii_lowercase().contains("upgrade"))
+    });
+    if watched && context.options.replay && upgrade.is_none() {
+        let recorded =
+            (context.replay)(&head.method, url.as_str()).filter(|recorded| recorded.status != 101);
+        if let Some(recorded) = recorded {
+            let (headers, body) = replayed(&recorded);
+            let status = recorded.status;
+            (context.record)(Exchange {
+                request,
+                response: recorded,
+                replayed: true,
+            });
+            return Forwarded::Ready(status, headers, body);
+        }
+    }
+    let Ok(method) = reqwest::Method::from_bytes(head.method.as_bytes()) else {
+        return Forwarded::Ready(405, Vec::new(), Vec::new());
+    };
+    let mut forwarded = context.client.request(method, url.clone());
+    for (name, value) in head
+        .headers
+        .iter()
+        .filter(|(name, _)| passed_on(name) && !name.eq_ignore_ascii_case("host"))
+    {
+        forwarded = forwarded.header(name, value);
+    }
+    if let Some(protocol) = upgrade {
+        forwarded = forwarded
+            .header("Connection", "upgrade")
+            .header("Upgrade", protocol);
+    }
+    let started = Instant::now();
+    match forwarded.body(body).send().await {
+        Ok(response) => Forwarded::Upstream(Box::new(Upstream {
+            response,
+            request: (watched && context.options.record).then_some(request),
+            started,
+        })),
+        Err(e) => Forwarded::Ready(
+            502,
+            Vec::new(),
+            format!("Proxy could not reach {}: {}", url, e).into_bytes(),
+        ),
+    }
+}
+
+fn captured_request(head: &Head, url: &reqwest::Url, body: &[u8]) -> RequestInput {
+    let content_type = header(&head.headers, "content-type");
+    RequestInput {
+        method: head.method.clone(),
+        url: url.to_string(),
+        headers: head
+            .headers
+            .iter()
+            .filter(|(name, _)| passed_on(name) && !name.eq_ignore_ascii_case("host"))
+            .cloned()
+            .collect(),
+        // Binary uploads are left out; history keeps text.
+        body: (!body.is_empty() && !looks_binary(content_type, body))
+            .then(|| String::from_utf8_lossy(body).into_owned()),
+        ..RequestInput::default()
+    }
+}
+
+// Decoded the way the app decodes its own responses, so history shows the text.
+async fn captured_response(
+    status: u16,
+    headers: &[(String, String)],
+    bytes: &[u8],
+    elapsed: std::time::Duration,
+) -> ResponseData {
+    let content_type = header(headers, "content-type").map(String::from);
+    let content_encoding = header(headers, "content-encoding").map(String::from);
+    let coding = parse_content_coding(content_encoding.as_deref());
+    let raw = Bytes::copy_from_slice(bytes);
+    let decoded = match coding {
+        Some(coding) if coding != ContentCoding::Identity => {
+            let stream = futures_util::stream::once(async move { Ok::<_, std::io::Error>(raw) });
+            decode_stream(stream, Some(coding), Arc::new(AtomicU64::new(0)))
+                .try_fold(Vec::new(), |mut all, chunk| async move {
+                    all.extend_from_slice(&chunk);
+                    Ok(all)
+                })
+                .await
+                .ok()
+        }
+        _ => None,
+    };
+    let decompressed = decoded.is_some();
+    let body = decoded.unwrap_or_else(|| bytes.to_vec());
+    let (text, body_encoding) = if looks_binary(content_type.as_deref(), &body) {
+        encode_preview(&body)
+    } else {
+        (
+            String::from_utf8_lossy(&body).into_owned(),
+            BodyEncoding::Text,
+        )
+    };
+    let mut collected: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, value) in headers {
+        collected
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .push(value.clone());
+    }
+    let elapsed_ms = elapsed.as_millis() as u64;
+    ResponseData {
+        status,
+        status_text: reqwest::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("")
+            .to_string(),
+        http_version: "HTTP/1.1".into(),
+        headers: collected,
+        header_list: headers.to_vec(),
+        body: text,
+        body_encoding,
+        body_path: None,
+        content_type,
+        elapsed_ms,
+        size: body.len() as u64,
+        encoded_size: bytes.len() as u64,
+        content_encoding,
+        decompressed,
+        timing: ResponseTiming {
+            total_ms: elapsed_ms as f64,
+            ..ResponseTiming::default()
+        },
+        ..ResponseData::default()
+    }
+}
+
+// Recordings hold decoded bodies, so the encoding and length headers of the original
+// response no longer apply.
+fn replayed(recorded: &ResponseData) -> (Vec<(String, String)>, Vec<u8>) {
+    let headers = recorded
+        .header_pairs()
+        .into_iter()
+        .filter(|(name, _)| {
+            passed_on(name)
+                && !name.eq_ignore_ascii_case("content-length")
+                && !name.eq_ignore_ascii_case("content-encoding")
+        })
+        .collect();
+    let body = match recorded.body_encoding {
+        BodyEncoding::Text => recorded.body.clone().into_bytes(),
+        BodyEncoding::Base64 => STANDARD.decode(&recorded.body).unwrap_or_default(),
+        BodyEncoding::None => Vec::new(),
+    };
+    (headers, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_oversized_requests_without_reading_them() {
+        let head = |text: &str| Head {
+            method: "POST".into(),
+            target: "/".into(),
+            version: "HTTP/1.1".into(),
+            headers: text
+                .lines()
+                .filter_map(|line| line.split_once(": "))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let huge = head("Content-Length: 18446744073709551615");
+        let mut stream = BufReader::new(&b""[..]);
+        assert_eq!(
+            read_body(&mut stream, &huge.headers).await,
+            Err(BODY_TOO_LARGE)
+        );
+        let chunked = head("Transfer-Encoding: chunked");
+        let mut stream = BufReader::new(&b"ffffffffff\r\n"[..]);
+        assert_eq!(
+            read_body(&mut stream, &chunked.headers).await,
+            Err(BODY_TOO_LARGE)
+        );
+        let mut stream = BufReader::new(&b"4\r\nabcd\r\n0\r\n\r\n"[..]);
+        assert_eq!(
+            read_body(&mut stream, &chunked.headers).await.unwrap(),
+            b"abcd"
+        );
+
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_BYTES));
+        let mut stream = BufReader::new(long.as_bytes());
+        assert_eq!(read_head(&mut stream).await.err(), Some(LINE_TOO_LONG));
+
+        let mut answer = Vec::new();
+        BODY_TOO_LARGE.answer(&mut answer).await;
+        assert!(String::from_utf8(answer)
+            .unwrap()
+            .starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    }
+
+    async fn origin_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(socket);
+                    while let Ok(Some(head)) = read_head(&mut stream).await {
+                        let body = read_body(&mut stream, &head.headers).await.unwrap();
+                        let reply = format!(
+                            "{} {} {}",
+                            head.method,
+                            head.target,
+                            String::from_utf8_lossy(&body)
+                        );
+                        let headers = [("Content-Type".to_string(), "text/plain".to_string())];
+                        write_response(stream.get_mut(), 201, &headers, reply.as_bytes(), false)
+                            .await
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        format!("http://127.0.0.1:{}", port)
+    }
+
+    #[tokio::test]
+    async fn forwards_records_and_replays_plain_http() {
+        let origin = origin_server().await;
+        let recorded: Arc<Mutex<Vec<Exchange>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = recorded.clone();
+        let record: Recorder = Arc::new(move |exchange| sink.lock().unwrap().push(exchange));
+        let source = recorded.clone();
+        let replay: Replayer = Arc::new(move |method, url| {
+            let exchanges = source.lock().unwrap();
+            exchanges
+                .iter()
+                .rev()
+                .find(|e| !e.replayed && e.request.method == method && e.request.url == url)
+                .map(|e| e.response.clone())
+        });
+        let options = ProxyOptions {
+            port: 0,
+            ..ProxyOptions::default()
+        };
+        let proxy = Proxy::start(options, None, record.clone(), replay.clone())
+            .await
+            .unwrap();
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(proxy.info().url).unwrap())
+            .build()
+            .unwrap();
+
+        let response = client
+            .post(format!("{}/items?x=1", origin))
+            .body("{\"a\":1}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        assert_eq!(response.text().await.unwrap(), "POST /items?x=1 {\"a\":1}");
+        {
+            let exchanges = recorded.lock().unwrap();
+            let exchange = &exchanges[0];
+            assert_eq!(exchange.request.url, format!("{}/items?x=1", origin));
+            assert_eq!(exchange.request.body.as_deref(), Some("{\"a\":1}"));
+            assert_eq!(exchange.response.body, "POST /items?x=1 {\"a\":1}");
+            assert_eq!(
+                exchange.response.content_type.as_deref(),
+                Some("text/plain")
+            );
+        }
+        proxy.stop();
+
+        // Replaying answers from the recording even once the origin has gone quiet.
+        let options = ProxyOptions {
+            port: 0,
+            replay: true,
+            hosts: vec!["127.0.0.1".into()],
+            ..ProxyOptions::default()
+        };
+        let proxy = Proxy::start(options, None, record, replay).await.unwrap();
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(proxy.info().url).unwrap())
+            .build()
+            .unwrap();
+        let response = client
+            .post(format!("{}/items?x=1", origin))
+            .body("other")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "POST /items?x=1 {\"a\":1}");
+        assert!(recorded.lock().unwrap()[1].replayed);
+        proxy.stop();
+
+        let data_dir = std::env::temp_dir().join(format!("restman-ca-{}", rand::random::<u32>()));
+        let authority = CertificateAuthority::load_or_create(&data_dir).unwrap();
+        assert!(authority.identity("api.test").is_ok());
+        let saved = std::fs::read_to_string(&authority.path).unwrap();
+        assert!(saved.starts_with("-----BEGIN CERTIFICATE-----"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&data_dir.join(CA_DIR)), 0o700);
+            assert_eq!(mode(&data_dir.join(CA_DIR).join(CA_KEY_FILE)), 0o600);
+        }
+        // A second start keeps the CA the user already trusts.
+        let reloaded = CertificateAuthority::load_or_create(&data_dir).unwrap();
+        assert_eq!(reloaded.pem, saved);
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[tokio::test]
+    async fn streams_responses_and_tunnels_upgrades() {
+        // Sends one event and holds the stream open until told to finish; switches
+        // `/ws` to a protocol that echoes what it is sent.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+        let finished = Arc::new(Mutex::new(Some(finished)));
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let finished = finished.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(socket);
+                    let Ok(Some(head)) = read_head(&mut stream).await else {
+                        return;
+                    };
+                    if head.target == "/ws" {
+                        let reply = "HTTP/1.1 101 Switching Protocols\r\n\
+                                     Connection: Upgrade\r\nUpgrade: echo\r\n\r\n";
+                        stream.write_all(reply.as_bytes()).await.unwrap();
+                        let mut ping = [0u8; 4];
+                        stream.read_exact(&mut ping).await.unwrap();
+                        stream.write_all(&ping).await.unwrap();
+                        return;
+                    }
+                    let reply = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                                 Transfer-Encoding: chunked\r\n\r\n9\r\ndata: 1\n\n\r\n";
+                    stream.write_all(reply.as_bytes()).await.unwrap();
+                    let finished = finished.lock().unwrap().take().unwrap();
+                    let _ = finished.await;
+                    stream
+                        .write_all(b"9\r\ndata: 2\n\n\r\n0\r\n\r\n")
+                        .await
+                        .unwrap();
+                });
+            }
+        });
+        let recorded: Arc<Mutex<Vec<Exchange>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = recorded.clone();
+        let options = ProxyOptions {
+            port: 0,
+            ..ProxyOptions::default()
+        };
+        let proxy = Proxy::start(
+            options,
+            None,
+            Arc::new(move |exchange| sink.lock().unwrap().push(exchange)),
+            Arc::new(|_: &str, _: &str| None),
+        )
+        .await
+        .unwrap();
+
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::http(proxy.info().url).unwrap())
+            .build()
+            .unwrap();
+        let mut events = client
+            .get(format!("{}/events", origin))
+            .send()
+            .await
+            .unwrap();
+        // The first event arrives while the server still holds the response open.
+        assert_eq!(&events.chunk().await.unwrap().unwrap()[..], b"data: 1\n\n");
+        assert!(recorded.lock().unwrap().is_empty());
+        finish.send(()).unwrap();
+        assert_eq!(&events.chunk().await.unwrap().unwrap()[..], b"data: 2\n\n");
+        assert!(events.chunk().await.unwrap().is_none());
+        assert_eq!(
+            recorded.lock().unwrap()[0].response.body,
+            "data: 1\n\ndata: 2\n\n"
+        );
+
+        let address = proxy.info().url.replace("http://", "");
+        let mut socket = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let handshake = format!(
+            "GET {}/ws HTTP/1.1\r\nHost: origin\r\nConnection: Upgrade\r\nUpgrade: echo\r\n\r\n",
+            origin
+        );
+        socket.write_all(handshake.as_bytes()).await.unwrap();
+        let mut status = String::new();
+        socket.read_line(&mut status).await.unwrap();
+        assert!(status.starts_with("HTTP/1.1 101"));
+        let mut line = String::new();
+        while socket.read_line(&mut line).await.unwrap() > 2 {
+            line.clear();
+        }
+        socket.write_all(b"ping").await.unwrap();
+        let mut echoed = [0u8; 4];
+        socket.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(&echoed, b"ping");
+        assert_eq!(recorded.lock().unwrap()[1].response.status, 101);
+        proxy.stop();
+    }
+}
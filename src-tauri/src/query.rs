@@ -0,0 +1,116 @@
+use crate::jmespath;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryLanguage {
+    #[default]
+    JsonPath,
+    JmesPath,
+}
+
+// A stored response body: the response of a history entry, or a response file the app
+// wrote (a response's `body_path`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "from", rename_all = "snake_case")]
+pub enum BodySource {
+    History { id: i64 },
+    File { path: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryMatch {
+    // Normalized path of a JSONPath match, e.g. `$['items'][0]`. JMESPath results have
+    // none.
+    pub path: Option<String>,
+    pub value: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryResult {
+    pub matches: Vec<QueryMatch>,
+    // Matches before `limit` was applied.
+    pub total: usize,
+}
+
+pub fn parse_body(body: &str) -> Result<Value, String> {
+    serde_json::from_str(body).map_err(|e| format!("Response body is not JSON: {}", e))
+}
+
+// Parses straight from the file so the text is never held in memory next to the value.
+pub fn read_body(path: &Path) -> Result<Value, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Response body is not JSON: {}", e))
+}
+
+// JSONPath returns every node it selects; JMESPath returns its single result, unless
+// that is null.
+pub fn query(
+    body: &Value,
+    language: QueryLanguage,
+    expression: &str,
+    limit: usize,
+) -> Result<QueryResult, String> {
+    let matches: Vec<QueryMatch> = match language {
+        QueryLanguage::JsonPath => {
+            let path =
+                JsonPath::parse(expression).map_err(|e| format!("Invalid JSONPath: {}", e))?;
+            path.query_located(body)
+                .into_iter()
+                .map(|node| QueryMatch {
+                    path: Some(node.location().to_string()),
+                    value: node.node().clone(),
+                })
+                .collect()
+        }
+        QueryLanguage::JmesPath => {
+            let value = jmespath::search(expression, body)
+                .map_err(|e| format!("Invalid JMESPath: {}", e))?;
+            (!value.is_null())
+                .then_some(QueryMatch { path: None, value })
+                .into_iter()
+                .collect()
+        }
+    };
+    let total = matches.len();
+    Ok(QueryResult {
+        matches: matches.into_iter().take(limit).collect(),
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn queries_with_either_language() {
+        let body = parse_body(r#"{"items": [{"id": 1}, {"id": 2}, {"id": 3}]}"#).unwrap();
+        let result = query(&body, QueryLanguage::JsonPath, "$.items[*].id", 2).unwrap();
+        assert_eq!(result.total, 3);
+        assert_eq!(result.matches.len(), 2);
+        assert_eq!(
+            result.matches[1].path.as_deref(),
+            Some("$['items'][1]['id']")
+        );
+        assert_eq!(result.matches[1].value, json!(2));
+
+        let result = query(&body, QueryLanguage::JmesPath, "items[?id > `1`].id", 10).unwrap();
+        assert_eq!(result.matches[0].value, json!([2, 3]));
+        assert_eq!(
+            query(&body, QueryLanguage::JmesPath, "nothing", 10)
+                .unwrap()
+                .total,
+            0
+        );
+        assert!(query(&body, QueryLanguage::JsonPath, "items", 10).is_err());
+        assert!(parse_body("<html>").is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::client::host_matches;
+use crate::retry::parse_retry_after;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{sleep, Duration, Instant};
+
+fn default_burst() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: f64,
+    // Requests let through back to back before the rate applies.
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+// `host` is a pattern as for client certificates, e.g. "*.staging.example.com".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HostRateLimit {
+    pub host: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    // Shared by every request, whatever its host.
+    pub global: Option<RateLimit>,
+    // Applied to each host on its own, unless one of `hosts` matches it.
+    pub per_host: Option<RateLimit>,
+    pub hosts: Vec<HostRateLimit>,
+}
+
+// Sent while a request waits its turn.
+#[derive(Serialize, Clone, Debug)]
+pub struct RateLimitWait {
+    pub request_id: Option<String>,
+    pub url: String,
+    pub wait_ms: u64,
+}
+
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        let capacity = limit.burst.max(1) as f64;
+        Bucket {
+            rate: limit.requests_per_second,
+            capacity,
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    // Takes a token, running into debt when none is left so requests queue up in the
+    // order they asked. Returns how long until the token is really there.
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+
+    fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(self.capacity);
+    }
+}
+
+// The buckets a token was taken from: the global one, and a host's by its key.
+type Reserved = Vec<Option<String>>;
+
+#[derive(Default)]
+struct Limits {
+    settings: RateLimitSettings,
+    global: Option<Bucket>,
+    hosts: HashMap<String, Bucket>,
+}
+
+#[derive(Default)]
+pub struct RateLimiter {
+    limits: Mutex<Limits>,
+}
+
+fn validate(limit: &RateLimit) -> Result<(), String> {
+    if limit.requests_per_second.is_finite() && limit.requests_per_second > 0.0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid rate limit: {} requests per second",
+            limit.requests_per_second
+        ))
+    }
+}
+
+impl RateLimiter {
+    // Buckets start full again, so new limits apply from the next request.
+    pub fn configure(&self, settings: &RateLimitSettings) -> Result<(), String> {
+        for limit in settings
+            .global
+            .iter()
+            .chain(settings.per_host.iter())
+            .chain(settings.hosts.iter().map(|host| &host.limit))
+        {
+            validate(limit)?;
+        }
+        *self.limits.lock().unwrap() = Limits {
+            settings: settings.clone(),
+            ..Limits::default()
+        };
+        Ok(())
+    }
+
+    fn reserve(&self, url: &str, now: Instant) -> (Duration, Reserved) {
+        let mut limits = self.limits.lock().unwrap();
+        let Limits {
+            settings,
+            global,
+            hosts,
+        } = &mut *limits;
+        let mut wait = Duration::ZERO;
+        let mut reserved = Vec::new();
+        if let Some(limit) = &settings.global {
+            let bucket = global.get_or_insert_with(|| Bucket::new(limit, now));
+            wait = wait.max(bucket.reserve(now));
+            reserved.push(None);
+        }
+        let host = Url::parse(url).ok().and_then(|url| {
+            let host = url.host_str()?.to_ascii_lowercase();
+            Some((url.port_or_known_default(), host))
+        });
+        if let Some((port, host)) = host {
+            let limit = settings
+                .hosts
+                .iter()
+                .find(|entry| host_matches(&entry.host, &host))
+                .map(|entry| &entry.limit)
+                .or(settings.per_host.as_ref());
+            if let Some(limit) = limit {
+                let key = format!("{}:{}", host, port.unwrap_or_default());
+                let bucket = hosts
+                    .entry(key.clone())
+                    .or_insert_with(|| Bucket::new(limit, now));
+                wait = wait.max(bucket.reserve(now));
+                reserved.push(Some(key));
+            }
+        }
+        (wait, reserved)
+    }
+
+    // Buckets replaced by `configure` since are left alone.
+    fn refund(&self, reserved: &Reserved) {
+        let mut limits = self.limits.lock().unwrap();
+        for key in reserved {
+            let bucket = match key {
+                None => limits.global.as_mut(),
+                Some(key) => limits.hosts.get_mut(key),
+            };
+            if let Some(bucket) = bucket {
+                bucket.refund();
+            }
+        }
+    }
+
+    // Waits for the request's turn. `on_wait` hears how long, when there is a wait at all.
+    // A wait that is cancelled gives its token back.
+    pub async fn acquire(&self, url: &str, on_wait: impl FnOnce(Duration)) {
+        let (wait, reserved) = self.reserve(url, Instant::now());
+        if !wait.is_zero() {
+            on_wait(wait);
+            let mut refund = Refund {
+                limiter: self,
+                reserved: Some(reserved),
+            };
+            sleep(wait).await;
+            refund.reserved = None;
+        }
+    }
+}
+
+struct Refund<'a> {
+    limiter: &'a RateLimiter,
+    reserved: Option<Reserved>,
+}
+
+impl Drop for Refund<'_> {
+    fn drop(&mut self) {
+        if let Some(reserved) = &self.reserved {
+            self.limiter.refund(reserved);
+        }
+    }
+}
+
+// What `execute_request` waits on before each attempt, so retries and authentication
+// round trips count against the limits too.
+pub struct Throttle {
+    pub limiter: Arc<RateLimiter>,
+    pub on_wait: Box<dyn Fn(Duration) + Send + Sync>,
+}
+
+impl Throttle {
+    pub async fn acquire(&self, url: &str) {
+        self.limiter.acquire(url, &self.on_wait).await;
+    }
+}
+
+// What a response said about the server's own limits, from the RateLimit-* headers,
+// their X-RateLimit-* predecessors and Retry-After. Times are in seconds from when the
+// response arrived.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RateLimitInfo {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset_secs: Option<u64>,
+    pub retry_after_secs: Option<u64>,
+    pub policy: Option<String>,
+}
+
+impl RateLimitInfo {
+    // How long the server wants clients to hold off: what Retry-After says, or until the
+    // reset once nothing is left.
+    pub fn delay(&self) -> Option<Duration> {
+        self.retry_after_secs
+            .or(self.reset_secs.filter(|_| self.remaining == Some(0)))
+            .map(Duration::from_secs)
+    }
+}
+
+// Some APIs send X-RateLimit-Reset as epoch seconds rather than a delay; nothing that
+// large is a delay.
+const EPOCH_SECONDS: u64 = 1_000_000_000;
+
+fn header<'a>(headers: &'a HashMap<String, Vec<String>>, names: &[&str]) -> Option<&'a str> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name)?.first())
+        .map(|value| value.trim())
+}
+
+// "100", or "100, 100;w=60" with a quota policy after it.
+fn leading_number(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+// `headers` as in `ResponseData`, with lowercased names. None when there are none of
+// the headers.
+pub fn parse_headers(
+    headers: &HashMap<String, Vec<String>>,
+    now: DateTime<Utc>,
+) -> Option<RateLimitInfo> {
+    let number = |names: &[&str]| header(headers, names).and_then(leading_number);
+    let mut info = RateLimitInfo {
+        limit: number(&["ratelimit-limit", "x-ratelimit-limit"]),
+        remaining: number(&["ratelimit-remaining", "x-ratelimit-remaining"]),
+        reset_secs: number(&["ratelimit-reset", "x-ratelimit-reset"]).map(|reset| {
+            if reset >= EPOCH_SECONDS {
+                reset.saturating_sub(now.timestamp().max(0) as u64)
+            } else {
+                reset
+            }
+        }),
+        retry_after_secs: header(headers, &["retry-after"])
+            .and_then(|value| parse_retry_after(value, now))
+            .map(|delay| delay.as_secs()),
+        policy: header(headers, &["ratelimit-policy", "x-ratelimit-policy"]).map(String::from),
+    };
+    // The single header of later drafts: `limit=100, remaining=0, reset=30`, or
+    // `"default";r=0;t=30`.
+    if let Some(value) = header(headers, &["ratelimit"]) {
+        for part in value.split([',', ';']) {
+            let Some((key, value)) = part.split_once('=') else {
+                continue;
+            };
+            let value = leading_number(value);
+            match key.trim() {
+                "limit" => info.limit = info.limit.or(value),
+                "remaining" | "r" => info.remaining = info.remaining.or(value),
+                "reset" | "t" => info.reset_secs = info.reset_secs.or(value),
+                _ => {}
+            }
+        }
+    }
+    (info != RateLimitInfo::default()).then_some(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queues_requests_per_host_and_globally() {
+        let limiter = RateLimiter::default();
+        let limit = |requests_per_second: f64, burst: u32| RateLimit {
+            requests_per_second,
+            burst,
+        };
+        limiter
+            .configure(&RateLimitSettings {
+                global: Some(limit(10.0, 3)),
+                per_host: Some(limit(2.0, 1)),
+                hosts: vec![HostRateLimit {
+                    host: "*.staging.test".into(),
+                    limit: limit(1.0, 1),
+                }],
+            })
+            .unwrap();
+        let start = Instant::now();
+        let ms = |url: &str, after: u64| {
+            limiter
+                .reserve(url, start + Duration::from_millis(after))
+                .0
+                .as_millis()
+        };
+        assert_eq!(ms("https://api.test/a", 0), 0);
+        assert_eq!(ms("https://api.test/b", 0), 500);
+        assert_eq!(ms("https://api.test/c", 0), 1000);
+        // Another host has a bucket of its own, but the global one is now empty.
+        assert_eq!(ms("https://other.test/", 0), 100);
+        assert_eq!(ms("https://gw.staging.test/", 1000), 0);
+        assert_eq!(ms("https://gw.staging.test/", 1000), 1000);
+        // A different port is a different host.
+        assert_eq!(ms("https://api.test:8443/", 1000), 0);
+
+        let invalid = RateLimitSettings {
+            per_host: Some(limit(0.0, 1)),
+            ..RateLimitSettings::default()
+        };
+        assert!(limiter.configure(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn cancelled_waits_give_their_token_back() {
+        let limiter = RateLimiter::default();
+        limiter
+            .configure(&RateLimitSettings {
+                per_host: Some(RateLimit {
+                    requests_per_second: 1.0,
+                    burst: 1,
+                }),
+                ..RateLimitSettings::default()
+            })
+            .unwrap();
+        let url = "https://api.test/";
+        limiter.acquire(url, |_| {}).await;
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(20), limiter.acquire(url, |_| {})).await;
+        assert!(cancelled.is_err());
+        // Without the refund this would wait behind the cancelled request, for ~2s.
+        let (wait, _) = limiter.reserve(url, Instant::now());
+        assert!(wait <= Duration::from_secs(1), "{:?}", wait);
+    }
+
+    #[test]
+    fn reads_rate_limit_headers() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        let headers = |pairs: &[(&str, &str)]| -> HashMap<String, Vec<String>> {
+            pairs
+                .iter()
+                .map(|(name, value)| (name.to_string(), vec![value.to_string()]))
+                .collect()
+        };
+        let github = parse_headers(
+            &headers(&[
+                ("x-ratelimit-limit", "60"),
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", &(now.timestamp() + 90).to_string()),
+            ]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            (github.limit, github.remaining, github.reset_secs),
+            (Some(60), Some(0), Some(90))
+        );
+        assert_eq!(github.delay(), Some(Duration::from_secs(90)));
+
+        let draft = parse_headers(
+            &headers(&[
+                ("ratelimit", "limit=100, remaining=40, reset=30"),
+                ("ratelimit-policy", "100;w=60"),
+            ]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            (draft.limit, draft.remaining, draft.reset_secs),
+            (Some(100), Some(40), Some(30))
+        );
+        assert_eq!(draft.policy.as_deref(), Some("100;w=60"));
+        // Requests are left, so there is nothing to wait for.
+        assert_eq!(draft.delay(), None);
+
+        let throttled = parse_headers(&headers(&[("retry-after", "5")]), now).unwrap();
+        assert_eq!(throttled.delay(), Some(Duration::from_secs(5)));
+        assert_eq!(parse_headers(&headers(&[("server", "x")]), now), None);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::spec::parse_document;
+use crate::watcher::local_spec_path;
+use reqwest::{Client, Url};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+// External documents are copied under this key of the root document so that every
+// `$ref` can be rewritten into an internal pointer the importer already resolves.
+const EXTERNAL_KEY: &str = "x-restman-external";
+const MAX_DOCUMENTS: usize = 200;
+
+fn base_url(location: &str) -> Option<Url> {
+    match local_spec_path(location) {
+        Some(path) => Url::from_file_path(path.canonicalize().ok()?).ok(),
+        None => Url::parse(location.trim()).ok(),
+    }
+}
+
+fn without_fragment(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+// Maps each fetched document to the pointer prefix its contents live under. A document
+// gets its prefix before it is fetched, which is what stops reference cycles.
+struct Bundler {
+    prefixes: HashMap<String, String>,
+    pending: Vec<(String, Url)>,
+}
+
+impl Bundler {
+    fn rewrite(&mut self, value: &mut Value, base: &Url, prefix: &str) {
+        match value {
+            Value::Object(map) => {
+                for (key, item) in map.iter_mut() {
+                    if key == "$ref" {
+                        if let Value::String(target) = item {
+                            if let Some(rewritten) = self.rewrite_ref(target, base, prefix) {
+                                *target = rewritten;
+                            }
+                        }
+                    } else {
+                        self.rewrite(item, base, prefix);
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.rewrite(item, base, prefix);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn rewrite_ref(&mut self, target: &str, base: &Url, prefix: &str) -> Option<String> {
+        let (document, pointer) = target.split_once('#').unwrap_or((target, ""));
+        if document.is_empty() {
+            return Some(format!("#{}{}", prefix, pointer));
+        }
+        let url = base.join(document).ok()?;
+        let key = without_fragment(&url);
+        let prefix = match self.prefixes.get(&key) {
+            Some(prefix) => prefix.clone(),
+            None => {
+                if self.prefixes.len() > MAX_DOCUMENTS {
+                    return None;
+                }
+                let prefix = format!("/{}/{}", EXTERNAL_KEY, self.prefixes.len() - 1);
+                self.prefixes.insert(key.clone(), prefix.clone());
+                self.pending.push((prefix.clone(), url));
+                prefix
+            }
+        };
+        Some(format!("#{}{}", prefix, pointer))
+    }
+}
+
+async fn fetch(client: &Client, url: &Url) -> Result<Value, String> {
+    if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| format!("Invalid file URL {}", url))?;
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| e.to_string())?;
+        return parse_document(&content, None, url.as_str());
+    }
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .error_for_status()
+        .map_err(|e| e.to_string())?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let content = response.text().await.map_err(|e| e.to_string())?;
+    parse_document(&content, content_type.as_deref(), url.as_str())
+}
+
+// Pulls every document referenced through external `$ref`s (relative files or URLs)
+// into the root document. Documents that cannot be fetched leave their refs dangling,
+// the same as a broken internal pointer.
+pub async fn bundle_external_refs(client: &Client, mut doc: Value, location: &str) -> Value {
+    let Some(base) = base_url(location) else {
+        return doc;
+    };
+    let mut bundler = Bundler {
+        prefixes: HashMap::from([(without_fragment(&base), String::new())]),
+        pending: Vec::new(),
+    };
+    bundler.rewrite(&mut doc, &base, "");
+    let mut external = Map::new();
+    while let Some((prefix, url)) = bundler.pending.pop() {
+        let mut document = match fetch(client, &url).await {
+            Ok(document) => document,
+            Err(_) => continue,
+        };
+        bundler.rewrite(&mut document, &url, &prefix);
+        let index = prefix.rsplit('/').next().unwrap_or_default().to_string();
+        external.insert(index, document);
+    }
+    if !external.is_empty() {
+        if let Some(root) = doc.as_object_mut() {
+            root.insert(EXTERNAL_KEY.into(), Value::Object(external));
+        }
+    }
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn inlines_relative_files_and_survives_cycles() {
+        let dir = std::env::temp_dir().join(format!("restman-refs-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("schemas")).unwrap();
+        std::fs::write(
+            dir.join("schemas/pet.yaml"),
+            "Pet:\n  type: object\n  properties:\n    owner:\n      $ref: 'owner.json#/Owner'\n    tag:\n      $ref: '#/Tag'\nTag:\n  type: string\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("schemas/owner.json"),
+            r#"{"Owner": {"type": "object", "properties": {"pets": {"type": "array", "items": {"$ref": "pet.yaml#/Pet"}}}}}"#,
+        )
+        .unwrap();
+        let root = dir.join("api.yaml");
+        std::fs::write(&root, "").unwrap();
+        let doc = json!({
+            "paths": {"/pets": {"get": {"responses": {"200": {"content": {"application/json": {
+                "schema": {"$ref": "./schemas/pet.yaml#/Pet"}
+            }}}}}}},
+            "components": {"schemas": {"Missing": {"$ref": "nowhere.yaml#/X"}}}
+        });
+        let bundled = bundle_external_refs(&Client::new(), doc, root.to_str().unwrap()).await;
+
+        let pet_ref = bundled
+            .pointer("/paths/~1pets/get/responses/200/content/application~1json/schema/$ref")
+            .and_then(|v| v.as_str())
+            .unwrap();
+        let pet = bundled.pointer(pet_ref.trim_start_matches('#')).unwrap();
+        assert_eq!(pet["type"], "object");
+        let tag_ref = pet["properties"]["tag"]["$ref"].as_str().unwrap();
+        assert_eq!(
+            bundled.pointer(tag_ref.trim_start_matches('#')),
+            Some(&json!({"type": "string"}))
+        );
+        let owner_ref = pet["properties"]["owner"]["$ref"].as_str().unwrap();
+        let owner = bundled.pointer(owner_ref.trim_start_matches('#')).unwrap();
+        // The cycle back to pet.yaml points at the copy that is already bundled.
+        assert_eq!(owner["properties"]["pets"]["items"]["$ref"], pet_ref);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::body::{response_dir, text_prefix};
+use regex::bytes::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+// Bounds on one `read_chunk`, so a chunk always holds a whole character and never
+// floods the IPC bridge.
+const MIN_CHUNK_BYTES: usize = 4;
+pub const MAX_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+const SEARCH_BLOCK_BYTES: usize = 1024 * 1024;
+// Bytes of context on either side of a search hit.
+const PREVIEW_CONTEXT: usize = 60;
+
+// A slice of a response file, decoded as text. `next_offset` is where the following
+// chunk starts; it differs from `offset + length` when a character was cut off.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BodyChunk {
+    pub offset: u64,
+    pub next_offset: u64,
+    pub size: u64,
+    pub text: String,
+    pub eof: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    // Byte offset of the match in the file, for `read_chunk`.
+    pub offset: u64,
+    pub line: u64,
+    pub preview: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct SearchResult {
+    pub hits: Vec<SearchHit>,
+    // Whether the search stopped at `limit` before the end of the file.
+    pub truncated: bool,
+}
+
+// Only files the app wrote responses to may be read through these commands.
+pub fn response_path(path: &str) -> Result<PathBuf, String> {
+    let path = std::fs::canonicalize(path).map_err(|e| e.to_string())?;
+    let dir = std::fs::canonicalize(response_dir()).map_err(|e| e.to_string())?;
+    if path.starts_with(&dir) {
+        Ok(path)
+    } else {
+        Err("Only response files can be read".into())
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+pub fn read_chunk(path: &PathBuf, offset: u64, length: usize) -> Result<BodyChunk, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let offset = offset.min(size);
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    file.take(length.clamp(MIN_CHUNK_BYTES, MAX_CHUNK_BYTES) as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    // An offset inside a character starts at the next one.
+    let skipped = bytes
+        .iter()
+        .take(3)
+        .take_while(|b| is_continuation(**b))
+        .count();
+    let (text, used) = text_prefix(&bytes[skipped..]);
+    let next_offset = offset + (skipped + used) as u64;
+    Ok(BodyChunk {
+        offset,
+        next_offset,
+        size,
+        text,
+        eof: next_offset >= size,
+    })
+}
+
+fn preview(data: &[u8], start: usize, end: usize) -> String {
+    let mut from = start.saturating_sub(PREVIEW_CONTEXT);
+    while from < start && is_continuation(data[from]) {
+        from += 1;
+    }
+    let to = (end + PREVIEW_CONTEXT).min(data.len());
+    text_prefix(&data[from..to])
+        .0
+        .replace(['\r', '\n'], " ")
+        .trim()
+        .to_string()
+}
+
+// Reads in blocks rather than lines, since large bodies are often minified onto one.
+// Each block starts with the tail of the previous one so matches across the seam are
+// found.
+fn search_blocks<R: Read>(
+    mut reader: R,
+    regex: &Regex,
+    overlap: usize,
+    block_size: usize,
+    limit: usize,
+) -> Result<SearchResult, String> {
+    let mut result = SearchResult::default();
+    let mut data: Vec<u8> = Vec::new();
+    let mut block = vec![0u8; block_size];
+    // File offset and line number of the start of `data`.
+    let mut data_offset = 0u64;
+    let mut line = 1u64;
+    loop {
+        let read = reader.read(&mut block).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(result);
+        }
+        let carried = data.len();
+        data.extend_from_slice(&block[..read]);
+        let (mut counted_to, mut counted_lines) = (0, 0);
+        for found in regex.find_iter(&data) {
+            // Matches that lie wholly in the carried tail were found last time.
+            if found.end() <= carried {
+                continue;
+            }
+            if result.hits.len() == limit {
+                result.truncated = true;
+                return Ok(result);
+            }
+            counted_lines += data[counted_to..found.start()]
+                .iter()
+                .filter(|b| **b == b'\n')
+                .count() as u64;
+            counted_to = found.start();
+            result.hits.push(SearchHit {
+                offset: data_offset + found.start() as u64,
+                line: line + counted_lines,
+                preview: preview(&data, found.start(), found.end()),
+            });
+        }
+        let keep = overlap.min(data.len());
+        let consumed = data.len() - keep;
+        line += data[..consumed].iter().filter(|b| **b == b'\n').count() as u64;
+        data_offset += consumed as u64;
+        data.drain(..consumed);
+    }
+}
+
+pub fn search(
+    path: &PathBuf,
+    query: &str,
+    case_sensitive: bool,
+    limit: usize,
+) -> Result<SearchResult, String> {
+    if query.is_empty() {
+        return Ok(SearchResult::default());
+    }
+    let regex = RegexBuilder::new(&regex::escape(query))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    // Case-insensitive matches can be a few bytes longer than the query itself.
+    let overlap = query.len() * 4 + PREVIEW_CONTEXT;
+    search_blocks(file, &regex, overlap, SEARCH_BLOCK_BYTES, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reads_whole_characters_and_searches_across_blocks() {
+        let path = std::env::temp_dir().join(format!("restman-chunks-{}", std::process::id()));
+        std::fs::write(&path, "abcé{\"id\": 1}\nnext line").unwrap();
+        let chunk = read_chunk(&path, 0, 4).unwrap();
+        assert_eq!((chunk.text.as_str(), chunk.next_offset), ("abc", 3));
+        let chunk = read_chunk(&path, chunk.next_offset, 6).unwrap();
+        assert_eq!(chunk.text, "é{\"id");
+        let chunk = read_chunk(&path, 4, 100).unwrap();
+        assert!(chunk.text.starts_with("{\"id"));
+        assert!(chunk.eof);
+        let _ = std::fs::remove_file(&path);
+
+        let text = "one NEEDLE\ntwo\nthree needle four needle";
+        let regex = RegexBuilder::new("needle")
+            .case_insensitive(true)
+            .build()
+            .unwrap();
+        let result = search_blocks(Cursor::new(text), &regex, 6, 5, 10).unwrap();
+        let found: Vec<(u64, u64)> = result
+            .hits
+            .iter()
+            .map(|hit| (hit.offset, hit.line))
+            .collect();
+        assert_eq!(found, vec![(4, 1), (21, 3), (33, 3)]);
+        assert_eq!(result.hits[0].preview, "one NEEDLE");
+        let result = search_blocks(Cursor::new(text), &regex, 6, 5, 2).unwrap();
+        assert!(result.truncated);
+        assert_eq!(result.hits.len(), 2);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::assertions::AssertionResult;
+use crate::extract::Extracted;
+use crate::http::{RequestInput, ResponseData};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// One request in a run: either a saved request or one built by the caller, such as
+// from a collection endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunStep {
+    Saved {
+        id: String,
+    },
+    Request {
+        #[serde(default)]
+        name: String,
+        request: Box<RequestInput>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RunOptions {
+    pub run_id: Option<String>,
+    pub steps: Vec<RunStep>,
+    // Environment for every step that does not name its own.
+    pub environment: Option<String>,
+    // Pause between steps.
+    pub delay_ms: u64,
+    // Skip the remaining steps after the first failed one.
+    pub stop_on_failure: bool,
+    // CSV or JSON file whose rows each get a run of all steps, with the row's columns
+    // as variables.
+    pub data_file: Option<String>,
+    // Hold the next step back for as long as a response's Retry-After, or its rate limit
+    // reset once none are left, asks; see `RateLimitInfo::delay`.
+    pub honor_rate_limits: bool,
+    // Used in place of an environment's variables, for runs of exported workspaces.
+    #[serde(skip)]
+    pub variables: Option<HashMap<String, String>>,
+    // Keeps the requests out of history, for monitors.
+    #[serde(skip)]
+    pub skip_history: bool,
+}
+
+// Emitted as a `run-step` event after each step and collected into the summary.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StepResult {
+    pub run_id: String,
+    // Row of the data file, 0 without one.
+    pub iteration: usize,
+    pub index: usize,
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub passed: bool,
+    pub status: Option<u16>,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+    pub extracted: Vec<Extracted>,
+    pub assertions: Vec<AssertionResult>,
+    pub response: Option<ResponseData>,
+}
+
+// A step with assertions or script tests passes when all of them do; one without
+// passes on any response with a non-error status. A failing script fails the step.
+impl StepResult {
+    pub fn new(
+        run_id: &str,
+        iteration: usize,
+        index: usize,
+        name: String,
+        request: &RequestInput,
+        result: Result<ResponseData, String>,
+        elapsed_ms: u64,
+    ) -> Self {
+        let (response, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(e) => (None, Some(e)),
+        };
+        let status = response.as_ref().map(|response| response.status);
+        let assertions = response
+            .as_ref()
+            .map(|response| response.assertions.clone())
+            .unwrap_or_default();
+        let passed = match response.as_ref() {
+            Some(response) => {
+                let tests = response
+                    .scripts
+                    .as_ref()
+                    .map(|report| report.tests.as_slice())
+                    .unwrap_or_default();
+                let checked = if response.assertions.is_empty() && tests.is_empty() {
+                    response.status < 400
+                } else {
+                    response.assertions.iter().all(|result| result.passed)
+                        && tests.iter().all(|test| test.passed)
+                };
+                let script_failed = response
+                    .scripts
+                    .as_ref()
+                    .is_some_and(|report| report.error.is_some());
+                checked && !script_failed
+            }
+            None => false,
+        };
+        StepResult {
+            run_id: run_id.to_string(),
+            iteration,
+            index,
+            name,
+            method: request.method.clone(),
+            url: request.url.clone(),
+            passed,
+            status,
+            elapsed_ms,
+            error,
+            extracted: response
+                .as_ref()
+                .map(|response| response.extracted.clone())
+                .unwrap_or_default(),
+            assertions,
+            response,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct IterationResult {
+    pub index: usize,
+    pub data: HashMap<String, String>,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub steps: Vec<StepResult>,
+    // One per data row; a single one without a data file.
+    pub iterations: Vec<IterationResult>,
+    pub passed: usize,
+    pub failed: usize,
+    // Steps left unrun after a failure with `stop_on_failure`.
+    pub skipped: usize,
+    pub elapsed_ms: u64,
+}
+
+// Variables shared by the steps of one iteration: its data row and the values
+// extracted by earlier steps, visible even when they could not be saved to an
+// environment.
+#[derive(Default)]
+pub struct RunContext {
+    row: HashMap<String, String>,
+    extracted: HashMap<String, String>,
+}
+
+impl RunContext {
+    pub fn for_row(row: HashMap<String, String>) -> Self {
+        RunContext {
+            row,
+            extracted: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, extracted: &[Extracted]) {
+        for item in extracted {
+            if let Some(value) = &item.value {
+                self.extracted.insert(item.variable.clone(), value.clone());
+            }
+        }
+    }
+
+    // Overlays the run's values on the environment's. Masked values stand for secrets
+    // that went to the keychain, which the environment itself provides.
+    pub fn variables(&self, mut environment: HashMap<String, String>) -> HashMap<String, String> {
+        environment.extend(self.row.clone());
+        for (name, value) in &self.extracted {
+            if value != crate::secrets::SECRET_MASK {
+                environment.insert(name.clone(), value.clone());
+            }
+        }
+        environment
+    }
+}
+
+// Splits CSV text into records, following RFC 4180 quoting.
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quoted field in CSV".into());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|record| !(record.len() == 1 && record[0].is_empty()));
+    Ok(records)
+}
+
+// Reads data rows from a JSON array of objects or from CSV with a header line.
+pub fn parse_data(content: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    if content.trim_start().starts_with('[') {
+        let rows: Vec<serde_json::Map<String, Value>> =
+            serde_json::from_str(content).map_err(|e| format!("Invalid data file: {}", e))?;
+        return Ok(rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(name, value)| match value {
+                        Value::String(s) => (name, s),
+                        other => (name, other.to_string()),
+                    })
+                    .collect()
+            })
+            .collect());
+    }
+    let mut records = csv_records(content)?.into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or("Data file is empty")?
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .collect();
+    records
+        .enumerate()
+        .map(|(line, record)| {
+            if record.len() != header.len() {
+                return Err(format!(
+                    "Row {} has {} columns, the header has {}",
+                    line + 1,
+                    record.len(),
+                    header.len()
+                ));
+            }
+            Ok(header.iter().cloned().zip(record).collect())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carries_extracted_values_between_steps() {
+        let options: RunOptions = serde_json::from_value(serde_json::json!({
+            "steps": [
+                { "type": "saved", "id": "login" },
+                { "type": "request", "request": { "method": "GET", "url": "{{base}}/me" } }
+            ],
+            "stop_on_failure": true
+        }))
+        .unwrap();
+        assert_eq!(options.steps.len(), 2);
+        assert_eq!(options.delay_ms, 0);
+
+        let mut context = RunContext::default();
+        context.record(&[
+            Extracted {
+                variable: "token".into(),
+                value: Some("t0k".into()),
+                error: None,
+            },
+            Extracted {
+                variable: "key".into(),
+                value: Some(crate::secrets::SECRET_MASK.into()),
+                error: None,
+            },
+            Extracted {
+                variable: "id".into(),
+                value: None,
+                error: Some("No match in the response".into()),
+            },
+        ]);
+        let variables = context.variables(HashMap::from([
+            ("token".to_string(), "old".to_string()),
+            ("key".to_string(), "k3y".to_string()),
+        ]));
+        assert_eq!(variables["token"], "t0k");
+        assert_eq!(variables["key"], "k3y");
+        assert!(!variables.contains_key("id"));
+
+        let request = RequestInput {
+            method: "GET".into(),
+            url: "https://api.test".into(),
+            ..RequestInput::default()
+        };
+        let failed = StepResult::new("run", 0, 0, "me".into(), &request, Err("refused".into()), 3);
+        assert!(!failed.passed);
+        assert_eq!(failed.error.as_deref(), Some("refused"));
+    }
+
+    #[test]
+    fn reads_csv_and_json_data_rows() {
+        let rows = parse_data(
+            "\u{feff}user, note\r\nalice,\"says \"\"hi\"\", twice\"\r\nbob,\"two\nlines\"\n\n",
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["note"], "says \"hi\", twice");
+        assert_eq!(rows[1]["note"], "two\nlines");
+        assert!(parse_data("a,b\n1\n").is_err());
+
+        let rows = parse_data(r#"[{"id": 7, "name": "rex"}]"#).unwrap();
+        assert_eq!(rows[0]["id"], "7");
+        let context = RunContext::for_row(rows[0].clone());
+        let variables = context.variables(HashMap::from([("id".to_string(), "1".to_string())]));
+        assert_eq!(variables["id"], "7");
+    }
+}
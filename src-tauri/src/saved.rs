@@ -0,0 +1,104 @@
+use crate::environments::Environment;
+use crate::http::{parse_method, RequestInput};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// A request the user built by hand rather than from a spec endpoint. `folder` is a
+// slash-separated path such as "users/admin"; the empty string is the top level.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedRequest {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub folder: String,
+    pub request: RequestInput,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<SavedExample>,
+    #[serde(default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+// An example response kept with a saved request, e.g. from an imported collection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SavedExample {
+    pub name: String,
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+// What importing another tool's collection produced; `warnings` lists what could not be
+// carried over.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct CollectionImport {
+    pub name: String,
+    pub saved_requests: Vec<SavedRequest>,
+    pub environments: Vec<Environment>,
+    pub warnings: Vec<String>,
+}
+
+pub fn new_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+// Trims the name and collapses empty folder segments, so " /users//admin/ " and
+// "users/admin" end up in the same folder.
+pub fn normalize(mut saved: SavedRequest) -> Result<SavedRequest, String> {
+    saved.name = saved.name.trim().to_string();
+    if saved.name.is_empty() {
+        return Err("Saved request needs a name".into());
+    }
+    saved.request.method = parse_method(&saved.request.method)?.to_string();
+    if saved.request.url.trim().is_empty() {
+        return Err("Saved request needs a URL".into());
+    }
+    saved.folder = saved
+        .folder
+        .split('/')
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(name: &str, folder: &str, method: &str, url: &str) -> SavedRequest {
+        SavedRequest {
+            id: String::new(),
+            name: name.into(),
+            folder: folder.into(),
+            request: RequestInput {
+                method: method.into(),
+                url: url.into(),
+                ..RequestInput::default()
+            },
+            examples: Vec::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn normalizes_names_and_folders() {
+        let normalized = normalize(saved(
+            " List users ",
+            " /users//admin/ ",
+            "get",
+            "https://api.test/users",
+        ))
+        .unwrap();
+        assert_eq!(normalized.name, "List users");
+        assert_eq!(normalized.folder, "users/admin");
+        assert_eq!(normalized.request.method, "GET");
+        assert!(normalize(saved(" ", "", "GET", "https://api.test")).is_err());
+        assert!(normalize(saved("x", "", "GET", "  ")).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::body::BodyEncoding;
+use crate::extract::Extracted;
+use crate::http::{RequestInput, ResponseData};
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, Map, Scope};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Scripts are Rhai (https://rhai.rs). They cannot touch files, the network or the
+// process, and these bound how much work one may do before it is stopped.
+const MAX_OPERATIONS: u64 = 5_000_000;
+const MAX_CALL_DEPTH: usize = 64;
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScriptTest {
+    pub name: String,
+    pub passed: bool,
+}
+
+// What the pre-request and post-response scripts of a request printed and checked.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ScriptReport {
+    pub logs: Vec<String>,
+    pub tests: Vec<ScriptTest>,
+    pub error: Option<String>,
+}
+
+impl ScriptReport {
+    fn merge(&mut self, other: ScriptReport) {
+        self.logs.extend(other.logs);
+        self.tests.extend(other.tests);
+        if self.error.is_none() {
+            self.error = other.error;
+        }
+    }
+}
+
+// The parts of the request a pre-request script sees as `request` and may change.
+#[derive(Serialize, Deserialize)]
+struct ScriptRequest {
+    method: String,
+    url: String,
+    headers: HashMap<String, String>,
+    query: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+// The response as post-response scripts see it; `json` is the parsed body, or `()`.
+#[derive(Serialize)]
+struct ScriptResponse {
+    status: u16,
+    headers: HashMap<String, String>,
+    body: String,
+    json: Value,
+    elapsed_ms: u64,
+}
+
+struct Run {
+    report: ScriptReport,
+    scope: Scope<'static>,
+    // Variables the script added or changed.
+    changed: Vec<Extracted>,
+}
+
+fn engine(report: &Arc<Mutex<ScriptReport>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_DEPTH);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    let logs = report.clone();
+    engine.on_print(move |text| logs.lock().unwrap().logs.push(text.to_string()));
+    let logs = report.clone();
+    engine.on_debug(move |text, _, _| logs.lock().unwrap().logs.push(text.to_string()));
+    let logs = report.clone();
+    engine.register_fn("log", move |value: Dynamic| {
+        logs.lock().unwrap().logs.push(value.to_string())
+    });
+    let tests = report.clone();
+    engine.register_fn("test", move |name: &str, passed: bool| {
+        tests.lock().unwrap().tests.push(ScriptTest {
+            name: name.to_string(),
+            passed,
+        })
+    });
+    engine
+}
+
+// Runs `script` with `variables` in scope as a map, plus whatever `scope` already holds.
+fn run(script: &str, mut scope: Scope<'static>, variables: &HashMap<String, String>) -> Run {
+    let report = Arc::new(Mutex::new(ScriptReport::default()));
+    let engine = engine(&report);
+    let map: Map = variables
+        .iter()
+        .map(|(name, value)| (name.as_str().into(), Dynamic::from(value.clone())))
+        .collect();
+    scope.push("variables", map);
+    let error = engine
+        .run_with_scope(&mut scope, script)
+        .err()
+        .map(|e| e.to_string());
+    let changed = scope
+        .get_value::<Map>("variables")
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .filter(|(name, value)| variables.get(name) != Some(value))
+        .map(|(variable, value)| Extracted {
+            variable,
+            value: Some(value),
+            error: None,
+        })
+        .collect();
+    drop(engine);
+    let mut report = Arc::try_unwrap(report)
+        .map(|report| report.into_inner().unwrap())
+        .unwrap_or_else(|report| report.lock().unwrap().clone());
+    report.error = error;
+    Run {
+        report,
+        scope,
+        changed,
+    }
+}
+
+// Runs the pre-request script before variables are substituted, so variables it sets
+// apply to the same request. A failing script stops the request.
+pub fn pre_request(
+    script: &str,
+    mut input: RequestInput,
+    variables: &mut HashMap<String, String>,
+) -> Result<(RequestInput, ScriptReport, Vec<Extracted>), String> {
+    let request = ScriptRequest {
+        method: input.method.clone(),
+        url: input.url.clone(),
+        headers: input.headers.clone(),
+        query: input.query.clone().unwrap_or_default(),
+        body: input.body.clone(),
+    };
+    let mut scope = Scope::new();
+    scope.push("request", to_dynamic(request).map_err(|e| e.to_string())?);
+    let run = run(script, scope, variables);
+    if let Some(error) = &run.report.error {
+        return Err(format!("Pre-request script failed: {}", error));
+    }
+    let request: ScriptRequest = run
+        .scope
+        .get_value::<Dynamic>("request")
+        .ok_or("Pre-request script removed `request`")
+        .and_then(|request| {
+            from_dynamic(&request).map_err(|_| "Pre-request script left `request` malformed")
+        })?;
+    input.method = request.method;
+    input.url = request.url;
+    input.headers = request.headers;
+    input.query = (!request.query.is_empty() || input.query.is_some()).then_some(request.query);
+    input.body = request.body;
+    for item in &run.changed {
+        if let Some(value) = &item.value {
+            variables.insert(item.variable.clone(), value.clone());
+        }
+    }
+    Ok((input, run.report, run.changed))
+}
+
+// Runs the post-response script. Its errors are reported, not returned, since the
+// request itself already succeeded.
+pub fn post_response(
+    script: &str,
+    response: &ResponseData,
+    variables: &HashMap<String, String>,
+) -> (ScriptReport, Vec<Extracted>) {
+    let json = match response.body_encoding {
+        BodyEncoding::Text if response.body_path.is_none() => {
+            serde_json::from_str(&response.body).unwrap_or(Value::Null)
+        }
+        _ => Value::Null,
+    };
+    let view = ScriptResponse {
+        status: response.status,
+        headers: response
+            .headers
+            .iter()
+            .map(|(name, values)| (name.clone(), values.join(", ")))
+            .collect(),
+        body: response.body.clone(),
+        json,
+        elapsed_ms: response.elapsed_ms,
+    };
+    let mut scope = Scope::new();
+    match to_dynamic(view) {
+        Ok(view) => scope.push("response", view),
+        Err(e) => {
+            let report = ScriptReport {
+                error: Some(e.to_string()),
+                ..ScriptReport::default()
+            };
+            return (report, Vec::new());
+        }
+    };
+    let run = run(script, scope, variables);
+    (run.report, run.changed)
+}
+
+pub fn merge(report: &mut Option<ScriptReport>, other: ScriptReport) {
+    match report {
+        Some(report) => report.merge(other),
+        None => *report = Some(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_change_the_request_and_read_the_response() {
+        let mut variables = HashMap::from([("user".to_string(), "alice".to_string())]);
+        let (input, report, changed) = pre_request(
+            r#"
+                request.headers["X-User"] = variables.user;
+                request.url += "?sig=" + request.method.len();
+                variables.nonce = "n1";
+                log("signed");
+            "#,
+            RequestInput {
+                method: "GET".into(),
+                url: "https://api.test/{{nonce}}".into(),
+                ..RequestInput::default()
+            },
+            &mut variables,
+        )
+        .unwrap();
+        assert_eq!(input.url, "https://api.test/{{nonce}}?sig=3");
+        assert_eq!(input.headers["X-User"], "alice");
+        assert_eq!(input.query, None);
+        assert_eq!(variables["nonce"], "n1");
+        assert_eq!(changed.len(), 1);
+        assert_eq!(report.logs, vec!["signed"]);
+
+        let response = ResponseData {
+            status: 200,
+            status_text: "OK".into(),
+            http_version: "HTTP/1.1".into(),
+            body: r#"{"token": "t0k", "items": [1, 2]}"#.into(),
+            elapsed_ms: 12,
+            ..ResponseData::default()
+        };
+        let (report, changed) = post_response(
+            r#"
+                test("ok", response.status == 200);
+                test("two items", response.json.items.len() == 2);
+                variables.token = response.json.token;
+            "#,
+            &response,
+            &variables,
+        );
+        assert_eq!(report.error, None);
+        assert!(report.tests.iter().all(|test| test.passed));
+        assert_eq!(changed[0].value.as_deref(), Some("t0k"));
+
+        let (report, _) = post_response("loop {}", &response, &variables);
+        assert!(report.error.is_some());
+        assert!(pre_request("throw \"no\"", RequestInput::default(), &mut variables).is_err());
+    }
+}
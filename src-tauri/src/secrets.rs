@@ -0,0 +1,255 @@
+use crate::environments::Environment;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SERVICE: &str = "restman";
+// Shown in place of secret values everywhere outside the keychain. Saving a variable
+// whose value is still the mask keeps the stored secret.
+pub const SECRET_MASK: &str = "••••••••";
+
+// Secret variable values live in the OS keychain, one entry per variable. The stored
+// environment keeps the variable with an empty value.
+pub struct Secrets {
+    // Stands in for the keychain in tests.
+    memory: Option<Mutex<HashMap<String, String>>>,
+}
+
+fn account(workspace: &str, environment: &str, variable: &str) -> String {
+    format!("{}/{}/{}", workspace, environment, variable)
+}
+
+impl Secrets {
+    pub fn keychain() -> Self {
+        Secrets { memory: None }
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Secrets {
+            memory: Some(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn get(&self, account: &str) -> Result<Option<String>, String> {
+        if let Some(memory) = &self.memory {
+            return Ok(memory.lock().unwrap().get(account).cloned());
+        }
+        let entry = keyring::Entry::new(SERVICE, account).map_err(|e| e.to_string())?;
+        match entry.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Cannot read {} from the keychain: {}", account, e)),
+        }
+    }
+
+    fn set(&self, account: &str, value: &str) -> Result<(), String> {
+        if let Some(memory) = &self.memory {
+            memory
+                .lock()
+                .unwrap()
+                .insert(account.to_string(), value.to_string());
+            return Ok(());
+        }
+        keyring::Entry::new(SERVICE, account)
+            .and_then(|entry| entry.set_password(value))
+            .map_err(|e| format!("Cannot store {} in the keychain: {}", account, e))
+    }
+
+    fn delete(&self, account: &str) -> Result<(), String> {
+        if let Some(memory) = &self.memory {
+            memory.lock().unwrap().remove(account);
+            return Ok(());
+        }
+        let entry = keyring::Entry::new(SERVICE, account).map_err(|e| e.to_string())?;
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Cannot remove {} from the keychain: {}",
+                account, e
+            )),
+        }
+    }
+
+    // Moves secret values into the keychain and returns the environment to store.
+    // `previous` is the stored version, if any, so renamed environments and variables
+    // carry their secrets along and secrets that went away are removed.
+    pub fn seal(
+        &self,
+        workspace: &str,
+        mut environment: Environment,
+        previous: Option<&Environment>,
+    ) -> Result<Environment, String> {
+        let previous_account = |name: &str| {
+            previous
+                .filter(|previous| {
+                    previous
+                        .variables
+                        .iter()
+                        .any(|variable| variable.secret && variable.name == name)
+                })
+                .map(|previous| account(workspace, &previous.name, name))
+        };
+        for variable in &mut environment.variables {
+            let masked = variable.value == SECRET_MASK;
+            let old = previous_account(&variable.name);
+            if masked {
+                variable.value = match &old {
+                    Some(old) => self.get(old)?.unwrap_or_default(),
+                    None => String::new(),
+                };
+            }
+            if variable.secret {
+                self.set(
+                    &account(workspace, &environment.name, &variable.name),
+                    &variable.value,
+                )?;
+                variable.value.clear();
+            }
+        }
+        if let Some(previous) = previous {
+            for variable in previous.variables.iter().filter(|variable| variable.secret) {
+                let kept = previous.name == environment.name
+                    && environment
+                        .variables
+                        .iter()
+                        .any(|v| v.secret && v.name == variable.name);
+                if !kept {
+                    self.delete(&account(workspace, &previous.name, &variable.name))?;
+                }
+            }
+        }
+        Ok(environment)
+    }
+
+    // Fills secret values back in from the keychain, for sending a request.
+    pub fn unseal(
+        &self,
+        workspace: &str,
+        mut environment: Environment,
+    ) -> Result<Environment, String> {
+        for variable in environment.variables.iter_mut().filter(|v| v.secret) {
+            variable.value = self
+                .get(&account(workspace, &environment.name, &variable.name))?
+                .unwrap_or_default();
+        }
+        Ok(environment)
+    }
+
+    pub fn forget(&self, workspace: &str, environment: &Environment) -> Result<(), String> {
+        for variable in environment.variables.iter().filter(|v| v.secret) {
+            self.delete(&account(workspace, &environment.name, &variable.name))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn mask(mut environment: Environment) -> Environment {
+    for variable in environment.variables.iter_mut().filter(|v| v.secret) {
+        variable.value = SECRET_MASK.to_string();
+    }
+    environment
+}
+
+#[cfg(unix)]
+pub fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+pub fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)
+}
+
+// A new file, readable by the user alone from the moment it exists. Fails if `path`
+// is already taken.
+#[cfg(unix)]
+pub fn create_private_file(path: &Path) -> std::io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+pub fn create_private_file(path: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+}
+
+pub fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let _ = std::fs::remove_file(path);
+    create_private_file(path)?.write_all(content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environments::Variable;
+
+    fn environment(name: &str, variables: &[(&str, &str, bool)]) -> Environment {
+        Environment {
+            name: name.into(),
+            variables: variables
+                .iter()
+                .map(|(name, value, secret)| Variable {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    enabled: true,
+                    secret: *secret,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn keeps_secret_values_out_of_the_environment() {
+        let secrets = Secrets::in_memory();
+        let stored = secrets
+            .seal(
+                "default",
+                environment(
+                    "dev",
+                    &[("host", "api.test", false), ("token", "t0k", true)],
+                ),
+                None,
+            )
+            .unwrap();
+        assert_eq!(stored.variables[1].value, "");
+        assert_eq!(mask(stored.clone()).variables[1].value, SECRET_MASK);
+        assert_eq!(
+            secrets.unseal("default", stored.clone()).unwrap().values()["token"],
+            "t0k"
+        );
+
+        // Renaming with the mask in place moves the secret to the new name.
+        let renamed = secrets
+            .seal(
+                "default",
+                environment(
+                    "staging",
+                    &[("host", "api.test", false), ("token", SECRET_MASK, true)],
+                ),
+                Some(&stored),
+            )
+            .unwrap();
+        assert_eq!(
+            secrets.unseal("default", renamed.clone()).unwrap().values()["token"],
+            "t0k"
+        );
+        assert_eq!(secrets.get("default/dev/token").unwrap(), None);
+
+        secrets.forget("default", &renamed).unwrap();
+        assert_eq!(secrets.get("default/staging/token").unwrap(), None);
+    }
+}
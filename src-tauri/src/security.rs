@@ -0,0 +1,322 @@
+use crate::auth::{ApiKeyLocation, Auth};
+use crate::oauth2::GrantType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// Scheme name to the scopes it needs. An operation lists alternatives, any one of
+// which is enough; every scheme inside one requirement has to be satisfied.
+pub type SecurityRequirement = BTreeMap<String, Vec<String>>;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OAuthFlow {
+    pub grant_type: GrantType,
+    pub authorization_url: Option<String>,
+    pub token_url: Option<String>,
+    pub refresh_url: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SecurityScheme {
+    ApiKey {
+        name: String,
+        location: ApiKeyLocation,
+    },
+    Http {
+        scheme: String,
+        bearer_format: Option<String>,
+    },
+    #[serde(rename = "oauth2")]
+    OAuth2 {
+        // Implicit flows are not listed since the token endpoint never sees them.
+        flows: Vec<OAuthFlow>,
+    },
+    OpenIdConnect {
+        url: String,
+    },
+    MutualTls,
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn parse_scheme(value: &Value) -> Option<SecurityScheme> {
+    match value.get("type")?.as_str()? {
+        "apiKey" => Some(SecurityScheme::ApiKey {
+            name: text(value, "name")?,
+            location: match value.get("in").and_then(|v| v.as_str()) {
+                Some("query") => ApiKeyLocation::Query,
+                Some("cookie") => ApiKeyLocation::Cookie,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "http" => Some(SecurityScheme::Http {
+            scheme: text(value, "scheme")?.to_ascii_lowercase(),
+            bearer_format: text(value, "bearerFormat"),
+        }),
+        "oauth2" => {
+            let mut flows = Vec::new();
+            if let Some(source) = value.get("flows").and_then(|v| v.as_object()) {
+                for (name, flow) in source {
+                    let grant_type = match name.as_str() {
+                        "authorizationCode" => GrantType::AuthorizationCode,
+                        "clientCredentials" => GrantType::ClientCredentials,
+                        "password" => GrantType::Password,
+                        _ => continue,
+                    };
+                    flows.push(OAuthFlow {
+                        grant_type,
+                        authorization_url: text(flow, "authorizationUrl"),
+                        token_url: text(flow, "tokenUrl"),
+                        refresh_url: text(flow, "refreshUrl"),
+                        scopes: flow
+                            .get("scopes")
+                            .and_then(|v| v.as_object())
+                            .map(|scopes| scopes.keys().cloned().collect())
+                            .unwrap_or_default(),
+                    });
+                }
+            }
+            Some(SecurityScheme::OAuth2 { flows })
+        }
+        "openIdConnect" => Some(SecurityScheme::OpenIdConnect {
+            url: text(value, "openIdConnectUrl")?,
+        }),
+        "mutualTLS" => Some(SecurityScheme::MutualTls),
+        _ => None,
+    }
+}
+
+// Schemes may themselves be `$ref`s into the components; unknown types are dropped.
+pub fn parse_security_schemes(doc: &Value) -> BTreeMap<String, SecurityScheme> {
+    let mut schemes = BTreeMap::new();
+    if let Some(source) = doc
+        .pointer("/components/securitySchemes")
+        .and_then(|v| v.as_object())
+    {
+        for (name, value) in source {
+            let value = value
+                .get("$ref")
+                .and_then(|v| v.as_str())
+                .and_then(|target| doc.pointer(target.trim_start_matches('#')))
+                .unwrap_or(value);
+            if let Some(scheme) = parse_scheme(value) {
+                schemes.insert(name.clone(), scheme);
+            }
+        }
+    }
+    schemes
+}
+
+// The operation's `security` replaces the document default, and an explicit empty
+// list turns authentication off for that operation.
+pub fn parse_requirements(doc: &Value, operation: &Value) -> Vec<SecurityRequirement> {
+    let source = operation.get("security").or_else(|| doc.get("security"));
+    let Some(items) = source.and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| item.as_object())
+        .map(|item| {
+            item.iter()
+                .map(|(name, scopes)| {
+                    let scopes = scopes
+                        .as_array()
+                        .map(|scopes| {
+                            scopes
+                                .iter()
+                                .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    (name.clone(), scopes)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Fits a saved credential to the scheme: API keys take the spec's name and location,
+// and OAuth grants fill in the flow's endpoints and scopes when left empty.
+pub fn adapt_credential(scheme: &SecurityScheme, scopes: &[String], auth: Auth) -> Auth {
+    match (scheme, auth) {
+        (SecurityScheme::ApiKey { name, location }, auth) => {
+            let value = match auth {
+                Auth::ApiKey { value, .. } => value,
+                Auth::Bearer { token } => token,
+                other => return other,
+            };
+            Auth::ApiKey {
+                name: name.clone(),
+                value,
+                location: *location,
+            }
+        }
+        (SecurityScheme::Http { scheme, .. }, Auth::ApiKey { value, .. }) if scheme == "bearer" => {
+            Auth::Bearer { token: value }
+        }
+        (SecurityScheme::OAuth2 { flows }, Auth::OAuth2Grant { mut config }) => {
+            if let Some(flow) = flows
+                .iter()
+                .find(|flow| flow.grant_type == config.grant_type)
+            {
+                if config.token_url.is_empty() {
+                    config.token_url = flow.token_url.clone().unwrap_or_default();
+                }
+                if config.authorization_url.is_empty() {
+                    config.authorization_url = flow.authorization_url.clone().unwrap_or_default();
+                }
+            }
+            if config.scopes.is_empty() {
+                config.scopes = scopes.to_vec();
+            }
+            Auth::OAuth2Grant { config }
+        }
+        (_, auth) => auth,
+    }
+}
+
+// Picks the first alternative whose schemes all have a saved credential and returns
+// those credentials adapted to the schemes. An empty alternative means anonymous
+// access is allowed, which yields no credentials at all.
+pub fn select_credentials(
+    requirements: &[SecurityRequirement],
+    schemes: &BTreeMap<String, SecurityScheme>,
+    credential: impl Fn(&str) -> Option<Auth>,
+) -> Option<Vec<Auth>> {
+    'requirements: for requirement in requirements {
+        let mut selected = Vec::new();
+        for (name, scopes) in requirement {
+            let (Some(scheme), Some(auth)) = (schemes.get(name), credential(name)) else {
+                continue 'requirements;
+            };
+            selected.push(adapt_credential(scheme, scopes, auth));
+        }
+        return Some(selected);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth2::OAuthConfig;
+    use serde_json::json;
+
+    fn spec() -> Value {
+        json!({
+            "security": [{ "key": [] }],
+            "components": {
+                "securitySchemes": {
+                    "key": { "type": "apiKey", "name": "X-Api-Key", "in": "header" },
+                    "token": { "type": "http", "scheme": "Bearer", "bearerFormat": "JWT" },
+                    "oauth": {
+                        "type": "oauth2",
+                        "flows": {
+                            "implicit": { "authorizationUrl": "https://auth.test/authorize", "scopes": {} },
+                            "clientCredentials": {
+                                "tokenUrl": "https://auth.test/token",
+                                "scopes": { "pets:read": "", "pets:write": "" }
+                            }
+                        }
+                    },
+                    "shared": { "$ref": "#/components/securitySchemes/key" },
+                    "unknown": { "type": "carrier-pigeon" }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn parses_schemes_and_requirements() {
+        let doc = spec();
+        let schemes = parse_security_schemes(&doc);
+        assert_eq!(
+            schemes.keys().collect::<Vec<_>>(),
+            vec!["key", "oauth", "shared", "token"]
+        );
+        assert_eq!(
+            schemes["token"],
+            SecurityScheme::Http {
+                scheme: "bearer".into(),
+                bearer_format: Some("JWT".into())
+            }
+        );
+        let SecurityScheme::OAuth2 { flows } = &schemes["oauth"] else {
+            panic!("expected an oauth2 scheme");
+        };
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].grant_type, GrantType::ClientCredentials);
+        assert_eq!(flows[0].scopes, vec!["pets:read", "pets:write"]);
+
+        assert_eq!(
+            parse_requirements(&doc, &json!({}))[0]["key"],
+            Vec::<String>::new()
+        );
+        assert!(parse_requirements(&doc, &json!({ "security": [] })).is_empty());
+        let own = parse_requirements(&doc, &json!({ "security": [{ "oauth": ["pets:read"] }] }));
+        assert_eq!(own[0]["oauth"], vec!["pets:read"]);
+    }
+
+    #[test]
+    fn selects_first_satisfiable_requirement() {
+        let doc = spec();
+        let schemes = parse_security_schemes(&doc);
+        let requirements = parse_requirements(
+            &doc,
+            &json!({ "security": [{ "token": [] }, { "oauth": ["pets:read"] }, {}] }),
+        );
+        let credential = |name: &str| match name {
+            "oauth" => Some(Auth::OAuth2Grant {
+                config: OAuthConfig {
+                    grant_type: GrantType::ClientCredentials,
+                    client_id: "cli".into(),
+                    ..OAuthConfig::default()
+                },
+            }),
+            _ => None,
+        };
+        let selected = select_credentials(&requirements, &schemes, credential).unwrap();
+        let Auth::OAuth2Grant { config } = &selected[0] else {
+            panic!("expected an oauth2 grant");
+        };
+        assert_eq!(config.token_url, "https://auth.test/token");
+        assert_eq!(config.scopes, vec!["pets:read"]);
+
+        assert_eq!(
+            select_credentials(&requirements[2..], &schemes, |_| None),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            select_credentials(&requirements[..1], &schemes, |_| None),
+            None
+        );
+    }
+
+    #[test]
+    fn adapts_api_keys_to_the_scheme() {
+        let schemes = parse_security_schemes(&spec());
+        let adapted = adapt_credential(
+            &schemes["key"],
+            &[],
+            Auth::Bearer {
+                token: "secret".into(),
+            },
+        );
+        assert_eq!(
+            adapted,
+            Auth::ApiKey {
+                name: "X-Api-Key".into(),
+                value: "secret".into(),
+                location: ApiKeyLocation::Header
+            }
+        );
+    }
+}
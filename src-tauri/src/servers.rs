@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServerVariable {
+    pub default: String,
+    pub enum_values: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Server {
+    // The URL template as written, with `{variable}` placeholders.
+    pub url: String,
+    pub description: Option<String>,
+    pub variables: BTreeMap<String, ServerVariable>,
+}
+
+impl Server {
+    // Fills placeholders from `values`, falling back to each variable's default.
+    // Values outside a variable's enum are rejected.
+    pub fn expand(&self, values: &HashMap<String, String>) -> Result<String, String> {
+        let mut url = self.url.clone();
+        for (name, variable) in &self.variables {
+            let value = values.get(name).unwrap_or(&variable.default);
+            if !variable.enum_values.is_empty() && !variable.enum_values.contains(value) {
+                return Err(format!(
+                    "{} is not an allowed value for server variable {}",
+                    value, name
+                ));
+            }
+            url = url.replace(&format!("{{{}}}", name), value);
+        }
+        Ok(url.trim_end_matches('/').to_string())
+    }
+
+    pub fn default_url(&self) -> String {
+        self.expand(&HashMap::new())
+            .unwrap_or_else(|_| self.url.trim_end_matches('/').to_string())
+    }
+}
+
+fn parse_variable(value: &Value) -> ServerVariable {
+    let enum_values: Vec<String> = value
+        .get("enum")
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Null => None,
+                    other => Some(other.to_string()),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let default = match value.get("default") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Null) | None => enum_values.first().cloned().unwrap_or_default(),
+        Some(other) => other.to_string(),
+    };
+    ServerVariable {
+        default,
+        enum_values,
+        description: value
+            .get("description")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+    }
+}
+
+// Reads a `servers` array from the document, a path item, or an operation.
+pub fn parse_servers(value: Option<&Value>) -> Vec<Server> {
+    let Some(items) = value.and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let url = item.get("url")?.as_str()?.to_string();
+            let variables = item
+                .get("variables")
+                .and_then(|v| v.as_object())
+                .map(|vars| {
+                    vars.iter()
+                        .map(|(name, var)| (name.clone(), parse_variable(var)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(Server {
+                url,
+                description: item
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
+                variables,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn expands_variables_with_defaults_and_enums() {
+        let servers = parse_servers(Some(&json!([
+            {
+                "url": "https://{region}.api.test:{port}/v1/",
+                "description": "Regional",
+                "variables": {
+                    "region": { "enum": ["eu", "us"], "default": "eu" },
+                    "port": { "default": 8443 }
+                }
+            },
+            { "description": "missing url" },
+            { "url": "/relative" }
+        ])));
+        assert_eq!(servers.len(), 2);
+        let regional = &servers[0];
+        assert_eq!(regional.default_url(), "https://eu.api.test:8443/v1");
+        let values = HashMap::from([("region".to_string(), "us".to_string())]);
+        assert_eq!(
+            regional.expand(&values).unwrap(),
+            "https://us.api.test:8443/v1"
+        );
+        let invalid = HashMap::from([("region".to_string(), "mars".to_string())]);
+        assert!(regional.expand(&invalid).is_err());
+        assert_eq!(servers[1].default_url(), "/relative");
+    }
+}
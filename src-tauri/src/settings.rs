@@ -0,0 +1,140 @@
+use crate::contract::RequestValidation;
+use crate::ratelimit::RateLimitSettings;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    None,
+    #[default]
+    System,
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ProxyServer {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // Use the credentials for an NTLM handshake instead of Basic auth.
+    pub ntlm: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub http: Option<ProxyServer>,
+    pub https: Option<ProxyServer>,
+    pub socks5: Option<ProxyServer>,
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CertificateFormat {
+    #[default]
+    Pem,
+    Pkcs12,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct ClientCertificate {
+    pub name: String,
+    pub format: CertificateFormat,
+    pub cert_path: String,
+    pub key_path: Option<String>,
+    pub passphrase: Option<String>,
+    pub hosts: Vec<String>,
+    pub collections: Vec<String>,
+}
+
+// Connection pool tuning shared by every client; None keeps reqwest's default.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct PoolSettings {
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout_secs: Option<u64>,
+    pub tcp_keepalive_secs: Option<u64>,
+    pub tcp_nodelay: bool,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        PoolSettings {
+            max_idle_per_host: None,
+            idle_timeout_secs: None,
+            tcp_keepalive_secs: None,
+            tcp_nodelay: true,
+        }
+    }
+}
+
+// Binds a spec security scheme of one collection to the auth profile that satisfies it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SchemeCredential {
+    pub collection: String,
+    pub scheme: String,
+    pub profile: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub proxy: ProxySettings,
+    pub client_certificates: Vec<ClientCertificate>,
+    pub ca_certificates: Vec<String>,
+    pub persist_cookies: bool,
+    pub security_credentials: Vec<SchemeCredential>,
+    // Text responses above this many bytes are written to a temp file and read in
+    // chunks; None uses `body::DEFAULT_SPILL_THRESHOLD`.
+    pub large_response_bytes: Option<u64>,
+    // What to do with requests of an OpenAPI collection that break their operation's
+    // parameters or request body.
+    pub request_validation: RequestValidation,
+    pub pool: PoolSettings,
+    pub rate_limit: RateLimitSettings,
+}
+
+pub fn settings_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SETTINGS_FILE)
+}
+
+// A missing or unreadable settings file falls back to defaults rather than blocking startup.
+pub fn load_settings(data_dir: &Path) -> Settings {
+    std::fs::read_to_string(settings_path(data_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(data_dir: &Path, settings: &Settings) -> Result<(), String> {
+    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(settings_path(data_dir), content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_and_tolerate_missing_fields() {
+        let dir = std::env::temp_dir().join(format!("restman-settings-{}", std::process::id()));
+        assert_eq!(load_settings(&dir), Settings::default());
+        let mut settings = Settings::default();
+        settings.proxy.mode = ProxyMode::Manual;
+        settings.proxy.no_proxy = vec!["localhost".into()];
+        save_settings(&dir, &settings).unwrap();
+        assert_eq!(load_settings(&dir), settings);
+        std::fs::write(settings_path(&dir), r#"{"proxy":{"mode":"none"}}"#).unwrap();
+        assert_eq!(load_settings(&dir).proxy.mode, ProxyMode::None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
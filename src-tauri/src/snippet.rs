@@ -0,0 +1,467 @@
+use crate::http::{parse_method, RequestSpec};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SnippetLanguage {
+    Curl,
+    Httpie,
+    Python,
+    JavaScript,
+    Go,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SnippetBody {
+    None,
+    Text(String),
+    Form(Vec<(String, String)>),
+    // Text fields, then (field, path) pairs for files.
+    Multipart(Vec<(String, String)>, Vec<(String, String)>),
+    File(String),
+}
+
+// A request reduced to what a snippet needs: the final URL, header list and body.
+#[derive(Clone, Debug, PartialEq)]
+struct Snippet {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: SnippetBody,
+}
+
+// Mirrors `build_request`: header auth and API key query pairs are applied and default
+// content types filled in. Challenge-based and signed auth depend on the exchange
+// itself and are left out.
+fn snippet(spec: &RequestSpec) -> Result<Snippet, String> {
+    let method = parse_method(&spec.method)?.to_string();
+    let mut url = Url::parse(&spec.url).map_err(|e| e.to_string())?;
+    let mut query = spec.query.clone();
+    let mut headers = spec.headers.clone();
+    if let Some(auth) = &spec.auth {
+        auth.apply_headers(&mut headers);
+        query.extend(auth.query_pair());
+    }
+    if !query.is_empty() {
+        url.query_pairs_mut().extend_pairs(&query);
+    }
+    let has_content_type = |headers: &std::collections::HashMap<String, String>| {
+        headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case("content-type"))
+    };
+    let body = if let Some(payload) = &spec.multipart {
+        headers.retain(|key, _| !key.eq_ignore_ascii_case("content-type"));
+        let mut fields: Vec<(String, String)> = payload
+            .fields
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        fields.sort();
+        let files = payload
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.paths
+                    .iter()
+                    .filter(|path| !path.is_empty())
+                    .map(|path| (file.name.clone(), path.clone()))
+            })
+            .collect();
+        SnippetBody::Multipart(fields, files)
+    } else if let Some(fields) = &spec.form {
+        headers.retain(|key, _| !key.eq_ignore_ascii_case("content-type"));
+        SnippetBody::Form(fields.clone())
+    } else if let Some(path) = spec.body_file.as_deref().filter(|p| !p.is_empty()) {
+        if !has_content_type(&headers) {
+            headers.insert("Content-Type".into(), "application/octet-stream".into());
+        }
+        SnippetBody::File(path.to_string())
+    } else {
+        match spec.body.as_deref().filter(|b| !b.is_empty()) {
+            Some(body) => {
+                if !has_content_type(&headers) {
+                    headers.insert("Content-Type".into(), "application/json".into());
+                }
+                SnippetBody::Text(body.to_string())
+            }
+            None => SnippetBody::None,
+        }
+    };
+    let mut headers: Vec<(String, String)> = headers.into_iter().collect();
+    headers.sort_by_key(|(name, _)| name.to_ascii_lowercase());
+    Ok(Snippet {
+        method,
+        url: url.to_string(),
+        headers,
+        body,
+    })
+}
+
+// POSIX shell single quoting.
+fn shell(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// JSON string escapes are valid in Python, JavaScript and Go string literals alike.
+fn literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file")
+        .to_string()
+}
+
+fn curl(snippet: &Snippet) -> String {
+    let mut lines = vec![format!(
+        "curl -X {} {}",
+        snippet.method,
+        shell(&snippet.url)
+    )];
+    for (name, value) in &snippet.headers {
+        lines.push(format!("-H {}", shell(&format!("{}: {}", name, value))));
+    }
+    match &snippet.body {
+        SnippetBody::None => {}
+        SnippetBody::Text(body) => lines.push(format!("--data-raw {}", shell(body))),
+        SnippetBody::Form(fields) => {
+            for (key, value) in fields {
+                lines.push(format!(
+                    "--data-urlencode {}",
+                    shell(&format!("{}={}", key, value))
+                ));
+            }
+        }
+        SnippetBody::Multipart(fields, files) => {
+            for (key, value) in fields {
+                lines.push(format!(
+                    "--form-string {}",
+                    shell(&format!("{}={}", key, value))
+                ));
+            }
+            for (key, path) in files {
+                lines.push(format!("-F {}", shell(&format!("{}=@{}", key, path))));
+            }
+        }
+        SnippetBody::File(path) => {
+            lines.push(format!("--data-binary {}", shell(&format!("@{}", path))))
+        }
+    }
+    lines.join(" \\\n  ")
+}
+
+fn httpie(snippet: &Snippet) -> String {
+    let mut parts = vec!["http".to_string()];
+    match &snippet.body {
+        SnippetBody::Form(_) => parts.push("--form".into()),
+        SnippetBody::Multipart(..) => parts.push("--multipart".into()),
+        SnippetBody::Text(body) => parts.push(format!("--raw {}", shell(body))),
+        SnippetBody::None | SnippetBody::File(_) => {}
+    }
+    parts.push(snippet.method.clone());
+    parts.push(shell(&snippet.url));
+    // Flags, method and URL on the first line, then one request item per line.
+    let mut parts = vec![parts.join(" ")];
+    for (name, value) in &snippet.headers {
+        parts.push(shell(&format!("{}:{}", name, value)));
+    }
+    match &snippet.body {
+        SnippetBody::Form(fields) => {
+            for (key, value) in fields {
+                parts.push(shell(&format!("{}={}", key, value)));
+            }
+        }
+        SnippetBody::Multipart(fields, files) => {
+            for (key, value) in fields {
+                parts.push(shell(&format!("{}={}", key, value)));
+            }
+            for (key, path) in files {
+                parts.push(shell(&format!("{}@{}", key, path)));
+            }
+        }
+        SnippetBody::File(path) => parts.push(format!("< {}", shell(path))),
+        SnippetBody::None | SnippetBody::Text(_) => {}
+    }
+    parts.join(" \\\n  ")
+}
+
+fn python_pairs(pairs: &[(String, String)]) -> String {
+    let items: Vec<String> = pairs
+        .iter()
+        .map(|(key, value)| format!("        ({}, {}),\n", literal(key), literal(value)))
+        .collect();
+    format!("[\n{}    ]", items.concat())
+}
+
+fn python(snippet: &Snippet) -> String {
+    let mut arguments = vec![literal(&snippet.method), literal(&snippet.url)];
+    if !snippet.headers.is_empty() {
+        let headers: Vec<String> = snippet
+            .headers
+            .iter()
+            .map(|(name, value)| format!("        {}: {},\n", literal(name), literal(value)))
+            .collect();
+        arguments.push(format!("headers={{\n{}    }}", headers.concat()));
+    }
+    match &snippet.body {
+        SnippetBody::None => {}
+        SnippetBody::Text(body) => arguments.push(format!("data={}", literal(body))),
+        SnippetBody::Form(fields) => arguments.push(format!("data={}", python_pairs(fields))),
+        SnippetBody::Multipart(fields, files) => {
+            if !fields.is_empty() {
+                arguments.push(format!("data={}", python_pairs(fields)));
+            }
+            let files: Vec<String> = files
+                .iter()
+                .map(|(key, path)| {
+                    format!(
+                        "        ({}, open({}, \"rb\")),\n",
+                        literal(key),
+                        literal(path)
+                    )
+                })
+                .collect();
+            arguments.push(format!("files=[\n{}    ]", files.concat()));
+        }
+        SnippetBody::File(path) => arguments.push(format!("data=open({}, \"rb\")", literal(path))),
+    }
+    let arguments: Vec<String> = arguments
+        .into_iter()
+        .map(|argument| format!("    {},\n", argument))
+        .collect();
+    format!(
+        "import requests\n\nresponse = requests.request(\n{})\nprint(response.status_code)\nprint(response.text)",
+        arguments.concat()
+    )
+}
+
+fn javascript(snippet: &Snippet) -> String {
+    let mut prelude = String::new();
+    let mut options = vec![format!("method: {}", literal(&snippet.method))];
+    if !snippet.headers.is_empty() {
+        let headers: Vec<String> = snippet
+            .headers
+            .iter()
+            .map(|(name, value)| format!("    {}: {},\n", literal(name), literal(value)))
+            .collect();
+        options.push(format!("headers: {{\n{}  }}", headers.concat()));
+    }
+    match &snippet.body {
+        SnippetBody::None => {}
+        SnippetBody::Text(body) => options.push(format!("body: {}", literal(body))),
+        SnippetBody::Form(fields) => {
+            let pairs: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("    [{}, {}],\n", literal(key), literal(value)))
+                .collect();
+            options.push(format!(
+                "body: new URLSearchParams([\n{}  ])",
+                pairs.concat()
+            ));
+        }
+        SnippetBody::Multipart(fields, files) => {
+            if !files.is_empty() {
+                prelude.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+            }
+            prelude.push_str("const form = new FormData();\n");
+            for (key, value) in fields {
+                prelude.push_str(&format!(
+                    "form.append({}, {});\n",
+                    literal(key),
+                    literal(value)
+                ));
+            }
+            for (key, path) in files {
+                prelude.push_str(&format!(
+                    "form.append({}, await openAsBlob({}), {});\n",
+                    literal(key),
+                    literal(path),
+                    literal(&file_name(path))
+                ));
+            }
+            prelude.push('\n');
+            options.push("body: form".into());
+        }
+        SnippetBody::File(path) => {
+            prelude.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+            options.push(format!("body: await openAsBlob({})", literal(path)));
+        }
+    }
+    let options: Vec<String> = options
+        .into_iter()
+        .map(|option| format!("  {},\n", option))
+        .collect();
+    format!(
+        "{}const response = await fetch({}, {{\n{}}});\nconsole.log(response.status);\nconsole.log(await response.text());",
+        prelude,
+        literal(&snippet.url),
+        options.concat()
+    )
+}
+
+fn go(snippet: &Snippet) -> String {
+    let mut imports = vec!["fmt", "io", "net/http"];
+    let mut setup = String::new();
+    let mut headers = snippet.headers.clone();
+    let mut content_type = None;
+    let body = match &snippet.body {
+        SnippetBody::None => "nil",
+        SnippetBody::Text(text) => {
+            imports.push("strings");
+            setup.push_str(&format!("\tbody := strings.NewReader({})\n", literal(text)));
+            "body"
+        }
+        SnippetBody::Form(fields) => {
+            imports.extend(["net/url", "strings"]);
+            setup.push_str("\tform := url.Values{}\n");
+            for (key, value) in fields {
+                setup.push_str(&format!(
+                    "\tform.Add({}, {})\n",
+                    literal(key),
+                    literal(value)
+                ));
+            }
+            setup.push_str("\tbody := strings.NewReader(form.Encode())\n");
+            headers.push((
+                "Content-Type".into(),
+                "application/x-www-form-urlencoded".into(),
+            ));
+            "body"
+        }
+        SnippetBody::Multipart(fields, files) => {
+            imports.extend(["bytes", "mime/multipart"]);
+            setup.push_str("\tvar body bytes.Buffer\n\twriter := multipart.NewWriter(&body)\n");
+            for (key, value) in fields {
+                setup.push_str(&format!(
+                    "\twriter.WriteField({}, {})\n",
+                    literal(key),
+                    literal(value)
+                ));
+            }
+            if !files.is_empty() {
+                imports.push("os");
+            }
+            for (key, path) in files {
+                setup.push_str(&format!(
+                    "\tif file, err := os.Open({}); err == nil {{\n\t\tpart, _ := writer.CreateFormFile({}, {})\n\t\tio.Copy(part, file)\n\t\tfile.Close()\n\t}}\n",
+                    literal(path),
+                    literal(key),
+                    literal(&file_name(path))
+                ));
+            }
+            setup.push_str("\twriter.Close()\n");
+            content_type = Some("writer.FormDataContentType()");
+            "&body"
+        }
+        SnippetBody::File(path) => {
+            imports.push("os");
+            setup.push_str(&format!(
+                "\tbody, err := os.Open({})\n\tif err != nil {{\n\t\tpanic(err)\n\t}}\n\tdefer body.Close()\n",
+                literal(path)
+            ));
+            "body"
+        }
+    };
+    imports.sort();
+    let imports: Vec<String> = imports
+        .iter()
+        .map(|import| format!("\t{}\n", literal(import)))
+        .collect();
+    let mut code = format!(
+        "package main\n\nimport (\n{})\n\nfunc main() {{\n{}\treq, err := http.NewRequest({}, {}, {})\n\tif err != nil {{\n\t\tpanic(err)\n\t}}\n",
+        imports.concat(),
+        setup,
+        literal(&snippet.method),
+        literal(&snippet.url),
+        body
+    );
+    for (name, value) in &headers {
+        code.push_str(&format!(
+            "\treq.Header.Set({}, {})\n",
+            literal(name),
+            literal(value)
+        ));
+    }
+    if let Some(content_type) = content_type {
+        code.push_str(&format!(
+            "\treq.Header.Set(\"Content-Type\", {})\n",
+            content_type
+        ));
+    }
+    code.push_str(
+        "\tresp, err := http.DefaultClient.Do(req)\n\tif err != nil {\n\t\tpanic(err)\n\t}\n\tdefer resp.Body.Close()\n\tdata, _ := io.ReadAll(resp.Body)\n\tfmt.Println(resp.Status)\n\tfmt.Println(string(data))\n}",
+    );
+    code
+}
+
+pub fn render(spec: &RequestSpec, language: SnippetLanguage) -> Result<String, String> {
+    let snippet = snippet(spec)?;
+    Ok(match language {
+        SnippetLanguage::Curl => curl(&snippet),
+        SnippetLanguage::Httpie => httpie(&snippet),
+        SnippetLanguage::Python => python(&snippet),
+        SnippetLanguage::JavaScript => javascript(&snippet),
+        SnippetLanguage::Go => go(&snippet),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Auth;
+    use crate::body::DEFAULT_SPILL_THRESHOLD;
+    use std::collections::HashMap;
+
+    #[test]
+    fn renders_requests_with_auth_query_and_body() {
+        let spec = RequestSpec {
+            method: "post".into(),
+            url: "https://api.example.com/items".into(),
+            query: vec![("q".into(), "a b".into())],
+            headers: HashMap::from([("X-Note".into(), "it's".into())]),
+            body: Some("{\"name\": \"x\"}".into()),
+            multipart: None,
+            form: None,
+            body_file: None,
+            decompress: true,
+            spill_threshold: DEFAULT_SPILL_THRESHOLD,
+            auth: Some(Auth::Bearer {
+                token: "t0k".into(),
+            }),
+            proxy_ntlm: None,
+        };
+        assert_eq!(
+            render(&spec, SnippetLanguage::Curl).unwrap(),
+            "curl -X POST 'https://api.example.com/items?q=a+b' \\\n  -H 'Authorization: Bearer t0k' \\\n  -H 'Content-Type: application/json' \\\n  -H 'X-Note: it'\\''s' \\\n  --data-raw '{\"name\": \"x\"}'"
+        );
+        let python = render(&spec, SnippetLanguage::Python).unwrap();
+        assert!(python.contains("    data=\"{\\\"name\\\": \\\"x\\\"}\",\n"));
+        assert!(python.contains("        \"Authorization\": \"Bearer t0k\",\n"));
+        let go = render(&spec, SnippetLanguage::Go).unwrap();
+        assert!(go.contains("\t\"fmt\"\n\t\"io\"\n\t\"net/http\"\n\t\"strings\"\n"));
+        assert!(
+            go.contains("http.NewRequest(\"POST\", \"https://api.example.com/items?q=a+b\", body)")
+        );
+
+        let spec = RequestSpec {
+            form: Some(vec![("a".into(), "1".into())]),
+            headers: HashMap::new(),
+            auth: None,
+            ..spec
+        };
+        assert_eq!(
+            render(&spec, SnippetLanguage::Httpie).unwrap(),
+            "http --form POST 'https://api.example.com/items?q=a+b' \\\n  'a=1'"
+        );
+        assert!(render(&spec, SnippetLanguage::JavaScript)
+            .unwrap()
+            .contains("  body: new URLSearchParams([\n    [\"a\", \"1\"],\n  ]),\n"));
+    }
+}
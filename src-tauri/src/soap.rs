@@ -0,0 +1,760 @@
+use crate::http::RequestInput;
+use crate::saved::{new_id, CollectionImport, SavedRequest};
+use chrono::Utc;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::{HashMap, HashSet};
+
+const SOAP11_ENVELOPE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+const SOAP12_ENVELOPE: &str = "http://www.w3.org/2003/05/soap-envelope";
+const SOAP11_BINDING: &str = "http://schemas.xmlsoap.org/wsdl/soap/";
+const SOAP12_BINDING: &str = "http://schemas.xmlsoap.org/wsdl/soap12/";
+const MAX_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SoapVersion {
+    Soap11,
+    Soap12,
+}
+
+// A parsed XML element. Names are local names; `namespace` is resolved from the
+// prefixes in scope. QName attribute values (`type="tns:Order"`) are matched by local
+// name only, which is enough for the WSDLs seen in practice.
+#[derive(Clone, Debug, Default)]
+struct Element {
+    name: String,
+    namespace: String,
+    attributes: HashMap<String, String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    // The local part of a QName attribute.
+    fn reference(&self, name: &str) -> Option<&str> {
+        self.attr(name).map(local)
+    }
+
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn first(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn start_element(
+    start: &BytesStart,
+    scopes: &mut Vec<HashMap<String, String>>,
+) -> Result<Element, String> {
+    let mut scope = scopes.last().cloned().unwrap_or_default();
+    let mut attributes = HashMap::new();
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| e.to_string())?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        let value = attribute
+            .unescape_value()
+            .map_err(|e| e.to_string())?
+            .into_owned();
+        if key == "xmlns" {
+            scope.insert(String::new(), value);
+        } else if let Some(prefix) = key.strip_prefix("xmlns:") {
+            scope.insert(prefix.to_string(), value);
+        } else {
+            attributes.insert(key, value);
+        }
+    }
+    let raw = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+    let prefix = raw.split_once(':').map(|(prefix, _)| prefix).unwrap_or("");
+    let element = Element {
+        name: local(&raw).to_string(),
+        namespace: scope.get(prefix).cloned().unwrap_or_default(),
+        attributes,
+        children: Vec::new(),
+    };
+    scopes.push(scope);
+    Ok(element)
+}
+
+fn parse_xml(content: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+    let mut scopes = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        match reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML: {}", e))?
+        {
+            Event::Start(start) => {
+                let element = start_element(&start, &mut scopes)?;
+                stack.push(element);
+            }
+            Event::Empty(start) => {
+                let element = start_element(&start, &mut scopes)?;
+                scopes.pop();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::End(_) => {
+                scopes.pop();
+                let element = stack.pop().ok_or("Invalid XML: unbalanced tags")?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Eof => return Err("Invalid XML: no root element".into()),
+            _ => {}
+        }
+    }
+}
+
+// The SOAP version of `body` if it is an envelope, judged by its root element alone.
+pub fn envelope_version(body: &str) -> Option<SoapVersion> {
+    let trimmed = body.trim_start();
+    if !trimmed.starts_with('<') {
+        return None;
+    }
+    let mut reader = Reader::from_str(trimmed);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(start) | Event::Empty(start) => {
+                let element = start_element(&start, &mut Vec::new()).ok()?;
+                return match (element.name.as_str(), element.namespace.as_str()) {
+                    ("Envelope", SOAP11_ENVELOPE) => Some(SoapVersion::Soap11),
+                    ("Envelope", SOAP12_ENVELOPE) => Some(SoapVersion::Soap12),
+                    _ => None,
+                };
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+fn header_key(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .cloned()
+}
+
+// Gives XML bodies sent without a content type the one their kind calls for, in place
+// of the JSON default. SOAP 1.1 actions must be quoted; SOAP 1.2 carries the action as a
+// content type parameter rather than a SOAPAction header.
+pub fn prepare_headers(body: &str, headers: &mut HashMap<String, String>) {
+    let has_content_type = header_key(headers, "content-type").is_some();
+    let action_key = header_key(headers, "soapaction");
+    match envelope_version(body) {
+        Some(SoapVersion::Soap11) => {
+            if let Some(key) = action_key {
+                let action = headers[&key].trim().to_string();
+                if !action.starts_with('"') {
+                    headers.insert(key, format!("\"{}\"", action));
+                }
+            }
+            if !has_content_type {
+                headers.insert("Content-Type".into(), "text/xml; charset=utf-8".into());
+            }
+        }
+        Some(SoapVersion::Soap12) if !has_content_type => {
+            let mut content_type = "application/soap+xml; charset=utf-8".to_string();
+            if let Some(action) = action_key.and_then(|key| headers.remove(&key)) {
+                let action = action.trim().trim_matches('"');
+                if !action.is_empty() {
+                    content_type = format!("{}; action=\"{}\"", content_type, action);
+                }
+            }
+            headers.insert("Content-Type".into(), content_type);
+        }
+        Some(SoapVersion::Soap12) => {}
+        None if !has_content_type && body.trim_start().starts_with('<') => {
+            headers.insert("Content-Type".into(), "application/xml".into());
+        }
+        None => {}
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Schema<'a> {
+    namespace: &'a str,
+    qualified: bool,
+}
+
+// Global schema components by local name, with the schema that declares them.
+struct Types<'a> {
+    elements: HashMap<&'a str, (&'a Element, Schema<'a>)>,
+    types: HashMap<&'a str, (&'a Element, Schema<'a>)>,
+}
+
+fn sample(builtin: &str) -> &'static str {
+    match builtin {
+        "int" | "integer" | "long" | "short" | "byte" | "unsignedInt" | "unsignedLong"
+        | "unsignedShort" | "unsignedByte" | "nonNegativeInteger" | "positiveInteger"
+        | "negativeInteger" | "nonPositiveInteger" => "0",
+        "decimal" | "float" | "double" => "0.0",
+        "boolean" => "false",
+        "date" => "2000-01-01",
+        "dateTime" => "2000-01-01T00:00:00",
+        "time" => "00:00:00",
+        "base64Binary" | "hexBinary" => "",
+        _ => "?",
+    }
+}
+
+// Renders elements from the schemas as an indented template, declaring a prefix for
+// each namespace used.
+struct Renderer<'a> {
+    types: &'a Types<'a>,
+    prefixes: Vec<(String, String)>,
+    lines: Vec<String>,
+}
+
+impl<'a> Renderer<'a> {
+    fn prefix(&mut self, namespace: &str) -> String {
+        if let Some((_, prefix)) = self.prefixes.iter().find(|(ns, _)| ns == namespace) {
+            return prefix.clone();
+        }
+        let prefix = format!("ns{}", self.prefixes.len() + 1);
+        self.prefixes.push((namespace.to_string(), prefix.clone()));
+        prefix
+    }
+
+    fn push(&mut self, indent: usize, line: String) {
+        self.lines.push(format!("{}{}", "   ".repeat(indent), line));
+    }
+
+    // The text sample for a simple type: its first enumeration value, or a sample of
+    // the built-in type it restricts.
+    fn simple(&self, simple_type: &Element, path: &mut HashSet<String>) -> String {
+        let Some(restriction) = simple_type.first("restriction") else {
+            return "?".into();
+        };
+        if let Some(value) = restriction
+            .first("enumeration")
+            .and_then(|e| e.attr("value"))
+        {
+            return value.to_string();
+        }
+        self.text(restriction.reference("base").unwrap_or("string"), path)
+    }
+
+    fn text(&self, type_name: &str, path: &mut HashSet<String>) -> String {
+        match self.types.types.get(type_name) {
+            Some((definition, _))
+                if definition.name == "simpleType" && path.insert(type_name.to_string()) =>
+            {
+                let text = self.simple(definition, path);
+                path.remove(type_name);
+                text
+            }
+            _ => sample(type_name).to_string(),
+        }
+    }
+
+    // The child elements of a complex type, base type first for extensions.
+    fn particles(
+        &mut self,
+        definition: &Element,
+        schema: Schema<'a>,
+        indent: usize,
+        path: &mut HashSet<String>,
+    ) {
+        for child in &definition.children {
+            match child.name.as_str() {
+                "sequence" | "all" => self.particles(child, schema, indent, path),
+                "choice" => {
+                    if let Some(first) = child.children.first() {
+                        let option = Element {
+                            children: vec![first.clone()],
+                            ..Element::default()
+                        };
+                        self.push(
+                            indent,
+                            "<!--You have a CHOICE of the next items at this level-->".into(),
+                        );
+                        self.particles(&option, schema, indent, path);
+                    }
+                }
+                "element" => self.element(child, schema, false, indent, path),
+                "complexContent" => {
+                    let Some(derivation) = child
+                        .first("extension")
+                        .or_else(|| child.first("restriction"))
+                    else {
+                        continue;
+                    };
+                    if let Some(base) = derivation.reference("base") {
+                        if let Some(&(base_type, base_schema)) = self.types.types.get(base) {
+                            if path.insert(base.to_string()) {
+                                self.particles(base_type, base_schema, indent, path);
+                                path.remove(base);
+                            }
+                        }
+                    }
+                    self.particles(derivation, schema, indent, path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(
+        &mut self,
+        element: &Element,
+        schema: Schema<'a>,
+        global: bool,
+        indent: usize,
+        path: &mut HashSet<String>,
+    ) {
+        if let Some(reference) = element.reference("ref") {
+            if let Some(&(target, target_schema)) = self.types.elements.get(reference) {
+                let mut referenced = target.clone();
+                for occurs in ["minOccurs", "maxOccurs"] {
+                    if let Some(value) = element.attr(occurs) {
+                        referenced.attributes.insert(occurs.into(), value.into());
+                    }
+                }
+                self.element(&referenced, target_schema, true, indent, path);
+            }
+            return;
+        }
+        let name = element.attr("name").unwrap_or("element");
+        let tag = if global || schema.qualified {
+            format!("{}:{}", self.prefix(schema.namespace), name)
+        } else {
+            name.to_string()
+        };
+        match (element.attr("minOccurs"), element.attr("maxOccurs")) {
+            (_, Some(max)) if max != "1" && max != "0" => {
+                self.push(indent, "<!--Zero or more repetitions:-->".into());
+            }
+            (Some("0"), _) => self.push(indent, "<!--Optional:-->".into()),
+            _ => {}
+        }
+        let named = element.reference("type").and_then(|type_name| {
+            self.types
+                .types
+                .get(type_name)
+                .map(|found| (type_name, *found))
+        });
+        let (complex, complex_schema, type_name) = match (element.first("complexType"), named) {
+            (Some(inline), _) => (Some(inline), schema, None),
+            (None, Some((type_name, (definition, type_schema))))
+                if definition.name == "complexType" =>
+            {
+                (Some(definition), type_schema, Some(type_name))
+            }
+            _ => (None, schema, None),
+        };
+        let Some(complex) = complex else {
+            let text = match element.first("simpleType") {
+                Some(simple) => self.simple(simple, path),
+                None => self.text(element.reference("type").unwrap_or("string"), path),
+            };
+            self.push(indent, format!("<{}>{}</{}>", tag, text, tag));
+            return;
+        };
+        if let Some(simple) = complex.first("simpleContent") {
+            let base = simple
+                .first("extension")
+                .or_else(|| simple.first("restriction"))
+                .and_then(|derivation| derivation.reference("base"))
+                .unwrap_or("string");
+            let text = self.text(base, path);
+            self.push(indent, format!("<{}>{}</{}>", tag, text, tag));
+            return;
+        }
+        let recursive = type_name.is_some_and(|name| !path.insert(name.to_string()));
+        if recursive || indent > MAX_DEPTH {
+            self.push(indent, format!("<{}/>", tag));
+            return;
+        }
+        let start = self.lines.len();
+        self.particles(complex, complex_schema, indent + 1, path);
+        if let Some(name) = type_name {
+            path.remove(name);
+        }
+        if self.lines.len() == start {
+            self.push(indent, format!("<{}/>", tag));
+        } else {
+            self.lines
+                .insert(start, format!("{}<{}>", "   ".repeat(indent), tag));
+            self.push(indent, format!("</{}>", tag));
+        }
+    }
+}
+
+struct Operation<'a> {
+    name: &'a str,
+    action: String,
+    rpc: bool,
+    // The namespace of an rpc operation's wrapper element.
+    namespace: String,
+    parts: Vec<&'a Element>,
+}
+
+fn envelope(version: SoapVersion, operation: &Operation, types: &Types) -> String {
+    let mut renderer = Renderer {
+        types,
+        prefixes: Vec::new(),
+        lines: Vec::new(),
+    };
+    let mut path = HashSet::new();
+    let (indent, wrapper) = if operation.rpc {
+        let prefix = renderer.prefix(&operation.namespace);
+        (3, Some(format!("{}:{}", prefix, operation.name)))
+    } else {
+        (2, None)
+    };
+    for part in &operation.parts {
+        if let Some(element) = part.reference("element") {
+            if let Some(&(definition, schema)) = types.elements.get(element) {
+                renderer.element(definition, schema, true, indent, &mut path);
+            }
+        } else {
+            // rpc parts and typed document parts are unqualified elements named after
+            // the part.
+            let unqualified = Schema {
+                namespace: "",
+                qualified: false,
+            };
+            let part_element = Element {
+                name: "element".into(),
+                attributes: part.attributes.clone(),
+                ..Element::default()
+            };
+            renderer.element(&part_element, unqualified, false, indent, &mut path);
+        }
+    }
+    let (prefix, namespace) = match version {
+        SoapVersion::Soap11 => ("soapenv", SOAP11_ENVELOPE),
+        SoapVersion::Soap12 => ("soap", SOAP12_ENVELOPE),
+    };
+    let declarations: String = renderer
+        .prefixes
+        .iter()
+        .map(|(namespace, prefix)| format!(" xmlns:{}=\"{}\"", prefix, namespace))
+        .collect();
+    let mut lines = vec![
+        format!(
+            "<{}:Envelope xmlns:{}=\"{}\"{}>",
+            prefix, prefix, namespace, declarations
+        ),
+        format!("   <{}:Header/>", prefix),
+        format!("   <{}:Body>", prefix),
+    ];
+    match wrapper {
+        Some(wrapper) => {
+            lines.push(format!("      <{}>", wrapper));
+            lines.extend(renderer.lines);
+            lines.push(format!("      </{}>", wrapper));
+        }
+        None => lines.extend(renderer.lines),
+    }
+    lines.push(format!("   </{}:Body>", prefix));
+    lines.push(format!("</{}:Envelope>", prefix));
+    lines.join("\n")
+}
+
+fn headers(version: SoapVersion, action: &str) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    match version {
+        SoapVersion::Soap11 => {
+            headers.insert("Content-Type".into(), "text/xml; charset=utf-8".into());
+            headers.insert("SOAPAction".into(), format!("\"{}\"", action));
+        }
+        SoapVersion::Soap12 if action.is_empty() => {
+            headers.insert(
+                "Content-Type".into(),
+                "application/soap+xml; charset=utf-8".into(),
+            );
+        }
+        SoapVersion::Soap12 => {
+            headers.insert(
+                "Content-Type".into(),
+                format!("application/soap+xml; charset=utf-8; action=\"{}\"", action),
+            );
+        }
+    }
+    headers
+}
+
+// Reads a WSDL 1.1 document into saved requests: one per operation of each SOAP port,
+// foldered by service, with an envelope built from the schema types. Schemas must be
+// inline; imported ones are reported in the warnings.
+pub fn import(content: &str) -> Result<CollectionImport, String> {
+    let definitions = parse_xml(content)?;
+    if definitions.name != "definitions" {
+        return Err("Not a WSDL 1.1 document".into());
+    }
+    let mut warnings = Vec::new();
+    if definitions.first("import").is_some() {
+        warnings.push("Imported WSDL documents were not loaded".to_string());
+    }
+    let mut types = Types {
+        elements: HashMap::new(),
+        types: HashMap::new(),
+    };
+    let schemas = definitions
+        .all("types")
+        .flat_map(|section| section.all("schema"));
+    for schema in schemas {
+        let info = Schema {
+            namespace: schema.attr("targetNamespace").unwrap_or(""),
+            qualified: schema.attr("elementFormDefault") == Some("qualified"),
+        };
+        for child in &schema.children {
+            let name = child.attr("name").unwrap_or_default();
+            match child.name.as_str() {
+                "element" => {
+                    types.elements.insert(name, (child, info));
+                }
+                "complexType" | "simpleType" => {
+                    types.types.insert(name, (child, info));
+                }
+                "import" | "include" => {
+                    if let Some(location) = child.attr("schemaLocation") {
+                        warnings.push(format!("External schema {} was not loaded", location));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    let target_namespace = definitions.attr("targetNamespace").unwrap_or("");
+    let messages: HashMap<&str, &Element> = definitions
+        .all("message")
+        .map(|message| (message.attr("name").unwrap_or_default(), message))
+        .collect();
+    let port_types: HashMap<&str, &Element> = definitions
+        .all("portType")
+        .map(|port_type| (port_type.attr("name").unwrap_or_default(), port_type))
+        .collect();
+    let bindings: HashMap<&str, &Element> = definitions
+        .all("binding")
+        .map(|binding| (binding.attr("name").unwrap_or_default(), binding))
+        .collect();
+
+    let now = Utc::now();
+    let mut saved_requests = Vec::new();
+    for service in definitions.all("service") {
+        let service_name = service.attr("name").unwrap_or("Service");
+        let ports: Vec<&Element> = service.all("port").collect();
+        for port in &ports {
+            let port_name = port.attr("name").unwrap_or("Port");
+            let Some(binding) = port
+                .reference("binding")
+                .and_then(|name| bindings.get(name))
+            else {
+                warnings.push(format!("{}: binding not found", port_name));
+                continue;
+            };
+            let soap_binding = binding.first("binding");
+            let version = match soap_binding.map(|b| b.namespace.as_str()) {
+                Some(SOAP11_BINDING) => SoapVersion::Soap11,
+                Some(SOAP12_BINDING) => SoapVersion::Soap12,
+                _ => {
+                    warnings.push(format!("{}: only SOAP bindings are imported", port_name));
+                    continue;
+                }
+            };
+            let binding_style = soap_binding
+                .and_then(|b| b.attr("style"))
+                .unwrap_or("document");
+            let address = port
+                .first("address")
+                .and_then(|address| address.attr("location"))
+                .unwrap_or_default();
+            let port_type = binding
+                .reference("type")
+                .and_then(|name| port_types.get(name));
+            let folder = if ports.len() > 1 {
+                format!("{}/{}", service_name, port_name)
+            } else {
+                service_name.to_string()
+            };
+            for bound in binding.all("operation") {
+                let name = bound.attr("name").unwrap_or_default();
+                let soap_operation = bound.first("operation");
+                let body = bound.first("input").and_then(|input| input.first("body"));
+                let parts = port_type
+                    .and_then(|port_type| {
+                        port_type
+                            .all("operation")
+                            .find(|op| op.attr("name") == Some(name))
+                    })
+                    .and_then(|operation| operation.first("input"))
+                    .and_then(|input| input.reference("message"))
+                    .and_then(|message| messages.get(message))
+                    .map(|message| message.all("part").collect())
+                    .unwrap_or_default();
+                let operation = Operation {
+                    name,
+                    action: soap_operation
+                        .and_then(|op| op.attr("soapAction"))
+                        .unwrap_or_default()
+                        .to_string(),
+                    rpc: soap_operation
+                        .and_then(|op| op.attr("style"))
+                        .unwrap_or(binding_style)
+                        == "rpc",
+                    namespace: body
+                        .and_then(|body| body.attr("namespace"))
+                        .unwrap_or(target_namespace)
+                        .to_string(),
+                    parts,
+                };
+                saved_requests.push(SavedRequest {
+                    id: new_id(),
+                    name: name.to_string(),
+                    folder: folder.clone(),
+                    request: RequestInput {
+                        method: "POST".into(),
+                        url: address.to_string(),
+                        headers: headers(version, &operation.action),
+                        body: Some(envelope(version, &operation, &types)),
+                        ..RequestInput::default()
+                    },
+                    examples: Vec::new(),
+                    created_at: now,
+                    updated_at: now,
+                });
+            }
+        }
+    }
+    let name = definitions
+        .attr("name")
+        .or_else(|| definitions.first("service").and_then(|s| s.attr("name")))
+        .unwrap_or("WSDL")
+        .to_string();
+    Ok(CollectionImport {
+        name,
+        saved_requests,
+        environments: Vec::new(),
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WSDL: &str = r#"<?xml version="1.0"?>
+<wsdl:definitions name="Orders" targetNamespace="urn:orders"
+    xmlns:wsdl="http://schemas.xmlsoap.org/wsdl/"
+    xmlns:soap="http://schemas.xmlsoap.org/wsdl/soap/"
+    xmlns:soap12="http://schemas.xmlsoap.org/wsdl/soap12/"
+    xmlns:xs="http://www.w3.org/2001/XMLSchema"
+    xmlns:tns="urn:orders">
+  <wsdl:types>
+    <xs:schema targetNamespace="urn:orders" elementFormDefault="qualified">
+      <xs:simpleType name="Status">
+        <xs:restriction base="xs:string">
+          <xs:enumeration value="OPEN"/>
+          <xs:enumeration value="CLOSED"/>
+        </xs:restriction>
+      </xs:simpleType>
+      <xs:complexType name="Line">
+        <xs:sequence>
+          <xs:element name="sku" type="xs:string"/>
+          <xs:element name="quantity" type="xs:int"/>
+        </xs:sequence>
+      </xs:complexType>
+      <xs:element name="GetOrder">
+        <xs:complexType>
+          <xs:sequence>
+            <xs:element name="id" type="xs:long"/>
+            <xs:element name="status" type="tns:Status" minOccurs="0"/>
+            <xs:element name="line" type="tns:Line" maxOccurs="unbounded"/>
+          </xs:sequence>
+        </xs:complexType>
+      </xs:element>
+    </xs:schema>
+  </wsdl:types>
+  <wsdl:message name="GetOrderRequest"><wsdl:part name="parameters" element="tns:GetOrder"/></wsdl:message>
+  <wsdl:portType name="OrdersPort">
+    <wsdl:operation name="GetOrder"><wsdl:input message="tns:GetOrderRequest"/></wsdl:operation>
+  </wsdl:portType>
+  <wsdl:binding name="OrdersSoap" type="tns:OrdersPort">
+    <soap:binding transport="http://schemas.xmlsoap.org/soap/http" style="document"/>
+    <wsdl:operation name="GetOrder">
+      <soap:operation soapAction="urn:orders/GetOrder"/>
+      <wsdl:input><soap:body use="literal"/></wsdl:input>
+    </wsdl:operation>
+  </wsdl:binding>
+  <wsdl:binding name="OrdersSoap12" type="tns:OrdersPort">
+    <soap12:binding transport="http://schemas.xmlsoap.org/soap/http"/>
+    <wsdl:operation name="GetOrder">
+      <soap12:operation soapAction="urn:orders/GetOrder"/>
+      <wsdl:input><soap12:body use="literal"/></wsdl:input>
+    </wsdl:operation>
+  </wsdl:binding>
+  <wsdl:service name="OrderService">
+    <wsdl:port name="OrdersSoap" binding="tns:OrdersSoap"><soap:address location="https://shop.test/orders"/></wsdl:port>
+    <wsdl:port name="OrdersSoap12" binding="tns:OrdersSoap12"><soap12:address location="https://shop.test/orders12"/></wsdl:port>
+  </wsdl:service>
+</wsdl:definitions>"#;
+
+    #[test]
+    fn imports_operations_with_envelopes_and_labels_soap_bodies() {
+        let imported = import(WSDL).unwrap();
+        assert_eq!(imported.name, "Orders");
+        let [soap11, soap12] = &imported.saved_requests[..] else {
+            panic!("expected one request per port");
+        };
+        assert_eq!(soap11.folder, "OrderService/OrdersSoap");
+        assert_eq!(soap11.request.url, "https://shop.test/orders");
+        assert_eq!(
+            soap11.request.headers["SOAPAction"],
+            "\"urn:orders/GetOrder\""
+        );
+        let body = soap11.request.body.as_deref().unwrap();
+        assert_eq!(
+            body,
+            r#"<soapenv:Envelope xmlns:soapenv="http://schemas.xmlsoap.org/soap/envelope/" xmlns:ns1="urn:orders">
+   <soapenv:Header/>
+   <soapenv:Body>
+      <ns1:GetOrder>
+         <ns1:id>0</ns1:id>
+         <!--Optional:-->
+         <ns1:status>OPEN</ns1:status>
+         <!--Zero or more repetitions:-->
+         <ns1:line>
+            <ns1:sku>?</ns1:sku>
+            <ns1:quantity>0</ns1:quantity>
+         </ns1:line>
+      </ns1:GetOrder>
+   </soapenv:Body>
+</soapenv:Envelope>"#
+        );
+        assert_eq!(
+            soap12.request.headers["Content-Type"],
+            "application/soap+xml; charset=utf-8; action=\"urn:orders/GetOrder\""
+        );
+        assert_eq!(
+            envelope_version(soap12.request.body.as_deref().unwrap()),
+            Some(SoapVersion::Soap12)
+        );
+
+        let mut headers =
+            HashMap::from([("soapaction".to_string(), "urn:orders/GetOrder".to_string())]);
+        prepare_headers(body, &mut headers);
+        assert_eq!(headers["soapaction"], "\"urn:orders/GetOrder\"");
+        assert_eq!(headers["Content-Type"], "text/xml; charset=utf-8");
+        let mut headers = HashMap::new();
+        prepare_headers("<order/>", &mut headers);
+        assert_eq!(headers["Content-Type"], "application/xml");
+    }
+}
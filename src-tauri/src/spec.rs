@@ -0,0 +1,150 @@
+use serde_json::{Map, Number, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+// Trusts an explicit content type or file extension, otherwise sniffs the content:
+// JSON documents start with `{`, anything else is treated as YAML.
+pub fn detect_format(content: &str, content_type: Option<&str>, location: &str) -> SpecFormat {
+    let content_type = content_type.unwrap_or("").to_ascii_lowercase();
+    if content_type.contains("yaml") || content_type.contains("yml") {
+        return SpecFormat::Yaml;
+    }
+    if content_type.contains("json") {
+        return SpecFormat::Json;
+    }
+    let path = location
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if path.ends_with(".yaml") || path.ends_with(".yml") {
+        return SpecFormat::Yaml;
+    }
+    if path.ends_with(".json") {
+        return SpecFormat::Json;
+    }
+    match content
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .chars()
+        .next()
+    {
+        Some('{') => SpecFormat::Json,
+        _ => SpecFormat::Yaml,
+    }
+}
+
+// YAML allows non-string keys (status codes are usually plain integers), so mapping
+// keys are stringified to fit the JSON model the parser works on.
+fn yaml_key(key: serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s,
+        serde_yaml::Value::Null => "null".into(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        other => serde_yaml::to_string(&other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null)
+            }
+        }
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => {
+            Value::Array(items.into_iter().map(yaml_to_json).collect())
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut object = Map::new();
+            for (key, value) in mapping {
+                object.insert(yaml_key(key), yaml_to_json(value));
+            }
+            Value::Object(object)
+        }
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+pub fn parse_document(
+    content: &str,
+    content_type: Option<&str>,
+    location: &str,
+) -> Result<Value, String> {
+    match detect_format(content, content_type, location) {
+        SpecFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        SpecFormat::Yaml => {
+            let mut value: serde_yaml::Value =
+                serde_yaml::from_str(content).map_err(|e| format!("Invalid YAML: {}", e))?;
+            // Resolves `<<: *anchor` merge keys, which specs use to share fragments.
+            value
+                .apply_merge()
+                .map_err(|e| format!("Invalid YAML: {}", e))?;
+            Ok(yaml_to_json(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detects_format_from_type_extension_and_content() {
+        let yaml = "openapi: 3.0.0\n";
+        assert_eq!(
+            detect_format(yaml, Some("application/x-yaml"), "https://x/spec"),
+            SpecFormat::Yaml
+        );
+        assert_eq!(
+            detect_format("{}", Some("application/json; charset=utf-8"), "spec.yaml"),
+            SpecFormat::Json
+        );
+        assert_eq!(
+            detect_format(yaml, Some("text/plain"), "https://x/openapi.yml?v=2"),
+            SpecFormat::Yaml
+        );
+        assert_eq!(
+            detect_format("  {\"a\":1}", None, "https://x/spec"),
+            SpecFormat::Json
+        );
+        assert_eq!(
+            detect_format(yaml, None, "https://x/spec"),
+            SpecFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn parses_yaml_with_integer_keys() {
+        let doc = parse_document(
+            "openapi: 3.0.0\npaths:\n  /pets:\n    get:\n      responses:\n        200:\n          description: ok\n",
+            None,
+            "pets.yaml",
+        )
+        .unwrap();
+        assert_eq!(
+            doc["paths"]["/pets"]["get"]["responses"]["200"],
+            json!({"description": "ok"})
+        );
+        assert_eq!(doc["openapi"], "3.0.0");
+    }
+}
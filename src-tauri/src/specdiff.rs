@@ -0,0 +1,571 @@
+use crate::{Endpoint, OpenApiCollection, Parameter};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Impact {
+    // Existing clients can stop working.
+    Breaking,
+    NonBreaking,
+}
+
+// One difference between two versions of a spec. `operation` is e.g. "GET /pets/{id}",
+// and `location` says where in it: "parameter query.limit", "request body /name",
+// "response 200 /items/0".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpecChange {
+    pub impact: Impact,
+    pub operation: Option<String>,
+    pub location: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpecDiff {
+    pub old_name: String,
+    pub new_name: String,
+    pub changes: Vec<SpecChange>,
+    pub breaking: usize,
+    pub non_breaking: usize,
+}
+
+// Requests go from client to server, responses the other way, which flips whether
+// narrowing or widening a schema breaks clients.
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+struct Changes<'a> {
+    operation: Option<&'a str>,
+    list: Vec<SpecChange>,
+}
+
+impl Changes<'_> {
+    fn push(&mut self, impact: Impact, location: &str, message: String) {
+        self.list.push(SpecChange {
+            impact,
+            operation: self.operation.map(String::from),
+            location: location.to_string(),
+            message,
+        });
+    }
+}
+
+fn breaking_if(condition: bool) -> Impact {
+    if condition {
+        Impact::Breaking
+    } else {
+        Impact::NonBreaking
+    }
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|item| match item {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+fn schema_type(schema: &Value) -> Option<String> {
+    match &schema["type"] {
+        Value::Null => None,
+        Value::String(kind) => Some(kind.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+// Enum values removed break requests that send them; values added break clients that
+// only expect the old ones in responses.
+fn compare_enums(
+    changes: &mut Changes,
+    location: &str,
+    old: &[String],
+    new: &[String],
+    direction: Direction,
+) {
+    if old.is_empty() && new.is_empty() {
+        return;
+    }
+    if new.is_empty() {
+        changes.push(
+            Impact::NonBreaking,
+            location,
+            "enum restriction removed".into(),
+        );
+        return;
+    }
+    let removed: Vec<&String> = old.iter().filter(|value| !new.contains(value)).collect();
+    let added: Vec<&String> = new.iter().filter(|value| !old.contains(value)).collect();
+    if old.is_empty() {
+        changes.push(
+            breaking_if(direction == Direction::Request),
+            location,
+            format!("restricted to {}", new.join(", ")),
+        );
+        return;
+    }
+    if !removed.is_empty() {
+        let list: Vec<&str> = removed.iter().map(|value| value.as_str()).collect();
+        changes.push(
+            breaking_if(direction == Direction::Request),
+            location,
+            format!("enum values removed: {}", list.join(", ")),
+        );
+    }
+    if !added.is_empty() {
+        let list: Vec<&str> = added.iter().map(|value| value.as_str()).collect();
+        changes.push(
+            breaking_if(direction == Direction::Response),
+            location,
+            format!("enum values added: {}", list.join(", ")),
+        );
+    }
+}
+
+fn compare_schemas(
+    changes: &mut Changes,
+    location: &str,
+    old: &Value,
+    new: &Value,
+    direction: Direction,
+) {
+    if old == new {
+        return;
+    }
+    if let (Some(old_type), Some(new_type)) = (schema_type(old), schema_type(new)) {
+        if old_type != new_type {
+            changes.push(
+                Impact::Breaking,
+                location,
+                format!("type changed from {} to {}", old_type, new_type),
+            );
+            return;
+        }
+    }
+    compare_enums(
+        changes,
+        location,
+        &strings(&old["enum"]),
+        &strings(&new["enum"]),
+        direction,
+    );
+
+    let old_required = strings(&old["required"]);
+    let new_required = strings(&new["required"]);
+    let empty = serde_json::Map::new();
+    let old_properties = old["properties"].as_object().unwrap_or(&empty);
+    let new_properties = new["properties"].as_object().unwrap_or(&empty);
+    let child = |name: &str| format!("{}/{}", location.trim_end_matches('/'), name);
+    for (name, property) in new_properties {
+        let required = new_required.contains(name);
+        match old_properties.get(name) {
+            None => changes.push(
+                breaking_if(direction == Direction::Request && required),
+                &child(name),
+                if required {
+                    "required property added".into()
+                } else {
+                    "property added".into()
+                },
+            ),
+            Some(previous) => {
+                let was_required = old_required.contains(name);
+                if required && !was_required {
+                    changes.push(
+                        breaking_if(direction == Direction::Request),
+                        &child(name),
+                        "became required".into(),
+                    );
+                } else if was_required && !required {
+                    changes.push(
+                        breaking_if(direction == Direction::Response),
+                        &child(name),
+                        "no longer required".into(),
+                    );
+                }
+                compare_schemas(changes, &child(name), previous, property, direction);
+            }
+        }
+    }
+    for name in old_properties
+        .keys()
+        .filter(|name| !new_properties.contains_key(*name))
+    {
+        changes.push(
+            breaking_if(direction == Direction::Response),
+            &child(name),
+            "property removed".into(),
+        );
+    }
+    if old["items"].is_object() && new["items"].is_object() {
+        compare_schemas(
+            changes,
+            &child("items"),
+            &old["items"],
+            &new["items"],
+            direction,
+        );
+    }
+}
+
+fn parameter_location(param: &Parameter) -> String {
+    format!("parameter {}.{}", param.in_type, param.name)
+}
+
+fn compare_parameters(changes: &mut Changes, old: &[Parameter], new: &[Parameter]) {
+    let find = |list: &'_ [Parameter], param: &Parameter| -> Option<Parameter> {
+        list.iter()
+            .find(|p| p.name == param.name && p.in_type == param.in_type)
+            .cloned()
+    };
+    for param in new {
+        let location = parameter_location(param);
+        let Some(previous) = find(old, param) else {
+            changes.push(
+                breaking_if(param.required),
+                &location,
+                if param.required {
+                    "required parameter added".into()
+                } else {
+                    "parameter added".into()
+                },
+            );
+            continue;
+        };
+        if param.required && !previous.required {
+            changes.push(Impact::Breaking, &location, "became required".into());
+        } else if previous.required && !param.required {
+            changes.push(Impact::NonBreaking, &location, "no longer required".into());
+        }
+        match (&previous.schema_type, &param.schema_type) {
+            (Some(old_type), Some(new_type)) if old_type != new_type => changes.push(
+                Impact::Breaking,
+                &location,
+                format!("type changed from {} to {}", old_type, new_type),
+            ),
+            _ => compare_enums(
+                changes,
+                &location,
+                previous.enum_values.as_deref().unwrap_or_default(),
+                param.enum_values.as_deref().unwrap_or_default(),
+                Direction::Request,
+            ),
+        }
+        if param.deprecated && !previous.deprecated {
+            changes.push(Impact::NonBreaking, &location, "deprecated".into());
+        }
+    }
+    for param in old.iter().filter(|param| find(new, param).is_none()) {
+        changes.push(
+            Impact::Breaking,
+            &parameter_location(param),
+            "parameter removed".into(),
+        );
+    }
+}
+
+fn compare_media_types(changes: &mut Changes, location: &str, old: &[String], new: &[String]) {
+    for media_type in old.iter().filter(|media_type| !new.contains(media_type)) {
+        changes.push(
+            Impact::Breaking,
+            location,
+            format!("media type removed: {}", media_type),
+        );
+    }
+    for media_type in new.iter().filter(|media_type| !old.contains(media_type)) {
+        changes.push(
+            Impact::NonBreaking,
+            location,
+            format!("media type added: {}", media_type),
+        );
+    }
+}
+
+fn response_schema<'a>(endpoint: &'a Endpoint, status: &str) -> Option<&'a Value> {
+    endpoint
+        .response_schemas
+        .iter()
+        .find(|schema| schema.status == status)
+        .and_then(|schema| schema.schema.as_ref())
+}
+
+fn compare_endpoints(changes: &mut Changes, old: &Endpoint, new: &Endpoint) {
+    compare_parameters(changes, &old.parameters, &new.parameters);
+
+    if new.body_required && !old.body_required {
+        changes.push(Impact::Breaking, "request body", "became required".into());
+    } else if old.body_required && !new.body_required {
+        changes.push(
+            Impact::NonBreaking,
+            "request body",
+            "no longer required".into(),
+        );
+    }
+    compare_media_types(
+        changes,
+        "request body",
+        &old.body_media_types,
+        &new.body_media_types,
+    );
+    if let (Some(old_schema), Some(new_schema)) = (&old.body_schema, &new.body_schema) {
+        compare_schemas(
+            changes,
+            "request body /",
+            old_schema,
+            new_schema,
+            Direction::Request,
+        );
+    }
+
+    for (status, response) in &new.responses {
+        let location = format!("response {}", status);
+        let Some(previous) = old.responses.get(status) else {
+            changes.push(Impact::NonBreaking, &location, "response added".into());
+            continue;
+        };
+        compare_media_types(
+            changes,
+            &location,
+            &previous.media_types,
+            &response.media_types,
+        );
+        if let (Some(old_schema), Some(new_schema)) =
+            (response_schema(old, status), response_schema(new, status))
+        {
+            compare_schemas(
+                changes,
+                &format!("{} /", location),
+                old_schema,
+                new_schema,
+                Direction::Response,
+            );
+        }
+    }
+    for status in old
+        .responses
+        .keys()
+        .filter(|status| !new.responses.contains_key(*status))
+    {
+        // Clients lose a documented outcome only when a success goes away.
+        changes.push(
+            breaking_if(status.starts_with('2')),
+            &format!("response {}", status),
+            "response removed".into(),
+        );
+    }
+
+    if serde_json::to_value(&old.security).ok() != serde_json::to_value(&new.security).ok() {
+        changes.push(
+            Impact::Breaking,
+            "security",
+            "security requirements changed".into(),
+        );
+    }
+    if new.deprecated && !old.deprecated {
+        changes.push(Impact::NonBreaking, "operation", "deprecated".into());
+    }
+}
+
+fn endpoints(collection: &OpenApiCollection) -> BTreeMap<String, &Endpoint> {
+    collection
+        .groups
+        .values()
+        .flatten()
+        .map(|endpoint| {
+            (
+                format!("{} {}", endpoint.method.to_uppercase(), endpoint.route),
+                endpoint,
+            )
+        })
+        .collect()
+}
+
+// Operations are matched by method and spec path, so moving them between servers or
+// tags is not a change.
+pub fn diff(old: &OpenApiCollection, new: &OpenApiCollection) -> SpecDiff {
+    let old_endpoints = endpoints(old);
+    let new_endpoints = endpoints(new);
+    let mut all = Vec::new();
+    for (operation, endpoint) in &new_endpoints {
+        let mut changes = Changes {
+            operation: Some(operation),
+            list: Vec::new(),
+        };
+        match old_endpoints.get(operation) {
+            None => changes.push(Impact::NonBreaking, "operation", "operation added".into()),
+            Some(previous) => compare_endpoints(&mut changes, previous, endpoint),
+        }
+        all.extend(changes.list);
+    }
+    for operation in old_endpoints
+        .keys()
+        .filter(|operation| !new_endpoints.contains_key(*operation))
+    {
+        all.push(SpecChange {
+            impact: Impact::Breaking,
+            operation: Some(operation.clone()),
+            location: "operation".into(),
+            message: "operation removed".into(),
+        });
+    }
+    all.sort_by(|a, b| (a.impact, &a.operation).cmp(&(b.impact, &b.operation)));
+    let breaking = all
+        .iter()
+        .filter(|change| change.impact == Impact::Breaking)
+        .count();
+    SpecDiff {
+        old_name: old.name.clone(),
+        new_name: new.name.clone(),
+        non_breaking: all.len() - breaking,
+        breaking,
+        changes: all,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_openapi_internal;
+    use serde_json::json;
+
+    fn collection(version: &str, paths: Value) -> OpenApiCollection {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": { "title": format!("Pets {}", version) },
+            "paths": paths,
+        });
+        parse_openapi_internal(doc, "pets.json", None).unwrap()
+    }
+
+    #[test]
+    fn separates_breaking_from_compatible_changes() {
+        let pet = |properties: Value, required: Value| json!({ "content": { "application/json": { "schema": { "type": "object", "properties": properties, "required": required } } } });
+        let old = collection(
+            "1",
+            json!({
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            { "name": "status", "in": "query", "schema": { "type": "string", "enum": ["available", "sold", "lost"] } },
+                            { "name": "page", "in": "query", "schema": { "type": "integer" } }
+                        ],
+                        "responses": { "200": pet(json!({ "id": { "type": "integer" }, "tag": { "type": "string" } }), json!(["id"])) }
+                    },
+                    "post": {
+                        "requestBody": pet(json!({ "name": { "type": "string" } }), json!([])),
+                        "responses": { "201": { "description": "created" } }
+                    },
+                    "delete": { "responses": { "204": { "description": "gone" } } }
+                }
+            }),
+        );
+        let new = collection(
+            "2",
+            json!({
+                "/pets": {
+                    "get": {
+                        "parameters": [
+                            { "name": "status", "in": "query", "schema": { "type": "string", "enum": ["available", "sold"] } },
+                            { "name": "page", "in": "query", "required": true, "schema": { "type": "integer" } },
+                            { "name": "limit", "in": "query", "schema": { "type": "integer" } }
+                        ],
+                        "responses": { "200": pet(json!({ "id": { "type": "string" }, "color": { "type": "string" } }), json!(["id"])) }
+                    },
+                    "post": {
+                        "requestBody": pet(json!({ "name": { "type": "string" }, "owner": { "type": "string" } }), json!(["name", "owner"])),
+                        "responses": { "201": { "description": "created" }, "409": { "description": "exists" } }
+                    }
+                }
+            }),
+        );
+        let diff = diff(&old, &new);
+        let summary: Vec<(Impact, &str, &str, &str)> = diff
+            .changes
+            .iter()
+            .map(|c| {
+                (
+                    c.impact,
+                    c.operation.as_deref().unwrap(),
+                    c.location.as_str(),
+                    c.message.as_str(),
+                )
+            })
+            .collect();
+        for expected in [
+            (
+                Impact::Breaking,
+                "DELETE /pets",
+                "operation",
+                "operation removed",
+            ),
+            (
+                Impact::Breaking,
+                "GET /pets",
+                "parameter query.status",
+                "enum values removed: lost",
+            ),
+            (
+                Impact::Breaking,
+                "GET /pets",
+                "parameter query.page",
+                "became required",
+            ),
+            (
+                Impact::Breaking,
+                "GET /pets",
+                "response 200 /id",
+                "type changed from integer to string",
+            ),
+            (
+                Impact::Breaking,
+                "GET /pets",
+                "response 200 /tag",
+                "property removed",
+            ),
+            (
+                Impact::Breaking,
+                "POST /pets",
+                "request body /name",
+                "became required",
+            ),
+            (
+                Impact::Breaking,
+                "POST /pets",
+                "request body /owner",
+                "required property added",
+            ),
+            (
+                Impact::NonBreaking,
+                "GET /pets",
+                "parameter query.limit",
+                "parameter added",
+            ),
+            (
+                Impact::NonBreaking,
+                "GET /pets",
+                "response 200 /color",
+                "property added",
+            ),
+            (
+                Impact::NonBreaking,
+                "POST /pets",
+                "response 409",
+                "response added",
+            ),
+        ] {
+            assert!(summary.contains(&expected), "missing {:?}", expected);
+        }
+        assert_eq!((diff.breaking, diff.non_breaking), (7, 3));
+        assert_eq!(diff.changes[0].impact, Impact::Breaking);
+        assert_eq!(diff.new_name, "Pets 2");
+    }
+}
@@ -0,0 +1,575 @@
+use crate::body::BodyEncoding;
+use crate::history::HistoryEntry;
+use crate::sigv4::percent_decode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+// A draft OpenAPI 3 document reverse-engineered from recorded exchanges. Schemas only
+// ever describe what was seen, so no values from requests or responses end up in it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GeneratedSpec {
+    pub document: Value,
+    pub operations: usize,
+    // Entries used, and those skipped for having no response or a URL that does not parse.
+    pub requests: usize,
+    pub skipped: usize,
+}
+
+// Headers every client sends, which say nothing about the API itself.
+const IGNORED_HEADERS: &[&str] = &[
+    "accept",
+    "accept-encoding",
+    "accept-language",
+    "authorization",
+    "cache-control",
+    "connection",
+    "content-length",
+    "content-type",
+    "cookie",
+    "host",
+    "origin",
+    "pragma",
+    "referer",
+    "user-agent",
+];
+
+#[derive(Default)]
+struct Observed {
+    // Parameters seen in every sample, and bodies sent with every one, become required.
+    samples: usize,
+    path_values: BTreeMap<String, Vec<String>>,
+    query: BTreeMap<String, (usize, Vec<String>)>,
+    headers: BTreeMap<String, (usize, Vec<String>)>,
+    bodies: BTreeMap<String, Value>,
+    body_samples: usize,
+    responses: BTreeMap<u16, BTreeMap<String, Option<Value>>>,
+}
+
+fn is_uuid(segment: &str) -> bool {
+    segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+// Segments that identify a resource rather than name one: numbers, UUIDs, and long
+// tokens mixing letters and digits such as object ids.
+fn looks_like_id(segment: &str) -> bool {
+    let digits = segment.chars().any(|c| c.is_ascii_digit());
+    let alphanumeric = segment.chars().all(|c| c.is_ascii_alphanumeric());
+    (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+        || is_uuid(segment)
+        || (alphanumeric && digits && segment.len() >= 16)
+}
+
+fn camel_case(words: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in words
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .enumerate()
+    {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            if i == 0 {
+                out.push(first.to_ascii_lowercase());
+            } else {
+                out.push(first.to_ascii_uppercase());
+            }
+            out.extend(chars);
+        }
+    }
+    out
+}
+
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if word.ends_with("ss") {
+        word.to_string()
+    } else {
+        word.strip_suffix('s').unwrap_or(word).to_string()
+    }
+}
+
+// Turns "/pets/42/toys/7" into "/pets/{petId}/toys/{toyId}" and the values seen for each
+// parameter. Already templated segments, as sent from a spec collection, stay as they are.
+fn template(path: &str) -> (String, Vec<(String, String)>) {
+    let mut segments = Vec::new();
+    let mut values: Vec<(String, String)> = Vec::new();
+    let mut previous = "";
+    for segment in path.split('/').skip(1) {
+        let decoded = percent_decode(segment);
+        if decoded.starts_with('{') && decoded.ends_with('}') {
+            segments.push(decoded);
+        } else if looks_like_id(&decoded) {
+            let base = match camel_case(&singular(previous)) {
+                name if name.is_empty() => "id".to_string(),
+                name => format!("{}Id", name),
+            };
+            let mut name = base.clone();
+            let mut n = 2;
+            while values.iter().any(|(taken, _)| *taken == name) {
+                name = format!("{}{}", base, n);
+                n += 1;
+            }
+            segments.push(format!("{{{}}}", name));
+            values.push((name, decoded));
+        } else {
+            segments.push(segment.to_string());
+            previous = segment;
+            continue;
+        }
+        previous = "";
+    }
+    (format!("/{}", segments.join("/")), values)
+}
+
+fn value_schema(values: &[String]) -> Value {
+    if !values.is_empty() && values.iter().all(|v| v.parse::<i64>().is_ok()) {
+        json!({ "type": "integer" })
+    } else if !values.is_empty() && values.iter().all(|v| v.parse::<f64>().is_ok()) {
+        json!({ "type": "number" })
+    } else if !values.is_empty() && values.iter().all(|v| v == "true" || v == "false") {
+        json!({ "type": "boolean" })
+    } else if !values.is_empty() && values.iter().all(|v| is_uuid(v)) {
+        json!({ "type": "string", "format": "uuid" })
+    } else {
+        json!({ "type": "string" })
+    }
+}
+
+fn infer(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "nullable": true }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::String(s) if chrono::DateTime::parse_from_rfc3339(s).is_ok() => {
+            json!({ "type": "string", "format": "date-time" })
+        }
+        Value::String(s) if is_uuid(s) => json!({ "type": "string", "format": "uuid" }),
+        Value::String(_) => json!({ "type": "string" }),
+        Value::Array(items) => {
+            let items = items.iter().map(infer).reduce(|a, b| merge(&a, &b));
+            json!({ "type": "array", "items": items.unwrap_or_else(|| json!({})) })
+        }
+        Value::Object(map) => json!({
+            "type": "object",
+            "properties": map.iter().map(|(k, v)| (k.clone(), infer(v))).collect::<Map<_, _>>(),
+            "required": map.keys().collect::<Vec<_>>(),
+        }),
+    }
+}
+
+// Widens two schemas into one that fits both samples: properties missing from either
+// stop being required, integers and numbers become numbers, and types that do not
+// agree at all leave an empty schema.
+fn merge(a: &Value, b: &Value) -> Value {
+    if a == b {
+        return a.clone();
+    }
+    let only_null = |s: &Value| s.get("type").is_none() && s["nullable"] == true;
+    if only_null(a) || only_null(b) {
+        let mut merged = if only_null(a) { b.clone() } else { a.clone() };
+        if let Some(map) = merged.as_object_mut() {
+            map.insert("nullable".into(), json!(true));
+        }
+        return merged;
+    }
+    let mut merged = match (a["type"].as_str(), b["type"].as_str()) {
+        (Some("object"), Some("object")) => {
+            let empty = Map::new();
+            let (pa, pb) = (
+                a["properties"].as_object().unwrap_or(&empty),
+                b["properties"].as_object().unwrap_or(&empty),
+            );
+            let mut properties = pa.clone();
+            for (name, schema) in pb {
+                let schema = match pa.get(name) {
+                    Some(existing) => merge(existing, schema),
+                    None => schema.clone(),
+                };
+                properties.insert(name.clone(), schema);
+            }
+            let required: Vec<&Value> = a["required"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|name| b["required"].as_array().is_some_and(|r| r.contains(name)))
+                .collect();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+        (Some("array"), Some("array")) => {
+            let items = match (&a["items"], &b["items"]) {
+                (Value::Object(x), other) | (other, Value::Object(x)) if x.is_empty() => {
+                    other.clone()
+                }
+                (x, y) => merge(x, y),
+            };
+            json!({ "type": "array", "items": items })
+        }
+        (Some("integer" | "number"), Some("integer" | "number")) => json!({ "type": "number" }),
+        (Some(x), Some(y)) if x == y => json!({ "type": x }),
+        _ => json!({}),
+    };
+    if a["nullable"] == true || b["nullable"] == true {
+        if let Some(map) = merged.as_object_mut() {
+            map.insert("nullable".into(), json!(true));
+        }
+    }
+    merged
+}
+
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn body_schema(media_type: &str, body: &str) -> Option<Value> {
+    if media_type.contains("json") {
+        serde_json::from_str::<Value>(body)
+            .ok()
+            .map(|value| infer(&value))
+    } else if media_type.starts_with("text/") {
+        Some(json!({ "type": "string" }))
+    } else {
+        None
+    }
+}
+
+fn record(map: &mut BTreeMap<String, Value>, media_type: String, schema: Option<Value>) {
+    let schema = schema.unwrap_or_else(|| json!({}));
+    let merged = match map.get(&media_type) {
+        Some(existing) => merge(existing, &schema),
+        None => schema,
+    };
+    map.insert(media_type, merged);
+}
+
+fn observe(
+    observed: &mut Observed,
+    entry: &HistoryEntry,
+    url: &reqwest::Url,
+    path_values: Vec<(String, String)>,
+) {
+    let request = &entry.request;
+    observed.samples += 1;
+    for (name, value) in path_values {
+        observed.path_values.entry(name).or_default().push(value);
+    }
+
+    let mut query: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let pairs = url
+        .query_pairs()
+        .into_owned()
+        .chain(request.query.clone().unwrap_or_default());
+    for (name, value) in pairs {
+        query.entry(name).or_default().push(value);
+    }
+    for param in request
+        .params
+        .iter()
+        .flatten()
+        .filter(|p| p.in_type == "query")
+    {
+        query.entry(param.name.clone()).or_default();
+    }
+    for (name, values) in query {
+        let seen = observed.query.entry(name).or_default();
+        seen.0 += 1;
+        seen.1.extend(values);
+    }
+    for (name, value) in &request.headers {
+        if IGNORED_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            continue;
+        }
+        let seen = observed
+            .headers
+            .entry(name.to_ascii_lowercase())
+            .or_default();
+        seen.0 += 1;
+        seen.1.push(value.clone());
+    }
+
+    let content_type = request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| media_type(value));
+    if let Some(form) = &request.form {
+        observed.body_samples += 1;
+        let properties: Map<String, Value> = form
+            .iter()
+            .map(|(name, _)| (name.clone(), json!({ "type": "string" })))
+            .collect();
+        let schema = json!({ "type": "object", "properties": properties });
+        let media_type = content_type.unwrap_or_else(|| "application/x-www-form-urlencoded".into());
+        record(&mut observed.bodies, media_type, Some(schema));
+    } else if let Some(body) = request
+        .body
+        .as_deref()
+        .filter(|body| !body.trim().is_empty())
+    {
+        observed.body_samples += 1;
+        let media_type = content_type.unwrap_or_else(|| {
+            let json = serde_json::from_str::<Value>(body).is_ok();
+            if json {
+                "application/json"
+            } else {
+                "text/plain"
+            }
+            .into()
+        });
+        let schema = body_schema(&media_type, body);
+        record(&mut observed.bodies, media_type, schema);
+    }
+
+    if let Some(response) = &entry.response {
+        let content = observed.responses.entry(response.status).or_default();
+        if let Some(content_type) = &response.content_type {
+            let media_type = media_type(content_type);
+            // Bodies over the history limit are dropped, so an empty one says nothing.
+            let schema = match response.body_encoding {
+                BodyEncoding::Text if !response.body.is_empty() => {
+                    body_schema(&media_type, &response.body)
+                }
+                _ => None,
+            };
+            let merged = match (content.get(&media_type).cloned().flatten(), schema) {
+                (Some(a), Some(b)) => Some(merge(&a, &b)),
+                (a, b) => a.or(b),
+            };
+            content.insert(media_type, merged);
+        }
+    }
+}
+
+fn parameter(name: &str, location: &str, required: bool, values: &[String]) -> Value {
+    json!({ "name": name, "in": location, "required": required, "schema": value_schema(values) })
+}
+
+fn operation(method: &str, path: &str, observed: &Observed) -> Value {
+    let mut operation = Map::new();
+    let words: Vec<String> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(
+            |s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => format!("by-{}", name),
+                None => s.to_string(),
+            },
+        )
+        .collect();
+    operation.insert(
+        "operationId".into(),
+        json!(camel_case(&format!("{} {}", method, words.join(" ")))),
+    );
+
+    let mut parameters = Vec::new();
+    for segment in path.split('/') {
+        if let Some(name) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            let values = observed.path_values.get(name).cloned().unwrap_or_default();
+            parameters.push(parameter(name, "path", true, &values));
+        }
+    }
+    for (name, (count, values)) in &observed.query {
+        parameters.push(parameter(name, "query", *count == observed.samples, values));
+    }
+    for (name, (count, values)) in &observed.headers {
+        parameters.push(parameter(
+            name,
+            "header",
+            *count == observed.samples,
+            values,
+        ));
+    }
+    if !parameters.is_empty() {
+        operation.insert("parameters".into(), Value::Array(parameters));
+    }
+
+    if !observed.bodies.is_empty() {
+        let content: Map<String, Value> = observed
+            .bodies
+            .iter()
+            .map(|(media_type, schema)| (media_type.clone(), json!({ "schema": schema })))
+            .collect();
+        operation.insert(
+            "requestBody".into(),
+            json!({ "required": observed.body_samples == observed.samples, "content": content }),
+        );
+    }
+
+    let responses: Map<String, Value> = observed
+        .responses
+        .iter()
+        .map(|(status, content)| {
+            let description = reqwest::StatusCode::from_u16(*status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or("Response");
+            let mut response = json!({ "description": description });
+            if !content.is_empty() {
+                let content: Map<String, Value> = content
+                    .iter()
+                    .map(|(media_type, schema)| {
+                        let schema = schema.clone().unwrap_or_else(|| json!({}));
+                        (media_type.clone(), json!({ "schema": schema }))
+                    })
+                    .collect();
+                response["content"] = Value::Object(content);
+            }
+            (status.to_string(), response)
+        })
+        .collect();
+    operation.insert("responses".into(), Value::Object(responses));
+    Value::Object(operation)
+}
+
+// Entries should come with their variables already substituted.
+pub fn generate(entries: &[HistoryEntry], title: &str) -> GeneratedSpec {
+    let mut servers: Vec<String> = Vec::new();
+    let mut operations: BTreeMap<(String, String), Observed> = BTreeMap::new();
+    let mut skipped = 0;
+    for entry in entries {
+        let url = match reqwest::Url::parse(&entry.request.url) {
+            Ok(url) if entry.response.is_some() && url.has_host() => url,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let server = url.origin().ascii_serialization();
+        if !servers.contains(&server) {
+            servers.push(server);
+        }
+        let (path, values) = template(url.path());
+        let observed = operations
+            .entry((path, entry.request.method.to_ascii_lowercase()))
+            .or_default();
+        observe(observed, entry, &url, values);
+    }
+
+    let mut paths = Map::new();
+    for ((path, method), observed) in &operations {
+        let item = paths.entry(path.clone()).or_insert_with(|| json!({}));
+        item[method] = operation(method, path, observed);
+    }
+    let requests = entries.len() - skipped;
+    GeneratedSpec {
+        document: json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": title,
+                "version": "0.1.0",
+                "description": format!("Draft inferred from {} recorded requests.", requests),
+            },
+            "servers": servers.iter().map(|url| json!({ "url": url })).collect::<Vec<_>>(),
+            "paths": paths,
+        }),
+        operations: operations.len(),
+        requests,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{RequestInput, ResponseData};
+
+    fn entry(
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        status: u16,
+        response: &str,
+    ) -> HistoryEntry {
+        let request = RequestInput {
+            method: method.into(),
+            url: url.into(),
+            headers: [("Content-Type".to_string(), "application/json".to_string())]
+                .into_iter()
+                .collect(),
+            body: body.map(String::from),
+            ..RequestInput::default()
+        };
+        let response = ResponseData {
+            status,
+            http_version: "HTTP/1.1".into(),
+            body: response.into(),
+            content_type: Some("application/json; charset=utf-8".into()),
+            size: response.len() as u64,
+            encoded_size: response.len() as u64,
+            ..ResponseData::default()
+        };
+        HistoryEntry::new(request, &Ok(response))
+    }
+
+    #[test]
+    fn infers_paths_parameters_and_schemas() {
+        let entries = vec![
+            entry(
+                "GET",
+                "https://api.test/pets/42?limit=10",
+                None,
+                200,
+                r#"{"id":42,"name":"Rex","tag":null}"#,
+            ),
+            entry(
+                "GET",
+                "https://api.test/pets/7",
+                None,
+                200,
+                r#"{"id":7,"name":"Tom","tag":"cat"}"#,
+            ),
+            entry(
+                "GET",
+                "https://api.test/pets/9",
+                None,
+                404,
+                r#"{"error":"not found"}"#,
+            ),
+            entry(
+                "POST",
+                "https://api.test/pets",
+                Some(r#"{"name":"Ace","age":2.5}"#),
+                201,
+                "{}",
+            ),
+            entry("GET", "{{base}}/pets", None, 200, "[]"),
+        ];
+        let spec = generate(&entries, "Pets");
+        assert_eq!((spec.operations, spec.requests, spec.skipped), (2, 4, 1));
+        let doc = &spec.document;
+        assert_eq!(doc["servers"], json!([{ "url": "https://api.test" }]));
+
+        let get = &doc["paths"]["/pets/{petId}"]["get"];
+        assert_eq!(get["operationId"], "getPetsByPetId");
+        assert_eq!(
+            get["parameters"],
+            json!([
+                { "name": "petId", "in": "path", "required": true, "schema": { "type": "integer" } },
+                { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer" } }
+            ])
+        );
+        let pet = &get["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(pet["required"], json!(["id", "name", "tag"]));
+        assert_eq!(
+            pet["properties"]["tag"],
+            json!({ "type": "string", "nullable": true })
+        );
+        assert_eq!(get["responses"]["404"]["description"], "Not Found");
+
+        let post = &doc["paths"]["/pets"]["post"];
+        assert_eq!(post["requestBody"]["required"], true);
+        let body = &post["requestBody"]["content"]["application/json"]["schema"];
+        assert_eq!(body["properties"]["age"], json!({ "type": "number" }));
+    }
+}
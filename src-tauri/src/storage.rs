@@ -0,0 +1,1236 @@
+use crate::cookies::{COOKIES_FILE, COOKIE_JARS_DIR};
+use crate::coverage::CoverageHit;
+use crate::environments::Environment;
+use crate::graphql::GraphqlSchema;
+use crate::history::{search_query, HistoryEntry, HistoryFilter};
+use crate::monitors::{Monitor, MonitorCheck, MONITOR_CHECKS_KEPT};
+use crate::offline::QueuedRequest;
+use crate::saved::SavedRequest;
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const DATABASE_FILE: &str = "restman.db";
+const LEGACY_COLLECTIONS_FILE: &str = "collections.json";
+
+// Each entry moves the schema up one version. The number applied so far is kept in
+// SQLite's `user_version`, so a migration runs exactly once per database.
+const MIGRATIONS: &[&str] = &[
+    // 1: collections and cookie jars
+    "CREATE TABLE collections (
+        url TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE cookie_jars (
+        jar TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );",
+    // 2: request history, with a full-text index the trigger keeps in step on delete
+    "CREATE TABLE history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        timestamp INTEGER NOT NULL,
+        collection TEXT,
+        status INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX history_timestamp ON history (timestamp);
+    CREATE VIRTUAL TABLE history_search USING fts5(line, headers, request_body, response_body);
+    CREATE TRIGGER history_search_delete AFTER DELETE ON history BEGIN
+        DELETE FROM history_search WHERE rowid = old.id;
+    END;",
+    // 3: saved requests
+    "CREATE TABLE saved_requests (
+        id TEXT PRIMARY KEY,
+        folder TEXT NOT NULL,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );",
+    // 4: environments, and small per-workspace values such as the active environment
+    "CREATE TABLE environments (
+        name TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE workspace_values (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 5: background monitors and the outcome of each of their checks
+    "CREATE TABLE monitors (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE monitor_checks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        monitor TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX monitor_checks_monitor ON monitor_checks (monitor, id);",
+    // 6: introspected GraphQL schemas
+    "CREATE TABLE graphql_schemas (
+        url TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );",
+    // 7: responses received per operation of a collection, for coverage reports
+    "CREATE TABLE coverage (
+        collection TEXT NOT NULL,
+        operation TEXT NOT NULL,
+        status INTEGER NOT NULL,
+        hits INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        PRIMARY KEY (collection, operation, status)
+    );",
+    // 8: the version of each collection its last sync replaced, for spec diffs
+    "CREATE TABLE collection_versions (
+        url TEXT PRIMARY KEY,
+        replaced_at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );",
+    // 9: requests waiting for the network to come back
+    "CREATE TABLE offline_queue (
+        id TEXT PRIMARY KEY,
+        queued_at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );",
+    // 10: the method and URL of history entries, for the proxy's replay lookups
+    "ALTER TABLE history ADD COLUMN method TEXT;
+    ALTER TABLE history ADD COLUMN url TEXT;
+    UPDATE history SET method = json_extract(data, '$.request.method'),
+        url = json_extract(data, '$.request.url');
+    CREATE INDEX history_request ON history (method, url, timestamp);",
+];
+
+const ACTIVE_ENVIRONMENT: &str = "active_environment";
+
+// The app's single transactional store. Records are kept as JSON documents next to
+// the columns needed to find and order them, so adding a field to a record type does
+// not need a migration.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+fn sqlite_error(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+// A database written by a newer version is left alone: its schema is unknown here,
+// and lowering its version would make that version migrate it again.
+fn migrate(conn: &mut Connection) -> Result<usize, String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(sqlite_error)?;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "The database was written by a newer version of the app (schema {}, this one knows {})",
+            version,
+            MIGRATIONS.len()
+        ));
+    }
+    if version == MIGRATIONS.len() {
+        return Ok(version);
+    }
+    let tx = conn.transaction().map_err(sqlite_error)?;
+    for migration in MIGRATIONS.iter().skip(version) {
+        tx.execute_batch(migration).map_err(sqlite_error)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())
+        .map_err(sqlite_error)?;
+    tx.commit().map_err(sqlite_error)?;
+    Ok(version)
+}
+
+// Only a damaged file is worth moving aside; a locked or unreadable database, or one
+// that does not migrate, is reported as it is.
+enum OpenError {
+    Corrupt(String),
+    Failed(String),
+}
+
+fn open_error(e: rusqlite::Error) -> OpenError {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => {
+            OpenError::Corrupt(e.to_string())
+        }
+        _ => OpenError::Failed(e.to_string()),
+    }
+}
+
+fn open_database(path: &Path) -> Result<(Connection, usize), OpenError> {
+    let mut conn = Connection::open(path).map_err(open_error)?;
+    let status: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(open_error)?;
+    if status != "ok" {
+        return Err(OpenError::Corrupt(status));
+    }
+    let version = migrate(&mut conn).map_err(OpenError::Failed)?;
+    Ok((conn, version))
+}
+
+fn corrupt_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".corrupt");
+    path.with_file_name(name)
+}
+
+impl Store {
+    // A database that fails its integrity check is moved aside for inspection and
+    // replaced by an empty one, rather than keeping the app from starting.
+    pub fn open(data_dir: &Path) -> Result<Store, String> {
+        std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+        let path = data_dir.join(DATABASE_FILE);
+        let (conn, version) = match open_database(&path) {
+            Ok(opened) => opened,
+            Err(OpenError::Corrupt(_)) => {
+                std::fs::rename(&path, corrupt_path(&path)).map_err(|e| e.to_string())?;
+                open_database(&path).map_err(|e| match e {
+                    OpenError::Corrupt(message) | OpenError::Failed(message) => message,
+                })?
+            }
+            Err(OpenError::Failed(message)) => return Err(message),
+        };
+        let store = Store {
+            conn: Mutex::new(conn),
+        };
+        if version == 0 {
+            store.import_legacy_files(data_dir);
+        }
+        Ok(store)
+    }
+
+    // Brings over the JSON files earlier versions wrote. They are left in place, since
+    // the import only ever runs on a fresh database.
+    fn import_legacy_files(&self, data_dir: &Path) {
+        let collections = std::fs::read_to_string(data_dir.join(LEGACY_COLLECTIONS_FILE))
+            .ok()
+            .and_then(|content| {
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content).ok()
+            })
+            .unwrap_or_default();
+        for (url, collection) in collections {
+            let _ = self.put_collection(&url, &collection);
+        }
+        if let Ok(content) = std::fs::read_to_string(data_dir.join(COOKIES_FILE)) {
+            let _ = self.put_cookie_jar("", &content);
+        }
+        let jars = std::fs::read_dir(data_dir.join(COOKIE_JARS_DIR))
+            .into_iter()
+            .flatten()
+            .flatten();
+        for entry in jars {
+            let path = entry.path();
+            let (Some(key), Ok(content)) = (
+                path.file_stem().and_then(|stem| stem.to_str()),
+                std::fs::read_to_string(&path),
+            ) else {
+                continue;
+            };
+            let _ = self.put_cookie_jar(key, &content);
+        }
+    }
+
+    // Collections in their stored order. Rows that no longer deserialize are skipped
+    // so one bad record does not hide the rest.
+    pub fn collections<T: DeserializeOwned>(&self) -> Result<Vec<(String, T)>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT url, data FROM collections ORDER BY position, url")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|(url, data)| Some((url, serde_json::from_str(&data).ok()?)))
+            .collect())
+    }
+
+    // New collections go to the end; updating one keeps its position.
+    pub fn put_collection<T: Serialize>(&self, url: &str, collection: &T) -> Result<(), String> {
+        let data = serde_json::to_string(collection).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO collections (url, position, data)
+                 VALUES (?1, (SELECT COALESCE(MAX(position) + 1, 0) FROM collections), ?2)
+                 ON CONFLICT(url) DO UPDATE SET data = excluded.data",
+                params![url, data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn delete_collection(&self, url: &str) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        tx.execute("DELETE FROM collections WHERE url = ?1", params![url])
+            .map_err(sqlite_error)?;
+        tx.execute("DELETE FROM coverage WHERE collection = ?1", params![url])
+            .map_err(sqlite_error)?;
+        tx.execute(
+            "DELETE FROM collection_versions WHERE url = ?1",
+            params![url],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)
+    }
+
+    // Only the version a sync replaced is kept, not the whole lineage.
+    pub fn put_previous_collection<T: Serialize>(
+        &self,
+        url: &str,
+        collection: &T,
+    ) -> Result<(), String> {
+        let data = serde_json::to_string(collection).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO collection_versions (url, replaced_at, data) VALUES (?1, ?2, ?3)",
+                params![url, Utc::now().timestamp_millis(), data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn previous_collection<T: DeserializeOwned>(&self, url: &str) -> Result<Option<T>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM collection_versions WHERE url = ?1",
+                params![url],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    pub fn record_coverage(
+        &self,
+        collection: &str,
+        operation: &str,
+        status: u16,
+    ) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO coverage (collection, operation, status, hits, last_seen) VALUES (?1, ?2, ?3, 1, ?4)
+                 ON CONFLICT(collection, operation, status)
+                 DO UPDATE SET hits = hits + 1, last_seen = excluded.last_seen",
+                params![collection, operation, status, Utc::now().timestamp_millis()],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn coverage(&self, collection: &str) -> Result<Vec<CoverageHit>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT operation, status, hits, last_seen FROM coverage WHERE collection = ?1
+                 ORDER BY operation, status",
+            )
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map(params![collection], |row| {
+                Ok(CoverageHit {
+                    operation: row.get(0)?,
+                    status: row.get(1)?,
+                    hits: row.get::<_, i64>(2)? as u64,
+                    last_seen: DateTime::from_timestamp_millis(row.get(3)?).unwrap_or_default(),
+                })
+            })
+            .map_err(sqlite_error)?;
+        rows.collect::<Result<_, _>>().map_err(sqlite_error)
+    }
+
+    pub fn clear_coverage(&self, collection: &str) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "DELETE FROM coverage WHERE collection = ?1",
+                params![collection],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    // Numbers the listed collections in order, in a single transaction.
+    pub fn set_collection_order(&self, urls: &[String]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        for (position, url) in urls.iter().enumerate() {
+            tx.execute(
+                "UPDATE collections SET position = ?1 WHERE url = ?2",
+                params![position as i64, url],
+            )
+            .map_err(sqlite_error)?;
+        }
+        tx.commit().map_err(sqlite_error)
+    }
+
+    pub fn add_history(&self, entry: &HistoryEntry) -> Result<i64, String> {
+        let data = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        tx.execute(
+            "INSERT INTO history (timestamp, collection, status, method, url, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.timestamp.timestamp_millis(),
+                entry.request.collection,
+                entry.response.as_ref().map(|response| response.status),
+                entry.request.method,
+                entry.request.url,
+                data
+            ],
+        )
+        .map_err(sqlite_error)?;
+        let id = tx.last_insert_rowid();
+        let [line, headers, request_body, response_body] = entry.search_columns();
+        tx.execute(
+            "INSERT INTO history_search (rowid, line, headers, request_body, response_body)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, line, headers, request_body, response_body],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+        Ok(id)
+    }
+
+    // Newest first.
+    pub fn history(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+        let mut sql = "SELECT id, data FROM history WHERE 1 = 1".to_string();
+        let mut values: Vec<SqlValue> = Vec::new();
+        if let Some(query) = filter.text.as_deref().and_then(search_query) {
+            sql.push_str(
+                " AND id IN (SELECT rowid FROM history_search WHERE history_search MATCH ?)",
+            );
+            values.push(query.into());
+        }
+        if let Some(collection) = &filter.collection {
+            sql.push_str(" AND collection = ?");
+            values.push(collection.clone().into());
+        }
+        if let Some(status) = filter.status_min {
+            sql.push_str(" AND status >= ?");
+            values.push(i64::from(status).into());
+        }
+        if let Some(status) = filter.status_max {
+            sql.push_str(" AND status <= ?");
+            values.push(i64::from(status).into());
+        }
+        if filter.failed {
+            sql.push_str(" AND status IS NULL");
+        }
+        if let Some(since) = filter.since {
+            sql.push_str(" AND timestamp >= ?");
+            values.push(since.timestamp_millis().into());
+        }
+        if let Some(until) = filter.until {
+            sql.push_str(" AND timestamp <= ?");
+            values.push(until.timestamp_millis().into());
+        }
+        // A negative limit means no limit to SQLite.
+        sql.push_str(" ORDER BY timestamp DESC, id DESC LIMIT ? OFFSET ?");
+        values.push(filter.limit.map(|limit| limit as i64).unwrap_or(-1).into());
+        values.push((filter.offset.unwrap_or(0) as i64).into());
+
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&sql).map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(sqlite_error)?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, data) = row.map_err(sqlite_error)?;
+            if let Ok(mut entry) = serde_json::from_str::<HistoryEntry>(&data) {
+                entry.id = id;
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn history_entry(&self, id: i64) -> Result<Option<HistoryEntry>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM history WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        let Some(data) = data else {
+            return Ok(None);
+        };
+        let mut entry: HistoryEntry = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+        entry.id = id;
+        Ok(Some(entry))
+    }
+
+    // The newest entry with a response to exactly this method and URL.
+    pub fn latest_response(&self, method: &str, url: &str) -> Result<Option<HistoryEntry>, String> {
+        let row: Option<(i64, String)> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, data FROM history
+                 WHERE method = ?1 AND url = ?2 AND status IS NOT NULL
+                 ORDER BY timestamp DESC, id DESC LIMIT 1",
+                params![method, url],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        let Some((id, data)) = row else {
+            return Ok(None);
+        };
+        let mut entry: HistoryEntry = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+        entry.id = id;
+        Ok(Some(entry))
+    }
+
+    // Drops entries older than `before` and then all but the newest `keep`. Returns how
+    // many entries were removed and the response files they referenced.
+    pub fn prune_history(
+        &self,
+        before: Option<DateTime<Utc>>,
+        keep: Option<usize>,
+    ) -> Result<(usize, Vec<String>), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        let mut removed = 0;
+        let mut files = Vec::new();
+        let mut delete = |sql: &str, value: i64| -> Result<(), String> {
+            let mut stmt = tx.prepare(sql).map_err(sqlite_error)?;
+            let rows = stmt
+                .query_map(params![value], |row| row.get::<_, Option<String>>(0))
+                .map_err(sqlite_error)?;
+            for path in rows {
+                removed += 1;
+                files.extend(path.map_err(sqlite_error)?);
+            }
+            Ok(())
+        };
+        if let Some(before) = before {
+            delete(
+                "DELETE FROM history WHERE timestamp < ?1
+                 RETURNING json_extract(data, '$.response.body_path')",
+                before.timestamp_millis(),
+            )?;
+        }
+        if let Some(keep) = keep {
+            delete(
+                "DELETE FROM history WHERE id NOT IN
+                 (SELECT id FROM history ORDER BY timestamp DESC, id DESC LIMIT ?1)
+                 RETURNING json_extract(data, '$.response.body_path')",
+                keep as i64,
+            )?;
+        }
+        tx.commit().map_err(sqlite_error)?;
+        Ok((removed, files))
+    }
+
+    // The response files history entries refer to.
+    pub fn response_files(&self) -> Result<Vec<String>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT json_extract(data, '$.response.body_path') FROM history
+                 WHERE json_extract(data, '$.response.body_path') IS NOT NULL",
+            )
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        rows.map(|row| row.map_err(sqlite_error)).collect()
+    }
+
+    // Sorted by folder, then name.
+    pub fn saved_requests(&self) -> Result<Vec<SavedRequest>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM saved_requests ORDER BY folder, name COLLATE NOCASE")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
+    }
+
+    pub fn saved_request(&self, id: &str) -> Result<Option<SavedRequest>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM saved_requests WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    pub fn put_saved_request(&self, saved: &SavedRequest) -> Result<(), String> {
+        let data = serde_json::to_string(saved).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO saved_requests (id, folder, name, data) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET
+                     folder = excluded.folder, name = excluded.name, data = excluded.data",
+                params![saved.id, saved.folder, saved.name, data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn delete_saved_request(&self, id: &str) -> Result<bool, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM saved_requests WHERE id = ?1", params![id])
+            .map(|deleted| deleted > 0)
+            .map_err(sqlite_error)
+    }
+
+    pub fn environments(&self) -> Result<Vec<Environment>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM environments ORDER BY name COLLATE NOCASE")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
+    }
+
+    pub fn environment(&self, name: &str) -> Result<Option<Environment>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM environments WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    // Saving under a new name with `previous` set renames the environment, and keeps
+    // it active if it was.
+    pub fn put_environment(
+        &self,
+        environment: &Environment,
+        previous: Option<&str>,
+    ) -> Result<(), String> {
+        let data = serde_json::to_string(environment).map_err(|e| e.to_string())?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        if let Some(previous) = previous.filter(|previous| *previous != environment.name) {
+            tx.execute(
+                "DELETE FROM environments WHERE name = ?1",
+                params![previous],
+            )
+            .map_err(sqlite_error)?;
+            tx.execute(
+                "UPDATE workspace_values SET value = ?1 WHERE key = ?2 AND value = ?3",
+                params![environment.name, ACTIVE_ENVIRONMENT, previous],
+            )
+            .map_err(sqlite_error)?;
+        }
+        tx.execute(
+            "INSERT INTO environments (name, data) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET data = excluded.data",
+            params![environment.name, data],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)
+    }
+
+    pub fn delete_environment(&self, name: &str) -> Result<bool, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        let deleted = tx
+            .execute("DELETE FROM environments WHERE name = ?1", params![name])
+            .map_err(sqlite_error)?;
+        tx.execute(
+            "DELETE FROM workspace_values WHERE key = ?1 AND value = ?2",
+            params![ACTIVE_ENVIRONMENT, name],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn active_environment(&self) -> Result<Option<String>, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT value FROM workspace_values WHERE key = ?1",
+                params![ACTIVE_ENVIRONMENT],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)
+    }
+
+    pub fn set_active_environment(&self, name: Option<&str>) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        match name {
+            Some(name) => conn.execute(
+                "INSERT INTO workspace_values (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![ACTIVE_ENVIRONMENT, name],
+            ),
+            None => conn.execute(
+                "DELETE FROM workspace_values WHERE key = ?1",
+                params![ACTIVE_ENVIRONMENT],
+            ),
+        }
+        .map(|_| ())
+        .map_err(sqlite_error)
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM monitors ORDER BY name COLLATE NOCASE")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
+    }
+
+    pub fn monitor(&self, id: &str) -> Result<Option<Monitor>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM monitors WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    pub fn put_monitor(&self, monitor: &Monitor) -> Result<(), String> {
+        let data = serde_json::to_string(monitor).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO monitors (id, name, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET name = excluded.name, data = excluded.data",
+                params![monitor.id, monitor.name, data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    // Oldest first, the order they are sent in.
+    pub fn queued_requests(&self) -> Result<Vec<QueuedRequest>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM offline_queue ORDER BY queued_at, rowid")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
+    }
+
+    pub fn put_queued_request(&self, queued: &QueuedRequest) -> Result<(), String> {
+        let data = serde_json::to_string(queued).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO offline_queue (id, queued_at, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+                params![queued.id, queued.queued_at.timestamp_millis(), data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn delete_queued_request(&self, id: &str) -> Result<bool, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM offline_queue WHERE id = ?1", params![id])
+            .map(|deleted| deleted > 0)
+            .map_err(sqlite_error)
+    }
+
+    // Removes the monitor along with its checks.
+    pub fn delete_monitor(&self, id: &str) -> Result<bool, String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        tx.execute("DELETE FROM monitor_checks WHERE monitor = ?1", params![id])
+            .map_err(sqlite_error)?;
+        let deleted = tx
+            .execute("DELETE FROM monitors WHERE id = ?1", params![id])
+            .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+        Ok(deleted > 0)
+    }
+
+    // Records a check and drops the monitor's oldest beyond `MONITOR_CHECKS_KEPT`.
+    pub fn add_monitor_check(&self, check: &MonitorCheck) -> Result<i64, String> {
+        let data = serde_json::to_string(check).map_err(|e| e.to_string())?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(sqlite_error)?;
+        tx.execute(
+            "INSERT INTO monitor_checks (monitor, timestamp, data) VALUES (?1, ?2, ?3)",
+            params![check.monitor, check.timestamp.timestamp_millis(), data],
+        )
+        .map_err(sqlite_error)?;
+        let id = tx.last_insert_rowid();
+        tx.execute(
+            "DELETE FROM monitor_checks WHERE monitor = ?1 AND id NOT IN
+                 (SELECT id FROM monitor_checks WHERE monitor = ?1 ORDER BY id DESC LIMIT ?2)",
+            params![check.monitor, MONITOR_CHECKS_KEPT as i64],
+        )
+        .map_err(sqlite_error)?;
+        tx.commit().map_err(sqlite_error)?;
+        Ok(id)
+    }
+
+    // Newest first.
+    pub fn monitor_checks(&self, monitor: &str, limit: usize) -> Result<Vec<MonitorCheck>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, data FROM monitor_checks WHERE monitor = ?1 ORDER BY id DESC LIMIT ?2",
+            )
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map(params![monitor, limit as i64], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(sqlite_error)?;
+        let mut checks = Vec::new();
+        for row in rows {
+            let (id, data) = row.map_err(sqlite_error)?;
+            if let Ok(mut check) = serde_json::from_str::<MonitorCheck>(&data) {
+                check.id = id;
+                checks.push(check);
+            }
+        }
+        Ok(checks)
+    }
+
+    pub fn graphql_schemas(&self) -> Result<Vec<GraphqlSchema>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT data FROM graphql_schemas ORDER BY name COLLATE NOCASE, url")
+            .map_err(sqlite_error)?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(sqlite_error)?;
+        Ok(rows
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
+    }
+
+    pub fn graphql_schema(&self, url: &str) -> Result<Option<GraphqlSchema>, String> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM graphql_schemas WHERE url = ?1",
+                params![url],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    pub fn put_graphql_schema(&self, schema: &GraphqlSchema) -> Result<(), String> {
+        let data = serde_json::to_string(schema).map_err(|e| e.to_string())?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO graphql_schemas (url, name, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(url) DO UPDATE SET name = excluded.name, data = excluded.data",
+                params![schema.url, schema.name, data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn delete_graphql_schema(&self, url: &str) -> Result<bool, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM graphql_schemas WHERE url = ?1", params![url])
+            .map(|deleted| deleted > 0)
+            .map_err(sqlite_error)
+    }
+
+    pub fn cookie_jar(&self, key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT data FROM cookie_jars WHERE jar = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)
+    }
+
+    pub fn put_cookie_jar(&self, key: &str, data: &str) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO cookie_jars (jar, data) VALUES (?1, ?2)
+                 ON CONFLICT(jar) DO UPDATE SET data = excluded.data",
+                params![key, data],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn clear_cookie_jars(&self) -> Result<(), String> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM cookie_jars", [])
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{RequestInput, ResponseData};
+    use std::collections::HashMap;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("restman-storage-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn migrates_and_imports_legacy_files_once() {
+        let dir = temp_dir("legacy");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(COOKIE_JARS_DIR)).unwrap();
+        std::fs::write(
+            dir.join(LEGACY_COLLECTIONS_FILE),
+            r#"{"https://a.test/spec.json": 1, "https://b.test/spec.json": 2}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join(COOKIES_FILE), "[]").unwrap();
+        std::fs::write(dir.join(COOKIE_JARS_DIR).join("abc123.json"), "[1]").unwrap();
+
+        let store = Store::open(&dir).unwrap();
+        let collections: HashMap<String, u32> = store.collections().unwrap().into_iter().collect();
+        assert_eq!(collections["https://b.test/spec.json"], 2);
+        assert_eq!(store.cookie_jar("abc123").unwrap().as_deref(), Some("[1]"));
+        store
+            .put_collection("https://a.test/spec.json", &"renamed")
+            .unwrap();
+        drop(store);
+
+        // Reopening neither re-runs migrations nor imports the files again.
+        let store = Store::open(&dir).unwrap();
+        let collections: Vec<(String, serde_json::Value)> = store.collections().unwrap();
+        assert_eq!(collections[0].1, "renamed");
+        assert_eq!(collections.len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn history_entry(url: &str, status: Option<u16>, body: &str) -> HistoryEntry {
+        let request = RequestInput {
+            method: "GET".into(),
+            url: url.into(),
+            collection: Some("pets".into()),
+            ..RequestInput::default()
+        };
+        let result = match status {
+            Some(status) => Ok(ResponseData {
+                status,
+                http_version: "HTTP/1.1".into(),
+                body: body.into(),
+                elapsed_ms: 5,
+                size: body.len() as u64,
+                encoded_size: body.len() as u64,
+                ..ResponseData::default()
+            }),
+            None => Err("connection refused".to_string()),
+        };
+        HistoryEntry::new(request, &result)
+    }
+
+    #[test]
+    fn records_searches_and_prunes_history() {
+        let dir = temp_dir("history");
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Store::open(&dir).unwrap();
+        let mut old = history_entry("https://api.test/pets/1", Some(200), r#"{"name":"Rex"}"#);
+        old.timestamp = Utc::now() - chrono::Duration::days(40);
+        old.response.as_mut().unwrap().body_path = Some("/cache/response-rex.json".into());
+        store.add_history(&old).unwrap();
+        let missing = store
+            .add_history(&history_entry("https://api.test/pets/2", Some(404), ""))
+            .unwrap();
+        store
+            .add_history(&history_entry("https://api.test/owners", None, ""))
+            .unwrap();
+
+        let search = |filter: HistoryFilter| -> Vec<String> {
+            store
+                .history(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.request.url)
+                .collect()
+        };
+        assert_eq!(
+            search(HistoryFilter::default()),
+            vec![
+                "https://api.test/owners",
+                "https://api.test/pets/2",
+                "https://api.test/pets/1"
+            ]
+        );
+        assert_eq!(
+            search(HistoryFilter {
+                text: Some("rex".into()),
+                ..HistoryFilter::default()
+            }),
+            vec!["https://api.test/pets/1"]
+        );
+        assert_eq!(
+            search(HistoryFilter {
+                text: Some("api.test/pets".into()),
+                status_min: Some(400),
+                ..HistoryFilter::default()
+            }),
+            vec!["https://api.test/pets/2"]
+        );
+        assert_eq!(
+            search(HistoryFilter {
+                failed: true,
+                ..HistoryFilter::default()
+            }),
+            vec!["https://api.test/owners"]
+        );
+        assert_eq!(
+            store.history_entry(missing).unwrap().unwrap().request.url,
+            "https://api.test/pets/2"
+        );
+        let latest = store
+            .latest_response("GET", "https://api.test/pets/1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(latest.response.unwrap().body, r#"{"name":"Rex"}"#);
+        assert!(store
+            .latest_response("GET", "https://api.test/pets")
+            .unwrap()
+            .is_none());
+        assert!(store
+            .latest_response("GET", "https://api.test/owners")
+            .unwrap()
+            .is_none());
+
+        let month_ago = Utc::now() - chrono::Duration::days(30);
+        assert_eq!(
+            store.response_files().unwrap(),
+            vec!["/cache/response-rex.json"]
+        );
+        assert_eq!(
+            store.prune_history(Some(month_ago), Some(1)).unwrap(),
+            (2, vec!["/cache/response-rex.json".to_string()])
+        );
+        assert!(store.response_files().unwrap().is_empty());
+        assert_eq!(
+            search(HistoryFilter::default()),
+            vec!["https://api.test/owners"]
+        );
+        assert!(search(HistoryFilter {
+            text: Some("rex".into()),
+            ..HistoryFilter::default()
+        })
+        .is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn renames_and_deletes_the_active_environment() {
+        let dir = temp_dir("environments");
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Store::open(&dir).unwrap();
+        let environment = |name: &str| Environment {
+            name: name.into(),
+            variables: Vec::new(),
+        };
+        store.put_environment(&environment("dev"), None).unwrap();
+        store.put_environment(&environment("prod"), None).unwrap();
+        store.set_active_environment(Some("dev")).unwrap();
+        store
+            .put_environment(&environment("local"), Some("dev"))
+            .unwrap();
+        assert_eq!(
+            store.active_environment().unwrap().as_deref(),
+            Some("local")
+        );
+        let names: Vec<String> = store
+            .environments()
+            .unwrap()
+            .into_iter()
+            .map(|environment| environment.name)
+            .collect();
+        assert_eq!(names, vec!["local", "prod"]);
+        assert!(store.delete_environment("local").unwrap());
+        assert_eq!(store.active_environment().unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stores_monitors_with_their_checks() {
+        let dir = temp_dir("monitors");
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Store::open(&dir).unwrap();
+        let monitor: Monitor = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "name": "Health",
+            "steps": [{ "type": "saved", "id": "health" }],
+            "interval_minutes": 5
+        }))
+        .unwrap();
+        store.put_monitor(&monitor).unwrap();
+        for passed in [true, false, true] {
+            store
+                .add_monitor_check(&MonitorCheck {
+                    id: 0,
+                    monitor: "m1".into(),
+                    timestamp: Utc::now(),
+                    passed,
+                    elapsed_ms: 10,
+                    status: None,
+                    error: None,
+                })
+                .unwrap();
+        }
+        let checks = store.monitor_checks("m1", 2).unwrap();
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].passed && !checks[1].passed);
+        assert!(checks[0].id > checks[1].id);
+        assert!(store.monitor("m1").unwrap().unwrap().enabled);
+        assert!(store.delete_monitor("m1").unwrap());
+        assert!(store.monitor_checks("m1", 10).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keeps_order_skips_bad_rows_and_recovers_from_corruption() {
+        let dir = temp_dir("corrupt");
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Store::open(&dir).unwrap();
+        store.put_collection("z", &1u32).unwrap();
+        store.put_collection("a", &2u32).unwrap();
+        store.put_collection("bad", &"two").unwrap();
+        store.put_collection("z", &3u32).unwrap();
+        let collections: Vec<(String, u32)> = store.collections().unwrap();
+        assert_eq!(
+            collections,
+            vec![("z".to_string(), 3), ("a".to_string(), 2)]
+        );
+        store
+            .set_collection_order(&["a".to_string(), "z".to_string()])
+            .unwrap();
+        store.delete_collection("bad").unwrap();
+        store.put_collection("new", &4u32).unwrap();
+        let order: Vec<String> = store
+            .collections::<u32>()
+            .unwrap()
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect();
+        assert_eq!(order, vec!["a", "z", "new"]);
+        drop(store);
+
+        std::fs::write(dir.join(DATABASE_FILE), "not a database").unwrap();
+        let store = Store::open(&dir).unwrap();
+        assert!(store.collections::<u32>().unwrap().is_empty());
+        assert!(corrupt_path(&dir.join(DATABASE_FILE)).exists());
+        store.put_collection("kept", &5u32).unwrap();
+        drop(store);
+
+        // A newer version's database is refused as it is, not moved aside or downgraded.
+        std::fs::remove_file(corrupt_path(&dir.join(DATABASE_FILE))).unwrap();
+        let newer = MIGRATIONS.len() + 1;
+        Connection::open(dir.join(DATABASE_FILE))
+            .unwrap()
+            .pragma_update(None, "user_version", newer)
+            .unwrap();
+        assert!(Store::open(&dir).is_err());
+        assert!(!corrupt_path(&dir.join(DATABASE_FILE)).exists());
+        let version: usize = Connection::open(dir.join(DATABASE_FILE))
+            .unwrap()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, newer);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
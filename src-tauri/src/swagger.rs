@@ -0,0 +1,488 @@
+use serde_json::{json, Map, Value};
+
+// Parameter fields that Swagger 2.0 keeps on the parameter itself and OpenAPI 3 moves
+// into its `schema`.
+const SCHEMA_FIELDS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "exclusiveMinimum",
+    "exclusiveMaximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minItems",
+    "maxItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+pub fn is_swagger2(doc: &Value) -> bool {
+    match &doc["swagger"] {
+        Value::String(version) => version.starts_with('2'),
+        Value::Number(version) => version.as_f64().is_some_and(|v| (2.0..3.0).contains(&v)),
+        _ => false,
+    }
+}
+
+// Points `#/definitions/...` style refs at their OpenAPI 3 locations.
+fn rewrite_refs(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut rewritten = Map::new();
+            for (key, item) in map {
+                let item = match (key.as_str(), item) {
+                    ("$ref", Value::String(target)) => Value::String(
+                        target
+                            .replacen("#/definitions/", "#/components/schemas/", 1)
+                            .replacen("#/parameters/", "#/components/parameters/", 1)
+                            .replacen("#/responses/", "#/components/responses/", 1),
+                    ),
+                    _ => rewrite_refs(item),
+                };
+                rewritten.insert(key.clone(), item);
+            }
+            Value::Object(rewritten)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(rewrite_refs).collect()),
+        other => other.clone(),
+    }
+}
+
+// `type: file` becomes a binary string, recursively through array items.
+fn convert_schema(schema: &Value) -> Value {
+    let mut schema = rewrite_refs(schema);
+    if let Some(map) = schema.as_object_mut() {
+        if map.get("type").and_then(|v| v.as_str()) == Some("file") {
+            map.insert("type".into(), json!("string"));
+            map.insert("format".into(), json!("binary"));
+        }
+        if let Some(items) = map.get("items").cloned() {
+            map.insert("items".into(), convert_schema(&items));
+        }
+    }
+    schema
+}
+
+fn servers(doc: &Value) -> Value {
+    let base_path = doc["basePath"].as_str().unwrap_or("");
+    let url = match doc["host"].as_str() {
+        Some(host) => {
+            let scheme = doc["schemes"]
+                .as_array()
+                .and_then(|schemes| {
+                    let schemes: Vec<&str> = schemes.iter().filter_map(|s| s.as_str()).collect();
+                    schemes
+                        .iter()
+                        .find(|s| **s == "https")
+                        .or(schemes.first())
+                        .copied()
+                })
+                .unwrap_or("https");
+            format!("{}://{}{}", scheme, host, base_path)
+        }
+        None => base_path.to_string(),
+    };
+    json!([{ "url": url }])
+}
+
+fn media_types(operation: &Value, doc: &Value, key: &str, fallback: &str) -> Vec<String> {
+    operation[key]
+        .as_array()
+        .or_else(|| doc[key].as_array())
+        .map(|types| {
+            types
+                .iter()
+                .filter_map(|t| t.as_str().map(|s| s.to_string()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|types| !types.is_empty())
+        .unwrap_or_else(|| vec![fallback.to_string()])
+}
+
+fn convert_parameter(param: &Value) -> Value {
+    let mut converted = Map::new();
+    let mut schema = Map::new();
+    if let Some(map) = param.as_object() {
+        for (key, value) in map {
+            if SCHEMA_FIELDS.contains(&key.as_str()) {
+                schema.insert(key.clone(), value.clone());
+            } else if key == "x-example" {
+                converted.insert("example".into(), value.clone());
+            } else if key == "collectionFormat" {
+                let (style, explode) = match value.as_str() {
+                    Some("ssv") => ("spaceDelimited", false),
+                    Some("pipes") => ("pipeDelimited", false),
+                    Some("multi") => ("form", true),
+                    _ => ("form", false),
+                };
+                // Path and header arrays are always comma separated.
+                if matches!(param["in"].as_str(), Some("query") | Some("formData")) {
+                    converted.insert("style".into(), json!(style));
+                }
+                converted.insert("explode".into(), json!(explode));
+            } else if key != "allowEmptyValue" {
+                converted.insert(key.clone(), rewrite_refs(value));
+            }
+        }
+    }
+    if !schema.is_empty() {
+        converted.insert("schema".into(), convert_schema(&Value::Object(schema)));
+    }
+    Value::Object(converted)
+}
+
+// Resolves `#/parameters/...` refs against the Swagger document so body and formData
+// parameters defined globally still end up in the request body.
+fn resolve_parameter<'a>(doc: &'a Value, param: &'a Value) -> &'a Value {
+    param["$ref"]
+        .as_str()
+        .and_then(|target| doc.pointer(target.trim_start_matches('#')))
+        .unwrap_or(param)
+}
+
+fn convert_operation(doc: &Value, operation: &Value, path_params: &[Value]) -> Value {
+    let mut converted = match operation.as_object() {
+        Some(map) => map.clone(),
+        None => return operation.clone(),
+    };
+    for key in ["parameters", "consumes", "produces", "responses", "schemes"] {
+        converted.remove(key);
+    }
+    let consumes = media_types(operation, doc, "consumes", "application/json");
+    let produces = media_types(operation, doc, "produces", "application/json");
+
+    let op_params = operation["parameters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut parameters = Vec::new();
+    let mut body = None;
+    let mut form_fields = Map::new();
+    let mut form_required = Vec::new();
+    let mut has_file = false;
+    for param in path_params.iter().chain(op_params.iter()) {
+        let resolved = resolve_parameter(doc, param);
+        let name = resolved["name"].as_str().unwrap_or("");
+        match resolved["in"].as_str() {
+            Some("body") => body = Some(resolved.clone()),
+            Some("formData") => {
+                let converted = convert_parameter(resolved);
+                let mut schema = converted["schema"].clone();
+                if let (Some(map), Some(description)) =
+                    (schema.as_object_mut(), resolved.get("description"))
+                {
+                    map.insert("description".into(), description.clone());
+                }
+                has_file |= resolved["type"].as_str() == Some("file");
+                if resolved["required"].as_bool().unwrap_or(false) {
+                    form_required.push(json!(name));
+                }
+                form_fields.insert(name.to_string(), schema);
+            }
+            _ if param.get("$ref").is_some() => parameters.push(rewrite_refs(param)),
+            _ => parameters.push(convert_parameter(resolved)),
+        }
+    }
+    if !parameters.is_empty() {
+        converted.insert("parameters".into(), Value::Array(parameters));
+    }
+
+    if let Some(body) = body {
+        let schema = convert_schema(&body["schema"]);
+        let mut content = Map::new();
+        for media_type in &consumes {
+            content.insert(media_type.clone(), json!({ "schema": schema }));
+        }
+        let mut request_body = json!({
+            "content": content,
+            "required": body["required"].as_bool().unwrap_or(false),
+        });
+        if let Some(description) = body.get("description") {
+            request_body["description"] = description.clone();
+        }
+        converted.insert("requestBody".into(), request_body);
+    } else if !form_fields.is_empty() {
+        let mut schema = json!({ "type": "object", "properties": form_fields });
+        if !form_required.is_empty() {
+            schema["required"] = Value::Array(form_required);
+        }
+        let mut form_types: Vec<String> = consumes
+            .iter()
+            .filter(|t| t.contains("form"))
+            .cloned()
+            .collect();
+        if form_types.is_empty()
+            || (has_file && !form_types.iter().any(|t| t == "multipart/form-data"))
+        {
+            form_types = vec![if has_file {
+                "multipart/form-data".to_string()
+            } else {
+                "application/x-www-form-urlencoded".to_string()
+            }];
+        }
+        let mut content = Map::new();
+        for media_type in form_types {
+            content.insert(media_type, json!({ "schema": schema }));
+        }
+        converted.insert("requestBody".into(), json!({ "content": content }));
+    }
+
+    if let Some(responses) = operation["responses"].as_object() {
+        let mut converted_responses = Map::new();
+        for (status, response) in responses {
+            converted_responses.insert(status.clone(), convert_response(response, &produces));
+        }
+        converted.insert("responses".into(), Value::Object(converted_responses));
+    }
+    Value::Object(converted)
+}
+
+fn convert_response(response: &Value, produces: &[String]) -> Value {
+    if response.get("$ref").is_some() {
+        return rewrite_refs(response);
+    }
+    let mut converted = json!({
+        "description": response["description"].as_str().unwrap_or(""),
+    });
+    if let Some(schema) = response.get("schema") {
+        let schema = convert_schema(schema);
+        let mut content = Map::new();
+        for media_type in produces {
+            let mut entry = json!({ "schema": schema });
+            if let Some(example) = response["examples"].get(media_type) {
+                entry["example"] = example.clone();
+            }
+            content.insert(media_type.clone(), entry);
+        }
+        converted["content"] = Value::Object(content);
+    }
+    if let Some(headers) = response.get("headers") {
+        converted["headers"] = rewrite_refs(headers);
+    }
+    converted
+}
+
+// Swagger 2.0 names the OAuth flows differently and has `basic` instead of `http`.
+fn convert_security_scheme(scheme: &Value) -> Value {
+    match scheme["type"].as_str() {
+        Some("basic") => json!({ "type": "http", "scheme": "basic" }),
+        Some("oauth2") => {
+            let flow = match scheme["flow"].as_str() {
+                Some("accessCode") => "authorizationCode",
+                Some("application") => "clientCredentials",
+                Some(other) => other,
+                None => "implicit",
+            };
+            let mut converted = Map::new();
+            for key in ["authorizationUrl", "tokenUrl"] {
+                if let Some(url) = scheme.get(key) {
+                    converted.insert(key.into(), url.clone());
+                }
+            }
+            converted.insert(
+                "scopes".into(),
+                scheme.get("scopes").cloned().unwrap_or_else(|| json!({})),
+            );
+            json!({ "type": "oauth2", "flows": { flow: converted } })
+        }
+        _ => scheme.clone(),
+    }
+}
+
+// Rewrites a Swagger 2.0 document into the OpenAPI 3 shape the importer reads.
+pub fn to_openapi3(doc: &Value) -> Value {
+    let mut paths = Map::new();
+    if let Some(source_paths) = doc["paths"].as_object() {
+        for (path, item) in source_paths {
+            let Some(item) = item.as_object() else {
+                continue;
+            };
+            let path_params: Vec<Value> = item
+                .get("parameters")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let mut converted = Map::new();
+            for (method, operation) in item {
+                if method == "parameters" || method.starts_with("x-") {
+                    continue;
+                }
+                converted.insert(
+                    method.clone(),
+                    convert_operation(doc, operation, &path_params),
+                );
+            }
+            paths.insert(path.clone(), Value::Object(converted));
+        }
+    }
+
+    let mut schemas = Map::new();
+    if let Some(definitions) = doc["definitions"].as_object() {
+        for (name, schema) in definitions {
+            schemas.insert(name.clone(), convert_schema(schema));
+        }
+    }
+    let mut parameters = Map::new();
+    if let Some(source) = doc["parameters"].as_object() {
+        for (name, param) in source {
+            if !matches!(param["in"].as_str(), Some("body") | Some("formData")) {
+                parameters.insert(name.clone(), convert_parameter(param));
+            }
+        }
+    }
+    let produces = media_types(&Value::Null, doc, "produces", "application/json");
+    let mut responses = Map::new();
+    if let Some(source) = doc["responses"].as_object() {
+        for (name, response) in source {
+            responses.insert(name.clone(), convert_response(response, &produces));
+        }
+    }
+
+    let mut security_schemes = Map::new();
+    if let Some(source) = doc["securityDefinitions"].as_object() {
+        for (name, scheme) in source {
+            security_schemes.insert(name.clone(), convert_security_scheme(scheme));
+        }
+    }
+
+    let mut converted = json!({
+        "openapi": "3.0.0",
+        "info": doc["info"].clone(),
+        "servers": servers(doc),
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "parameters": parameters,
+            "responses": responses,
+            "securitySchemes": security_schemes,
+        },
+    });
+    for key in ["security", "tags", "x-tagGroups"] {
+        if let Some(value) = doc.get(key) {
+            converted[key] = value.clone();
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn petstore() -> Value {
+        json!({
+            "swagger": "2.0",
+            "info": { "title": "Petstore", "version": "1.0" },
+            "host": "petstore.swagger.io",
+            "basePath": "/v2",
+            "schemes": ["http", "https"],
+            "consumes": ["application/json"],
+            "produces": ["application/json"],
+            "paths": {
+                "/pet/{petId}": {
+                    "parameters": [
+                        { "name": "petId", "in": "path", "required": true, "type": "integer", "format": "int64" }
+                    ],
+                    "get": {
+                        "tags": ["pet"],
+                        "parameters": [
+                            { "name": "status", "in": "query", "type": "string", "enum": ["available", "sold"] },
+                            { "name": "tags", "in": "query", "type": "array", "items": { "type": "string" }, "collectionFormat": "pipes" }
+                        ],
+                        "responses": {
+                            "200": { "description": "ok", "schema": { "$ref": "#/definitions/Pet" } }
+                        }
+                    },
+                    "put": {
+                        "parameters": [
+                            { "name": "body", "in": "body", "required": true, "schema": { "$ref": "#/definitions/Pet" } }
+                        ],
+                        "responses": { "204": { "description": "updated" } }
+                    },
+                    "post": {
+                        "consumes": ["multipart/form-data"],
+                        "parameters": [
+                            { "name": "note", "in": "formData", "type": "string", "description": "Caption" },
+                            { "name": "file", "in": "formData", "type": "file", "required": true }
+                        ],
+                        "responses": { "200": { "description": "uploaded" } }
+                    }
+                }
+            },
+            "securityDefinitions": {
+                "petstore_auth": {
+                    "type": "oauth2",
+                    "flow": "accessCode",
+                    "authorizationUrl": "https://petstore.swagger.io/oauth/authorize",
+                    "tokenUrl": "https://petstore.swagger.io/oauth/token",
+                    "scopes": { "write:pets": "modify pets" }
+                },
+                "basic": { "type": "basic" }
+            },
+            "security": [{ "petstore_auth": ["write:pets"] }],
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string", "example": "doggie" },
+                        "category": { "$ref": "#/definitions/Category" }
+                    }
+                },
+                "Category": { "type": "object", "properties": { "id": { "type": "integer" } } }
+            }
+        })
+    }
+
+    #[test]
+    fn converts_servers_parameters_and_bodies() {
+        let doc = to_openapi3(&petstore());
+        assert!(is_swagger2(&petstore()) && !is_swagger2(&doc));
+        assert_eq!(doc["servers"][0]["url"], "https://petstore.swagger.io/v2");
+        assert_eq!(
+            doc["components"]["schemas"]["Pet"]["properties"]["category"]["$ref"],
+            "#/components/schemas/Category"
+        );
+
+        let get = &doc["paths"]["/pet/{petId}"]["get"];
+        assert_eq!(get["parameters"][0]["schema"]["format"], "int64");
+        assert_eq!(
+            get["parameters"][1]["schema"]["enum"],
+            json!(["available", "sold"])
+        );
+        assert_eq!(get["parameters"][2]["style"], "pipeDelimited");
+        assert_eq!(get["parameters"][2]["explode"], false);
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Pet"
+        );
+
+        let put = &doc["paths"]["/pet/{petId}"]["put"];
+        assert_eq!(put["requestBody"]["required"], true);
+        assert_eq!(
+            put["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Pet"
+        );
+
+        let form = &doc["paths"]["/pet/{petId}"]["post"]["requestBody"]["content"]
+            ["multipart/form-data"]["schema"];
+        assert_eq!(form["properties"]["file"]["format"], "binary");
+        assert_eq!(form["properties"]["note"]["description"], "Caption");
+        assert_eq!(form["required"], json!(["file"]));
+
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(
+            schemes["basic"],
+            json!({ "type": "http", "scheme": "basic" })
+        );
+        assert_eq!(
+            schemes["petstore_auth"]["flows"]["authorizationCode"]["tokenUrl"],
+            "https://petstore.swagger.io/oauth/token"
+        );
+        assert_eq!(doc["security"][0]["petstore_auth"], json!(["write:pets"]));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::http::RequestInput;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{SecondsFormat, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde_json::Value;
+use std::collections::HashMap;
+
+// Variables may refer to other variables; this bounds how deep that goes so a
+// variable referring to itself cannot loop forever.
+const MAX_DEPTH: usize = 10;
+
+// Replaces `{{name}}` placeholders with variable values. Whitespace inside the braces
+// is ignored, and placeholders naming an unknown variable are left as written. Names
+// starting with `$` are generated on the spot; see `dynamic`.
+pub fn render(text: &str, variables: &HashMap<String, String>) -> String {
+    render_depth(text, variables, 0)
+}
+
+fn render_depth(text: &str, variables: &HashMap<String, String>, depth: usize) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length;
+        rendered.push_str(&rest[..start]);
+        let name = rest[start + 2..end].trim();
+        if let Some(value) = name
+            .starts_with('$')
+            .then(|| dynamic(name, variables))
+            .flatten()
+        {
+            rendered.push_str(&value);
+            rest = &rest[end + 2..];
+            continue;
+        }
+        match variables.get(name) {
+            Some(value) if depth < MAX_DEPTH => {
+                rendered.push_str(&render_depth(value, variables, depth + 1))
+            }
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// Built-in generators, evaluated anew for every placeholder:
+//   {{$uuid}}                  random UUID v4
+//   {{$timestamp}}             Unix time in seconds
+//   {{$isoTimestamp}}          current time as RFC 3339
+//   {{$randomInt min max}}     integer in [min, max], 0 to 1000 by default
+//   {{$randomString length}}   alphanumeric string, 16 characters by default
+//   {{$base64 name}}           base64 of the variable `name`
+// Unknown generators and bad arguments leave the placeholder as written.
+fn dynamic(expression: &str, variables: &HashMap<String, String>) -> Option<String> {
+    let mut parts = expression.split_whitespace();
+    let name = parts.next()?;
+    let args: Vec<&str> = parts.collect();
+    let mut rng = rand::thread_rng();
+    match name {
+        "$uuid" | "$guid" | "$randomUUID" => Some(uuid_v4()),
+        "$timestamp" => Some(Utc::now().timestamp().to_string()),
+        "$isoTimestamp" => Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+        "$randomInt" => {
+            let (min, max) = match args.as_slice() {
+                [] => (0, 1000),
+                [min, max] => (min.parse::<i64>().ok()?, max.parse::<i64>().ok()?),
+                _ => return None,
+            };
+            (min <= max).then(|| rng.gen_range(min..=max).to_string())
+        }
+        "$randomString" => {
+            let length = match args.as_slice() {
+                [] => 16,
+                [length] => length.parse::<usize>().ok()?,
+                _ => return None,
+            };
+            Some(
+                (&mut rng)
+                    .sample_iter(&Alphanumeric)
+                    .take(length)
+                    .map(char::from)
+                    .collect(),
+            )
+        }
+        "$base64" => {
+            let [variable] = args.as_slice() else {
+                return None;
+            };
+            let value = render(variables.get(*variable)?, variables);
+            Some(STANDARD.encode(value))
+        }
+        _ => None,
+    }
+}
+
+// Names of the placeholders in `text` that have no variable, for warning the user.
+pub fn unresolved(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + length].trim().to_string();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+        rest = &rest[start + 4 + length..];
+    }
+    names
+}
+
+pub fn render_json(value: Value, variables: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(s) => Value::String(render(&s, variables)),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| render_json(item, variables))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, render_json(value, variables)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+// Substitutes variables into the URL, query, headers, parameters and body of a request.
+pub fn render_input(mut input: RequestInput, variables: &HashMap<String, String>) -> RequestInput {
+    let pairs = |pairs: Vec<(String, String)>| -> Vec<(String, String)> {
+        pairs
+            .into_iter()
+            .map(|(name, value)| (render(&name, variables), render(&value, variables)))
+            .collect()
+    };
+    input.url = render(&input.url, variables);
+    input.query = input.query.map(pairs);
+    input.form = input.form.map(pairs);
+    input.headers = input
+        .headers
+        .into_iter()
+        .map(|(name, value)| (render(&name, variables), render(&value, variables)))
+        .collect();
+    input.body = input.body.map(|body| render(&body, variables));
+    if let Some(graphql) = &mut input.graphql {
+        graphql.query = render(&graphql.query, variables);
+        graphql.variables = graphql
+            .variables
+            .take()
+            .map(|value| render_json(value, variables));
+    }
+    if let Some(jsonrpc) = &mut input.jsonrpc {
+        for call in jsonrpc.calls.iter_mut() {
+            call.method = render(&call.method, variables);
+            call.params = call
+                .params
+                .take()
+                .map(|value| render_json(value, variables));
+        }
+    }
+    if let Some(multipart) = &mut input.multipart {
+        for value in multipart.fields.values_mut() {
+            *value = render(value, variables);
+        }
+    }
+    if let Some(params) = &mut input.params {
+        for param in params.iter_mut() {
+            param.value = render_json(std::mem::take(&mut param.value), variables);
+        }
+    }
+    input
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitutes_nested_and_leaves_unknown_placeholders() {
+        let variables = vars(&[
+            ("host", "api.test"),
+            ("base", "https://{{host}}/v1"),
+            ("loop", "{{loop}}"),
+        ]);
+        assert_eq!(
+            render("{{ base }}/users?q={{missing}}", &variables),
+            "https://api.test/v1/users?q={{missing}}"
+        );
+        assert_eq!(render("{{loop}}", &variables), "{{loop}}");
+        assert_eq!(render("{{unterminated", &variables), "{{unterminated");
+        assert_eq!(
+            unresolved(&render("{{base}} {{a}} {{ a }} {{b}}", &variables)),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn generates_dynamic_values() {
+        let variables = vars(&[("user", "alice"), ("credentials", "{{user}}:pw")]);
+        let uuid = render("{{$uuid}}", &variables);
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert_ne!(render("{{$uuid}}", &variables), uuid);
+        let number: i64 = render("{{ $randomInt 5 7 }}", &variables).parse().unwrap();
+        assert!((5..=7).contains(&number));
+        assert_eq!(render("{{$randomString 8}}", &variables).len(), 8);
+        assert!(render("{{$timestamp}}", &variables).parse::<i64>().is_ok());
+        assert_eq!(
+            render("Basic {{$base64 credentials}}", &variables),
+            "Basic YWxpY2U6cHc="
+        );
+        assert_eq!(
+            render("{{$randomInt 9 1}} {{$nope}}", &variables),
+            "{{$randomInt 9 1}} {{$nope}}"
+        );
+    }
+
+    #[test]
+    fn renders_every_part_of_a_request() {
+        let variables = vars(&[("host", "api.test"), ("token", "t0k"), ("id", "7")]);
+        let input = render_input(
+            RequestInput {
+                method: "POST".into(),
+                url: "https://{{host}}/users/{id}".into(),
+                query: Some(vec![("q".into(), "{{id}}".into())]),
+                headers: HashMap::from([("Authorization".into(), "Bearer {{token}}".into())]),
+                body: Some(r#"{"id": {{id}}}"#.into()),
+                params: Some(vec![crate::params::ParameterValue {
+                    name: "id".into(),
+                    in_type: "path".into(),
+                    value: serde_json::json!(["{{id}}", 8]),
+                    style: None,
+                    explode: None,
+                }]),
+                ..RequestInput::default()
+            },
+            &variables,
+        );
+        assert_eq!(input.url, "https://api.test/users/{id}");
+        assert_eq!(input.query.unwrap()[0].1, "7");
+        assert_eq!(input.headers["Authorization"], "Bearer t0k");
+        assert_eq!(input.body.as_deref(), Some(r#"{"id": 7}"#));
+        assert_eq!(input.params.unwrap()[0].value, serde_json::json!(["7", 8]));
+    }
+}
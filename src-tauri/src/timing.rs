@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+
+// reqwest does not expose its connections, so DNS, TCP and TLS are not timed apart:
+// `ttfb_ms` includes whatever connection setup the request needed, which is nothing
+// when a pooled connection was reused.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ResponseTiming {
+    pub ttfb_ms: f64,
+    pub download_ms: f64,
+    pub total_ms: f64,
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,125 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+
+// Collections imported from `file://` URLs or plain paths are read from disk.
+pub fn local_spec_path(location: &str) -> Option<PathBuf> {
+    let location = location.trim();
+    let lower = location.to_ascii_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        return None;
+    }
+    if lower.starts_with("file://") {
+        return reqwest::Url::parse(location).ok()?.to_file_path().ok();
+    }
+    Some(PathBuf::from(location))
+}
+
+// Watches the directories holding imported spec files and reports the collections
+// reading every file that changes. Directories are watched rather than the files because
+// editors often save by replacing the file, which would end a per-file watch.
+pub struct SpecWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    files: Arc<Mutex<HashMap<PathBuf, Vec<String>>>>,
+}
+
+impl SpecWatcher {
+    pub fn new(changes: UnboundedSender<String>) -> Result<Self, String> {
+        let files: Arc<Mutex<HashMap<PathBuf, Vec<String>>>> = Arc::new(Mutex::new(HashMap::new()));
+        let watched = files.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if event.kind.is_access() {
+                return;
+            }
+            let files = watched.lock().unwrap();
+            for path in &event.paths {
+                for location in files.get(path).into_iter().flatten() {
+                    let _ = changes.send(location.clone());
+                }
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        Ok(SpecWatcher {
+            watcher: Mutex::new(watcher),
+            files,
+        })
+    }
+
+    pub fn watch(&self, path: &Path, location: &str) -> Result<(), String> {
+        let path = path.canonicalize().map_err(|e| e.to_string())?;
+        let dir = path.parent().ok_or("Spec file has no parent directory")?;
+        let mut files = self.files.lock().unwrap();
+        if !files.keys().any(|file| file.parent() == Some(dir)) {
+            self.watcher
+                .lock()
+                .unwrap()
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|e| e.to_string())?;
+        }
+        let locations = files.entry(path).or_default();
+        if !locations.iter().any(|l| l == location) {
+            locations.push(location.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        self.files.lock().unwrap().clear();
+    }
+
+    // The directory stays watched; events for files nobody reads are ignored.
+    pub fn unwatch(&self, location: &str) {
+        let mut files = self.files.lock().unwrap();
+        for locations in files.values_mut() {
+            locations.retain(|l| l != location);
+        }
+        files.retain(|_, locations| !locations.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn recognizes_local_locations() {
+        assert_eq!(local_spec_path("https://x.test/spec.yaml"), None);
+        assert_eq!(
+            local_spec_path("file:///tmp/spec.yaml"),
+            Some(PathBuf::from("/tmp/spec.yaml"))
+        );
+        assert_eq!(
+            local_spec_path(" ./specs/api.json "),
+            Some(PathBuf::from("./specs/api.json"))
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_changes_to_watched_files() {
+        let dir = std::env::temp_dir().join(format!("restman-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spec = dir.join("api.yaml");
+        std::fs::write(&spec, "openapi: 3.0.0\n").unwrap();
+        std::fs::write(dir.join("other.yaml"), "").unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let watcher = SpecWatcher::new(tx).unwrap();
+        watcher.watch(&spec, "file-collection").unwrap();
+        watcher.watch(&spec, "removed-copy").unwrap();
+        watcher.unwatch("removed-copy");
+        std::fs::write(dir.join("other.yaml"), "ignored").unwrap();
+        std::fs::write(&spec, "openapi: 3.0.1\n").unwrap();
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(changed.as_deref(), Some("file-collection"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
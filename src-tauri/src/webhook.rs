@@ -0,0 +1,335 @@
+use crate::body::{encode_preview, looks_binary, BodyEncoding};
+use crate::proxy::{answered, read_body, read_head, write_reply};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+fn default_retain() -> usize {
+    100
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+fn default_body() -> String {
+    "OK".into()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WebhookOptions {
+    // 0 picks a free port.
+    pub port: u16,
+    // Captures kept by this listener; the oldest are dropped first.
+    #[serde(default = "default_retain")]
+    pub retain: usize,
+    // Captures older than this are dropped as well.
+    pub retain_minutes: Option<u64>,
+    // The reply every request gets, such as the 200 a payment provider waits for.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    pub content_type: Option<String>,
+    #[serde(default = "default_body")]
+    pub body: String,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        WebhookOptions {
+            port: 0,
+            retain: default_retain(),
+            retain_minutes: None,
+            status: default_status(),
+            content_type: None,
+            body: default_body(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WebhookInfo {
+    pub listener_id: String,
+    pub url: String,
+    pub options: WebhookOptions,
+    pub captures: usize,
+}
+
+// One request received by a listener. Binary bodies come base64 encoded.
+#[derive(Serialize, Clone, Debug)]
+pub struct WebhookCapture {
+    pub id: u64,
+    pub listener_id: String,
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub body_encoding: BodyEncoding,
+    pub size: u64,
+    pub remote_addr: String,
+    pub received_at: DateTime<Utc>,
+}
+
+struct Shared {
+    listener_id: String,
+    options: RwLock<WebhookOptions>,
+    captures: Mutex<VecDeque<WebhookCapture>>,
+    next_id: AtomicU64,
+}
+
+impl Shared {
+    fn prune(&self, captures: &mut VecDeque<WebhookCapture>) {
+        let options = self.options.read().unwrap();
+        while captures.len() > options.retain {
+            captures.pop_front();
+        }
+        if let Some(minutes) = options.retain_minutes {
+            let cutoff = Utc::now() - ChronoDuration::minutes(minutes as i64);
+            captures.retain(|capture| capture.received_at >= cutoff);
+        }
+    }
+}
+
+pub struct WebhookListener {
+    shared: Arc<Shared>,
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+impl WebhookListener {
+    pub async fn start(
+        listener_id: &str,
+        options: WebhookOptions,
+        on_capture: Arc<dyn Fn(WebhookCapture) + Send + Sync>,
+    ) -> Result<WebhookListener, String> {
+        validate(&options)?;
+        let listener = TcpListener::bind(("127.0.0.1", options.port))
+            .await
+            .map_err(|e| format!("Cannot start the webhook listener: {}", e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let shared = Arc::new(Shared {
+            listener_id: listener_id.to_string(),
+            options: RwLock::new(options),
+            captures: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        });
+        let serving = shared.clone();
+        let task = tokio::spawn(async move {
+            // Dropped when the listener stops, which aborts the requests still being read.
+            let mut connections = JoinSet::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                while connections.try_join_next().is_some() {}
+                let shared = serving.clone();
+                let on_capture = on_capture.clone();
+                connections.spawn(async move { serve(socket, &shared, on_capture.as_ref()).await });
+            }
+            while connections.join_next().await.is_some() {}
+        });
+        Ok(WebhookListener { shared, port, task })
+    }
+
+    pub fn info(&self) -> WebhookInfo {
+        let mut options = self.shared.options.read().unwrap().clone();
+        options.port = self.port;
+        WebhookInfo {
+            listener_id: self.shared.listener_id.clone(),
+            url: format!("http://127.0.0.1:{}", self.port),
+            options,
+            captures: self.captures().len(),
+        }
+    }
+
+    // Oldest first.
+    pub fn captures(&self) -> Vec<WebhookCapture> {
+        let mut captures = self.shared.captures.lock().unwrap();
+        self.shared.prune(&mut captures);
+        captures.iter().cloned().collect()
+    }
+
+    // Drops one capture, or all of them without an id. Returns how many went.
+    pub fn clear(&self, capture_id: Option<u64>) -> usize {
+        let mut captures = self.shared.captures.lock().unwrap();
+        let before = captures.len();
+        match capture_id {
+            Some(id) => captures.retain(|capture| capture.id != id),
+            None => captures.clear(),
+        }
+        before - captures.len()
+    }
+
+    // The reply and retention change in place; the port does not.
+    pub fn configure(&self, options: WebhookOptions) -> Result<(), String> {
+        validate(&options)?;
+        *self.shared.options.write().unwrap() = options;
+        let mut captures = self.shared.captures.lock().unwrap();
+        self.shared.prune(&mut captures);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+fn validate(options: &WebhookOptions) -> Result<(), String> {
+    if !(100..600).contains(&options.status) {
+        return Err(format!("Invalid reply status: {}", options.status));
+    }
+    Ok(())
+}
+
+// Captures one request per connection and answers it with the configured reply.
+async fn serve(
+    mut socket: TcpStream,
+    shared: &Shared,
+    on_capture: &(dyn Fn(WebhookCapture) + Send + Sync),
+) -> Option<()> {
+    let remote_addr = socket
+        .peer_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    let mut reader = BufReader::new(&mut socket);
+    let head = read_head(&mut reader).await;
+    let head = answered(head, reader.get_mut()).await.ok()??;
+    let bytes = read_body(&mut reader, &head.headers).await;
+    let bytes = answered(bytes, reader.get_mut()).await.ok()?;
+    let content_type = head
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str());
+    let (body, body_encoding) = if looks_binary(content_type, &bytes) {
+        encode_preview(&bytes)
+    } else {
+        (
+            String::from_utf8_lossy(&bytes).into_owned(),
+            BodyEncoding::Text,
+        )
+    };
+    let (path, query) = match reqwest::Url::parse(&format!("http://localhost{}", head.target)) {
+        Ok(url) => (
+            url.path().to_string(),
+            url.query_pairs().into_owned().collect(),
+        ),
+        Err(_) => (head.target.clone(), Vec::new()),
+    };
+    let capture = WebhookCapture {
+        id: shared.next_id.fetch_add(1, Ordering::Relaxed),
+        listener_id: shared.listener_id.clone(),
+        method: head.method,
+        path,
+        query,
+        headers: head.headers,
+        body,
+        body_encoding,
+        size: bytes.len() as u64,
+        remote_addr,
+        received_at: Utc::now(),
+    };
+    {
+        let mut captures = shared.captures.lock().unwrap();
+        captures.push_back(capture.clone());
+        shared.prune(&mut captures);
+    }
+    on_capture(capture);
+    let options = shared.options.read().unwrap().clone();
+    write_reply(
+        &mut socket,
+        options.status,
+        options.content_type.as_deref(),
+        &[],
+        options.body.as_bytes(),
+    )
+    .await;
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn captures_requests_and_keeps_the_newest() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let options = WebhookOptions {
+            retain: 2,
+            status: 202,
+            body: "accepted".into(),
+            ..WebhookOptions::default()
+        };
+        let listener = WebhookListener::start(
+            "payments",
+            options,
+            Arc::new(move |capture: WebhookCapture| sink.lock().unwrap().push(capture.id)),
+        )
+        .await
+        .unwrap();
+        let url = listener.info().url;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/hooks/stripe?attempt=1", url))
+            .header("Stripe-Signature", "t=1,v1=abc")
+            .json(&serde_json::json!({ "type": "charge.succeeded" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 202);
+        assert_eq!(response.text().await.unwrap(), "accepted");
+        client
+            .get(format!("{}/callback?code=xyz&state=s1", url))
+            .send()
+            .await
+            .unwrap();
+        client
+            .put(format!("{}/bin", url))
+            .header("Content-Type", "application/octet-stream")
+            .body(vec![0u8, 1, 2])
+            .send()
+            .await
+            .unwrap();
+
+        let captures = listener.captures();
+        assert_eq!(captures.len(), 2);
+        assert_eq!(captures[0].path, "/callback");
+        assert_eq!(
+            captures[0].query,
+            vec![("code".into(), "xyz".into()), ("state".into(), "s1".into())]
+        );
+        assert_eq!(captures[1].body, "AAEC");
+        assert_eq!(captures[1].body_encoding, BodyEncoding::Base64);
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+
+        assert_eq!(listener.clear(Some(captures[0].id)), 1);
+        assert_eq!(listener.info().captures, 1);
+        listener.stop();
+    }
+
+    #[tokio::test]
+    async fn stop_closes_open_connections() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = WebhookListener::start("hooks", WebhookOptions::default(), Arc::new(|_| {}))
+            .await
+            .unwrap();
+        let address = listener.info().url.replace("http://", "");
+        let mut socket = TcpStream::connect(address).await.unwrap();
+        // Half a request keeps its connection waiting for the rest.
+        socket.write_all(b"POST / HTTP/1.1\r\n").await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        listener.stop();
+        let mut rest = Vec::new();
+        let read = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            socket.read_to_end(&mut rest),
+        )
+        .await;
+        assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))));
+    }
+}
@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const WORKSPACES_FILE: &str = "workspaces.json";
+const WORKSPACES_DIR: &str = "workspaces";
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Registry {
+    active: String,
+    workspaces: Vec<Workspace>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry {
+            active: DEFAULT_WORKSPACE.into(),
+            workspaces: vec![Workspace {
+                id: DEFAULT_WORKSPACE.into(),
+                name: "Default".into(),
+                created_at: Utc::now(),
+            }],
+        }
+    }
+}
+
+// Every workspace keeps its collections, saved requests and cookies in a store of its
+// own, so projects never see each other's data. The default workspace lives directly
+// in the data dir, where everything was kept before workspaces existed.
+pub fn workspace_dir(data_dir: &Path, id: &str) -> PathBuf {
+    if id == DEFAULT_WORKSPACE {
+        data_dir.to_path_buf()
+    } else {
+        data_dir.join(WORKSPACES_DIR).join(id)
+    }
+}
+
+// Turns a name into a directory-safe id: "Acme Corp." becomes "acme-corp".
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "workspace".into()
+    } else {
+        slug.into()
+    }
+}
+
+pub struct WorkspaceRegistry {
+    path: PathBuf,
+    registry: Mutex<Registry>,
+}
+
+impl WorkspaceRegistry {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(WORKSPACES_FILE);
+        let mut registry: Registry = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        if !registry.workspaces.iter().any(|w| w.id == registry.active) {
+            registry.active = DEFAULT_WORKSPACE.into();
+        }
+        WorkspaceRegistry {
+            path,
+            registry: Mutex::new(registry),
+        }
+    }
+
+    fn save(&self, registry: &Registry) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let content = serde_json::to_string_pretty(registry).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> Vec<Workspace> {
+        self.registry.lock().unwrap().workspaces.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Workspace> {
+        self.registry
+            .lock()
+            .unwrap()
+            .workspaces
+            .iter()
+            .find(|w| w.id == id)
+            .cloned()
+    }
+
+    pub fn active(&self) -> String {
+        self.registry.lock().unwrap().active.clone()
+    }
+
+    pub fn create(&self, name: &str) -> Result<Workspace, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Workspace name is required".into());
+        }
+        let mut registry = self.registry.lock().unwrap();
+        let base = slug(name);
+        let id = (1..)
+            .map(|n| {
+                if n == 1 {
+                    base.clone()
+                } else {
+                    format!("{}-{}", base, n)
+                }
+            })
+            .find(|id| !registry.workspaces.iter().any(|w| &w.id == id))
+            .unwrap_or(base);
+        let workspace = Workspace {
+            id,
+            name: name.to_string(),
+            created_at: Utc::now(),
+        };
+        registry.workspaces.push(workspace.clone());
+        self.save(&registry)?;
+        Ok(workspace)
+    }
+
+    pub fn set_active(&self, id: &str) -> Result<(), String> {
+        let mut registry = self.registry.lock().unwrap();
+        if !registry.workspaces.iter().any(|w| w.id == id) {
+            return Err(format!("Unknown workspace: {}", id));
+        }
+        registry.active = id.to_string();
+        self.save(&registry)
+    }
+
+    // The default and the active workspace cannot be deleted.
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let mut registry = self.registry.lock().unwrap();
+        if id == DEFAULT_WORKSPACE || id == registry.active {
+            return Err("Cannot delete the default or the active workspace".into());
+        }
+        let before = registry.workspaces.len();
+        registry.workspaces.retain(|w| w.id != id);
+        if registry.workspaces.len() == before {
+            return Err(format!("Unknown workspace: {}", id));
+        }
+        self.save(&registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_switches_and_deletes_workspaces() {
+        let dir = std::env::temp_dir().join(format!("restman-workspaces-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let registry = WorkspaceRegistry::load(&dir);
+        assert_eq!(registry.active(), DEFAULT_WORKSPACE);
+        let acme = registry.create(" Acme Corp. ").unwrap();
+        assert_eq!(acme.id, "acme-corp");
+        assert_eq!(registry.create("ACME corp").unwrap().id, "acme-corp-2");
+        assert_eq!(registry.create("???").unwrap().id, "workspace");
+        registry.set_active("acme-corp").unwrap();
+        assert!(registry.delete("acme-corp").is_err());
+        assert!(registry.delete(DEFAULT_WORKSPACE).is_err());
+        registry.delete("acme-corp-2").unwrap();
+
+        let reloaded = WorkspaceRegistry::load(&dir);
+        assert_eq!(reloaded.active(), "acme-corp");
+        assert_eq!(reloaded.list().len(), 3);
+        assert_eq!(
+            workspace_dir(&dir, "acme-corp"),
+            dir.join("workspaces").join("acme-corp")
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -11,6 +11,7 @@ import type {
   Endpoint,
   HistoryEntry,
   HttpMethod,
+  ResponseData,
 } from "./types";
 import "./App.css";
 
@@ -40,6 +41,20 @@ const syncResetDelayMs = 3500;
 const minSidebarWidth = 240;
 const minWorkspaceWidth = 360;
 
+function formatResponse(data: ResponseData) {
+  const statusLine = `${data.status} ${data.status_text}`.trim();
+  const headerLines = Object.entries(data.headers)
+    .flatMap(([name, values]) => values.map((value) => `${name}: ${value}`))
+    .join("\n");
+  const body =
+    data.body_encoding === "text"
+      ? data.body
+      : `[binary ${data.content_type || "content"}, ${data.size} bytes${
+          data.body_path ? ` saved to ${data.body_path}` : ""
+        }]`;
+  return `Status: ${statusLine}\n\nHeaders:\n${headerLines}\n\nBody:\n${body}`;
+}
+
 function endpointKey(endpoint: Endpoint) {
   return `${endpoint.method}:${endpoint.path}`;
 }
@@ -692,7 +707,7 @@ function App() {
   ) {
     let finalUrl = urlInput;
     const headers: Record<string, string> = {};
-    const queryParams = new URLSearchParams();
+    const query: Array<[string, string]> = [];
 
     if (endpoint) {
       endpoint.parameters.forEach((param) => {
@@ -705,14 +720,15 @@ function App() {
             encodeURIComponent(val)
           );
         } else if (param.in_type === "query") {
-          queryParams.append(param.name, val);
+          query.push([param.name, val]);
         } else if (param.in_type === "header") {
           headers[param.name] = val;
         }
       });
     }
 
-    const queryString = queryParams.toString();
+    const requestUrl = finalUrl;
+    const queryString = new URLSearchParams(query).toString();
     if (queryString) {
       finalUrl += (finalUrl.includes("?") ? "&" : "?") + queryString;
     }
@@ -726,8 +742,9 @@ function App() {
       headers["Authorization"] = authHeader;
     }
     const isFormBody = isFormBodyType(bodyTypeInput);
-    const allowBody = methodInput !== "GET";
+    const allowBody = methodInput !== "GET" && methodInput !== "HEAD";
     let body: string | null = null;
+    let form: Array<[string, string]> | null = null;
     let multipart: {
       fields: Record<string, string>;
       files: Array<{ name: string; paths: string[] }>;
@@ -746,17 +763,10 @@ function App() {
           .filter((entry) => entry.paths.length > 0);
         multipart = { fields, files };
       } else if (bodyTypeInput === "application/x-www-form-urlencoded") {
-        const formParams = new URLSearchParams();
-        Object.entries(formValuesInput).forEach(([key, value]) => {
-          if (value) {
-            formParams.append(key, value);
-          }
-        });
-        const encoded = formParams.toString();
-        body = encoded.length > 0 ? encoded : null;
-        if (body && !hasHeader("Content-Type")) {
-          headers["Content-Type"] = "application/x-www-form-urlencoded";
-        }
+        const fields = Object.entries(formValuesInput).filter(
+          ([, value]) => value
+        );
+        form = fields.length > 0 ? fields : null;
       } else if (!isFormBody) {
         const trimmed = bodyInput.trim();
         body = trimmed.length > 0 ? bodyInput : null;
@@ -766,7 +776,7 @@ function App() {
       }
     }
 
-    return { finalUrl, headers, body, multipart };
+    return { finalUrl, requestUrl, query, headers, body, form, multipart };
   }
 
   async function runBackgroundRequest(endpoint: Endpoint) {
@@ -781,25 +791,29 @@ function App() {
       ? collectionAuthTokensRef.current[collectionUrl]
       : undefined;
     const draft = draftsRef.current[key] || buildDraftFromEndpoint(endpoint);
-    const { finalUrl, headers, body, multipart } = buildRequestPayload(
-      endpoint,
-      endpoint.method,
-      resolvedEndpointUrl,
-      draft.params,
-      draft.body,
-      draft.bodyType,
-      draft.formValues,
-      draft.fileValues,
-      collectionAuthToken
-    );
+    const { finalUrl, requestUrl, query, headers, body, form, multipart } =
+      buildRequestPayload(
+        endpoint,
+        endpoint.method,
+        resolvedEndpointUrl,
+        draft.params,
+        draft.body,
+        draft.bodyType,
+        draft.formValues,
+        draft.fileValues,
+        collectionAuthToken
+      );
     try {
-      const res: string = await invoke("request", {
+      const data: ResponseData = await invoke("request", {
         method: endpoint.method,
-        url: finalUrl,
+        url: requestUrl,
+        query,
         headers,
         body,
+        form,
         multipart,
       });
+      const res = formatResponse(data);
       addHistoryEntry({
         id: `${Date.now()}-${Math.random().toString(16).slice(2)}`,
         created_at: Date.now(),
@@ -856,25 +870,29 @@ function App() {
       const collectionAuthToken = collectionUrl
         ? collectionAuthTokens[collectionUrl]
         : undefined;
-      const { finalUrl, headers, body, multipart } = buildRequestPayload(
-        selectedEndpoint,
-        method,
-        trimmedUrl,
-        paramSnapshot,
-        bodySnapshot,
-        bodyTypeSnapshot,
-        formSnapshot,
-        fileSnapshot,
-        collectionAuthToken
-      );
+      const { finalUrl, requestUrl, query, headers, body, form, multipart } =
+        buildRequestPayload(
+          selectedEndpoint,
+          method,
+          trimmedUrl,
+          paramSnapshot,
+          bodySnapshot,
+          bodyTypeSnapshot,
+          formSnapshot,
+          fileSnapshot,
+          collectionAuthToken
+        );
       resolvedUrl = finalUrl;
-      const res: string = await invoke("request", {
+      const data: ResponseData = await invoke("request", {
         method,
-        url: finalUrl,
+        url: requestUrl,
+        query,
         headers,
         body,
+        form,
         multipart,
       });
+      const res = formatResponse(data);
       finalResponse = res;
       setResponse(res);
       showMessage("Request Success");
@@ -25,10 +25,18 @@ type RequestPanelProps = {
   onFileValuesChange: (name: string, paths: string[]) => void;
 };
 
-const methodOptions: HttpMethod[] = ["GET", "POST", "PUT", "DELETE", "PATCH"];
+const methodOptions: HttpMethod[] = [
+  "GET",
+  "POST",
+  "PUT",
+  "DELETE",
+  "PATCH",
+  "HEAD",
+  "OPTIONS",
+];
 
 function hasBody(method: HttpMethod) {
-  return method !== "GET";
+  return method !== "GET" && method !== "HEAD";
 }
 
 function buildParamLabel(param: Parameter) {
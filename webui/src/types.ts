@@ -1,4 +1,11 @@
-export type HttpMethod = "GET" | "POST" | "PUT" | "DELETE" | "PATCH";
+export type HttpMethod =
+  | "GET"
+  | "POST"
+  | "PUT"
+  | "DELETE"
+  | "PATCH"
+  | "HEAD"
+  | "OPTIONS";
 
 export interface Parameter {
   name: string;
@@ -7,6 +14,21 @@ export interface Parameter {
   required: boolean;
   example?: unknown;
   enum_values?: string[];
+  schema_type?: string;
+  format?: string;
+  default?: unknown;
+  deprecated?: boolean;
+  style?: string;
+  explode?: boolean;
+}
+
+// Values the backend serializes by style/explode when building the request URL.
+export interface ParameterValue {
+  name: string;
+  in_type: string;
+  value: unknown;
+  style?: string;
+  explode?: boolean;
 }
 
 export interface BodyField {
@@ -24,11 +46,43 @@ export interface ResponseSchema {
   schema?: unknown;
 }
 
+export interface ResponseHeader {
+  name: string;
+  description?: string;
+  required: boolean;
+  example?: unknown;
+}
+
+export interface EndpointResponse {
+  description?: string;
+  media_types: string[];
+  example?: string;
+  examples?: Record<string, string>;
+  headers: ResponseHeader[];
+}
+
+export interface ServerVariable {
+  default: string;
+  enum_values: string[];
+  description?: string;
+}
+
+export interface Server {
+  url: string;
+  description?: string;
+  variables: Record<string, ServerVariable>;
+}
+
 export interface Endpoint {
   method: HttpMethod;
   path: string;
+  route?: string;
+  servers?: Server[];
   summary?: string;
   description?: string;
+  operation_id?: string;
+  deprecated?: boolean;
+  external_docs?: { url: string; description?: string };
   parameters: Parameter[];
   body_example?: string;
   body_description?: string;
@@ -36,9 +90,141 @@ export interface Endpoint {
   body_media_types?: string[];
   body_fields?: BodyField[];
   body_fields_type?: string;
+  body_schema?: unknown;
   response_schemas?: ResponseSchema[];
+  responses?: Record<string, EndpointResponse>;
+  security?: Record<string, string[]>[];
+}
+
+// "http2" is offered over TLS and falls back to HTTP/1.1; "h2c" speaks HTTP/2 in
+// cleartext from the start, without negotiating.
+export type HttpVersionPreference = "auto" | "http1" | "http2" | "h2c" | "http3";
+
+// `ttfb_ms` includes any DNS, TCP and TLS setup the request needed.
+export interface ResponseTiming {
+  ttfb_ms: number;
+  download_ms: number;
+  total_ms: number;
+}
+
+export interface AttemptRecord {
+  attempt: number;
+  status?: number;
+  error?: string;
+  elapsed_ms: number;
+  delay_ms?: number;
+}
+
+export interface ResponseData {
+  status: number;
+  status_text: string;
+  http_version: string;
+  headers: Record<string, string[]>;
+  // Every header in wire order and case, repeats included. Only set for proxy
+  // captures; responses to sent requests carry just `headers`.
+  header_list?: [string, string][];
+  body: string;
+  body_encoding: "text" | "base64" | "none";
+  // Set for binary bodies and for text bodies too large to return whole; a text
+  // `body` is then only the start of the file, read the rest with read_response_chunk.
+  body_path?: string;
+  content_type?: string;
+  elapsed_ms: number;
+  size: number;
+  encoded_size: number;
+  content_encoding?: string;
+  decompressed: boolean;
+  timing: ResponseTiming;
+  attempts: AttemptRecord[];
+  extracted?: { variable: string; value?: string; error?: string }[];
+  assertions?: AssertionResult[];
+  scripts?: ScriptReport;
+  cookies?: SetCookie[];
+  jsonrpc?: JsonRpcOutcome[];
+  // Present for requests of an OpenAPI collection whose URL matches an operation.
+  contract?: ContractReport;
+  // Present when the response carried RateLimit-*, X-RateLimit-* or Retry-After headers.
+  rate_limit?: RateLimitInfo;
+}
+
+// Times are seconds from when the response arrived; an epoch `X-RateLimit-Reset` is
+// turned into one too.
+export interface RateLimitInfo {
+  limit: number | null;
+  remaining: number | null;
+  reset_secs: number | null;
+  retry_after_secs: number | null;
+  policy: string | null;
+}
+
+// `declared` is the spec response the status matched, such as "200", "4XX" or
+// "default"; `path` is a JSON pointer into the body for schema violations.
+export interface ContractReport {
+  operation: string;
+  declared: string | null;
+  violations: { kind: "status" | "content_type" | "body"; path: string | null; message: string }[];
+  // Problems with the request itself, found before it was sent.
+  request?: RequestIssue[];
+}
+
+// `name` is the parameter's, or a JSON pointer for body problems. With validation set
+// to "block", requests with issues fail instead of being sent.
+export interface RequestIssue {
+  location: "path" | "query" | "header" | "cookie" | "body";
+  name: string | null;
+  message: string;
+}
+
+export type RequestValidation = "off" | "warn" | "block";
+
+// A `Set-Cookie` header of the response or of a redirect or challenge before it, from
+// `url`; `accepted` is whether the jar stored it, `rejected` says why it did not.
+export interface SetCookie {
+  raw: string;
+  url: string;
+  name: string;
+  value: string;
+  domain?: string;
+  path?: string;
+  expires?: string;
+  max_age?: number;
+  same_site?: string;
+  secure: boolean;
+  http_only: boolean;
+  accepted: boolean;
+  rejected?: string;
 }
 
+export interface ScriptReport {
+  logs: string[];
+  tests: { name: string; passed: boolean }[];
+  error?: string;
+}
+
+export type Comparison =
+  | "eq" | "ne" | "lt" | "lte" | "gt" | "gte"
+  | "contains" | "not_contains" | "matches" | "exists" | "not_exists";
+
+export type Assertion =
+  | { type: "status"; op?: Comparison; value: unknown }
+  | { type: "response_time"; max_ms: number }
+  | { type: "header"; name: string; op?: Comparison; value?: unknown }
+  | { type: "json"; path: string; op?: Comparison; value?: unknown }
+  | { type: "schema"; schema: unknown };
+
+export interface AssertionResult {
+  assertion: Assertion;
+  passed: boolean;
+  actual?: unknown;
+  message?: string;
+}
+
+export type Extraction = { variable: string } & (
+  | { from: "json"; path: string }
+  | { from: "header"; name: string }
+  | { from: "regex"; pattern: string; group?: number }
+);
+
 export interface HistoryEntry {
   id: string;
   created_at: number;
@@ -53,9 +239,638 @@ export interface HistoryEntry {
   response: string;
 }
 
+// History kept by the backend; see list_history / replay_history.
+export interface RecordedRequest {
+  id: number;
+  timestamp: string;
+  request: {
+    method: string;
+    url: string;
+    query?: [string, string][];
+    headers: Record<string, string>;
+    body?: string;
+    graphql?: GraphqlQuery;
+    jsonrpc?: JsonRpcRequest;
+    collection?: string;
+    params?: ParameterValue[];
+    environment?: string;
+    extract?: Extraction[];
+    assertions?: Assertion[];
+    pre_request_script?: string;
+    post_response_script?: string;
+    request_validation?: RequestValidation;
+  };
+  response?: ResponseData;
+  error?: string;
+}
+
+export interface SavedRequest {
+  id: string;
+  name: string;
+  folder: string;
+  request: RecordedRequest["request"] & { auth?: unknown };
+  examples?: { name: string; status?: number; headers: [string, string][]; body?: string }[];
+  created_at?: string;
+  updated_at?: string;
+}
+
+export interface Environment {
+  name: string;
+  variables: { name: string; value: string; enabled?: boolean; secret?: boolean }[];
+}
+
+export type ExportSource = { from: "collection"; url: string } | { from: "saved_requests" };
+
+export type ExportFormat = "postman" | "insomnia";
+
+export type HarSource =
+  | { from: "history"; filter?: HistoryFilter }
+  | { from: "run"; summary: RunSummary };
+
+export interface HarImport {
+  saved_requests: SavedRequest[];
+  warnings: string[];
+}
+
+// Returned by import_postman, import_insomnia, import_bruno and import_wsdl.
+export interface CollectionImport {
+  name: string;
+  saved_requests: SavedRequest[];
+  environments: Environment[];
+  warnings: string[];
+}
+
+export type RunStep =
+  | { type: "saved"; id: string }
+  | { type: "request"; name?: string; request: RecordedRequest["request"] };
+
+export interface RunOptions {
+  run_id?: string;
+  steps: RunStep[];
+  environment?: string;
+  delay_ms?: number;
+  stop_on_failure?: boolean;
+  data_file?: string;
+  // Wait before the next step as long as a response's Retry-After, or its rate limit
+  // reset once none are left, asks (up to a minute).
+  honor_rate_limits?: boolean;
+}
+
+export interface StepResult {
+  run_id: string;
+  iteration: number;
+  index: number;
+  name: string;
+  method: string;
+  url: string;
+  passed: boolean;
+  status?: number;
+  elapsed_ms: number;
+  error?: string;
+  extracted: NonNullable<ResponseData["extracted"]>;
+  assertions: AssertionResult[];
+  response?: ResponseData;
+}
+
+export interface IterationResult {
+  index: number;
+  data: Record<string, string>;
+  passed: number;
+  failed: number;
+}
+
+export interface RunSummary {
+  run_id: string;
+  steps: StepResult[];
+  iterations: IterationResult[];
+  passed: number;
+  failed: number;
+  skipped: number;
+  elapsed_ms: number;
+}
+
+export interface Monitor {
+  id?: string;
+  name: string;
+  steps: RunStep[];
+  interval_minutes: number;
+  environment?: string;
+  enabled?: boolean;
+  notify?: boolean;
+}
+
+export interface MonitorCheck {
+  id: number;
+  monitor: string;
+  timestamp: string;
+  passed: boolean;
+  elapsed_ms: number;
+  status?: number;
+  error?: string;
+}
+
+export interface MonitorStatus {
+  monitor: Monitor;
+  stats: {
+    checks: number;
+    passed: number;
+    uptime: number;
+    average_ms: number;
+    p95_ms: number;
+    last_check?: string;
+    last_passed?: boolean;
+  };
+}
+
+export interface LoadTestOptions {
+  test_id?: string;
+  // Sent without scripts, extractions or contract checks; only assertions apply.
+  request: RecordedRequest;
+  concurrency?: number;
+  duration_secs?: number;
+  iterations?: number;
+  ramp_up_secs?: number;
+}
+
+// Emitted as `load-test-progress` and returned by `load_test`.
+export interface LoadTestReport {
+  test_id: string;
+  done: boolean;
+  elapsed_ms: number;
+  requests: number;
+  errors: number;
+  error_rate: number;
+  throughput: number;
+  latency: {
+    min_ms: number;
+    mean_ms: number;
+    p50_ms: number;
+    p90_ms: number;
+    p95_ms: number;
+    p99_ms: number;
+    max_ms: number;
+  };
+  histogram: { le_ms?: number; count: number }[];
+  status_codes: Record<string, number>;
+  error_kinds: Record<string, number>;
+}
+
+export type DiffSide =
+  | { from: "history"; id: number }
+  | { from: "request"; request: RecordedRequest; environment?: string };
+
+export interface Change {
+  path: string;
+  kind: "added" | "removed" | "changed";
+  left?: unknown;
+  right?: unknown;
+}
+
+export interface ResponseDiff {
+  identical: boolean;
+  status?: Change;
+  headers: Change[];
+  body_kind: "json" | "text" | "opaque";
+  body: Change[];
+}
+
+export type QueryLanguage = "jsonpath" | "jmespath";
+
+export type BodySource =
+  | { from: "history"; id: number }
+  | { from: "file"; path: string };
+
+export interface BodyChunk {
+  offset: number;
+  next_offset: number;
+  size: number;
+  text: string;
+  eof: boolean;
+}
+
+export interface FormattedBody {
+  language: "json" | "xml";
+  body: string;
+  body_path?: string;
+  size: number;
+}
+
+export interface HexPage {
+  offset: number;
+  rows: { offset: number; hex: string; ascii: string }[];
+  size: number;
+  next_offset?: number;
+}
+
+export interface SearchResult {
+  hits: { offset: number; line: number; preview: string }[];
+  truncated: boolean;
+}
+
+export interface QueryResult {
+  matches: { path?: string; value: unknown }[];
+  total: number;
+}
+
+export type SnippetLanguage = "curl" | "httpie" | "python" | "javascript" | "go";
+
+export interface Workspace {
+  id: string;
+  name: string;
+  created_at: string;
+}
+
+export interface HistoryFilter {
+  text?: string;
+  collection?: string;
+  status_min?: number;
+  status_max?: number;
+  failed?: boolean;
+  since?: string;
+  until?: string;
+  limit?: number;
+  offset?: number;
+}
+
 export interface Collection {
   name: string;
   url: string;
+  source?: string;
+  name_override?: string;
   groups: Record<string, Endpoint[]>;
+  tags?: { name: string; description?: string; external_docs?: { url: string; description?: string } }[];
+  tag_groups?: { name: string; tags: string[] }[];
   sync_enabled?: boolean;
+  sync_interval_secs?: number;
+  servers?: Server[];
+  server_index?: number;
+  server_variables?: Record<string, string>;
+}
+
+// Sent as a JSON POST in place of the request body.
+export interface GraphqlQuery {
+  query: string;
+  variables?: unknown;
+  operation_name?: string;
+}
+
+// Sent as a JSON POST in place of the request body; several calls, or `batch`, send
+// an array. Calls without an id get a generated one.
+export interface JsonRpcRequest {
+  calls: { method: string; params?: unknown[] | Record<string, unknown>; id?: string | number; notification?: boolean }[];
+  batch?: boolean;
+}
+
+export interface JsonRpcOutcome {
+  id: string | number | null;
+  method: string | null;
+  result: unknown | null;
+  error: { code: number; message: string; data?: unknown } | null;
+}
+
+export interface GraphqlArgument {
+  name: string;
+  description?: string;
+  type_name: string;
+  default_value?: string;
+}
+
+export interface GraphqlField {
+  name: string;
+  description?: string;
+  type_name: string;
+  args: GraphqlArgument[];
+  deprecated: boolean;
+  deprecation_reason?: string;
+}
+
+// See import_graphql_schema / refresh_graphql_schema.
+export interface GraphqlSchema {
+  name: string;
+  url: string;
+  queries: { field: GraphqlField; example: GraphqlQuery }[];
+  mutations: { field: GraphqlField; example: GraphqlQuery }[];
+  subscriptions: { field: GraphqlField; example: GraphqlQuery }[];
+  types: {
+    name: string;
+    kind: string;
+    description?: string;
+    fields: GraphqlField[];
+    input_fields: GraphqlArgument[];
+    enum_values: string[];
+    interfaces: string[];
+    possible_types: string[];
+  }[];
+  last_updated: string;
+}
+
+export type DescriptorSource =
+  | { from: "protos"; files: string[]; import_paths?: string[] }
+  | { from: "reflection" };
+
+export interface GrpcService {
+  name: string;
+  methods: {
+    name: string;
+    path: string;
+    input_type: string;
+    output_type: string;
+    client_streaming: boolean;
+    server_streaming: boolean;
+    request_template: unknown;
+  }[];
+}
+
+// See grpc_call; streamed messages also arrive as `grpc-message` events.
+export interface GrpcCall {
+  url: string;
+  method: string;
+  message?: unknown;
+  metadata?: [string, string][];
+  source: DescriptorSource;
+  timeout_ms?: number;
+}
+
+export interface GrpcResponse {
+  status: number;
+  status_name: string;
+  status_message: string;
+  messages: unknown[];
+  headers: [string, string][];
+  trailers: [string, string][];
+  elapsed_ms: number;
+}
+
+// mqtt://, mqtts://, ws:// or wss:// broker URL.
+export interface MqttOptions {
+  url: string;
+  client_id?: string;
+  username?: string | null;
+  password?: string | null;
+  keep_alive_secs?: number;
+  clean_session?: boolean;
+  will?: MqttPublish | null;
+  accept_invalid_certs?: boolean;
+}
+
+export interface MqttPublish {
+  topic: string;
+  payload: string;
+  base64?: boolean;
+  qos?: 0 | 1 | 2;
+  retain?: boolean;
+}
+
+export interface MqttSubscription {
+  topic: string;
+  qos?: 0 | 1 | 2;
+}
+
+// Payload of the `mqtt-message` event.
+export interface MqttMessageEvent {
+  connection_id: string;
+  message: {
+    topic: string;
+    payload: string;
+    base64: boolean;
+    qos: number;
+    retain: boolean;
+    received_at: string;
+  };
+}
+
+// Payload of the `mqtt-closed` event.
+export interface MqttClosedEvent {
+  connection_id: string;
+  reason: string;
+}
+
+// Selections are keyed by "METHOD /route", e.g. "GET /pets/{id}". Requests can also
+// pick a response with `Prefer: code=404, example=name`.
+export interface MockOptions {
+  port?: number;
+  latency_ms?: number;
+  latency_jitter_ms?: number;
+  error_rate?: number;
+  error_status?: number;
+  selections?: Record<string, { status?: number | null; example?: string | null }>;
+}
+
+export interface MockServerInfo {
+  collection: string;
+  url: string;
+  routes: number;
+  options: MockOptions;
+}
+
+// Payload of the `mock-request` event.
+export interface MockHit {
+  collection: string;
+  method: string;
+  path: string;
+  route: string | null;
+  status: number;
+  injected: boolean;
+  at: string;
+}
+
+// Point a browser or app at `ProxyInfo.url` as its HTTP proxy. HTTPS is only recorded
+// with `intercept_tls`, once the certificate at `ca_certificate` is trusted.
+export interface ProxyOptions {
+  port?: number;
+  record?: boolean;
+  replay?: boolean;
+  intercept_tls?: boolean;
+  hosts?: string[];
+}
+
+export interface ProxyInfo {
+  url: string;
+  options: ProxyOptions;
+  ca_certificate: string | null;
+}
+
+// Payload of the `proxy-exchange` event. Pass `history_id`s to `save_captured_requests`.
+export interface ProxyExchange {
+  history_id: number | null;
+  method: string;
+  url: string;
+  status: number;
+  replayed: boolean;
+}
+
+export interface WebhookOptions {
+  port?: number;
+  retain?: number;
+  retain_minutes?: number | null;
+  status?: number;
+  content_type?: string | null;
+  body?: string;
+}
+
+export interface WebhookInfo {
+  listener_id: string;
+  url: string;
+  options: WebhookOptions;
+  captures: number;
+}
+
+// Payload of the `webhook-request` event, and what `webhook_captures` returns.
+export interface WebhookCapture {
+  id: number;
+  listener_id: string;
+  method: string;
+  path: string;
+  query: [string, string][];
+  headers: [string, string][];
+  body: string;
+  body_encoding: "text" | "base64" | "none";
+  size: number;
+  remote_addr: string;
+  received_at: string;
+}
+
+// Returned by `coverage_report`. Operations are named "METHOD /route" as in the spec;
+// statuses are counted from every request sent to the collection.
+export interface CoverageReport {
+  collection: string;
+  operations: {
+    operation: string;
+    summary: string | null;
+    tag: string;
+    hits: number;
+    last_seen: string | null;
+    statuses: { status: number; hits: number }[];
+    untested_statuses: string[];
+  }[];
+  covered: number;
+  total: number;
+  percent: number;
+  untested: string[];
+}
+
+// Returned by `lint_collection`. `pointer` is a JSON pointer into the spec; Swagger 2
+// specs are checked after conversion, so pointers follow the OpenAPI 3 layout.
+export interface LintReport {
+  findings: {
+    rule: string;
+    severity: "error" | "warning" | "info";
+    message: string;
+    pointer: string;
+  }[];
+  errors: number;
+  warnings: number;
+  infos: number;
+}
+
+// Either side of `diff_specs`. `previous_version` is what the collection's last sync
+// replaced.
+export type SpecSource =
+  | { from: "location"; location: string; headers?: Record<string, string> }
+  | { from: "content"; content: string }
+  | { from: "collection"; url: string }
+  | { from: "previous_version"; url: string };
+
+// Returned by `diff_specs`, breaking changes first. `operation` is "METHOD /route".
+export interface SpecDiff {
+  old_name: string;
+  new_name: string;
+  changes: {
+    impact: "breaking" | "non_breaking";
+    operation: string | null;
+    location: string;
+    message: string;
+  }[];
+  breaking: number;
+  non_breaking: number;
+}
+
+// Returned by `generate_spec`. `document` is a draft OpenAPI 3 spec inferred from the
+// history entries matching the filter; `skipped` counts entries without a response or
+// with a URL that still held unresolved variables.
+export interface GeneratedSpec {
+  document: Record<string, unknown>;
+  operations: number;
+  requests: number;
+  skipped: number;
+}
+
+// The file format `export_docs` writes a collection's reference documentation in.
+export type DocFormat = "markdown" | "html";
+
+// Payload of `import-progress`, sent while `import_openapi` parses a spec for a given
+// `import_id`. `processed` counts path items out of `total`.
+export interface ImportProgress {
+  import_id: string;
+  url: string;
+  processed: number;
+  total: number;
+}
+
+// Returned by `sync_status`, by collection URL: the last background, file-watch or
+// manual sync of each collection synced since the app started. `last_success` tells
+// how stale a failing collection is; `failures` counts failed attempts in a row.
+export interface SyncStatus {
+  outcome: "updated" | "unchanged" | "failed";
+  checked_at: string;
+  last_success: string;
+  error: string | null;
+  failures: number;
+}
+
+// Payload of `collection-sync-failed`, sent on every failed sync attempt.
+export interface SyncFailed {
+  url: string;
+  status: SyncStatus;
+}
+
+// Payload of `rate-limit-wait`, sent when a request, or one of its retries or
+// authentication round trips, is held back by the global or per-host limits in
+// settings; it is sent `wait_ms` later unless cancelled first.
+export interface RateLimitWait {
+  request_id: string | null;
+  url: string;
+  wait_ms: number;
+}
+
+// Returned by `queue_request` and `queued_requests`, which list the active workspace's
+// queue. A queued request is sent once `host`:`port` accepts connections again, or
+// answers through the proxy when one applies; `attempts` counts sends that lost the
+// connection midway, `last_error` says how.
+export interface QueuedRequest {
+  id: string;
+  name: string;
+  request: RecordedRequest["request"];
+  scheme: string;
+  host: string;
+  port: number;
+  queued_at: string;
+  attempts: number;
+  last_error: string | null;
+}
+
+// Requests queued in a workspace other than the active one; they are only sent once
+// that workspace is switched to.
+export interface WorkspaceQueue {
+  workspace_id: string;
+  workspace_name: string;
+  count: number;
+}
+
+// Returned by `network_status` and the payload of `network-status`, sent when the
+// offline queue's probes find the network gone or back. `other_workspaces` is updated
+// when switching or deleting workspaces.
+export interface NetworkStatus {
+  online: boolean;
+  checked_at: string | null;
+  other_workspaces: WorkspaceQueue[];
+}
+
+// Payload of `queued-request-sent`: one of `response` and `error` is set. The request
+// is in history like any other.
+export interface QueuedRequestSent {
+  id: string;
+  name: string;
+  response: ResponseData | null;
+  error: string | null;
 }